@@ -298,6 +298,8 @@ class ManagementService(
                         }
                         ManagementInterface.DaemonEvent.EventCase.REMOVE_DEVICE -> {}
                         ManagementInterface.DaemonEvent.EventCase.LEAK_INFO -> {}
+                        ManagementInterface.DaemonEvent.EventCase.FIREWALL_DRIFT -> {}
+                        ManagementInterface.DaemonEvent.EventCase.ACCOUNT_EXPIRY_WARNING -> {}
                         ManagementInterface.DaemonEvent.EventCase.EVENT_NOT_SET -> {}
                     }
                 }
@@ -789,6 +789,11 @@ internal fun ManagementInterface.FeatureIndicator.toDomain() =
         ManagementInterface.FeatureIndicator.LWO -> FeatureIndicator.LWO
         ManagementInterface.FeatureIndicator.WIREGUARD_PORT -> FeatureIndicator.WIREGUARD_PORT
         ManagementInterface.FeatureIndicator.LOCKDOWN_MODE,
+        ManagementInterface.FeatureIndicator.FIREWALL_EXCEPTIONS,
+        ManagementInterface.FeatureIndicator.INBOUND_PORTS,
+        ManagementInterface.FeatureIndicator.SETTINGS_PROFILE,
+        ManagementInterface.FeatureIndicator.LAN_GATEWAY,
+        ManagementInterface.FeatureIndicator.TRUSTED_INTERFACES,
         ManagementInterface.FeatureIndicator.UNRECOGNIZED ->
             error("Feature not supported ${this.name}")
     }
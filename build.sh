@@ -270,6 +270,7 @@ function build {
     )
     if [[ ("$(uname -s)" == "Linux") ]]; then
         cargo_crates_to_build+=(-p mullvad-exclude --bin mullvad-exclude)
+        cargo_crates_to_build+=(-p mullvad-include --bin mullvad-include)
     fi
 
     if [[ ("$(uname -s)" == "Linux") ]]; then
@@ -314,6 +315,7 @@ function build {
             mullvad-problem-report
             mullvad-setup
             mullvad-exclude
+            mullvad-include
         )
     elif [[ ("$(uname -s)" == "MINGW"*) ]]; then
         BINARIES=(
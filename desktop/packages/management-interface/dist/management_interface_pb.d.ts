@@ -3555,6 +3555,11 @@ export enum FeatureIndicator {
     CUSTOM_MTU = 13,
     DAITA = 14,
     MULTIHOP_AUTO = 15,
+    FIREWALL_EXCEPTIONS = 16,
+    INBOUND_PORTS = 17,
+    SETTINGS_PROFILE = 18,
+    LAN_GATEWAY = 19,
+    TRUSTED_INTERFACES = 20,
 }
 
 export enum Ownership {
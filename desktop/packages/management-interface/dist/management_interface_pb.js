@@ -26949,7 +26949,12 @@ proto.mullvad_daemon.management_interface.FeatureIndicator = {
   SERVER_IP_OVERRIDE: 12,
   CUSTOM_MTU: 13,
   DAITA: 14,
-  MULTIHOP_AUTO: 15
+  MULTIHOP_AUTO: 15,
+  FIREWALL_EXCEPTIONS: 16,
+  INBOUND_PORTS: 17,
+  SETTINGS_PROFILE: 18,
+  LAN_GATEWAY: 19,
+  TRUSTED_INTERFACES: 20
 };
 
 /**
@@ -396,6 +396,16 @@ function convertFromFeatureIndicator(
       return FeatureIndicator.lwo;
     case grpcTypes.FeatureIndicator.WIREGUARD_PORT:
       return FeatureIndicator.wireGuardPort;
+    case grpcTypes.FeatureIndicator.FIREWALL_EXCEPTIONS:
+      return FeatureIndicator.firewallExceptions;
+    case grpcTypes.FeatureIndicator.INBOUND_PORTS:
+      return FeatureIndicator.inboundPorts;
+    case grpcTypes.FeatureIndicator.SETTINGS_PROFILE:
+      return FeatureIndicator.settingsProfile;
+    case grpcTypes.FeatureIndicator.LAN_GATEWAY:
+      return FeatureIndicator.lanGateway;
+    case grpcTypes.FeatureIndicator.TRUSTED_INTERFACES:
+      return FeatureIndicator.trustedInterfaces;
   }
 }
 
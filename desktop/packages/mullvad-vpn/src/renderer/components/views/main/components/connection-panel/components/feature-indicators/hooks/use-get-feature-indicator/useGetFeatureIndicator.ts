@@ -208,6 +208,34 @@ export const useGetFeatureIndicator = () => {
       label: messages.pgettext('vpn-settings-view', 'DNS content blockers'),
       onClick: gotoDnsContentBlockersFeature,
     },
+    [FeatureIndicator.firewallExceptions]: {
+      label:
+        // TRANSLATORS: This is displayed when traffic to some hosts is allowed outside the tunnel.
+        messages.pgettext('connect-view', 'Firewall exceptions'),
+    },
+    [FeatureIndicator.inboundPorts]: {
+      label:
+        // TRANSLATORS: This is displayed when incoming connections are accepted on some ports
+        // TRANSLATORS: of the tunnel interface.
+        messages.pgettext('connect-view', 'Inbound ports'),
+    },
+    [FeatureIndicator.settingsProfile]: {
+      label:
+        // TRANSLATORS: This is displayed when the settings match a saved settings profile.
+        messages.pgettext('connect-view', 'Settings profile'),
+    },
+    [FeatureIndicator.lanGateway]: {
+      label:
+        // TRANSLATORS: This is displayed when traffic from other devices on the local network is
+        // TRANSLATORS: forwarded through the tunnel.
+        messages.pgettext('connect-view', 'LAN gateway'),
+    },
+    [FeatureIndicator.trustedInterfaces]: {
+      label:
+        // TRANSLATORS: This is displayed when the interfaces of other VPN or mesh network apps,
+        // TRANSLATORS: such as Tailscale, are allowed alongside the tunnel.
+        messages.pgettext('connect-view', 'Trusted interfaces'),
+    },
   };
 
   return featureMap;
@@ -235,6 +235,11 @@ export enum FeatureIndicator {
   customDns,
   serverIpOverride,
   customMtu,
+  firewallExceptions,
+  inboundPorts,
+  settingsProfile,
+  lanGateway,
+  trustedInterfaces,
 }
 
 export type DisconnectedState = {
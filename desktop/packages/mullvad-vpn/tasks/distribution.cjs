@@ -207,6 +207,7 @@ function newConfig() {
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
+        distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-include')) + '=/usr/bin/',
         distAssets('linux/problem-report-link') + '=/usr/bin/mullvad-problem-report',
         buildAssets('shell-completions/mullvad.bash') +
           '=/usr/share/bash-completion/completions/mullvad',
@@ -250,6 +251,7 @@ function newConfig() {
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
+        distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-include')) + '=/usr/bin/',
         distAssets('linux/problem-report-link') + '=/usr/bin/mullvad-problem-report',
         buildAssets('shell-completions/mullvad.bash') +
           '=/usr/share/bash-completion/completions/mullvad',
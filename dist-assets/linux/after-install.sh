@@ -2,6 +2,7 @@
 set -eu
 
 chmod u+s "/usr/bin/mullvad-exclude"
+chmod u+s "/usr/bin/mullvad-include"
 
 systemctl enable "/usr/lib/systemd/system/mullvad-daemon.service"
 systemctl start mullvad-daemon.service || echo "Failed to start mullvad-daemon.service"
@@ -0,0 +1,593 @@
+//! The state of the fake API, and the endpoints that operate on it.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use hyper::{Method, Request, StatusCode, body::Incoming, header};
+use ipnetwork::{Ipv4Network, Ipv6Network};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+use talpid_types::net::wireguard::PublicKey;
+
+use crate::{
+    DEFAULT_MAX_DEVICES, Fault, FaultRule, TUNNEL_NETWORK_V4, TUNNEL_NETWORK_V6, fault,
+    server::{Response, empty, error, json},
+};
+
+/// How long access tokens are valid for.
+const ACCESS_TOKEN_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+/// Error codes, as defined by the API.
+const INVALID_ACCOUNT: &str = "INVALID_ACCOUNT";
+const INVALID_ACCESS_TOKEN: &str = "INVALID_ACCESS_TOKEN";
+const DEVICE_NOT_FOUND: &str = "DEVICE_NOT_FOUND";
+const MAX_DEVICES_REACHED: &str = "MAX_DEVICES_REACHED";
+const PUBKEY_IN_USE: &str = "PUBKEY_IN_USE";
+const INVALID_VOUCHER: &str = "INVALID_VOUCHER";
+const VOUCHER_USED: &str = "VOUCHER_USED";
+
+/// A device registered on an account.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Device {
+    pub id: String,
+    pub name: String,
+    pub pubkey: PublicKey,
+    pub ipv4_address: Ipv4Network,
+    pub ipv6_address: Ipv6Network,
+    pub hijack_dns: bool,
+    pub created: DateTime<Utc>,
+}
+
+/// A change to the devices of an account, made through the API.
+#[derive(Debug, Clone)]
+pub enum DeviceEvent {
+    /// A device was created.
+    Created(Device),
+    /// A device was removed, either directly or because its account was deleted.
+    Removed(Device),
+    /// The key of a device was rotated. `device` has the new key.
+    KeyRotated {
+        old_pubkey: PublicKey,
+        device: Device,
+    },
+}
+
+/// A problem report submitted to the API.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProblemReport {
+    pub address: String,
+    pub message: String,
+    pub log: String,
+    pub metadata: BTreeMap<String, String>,
+}
+
+pub(crate) type DeviceHook =
+    Arc<dyn Fn(DeviceEvent) -> BoxFuture<'static, anyhow::Result<()>> + Send + Sync>;
+
+pub(crate) struct State {
+    inner: Mutex<Inner>,
+    device_hook: Mutex<Option<DeviceHook>>,
+}
+
+pub(crate) struct Inner {
+    pub accounts: HashMap<String, Account>,
+    pub access_tokens: HashMap<String, AccessToken>,
+    pub vouchers: HashMap<String, Voucher>,
+    pub relay_list: serde_json::Value,
+    /// Incremented whenever the relay list changes. Used as its ETag.
+    pub relay_list_version: u64,
+    pub api_addrs: Vec<SocketAddr>,
+    pub problem_reports: Vec<ProblemReport>,
+    /// The path of every request that has been received.
+    pub requests: Vec<String>,
+    pub faults: Vec<FaultRule>,
+    pub max_devices: usize,
+    /// Used to generate unique account numbers, tokens and device IDs.
+    next_id: u64,
+    /// Number of devices that have ever been created. Used to allocate tunnel addresses.
+    devices_created: u32,
+}
+
+pub(crate) struct Account {
+    pub id: String,
+    pub expiry: DateTime<Utc>,
+    pub devices: Vec<Device>,
+}
+
+pub(crate) struct AccessToken {
+    account_number: String,
+    expiry: DateTime<Utc>,
+}
+
+pub(crate) struct Voucher {
+    pub time_added: Duration,
+    pub used: bool,
+}
+
+impl Inner {
+    fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Add an account with a generated number and return the number.
+    pub fn create_account(&mut self, expiry: DateTime<Utc>) -> String {
+        let id = self.next_id();
+        let number = format!("{:016}", 1_000_000_000_000_000 + id);
+        self.add_account(number.clone(), expiry);
+        number
+    }
+
+    /// Add an account, replacing any existing account with the same number.
+    pub fn add_account(&mut self, number: String, expiry: DateTime<Utc>) {
+        let id = self.next_id();
+        let account = Account {
+            id: format!("fake-account-{id}"),
+            expiry,
+            devices: vec![],
+        };
+        self.accounts.insert(number, account);
+    }
+
+    /// Remove an account and revoke its access tokens. Returns the devices it had.
+    pub fn remove_account(&mut self, number: &str) -> Vec<Device> {
+        self.access_tokens
+            .retain(|_, token| token.account_number != number);
+        self.accounts
+            .remove(number)
+            .map(|account| account.devices)
+            .unwrap_or_default()
+    }
+
+    fn pubkey_in_use(&self, pubkey: &PublicKey) -> bool {
+        self.accounts
+            .values()
+            .flat_map(|account| &account.devices)
+            .any(|device| &device.pubkey == pubkey)
+    }
+}
+
+impl State {
+    pub fn new(api_addrs: Vec<SocketAddr>) -> Self {
+        let inner = Inner {
+            accounts: HashMap::new(),
+            access_tokens: HashMap::new(),
+            vouchers: HashMap::new(),
+            relay_list: empty_relay_list(),
+            relay_list_version: 1,
+            api_addrs,
+            problem_reports: vec![],
+            requests: vec![],
+            faults: vec![],
+            max_devices: DEFAULT_MAX_DEVICES,
+            next_id: 0,
+            devices_created: 0,
+        };
+        Self {
+            inner: Mutex::new(inner),
+            device_hook: Mutex::new(None),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap()
+    }
+
+    pub fn set_device_hook(&self, hook: DeviceHook) {
+        *self.device_hook.lock().unwrap() = Some(hook);
+    }
+
+    /// Record a request for `path`, and take the fault that applies to it, if any.
+    pub fn begin_request(&self, path: &str) -> Option<Fault> {
+        let mut inner = self.lock();
+        inner.requests.push(path.to_owned());
+        fault::take(&mut inner.faults, path)
+    }
+
+    pub async fn handle(&self, request: Request<Incoming>) -> Response {
+        let method = request.method().clone();
+        let path = request.uri().path().to_owned();
+        log::debug!("Fake API request: {method} {path}");
+
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let result = match segments.as_slice() {
+            ["auth", "v1", "token"] if method == Method::POST => {
+                self.create_access_token(request).await
+            }
+            ["accounts", "v1", "accounts"] if method == Method::POST => Ok(self.create_account()),
+            ["accounts", "v1", "accounts", "me"] if method == Method::GET => {
+                self.get_account(&request)
+            }
+            ["accounts", "v1", "accounts", "me"] if method == Method::DELETE => {
+                self.delete_account(request).await
+            }
+            ["accounts", "v1", "devices"] if method == Method::GET => self.list_devices(&request),
+            ["accounts", "v1", "devices"] if method == Method::POST => {
+                self.create_device(request).await
+            }
+            ["accounts", "v1", "devices", id] if method == Method::GET => {
+                self.get_device(&request, id)
+            }
+            ["accounts", "v1", "devices", id] if method == Method::DELETE => {
+                self.remove_device(request, id).await
+            }
+            ["accounts", "v1", "devices", id, "pubkey"] if method == Method::PUT => {
+                self.rotate_device_key(request, id).await
+            }
+            ["app", "v1", "api-addrs"] if method == Method::GET || method == Method::HEAD => {
+                Ok(self.get_api_addrs())
+            }
+            ["app", "v1", "relays"] if method == Method::GET => Ok(self.get_relay_list(&request)),
+            ["app", "v1", "submit-voucher"] if method == Method::POST => {
+                self.submit_voucher(request).await
+            }
+            ["app", "v1", "www-auth-token"] if method == Method::POST => {
+                self.create_www_auth_token(&request)
+            }
+            ["app", "v1", "problem-report"] if method == Method::POST => {
+                self.submit_problem_report(request).await
+            }
+            _ => Ok(empty(StatusCode::NOT_FOUND)),
+        };
+        result.unwrap_or_else(|response| response)
+    }
+
+    /// Return the account number that the access token of `request` belongs to.
+    fn authenticate<B>(&self, request: &Request<B>) -> Result<String, Response> {
+        let unauthorized = || error(StatusCode::UNAUTHORIZED, INVALID_ACCESS_TOKEN);
+        let token = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(unauthorized)?;
+
+        let inner = self.lock();
+        match inner.access_tokens.get(token) {
+            Some(token) if token.expiry > Utc::now() => Ok(token.account_number.clone()),
+            _ => Err(unauthorized()),
+        }
+    }
+
+    async fn create_access_token(&self, request: Request<Incoming>) -> Result<Response, Response> {
+        #[derive(Deserialize)]
+        struct AccessTokenRequest {
+            account_number: String,
+        }
+
+        let request: AccessTokenRequest = read_json(request).await?;
+
+        let mut inner = self.lock();
+        if !inner.accounts.contains_key(&request.account_number) {
+            return Err(error(StatusCode::BAD_REQUEST, INVALID_ACCOUNT));
+        }
+        let access_token = format!("fake-access-token-{}", inner.next_id());
+        let expiry = Utc::now() + TimeDelta::from_std(ACCESS_TOKEN_LIFETIME).unwrap();
+        inner.access_tokens.insert(
+            access_token.clone(),
+            AccessToken {
+                account_number: request.account_number,
+                expiry,
+            },
+        );
+        Ok(json(
+            StatusCode::OK,
+            &json!({ "access_token": access_token, "expiry": expiry }),
+        ))
+    }
+
+    /// New accounts have no time added.
+    fn create_account(&self) -> Response {
+        let number = self.lock().create_account(Utc::now());
+        json(StatusCode::CREATED, &json!({ "number": number }))
+    }
+
+    fn get_account<B>(&self, request: &Request<B>) -> Result<Response, Response> {
+        let number = self.authenticate(request)?;
+        let inner = self.lock();
+        let account = account(&inner, &number)?;
+        Ok(json(
+            StatusCode::OK,
+            &json!({ "id": account.id, "expiry": account.expiry }),
+        ))
+    }
+
+    async fn delete_account(&self, request: Request<Incoming>) -> Result<Response, Response> {
+        let number = self.authenticate(&request)?;
+        if request
+            .headers()
+            .get("Mullvad-Account-Number")
+            .is_none_or(|header| header != number.as_str())
+        {
+            return Err(error(StatusCode::BAD_REQUEST, INVALID_ACCOUNT));
+        }
+
+        let devices = self.lock().remove_account(&number);
+        for device in devices {
+            self.call_device_hook(DeviceEvent::Removed(device)).await?;
+        }
+        Ok(empty(StatusCode::NO_CONTENT))
+    }
+
+    fn list_devices<B>(&self, request: &Request<B>) -> Result<Response, Response> {
+        let number = self.authenticate(request)?;
+        let inner = self.lock();
+        Ok(json(StatusCode::OK, &account(&inner, &number)?.devices))
+    }
+
+    fn get_device<B>(&self, request: &Request<B>, id: &str) -> Result<Response, Response> {
+        let number = self.authenticate(request)?;
+        let inner = self.lock();
+        let device = account(&inner, &number)?
+            .devices
+            .iter()
+            .find(|device| device.id == id)
+            .ok_or_else(|| error(StatusCode::NOT_FOUND, DEVICE_NOT_FOUND))?;
+        Ok(json(StatusCode::OK, device))
+    }
+
+    async fn create_device(&self, request: Request<Incoming>) -> Result<Response, Response> {
+        #[derive(Deserialize)]
+        struct DeviceSubmission {
+            pubkey: PublicKey,
+            #[serde(default)]
+            hijack_dns: bool,
+        }
+
+        let number = self.authenticate(&request)?;
+        let submission: DeviceSubmission = read_json(request).await?;
+
+        let device = {
+            let mut inner = self.lock();
+            if inner.pubkey_in_use(&submission.pubkey) {
+                return Err(error(StatusCode::BAD_REQUEST, PUBKEY_IN_USE));
+            }
+            let max_devices = inner.max_devices;
+            if account(&inner, &number)?.devices.len() >= max_devices {
+                return Err(error(StatusCode::BAD_REQUEST, MAX_DEVICES_REACHED));
+            }
+
+            let id = inner.next_id();
+            inner.devices_created += 1;
+            // The first address in the tunnel network belongs to the relay
+            let host = inner.devices_created + 1;
+            let device = Device {
+                id: format!("fake-device-{id}"),
+                name: format!("fake device {id}"),
+                pubkey: submission.pubkey,
+                ipv4_address: tunnel_address_v4(host),
+                ipv6_address: tunnel_address_v6(host),
+                hijack_dns: submission.hijack_dns,
+                created: Utc::now(),
+            };
+            account_mut(&mut inner, &number)?
+                .devices
+                .push(device.clone());
+            device
+        };
+
+        self.call_device_hook(DeviceEvent::Created(device.clone()))
+            .await?;
+        Ok(json(StatusCode::CREATED, &device))
+    }
+
+    async fn remove_device(
+        &self,
+        request: Request<Incoming>,
+        id: &str,
+    ) -> Result<Response, Response> {
+        let number = self.authenticate(&request)?;
+        let device = {
+            let mut inner = self.lock();
+            let devices = &mut account_mut(&mut inner, &number)?.devices;
+            let index = devices
+                .iter()
+                .position(|device| device.id == id)
+                .ok_or_else(|| error(StatusCode::NOT_FOUND, DEVICE_NOT_FOUND))?;
+            devices.remove(index)
+        };
+
+        self.call_device_hook(DeviceEvent::Removed(device)).await?;
+        Ok(empty(StatusCode::NO_CONTENT))
+    }
+
+    async fn rotate_device_key(
+        &self,
+        request: Request<Incoming>,
+        id: &str,
+    ) -> Result<Response, Response> {
+        #[derive(Deserialize)]
+        struct RotateDevicePubkey {
+            pubkey: PublicKey,
+        }
+
+        let number = self.authenticate(&request)?;
+        let rotation: RotateDevicePubkey = read_json(request).await?;
+
+        let (old_pubkey, device) = {
+            let mut inner = self.lock();
+            if inner.pubkey_in_use(&rotation.pubkey) {
+                return Err(error(StatusCode::BAD_REQUEST, PUBKEY_IN_USE));
+            }
+            let device = account_mut(&mut inner, &number)?
+                .devices
+                .iter_mut()
+                .find(|device| device.id == id)
+                .ok_or_else(|| error(StatusCode::NOT_FOUND, DEVICE_NOT_FOUND))?;
+            let old_pubkey = std::mem::replace(&mut device.pubkey, rotation.pubkey);
+            (old_pubkey, device.clone())
+        };
+
+        self.call_device_hook(DeviceEvent::KeyRotated {
+            old_pubkey,
+            device: device.clone(),
+        })
+        .await?;
+        Ok(json(StatusCode::OK, &device))
+    }
+
+    fn get_api_addrs(&self) -> Response {
+        json(StatusCode::OK, &self.lock().api_addrs)
+    }
+
+    fn get_relay_list<B>(&self, request: &Request<B>) -> Response {
+        let inner = self.lock();
+        let etag = format!("\"{}\"", inner.relay_list_version);
+        if request
+            .headers()
+            .get(header::IF_NONE_MATCH)
+            .is_some_and(|if_none_match| if_none_match == etag.as_str())
+        {
+            return empty(StatusCode::NOT_MODIFIED);
+        }
+
+        let mut response = json(StatusCode::OK, &inner.relay_list);
+        response
+            .headers_mut()
+            .insert(header::ETAG, etag.parse().unwrap());
+        response
+    }
+
+    async fn submit_voucher(&self, request: Request<Incoming>) -> Result<Response, Response> {
+        #[derive(Deserialize)]
+        struct VoucherSubmission {
+            voucher_code: String,
+        }
+
+        let number = self.authenticate(&request)?;
+        let submission: VoucherSubmission = read_json(request).await?;
+
+        let mut inner = self.lock();
+        let voucher = inner
+            .vouchers
+            .get_mut(&submission.voucher_code)
+            .ok_or_else(|| error(StatusCode::BAD_REQUEST, INVALID_VOUCHER))?;
+        if voucher.used {
+            return Err(error(StatusCode::BAD_REQUEST, VOUCHER_USED));
+        }
+        voucher.used = true;
+        let time_added = voucher.time_added;
+
+        let account = account_mut(&mut inner, &number)?;
+        // Time is added from now if the account has already expired
+        account.expiry = account.expiry.max(Utc::now())
+            + TimeDelta::from_std(time_added).expect("Voucher time is out of range");
+        Ok(json(
+            StatusCode::OK,
+            &json!({ "time_added": time_added.as_secs(), "new_expiry": account.expiry }),
+        ))
+    }
+
+    fn create_www_auth_token<B>(&self, request: &Request<B>) -> Result<Response, Response> {
+        self.authenticate(request)?;
+        let auth_token = format!("fake-www-auth-token-{}", self.lock().next_id());
+        Ok(json(StatusCode::OK, &json!({ "auth_token": auth_token })))
+    }
+
+    async fn submit_problem_report(
+        &self,
+        request: Request<Incoming>,
+    ) -> Result<Response, Response> {
+        let report: ProblemReport = read_json(request).await?;
+        self.lock().problem_reports.push(report);
+        Ok(empty(StatusCode::NO_CONTENT))
+    }
+
+    async fn call_device_hook(&self, event: DeviceEvent) -> Result<(), Response> {
+        let Some(hook) = self.device_hook.lock().unwrap().clone() else {
+            return Ok(());
+        };
+        hook(event).await.map_err(|error| {
+            log::error!("{error:#}");
+            empty(StatusCode::INTERNAL_SERVER_ERROR)
+        })
+    }
+}
+
+fn account<'a>(inner: &'a Inner, number: &str) -> Result<&'a Account, Response> {
+    inner
+        .accounts
+        .get(number)
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, INVALID_ACCOUNT))
+}
+
+fn account_mut<'a>(inner: &'a mut Inner, number: &str) -> Result<&'a mut Account, Response> {
+    inner
+        .accounts
+        .get_mut(number)
+        .ok_or_else(|| error(StatusCode::BAD_REQUEST, INVALID_ACCOUNT))
+}
+
+/// Read the body of `request` as JSON, or return a response explaining why that failed.
+async fn read_json<T: DeserializeOwned>(request: Request<Incoming>) -> Result<T, Response> {
+    let body = request
+        .into_body()
+        .collect()
+        .await
+        .map_err(|_| empty(StatusCode::BAD_REQUEST))?
+        .to_bytes();
+    serde_json::from_slice(&body).map_err(|error| {
+        log::warn!("Invalid fake API request body: {error}");
+        empty(StatusCode::BAD_REQUEST)
+    })
+}
+
+fn tunnel_address_v4(host: u32) -> Ipv4Network {
+    let address = Ipv4Addr::from(u32::from(TUNNEL_NETWORK_V4.network()) + host);
+    Ipv4Network::new(address, 32).unwrap()
+}
+
+fn tunnel_address_v6(host: u32) -> Ipv6Network {
+    let address = Ipv6Addr::from(u128::from(TUNNEL_NETWORK_V6.network()) + u128::from(host));
+    Ipv6Network::new(address, 128).unwrap()
+}
+
+/// A valid relay list without any relays.
+fn empty_relay_list() -> serde_json::Value {
+    json!({
+        "locations": {},
+        "wireguard": {
+            "port_ranges": [],
+            "ipv4_gateway": Ipv4Addr::from(u32::from(TUNNEL_NETWORK_V4.network()) + 1),
+            "ipv6_gateway": Ipv6Addr::from(u128::from(TUNNEL_NETWORK_V6.network()) + 1),
+            "relays": [],
+        },
+        "bridge": {
+            "shadowsocks": [],
+            "relays": [],
+        },
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_tunnel_addresses() {
+        assert_eq!(tunnel_address_v4(2).to_string(), "10.64.0.2/32");
+        assert_eq!(tunnel_address_v4(256).to_string(), "10.64.1.0/32");
+        assert_eq!(
+            tunnel_address_v6(2).to_string(),
+            "fc00:bbbb:bbbb:bb01::2/128"
+        );
+    }
+
+    #[test]
+    fn test_account_numbers() {
+        let state = State::new(vec![]);
+        let mut inner = state.lock();
+        let first = inner.create_account(Utc::now());
+        let second = inner.create_account(Utc::now());
+
+        assert_eq!(first.len(), 16);
+        assert!(first.chars().all(|c| c.is_ascii_digit()));
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,117 @@
+//! Deliberate misbehavior of the fake API.
+
+use hyper::StatusCode;
+use std::time::Duration;
+
+/// A way in which the fake API can fail to answer a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Accept the request but never respond, so that the client times out.
+    Hang,
+    /// Handle the request normally, but wait before responding.
+    Delay(Duration),
+    /// Respond with this status and an empty body, as an overloaded or broken server would.
+    Status(StatusCode),
+    /// Close the connection without responding.
+    CloseConnection,
+    /// Answer with a fatal TLS `handshake_failure` alert instead of HTTP, and close the
+    /// connection. Clients see a failure after the connection has been established, as they
+    /// would if a middlebox interfered with the TLS handshake.
+    TlsAlert,
+}
+
+/// Which requests a [`Fault`] applies to, and how many times.
+#[derive(Debug, Clone)]
+pub struct FaultRule {
+    fault: Fault,
+    path_prefix: String,
+    remaining: Option<usize>,
+}
+
+impl FaultRule {
+    /// Apply `fault` to every request, until the rule is removed with
+    /// [`FakeApi::clear_faults`](crate::FakeApi::clear_faults).
+    pub fn new(fault: Fault) -> Self {
+        Self {
+            fault,
+            path_prefix: String::new(),
+            remaining: None,
+        }
+    }
+
+    /// Only apply the fault to requests whose path starts with `prefix`, such as
+    /// `/auth/v1/token`.
+    pub fn path(mut self, prefix: impl Into<String>) -> Self {
+        self.path_prefix = prefix.into();
+        self
+    }
+
+    /// Only apply the fault to the next `count` matching requests.
+    pub fn times(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+}
+
+/// Take the fault for a request to `path` from the first matching rule in `rules`. Rules that
+/// have been used up are removed.
+pub(crate) fn take(rules: &mut Vec<FaultRule>, path: &str) -> Option<Fault> {
+    rules.retain(|rule| rule.remaining != Some(0));
+
+    let index = rules
+        .iter()
+        .position(|rule| path.starts_with(&rule.path_prefix))?;
+    let rule = &mut rules[index];
+    let fault = rule.fault.clone();
+    if let Some(remaining) = &mut rule.remaining {
+        *remaining -= 1;
+        if *remaining == 0 {
+            rules.remove(index);
+        }
+    }
+    Some(fault)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_take() {
+        let mut rules = vec![
+            FaultRule::new(Fault::CloseConnection)
+                .path("/auth/v1/token")
+                .times(2),
+            FaultRule::new(Fault::Status(StatusCode::SERVICE_UNAVAILABLE)),
+        ];
+
+        for _ in 0..2 {
+            assert_eq!(
+                take(&mut rules, "/auth/v1/token"),
+                Some(Fault::CloseConnection)
+            );
+        }
+        assert_eq!(
+            take(&mut rules, "/auth/v1/token"),
+            Some(Fault::Status(StatusCode::SERVICE_UNAVAILABLE))
+        );
+        assert_eq!(rules.len(), 1, "Used up rules should be removed");
+    }
+
+    #[test]
+    fn test_take_path() {
+        let mut rules = vec![FaultRule::new(Fault::Hang).path("/app/v1/relays")];
+
+        assert_eq!(take(&mut rules, "/app/v1/api-addrs"), None);
+        assert_eq!(take(&mut rules, "/app/v1/relays"), Some(Fault::Hang));
+        assert_eq!(take(&mut rules, "/app/v1/relays"), Some(Fault::Hang));
+    }
+
+    #[test]
+    fn test_take_zero_times() {
+        let mut rules = vec![FaultRule::new(Fault::Hang).times(0)];
+
+        assert_eq!(take(&mut rules, "/"), None);
+        assert!(rules.is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+//! An in-process fake of the Mullvad REST API, for testing API clients.
+//!
+//! [`FakeApi`] serves the endpoints that the apps use: access tokens, accounts, devices, the relay
+//! list, API addresses, vouchers and problem reports. Tests can inspect and change its state
+//! directly, and make it misbehave in controlled ways with [`FaultRule`]s, so that error handling
+//! and retries can be tested deterministically.
+//!
+//! The API is served over plain HTTP, so clients must have TLS disabled. In `mullvad-api`, this
+//! is the `disable_tls` flag of `ApiEndpoint`, which is available in its own tests and with the
+//! `api-override` feature. Clients that attempt a TLS handshake are sent a fatal alert.
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use ipnetwork::{Ipv4Network, Ipv6Network};
+use std::{
+    future::Future,
+    io,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+mod api;
+mod fault;
+mod server;
+
+pub use api::{Device, DeviceEvent, ProblemReport};
+pub use fault::{Fault, FaultRule};
+
+use api::{State, Voucher};
+
+/// IPv4 network that device tunnel addresses are allocated from. The first address in the network
+/// is reserved for the relay.
+pub const TUNNEL_NETWORK_V4: Ipv4Network =
+    Ipv4Network::new_checked(Ipv4Addr::new(10, 64, 0, 0), 10).unwrap();
+/// IPv6 network that device tunnel addresses are allocated from. The first address in the network
+/// is reserved for the relay.
+pub const TUNNEL_NETWORK_V6: Ipv6Network = Ipv6Network::new_checked(
+    Ipv6Addr::new(0xfc00, 0xbbbb, 0xbbbb, 0xbb01, 0, 0, 0, 0),
+    64,
+)
+.unwrap();
+/// The number of devices an account may have, unless changed with [`FakeApi::set_max_devices`].
+pub const DEFAULT_MAX_DEVICES: usize = 5;
+
+/// A running fake API. It stops when dropped.
+///
+/// It starts out without any accounts. Accounts can be added directly with
+/// [`FakeApi::create_account`], or created by clients through the API.
+pub struct FakeApi {
+    address: SocketAddr,
+    state: Arc<State>,
+    task: JoinHandle<()>,
+}
+
+impl FakeApi {
+    /// Start the API on an unused port on localhost.
+    pub async fn start() -> io::Result<Self> {
+        Self::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0))).await
+    }
+
+    /// Start the API on `address`.
+    pub async fn bind(address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(State::new(vec![address]));
+        let task = tokio::spawn(server::serve(listener, Arc::clone(&state)));
+        log::debug!("Fake API listening on {address}");
+
+        Ok(Self {
+            address,
+            state,
+            task,
+        })
+    }
+
+    /// The address that the API is served on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Add an account that expires at `expiry`, and return its number.
+    pub fn create_account(&self, expiry: DateTime<Utc>) -> String {
+        self.state.lock().create_account(expiry)
+    }
+
+    /// Add an account with a specific number that expires at `expiry`. An existing account with
+    /// the same number is replaced.
+    pub fn add_account(&self, number: impl Into<String>, expiry: DateTime<Utc>) {
+        self.state.lock().add_account(number.into(), expiry);
+    }
+
+    /// Remove an account, along with its devices and access tokens. Unlike when an account is
+    /// deleted through the API, no [`DeviceEvent`]s are emitted.
+    pub fn remove_account(&self, number: &str) {
+        self.state.lock().remove_account(number);
+    }
+
+    /// The expiry of an account, or `None` if it does not exist.
+    pub fn account_expiry(&self, number: &str) -> Option<DateTime<Utc>> {
+        let inner = self.state.lock();
+        inner.accounts.get(number).map(|account| account.expiry)
+    }
+
+    /// Change the expiry of an account.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the account does not exist.
+    pub fn set_account_expiry(&self, number: &str, expiry: DateTime<Utc>) {
+        let mut inner = self.state.lock();
+        let account = inner.accounts.get_mut(number).expect("No such account");
+        account.expiry = expiry;
+    }
+
+    /// The devices of an account. Empty if the account does not exist.
+    pub fn devices(&self, number: &str) -> Vec<Device> {
+        let inner = self.state.lock();
+        inner
+            .accounts
+            .get(number)
+            .map(|account| account.devices.clone())
+            .unwrap_or_default()
+    }
+
+    /// Set the number of devices an account may have. Existing devices are kept if there are
+    /// more than `max_devices` of them.
+    pub fn set_max_devices(&self, max_devices: usize) {
+        self.state.lock().max_devices = max_devices;
+    }
+
+    /// Invalidate every access token that has been handed out, as happens when they expire early.
+    pub fn revoke_access_tokens(&self) {
+        self.state.lock().access_tokens.clear();
+    }
+
+    /// Add a voucher that adds `time_added` to an account. It can be redeemed once.
+    pub fn add_voucher(&self, code: impl Into<String>, time_added: Duration) {
+        let voucher = Voucher {
+            time_added,
+            used: false,
+        };
+        self.state.lock().vouchers.insert(code.into(), voucher);
+    }
+
+    /// Replace the relay list, in the format served by `app/v1/relays`. This changes the ETag of
+    /// the relay list. The initial relay list is valid but empty.
+    pub fn set_relay_list(&self, relay_list: serde_json::Value) {
+        let mut inner = self.state.lock();
+        inner.relay_list = relay_list;
+        inner.relay_list_version += 1;
+    }
+
+    /// Replace the addresses served by `app/v1/api-addrs`. Initially, this is only the address
+    /// of this API.
+    pub fn set_api_addrs(&self, api_addrs: Vec<SocketAddr>) {
+        self.state.lock().api_addrs = api_addrs;
+    }
+
+    /// Every problem report that has been submitted, oldest first.
+    pub fn problem_reports(&self) -> Vec<ProblemReport> {
+        self.state.lock().problem_reports.clone()
+    }
+
+    /// The number of requests received so far whose path starts with `path_prefix`, including
+    /// requests that failed due to a [`Fault`].
+    pub fn request_count(&self, path_prefix: &str) -> usize {
+        let inner = self.state.lock();
+        inner
+            .requests
+            .iter()
+            .filter(|path| path.starts_with(path_prefix))
+            .count()
+    }
+
+    /// Call `hook` whenever a device is created, removed or has its key rotated through the API.
+    /// The response is sent after the hook completes, or with status 500 if it fails. This
+    /// replaces any previous hook.
+    ///
+    /// This can be used to register devices with a WireGuard relay.
+    pub fn on_device_event<F, Fut>(&self, hook: F)
+    where
+        F: Fn(DeviceEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.state.set_device_hook(Arc::new(
+            move |event| -> BoxFuture<'static, anyhow::Result<()>> { Box::pin(hook(event)) },
+        ));
+    }
+
+    /// Make requests fail according to `rule`. Rules are checked in the order they were added,
+    /// and at most one fault is applied to each request.
+    pub fn inject_fault(&self, rule: FaultRule) {
+        self.state.lock().faults.push(rule);
+    }
+
+    /// Remove all fault rules.
+    pub fn clear_faults(&self) {
+        self.state.lock().faults.clear();
+    }
+}
+
+impl Drop for FakeApi {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
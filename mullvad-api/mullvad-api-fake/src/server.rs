@@ -0,0 +1,173 @@
+//! The HTTP server, and the connection-level faults that bypass it.
+
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{
+    Request, StatusCode, body::Incoming, header, server::conn::http1, service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::{convert::Infallible, io, net::SocketAddr, sync::Arc, time::Duration};
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+    task::JoinSet,
+};
+
+use crate::{Fault, api::State};
+
+pub(crate) type Response = hyper::Response<Full<Bytes>>;
+
+/// Longest request line that is inspected before the request is handed to hyper.
+const MAX_REQUEST_LINE: usize = 8 * 1024;
+/// The content type of every TLS handshake record, including the ClientHello.
+const TLS_HANDSHAKE: u8 = 0x16;
+/// A TLS 1.2 alert record with a fatal `handshake_failure` alert.
+const TLS_HANDSHAKE_FAILURE_ALERT: [u8; 7] = [0x15, 0x03, 0x03, 0x00, 0x02, 0x02, 0x28];
+
+/// Accept connections on `listener` and serve them until the future is dropped.
+pub(crate) async fn serve(listener: TcpListener, state: Arc<State>) {
+    // Dropping the set aborts the connections, including any that are deliberately hanging.
+    let mut connections = JoinSet::new();
+    loop {
+        let (stream, client) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(error) => {
+                log::error!("Fake API failed to accept connection: {error}");
+                return;
+            }
+        };
+        while connections.try_join_next().is_some() {}
+        connections.spawn(handle_connection(stream, client, Arc::clone(&state)));
+    }
+}
+
+/// The start of what a client sent on a new connection.
+enum Preamble {
+    /// An HTTP request for `path`.
+    Http { path: String },
+    /// A TLS handshake.
+    Tls,
+}
+
+async fn handle_connection(mut stream: TcpStream, client: SocketAddr, state: Arc<State>) {
+    let preamble = match read_preamble(&stream).await {
+        Ok(Some(preamble)) => preamble,
+        Ok(None) => return,
+        Err(error) => {
+            log::debug!("Failed to read request from {client}: {error}");
+            return;
+        }
+    };
+
+    let fault = match preamble {
+        Preamble::Http { path } => state.begin_request(&path),
+        Preamble::Tls => {
+            log::debug!("Rejecting TLS handshake from {client}. The fake API only serves HTTP");
+            Some(Fault::TlsAlert)
+        }
+    };
+
+    match fault {
+        Some(Fault::Hang) => {
+            // Read and discard everything until the client gives up
+            let _ = tokio::io::copy(&mut stream, &mut tokio::io::sink()).await;
+        }
+        Some(Fault::CloseConnection) => (),
+        Some(Fault::TlsAlert) => {
+            let _ = stream.write_all(&TLS_HANDSHAKE_FAILURE_ALERT).await;
+            let _ = stream.shutdown().await;
+        }
+        fault @ (None | Some(Fault::Delay(_)) | Some(Fault::Status(_))) => {
+            serve_request(stream, client, state, fault).await
+        }
+    }
+}
+
+/// Peek at the first line sent on `stream`, without consuming it. Returns `None` if the client
+/// closed the connection without sending anything.
+async fn read_preamble(stream: &TcpStream) -> io::Result<Option<Preamble>> {
+    let mut buffer = vec![0; MAX_REQUEST_LINE];
+    loop {
+        let length = stream.peek(&mut buffer).await?;
+        let received = &buffer[..length];
+        if received.is_empty() {
+            return Ok(None);
+        }
+        if received[0] == TLS_HANDSHAKE {
+            return Ok(Some(Preamble::Tls));
+        }
+        if let Some(end) = received.iter().position(|&byte| byte == b'\n') {
+            // The request line is `<method> <path>[?<query>] <version>`
+            let line = String::from_utf8_lossy(&received[..end]);
+            let target = line.split(' ').nth(1).unwrap_or_default();
+            let path = target.split('?').next().unwrap_or_default().to_owned();
+            return Ok(Some(Preamble::Http { path }));
+        }
+        if length == buffer.len() {
+            // Let hyper reject the oversized request
+            return Ok(Some(Preamble::Http {
+                path: String::new(),
+            }));
+        }
+        // Wait for the rest of the request line
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+}
+
+/// Serve a single request on `stream`. `fault` must be a fault that applies to a response.
+async fn serve_request(
+    stream: TcpStream,
+    client: SocketAddr,
+    state: Arc<State>,
+    fault: Option<Fault>,
+) {
+    let service = service_fn(move |request: Request<Incoming>| {
+        let fault = fault.clone();
+        let state = Arc::clone(&state);
+        async move {
+            let response = match fault {
+                Some(Fault::Status(status)) => empty(status),
+                Some(Fault::Delay(delay)) => {
+                    tokio::time::sleep(delay).await;
+                    state.handle(request).await
+                }
+                _ => state.handle(request).await,
+            };
+            Ok::<_, Infallible>(response)
+        }
+    });
+
+    // Every connection carries a single request, so that faults can be applied to each request
+    // before hyper takes over the connection.
+    if let Err(error) = http1::Builder::new()
+        .keep_alive(false)
+        .serve_connection(TokioIo::new(stream), service)
+        .await
+    {
+        log::debug!("Fake API connection from {client} failed: {error}");
+    }
+}
+
+/// A response with `body` serialized as JSON.
+pub(crate) fn json(status: StatusCode, body: &impl Serialize) -> Response {
+    let body = serde_json::to_vec(body).expect("Failed to serialize response");
+    hyper::Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// A response without a body.
+pub(crate) fn empty(status: StatusCode) -> Response {
+    hyper::Response::builder()
+        .status(status)
+        .body(Full::default())
+        .unwrap()
+}
+
+/// An error response in the format that `mullvad-api` understands.
+pub(crate) fn error(status: StatusCode, code: &str) -> Response {
+    json(status, &serde_json::json!({ "code": code }))
+}
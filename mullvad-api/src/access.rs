@@ -181,3 +181,38 @@ async fn fetch_access_token(
         .expected_status(&[StatusCode::OK]);
     service.request(rest_request).await?.deserialize().await
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{AccountsProxy, INVALID_ACCESS_TOKEN, rest, test::start_fake_api};
+    use chrono::Utc;
+
+    /// An access token should be fetched once and reused.
+    #[tokio::test]
+    async fn test_reuse_access_token() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = AccountsProxy::new(handle);
+        let account = api.create_account(Utc::now());
+
+        proxy.get_data(account.clone()).await.unwrap();
+        proxy.get_data(account).await.unwrap();
+
+        assert_eq!(api.request_count("/auth/v1/token"), 1);
+    }
+
+    /// A rejected access token should be forgotten, so that the next request gets a new one.
+    #[tokio::test]
+    async fn test_replace_revoked_access_token() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = AccountsProxy::new(handle);
+        let account = api.create_account(Utc::now());
+        proxy.get_data(account.clone()).await.unwrap();
+
+        api.revoke_access_tokens();
+
+        let error = proxy.get_data(account.clone()).await.unwrap_err();
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == INVALID_ACCESS_TOKEN));
+        proxy.get_data(account).await.unwrap();
+        assert_eq!(api.request_count("/auth/v1/token"), 2);
+    }
+}
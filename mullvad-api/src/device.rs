@@ -219,3 +219,74 @@ impl DevicesProxy {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DEVICE_NOT_FOUND, MAX_DEVICES_REACHED, PUBKEY_IN_USE, test::start_fake_api};
+    use talpid_types::net::wireguard::PrivateKey;
+
+    fn new_pubkey() -> wireguard::PublicKey {
+        PrivateKey::new_from_random().public_key()
+    }
+
+    #[tokio::test]
+    async fn test_device_lifecycle() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = DevicesProxy::new(handle);
+        let account = api.create_account(Utc::now());
+
+        let (device, addresses) = proxy.create(account.clone(), new_pubkey()).await.unwrap();
+        let fake_device = &api.devices(&account)[0];
+        assert_eq!(device.id, fake_device.id);
+        assert_eq!(addresses.ipv4_address, fake_device.ipv4_address);
+
+        let listed = proxy.list(account.clone()).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].id, device.id);
+        let fetched = proxy.get(account.clone(), device.id.clone()).await.unwrap();
+        assert_eq!(fetched.pubkey, device.pubkey);
+
+        let pubkey = new_pubkey();
+        proxy
+            .replace_wg_key(account.clone(), device.id.clone(), pubkey.clone())
+            .await
+            .unwrap();
+        assert_eq!(api.devices(&account)[0].pubkey, pubkey);
+
+        proxy
+            .remove(account.clone(), device.id.clone())
+            .await
+            .unwrap();
+        assert!(api.devices(&account).is_empty());
+        let error = proxy.get(account, device.id).await.unwrap_err();
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == DEVICE_NOT_FOUND));
+    }
+
+    #[tokio::test]
+    async fn test_max_devices() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = DevicesProxy::new(handle);
+        let account = api.create_account(Utc::now());
+        api.set_max_devices(1);
+
+        proxy.create(account.clone(), new_pubkey()).await.unwrap();
+        let error = proxy.create(account, new_pubkey()).await.unwrap_err();
+
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == MAX_DEVICES_REACHED));
+    }
+
+    #[tokio::test]
+    async fn test_pubkey_in_use() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = DevicesProxy::new(handle);
+        let first_account = api.create_account(Utc::now());
+        let second_account = api.create_account(Utc::now());
+        let pubkey = new_pubkey();
+
+        proxy.create(first_account, pubkey.clone()).await.unwrap();
+        let error = proxy.create(second_account, pubkey).await.unwrap_err();
+
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == PUBKEY_IN_USE));
+    }
+}
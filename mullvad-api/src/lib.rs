@@ -797,3 +797,114 @@ impl ApiProxy {
         Ok(response.status().is_success())
     }
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use chrono::{TimeDelta, Utc};
+    use mullvad_api_fake::FakeApi;
+    use std::time::Duration;
+
+    /// Start a fake API, and return it along with a handle that reaches it directly.
+    pub(crate) async fn start_fake_api() -> (FakeApi, rest::MullvadRestHandle) {
+        start_fake_api_with_provider(ApiConnectionMode::Direct.into_provider()).await
+    }
+
+    /// Start a fake API, and return it along with a handle that uses `connection_mode_provider`.
+    pub(crate) async fn start_fake_api_with_provider(
+        connection_mode_provider: impl ConnectionModeProvider + 'static,
+    ) -> (FakeApi, rest::MullvadRestHandle) {
+        let api = FakeApi::start().await.expect("Failed to start fake API");
+        let endpoint = ApiEndpoint::new(API_HOST_DEFAULT.to_owned(), api.address(), true);
+        let runtime = Runtime::new(tokio::runtime::Handle::current(), &endpoint);
+        let handle = runtime.mullvad_rest_handle(connection_mode_provider);
+        (api, handle)
+    }
+
+    #[tokio::test]
+    async fn test_create_account() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = AccountsProxy::new(handle);
+
+        let account = proxy.create_account().await.unwrap();
+        let data = proxy.get_data(account.clone()).await.unwrap();
+
+        assert_eq!(api.account_expiry(&account), Some(data.expiry));
+        assert!(data.is_expired(), "New accounts should have no time");
+    }
+
+    #[tokio::test]
+    async fn test_submit_voucher() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = AccountsProxy::new(handle);
+        let expiry = Utc::now() + TimeDelta::days(1);
+        let account = api.create_account(expiry);
+        let time_added = Duration::from_secs(30 * 24 * 60 * 60);
+        api.add_voucher("VOUCHER", time_added);
+
+        let submission = proxy
+            .submit_voucher(account.clone(), "VOUCHER".to_owned())
+            .await
+            .unwrap();
+        assert_eq!(submission.time_added, time_added.as_secs());
+        assert_eq!(
+            submission.new_expiry,
+            expiry + TimeDelta::from_std(time_added).unwrap()
+        );
+
+        let error = proxy
+            .submit_voucher(account.clone(), "VOUCHER".to_owned())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == VOUCHER_USED));
+
+        let error = proxy
+            .submit_voucher(account, "NOT-A-VOUCHER".to_owned())
+            .await
+            .unwrap_err();
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == INVALID_VOUCHER));
+    }
+
+    #[tokio::test]
+    async fn test_delete_account() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = AccountsProxy::new(handle);
+        let account = api.create_account(Utc::now());
+
+        proxy.delete_account(account.clone()).await.unwrap();
+
+        assert_eq!(api.account_expiry(&account), None);
+        // The first request is rejected along with the revoked access token. The next one fails
+        // to obtain a new token.
+        let _ = proxy.get_data(account.clone()).await;
+        let error = proxy.get_data(account).await.unwrap_err();
+        assert!(matches!(error, rest::Error::ApiError(_, code) if code == INVALID_ACCOUNT));
+    }
+
+    #[tokio::test]
+    async fn test_problem_report() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = ProblemReportProxy::new(handle);
+        let metadata = BTreeMap::from([("os".to_owned(), "linux".to_owned())]);
+
+        proxy
+            .problem_report("user@example.com", "It broke", "log", &metadata)
+            .await
+            .unwrap();
+
+        let reports = api.problem_reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].address, "user@example.com");
+        assert_eq!(reports[0].message, "It broke");
+        assert_eq!(reports[0].metadata, metadata);
+    }
+
+    #[tokio::test]
+    async fn test_api_addrs() {
+        let (api, handle) = start_fake_api().await;
+        let proxy = ApiProxy::new(handle);
+
+        assert_eq!(proxy.get_api_addrs().await.unwrap(), vec![api.address()]);
+        assert!(proxy.api_addrs_available().await.unwrap());
+    }
+}
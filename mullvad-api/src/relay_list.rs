@@ -677,4 +677,49 @@ mod test {
         assert_eq!(list.wireguard.relays.len(), 2);
         assert_eq!(list.bridge.relays.len(), 1);
     }
+
+    /// Verify that the relay list is only downloaded again once its ETag has changed.
+    #[tokio::test]
+    async fn relay_list_is_cached_by_etag() {
+        let (api, handle) = crate::test::start_fake_api().await;
+        let proxy = RelayListProxy::new(handle);
+
+        let relay_list = proxy.relay_list(None).await.unwrap().unwrap();
+        let etag = relay_list.etag.expect("Relay list should have an ETag");
+        assert!(
+            proxy
+                .relay_list(Some(etag.clone()))
+                .await
+                .unwrap()
+                .is_none(),
+            "An unchanged relay list should not be returned"
+        );
+
+        api.set_relay_list(serde_json::json!({
+            "locations": {},
+            "wireguard": {
+                "port_ranges": [[53, 53]],
+                "ipv4_gateway": "10.64.0.1",
+                "ipv6_gateway": "fc00:bbbb::1",
+                "relays": [{
+                    "hostname": "se-got-test-001",
+                    "active": true,
+                    "owned": true,
+                    "location": "se-got",
+                    "provider": "provider",
+                    "ipv4_addr_in": "1.2.3.4",
+                    "weight": 1,
+                    "include_in_country": true,
+                    "public_key": "ylcRkwdcalOkZEf+v+jz2qBbw22X0v+wZdPmoa6w+FI=",
+                }],
+            },
+            "bridge": {
+                "shadowsocks": [],
+                "relays": [],
+            },
+        }));
+
+        let relay_list = proxy.relay_list(Some(etag)).await.unwrap().unwrap();
+        assert_eq!(relay_list.relay_list.wireguard.relays.len(), 1);
+    }
 }
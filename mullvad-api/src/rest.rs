@@ -778,3 +778,126 @@ impl_into_arc_err!(hyper_util::client::legacy::Error);
 impl_into_arc_err!(serde_json::Error);
 impl_into_arc_err!(http::Error);
 impl_into_arc_err!(http::uri::InvalidUri);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        API_HOST_DEFAULT, ApiEndpoint, Runtime,
+        proxy::ApiConnectionMode,
+        test::{start_fake_api, start_fake_api_with_provider},
+    };
+    use mullvad_api_fake::{Fault, FaultRule};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+
+    /// Stays in direct mode, but counts how many times it has been asked to rotate.
+    struct CountingProvider {
+        rotations: Arc<AtomicUsize>,
+    }
+
+    impl ConnectionModeProvider for CountingProvider {
+        fn initial(&self) -> ApiConnectionMode {
+            ApiConnectionMode::Direct
+        }
+
+        fn rotate(&self) -> impl std::future::Future<Output = ()> + Send {
+            self.rotations.fetch_add(1, Ordering::SeqCst);
+            futures::future::ready(())
+        }
+
+        fn receive(
+            &mut self,
+        ) -> impl std::future::Future<Output = Option<ApiConnectionMode>> + Send {
+            futures::future::pending()
+        }
+    }
+
+    async fn get_api_addrs(handle: &MullvadRestHandle) -> Result<Response<Incoming>> {
+        let request = handle
+            .factory
+            .get("app/v1/api-addrs")?
+            .timeout(REQUEST_TIMEOUT)
+            .expected_status(&[hyper::StatusCode::OK]);
+        handle.service.request(request).await
+    }
+
+    /// Requests that fail without a response should make the service try another connection
+    /// mode. Any request that is sent afterwards goes out after the rotation.
+    #[tokio::test]
+    async fn test_network_errors_rotate_connection_mode() {
+        let rotations = Arc::new(AtomicUsize::new(0));
+        let provider = CountingProvider {
+            rotations: Arc::clone(&rotations),
+        };
+        let (api, handle) = start_fake_api_with_provider(provider).await;
+
+        for (fault, expected_rotations) in [Fault::CloseConnection, Fault::TlsAlert, Fault::Hang]
+            .into_iter()
+            .zip(1..)
+        {
+            api.inject_fault(FaultRule::new(fault.clone()).times(1));
+
+            let error = get_api_addrs(&handle).await.unwrap_err();
+            assert!(error.is_network_error(), "{fault:?} caused {error:?}");
+            get_api_addrs(&handle).await.unwrap();
+            assert_eq!(rotations.load(Ordering::SeqCst), expected_rotations);
+        }
+    }
+
+    /// Error responses from the API should not be mistaken for network errors.
+    #[tokio::test]
+    async fn test_server_error() {
+        let rotations = Arc::new(AtomicUsize::new(0));
+        let provider = CountingProvider {
+            rotations: Arc::clone(&rotations),
+        };
+        let (api, handle) = start_fake_api_with_provider(provider).await;
+        api.inject_fault(
+            FaultRule::new(Fault::Status(hyper::StatusCode::SERVICE_UNAVAILABLE)).times(1),
+        );
+
+        let error = get_api_addrs(&handle).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                Error::ApiError(hyper::StatusCode::SERVICE_UNAVAILABLE, _)
+            ),
+            "Unexpected error: {error:?}"
+        );
+        get_api_addrs(&handle).await.unwrap();
+        assert_eq!(rotations.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_timeout() {
+        let (api, handle) = start_fake_api().await;
+        api.inject_fault(FaultRule::new(Fault::Hang).path("/app/v1/api-addrs"));
+
+        let error = get_api_addrs(&handle).await.unwrap_err();
+
+        assert!(matches!(error, Error::TimeoutError), "{error:?}");
+    }
+
+    #[tokio::test]
+    async fn test_slow_response() {
+        let (api, handle) = start_fake_api().await;
+        api.inject_fault(FaultRule::new(Fault::Delay(REQUEST_TIMEOUT / 10)).times(1));
+
+        get_api_addrs(&handle).await.unwrap();
+    }
+
+    /// The fake API does not speak TLS, so the handshake fails.
+    #[tokio::test]
+    async fn test_tls_handshake_failure() {
+        let (api, _handle) = start_fake_api().await;
+        let endpoint = ApiEndpoint::new(API_HOST_DEFAULT.to_owned(), api.address(), false);
+        let runtime = Runtime::new(tokio::runtime::Handle::current(), &endpoint);
+        let handle = runtime.mullvad_rest_handle(ApiConnectionMode::Direct.into_provider());
+
+        let error = get_api_addrs(&handle).await.unwrap_err();
+
+        assert!(error.is_network_error(), "{error:?}");
+    }
+}
@@ -1,14 +1,27 @@
-use crate::BIN_NAME;
-use anyhow::{Result, anyhow};
+use crate::{BIN_NAME, output, println_human};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::Subcommand;
 use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::{account::AccountNumber, device::DeviceState};
+use mullvad_types::{
+    account::{AccountData, AccountNumber, ExpiryWarningThresholds},
+    device::DeviceState,
+};
+use serde::Serialize;
+use serde_json::json;
 use std::io::{self, Write};
 
 const NOT_LOGGED_IN_MESSAGE: &str = "Not logged in on any account";
 const REVOKED_MESSAGE: &str = "The current device has been revoked";
 
+/// JSON output of `account get`.
+#[derive(Serialize)]
+struct AccountOutput {
+    device: DeviceState,
+    /// Expiry of the account. Missing if the device is not logged in.
+    account: Option<AccountData>,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Account {
     /// Create and log in on a new account
@@ -56,6 +69,25 @@ pub enum Account {
         /// Voucher code to submit
         voucher: String,
     },
+
+    /// Display or set when the daemon warns that the account is about to run out of time
+    #[clap(subcommand)]
+    ExpiryWarnings(ExpiryWarnings),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExpiryWarnings {
+    /// Display how long before the account expires warnings are sent
+    Get,
+
+    /// Set how long before the account expires warnings are sent. A warning is always sent when
+    /// the account has expired
+    Set {
+        /// Time before the account expires, in days or hours, e.g. 7d or 12h. If omitted, a
+        /// warning is only sent when the account has expired
+        #[arg(value_parser = parse_threshold)]
+        thresholds: Vec<u32>,
+    },
 }
 
 impl Account {
@@ -79,24 +111,30 @@ impl Account {
                 Self::revoke_device(&mut rpc, device, account).await
             }
             Account::Redeem { voucher } => Self::redeem_voucher(&mut rpc, voucher).await,
+            Account::ExpiryWarnings(ExpiryWarnings::Get) => {
+                Self::get_expiry_warnings(&mut rpc).await
+            }
+            Account::ExpiryWarnings(ExpiryWarnings::Set { thresholds }) => {
+                Self::set_expiry_warnings(&mut rpc, thresholds).await
+            }
         }
     }
 
     async fn create(rpc: &mut MullvadProxyClient) -> Result<()> {
         rpc.create_new_account().await?;
-        println!("New account created!");
+        println_human!("New account created!");
         Self::get(rpc, false).await
     }
 
     async fn login(rpc: &mut MullvadProxyClient, account_number: AccountNumber) -> Result<()> {
         rpc.login_account(account_number.clone()).await?;
-        println!("Mullvad account \"{account_number}\" set");
+        println_human!("Mullvad account \"{account_number}\" set");
         Ok(())
     }
 
     async fn logout(rpc: &mut MullvadProxyClient) -> Result<()> {
         rpc.logout_account(&format!("{BIN_NAME} logout")).await?;
-        println!("Removed device from Mullvad account");
+        println_human!("Removed device from Mullvad account");
         Ok(())
     }
 
@@ -105,6 +143,19 @@ impl Account {
 
         let state = rpc.get_device().await?;
 
+        if output::is_json() {
+            let account = match &state {
+                DeviceState::LoggedIn(device) => {
+                    Some(rpc.get_account_data(device.account_number.clone()).await?)
+                }
+                DeviceState::LoggedOut | DeviceState::Revoked => None,
+            };
+            return output::print_json(&AccountOutput {
+                device: state,
+                account,
+            });
+        }
+
         match state {
             DeviceState::LoggedIn(device) => {
                 println!("{:<20}{}", "Mullvad account:", device.account_number);
@@ -147,9 +198,13 @@ impl Account {
     ) -> Result<()> {
         let account_number = account_else_current(rpc, account).await?;
         let mut device_list = rpc.list_devices(account_number).await?;
+        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
+
+        if output::is_json() {
+            return output::print_json(&device_list);
+        }
 
         println!("Devices on the account:");
-        device_list.sort_unstable_by_key(|dev| dev.created.timestamp());
         for device in device_list {
             if verbose {
                 println!();
@@ -185,7 +240,7 @@ impl Account {
             .ok_or(mullvad_management_interface::Error::DeviceNotFound)?;
 
         rpc.remove_device(account_number, device_id).await?;
-        println!("Removed device");
+        println_human!("Removed device");
         Ok(())
     }
 
@@ -193,6 +248,9 @@ impl Account {
         voucher.retain(|c| c.is_alphanumeric());
 
         let submission = rpc.submit_voucher(voucher).await?;
+        if output::is_json() {
+            return output::print_json(&submission);
+        }
         println!(
             "Added {} to the account",
             format_duration(submission.time_added)
@@ -203,6 +261,63 @@ impl Account {
         );
         Ok(())
     }
+
+    async fn get_expiry_warnings(rpc: &mut MullvadProxyClient) -> Result<()> {
+        let thresholds = rpc.get_settings().await?.expiry_warning_thresholds;
+        if output::is_json() {
+            return output::print_json(&json!({ "expiry_warning_thresholds": thresholds }));
+        }
+        if thresholds.hours().is_empty() {
+            println!("Warn when the account has expired");
+        } else {
+            println!(
+                "Warn {} before the account expires, and when it has expired",
+                thresholds
+                    .hours()
+                    .iter()
+                    .copied()
+                    .map(format_hours)
+                    .join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    async fn set_expiry_warnings(rpc: &mut MullvadProxyClient, hours: Vec<u32>) -> Result<()> {
+        rpc.set_expiry_warning_thresholds(&ExpiryWarningThresholds::new(hours))
+            .await?;
+        println_human!("Updated expiry warnings");
+        Ok(())
+    }
+}
+
+/// Parse a time before the account expires, such as `7d` or `12h`, into a number of hours.
+fn parse_threshold(s: &str) -> Result<u32> {
+    let (value, hours_per_unit) = if let Some(days) = s.strip_suffix('d') {
+        (days, 24)
+    } else if let Some(hours) = s.strip_suffix('h') {
+        (hours, 1)
+    } else {
+        bail!("Expected a number of days or hours, such as 7d or 12h");
+    };
+    let value: u32 = value.parse().context("Invalid number")?;
+    let hours = value
+        .checked_mul(hours_per_unit)
+        .context("Threshold is too large")?;
+    if hours == 0 {
+        bail!("Threshold must be larger than zero");
+    }
+    Ok(hours)
+}
+
+/// Format a number of hours as days if possible, e.g. "3 days" or "12 hours".
+pub fn format_hours(hours: u32) -> String {
+    match (hours / 24, hours % 24) {
+        (1, 0) => "1 day".to_string(),
+        (days, 0) => format!("{days} days"),
+        _ if hours == 1 => "1 hour".to_string(),
+        _ => format!("{hours} hours"),
+    }
 }
 
 async fn account_else_current(
@@ -233,10 +348,16 @@ async fn unwrap_or_from_stdin(val: Option<String>, prompt_str: &'static str) ->
 
 fn from_stdin(prompt_str: &'static str) -> String {
     let mut val = String::new();
-    io::stdout()
+    // Keep standard output free from anything but JSON
+    let mut prompt_out: Box<dyn Write> = if output::is_json() {
+        Box::new(io::stderr())
+    } else {
+        Box::new(io::stdout())
+    };
+    prompt_out
         .write_all(prompt_str.as_bytes())
-        .expect("Failed to write to STDOUT");
-    let _ = io::stdout().flush();
+        .expect("Failed to write prompt");
+    let _ = prompt_out.flush();
     io::stdin()
         .read_line(&mut val)
         .expect("Failed to read from STDIN");
@@ -255,3 +376,43 @@ fn format_duration(seconds: u64) -> String {
         format!("{} seconds", dur.num_seconds())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The JSON printed by `account get`, as described in `JSON.md`
+    #[test]
+    fn test_account_output_json() {
+        let device = json!({
+            "logged_in": {
+                "account_number": "1234123412341234",
+                "device": {
+                    "id": "7d2c9b7e-1c5a-4c39-a9a4-0e1f2d3c4b5a",
+                    "name": "happy seagull",
+                    "pubkey": "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=",
+                    "hijack_dns": false,
+                    "created": "2026-01-01T00:00:00Z",
+                },
+            },
+        });
+        let account = json!({ "id": "account-id", "expiry": "2027-01-01T00:00:00Z" });
+        let output = AccountOutput {
+            device: serde_json::from_value(device.clone()).unwrap(),
+            account: serde_json::from_value(account.clone()).unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_value(output).unwrap(),
+            json!({ "device": device, "account": account })
+        );
+
+        let output = AccountOutput {
+            device: DeviceState::LoggedOut,
+            account: None,
+        };
+        assert_eq!(
+            serde_json::to_value(output).unwrap(),
+            json!({ "device": "logged_out", "account": null })
+        );
+    }
+}
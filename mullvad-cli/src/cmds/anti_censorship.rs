@@ -3,11 +3,15 @@ use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
+    network_hints::NetworkHint,
     relay_constraints::{
-        LwoSettings, ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings,
-        Udp2TcpObfuscationSettings, WireguardPortSettings,
+        LwoSettings, ObfuscationSettings, RaceSettings, RaceTransport, SelectedObfuscation,
+        ShadowsocksSettings, Udp2TcpObfuscationSettings, WireguardPortSettings,
     },
 };
+use std::time::Duration;
+
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum AntiCensorship {
@@ -17,6 +21,20 @@ pub enum AntiCensorship {
     /// Set anti-censorship settings
     #[clap(subcommand)]
     Set(SetCommands),
+
+    /// Manage the anti-censorship methods that are tried first on networks where they have
+    /// worked before
+    #[clap(subcommand)]
+    Hints(HintsCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum HintsCommands {
+    /// List the networks and the method that last worked on each of them
+    List,
+
+    /// Forget the methods remembered for all networks
+    Clear,
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -51,6 +69,18 @@ pub enum SetCommands {
         #[arg(long, short = 'p')]
         port: Constraint<u16>,
     },
+
+    /// Configure which methods the race mode tries, and how long to wait between starting them.
+    /// The first method to get a response from the relay is used.
+    #[clap(arg_required_else_help = true)]
+    Race {
+        /// Comma-separated list of methods to race
+        #[arg(long, short = 't', value_delimiter = ',')]
+        transports: Option<Vec<RaceTransport>>,
+        /// Delay in milliseconds between starting each method
+        #[arg(long, short = 'd', value_parser = clap::value_parser!(u64).range(1..))]
+        stagger_delay: Option<u64>,
+    },
 }
 
 impl AntiCensorship {
@@ -59,6 +89,9 @@ impl AntiCensorship {
             AntiCensorship::Get => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let obfuscation_settings = rpc.get_settings().await?.obfuscation_settings;
+                if output::is_json() {
+                    return output::print_json(&obfuscation_settings);
+                }
                 println!("mode: {}", obfuscation_settings.selected_obfuscation);
                 println!("udp2tcp settings: {}", obfuscation_settings.udp2tcp);
                 println!("shadowsocks settings: {}", obfuscation_settings.shadowsocks);
@@ -67,12 +100,39 @@ impl AntiCensorship {
                     obfuscation_settings.wireguard_port
                 );
                 println!("lwo settings: {}", obfuscation_settings.lwo);
+                println!("race settings: {}", obfuscation_settings.race);
                 Ok(())
             }
             AntiCensorship::Set(subcmd) => Self::set(subcmd).await,
+            AntiCensorship::Hints(subcmd) => Self::hints(subcmd).await,
         }
     }
 
+    async fn hints(subcmd: HintsCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+
+        match subcmd {
+            HintsCommands::List => {
+                let hints = rpc.get_network_hints().await?;
+                if output::is_json() {
+                    return output::print_json(&hints);
+                }
+                if hints.is_empty() {
+                    println!("No networks are remembered");
+                }
+                for hint in hints.iter() {
+                    print_hint(hint);
+                }
+            }
+            HintsCommands::Clear => {
+                rpc.clear_network_hints().await?;
+                println_human!("Cleared remembered anti-censorship methods");
+            }
+        }
+
+        Ok(())
+    }
+
     async fn set(subcmd: SetCommands) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let current_settings = rpc.get_settings().await?.obfuscation_settings;
@@ -125,14 +185,56 @@ impl AntiCensorship {
                 })
                 .await?;
             }
+            SetCommands::Race {
+                transports,
+                stagger_delay,
+            } => {
+                let current_race = current_settings.race.clone();
+                let race = RaceSettings {
+                    transports: transports.unwrap_or(current_race.transports),
+                    stagger_delay: stagger_delay
+                        .map(Duration::from_millis)
+                        .unwrap_or(current_race.stagger_delay),
+                };
+                if race.transports.is_empty() {
+                    return Err(anyhow::anyhow!("At least one method must be raced"));
+                }
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    race,
+                    ..current_settings
+                })
+                .await?;
+            }
         }
 
-        println!("Updated anti-censorship settings");
+        println_human!("Updated anti-censorship settings");
 
         Ok(())
     }
 }
 
+fn print_hint(hint: &NetworkHint) {
+    let obfuscation = &hint.obfuscation;
+    println!("{}", hint.network);
+    print!("\tmode: {}", obfuscation.selected_obfuscation);
+    match obfuscation.selected_obfuscation {
+        SelectedObfuscation::Udp2Tcp => print!(" ({})", obfuscation.udp2tcp),
+        SelectedObfuscation::Shadowsocks => print!(" ({})", obfuscation.shadowsocks),
+        SelectedObfuscation::WireguardPort => print!(" ({})", obfuscation.wireguard_port),
+        SelectedObfuscation::Lwo => print!(" ({})", obfuscation.lwo),
+        SelectedObfuscation::Race => print!(" ({})", obfuscation.race),
+        SelectedObfuscation::Auto | SelectedObfuscation::Off | SelectedObfuscation::Quic => (),
+    }
+    println!();
+    if let Constraint::Only(ip_version) = hint.ip_version {
+        println!("\tIP version: {ip_version}");
+    }
+    println!(
+        "\tlast worked: {}",
+        hint.last_success.with_timezone(&chrono::Local)
+    );
+}
+
 fn is_valid_wg_port(
     wireguard: &mullvad_types::relay_list::EndpointData,
     port: Constraint<u16>,
@@ -6,6 +6,7 @@ use talpid_types::net::proxy::CustomProxy;
 use clap::{Args, Subcommand};
 
 use super::proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum ApiAccess {
@@ -69,7 +70,11 @@ impl ApiAccess {
     /// Show all API access methods.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        for (index, api_access_method) in rpc.get_api_access_methods().await?.iter().enumerate() {
+        let api_access_methods = rpc.get_api_access_methods().await?;
+        if output::is_json() {
+            return output::print_json(&api_access_methods);
+        }
+        for (index, api_access_method) in api_access_methods.iter().enumerate() {
             println!(
                 "{}. {}",
                 index + 1,
@@ -190,10 +195,10 @@ impl ApiAccess {
         let mut rpc = MullvadProxyClient::new().await?;
         let access_method = Self::get_access_method(&mut rpc, &item).await?;
 
-        println!("Testing access method \"{}\"", access_method.name);
+        println_human!("Testing access method \"{}\"", access_method.name);
         match rpc.test_api_access_method(access_method.get_id()).await {
             Ok(true) => {
-                println!("Success!");
+                println_human!("Success!");
                 Ok(())
             }
             Ok(false) | Err(_) => Err(anyhow!("Could not reach the Mullvad API.")),
@@ -226,13 +231,16 @@ impl ApiAccess {
             ;
         // If the test succeeded, the new access method should be used from now on.
         rpc.set_access_method(new_access_method.get_id()).await?;
-        println!("Using access method \"{}\"", new_access_method.get_name());
+        println_human!("Using access method \"{}\"", new_access_method.get_name());
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let current = rpc.get_current_api_access_method().await?;
+        if output::is_json() {
+            return output::print_json(&current);
+        }
         let mut access_method_formatter = pp::ApiAccessMethodFormatter::new(&current);
         access_method_formatter.settings.write_enabled = false;
         println!("{access_method_formatter}");
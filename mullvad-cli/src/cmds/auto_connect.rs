@@ -1,8 +1,10 @@
 use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
 
 use super::BooleanOption;
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum AutoConnect {
@@ -23,13 +25,17 @@ impl AutoConnect {
     async fn set(policy: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_auto_connect(*policy).await?;
-        println!("Changed auto-connect setting");
+        println_human!("Changed auto-connect setting");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let auto_connect = BooleanOption::from(rpc.get_settings().await?.auto_connect);
+        let auto_connect = rpc.get_settings().await?.auto_connect;
+        if output::is_json() {
+            return output::print_json(&json!({ "auto_connect": auto_connect }));
+        }
+        let auto_connect = BooleanOption::from(auto_connect);
         println!("Autoconnect: {auto_connect}");
         Ok(())
     }
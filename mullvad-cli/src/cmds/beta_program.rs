@@ -1,8 +1,10 @@
 use anyhow::{Result, anyhow};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
 
 use super::BooleanOption;
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum BetaProgram {
@@ -30,13 +32,17 @@ impl BetaProgram {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_show_beta_releases(*state).await?;
 
-        println!("Beta program: {state}");
+        println_human!("Beta program: {state}");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let opt = BooleanOption::from(rpc.get_settings().await?.show_beta_releases);
+        let show_beta_releases = rpc.get_settings().await?.show_beta_releases;
+        if output::is_json() {
+            return output::print_json(&json!({ "show_beta_releases": show_beta_releases }));
+        }
+        let opt = BooleanOption::from(show_beta_releases);
         println!("Beta program: {opt}");
         Ok(())
     }
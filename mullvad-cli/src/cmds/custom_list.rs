@@ -1,9 +1,19 @@
 use super::{relay::resolve_location_constraint, relay_constraints::LocationArgs};
-use anyhow::{Result, anyhow, bail};
+use crate::{output, println_human};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
-    constraints::Constraint, relay_constraints::GeographicLocationConstraint, relay_list::RelayList,
+    constraints::Constraint,
+    custom_list::{CUSTOM_LISTS_EXPORT_VERSION, CustomListsExport, Id},
+    relay_constraints::GeographicLocationConstraint,
+    relay_list::RelayList,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{BufReader, read_to_string, stdin},
 };
 
 /// Custom list length, expressed as a number of UTF8 codepoints (i.e. chars).
@@ -35,6 +45,32 @@ pub enum CustomList {
         /// A custom list
         name: String,
     },
+
+    /// Export custom lists in a format that can be shared and imported on other devices
+    Export {
+        /// File to write to. If this is "-", write to standard output
+        file: String,
+
+        /// Custom lists to export. If omitted, all custom lists are exported
+        names: Vec<String>,
+    },
+
+    /// Import custom lists from a file created by 'export'. Lists are matched by name. Locations
+    /// are added to existing lists, and lists that do not exist are created. If any list cannot
+    /// be imported, none of them are
+    Import {
+        /// File to read from. If this is "-", read from standard input
+        file: String,
+
+        /// Replace the locations of existing lists instead of adding to them
+        #[arg(long)]
+        replace: bool,
+
+        /// Import lists even if some of their locations are not in the relay list. Such
+        /// locations are left out
+        #[arg(long)]
+        skip_unknown: bool,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -73,6 +109,12 @@ impl CustomList {
             CustomList::List { name: Some(name) } => Self::get(name).await,
             CustomList::New { name } => Self::create_list(name).await,
             CustomList::Delete { name } => Self::delete_list(name).await,
+            CustomList::Export { file, names } => Self::export(file, names).await,
+            CustomList::Import {
+                file,
+                replace,
+                skip_unknown,
+            } => Self::import(file, replace, skip_unknown).await,
             CustomList::Edit(cmd) => match cmd {
                 EditCommand::Add { name, location } => Self::add_location(name, location).await,
                 EditCommand::Rename { name, new_name } => Self::rename_list(name, new_name).await,
@@ -86,8 +128,13 @@ impl CustomList {
     /// Print all custom lists.
     async fn list() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
+        let custom_lists = rpc.get_settings().await?.custom_lists;
+        if output::is_json() {
+            // Print the lists themselves rather than the settings that contain them
+            return output::print_json(&*custom_lists);
+        }
         let cache = rpc.get_relay_locations().await?;
-        for custom_list in rpc.get_settings().await?.custom_lists {
+        for custom_list in custom_lists {
             Self::print_custom_list(&custom_list, &cache)
         }
         Ok(())
@@ -98,6 +145,9 @@ impl CustomList {
     async fn get(name: String) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let custom_list = find_list_by_name(&mut rpc, &name).await?;
+        if output::is_json() {
+            return output::print_json(&custom_list);
+        }
         let cache = rpc.get_relay_locations().await?;
         Self::print_custom_list_content(&custom_list, &cache);
         Ok(())
@@ -123,7 +173,7 @@ impl CustomList {
                 let mut list = find_list_by_name(&mut rpc, &name).await?;
                 if list.locations.insert(location) {
                     rpc.update_custom_list(list).await?;
-                    println!("Location added to custom-list")
+                    println_human!("Location added to custom-list")
                 } else {
                     bail!("Provided location is already present in custom-list")
                 };
@@ -147,7 +197,7 @@ impl CustomList {
                 let mut list = find_list_by_name(&mut rpc, &name).await?;
                 if list.locations.remove(&location) {
                     rpc.update_custom_list(list).await?;
-                    println!("Location removed from custom-list")
+                    println_human!("Location removed from custom-list")
                 } else {
                     bail!("Provided location was not present in custom-list")
                 };
@@ -174,6 +224,173 @@ impl CustomList {
         Ok(())
     }
 
+    async fn export(dest: String, names: Vec<String>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let custom_lists = rpc.get_settings().await?.custom_lists;
+        for name in &names {
+            if !custom_lists.iter().any(|list| &list.name == name) {
+                bail!("List not found: {name}");
+            }
+        }
+        let export = CustomListsExport::new(
+            custom_lists
+                .iter()
+                .filter(|list| names.is_empty() || names.contains(&list.name)),
+        );
+        let blob = serde_json::to_string_pretty(&export)?;
+
+        match dest.as_str() {
+            "-" => {
+                println!("{blob}");
+                Ok(())
+            }
+            _ => tokio::fs::write(&dest, blob)
+                .await
+                .context(format!("Failed to write to path {dest}")),
+        }
+    }
+
+    async fn import(source: String, replace: bool, skip_unknown: bool) -> Result<()> {
+        #[derive(Default, Serialize)]
+        struct ImportResult {
+            created: Vec<String>,
+            updated: Vec<String>,
+            unknown_locations: BTreeMap<String, Vec<String>>,
+        }
+
+        let json_blob = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => File::open(&source)
+                .and_then(read_to_string)
+                .with_context(|| format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+        let import: CustomListsExport =
+            serde_json::from_str(&json_blob).context("Invalid custom list file")?;
+        if import.version != CUSTOM_LISTS_EXPORT_VERSION {
+            bail!("Unsupported custom list file version: {}", import.version);
+        }
+        let mut names = HashSet::new();
+        for list in &import.custom_lists {
+            if !names.insert(&list.name) {
+                bail!("The file contains more than one list named {}", list.name);
+            }
+        }
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        let relay_list = rpc.get_relay_locations().await?;
+        let mut result = ImportResult::default();
+        let mut resolved_lists = Vec::new();
+        for list in &import.custom_lists {
+            let (locations, unknown) = list.resolve(&relay_list);
+            if !unknown.is_empty() {
+                result.unknown_locations.insert(list.name.clone(), unknown);
+            }
+            resolved_lists.push((list.name.clone(), locations));
+        }
+        for (name, unknown) in &result.unknown_locations {
+            eprintln!("Unknown locations in {name}: {}", unknown.join(", "));
+        }
+        if !result.unknown_locations.is_empty() && !skip_unknown {
+            bail!("No lists were imported. Use --skip-unknown to leave out unknown locations");
+        }
+
+        // Validate every list before changing anything, so that an invalid list leaves all lists
+        // as they were
+        let existing_lists = rpc.get_settings().await?.custom_lists;
+        let mut imports = Vec::new();
+        for (name, locations) in resolved_lists {
+            match existing_lists.iter().find(|list| list.name == name) {
+                Some(previous) => {
+                    let mut list = previous.clone();
+                    if replace {
+                        list.locations = locations;
+                    } else {
+                        list.append(locations);
+                    }
+                    imports.push((Some(previous.clone()), list));
+                }
+                None => {
+                    let mut list = mullvad_types::custom_list::CustomList::new(name.clone())
+                        .with_context(|| format!("Invalid custom list name: {name}"))?;
+                    list.locations = locations;
+                    imports.push((None, list));
+                }
+            }
+        }
+
+        let mut undo = Vec::new();
+        for (previous, list) in imports {
+            let name = list.name.clone();
+            match Self::import_list(&mut rpc, previous, list).await {
+                Ok(change) => {
+                    match change {
+                        ImportUndo::Delete(_) => result.created.push(name),
+                        ImportUndo::Restore(_) => result.updated.push(name),
+                    }
+                    undo.push(change);
+                }
+                Err(error) => {
+                    Self::undo_import(&mut rpc, undo).await;
+                    return Err(error.context("No lists were imported"));
+                }
+            }
+        }
+        for name in &result.created {
+            println_human!("Created custom list {name}");
+        }
+        for name in &result.updated {
+            println_human!("Updated custom list {name}");
+        }
+
+        if output::is_json() {
+            return output::print_json(&result);
+        }
+        Ok(())
+    }
+
+    /// Create `list`, or replace `previous` by it. Returns how to undo the change.
+    async fn import_list(
+        rpc: &mut MullvadProxyClient,
+        previous: Option<mullvad_types::custom_list::CustomList>,
+        list: mullvad_types::custom_list::CustomList,
+    ) -> Result<ImportUndo> {
+        let name = list.name.clone();
+        if let Some(previous) = previous {
+            rpc.update_custom_list(list)
+                .await
+                .with_context(|| format!("Failed to update custom list {name}"))?;
+            return Ok(ImportUndo::Restore(previous));
+        }
+
+        let id = rpc
+            .create_custom_list(name.clone())
+            .await
+            .with_context(|| format!("Failed to create custom list {name}"))?;
+        let mut created = mullvad_types::custom_list::CustomList::with_id(id);
+        created.name = list.name;
+        created.locations = list.locations;
+        if let Err(error) = rpc.update_custom_list(created).await {
+            Self::undo_import(rpc, vec![ImportUndo::Delete(id)]).await;
+            return Err(error).with_context(|| format!("Failed to update custom list {name}"));
+        }
+        Ok(ImportUndo::Delete(id))
+    }
+
+    /// Undo the changes made by [`Self::import_list`], the most recent first.
+    async fn undo_import(rpc: &mut MullvadProxyClient, undo: Vec<ImportUndo>) {
+        for change in undo.into_iter().rev() {
+            let result = match change {
+                ImportUndo::Delete(id) => rpc.delete_custom_list(id).await,
+                ImportUndo::Restore(list) => rpc.update_custom_list(list).await,
+            };
+            if let Err(error) = result {
+                eprintln!("Failed to undo import of custom list: {error}");
+            }
+        }
+    }
+
     fn print_custom_list(custom_list: &mullvad_types::custom_list::CustomList, cache: &RelayList) {
         println!("{}", custom_list.name);
         Self::print_custom_list_content(custom_list, cache);
@@ -192,6 +409,14 @@ impl CustomList {
     }
 }
 
+/// How to undo a change made when importing custom lists.
+enum ImportUndo {
+    /// Delete a list that was created.
+    Delete(Id),
+    /// Restore a list that was updated.
+    Restore(mullvad_types::custom_list::CustomList),
+}
+
 /// Struct used for pretty printing [`GeographicLocationConstraint`] with
 /// human-readable names for countries and cities.
 pub struct GeographicLocationConstraintFormatter<'a> {
@@ -1,9 +1,14 @@
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{RelayConstraints, RelaySettings},
 };
+use serde_json::json;
+use std::time::Duration;
+use talpid_types::net::TunnelCaptureSettings;
+
+use crate::{output, println_human};
 
 #[derive(clap::Subcommand, Debug)]
 pub enum DebugCommands {
@@ -15,6 +20,13 @@ pub enum DebugCommands {
     /// Handy commands for interacting with the app release rollout system.
     #[clap(subcommand)]
     Rollout(RolloutDebugCommands),
+    /// Print the firewall rules that the daemon expects to be active, and the rules that are
+    /// actually active.
+    #[cfg(target_os = "linux")]
+    Firewall,
+    /// Capture packets of the userspace WireGuard tunnel to a pcapng file in the log directory.
+    #[clap(subcommand)]
+    Capture(CaptureDebugCommands),
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -39,6 +51,29 @@ pub enum RolloutDebugCommands {
     Seed { value: u32 },
 }
 
+#[derive(clap::Subcommand, Debug)]
+pub enum CaptureDebugCommands {
+    /// Start capturing. Both kinds of packets are captured unless `--inner` or `--outer` is given.
+    ///
+    /// Only tunnels that use userspace WireGuard (GotaTun) are captured.
+    Start {
+        /// Capture the plaintext IP packets inside the tunnel
+        #[arg(long)]
+        inner: bool,
+        /// Capture the WireGuard packets exchanged with the entry relay, before any obfuscation
+        #[arg(long)]
+        outer: bool,
+        /// Stop once the capture file reaches this size, in MiB. At most 1024 MiB
+        #[arg(long, default_value_t = 100, value_parser = clap::value_parser!(u64).range(1..))]
+        max_size: u64,
+        /// Stop after this many seconds. At most 24 hours
+        #[arg(long, default_value_t = 600, value_parser = clap::value_parser!(u64).range(1..))]
+        duration: u64,
+    },
+    /// Stop the active capture
+    Stop,
+}
+
 impl DebugCommands {
     pub async fn handle(self) -> Result<()> {
         match self {
@@ -50,7 +85,7 @@ impl DebugCommands {
                 let mut constraints = match relay_settings {
                     RelaySettings::Normal(normal) => normal,
                     RelaySettings::CustomTunnelEndpoint(_custom) => {
-                        println!("Removing custom relay settings");
+                        println_human!("Removing custom relay settings");
                         RelayConstraints::default()
                     }
                 };
@@ -72,16 +107,46 @@ impl DebugCommands {
             DebugCommands::Relay(RelayDebugCommands::Disable { relay }) => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.disable_relay(relay.clone()).await?;
-                println!("{relay} is now marked as inactive");
+                println_human!("{relay} is now marked as inactive");
                 Ok(())
             }
             DebugCommands::Relay(RelayDebugCommands::Enable { relay }) => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.enable_relay(relay.clone()).await?;
-                println!("{relay} is now marked as active");
+                println_human!("{relay} is now marked as active");
                 Ok(())
             }
             DebugCommands::Rollout(rollout_cmd) => rollout_cmd.handle().await,
+            DebugCommands::Capture(capture_cmd) => capture_cmd.handle().await,
+            #[cfg(target_os = "linux")]
+            DebugCommands::Firewall => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let rules = rpc.get_firewall_rules().await?;
+
+                if output::is_json() {
+                    return output::print_json(&json!({
+                        "policy": rules.policy,
+                        "expected": rules.expected,
+                        "actual": rules.actual,
+                    }));
+                }
+
+                println!("Policy: {}", rules.policy.as_deref().unwrap_or("none"));
+                println!("Expected rules:");
+                for rule in &rules.expected {
+                    println!("    {rule}");
+                }
+                println!("Active rules:");
+                for rule in &rules.actual {
+                    println!("    {rule}");
+                }
+                if rules.expected == rules.actual {
+                    println!("The active rules match the expected rules");
+                } else {
+                    println!("WARNING: The active rules differ from the expected rules");
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -94,6 +159,9 @@ impl RolloutDebugCommands {
                 let Ok(threshold) = rpc.get_rollout_threshold().await else {
                     bail!("Failed to get rollout");
                 };
+                if output::is_json() {
+                    return output::print_json(&json!({ "rollout_threshold": threshold }));
+                }
                 println!("{threshold}");
                 Ok(())
             }
@@ -101,6 +169,9 @@ impl RolloutDebugCommands {
                 let Ok(threshold) = rpc.generate_new_rollout_threshold().await else {
                     bail!("Failed to get rollout");
                 };
+                if output::is_json() {
+                    return output::print_json(&json!({ "rollout_threshold": threshold }));
+                }
                 println!("{threshold}");
                 Ok(())
             }
@@ -113,3 +184,39 @@ impl RolloutDebugCommands {
         }
     }
 }
+
+impl CaptureDebugCommands {
+    pub async fn handle(self) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        match self {
+            CaptureDebugCommands::Start {
+                inner,
+                outer,
+                max_size,
+                duration,
+            } => {
+                let max_size = max_size
+                    .checked_mul(1024 * 1024)
+                    .context("Maximum capture size is too large")?;
+                let capture_all = !inner && !outer;
+                let settings = TunnelCaptureSettings {
+                    inner: inner || capture_all,
+                    outer: outer || capture_all,
+                    max_size,
+                    max_duration: Duration::from_secs(duration),
+                };
+                let path = rpc.start_tunnel_capture(settings).await?;
+                if output::is_json() {
+                    return output::print_json(&json!({ "path": path }));
+                }
+                println!("Capturing tunnel packets to {}", path.display());
+                Ok(())
+            }
+            CaptureDebugCommands::Stop => {
+                rpc.stop_tunnel_capture().await?;
+                println_human!("Stopped capturing tunnel packets");
+                Ok(())
+            }
+        }
+    }
+}
@@ -2,7 +2,12 @@ use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
+use serde_json::json;
 use std::net::IpAddr;
+#[cfg(target_os = "linux")]
+use talpid_types::net::DnsBackend;
+
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum Dns {
@@ -14,6 +19,15 @@ pub enum Dns {
         #[clap(subcommand)]
         cmd: DnsSet,
     },
+
+    /// Display or set how DNS is configured while connected
+    #[cfg(target_os = "linux")]
+    Backend {
+        /// One of auto, systemd-resolved, network-manager, resolvconf, static-file or
+        /// nftables-redirect. nftables-redirect leaves the system DNS config untouched and
+        /// redirects all DNS traffic to the tunnel DNS servers using the firewall.
+        backend: Option<DnsBackend>,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -82,12 +96,21 @@ impl Dns {
             Dns::Set {
                 cmd: DnsSet::Custom { servers },
             } => Self::set_custom(servers).await,
+            #[cfg(target_os = "linux")]
+            Dns::Backend { backend: None } => Self::get_backend().await,
+            #[cfg(target_os = "linux")]
+            Dns::Backend {
+                backend: Some(backend),
+            } => Self::set_backend(backend).await,
         }
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let options = rpc.get_settings().await?.tunnel_options.dns_options;
+        if output::is_json() {
+            return output::print_json(&options);
+        }
 
         match options.state {
             DnsState::Default => {
@@ -139,7 +162,7 @@ impl Dns {
             ..settings.tunnel_options.dns_options
         })
         .await?;
-        println!("Updated DNS settings");
+        println_human!("Updated DNS settings");
         Ok(())
     }
 
@@ -152,7 +175,26 @@ impl Dns {
             ..settings.tunnel_options.dns_options
         })
         .await?;
-        println!("Updated DNS settings");
+        println_human!("Updated DNS settings");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn get_backend() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let dns_backend = rpc.get_settings().await?.dns_backend;
+        if output::is_json() {
+            return output::print_json(&json!({ "dns_backend": dns_backend }));
+        }
+        println!("DNS backend: {dns_backend}");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_backend(backend: DnsBackend) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_dns_backend(backend).await?;
+        println_human!("Changed DNS backend to {backend}");
         Ok(())
     }
 }
@@ -0,0 +1,123 @@
+use anyhow::{Context, Result, ensure};
+use clap::{Args, Subcommand};
+use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::net::{FirewallException as Exception, TransportProtocol};
+
+use crate::{output, println_human};
+
+/// Manage outbound traffic that is allowed outside the tunnel in every tunnel state
+#[derive(Subcommand, Debug)]
+pub enum FirewallException {
+    /// List all firewall exceptions
+    List,
+
+    /// Allow traffic to a host or network outside the tunnel
+    Add(ExceptionArgs),
+
+    /// Remove a firewall exception
+    Remove(ExceptionArgs),
+
+    /// Remove all firewall exceptions
+    Clear,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ExceptionArgs {
+    /// IP address or network in CIDR notation, e.g. 192.0.2.10 or 192.0.2.0/24
+    destination: String,
+
+    /// Transport protocol of the traffic
+    #[arg(long, default_value_t = TransportProtocol::Tcp)]
+    protocol: TransportProtocol,
+
+    /// Destination port of the traffic
+    #[arg(long)]
+    port: u16,
+
+    /// Only allow the traffic to leave through this network interface
+    #[arg(long)]
+    interface: Option<String>,
+}
+
+impl TryFrom<ExceptionArgs> for Exception {
+    type Error = anyhow::Error;
+
+    fn try_from(args: ExceptionArgs) -> Result<Self> {
+        ensure!(args.port != 0, "Port must not be 0");
+        if let Some(interface) = &args.interface {
+            ensure!(
+                Exception::is_valid_interface_name(interface),
+                "Invalid interface name: {interface}"
+            );
+        }
+        Ok(Exception {
+            destination: args
+                .destination
+                .parse()
+                .with_context(|| format!("Invalid destination: {}", args.destination))?,
+            protocol: args.protocol,
+            port: args.port,
+            interface: args.interface,
+        })
+    }
+}
+
+impl FirewallException {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            FirewallException::List => Self::list().await,
+            FirewallException::Add(args) => Self::add(Exception::try_from(args)?).await,
+            FirewallException::Remove(args) => Self::remove(Exception::try_from(args)?).await,
+            FirewallException::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let exceptions = rpc.get_settings().await?.firewall_exceptions;
+        if output::is_json() {
+            return output::print_json(&exceptions);
+        }
+        if exceptions.is_empty() {
+            println!("No firewall exceptions");
+        }
+        for exception in exceptions {
+            println!("{exception}");
+        }
+        Ok(())
+    }
+
+    async fn add(exception: Exception) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut exceptions = rpc.get_settings().await?.firewall_exceptions;
+        ensure!(
+            !exceptions.contains(&exception),
+            "Firewall exception already exists: {exception}"
+        );
+        exceptions.push(exception.clone());
+        rpc.set_firewall_exceptions(exceptions).await?;
+        println_human!("Allowing {exception}");
+        Ok(())
+    }
+
+    async fn remove(exception: Exception) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut exceptions = rpc.get_settings().await?.firewall_exceptions;
+        let len = exceptions.len();
+        exceptions.retain(|existing| existing != &exception);
+        ensure!(
+            exceptions.len() != len,
+            "No such firewall exception: {exception}"
+        );
+        rpc.set_firewall_exceptions(exceptions).await?;
+        println_human!("Removed {exception}");
+        Ok(())
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_firewall_exceptions(vec![]).await?;
+        println_human!("Removed all firewall exceptions");
+        Ok(())
+    }
+}
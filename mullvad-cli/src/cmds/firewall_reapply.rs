@@ -0,0 +1,42 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
+
+use super::BooleanOption;
+use crate::{output, println_human};
+
+#[derive(Subcommand, Debug)]
+pub enum FirewallReapply {
+    /// Display the current firewall reapply setting
+    Get,
+    /// Change the firewall reapply setting
+    Set { policy: BooleanOption },
+}
+
+impl FirewallReapply {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            FirewallReapply::Get => Self::get().await,
+            FirewallReapply::Set { policy } => Self::set(policy).await,
+        }
+    }
+
+    async fn set(policy: BooleanOption) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_reapply_firewall_on_drift(*policy).await?;
+        println_human!("Changed firewall reapply setting");
+        Ok(())
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let reapply = rpc.get_settings().await?.reapply_firewall_on_drift;
+        if output::is_json() {
+            return output::print_json(&json!({ "reapply_firewall_on_drift": reapply }));
+        }
+        let state = BooleanOption::from(reapply);
+        println!("Apply firewall rules again when changed by another program: {state}");
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{Context, Result, ensure};
+use clap::{Args, Subcommand};
+use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::net::{InboundPort as Port, TransportProtocol};
+
+use crate::{output, println_human};
+
+/// Manage ports on the tunnel interface that accept incoming connections while connected
+#[derive(Subcommand, Debug)]
+pub enum InboundPort {
+    /// List all inbound ports
+    List,
+
+    /// Accept incoming connections to a port on the tunnel interface. While any inbound ports are
+    /// set, other incoming connections on the tunnel interface are blocked
+    Add(PortArgs),
+
+    /// Remove an inbound port
+    Remove(PortArgs),
+
+    /// Remove all inbound ports
+    Clear,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct PortArgs {
+    /// Local port that incoming connections are sent to
+    port: u16,
+
+    /// Transport protocol of the traffic
+    #[arg(long, default_value_t = TransportProtocol::Tcp)]
+    protocol: TransportProtocol,
+
+    /// Only accept traffic from this IP address or network in CIDR notation
+    #[arg(long)]
+    source: Option<String>,
+}
+
+impl TryFrom<PortArgs> for Port {
+    type Error = anyhow::Error;
+
+    fn try_from(args: PortArgs) -> Result<Self> {
+        ensure!(args.port != 0, "Port must not be 0");
+        let source = args
+            .source
+            .map(|source| {
+                source
+                    .parse()
+                    .with_context(|| format!("Invalid source: {source}"))
+            })
+            .transpose()?;
+        Ok(Port {
+            protocol: args.protocol,
+            port: args.port,
+            source,
+        })
+    }
+}
+
+impl InboundPort {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            InboundPort::List => Self::list().await,
+            InboundPort::Add(args) => Self::add(Port::try_from(args)?).await,
+            InboundPort::Remove(args) => Self::remove(Port::try_from(args)?).await,
+            InboundPort::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let inbound_ports = rpc.get_settings().await?.inbound_ports;
+        if output::is_json() {
+            return output::print_json(&inbound_ports);
+        }
+        if inbound_ports.is_empty() {
+            println!("No inbound ports");
+        }
+        for inbound_port in inbound_ports {
+            println!("{inbound_port}");
+        }
+        Ok(())
+    }
+
+    async fn add(inbound_port: Port) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut inbound_ports = rpc.get_settings().await?.inbound_ports;
+        ensure!(
+            !inbound_ports.contains(&inbound_port),
+            "Inbound port already exists: {inbound_port}"
+        );
+        inbound_ports.push(inbound_port.clone());
+        rpc.set_inbound_ports(inbound_ports).await?;
+        println_human!("Accepting incoming connections to {inbound_port}");
+        Ok(())
+    }
+
+    async fn remove(inbound_port: Port) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut inbound_ports = rpc.get_settings().await?.inbound_ports;
+        let len = inbound_ports.len();
+        inbound_ports.retain(|existing| existing != &inbound_port);
+        ensure!(
+            inbound_ports.len() != len,
+            "No such inbound port: {inbound_port}"
+        );
+        rpc.set_inbound_ports(inbound_ports).await?;
+        println_human!("Removed {inbound_port}");
+        Ok(())
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_inbound_ports(vec![]).await?;
+        println_human!("Removed all inbound ports");
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
 
 use super::BooleanOption;
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum Lan {
@@ -27,14 +29,17 @@ impl Lan {
     async fn set(policy: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_allow_lan(*policy).await?;
-        println!("Changed local network sharing setting");
+        println_human!("Changed local network sharing setting");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let allow_lan =
-            BooleanOption::with_labels(rpc.get_settings().await?.allow_lan, "allow", "block");
+        let allow_lan = rpc.get_settings().await?.allow_lan;
+        if output::is_json() {
+            return output::print_json(&json!({ "allow_lan": allow_lan }));
+        }
+        let allow_lan = BooleanOption::with_labels(allow_lan, "allow", "block");
         println!("Local network sharing setting: {allow_lan}");
         Ok(())
     }
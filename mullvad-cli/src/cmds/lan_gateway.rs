@@ -0,0 +1,97 @@
+use anyhow::{Context, Result, ensure};
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::net::{FirewallException, LanGateway as Gateway};
+
+use crate::{output, println_human};
+
+/// Controls whether the kernel forwards IPv4 packets between interfaces.
+const IPV4_FORWARD_SYSCTL: &str = "/proc/sys/net/ipv4/ip_forward";
+
+/// Manage forwarding of traffic from other devices on the local network through the tunnel
+#[derive(Subcommand, Debug)]
+pub enum LanGateway {
+    /// Display the current LAN gateway setting
+    Get,
+
+    /// Forward traffic from devices on the given interfaces through the tunnel. Forwarded traffic
+    /// is blocked whenever the tunnel is not connected
+    Set {
+        /// Local network interface that the forwarded devices are connected to
+        #[arg(long = "interface", required = true)]
+        interfaces: Vec<String>,
+
+        /// Only forward traffic from this network in CIDR notation
+        #[arg(long = "subnet")]
+        subnets: Vec<String>,
+    },
+
+    /// Stop forwarding traffic from other devices
+    Off,
+}
+
+impl LanGateway {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            LanGateway::Get => Self::get().await,
+            LanGateway::Set {
+                interfaces,
+                subnets,
+            } => Self::set(interfaces, subnets).await,
+            LanGateway::Off => Self::off().await,
+        }
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let lan_gateway = rpc.get_settings().await?.lan_gateway;
+        if output::is_json() {
+            return output::print_json(&lan_gateway);
+        }
+        match lan_gateway {
+            Some(lan_gateway) => println!("Forwarding traffic from {lan_gateway}"),
+            None => println!("LAN gateway: off"),
+        }
+        Ok(())
+    }
+
+    async fn set(interfaces: Vec<String>, subnets: Vec<String>) -> Result<()> {
+        for interface in &interfaces {
+            ensure!(
+                FirewallException::is_valid_interface_name(interface),
+                "Invalid interface: {interface}"
+            );
+        }
+        let subnets = subnets
+            .iter()
+            .map(|subnet| {
+                subnet
+                    .parse()
+                    .with_context(|| format!("Invalid subnet: {subnet}"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let lan_gateway = Gateway {
+            interfaces,
+            subnets,
+        };
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_lan_gateway(Some(lan_gateway.clone())).await?;
+        println_human!("Forwarding traffic from {lan_gateway}");
+
+        let ip_forward = std::fs::read_to_string(IPV4_FORWARD_SYSCTL).unwrap_or_default();
+        if ip_forward.trim() != "1" {
+            eprintln!(
+                "Warning: IP forwarding is disabled. Enable it with `sysctl -w net.ipv4.ip_forward=1`"
+            );
+        }
+        Ok(())
+    }
+
+    async fn off() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_lan_gateway(None).await?;
+        println_human!("Stopped forwarding traffic from other devices");
+        Ok(())
+    }
+}
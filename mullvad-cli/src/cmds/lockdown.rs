@@ -1,8 +1,10 @@
 use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
 
 use super::BooleanOption;
+use crate::{output, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum LockdownMode {
@@ -23,13 +25,17 @@ impl LockdownMode {
     async fn set(policy: BooleanOption) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         rpc.set_lockdown_mode(*policy).await?;
-        println!("Changed lockdown mode setting");
+        println_human!("Changed lockdown mode setting");
         Ok(())
     }
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let state = BooleanOption::from(rpc.get_settings().await?.lockdown_mode);
+        let lockdown_mode = rpc.get_settings().await?.lockdown_mode;
+        if output::is_json() {
+            return output::print_json(&json!({ "lockdown_mode": lockdown_mode }));
+        }
+        let state = BooleanOption::from(lockdown_mode);
         println!("Block traffic when the VPN is disconnected: {state}");
         Ok(())
     }
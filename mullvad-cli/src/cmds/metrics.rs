@@ -0,0 +1,78 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::metrics::MetricsEndpoint;
+use serde_json::json;
+
+use crate::{output, println_human};
+
+#[derive(Subcommand, Debug)]
+pub enum Metrics {
+    /// Display where metrics are served, if anywhere
+    Get,
+    /// Serve metrics in the OpenMetrics text format
+    Set {
+        #[clap(subcommand)]
+        endpoint: SetEndpoint,
+    },
+    /// Stop serving and collecting metrics
+    Off,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum SetEndpoint {
+    /// Serve metrics over HTTP on a TCP port on the loopback interface
+    Tcp { port: u16 },
+    /// Serve metrics over HTTP on a Unix domain socket
+    ///
+    /// The socket is created in the `metrics` directory in the cache directory of the daemon, e.g.
+    /// `/var/cache/mullvad-vpn/metrics` on Linux.
+    #[cfg(unix)]
+    UnixSocket {
+        /// File name of the socket
+        name: String,
+    },
+}
+
+impl From<SetEndpoint> for MetricsEndpoint {
+    fn from(endpoint: SetEndpoint) -> Self {
+        match endpoint {
+            SetEndpoint::Tcp { port } => MetricsEndpoint::Tcp { port },
+            #[cfg(unix)]
+            SetEndpoint::UnixSocket { name } => MetricsEndpoint::UnixSocket { name },
+        }
+    }
+}
+
+impl Metrics {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Metrics::Get => Self::get().await,
+            Metrics::Set { endpoint } => Self::set(Some(MetricsEndpoint::from(endpoint))).await,
+            Metrics::Off => Self::set(None).await,
+        }
+    }
+
+    async fn set(endpoint: Option<MetricsEndpoint>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_metrics_endpoint(endpoint.as_ref()).await?;
+        match endpoint {
+            Some(endpoint) => println_human!("Serving metrics at {endpoint}"),
+            None => println_human!("Stopped serving metrics"),
+        }
+        Ok(())
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let metrics_endpoint = rpc.get_settings().await?.metrics_endpoint;
+        if output::is_json() {
+            return output::print_json(&json!({ "metrics_endpoint": metrics_endpoint }));
+        }
+        match metrics_endpoint {
+            Some(endpoint) => println!("Serving metrics at {endpoint}"),
+            None => println!("Metrics are off"),
+        }
+        Ok(())
+    }
+}
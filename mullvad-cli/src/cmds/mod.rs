@@ -9,17 +9,31 @@ pub mod beta_program;
 pub mod custom_list;
 pub mod debug;
 pub mod dns;
+#[cfg(target_os = "linux")]
+pub mod firewall_exception;
+#[cfg(target_os = "linux")]
+pub mod firewall_reapply;
+#[cfg(target_os = "linux")]
+pub mod inbound_port;
 pub mod lan;
+#[cfg(target_os = "linux")]
+pub mod lan_gateway;
 pub mod lockdown;
 pub mod log;
+pub mod metrics;
 pub mod patch;
 pub mod proxies;
 pub mod relay;
 pub mod relay_constraints;
 pub mod reset;
+pub mod settings_profile;
 pub mod split_tunnel;
 pub mod status;
+#[cfg(target_os = "linux")]
+pub mod trusted_interface;
 pub mod tunnel;
+#[cfg(target_os = "linux")]
+pub mod tunnel_namespace;
 pub mod tunnel_state;
 pub mod version;
 
@@ -90,7 +104,7 @@ async fn receive_confirmation(msg: &'static str, default: bool) -> bool {
         false => "[y/N]",
     };
 
-    println!("{msg} {helper_str}");
+    crate::println_human!("{msg} {helper_str}");
 
     tokio::task::spawn_blocking(move || {
         loop {
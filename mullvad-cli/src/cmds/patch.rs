@@ -1,4 +1,6 @@
-use anyhow::{Context, Result};
+use super::settings_profile::format_keys;
+use crate::{output, println_human};
+use anyhow::{Context, Result, bail};
 use mullvad_management_interface::MullvadProxyClient;
 use std::{
     fs::File,
@@ -6,11 +8,11 @@ use std::{
 };
 
 /// Read a settings patch and send it to the daemon for validation and
-/// application.
+/// application. If `dry_run` is set, the patch is only validated.
 ///
 /// * If `source` is "-", read the patch from standard input
 /// * Otherwise, interpret `source` as a filepath and read from the provided file
-pub async fn import(source: String) -> Result<()> {
+pub async fn import(source: String, dry_run: bool) -> Result<()> {
     let json_blob = tokio::task::spawn_blocking(move || match source.as_str() {
         "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
         _ => read_to_string(File::open(&source)?)
@@ -20,11 +22,39 @@ pub async fn import(source: String) -> Result<()> {
     .unwrap()?;
 
     let mut rpc = MullvadProxyClient::new().await?;
+    if dry_run {
+        return preview(&mut rpc, json_blob).await;
+    }
     rpc.apply_json_settings(json_blob)
         .await
         .context("Error applying patch")?;
 
-    println!("Settings applied");
+    println_human!("Settings applied");
+
+    Ok(())
+}
+
+async fn preview(rpc: &mut MullvadProxyClient, json_blob: String) -> Result<()> {
+    let preview = rpc
+        .preview_json_settings(json_blob)
+        .await
+        .context("Error validating patch")?;
+
+    if output::is_json() {
+        return output::print_json(&preview);
+    }
+
+    if preview.changed.is_empty() {
+        println!("The patch would not change any settings");
+    } else {
+        println!("The patch would change: {}", format_keys(preview.changed));
+    }
+    if !preview.errors.is_empty() {
+        for error in &preview.errors {
+            println!("Error: {error}");
+        }
+        bail!("The patch would be rejected");
+    }
 
     Ok(())
 }
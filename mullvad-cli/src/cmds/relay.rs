@@ -7,8 +7,8 @@ use mullvad_types::{
     constraints::{Constraint, Match},
     location::CountryCode,
     relay_constraints::{
-        GeographicLocationConstraint, LocationConstraint, LocationConstraintFormatter, Multihop,
-        Ownership, Provider, Providers, RelayConstraints, RelayOverride, RelaySettings,
+        CustomEntry, GeographicLocationConstraint, LocationConstraint, LocationConstraintFormatter,
+        Multihop, Ownership, Provider, Providers, RelayConstraints, RelayOverride, RelaySettings,
         WireguardConstraints, allowed_ip::AllowedIps,
     },
     relay_list::RelayListCountry,
@@ -17,11 +17,12 @@ use std::{
     collections::HashMap,
     io::BufRead,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
 };
 use talpid_types::net::{IpVersion, wireguard};
 
 use super::relay_constraints::LocationArgs;
-use crate::{cmds::receive_confirmation, print_option};
+use crate::{cmds::receive_confirmation, output, print_option, println_human};
 
 #[derive(Subcommand, Debug)]
 pub enum Relay {
@@ -38,6 +39,19 @@ pub enum Relay {
     /// Update the relay list
     Update,
 
+    /// Replace the relay list with one read from a file, for devices that cannot reach the API.
+    /// The file must be in the format produced by the `relay_list` tool of `mullvad-api`.
+    ///
+    /// The relay list is not signed, so neither its authenticity nor its age can be verified.
+    /// Only import relay lists from a trusted source. Requires the admin role
+    ImportUnverified {
+        /// Path to the relay list
+        file: PathBuf,
+    },
+
+    /// Display where the relay list in use was obtained from
+    Source,
+
     /// Override options for individual relays/servers
     #[clap(subcommand)]
     Override(OverrideCommands),
@@ -151,6 +165,16 @@ pub enum EntryArgs {
     Location(LocationArgs),
     /// Name of custom list to use to pick entry endpoint.
     CustomList { custom_list_name: String },
+    /// Use a self-hosted WireGuard server as the entry instead of a relay. The exit relay is
+    /// reached through this server, which must forward traffic from the device's tunnel
+    /// addresses. Setting an entry location removes the custom entry.
+    CustomServer {
+        /// IP address and port of the server
+        endpoint: SocketAddr,
+        /// Base64 encoded public key of the server
+        #[arg(value_parser = wireguard::PublicKey::from_base64)]
+        public_key: wireguard::PublicKey,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -203,6 +227,8 @@ impl Relay {
             Relay::Get => Self::get().await,
             Relay::List => Self::list().await,
             Relay::Update => Self::update().await,
+            Relay::ImportUnverified { file } => Self::import(file).await,
+            Relay::Source => Self::source().await,
             Relay::Set(subcmd) => Self::set(subcmd).await,
             Relay::Override(subcmd) => Self::r#override(subcmd).await,
         }
@@ -213,6 +239,10 @@ impl Relay {
         let settings = rpc.get_settings().await?;
         let relay_settings = settings.relay_settings;
 
+        if output::is_json() {
+            return output::print_json(&relay_settings);
+        }
+
         match relay_settings {
             RelaySettings::CustomTunnelEndpoint(endpoint) => {
                 println!("Custom endpoint: {endpoint}")
@@ -251,6 +281,9 @@ impl Relay {
                             custom_lists: &settings.custom_lists
                         }),
                 );
+                if let Some(custom_entry) = &constraints.wireguard_constraints.custom_entry {
+                    print_option!("Custom entry", custom_entry);
+                }
             }
         }
 
@@ -260,14 +293,23 @@ impl Relay {
     async fn list() -> Result<()> {
         let mut countries = get_active_relays().await?;
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-        for mut country in countries {
+        for country in &mut countries {
             country
                 .cities
                 .sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-            println!("{} ({})", country.name, country.code);
-            for mut city in country.cities {
+            for city in &mut country.cities {
                 city.relays
                     .sort_by(|r1, r2| natord::compare_ignore_case(&r1.hostname, &r2.hostname));
+            }
+        }
+
+        if output::is_json() {
+            return output::print_json(&countries);
+        }
+
+        for country in countries {
+            println!("{} ({})", country.name, country.code);
+            for city in country.cities {
                 println!(
                     "\t{} ({}) @ {:.5}°N, {:.5}°W",
                     city.name, city.code, city.latitude, city.longitude
@@ -300,7 +342,39 @@ impl Relay {
             .await?
             .update_relay_locations()
             .await?;
-        println!("Updating relay list in the background...");
+        println_human!("Updating relay list in the background...");
+        Ok(())
+    }
+
+    async fn import(file: PathBuf) -> Result<()> {
+        let path = std::path::absolute(&file)
+            .with_context(|| format!("Invalid path: {}", file.display()))?;
+        let relay_list = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read from path: {}", path.display()))?;
+
+        let info = MullvadProxyClient::new()
+            .await?
+            .import_relay_list(relay_list, path.display().to_string())
+            .await
+            .context("Error importing relay list")?;
+        if output::is_json() {
+            return output::print_json(&info);
+        }
+        println!("Using relay list {}", info.source);
+        Ok(())
+    }
+
+    async fn source() -> Result<()> {
+        let info = MullvadProxyClient::new()
+            .await?
+            .get_relay_list_info()
+            .await?;
+        if output::is_json() {
+            return output::print_json(&info);
+        }
+        println!("Relay list {}", info.source);
+        print_option!("ETag", info.etag.as_deref().unwrap_or("none"));
         Ok(())
     }
 
@@ -312,14 +386,14 @@ impl Relay {
         let mut constraints = match relay_settings {
             RelaySettings::Normal(normal) => normal,
             RelaySettings::CustomTunnelEndpoint(_custom) => {
-                println!("Removing custom relay settings");
+                println_human!("Removing custom relay settings");
                 RelayConstraints::default()
             }
         };
         update_fn(&mut constraints);
         rpc.set_relay_settings(RelaySettings::Normal(constraints))
             .await?;
-        println!("Relay constraints updated");
+        println_human!("Relay constraints updated");
         Ok(())
     }
 
@@ -367,6 +441,7 @@ impl Relay {
 
                         wireguard_constraints.entry_location =
                             location_constraint.map(LocationConstraint::from);
+                        wireguard_constraints.custom_entry = None;
                     }
                     EntryArgs::CustomList { custom_list_name } => {
                         let list_id =
@@ -375,6 +450,16 @@ impl Relay {
                                 .id();
                         wireguard_constraints.entry_location =
                             Constraint::Only(LocationConstraint::CustomList { list_id });
+                        wireguard_constraints.custom_entry = None;
+                    }
+                    EntryArgs::CustomServer {
+                        endpoint,
+                        public_key,
+                    } => {
+                        wireguard_constraints.custom_entry = Some(CustomEntry {
+                            endpoint,
+                            public_key,
+                        });
                     }
                 }
 
@@ -403,7 +488,7 @@ impl Relay {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(custom_endpoint))
                     .await?;
-                println!("Relay constraints updated");
+                println_human!("Relay constraints updated");
                 Ok(())
             }
         }
@@ -417,7 +502,7 @@ impl Relay {
         ipv4_gateway: Ipv4Addr,
         ipv6_gateway: Option<Ipv6Addr>,
     ) -> Result<CustomTunnelEndpoint> {
-        println!("Reading private key from standard input");
+        println_human!("Reading private key from standard input");
 
         let private_key_str = tokio::task::spawn_blocking(|| {
             let mut private_key_str = String::new();
@@ -454,6 +539,9 @@ impl Relay {
                 // NOTE: Ignored in gRPC
                 #[cfg(target_os = "linux")]
                 fwmark: None,
+                #[cfg(target_os = "linux")]
+                interface_name: None,
+                custom_entry: false,
             },
         })
     }
@@ -511,7 +599,7 @@ impl Relay {
         match rpc.get_settings().await?.relay_settings {
             RelaySettings::Normal(settings) => Ok(settings.wireguard_constraints),
             RelaySettings::CustomTunnelEndpoint(_settings) => {
-                println!("Clearing custom tunnel constraints");
+                println_human!("Clearing custom tunnel constraints");
                 Ok(WireguardConstraints::default())
             }
         }
@@ -543,7 +631,7 @@ impl Relay {
         update_fn(&mut element);
 
         rpc.set_relay_override(element).await?;
-        println!("Updated override options for {hostname}");
+        println_human!("Updated override options for {hostname}");
         Ok(())
     }
 
@@ -553,6 +641,10 @@ impl Relay {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?;
 
+                if output::is_json() {
+                    return output::print_json(&settings.relay_overrides);
+                }
+
                 let mut overrides = HashMap::new();
                 for relay_override in settings.relay_overrides {
                     overrides.insert(relay_override.hostname.clone(), relay_override);
@@ -670,7 +762,7 @@ impl Relay {
                 {
                     let mut rpc = MullvadProxyClient::new().await?;
                     rpc.clear_all_relay_overrides().await?;
-                    println!("All overrides unset");
+                    println_human!("All overrides unset");
                 }
             }
         }
@@ -1,11 +1,12 @@
 use super::receive_confirmation;
+use crate::println_human;
 use anyhow::Result;
 use clap::{ValueEnum, builder::PossibleValue};
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::settings::SettingsKeyList;
 
 #[derive(Clone, Debug)]
-pub struct SettingsKey(mullvad_types::settings::SettingsKey);
+pub struct SettingsKey(pub mullvad_types::settings::SettingsKey);
 
 impl ValueEnum for SettingsKey {
     fn value_variants<'a>() -> &'a [Self] {
@@ -19,7 +20,24 @@ impl ValueEnum for SettingsKey {
             Self(AllowLan),
             #[cfg(not(target_os = "android"))]
             Self(LockdownMode),
+            #[cfg(target_os = "linux")]
+            Self(TunnelNamespace),
+            #[cfg(target_os = "linux")]
+            Self(FirewallExceptions),
+            #[cfg(target_os = "linux")]
+            Self(InboundPorts),
+            #[cfg(target_os = "linux")]
+            Self(LanGateway),
+            #[cfg(target_os = "linux")]
+            Self(TrustedInterfaces),
+            #[cfg(target_os = "linux")]
+            Self(DnsBackend),
+            #[cfg(target_os = "linux")]
+            Self(ReapplyFirewallOnDrift),
             Self(AutoConnect),
+            Self(ExpiryWarningThresholds),
+            #[cfg(not(target_os = "android"))]
+            Self(MetricsEndpoint),
             Self(TunnelOptions),
             Self(RelayOverrides),
             Self(ShowBetaReleases),
@@ -49,7 +67,36 @@ impl ValueEnum for SettingsKey {
             mullvad_types::settings::SettingsKey::LockdownMode => {
                 PossibleValue::new("lockdown-mode")
             }
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::TunnelNamespace => {
+                PossibleValue::new("tunnel-namespace")
+            }
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::FirewallExceptions => {
+                PossibleValue::new("firewall-exceptions")
+            }
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::InboundPorts => {
+                PossibleValue::new("inbound-ports")
+            }
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::LanGateway => PossibleValue::new("lan-gateway"),
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::TrustedInterfaces => {
+                PossibleValue::new("trusted-interfaces")
+            }
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::DnsBackend => PossibleValue::new("dns-backend"),
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::ReapplyFirewallOnDrift => {
+                PossibleValue::new("firewall-reapply")
+            }
             mullvad_types::settings::SettingsKey::AutoConnect => PossibleValue::new("auto-connect"),
+            mullvad_types::settings::SettingsKey::ExpiryWarningThresholds => {
+                PossibleValue::new("expiry-warnings")
+            }
+            #[cfg(not(target_os = "android"))]
+            mullvad_types::settings::SettingsKey::MetricsEndpoint => PossibleValue::new("metrics"),
             mullvad_types::settings::SettingsKey::TunnelOptions => {
                 PossibleValue::new("tunnel-options")
             }
@@ -73,7 +120,7 @@ pub async fn handle_factory_reset(assume_yes: bool) -> Result<()> {
     let mut rpc = MullvadProxyClient::new().await?;
     rpc.factory_reset().await?;
     #[cfg(target_os = "linux")]
-    println!("If you're running systemd, to remove all logs, you must use journalctl");
+    println_human!("If you're running systemd, to remove all logs, you must use journalctl");
     Ok(())
 }
 
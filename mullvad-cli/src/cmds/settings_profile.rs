@@ -0,0 +1,120 @@
+use anyhow::Result;
+use clap::{Subcommand, ValueEnum};
+use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
+
+use super::reset::SettingsKey;
+use crate::{output, println_human};
+
+#[derive(Subcommand, Debug)]
+pub enum SettingsProfile {
+    /// List all settings profiles
+    List,
+
+    /// Save the current values of some settings as a new profile
+    Create {
+        /// A name for the new profile
+        name: String,
+
+        /// The settings to include in the profile
+        #[arg(required = true, num_args = 1..)]
+        settings: Vec<SettingsKey>,
+    },
+
+    /// Delete a settings profile
+    Delete {
+        /// A settings profile
+        name: String,
+    },
+
+    /// Show which settings would change if a profile was activated
+    Diff {
+        /// A settings profile
+        name: String,
+    },
+
+    /// Apply all settings in a profile at once. The tunnel is reconnected at most once
+    Activate {
+        /// A settings profile
+        name: String,
+    },
+}
+
+impl SettingsProfile {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            SettingsProfile::List => Self::list().await,
+            SettingsProfile::Create { name, settings } => Self::create(name, settings).await,
+            SettingsProfile::Delete { name } => Self::delete(name).await,
+            SettingsProfile::Diff { name } => Self::diff(name).await,
+            SettingsProfile::Activate { name } => Self::activate(name).await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let profiles = rpc.list_settings_profiles().await?;
+        if output::is_json() {
+            return output::print_json(&profiles);
+        }
+        let active = rpc.get_settings().await?.active_settings_profile;
+        for profile in profiles {
+            let marker = if active.as_ref() == Some(&profile.name) {
+                " (active)"
+            } else {
+                ""
+            };
+            println!("{}{marker}", profile.name);
+            println!("\t{}", format_keys(profile.keys));
+        }
+        Ok(())
+    }
+
+    async fn create(name: String, settings: Vec<SettingsKey>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let keys = settings.into_iter().map(|key| key.0).collect();
+        rpc.create_settings_profile(name.clone(), keys).await?;
+        println_human!("Created settings profile \"{name}\"");
+        Ok(())
+    }
+
+    async fn delete(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.delete_settings_profile(name.clone()).await?;
+        println_human!("Deleted settings profile \"{name}\"");
+        Ok(())
+    }
+
+    async fn diff(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let changed = rpc.diff_settings_profile(name.clone()).await?;
+        if output::is_json() {
+            return output::print_json(&json!({ "changed": changed }));
+        }
+        if changed.is_empty() {
+            println!("Activating \"{name}\" would not change any settings");
+        } else {
+            println!(
+                "Activating \"{name}\" would change: {}",
+                format_keys(changed)
+            );
+        }
+        Ok(())
+    }
+
+    async fn activate(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.activate_settings_profile(name.clone()).await?;
+        println_human!("Activated settings profile \"{name}\"");
+        Ok(())
+    }
+}
+
+/// Format settings keys using the names accepted by the CLI.
+pub fn format_keys(keys: Vec<mullvad_types::settings::SettingsKey>) -> String {
+    keys.into_iter()
+        .filter_map(|key| SettingsKey(key).to_possible_value())
+        .map(|value| value.get_name().to_owned())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
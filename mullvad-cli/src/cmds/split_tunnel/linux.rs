@@ -2,6 +2,8 @@ use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
 
+use crate::{output, println_human};
+
 /// Manage split tunneling. To launch applications outside the tunnel, use the program
 /// 'mullvad-exclude' instead of this command
 #[derive(Subcommand, Debug)]
@@ -25,6 +27,10 @@ impl SplitTunnel {
                     .get_split_tunnel_processes()
                     .await?;
 
+                if output::is_json() {
+                    return output::print_json(&pids);
+                }
+
                 println!("Excluded PIDs:");
                 for pid in &pids {
                     println!("{pid}");
@@ -37,7 +43,7 @@ impl SplitTunnel {
                     .await?
                     .add_split_tunnel_process(pid)
                     .await?;
-                println!("Excluding process");
+                println_human!("Excluding process");
                 Ok(())
             }
             SplitTunnel::Delete { pid } => {
@@ -45,7 +51,7 @@ impl SplitTunnel {
                     .await?
                     .remove_split_tunnel_process(pid)
                     .await?;
-                println!("Stopped excluding process");
+                println_human!("Stopped excluding process");
                 Ok(())
             }
             SplitTunnel::Clear => {
@@ -53,7 +59,7 @@ impl SplitTunnel {
                     .await?
                     .clear_split_tunnel_processes()
                     .await?;
-                println!("Stopped excluding all processes");
+                println_human!("Stopped excluding all processes");
                 Ok(())
             }
         }
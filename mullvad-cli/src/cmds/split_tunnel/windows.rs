@@ -6,8 +6,10 @@ use std::{
 
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
 
 use super::super::BooleanOption;
+use crate::{output, println_human};
 
 /// Set options for applications to exclude from the tunnel.
 #[derive(Subcommand, Debug)]
@@ -42,6 +44,23 @@ impl SplitTunnel {
             SplitTunnel::Get { list_processes } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 let settings = rpc.get_settings().await?.split_tunnel;
+                if output::is_json() {
+                    let mut split_tunnel = json!({ "split_tunnel": settings });
+                    if list_processes {
+                        let processes = rpc.get_excluded_processes().await?;
+                        split_tunnel["excluded_processes"] = processes
+                            .iter()
+                            .map(|process| {
+                                json!({
+                                    "pid": process.pid,
+                                    "image": process.image.to_string_lossy(),
+                                    "inherited": process.inherited,
+                                })
+                            })
+                            .collect();
+                    }
+                    return output::print_json(&split_tunnel);
+                }
 
                 let enable_exclusions = BooleanOption::from(settings.enable_exclusions);
 
@@ -72,7 +91,7 @@ impl SplitTunnel {
             SplitTunnel::Set { policy } => {
                 let mut rpc = MullvadProxyClient::new().await?;
                 rpc.set_split_tunnel_state(*policy).await?;
-                println!("Split tunnel policy: {policy}");
+                println_human!("Split tunnel policy: {policy}");
                 Ok(())
             }
             SplitTunnel::App(subcmd) => Self::app(subcmd).await,
@@ -86,7 +105,7 @@ impl SplitTunnel {
                     .await?
                     .add_split_tunnel_app(path)
                     .await?;
-                println!("Added path to excluded apps list");
+                println_human!("Added path to excluded apps list");
                 Ok(())
             }
             App::Remove { path } => {
@@ -94,7 +113,7 @@ impl SplitTunnel {
                     .await?
                     .remove_split_tunnel_app(path)
                     .await?;
-                println!("Stopped excluding app from tunnel");
+                println_human!("Stopped excluding app from tunnel");
                 Ok(())
             }
             App::Clear => {
@@ -102,7 +121,7 @@ impl SplitTunnel {
                     .await?
                     .clear_split_tunnel_apps()
                     .await?;
-                println!("Stopped excluding all apps");
+                println_human!("Stopped excluding all apps");
                 Ok(())
             }
         }
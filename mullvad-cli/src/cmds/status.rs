@@ -1,4 +1,4 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::{Args, Subcommand};
 use futures::StreamExt;
 use mullvad_management_interface::{MullvadProxyClient, client::DaemonEvent};
@@ -6,7 +6,8 @@ use mullvad_types::{device::DeviceState, states::TunnelState};
 use serde::Serialize;
 use std::fmt::Debug;
 
-use crate::format;
+use super::account::format_hours;
+use crate::{format, output, println_human};
 
 #[derive(Subcommand, Debug, PartialEq)]
 pub enum Status {
@@ -17,16 +18,12 @@ pub enum Status {
 #[derive(Args, Debug)]
 pub struct StatusArgs {
     /// Enable verbose output
-    #[arg(long, short = 'v')]
+    #[arg(long, short = 'v', conflicts_with = "json")]
     verbose: bool,
 
     /// Enable debug output
     #[arg(long, short = 'd', conflicts_with_all = ["verbose", "json"])]
     debug: bool,
-
-    /// Format output as JSON
-    #[arg(long, short = 'j', conflicts_with_all = ["verbose", "debug"])]
-    json: bool,
 }
 
 impl Status {
@@ -74,6 +71,38 @@ impl Status {
                     };
                     print_debug_or_json(&args, "Leak detected", &leak)?;
                 }
+                DaemonEvent::FirewallDrift(drift) => {
+                    #[derive(Debug, Serialize)]
+                    struct FirewallDrift {
+                        expected: Vec<String>,
+                        actual: Vec<String>,
+                        reapplied: bool,
+                    }
+                    let drift = FirewallDrift {
+                        expected: drift.rules.expected,
+                        actual: drift.rules.actual,
+                        reapplied: drift.reapplied,
+                    };
+                    if !print_debug_or_json(&args, "Firewall drift detected", &drift)? {
+                        println!("Firewall rules were changed by another program");
+                        if drift.reapplied {
+                            println!("The firewall rules have been applied again");
+                        }
+                    }
+                }
+                DaemonEvent::AccountExpiryWarning(warning) => {
+                    if !print_debug_or_json(&args, "Account expiry warning", &warning)? {
+                        let expiry = warning.expiry.with_timezone(&chrono::Local);
+                        if warning.is_expired() {
+                            println!("The account ran out of time at {expiry}");
+                        } else {
+                            println!(
+                                "The account runs out of time in less than {} ({expiry})",
+                                format_hours(warning.threshold_hours)
+                            );
+                        }
+                    }
+                }
             }
         }
         Ok(())
@@ -102,9 +131,9 @@ fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
         TunnelState::Connecting { .. } | TunnelState::Connected { .. } | TunnelState::Error(_) => {
             match device {
                 DeviceState::LoggedOut => {
-                    println!("Warning: You are not logged in to an account.")
+                    println_human!("Warning: You are not logged in to an account.")
                 }
-                DeviceState::Revoked => println!("Warning: This device has been revoked."),
+                DeviceState::Revoked => println_human!("Warning: This device has been revoked."),
                 DeviceState::LoggedIn(_) => (),
             }
         }
@@ -115,7 +144,7 @@ fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
 /// Print the given value as debug or JSON output based on the provided arguments.
 ///
 /// Returns `true` if the value was printed. Returns `false` otherwise, i.e. if
-/// both `args.debug` and `args.json` are `false`.
+/// `args.debug` is `false` and the output is not formatted as JSON.
 fn print_debug_or_json<T: Debug + Serialize>(
     args: &StatusArgs,
     debug_message: &str,
@@ -124,9 +153,8 @@ fn print_debug_or_json<T: Debug + Serialize>(
     if args.debug {
         println!("{debug_message}: {t:#?}");
         Ok(true)
-    } else if args.json {
-        let json = serde_json::to_string(&t).context("Failed to format output as JSON")?;
-        println!("{json}");
+    } else if output::is_json() {
+        output::print_json(t)?;
         Ok(true)
     } else {
         Ok(false)
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result, ensure};
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::net::{DnsForwarding, FirewallException, TrustedInterface as Interface};
+
+use crate::{output, println_human};
+
+/// Manage interfaces of other VPN or mesh network clients, such as Tailscale or ZeroTier, that
+/// are used alongside the tunnel
+#[derive(Subcommand, Debug)]
+pub enum TrustedInterface {
+    /// List all trusted interfaces
+    List,
+
+    /// Allow traffic on an interface in every tunnel state, and let its routes take precedence
+    /// over the tunnel
+    Add {
+        /// Name of the network interface, such as tailscale0
+        name: String,
+
+        /// DNS resolver that is reached through the interface, such as 100.100.100.100
+        #[arg(long, requires = "dns_domains")]
+        dns_resolver: Option<String>,
+
+        /// Resolve this domain and its subdomains using the DNS resolver of the interface
+        #[arg(long = "dns-domain", requires = "dns_resolver")]
+        dns_domains: Vec<String>,
+    },
+
+    /// Remove a trusted interface
+    Remove {
+        /// Name of the network interface
+        name: String,
+    },
+
+    /// Remove all trusted interfaces
+    Clear,
+}
+
+impl TrustedInterface {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            TrustedInterface::List => Self::list().await,
+            TrustedInterface::Add {
+                name,
+                dns_resolver,
+                dns_domains,
+            } => Self::add(name, dns_resolver, dns_domains).await,
+            TrustedInterface::Remove { name } => Self::remove(name).await,
+            TrustedInterface::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let trusted_interfaces = rpc.get_settings().await?.trusted_interfaces;
+        if output::is_json() {
+            return output::print_json(&trusted_interfaces);
+        }
+        if trusted_interfaces.is_empty() {
+            println!("No trusted interfaces");
+        }
+        for trusted_interface in trusted_interfaces {
+            println!("{trusted_interface}");
+        }
+        Ok(())
+    }
+
+    async fn add(
+        name: String,
+        dns_resolver: Option<String>,
+        dns_domains: Vec<String>,
+    ) -> Result<()> {
+        ensure!(
+            FirewallException::is_valid_interface_name(&name),
+            "Invalid interface: {name}"
+        );
+        for domain in &dns_domains {
+            ensure!(
+                DnsForwarding::is_valid_domain(domain),
+                "Invalid domain: {domain}"
+            );
+        }
+        let dns_forwarding = dns_resolver
+            .map(|resolver| {
+                let resolver = resolver
+                    .parse()
+                    .with_context(|| format!("Invalid DNS resolver: {resolver}"))?;
+                anyhow::Ok(DnsForwarding {
+                    resolver,
+                    domains: dns_domains,
+                })
+            })
+            .transpose()?;
+        let trusted_interface = Interface {
+            name,
+            dns_forwarding,
+        };
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut trusted_interfaces = rpc.get_settings().await?.trusted_interfaces;
+        ensure!(
+            !trusted_interfaces
+                .iter()
+                .any(|existing| existing.name == trusted_interface.name),
+            "Interface is already trusted: {}",
+            trusted_interface.name
+        );
+        trusted_interfaces.push(trusted_interface.clone());
+        rpc.set_trusted_interfaces(trusted_interfaces).await?;
+        println_human!("Trusting {trusted_interface}");
+        Ok(())
+    }
+
+    async fn remove(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut trusted_interfaces = rpc.get_settings().await?.trusted_interfaces;
+        let len = trusted_interfaces.len();
+        trusted_interfaces.retain(|existing| existing.name != name);
+        ensure!(
+            trusted_interfaces.len() != len,
+            "No such trusted interface: {name}"
+        );
+        rpc.set_trusted_interfaces(trusted_interfaces).await?;
+        println_human!("Removed {name}");
+        Ok(())
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_trusted_interfaces(vec![]).await?;
+        println_human!("Removed all trusted interfaces");
+        Ok(())
+    }
+}
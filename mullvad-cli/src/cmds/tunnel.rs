@@ -4,11 +4,23 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{AllowedIps, RelaySettings, WireguardConstraints},
-    wireguard::{QuantumResistantState, RotationInterval},
+    settings::TunnelOptions as TunnelSettings,
+    wireguard::{PublicKey, QuantumResistantState, RekeyInterval, RotationInterval},
 };
+use serde::Serialize;
+use talpid_types::net::wireguard::DaitaLevel;
 
 use super::BooleanOption;
-use crate::print_option;
+use crate::{output, print_option, println_human};
+
+/// JSON output of `tunnel get`.
+#[derive(Serialize)]
+struct TunnelOutput {
+    tunnel_options: TunnelSettings,
+    wireguard_key: PublicKey,
+    allowed_ips: Constraint<AllowedIps>,
+    last_rekey: Option<chrono::DateTime<chrono::Utc>>,
+}
 
 #[derive(Subcommand, Debug)]
 pub enum Tunnel {
@@ -25,11 +37,23 @@ pub enum TunnelOptions {
     /// Configure the tunnel MTU, or 'any'
     Mtu { mtu: Constraint<u16> },
 
-    /// Configure quantum-resistant key exchange
+    /// Configure quantum-resistant key exchange. While it is on, changing relays always
+    /// reconnects, which drops open connections
     QuantumResistant { state: QuantumResistantState },
 
-    /// Configure whether to enable DAITA
-    Daita { state: BooleanOption },
+    /// How often to renegotiate the quantum-resistant PSK while connected. Number of hours, or
+    /// 'any' to only negotiate it when connecting. Not applied to multihop or LWO obfuscation
+    RekeyInterval { interval: Constraint<RekeyInterval> },
+
+    /// Configure whether to enable DAITA, and which DAITA level to request
+    #[clap(arg_required_else_help = true)]
+    Daita {
+        state: Option<BooleanOption>,
+        /// DAITA level from 1 (least overhead) to 10 (strongest traffic-analysis resistance),
+        /// or 'any' to let the relay pick its default level
+        #[arg(long)]
+        level: Option<Constraint<u8>>,
+    },
 
     /// Specify custom allowed IPs for WireGuard tunnels. Use comma-separated values of IPs and IP ranges in CIDR notation.
     /// A empty string resets to the default value, where all traffic is allowed, i.e. (0.0.0.0/0,::/0).
@@ -66,7 +90,27 @@ impl Tunnel {
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let tunnel_options = rpc.get_settings().await?.tunnel_options;
+        let settings = rpc.get_settings().await?;
+        let tunnel_options = settings.tunnel_options;
+        let key = rpc.get_wireguard_key().await?;
+        let last_rekey = rpc
+            .get_last_quantum_resistant_rekey()
+            .await?
+            .map(chrono::DateTime::<chrono::Utc>::from);
+        // Get the WireGuard allowed IPs
+        let wireguard_constraints = match settings.relay_settings {
+            RelaySettings::Normal(settings) => settings.wireguard_constraints,
+            RelaySettings::CustomTunnelEndpoint(_) => WireguardConstraints::default(),
+        };
+
+        if output::is_json() {
+            return output::print_json(&TunnelOutput {
+                tunnel_options,
+                wireguard_key: key,
+                allowed_ips: wireguard_constraints.allowed_ips,
+                last_rekey,
+            });
+        }
 
         println!("WireGuard options");
 
@@ -82,10 +126,31 @@ impl Tunnel {
             "Quantum resistance",
             tunnel_options.wireguard.quantum_resistant,
         );
+        print_option!(
+            "Rekey interval",
+            match tunnel_options.wireguard.rekey_interval {
+                Some(interval) => interval.to_string(),
+                None => "unset".to_string(),
+            },
+        );
+        print_option!(
+            "Last rekey",
+            match last_rekey {
+                Some(time) => time.with_timezone(&chrono::Local).to_string(),
+                None => "never".to_string(),
+            },
+        );
 
         print_option!("DAITA", tunnel_options.wireguard.daita);
+        print_option!(
+            "DAITA level",
+            tunnel_options
+                .wireguard
+                .daita_level
+                .map(|level| level.to_string())
+                .unwrap_or("default".to_string()),
+        );
 
-        let key = rpc.get_wireguard_key().await?;
         print_option!("Public key", key.key,);
         print_option!(format_args!(
             "Created {}",
@@ -99,12 +164,6 @@ impl Tunnel {
             },
         );
 
-        // Get the WireGuard allowed IPs
-        let wireguard_constraints = match rpc.get_settings().await?.relay_settings {
-            RelaySettings::Normal(settings) => settings.wireguard_constraints,
-            RelaySettings::CustomTunnelEndpoint(_) => WireguardConstraints::default(),
-        };
-
         print_option!(
             "Allowed IPs",
             match wireguard_constraints.allowed_ips {
@@ -135,29 +194,46 @@ impl Tunnel {
         match options {
             TunnelOptions::Mtu { mtu } => {
                 rpc.set_wireguard_mtu(mtu.option()).await?;
-                println!("MTU parameter has been updated");
+                println_human!("MTU parameter has been updated");
             }
             TunnelOptions::QuantumResistant { state } => {
                 rpc.set_quantum_resistant_tunnel(state).await?;
-                println!("Quantum resistant setting has been updated");
+                println_human!("Quantum resistant setting has been updated");
             }
-            TunnelOptions::Daita { state } => {
-                rpc.set_enable_daita(*state).await?;
-                println!("DAITA setting has been updated");
+            TunnelOptions::RekeyInterval { interval } => match interval {
+                Constraint::Only(interval) => {
+                    rpc.set_quantum_resistant_rekey_interval(interval).await?;
+                    println_human!("Set rekey interval to {interval}");
+                }
+                Constraint::Any => {
+                    rpc.reset_quantum_resistant_rekey_interval().await?;
+                    println_human!("Disabled rekeying");
+                }
+            },
+            TunnelOptions::Daita { state, level } => {
+                if let Some(level) = level {
+                    let level = level.option().map(DaitaLevel::try_from).transpose()?;
+                    rpc.set_daita_level(level).await?;
+                    println_human!("DAITA level has been updated");
+                }
+                if let Some(state) = state {
+                    rpc.set_enable_daita(*state).await?;
+                    println_human!("DAITA setting has been updated");
+                }
             }
             TunnelOptions::AllowedIps { allowed_ips } => {
                 let ips = AllowedIps::parse(allowed_ips.split(','))?;
                 rpc.set_wireguard_allowed_ips(ips).await?;
-                println!("WireGuard allowed IPs have been updated");
+                println_human!("WireGuard allowed IPs have been updated");
             }
             TunnelOptions::RotationInterval { interval } => match interval {
                 Constraint::Only(interval) => {
                     rpc.set_wireguard_rotation_interval(interval).await?;
-                    println!("Set key rotation interval to {interval}");
+                    println_human!("Set key rotation interval to {interval}");
                 }
                 Constraint::Any => {
                     rpc.reset_wireguard_rotation_interval().await?;
-                    println!(
+                    println_human!(
                         "Reset key rotation interval to {}",
                         RotationInterval::default()
                     );
@@ -165,15 +241,15 @@ impl Tunnel {
             },
             TunnelOptions::RotateKey => {
                 rpc.rotate_wireguard_key().await?;
-                println!("Rotated WireGuard key");
+                println_human!("Rotated WireGuard key");
             }
             TunnelOptions::Ipv6 { state } => {
                 rpc.set_enable_ipv6(*state).await?;
-                println!("IPv6: {state}");
+                println_human!("IPv6: {state}");
             }
             TunnelOptions::Userspace { state } => {
                 rpc.set_userspace_wireguard(*state).await?;
-                println!("Userspace WireGuard: {state}");
+                println_human!("Userspace WireGuard: {state}");
             }
         }
 
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use serde_json::json;
+
+use super::BooleanOption;
+use crate::{output, println_human};
+
+#[derive(Subcommand, Debug)]
+pub enum TunnelNamespace {
+    /// Display the current tunnel namespace setting
+    Get,
+    /// Change the tunnel namespace setting
+    Set { policy: BooleanOption },
+}
+
+impl TunnelNamespace {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            TunnelNamespace::Get => Self::get().await,
+            TunnelNamespace::Set { policy } => Self::set(policy).await,
+        }
+    }
+
+    async fn set(policy: BooleanOption) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_tunnel_namespace(*policy).await?;
+        println_human!("Changed tunnel namespace setting");
+        Ok(())
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let tunnel_namespace = rpc.get_settings().await?.tunnel_namespace;
+        if output::is_json() {
+            return output::print_json(&json!({ "tunnel_namespace": tunnel_namespace }));
+        }
+        let state = BooleanOption::from(tunnel_namespace);
+        println!("Only tunnel programs launched with mullvad-include: {state}");
+        Ok(())
+    }
+}
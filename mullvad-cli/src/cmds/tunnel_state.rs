@@ -1,4 +1,4 @@
-use crate::{BIN_NAME, format};
+use crate::{BIN_NAME, format, output, println_human};
 use anyhow::{Result, anyhow, bail};
 use futures::{Stream, StreamExt};
 use mullvad_management_interface::{MullvadProxyClient, client::DaemonEvent};
@@ -86,8 +86,13 @@ async fn wait_for_tunnel_state(
 ) -> Result<()> {
     while let Some(state) = event_stream.next().await {
         if let DaemonEvent::TunnelState(new_state) = state? {
-            format::print_state(&new_state, None, false);
+            if !output::is_json() {
+                format::print_state(&new_state, None, false);
+            }
             if matches_event(&new_state)? {
+                if output::is_json() {
+                    output::print_json(&new_state)?;
+                }
                 return Ok(());
             }
         }
@@ -104,12 +109,12 @@ async fn wait_for_tunnel_state(
 /// the user when they inevitably will go troubleshooting.
 fn print_account_loggedout(state: &DeviceState) {
     match state {
-        DeviceState::LoggedOut => println!("Warning: You are not logged in to an account."),
-        DeviceState::Revoked => println!("Warning: This device has been revoked"),
+        DeviceState::LoggedOut => println_human!("Warning: You are not logged in to an account."),
+        DeviceState::Revoked => println_human!("Warning: This device has been revoked"),
         DeviceState::LoggedIn(_) => return, // Normal case, do nothing.
     };
 
-    println!(
+    println_human!(
         "Mullvad is blocking all network traffic until you perform one of the following actions:
 
 1. Login to a Mullvad account with available time/credits.
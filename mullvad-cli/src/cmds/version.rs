@@ -1,8 +1,22 @@
 use anyhow::{Context, Result};
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::version::AppVersionInfo;
+use serde::Serialize;
+
+use crate::output;
+
+/// JSON output of `version`.
+#[derive(Serialize)]
+struct VersionOutput {
+    cli_version: &'static str,
+    daemon_version: String,
+    version_info: AppVersionInfo,
+}
 
 pub async fn print() -> Result<()> {
-    println!("{:22}: {}", "Current version", mullvad_version::VERSION);
+    if !output::is_json() {
+        println!("{:22}: {}", "Current version", mullvad_version::VERSION);
+    }
 
     let mut rpc = MullvadProxyClient::new()
         .await
@@ -13,14 +27,23 @@ pub async fn print() -> Result<()> {
         .await
         .context("Failed to get current mullvad-daemon version")?;
 
-    if daemon_version != mullvad_version::VERSION {
-        println!("{:22}: {}", "mullvad-daemon version", daemon_version);
-    };
-
     let version_info = rpc
         .get_version_info()
         .await
         .context("Failed to get version info")?;
+
+    if output::is_json() {
+        return output::print_json(&VersionOutput {
+            cli_version: mullvad_version::VERSION,
+            daemon_version,
+            version_info,
+        });
+    }
+
+    if daemon_version != mullvad_version::VERSION {
+        println!("{:22}: {}", "mullvad-daemon version", daemon_version);
+    };
+
     println!(
         "{:22}: {}",
         "Is supported", version_info.current_version_supported
@@ -34,3 +57,31 @@ pub async fn print() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    /// The JSON printed by `version`, as described in `JSON.md`
+    #[test]
+    fn test_version_output_json() {
+        let version_info = json!({
+            "current_version_supported": true,
+            "suggested_upgrade": null,
+        });
+        let output = VersionOutput {
+            cli_version: "2026.1",
+            daemon_version: "2026.1".to_owned(),
+            version_info: serde_json::from_value(version_info.clone()).unwrap(),
+        };
+        assert_eq!(
+            serde_json::to_value(output).unwrap(),
+            json!({
+                "cli_version": "2026.1",
+                "daemon_version": "2026.1",
+                "version_info": version_info,
+            })
+        );
+    }
+}
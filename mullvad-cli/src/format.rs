@@ -154,6 +154,11 @@ fn connection_information(
         .filter(|_| verbose)
         .and_then(|endpoint| endpoint.tunnel_interface.clone());
     info.insert("Tunnel interface", tunnel_interface_fmt);
+    let daita_fmt = endpoint
+        .filter(|_| verbose)
+        .and_then(|endpoint| endpoint.daita_parameters)
+        .map(|daita| daita.to_string());
+    info.insert("DAITA", daita_fmt);
 
     info.insert("Visible location", location.map(format_location));
     let features_fmt = feature_indicators
@@ -219,7 +224,7 @@ fn format_relay_connection(
             location.and_then(|l| l.entry_hostname.as_deref()),
             // Check if we *actually* want to print an obfuscator endpoint ..
             match endpoint.obfuscation {
-                Some(ref info) => info.get_endpoints(),
+                Some(ref info) => info.active_endpoints(),
                 _ => vec![*entry],
             },
             verbose,
@@ -231,7 +236,7 @@ fn format_relay_connection(
         // Check if we *actually* want to print an obfuscator endpoint ..
         // The obfuscator information should be printed for the exit relay if multihop is disabled
         match (&endpoint.obfuscation, &first_hop) {
-            (Some(obfuscation), None) => obfuscation.get_endpoints(),
+            (Some(obfuscation), None) => obfuscation.active_endpoints(),
             _ => vec![endpoint.endpoint],
         },
         verbose,
@@ -1,8 +1,10 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use std::ffi::OsString;
 
 mod cmds;
 mod format;
+mod output;
 use cmds::*;
 
 use crate::cmds::reset::SettingsKey;
@@ -12,7 +14,18 @@ pub const BIN_NAME: &str = env!("CARGO_BIN_NAME");
 #[derive(Debug, Parser)]
 #[command(version = mullvad_version::VERSION, about, long_about = None)]
 #[command(propagate_version = true)]
-enum Cli {
+struct Cli {
+    /// Format output and errors as JSON. Commands that only change settings or state print
+    /// nothing to standard output when they succeed
+    #[arg(long, short = 'j', global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
     /// Control and display information about your Mullvad account
     #[clap(subcommand)]
     Account(account::Account),
@@ -44,6 +57,38 @@ enum Cli {
     #[clap(subcommand)]
     Lan(lan::Lan),
 
+    /// Allow traffic to selected hosts and ports outside the tunnel, even when the firewall
+    /// blocks all other traffic
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    FirewallException(firewall_exception::FirewallException),
+
+    /// Apply the firewall rules again when another program, such as firewalld or docker, changes
+    /// or removes them. Clients are notified of such changes either way
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    FirewallReapply(firewall_reapply::FirewallReapply),
+
+    /// Accept incoming connections to selected ports on the tunnel interface while connected
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    InboundPort(inbound_port::InboundPort),
+
+    /// Forward traffic from other devices on the local network through the tunnel
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    LanGateway(lan_gateway::LanGateway),
+
+    /// Use the tunnel alongside other VPN or mesh network clients, such as Tailscale or ZeroTier
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    TrustedInterface(trusted_interface::TrustedInterface),
+
+    /// Serve metrics about the daemon, such as tunnel state transitions and traffic counters, to
+    /// a local monitoring system
+    #[clap(subcommand)]
+    Metrics(metrics::Metrics),
+
     /// Connect to a VPN relay
     Connect {
         /// Wait until connected before exiting
@@ -107,6 +152,12 @@ enum Cli {
     #[clap(subcommand)]
     Tunnel(tunnel::Tunnel),
 
+    /// Confine the VPN tunnel to a network namespace. When enabled, only programs launched with
+    /// `mullvad-include` use the tunnel
+    #[cfg(target_os = "linux")]
+    #[clap(subcommand)]
+    TunnelNamespace(tunnel_namespace::TunnelNamespace),
+
     /// Show information about the current Mullvad version
     /// and available versions
     Version,
@@ -142,11 +193,20 @@ enum Cli {
     #[clap(subcommand)]
     CustomList(custom_list::CustomList),
 
+    /// Manage named sets of settings that can be applied all at once, such as one for home and
+    /// one for travel
+    #[clap(subcommand)]
+    SettingsProfile(settings_profile::SettingsProfile),
+
     /// Apply a JSON patch generated by 'export-settings'
     #[clap(arg_required_else_help = true)]
     ImportSettings {
         /// File to read from. If this is "-", read from standard input
         file: String,
+
+        /// Only validate the patch and show which settings it would change
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Export a JSON patch based on the current settings
@@ -169,41 +229,79 @@ async fn main() -> Result<()> {
     #[cfg(unix)]
     handle_sigpipe().unwrap();
 
-    match Cli::parse() {
-        Cli::Account(cmd) => cmd.handle().await,
-        Cli::Connect { wait } => tunnel_state::connect(wait).await,
-        Cli::Reconnect { wait } => tunnel_state::reconnect(wait).await,
-        Cli::Debug(cmd) => cmd.handle().await,
-        Cli::Disconnect { wait } => tunnel_state::disconnect(wait).await,
-        Cli::AutoConnect(cmd) => cmd.handle().await,
-        Cli::BetaProgram(cmd) => cmd.handle().await,
-        Cli::LockdownMode(cmd) => cmd.handle().await,
-        Cli::Dns(cmd) => cmd.handle().await,
-        Cli::Lan(cmd) => cmd.handle().await,
-        Cli::AntiCensorship(cmd) => cmd.handle().await,
-        Cli::ApiAccess(cmd) => cmd.handle().await,
-        Cli::Version => version::print().await,
-        Cli::FactoryReset { assume_yes } => reset::handle_factory_reset(assume_yes).await,
-        Cli::ResetSettings {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        // Help and version output is printed as usual
+        Err(error) if error.use_stderr() && json_requested(std::env::args_os()) => {
+            output::print_json_error(&output::parse_error(&error));
+            std::process::exit(error.exit_code());
+        }
+        Err(error) => error.exit(),
+    };
+    if cli.json {
+        output::enable_json();
+    }
+
+    let result = run(cli.command).await;
+    if let Err(error) = &result
+        && output::is_json()
+    {
+        output::print_json_error(error);
+        std::process::exit(1);
+    }
+    result
+}
+
+async fn run(command: Command) -> Result<()> {
+    match command {
+        Command::Account(cmd) => cmd.handle().await,
+        Command::Connect { wait } => tunnel_state::connect(wait).await,
+        Command::Reconnect { wait } => tunnel_state::reconnect(wait).await,
+        Command::Debug(cmd) => cmd.handle().await,
+        Command::Disconnect { wait } => tunnel_state::disconnect(wait).await,
+        Command::AutoConnect(cmd) => cmd.handle().await,
+        Command::BetaProgram(cmd) => cmd.handle().await,
+        Command::LockdownMode(cmd) => cmd.handle().await,
+        Command::Dns(cmd) => cmd.handle().await,
+        Command::Lan(cmd) => cmd.handle().await,
+        Command::Metrics(cmd) => cmd.handle().await,
+        Command::AntiCensorship(cmd) => cmd.handle().await,
+        Command::ApiAccess(cmd) => cmd.handle().await,
+        Command::Version => version::print().await,
+        Command::FactoryReset { assume_yes } => reset::handle_factory_reset(assume_yes).await,
+        Command::ResetSettings {
             assume_yes,
             preserve,
         } => reset::handle_settings_reset(assume_yes, preserve).await,
-        Cli::Relay(cmd) => cmd.handle().await,
-        Cli::Tunnel(cmd) => cmd.handle().await,
-        Cli::SplitTunnel(cmd) => cmd.handle().await,
-        Cli::Status { cmd, args } => status::handle(cmd, args).await,
-        Cli::CustomList(cmd) => cmd.handle().await,
-        Cli::ImportSettings { file } => patch::import(file).await,
-        Cli::ExportSettings { file } => patch::export(file).await,
-        Cli::Log(cmd) => cmd.handle().await,
+        Command::Relay(cmd) => cmd.handle().await,
+        Command::Tunnel(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::TunnelNamespace(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::FirewallException(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::FirewallReapply(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::InboundPort(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::LanGateway(cmd) => cmd.handle().await,
+        #[cfg(target_os = "linux")]
+        Command::TrustedInterface(cmd) => cmd.handle().await,
+        Command::SplitTunnel(cmd) => cmd.handle().await,
+        Command::Status { cmd, args } => status::handle(cmd, args).await,
+        Command::CustomList(cmd) => cmd.handle().await,
+        Command::SettingsProfile(cmd) => cmd.handle().await,
+        Command::ImportSettings { file, dry_run } => patch::import(file, dry_run).await,
+        Command::ExportSettings { file } => patch::export(file).await,
+        Command::Log(cmd) => cmd.handle().await,
 
         #[cfg(all(unix, not(target_os = "android")))]
-        Cli::ShellCompletions { shell, dir } => {
+        Command::ShellCompletions { shell, dir } => {
             use anyhow::Context;
             use clap::CommandFactory;
 
             // FIXME: The shell completions include hidden commands (including "shell-completions")
-            println!("Generating shell completions to {}", dir.display());
+            println_human!("Generating shell completions to {}", dir.display());
             clap_complete::generate_to(shell, &mut Cli::command(), BIN_NAME, dir)
                 .context("Failed to generate shell completions")?;
             Ok(())
@@ -211,6 +309,14 @@ async fn main() -> Result<()> {
     }
 }
 
+/// Return whether `--json` is among `args`, for when they could not be parsed.
+fn json_requested(args: impl IntoIterator<Item = OsString>) -> bool {
+    args.into_iter()
+        .skip(1)
+        .take_while(|arg| arg != "--")
+        .any(|arg| arg == "--json" || arg == "-j")
+}
+
 /// Install the default signal handler for `SIGPIPE`.
 ///
 /// By default, Rust replaces it with an empty handler because reasons: <https://github.com/rust-lang/rust/issues/119980>
@@ -222,3 +328,35 @@ fn handle_sigpipe() -> Result<(), nix::errno::Errno> {
     unsafe { signal(Signal::SIGPIPE, SigHandler::SigDfl) }?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_json_requested() {
+        let args = |args: &[&str]| args.iter().map(OsString::from).collect::<Vec<_>>();
+
+        assert!(json_requested(args(&["mullvad", "--json", "status"])));
+        assert!(json_requested(args(&["mullvad", "relay", "list", "-j"])));
+        assert!(!json_requested(args(&["mullvad", "status"])));
+        assert!(!json_requested(args(&[
+            "mullvad",
+            "custom-list",
+            "new",
+            "--",
+            "--json"
+        ])));
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let error = Cli::try_parse_from(["mullvad", "--json", "not-a-command"]).unwrap_err();
+        assert!(error.use_stderr());
+        assert_eq!(error.exit_code(), 2);
+
+        let error = output::parse_error(&error);
+        assert_eq!(error.to_string(), "unrecognized subcommand 'not-a-command'");
+        assert_eq!(error.chain().count(), 1);
+    }
+}
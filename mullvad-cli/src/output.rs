@@ -0,0 +1,239 @@
+//! Output format selected with the global `--json` flag. The JSON output is described in
+//! `mullvad-cli/JSON.md`.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Print a human-readable message. When the output is formatted as JSON, the message is printed
+/// to standard error instead, so that standard output only contains JSON.
+#[macro_export]
+macro_rules! println_human {
+    ($($arg:tt)*) => {{
+        if $crate::output::is_json() {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    }};
+}
+
+/// Format all output as JSON.
+pub fn enable_json() {
+    JSON.store(true, Ordering::Relaxed);
+}
+
+/// Return whether output should be formatted as JSON.
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Print `value` as JSON on a single line.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string(value).context("Failed to format output as JSON")?;
+    println!("{json}");
+    Ok(())
+}
+
+/// JSON printed for an error by [`print_json_error`].
+#[derive(Serialize)]
+struct ErrorOutput {
+    error: ErrorDetails,
+}
+
+#[derive(Serialize)]
+struct ErrorDetails {
+    message: String,
+    causes: Vec<String>,
+}
+
+impl From<&anyhow::Error> for ErrorOutput {
+    fn from(error: &anyhow::Error) -> Self {
+        ErrorOutput {
+            error: ErrorDetails {
+                message: error.to_string(),
+                causes: error.chain().skip(1).map(ToString::to_string).collect(),
+            },
+        }
+    }
+}
+
+/// Print `error` and the errors that caused it as JSON on a single line.
+pub fn print_json_error(error: &anyhow::Error) {
+    if let Err(error) = print_json(&ErrorOutput::from(error)) {
+        eprintln!("{error:?}");
+    }
+}
+
+/// Convert an error from parsing the command line to an error that can be printed as JSON. Only
+/// the first line of the message is kept, without the usage and hints that follow it.
+pub fn parse_error(error: &clap::Error) -> anyhow::Error {
+    let rendered = error.to_string();
+    let message = rendered.lines().next().unwrap_or_default();
+    anyhow::anyhow!("{}", message.trim_start_matches("error: "))
+}
+
+/// The JSON printed by each command is described in `JSON.md`, and must not change by accident.
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{
+        access_method::AccessMethodSetting, custom_list::CustomList, device::Device,
+        relay_list::RelayListCountry, settings::DnsOptions,
+    };
+    use serde::de::DeserializeOwned;
+    use serde_json::{Value, json};
+
+    const PUBLIC_KEY: &str = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=";
+
+    /// Assert that `expected` is read as a `T`, and printed exactly as it was.
+    fn assert_json_shape<T: Serialize + DeserializeOwned>(expected: Value) {
+        let value: T = serde_json::from_value(expected.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&value).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_error_json() {
+        let output = serde_json::to_string(&ErrorOutput::from(&anyhow::anyhow!("Failed"))).unwrap();
+        assert_eq!(output, r#"{"error":{"message":"Failed","causes":[]}}"#);
+
+        let error = anyhow::anyhow!("No such file or directory")
+            .context("Failed to read socket")
+            .context("Failed to connect to mullvad-daemon");
+        assert_eq!(
+            serde_json::to_value(ErrorOutput::from(&error)).unwrap(),
+            json!({
+                "error": {
+                    "message": "Failed to connect to mullvad-daemon",
+                    "causes": ["Failed to read socket", "No such file or directory"],
+                }
+            })
+        );
+    }
+
+    /// `account list-devices`
+    #[test]
+    fn test_device_list_json() {
+        assert_json_shape::<Vec<Device>>(json!([{
+            "id": "7d2c9b7e-1c5a-4c39-a9a4-0e1f2d3c4b5a",
+            "name": "happy seagull",
+            "pubkey": PUBLIC_KEY,
+            "hijack_dns": false,
+            "created": "2026-01-01T00:00:00Z",
+        }]));
+    }
+
+    /// `relay list`
+    #[test]
+    fn test_relay_list_json() {
+        let location = json!({
+            "country": "Sweden",
+            "country_code": "se",
+            "city": "Gothenburg",
+            "city_code": "got",
+            "latitude": 57.70887,
+            "longitude": 11.97456,
+        });
+        assert_json_shape::<Vec<RelayListCountry>>(json!([{
+            "name": "Sweden",
+            "code": "se",
+            "cities": [{
+                "name": "Gothenburg",
+                "code": "got",
+                "latitude": 57.70887,
+                "longitude": 11.97456,
+                "relays": [{
+                    "overridden_ipv4": false,
+                    "overridden_ipv6": false,
+                    "include_in_country": true,
+                    "owned": true,
+                    "provider": "31173",
+                    "endpoint_data": {
+                        "public_key": PUBLIC_KEY,
+                        "daita": true,
+                        "quic": null,
+                        "lwo": false,
+                        "shadowsocks_extra_addr_in": [],
+                    },
+                    "needs_other_entry": false,
+                    "inner": {
+                        "hostname": "se-got-wg-001",
+                        "ipv4_addr_in": "192.0.2.1",
+                        "ipv6_addr_in": "2001:db8::1",
+                        "active": true,
+                        "weight": 100,
+                        "location": location,
+                    },
+                }],
+            }],
+        }]));
+    }
+
+    /// `api-access list`
+    #[test]
+    fn test_access_method_list_json() {
+        assert_json_shape::<Vec<AccessMethodSetting>>(json!([
+            {
+                "id": "0b8a4a5e-6d3b-4f7e-8c1d-2e3f4a5b6c7d",
+                "name": "Direct",
+                "enabled": true,
+                "access_method": { "built_in": "direct" },
+            },
+            {
+                "id": "1c9b5b6f-7e4c-4a8f-9d2e-3f4a5b6c7d8e",
+                "name": "Proxy",
+                "enabled": false,
+                "access_method": {
+                    "custom": {
+                        "socks5_remote": { "endpoint": "192.0.2.1:1080", "auth": null },
+                    },
+                },
+            },
+        ]));
+    }
+
+    /// `custom-list list`
+    #[test]
+    fn test_custom_list_json() {
+        assert_json_shape::<Vec<CustomList>>(json!([{
+            "id": "2d0c6c7a-8f5d-4b9a-8e3f-4a5b6c7d8e9f",
+            "name": "Nordics",
+            "locations": [
+                { "country": "se" },
+                { "city": ["no", "osl"] },
+                { "hostname": ["fi", "hel", "fi-hel-wg-001"] },
+            ],
+        }]));
+    }
+
+    /// `dns get`
+    #[test]
+    fn test_dns_options_json() {
+        assert_json_shape::<DnsOptions>(json!({
+            "state": "custom",
+            "default_options": {
+                "block_ads": true,
+                "block_trackers": false,
+                "block_malware": false,
+                "block_adult_content": false,
+                "block_gambling": false,
+                "block_social_media": false,
+            },
+            "custom_options": { "addresses": ["192.0.2.53"] },
+        }));
+    }
+
+    /// `split-tunnel get`
+    #[cfg(any(windows, target_os = "macos"))]
+    #[test]
+    fn test_split_tunnel_json() {
+        use mullvad_types::settings::SplitTunnelSettings;
+
+        assert_json_shape::<SplitTunnelSettings>(json!({
+            "enable_exclusions": true,
+            "apps": ["/Applications/Example.app"],
+        }));
+    }
+}
@@ -13,4 +13,10 @@ pub enum Error {
 
     #[error(transparent)]
     Serialize(#[from] serde_json::Error),
+
+    #[error("The relay list has no ETag")]
+    MissingETag,
+
+    #[error("The relay list contains no relays")]
+    EmptyRelayList,
 }
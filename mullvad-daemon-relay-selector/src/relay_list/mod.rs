@@ -3,4 +3,7 @@ pub mod parsed_relays;
 pub mod update;
 
 /// Where the relay list is cached on disk.
-const RELAYS_FILENAME: &str = "relays.json";
+pub const RELAYS_FILENAME: &str = "relays.json";
+/// Where the [`RelayListSource`](mullvad_types::relay_list::RelayListSource) of the cached relay
+/// list is stored on disk.
+pub const RELAY_LIST_SOURCE_FILENAME: &str = "relays-source.json";
@@ -5,15 +5,26 @@ use std::path::Path;
 use std::time::SystemTime;
 
 use mullvad_api::CachedRelayList;
+use mullvad_types::relay_list::RelayListSource;
 
-use crate::relay_list::RELAYS_FILENAME;
 use crate::relay_list::error::Error;
+use crate::relay_list::{RELAY_LIST_SOURCE_FILENAME, RELAYS_FILENAME};
 
 /// Try to read the relays from disk, preferring the newer ones.
 pub fn parse_relays_from_file(
     cache_dir: impl AsRef<Path>,
     resource_dir: impl AsRef<Path>,
 ) -> Result<CachedRelayList, Error> {
+    parse_relays_with_source_from_file(cache_dir, resource_dir).map(|(relay_list, _)| relay_list)
+}
+
+/// Try to read the relays from disk, preferring the newer ones. Also return where the relays
+/// were obtained from.
+pub fn parse_relays_with_source_from_file(
+    cache_dir: impl AsRef<Path>,
+    resource_dir: impl AsRef<Path>,
+) -> Result<(CachedRelayList, RelayListSource), Error> {
+    let cached_source = || read_source(cache_dir.as_ref());
     let relay_list = match (
         from_file_inner(cache_dir.as_ref().join(RELAYS_FILENAME)),
         from_file_inner(resource_dir.as_ref().join(RELAYS_FILENAME)),
@@ -22,12 +33,12 @@ pub fn parse_relays_from_file(
         // before the resource one was created.
         // If cache_time is later than install_time, return cached relay list
         (Ok((cached_relays, cache_time)), Ok((_, install_time))) if cache_time >= install_time => {
-            cached_relays
+            (cached_relays, cached_source())
         }
         // else, return the bundled relay list
-        (Ok(_), Ok((bundled_relays, _))) => bundled_relays,
-        (Ok((cached_relays, _)), _) => cached_relays,
-        (_, Ok((bundled_relays, _))) => bundled_relays,
+        (Ok(_), Ok((bundled_relays, _))) => (bundled_relays, RelayListSource::Bundled),
+        (Ok((cached_relays, _)), _) => (cached_relays, cached_source()),
+        (_, Ok((bundled_relays, _))) => (bundled_relays, RelayListSource::Bundled),
         (Err(cached_error), Err(bundled_error)) => {
             log::error!("Failed to load bundled relays: {bundled_error}");
             log::error!("Failed to load cached relays: {cached_error}");
@@ -38,6 +49,15 @@ pub fn parse_relays_from_file(
     Ok(relay_list)
 }
 
+/// Read the source of the cached relay list. Relay lists cached before the source was recorded
+/// were always downloaded from the API.
+fn read_source(cache_dir: &Path) -> RelayListSource {
+    std::fs::read(cache_dir.join(RELAY_LIST_SOURCE_FILENAME))
+        .ok()
+        .and_then(|source| serde_json::from_slice(&source).ok())
+        .unwrap_or(RelayListSource::Api)
+}
+
 fn from_file_inner(path: impl AsRef<Path>) -> Result<(CachedRelayList, SystemTime), Error> {
     log::trace!("Reading relays from {}", path.as_ref().display());
     let (file, last_modified) = open_file(path).map_err(Error::OpenRelayCache)?;
@@ -3,19 +3,19 @@
 use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use futures::channel::mpsc;
+use futures::channel::{mpsc, oneshot};
 use futures::future::{Fuse, FusedFuture};
 use futures::{Future, FutureExt, SinkExt, StreamExt};
 use mullvad_types::relay_constraints::RelayOverride;
 use tokio::fs::File;
 
-use super::RELAYS_FILENAME;
 use super::error::Error;
+use super::{RELAY_LIST_SOURCE_FILENAME, RELAYS_FILENAME};
 
 use mullvad_api::{
     CachedRelayList, ETag, RelayListProxy, availability::ApiAvailability, rest::MullvadRestHandle,
 };
-use mullvad_types::relay_list::{BridgeList, RelayList};
+use mullvad_types::relay_list::{BridgeList, RelayList, RelayListInfo, RelayListSource};
 use talpid_future::retry::{ExponentialBackoff, Jittered, retry_future};
 use talpid_types::ErrorExt;
 
@@ -44,6 +44,14 @@ enum Event {
     Update,
     /// Register new relay IP overrides.
     Override(Vec<RelayOverride>),
+    /// Replace the relay list with one that was read from a file.
+    Import {
+        relay_list: String,
+        path: String,
+        tx: oneshot::Sender<Result<RelayListInfo, Error>>,
+    },
+    /// Return the source and version of the relay list.
+    GetInfo(oneshot::Sender<RelayListInfo>),
 }
 
 impl RelayListUpdaterHandle {
@@ -67,15 +75,47 @@ impl RelayListUpdaterHandle {
             log::error!("Failed to apply new relay overrides");
         };
     }
+
+    /// Replace the relay list with `relay_list`, in the format produced by the `relay_list`
+    /// binary of `mullvad-api`. `path` is the file it was read from, which is recorded as the
+    /// source of the relay list.
+    pub async fn import(
+        &mut self,
+        relay_list: String,
+        path: String,
+    ) -> Result<RelayListInfo, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Event::Import {
+                relay_list,
+                path,
+                tx,
+            })
+            .await
+            .map_err(|_| Error::DownloaderShutdown)?;
+        rx.await.map_err(|_| Error::DownloaderShutdown)?
+    }
+
+    /// Return the source and version of the relay list.
+    pub async fn info(&mut self) -> Result<RelayListInfo, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(Event::GetInfo(tx))
+            .await
+            .map_err(|_| Error::DownloaderShutdown)?;
+        rx.await.map_err(|_| Error::DownloaderShutdown)
+    }
 }
 
 pub struct RelayListUpdater {
     api_client: RelayListProxy,
     cache_path: PathBuf,
+    source_path: PathBuf,
     on_update: Box<dyn Fn(&RelayList) + Send + 'static>,
     last_check: SystemTime,
     api_availability: ApiAvailability,
     etag: Option<ETag>,
+    source: RelayListSource,
     // Keep tabs on the up-to-date relay list.
     // Use [RelayListUpdater::get_final_relay_list] when exposing the relay list to other parts of
     // the app.
@@ -93,26 +133,34 @@ impl RelayListUpdater {
         cache_dir: &Path,
         overrides: Vec<RelayOverride>,
         on_update: impl Fn(&RelayList) + Send + 'static,
-        cached_relay_list: Option<CachedRelayList>,
+        cached_relay_list: Option<(CachedRelayList, RelayListSource)>,
     ) -> RelayListUpdaterHandle {
         let (tx, cmd_rx) = mpsc::channel(1);
         let api_availability = api_handle.availability.clone();
         let api_client = RelayListProxy::new(api_handle);
 
-        let (relay_list, bridge_list, etag) = cached_relay_list
-            .map(|cached_relay_list| {
+        let (relay_list, bridge_list, etag, source) = match cached_relay_list {
+            Some((cached_relay_list, source)) => {
                 let etag = cached_relay_list.etag().cloned();
                 let (relay_list, bridge_list) = cached_relay_list.into_internal_repr();
-                (relay_list, bridge_list, etag)
-            })
-            .unwrap_or_default();
+                (relay_list, bridge_list, etag, source)
+            }
+            None => (
+                RelayList::default(),
+                BridgeList::default(),
+                None,
+                RelayListSource::Bundled,
+            ),
+        };
         let updater = RelayListUpdater {
             api_client,
             cache_path: cache_dir.join(RELAYS_FILENAME),
+            source_path: cache_dir.join(RELAY_LIST_SOURCE_FILENAME),
             relay_selector: selector,
             on_update: Box::new(on_update),
             last_check: UNIX_EPOCH,
             etag,
+            source,
             overrides,
             api_availability,
             relay_list,
@@ -166,6 +214,12 @@ impl RelayListUpdater {
                             log::trace!("New overrides match the old overrides.");
                             log::trace!("{overrides:#?}");
                         }
+                        Event::Import { relay_list, path, tx } => {
+                            let _ = tx.send(self.import_relay_list(&relay_list, path).await);
+                        }
+                        Event::GetInfo(tx) => {
+                            let _ = tx.send(self.info());
+                        }
                     }
                 }
 
@@ -229,14 +283,52 @@ impl RelayListUpdater {
 
     async fn update_cache(&mut self, new_relay_list: CachedRelayList) {
         // Save the new relay list to the cache file
-        if let Err(error) = Self::cache_relays(&self.cache_path, &new_relay_list).await {
+        if let Err(error) = self
+            .cache_relays(&new_relay_list, &RelayListSource::Api)
+            .await
+        {
             log::error!(
                 "{}",
                 error.display_chain_with_msg("Failed to update relay cache on disk")
             );
         }
+        self.use_relay_list(new_relay_list, RelayListSource::Api);
+    }
+
+    /// Validate and use a relay list that was read from a file. Unlike downloaded relay lists, it
+    /// is not used unless it could be written to the cache.
+    ///
+    /// The relay list is not signed, so its authenticity and freshness cannot be verified. Only
+    /// admin clients may import relay lists, and they are trusted to provide a genuine one. The
+    /// imported list is replaced by the next relay list downloaded from the API.
+    async fn import_relay_list(
+        &mut self,
+        relay_list: &str,
+        path: String,
+    ) -> Result<RelayListInfo, Error> {
+        let Some(relay_list) = parse_imported_relay_list(relay_list, self.etag.as_ref())? else {
+            log::info!("Imported relay list from {path} is already in use");
+            return Ok(self.info());
+        };
+
+        let source = RelayListSource::Imported { path };
+        self.cache_relays(&relay_list, &source).await?;
+        log::warn!("Using unverified relay list {source}");
+        self.use_relay_list(relay_list, source);
+        Ok(self.info())
+    }
+
+    fn info(&self) -> RelayListInfo {
+        RelayListInfo {
+            source: self.source.clone(),
+            etag: self.etag.as_ref().map(|etag| etag.0.clone()),
+        }
+    }
+
+    fn use_relay_list(&mut self, new_relay_list: CachedRelayList, source: RelayListSource) {
         // Cache the ETag so that we send the correct one in the next request
         self.etag = new_relay_list.etag().cloned();
+        self.source = source;
         // Propagate the new relay list to the relay selector
         let (relay_list, bridge_list) = new_relay_list.into_internal_repr();
         self.relay_list = relay_list;
@@ -256,18 +348,13 @@ impl RelayListUpdater {
         (self.on_update)(&relay_list);
     }
 
-    /// Write a [`CachedRelayList`] to the file at `cache_path`.
-    async fn cache_relays(cache_path: &Path, relays: &CachedRelayList) -> Result<(), Error> {
-        log::debug!("Writing relays cache to {}", cache_path.display());
-        let mut file = File::create(cache_path)
-            .await
-            .map_err(Error::OpenRelayCache)?;
-        let bytes = serde_json::to_vec_pretty(relays)?;
-        let mut slice: &[u8] = bytes.as_slice();
-        let _ = tokio::io::copy(&mut slice, &mut file)
-            .await
-            .map_err(Error::WriteRelayCache)?;
-        Ok(())
+    /// Write a [`CachedRelayList`] and its source to the cache.
+    async fn cache_relays(
+        &self,
+        relays: &CachedRelayList,
+        source: &RelayListSource,
+    ) -> Result<(), Error> {
+        write_relay_cache(&self.cache_path, &self.source_path, relays, source).await
     }
 
     /// Return a version of the [`RelayList`] where [`RelayOverride`]s have been applied.
@@ -277,3 +364,160 @@ impl RelayListUpdater {
             .apply_overrides(self.overrides.clone())
     }
 }
+
+/// Parse and validate a relay list that was read from a file. Return `None` if the relay list has
+/// the same ETag as `current_etag`, i.e. it is already in use.
+fn parse_imported_relay_list(
+    relay_list: &str,
+    current_etag: Option<&ETag>,
+) -> Result<Option<CachedRelayList>, Error> {
+    let relay_list: CachedRelayList = serde_json::from_str(relay_list)?;
+    let etag = relay_list
+        .etag()
+        .filter(|etag| !etag.0.trim().is_empty())
+        .ok_or(Error::MissingETag)?;
+    if current_etag.is_some_and(|current| current.0 == etag.0) {
+        return Ok(None);
+    }
+    let (relays, _) = relay_list.clone().into_internal_repr();
+    if relays.relays().next().is_none() {
+        return Err(Error::EmptyRelayList);
+    }
+    Ok(Some(relay_list))
+}
+
+/// Write a [`CachedRelayList`] to the file at `cache_path`, and its source to the file at
+/// `source_path`.
+async fn write_relay_cache(
+    cache_path: &Path,
+    source_path: &Path,
+    relays: &CachedRelayList,
+    source: &RelayListSource,
+) -> Result<(), Error> {
+    log::debug!("Writing relays cache to {}", cache_path.display());
+    let mut file = File::create(cache_path)
+        .await
+        .map_err(Error::OpenRelayCache)?;
+    let bytes = serde_json::to_vec_pretty(relays)?;
+    let mut slice: &[u8] = bytes.as_slice();
+    let _ = tokio::io::copy(&mut slice, &mut file)
+        .await
+        .map_err(Error::WriteRelayCache)?;
+
+    let source = serde_json::to_vec(source)?;
+    tokio::fs::write(source_path, source)
+        .await
+        .map_err(Error::WriteRelayCache)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::relay_list::parsed_relays::parse_relays_with_source_from_file;
+
+    const RELAY: &str = r#"{
+        "hostname": "se-got-wg-001",
+        "active": true,
+        "owned": true,
+        "location": "se-got",
+        "provider": "31173",
+        "ipv4_addr_in": "185.213.154.68",
+        "ipv6_addr_in": "2a03:1b20:5:f011::a01f",
+        "weight": 100,
+        "include_in_country": true,
+        "public_key": "hnRorSW0YHlHAzGb4Uc/sjOqQIrqDnpJnTQi/n7Rp1c="
+    }"#;
+
+    /// Return a relay list with the given ETag and relays, in the format read from files.
+    fn relay_list_json(etag: Option<&str>, relays: &[&str]) -> String {
+        let etag = etag.map(|etag| format!(r#","etag":"{etag}""#));
+        format!(
+            r#"{{
+                "locations": {{
+                    "se-got": {{
+                        "city": "Gothenburg",
+                        "country": "Sweden",
+                        "latitude": 57.70887,
+                        "longitude": 11.97456
+                    }}
+                }},
+                "wireguard": {{
+                    "port_ranges": [[53, 53], [4000, 33433]],
+                    "ipv4_gateway": "10.64.0.1",
+                    "ipv6_gateway": "fc00:bbbb:bbbb:bb01::1",
+                    "relays": [{}]
+                }},
+                "bridge": {{
+                    "shadowsocks": [],
+                    "relays": []
+                }}
+                {}
+            }}"#,
+            relays.join(","),
+            etag.unwrap_or_default(),
+        )
+    }
+
+    #[test]
+    fn test_import_missing_etag() {
+        let relay_list = relay_list_json(None, &[RELAY]);
+        let result = parse_imported_relay_list(&relay_list, None);
+        assert!(matches!(result, Err(Error::MissingETag)), "{result:?}");
+
+        let relay_list = relay_list_json(Some(" "), &[RELAY]);
+        let result = parse_imported_relay_list(&relay_list, None);
+        assert!(matches!(result, Err(Error::MissingETag)), "{result:?}");
+    }
+
+    #[test]
+    fn test_import_same_etag() {
+        let relay_list = relay_list_json(Some("abc"), &[RELAY]);
+        let current = ETag("abc".to_owned());
+        let result = parse_imported_relay_list(&relay_list, Some(&current));
+        assert!(matches!(result, Ok(None)), "{result:?}");
+    }
+
+    #[test]
+    fn test_import_empty_relay_list() {
+        let relay_list = relay_list_json(Some("abc"), &[]);
+        let result = parse_imported_relay_list(&relay_list, None);
+        assert!(matches!(result, Err(Error::EmptyRelayList)), "{result:?}");
+    }
+
+    #[test]
+    fn test_import_invalid_json() {
+        let result = parse_imported_relay_list("{\"etag\": \"abc\"", None);
+        assert!(matches!(result, Err(Error::Serialize(_))), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn test_import_relay_list() {
+        let relay_list = relay_list_json(Some("new"), &[RELAY]);
+        let current = ETag("old".to_owned());
+        let relay_list = parse_imported_relay_list(&relay_list, Some(&current))
+            .expect("relay list should be valid")
+            .expect("relay list should not be in use");
+
+        let cache_dir = tempfile::tempdir().unwrap();
+        let resource_dir = tempfile::tempdir().unwrap();
+        let source = RelayListSource::Imported {
+            path: "/tmp/relays.json".to_owned(),
+        };
+        write_relay_cache(
+            &cache_dir.path().join(RELAYS_FILENAME),
+            &cache_dir.path().join(RELAY_LIST_SOURCE_FILENAME),
+            &relay_list,
+            &source,
+        )
+        .await
+        .unwrap();
+
+        let (cached, cached_source) =
+            parse_relays_with_source_from_file(cache_dir.path(), resource_dir.path()).unwrap();
+        assert_eq!(cached.etag().map(|etag| etag.0.as_str()), Some("new"));
+        assert_eq!(cached_source, source);
+        let (relays, _) = cached.into_internal_repr();
+        assert_eq!(relays.relays().count(), 1);
+    }
+}
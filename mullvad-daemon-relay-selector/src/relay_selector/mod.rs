@@ -136,6 +136,24 @@ impl RelaySelectorIO {
         self.get_relay_with_custom_params(retry_attempt, &RETRY_ORDER, runtime_ip_availability)
     }
 
+    /// Like [`Self::get_relay`], but tries `hint` before the rest of [`RETRY_ORDER`]. See
+    /// [`hinted_retry_order`].
+    ///
+    /// Also returns the retry entry that the relay was selected with, or `None` if no retry entry
+    /// was compatible with the user's preferences.
+    pub fn get_relay_with_hint(
+        &self,
+        retry_attempt: usize,
+        hint: Option<EntrySpecificConstraints>,
+        runtime_ip_availability: IpAvailability,
+    ) -> Result<(GetRelay, Option<EntrySpecificConstraints>), Error> {
+        let retry_order = match hint {
+            Some(hint) => hinted_retry_order(hint),
+            None => RETRY_ORDER.clone(),
+        };
+        self.get_relay_and_retry_entry(retry_attempt, &retry_order, runtime_ip_availability)
+    }
+
     /// Returns a random relay and relay endpoint matching the current constraints defined by
     /// `retry_order` corresponding to `retry_attempt`.
     pub fn get_relay_with_custom_params(
@@ -144,6 +162,16 @@ impl RelaySelectorIO {
         retry_order: &[EntrySpecificConstraints],
         runtime_ip_availability: IpAvailability,
     ) -> Result<GetRelay, Error> {
+        self.get_relay_and_retry_entry(retry_attempt, retry_order, runtime_ip_availability)
+            .map(|(relay, _retry)| relay)
+    }
+
+    fn get_relay_and_retry_entry(
+        &self,
+        retry_attempt: usize,
+        retry_order: &[EntrySpecificConstraints],
+        runtime_ip_availability: IpAvailability,
+    ) -> Result<(GetRelay, Option<EntrySpecificConstraints>), Error> {
         let mut user_query = self.config.query.lock().unwrap().clone();
         // Runtime parameters may shrink the set of usable IP versions — apply that *before*
         // merging with retry_order so an IPv6-only retry attempt is correctly rejected when only
@@ -155,8 +183,11 @@ impl RelaySelectorIO {
         // looping back to the start if necessary.
         let maybe_relay = retry_order
             .iter()
-            .filter_map(|retry| user_query.clone().merge_retry(retry.clone()))
-            .filter_map(|query| self.get_relay_by_query(query).ok())
+            .filter_map(|retry| {
+                let query = user_query.clone().merge_retry(retry.clone())?;
+                let relay = self.get_relay_by_query(query).ok()?;
+                Some((relay, Some(retry.clone())))
+            })
             .cycle()
             .nth(retry_attempt);
 
@@ -164,11 +195,20 @@ impl RelaySelectorIO {
             Some(v) => Ok(v),
             // If no retry merged with `user_query` yields a relay, fall back to the user's
             // preferences alone.
-            None => self.get_relay_by_query(user_query),
+            None => self
+                .get_relay_by_query(user_query)
+                .map(|relay| (relay, None)),
         }
     }
 }
 
+/// Returns [`RETRY_ORDER`] with `hint` moved to the front. The hint is typically the retry entry
+/// that last managed to establish a tunnel on the current network.
+pub fn hinted_retry_order(hint: EntrySpecificConstraints) -> Vec<EntrySpecificConstraints> {
+    let rest = RETRY_ORDER.iter().filter(|retry| **retry != hint).cloned();
+    std::iter::once(hint).chain(rest).collect()
+}
+
 /// Relay selector configuration. This datastructure keeps the relay selector in sync with
 /// mullvad-daemon.
 ///
@@ -256,4 +296,26 @@ mod test {
     "
         );
     }
+
+    #[test]
+    fn test_hinted_retry_order() {
+        let hint = EntrySpecificConstraints::quic();
+        let retry_order = hinted_retry_order(hint.clone());
+
+        assert_eq!(retry_order.len(), RETRY_ORDER.len());
+        assert_eq!(retry_order[0], hint);
+        assert_eq!(
+            retry_order.iter().filter(|retry| **retry == hint).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_hinted_retry_order_unknown_entry() {
+        let hint = EntrySpecificConstraints::lwo().ip_version(IpVersion::V6);
+        let retry_order = hinted_retry_order(hint.clone());
+
+        assert_eq!(retry_order[0], hint);
+        assert_eq!(retry_order[1..], RETRY_ORDER[..]);
+    }
 }
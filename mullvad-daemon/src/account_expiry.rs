@@ -0,0 +1,264 @@
+//! Warns clients when the account is about to run out of time, and when it has run out of time.
+//!
+//! The expiry is fetched again whenever one of the configured thresholds is passed, so that no
+//! warning is sent if time has been added to the account in the meantime.
+
+use crate::{
+    DaemonEventSender,
+    device::{AccountEvent, AccountManagerHandle, PrivateDeviceEvent},
+};
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, channel::mpsc};
+use mullvad_types::account::{AccountExpiryWarning, ExpiryWarningThresholds};
+use std::time::Duration;
+use talpid_core::mpsc::Sender;
+use talpid_types::ErrorExt;
+
+/// Longest time to sleep before comparing the expiry against the system clock again. Timers may
+/// not advance while the system is suspended, so they cannot be relied upon for long periods.
+const MAX_SLEEP: Duration = Duration::from_secs(60 * 60);
+
+/// How long to wait before fetching the expiry again after failing to do so, in minutes.
+const RETRY_INTERVAL_MINUTES: i64 = 15;
+
+enum Command {
+    SetThresholds(ExpiryWarningThresholds),
+    AccountEvent(AccountEvent),
+}
+
+#[derive(Clone)]
+pub(crate) struct ExpiryMonitorHandle {
+    tx: mpsc::UnboundedSender<Command>,
+}
+
+impl ExpiryMonitorHandle {
+    pub fn set_thresholds(&self, thresholds: ExpiryWarningThresholds) {
+        let _ = self.tx.unbounded_send(Command::SetThresholds(thresholds));
+    }
+
+    /// Notify the monitor of logins, logouts and new expiry dates.
+    pub fn handle_account_event(&self, event: AccountEvent) {
+        let _ = self.tx.unbounded_send(Command::AccountEvent(event));
+    }
+}
+
+/// Spawn a task that sends an [`AccountExpiryWarning`] to the daemon whenever the account passes
+/// one of `thresholds`. The task stops when the daemon goes away.
+pub(crate) fn spawn(
+    account_manager: AccountManagerHandle,
+    logged_in: bool,
+    thresholds: ExpiryWarningThresholds,
+    event_tx: DaemonEventSender<AccountExpiryWarning>,
+) -> ExpiryMonitorHandle {
+    let (tx, rx) = mpsc::unbounded();
+    let monitor = ExpiryMonitor {
+        account_manager,
+        event_tx,
+        thresholds,
+        logged_in,
+        expiry: None,
+        warned: None,
+        retry_at: None,
+    };
+    tokio::spawn(monitor.run(rx));
+    ExpiryMonitorHandle { tx }
+}
+
+struct ExpiryMonitor {
+    account_manager: AccountManagerHandle,
+    event_tx: DaemonEventSender<AccountExpiryWarning>,
+    thresholds: ExpiryWarningThresholds,
+    logged_in: bool,
+    /// The last known expiry of the account, if logged in.
+    expiry: Option<DateTime<Utc>>,
+    /// The smallest threshold, in hours, that a warning has been sent for since the expiry last
+    /// changed.
+    warned: Option<u32>,
+    /// Do not try to fetch the expiry before this time, since the last attempt failed.
+    retry_at: Option<DateTime<Utc>>,
+}
+
+impl ExpiryMonitor {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Command>) {
+        loop {
+            if self.check().await.is_err() {
+                return;
+            }
+
+            let sleep = tokio::time::sleep(self.time_until_next_check(Utc::now()));
+            tokio::select! {
+                command = rx.next() => match command {
+                    Some(command) => self.handle_command(command),
+                    None => return,
+                },
+                _ = sleep => (),
+            }
+        }
+    }
+
+    fn handle_command(&mut self, command: Command) {
+        match command {
+            Command::SetThresholds(thresholds) => self.thresholds = thresholds,
+            Command::AccountEvent(AccountEvent::Device(PrivateDeviceEvent::Login(_))) => {
+                self.logged_in = true;
+                self.expiry = None;
+                self.warned = None;
+                self.retry_at = None;
+            }
+            Command::AccountEvent(AccountEvent::Device(
+                PrivateDeviceEvent::Logout | PrivateDeviceEvent::Revoked,
+            )) => {
+                self.logged_in = false;
+                self.expiry = None;
+                self.warned = None;
+            }
+            Command::AccountEvent(AccountEvent::Expiry(expiry)) => {
+                if self.logged_in {
+                    self.set_expiry(expiry, Utc::now());
+                }
+            }
+            Command::AccountEvent(AccountEvent::Device(_)) => (),
+        }
+    }
+
+    /// Update the known expiry. If the expiry changed, warnings are sent again for thresholds
+    /// that are passed from now on, but not for the ones that have already been passed.
+    fn set_expiry(&mut self, expiry: DateTime<Utc>, now: DateTime<Utc>) {
+        match self.expiry {
+            Some(old_expiry) if old_expiry == expiry => return,
+            Some(_) => self.warned = passed_threshold(&self.thresholds, expiry, now),
+            // Warn immediately about the expiry that is learned first
+            None => self.warned = None,
+        }
+        self.expiry = Some(expiry);
+    }
+
+    /// Fetch the expiry if it is unknown or a threshold has been passed, and send a warning if
+    /// one is due. Returns an error if the daemon has gone away.
+    async fn check(&mut self) -> Result<(), ()> {
+        if !self.logged_in {
+            return Ok(());
+        }
+
+        let now = Utc::now();
+        let needs_fetch = match self.expiry {
+            Some(expiry) => self.due_threshold(expiry, now).is_some(),
+            None => true,
+        };
+        if needs_fetch && self.retry_at.is_none_or(|retry_at| now >= retry_at) {
+            match self.account_manager.check_expiry().await {
+                Ok(expiry) => {
+                    self.retry_at = None;
+                    self.set_expiry(expiry, Utc::now());
+                }
+                Err(error) => {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to check account expiry")
+                    );
+                    self.retry_at = Some(now + chrono::Duration::minutes(RETRY_INTERVAL_MINUTES));
+                }
+            }
+        }
+
+        let Some(expiry) = self.expiry else {
+            return Ok(());
+        };
+        if let Some(threshold_hours) = self.due_threshold(expiry, Utc::now()) {
+            self.warned = Some(threshold_hours);
+            let warning = AccountExpiryWarning {
+                expiry,
+                threshold_hours,
+            };
+            self.event_tx.send(warning).map_err(|_| ())?;
+        }
+        Ok(())
+    }
+
+    /// Return the passed threshold that a warning should be sent for, if any.
+    fn due_threshold(&self, expiry: DateTime<Utc>, now: DateTime<Utc>) -> Option<u32> {
+        passed_threshold(&self.thresholds, expiry, now)
+            .filter(|threshold| self.warned.is_none_or(|warned| *threshold < warned))
+    }
+
+    fn time_until_next_check(&self, now: DateTime<Utc>) -> Duration {
+        let next_check = if !self.logged_in {
+            None
+        } else if let Some(expiry) = self.expiry {
+            next_threshold_time(&self.thresholds, expiry, self.warned, now)
+        } else {
+            Some(self.retry_at.unwrap_or(now))
+        };
+        next_check
+            .map(|next_check| (next_check - now).to_std().unwrap_or_default())
+            .unwrap_or(MAX_SLEEP)
+            .min(MAX_SLEEP)
+    }
+}
+
+/// Return the thresholds in hours, including `0` for when the account has expired.
+fn thresholds_and_expiry(thresholds: &ExpiryWarningThresholds) -> impl Iterator<Item = u32> + '_ {
+    thresholds.hours().iter().copied().chain([0])
+}
+
+/// Return the smallest threshold, in hours, that has been passed at `now`.
+fn passed_threshold(
+    thresholds: &ExpiryWarningThresholds,
+    expiry: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Option<u32> {
+    thresholds_and_expiry(thresholds)
+        .filter(|hours| now >= expiry - chrono::Duration::hours(i64::from(*hours)))
+        .min()
+}
+
+/// Return the time when the next threshold that is smaller than `warned` is passed, if any.
+fn next_threshold_time(
+    thresholds: &ExpiryWarningThresholds,
+    expiry: DateTime<Utc>,
+    warned: Option<u32>,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    thresholds_and_expiry(thresholds)
+        .filter(|hours| warned.is_none_or(|warned| *hours < warned))
+        .map(|hours| expiry - chrono::Duration::hours(i64::from(hours)))
+        .filter(|time| *time > now)
+        .min()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_passed_threshold() {
+        let thresholds = ExpiryWarningThresholds::new([72, 24]);
+        let now = Utc::now();
+        let hours = |hours| chrono::Duration::hours(hours);
+
+        assert_eq!(passed_threshold(&thresholds, now + hours(100), now), None);
+        assert_eq!(
+            passed_threshold(&thresholds, now + hours(72), now),
+            Some(72)
+        );
+        assert_eq!(passed_threshold(&thresholds, now + hours(5), now), Some(24));
+        assert_eq!(passed_threshold(&thresholds, now - hours(1), now), Some(0));
+    }
+
+    #[test]
+    fn test_next_threshold_time() {
+        let thresholds = ExpiryWarningThresholds::new([72, 24]);
+        let now = Utc::now();
+        let expiry = now + chrono::Duration::hours(48);
+
+        assert_eq!(
+            next_threshold_time(&thresholds, expiry, Some(72), now),
+            Some(now + chrono::Duration::hours(24))
+        );
+        assert_eq!(
+            next_threshold_time(&thresholds, expiry, Some(24), now),
+            Some(expiry)
+        );
+        assert_eq!(next_threshold_time(&thresholds, expiry, Some(0), now), None);
+    }
+}
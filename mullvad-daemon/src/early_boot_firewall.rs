@@ -1,4 +1,8 @@
-use mullvad_daemon::settings::{self, SettingsPersister};
+use mullvad_daemon::{
+    network_ids::NetworkIdentifiers,
+    settings::{self, SettingsPersister},
+};
+use mullvad_types::settings::Settings;
 use talpid_core::firewall::{self, Firewall, FirewallPolicy};
 
 #[derive(thiserror::Error, Debug)]
@@ -14,28 +18,39 @@ pub enum Error {
 }
 
 pub async fn initialize_firewall() -> Result<(), Error> {
-    let mut firewall = Firewall::new(mullvad_types::TUNNEL_FWMARK, None, None)?;
-    let allow_lan = get_allow_lan().await.unwrap_or_else(|err| {
+    let network_ids = NetworkIdentifiers::from_env_vars().unwrap_or_else(|err| {
+        log::warn!("Using the default firewall marks: {}", err);
+        NetworkIdentifiers::default()
+    });
+    let mut firewall = Firewall::new(
+        network_ids.fwmark,
+        network_ids.split_tunnel_mark,
+        None,
+        None,
+    )?;
+    let settings = get_settings().await.unwrap_or_else(|err| {
         log::info!(
-            "Not allowing LAN traffic due to failing to read settings: {}",
+            "Not allowing LAN traffic or firewall exceptions due to failing to read settings: {}",
             err
         );
-        false
+        Settings::default()
     });
     let policy = FirewallPolicy::Blocked {
-        allow_lan,
+        allow_lan: settings.allow_lan,
         allowed_endpoint: None,
+        exceptions: settings.firewall_exceptions,
+        lan_gateway: settings.lan_gateway,
+        trusted_interfaces: settings.trusted_interfaces,
     };
     log::info!("Applying firewall policy {policy}");
     firewall.apply_policy(policy)?;
     Ok(())
 }
 
-async fn get_allow_lan() -> Result<bool, Error> {
+async fn get_settings() -> Result<Settings, Error> {
     let path = mullvad_paths::settings_dir()?;
     // NOTE: This may fail if the daemon has not been restarted after an upgrade.
-    //       This will cause `allow_lan` to be disabled during early boot. This
-    //       is probably acceptable.
-    let settings = SettingsPersister::read_only(&path).await;
-    Ok(settings.allow_lan)
+    //       This will cause `allow_lan` and firewall exceptions to be disabled during
+    //       early boot. This is probably acceptable.
+    Ok(SettingsPersister::read_only(&path).await)
 }
@@ -0,0 +1,65 @@
+//! Detects when the firewall rules applied by the tunnel state machine are changed by another
+//! program, such as firewalld, docker or ufw.
+
+use crate::DaemonEventSender;
+use futures::channel::{mpsc, oneshot};
+use std::{sync::Weak, time::Duration};
+use talpid_core::{firewall::FirewallDrift, mpsc::Sender, tunnel_state_machine::TunnelCommand};
+use talpid_types::ErrorExt;
+use tokio::time::MissedTickBehavior;
+
+/// How often the active firewall rules are compared against the expected rules.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Spawn a task that periodically checks that the firewall rules match the last applied policy,
+/// and sends an event to the daemon when they stop doing so. No further events are sent until the
+/// rules have matched the policy again. The task stops when the tunnel state machine or the daemon
+/// goes away.
+pub fn spawn(
+    tunnel_command_tx: Weak<mpsc::UnboundedSender<TunnelCommand>>,
+    event_tx: DaemonEventSender<FirewallDrift>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately
+        interval.tick().await;
+        let mut drifted = false;
+
+        loop {
+            interval.tick().await;
+
+            let (result_tx, result_rx) = oneshot::channel();
+            let Some(command_tx) = tunnel_command_tx.upgrade() else {
+                return;
+            };
+            if command_tx
+                .unbounded_send(TunnelCommand::VerifyFirewall(result_tx))
+                .is_err()
+            {
+                return;
+            }
+            drop(command_tx);
+
+            match result_rx.await {
+                Ok(Ok(Some(drift))) => {
+                    if !drifted && event_tx.send(drift).is_err() {
+                        return;
+                    }
+                    drifted = true;
+                }
+                Ok(Ok(None)) => {
+                    if drifted {
+                        log::info!("Firewall rules match the applied policy again");
+                    }
+                    drifted = false;
+                }
+                Ok(Err(error)) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to verify firewall rules")
+                ),
+                Err(_) => return,
+            }
+        }
+    });
+}
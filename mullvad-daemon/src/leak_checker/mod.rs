@@ -174,7 +174,7 @@ async fn check_for_leaks(
     let interface = {
         // By setting FWMARK, we are effectively getting the same route as when using split tunneling.
         let route = route_manager
-            .get_destination_route(destination.address.ip(), Some(mullvad_types::TUNNEL_FWMARK))
+            .get_destination_route(destination.address.ip(), Some(route_manager.fwmark()))
             .await
             .context("Failed to get route to relay")?
             .ok_or(anyhow!("No route to relay"))?;
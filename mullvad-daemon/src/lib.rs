@@ -2,6 +2,7 @@
 #![allow(rustdoc::private_intra_doc_links)]
 
 mod access_method;
+mod account_expiry;
 pub mod account_history;
 mod android_dns;
 mod api;
@@ -12,13 +13,20 @@ mod custom_list;
 pub mod device;
 mod dns;
 pub mod exception_logging;
+#[cfg(target_os = "linux")]
+mod firewall_monitor;
 mod geoip;
 mod leak_checker;
 pub mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
 pub mod management_interface;
+#[cfg(not(target_os = "android"))]
+mod metrics;
 mod migrations;
+pub mod network_hints;
+#[cfg(target_os = "linux")]
+pub mod network_ids;
 #[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
 pub mod runtime;
@@ -47,7 +55,8 @@ use mullvad_api::{
 };
 use mullvad_daemon_relay_selector::{
     relay_list::{
-        parsed_relays::parse_relays_from_file,
+        RELAYS_FILENAME,
+        parsed_relays::parse_relays_with_source_from_file,
         update::{RelayListUpdater, RelayListUpdaterHandle},
     },
     relay_selector::RelaySelectorIO,
@@ -55,25 +64,34 @@ use mullvad_daemon_relay_selector::{
 use mullvad_encrypted_dns_proxy::state::EncryptedDnsProxyState;
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayExternalObfuscatedAccountId, PlayPurchase};
+#[cfg(not(target_os = "android"))]
+use mullvad_types::metrics::MetricsEndpoint;
 #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
 use mullvad_types::settings::SplitApp;
 use mullvad_types::{
     access_method::{AccessMethod, AccessMethodSetting},
-    account::{AccountData, AccountNumber, VoucherSubmission},
+    account::{
+        AccountData, AccountExpiryWarning, AccountNumber, ExpiryWarningThresholds,
+        VoucherSubmission,
+    },
     auth_failed::AuthFailed,
     constraints::Constraint,
     custom_list::CustomList,
     device::{Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceState, RemoveDeviceEvent},
     features::{FeatureIndicator, FeatureIndicators, compute_feature_indicators},
     location::{GeoIpLocation, LocationEventData},
+    network_hints::NetworkHints,
     relay_constraints::{
         ObfuscationSettings, RelayOverride, RelaySettings, allowed_ip::AllowedIps,
     },
-    relay_list::RelayList,
-    settings::{DnsOptions, Settings, SettingsKeyList},
+    relay_list::{RelayList, RelayListInfo},
+    settings::{
+        DnsOptions, Settings, SettingsKey, SettingsKeyList, SettingsPatchPreview,
+        profile::SettingsProfile,
+    },
     states::{Secured, TargetState, TargetStateStrict, TunnelState},
     version::AppVersionInfo,
-    wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    wireguard::{PublicKey, QuantumResistantState, RekeyInterval, RotationInterval},
 };
 use mullvad_types::{
     relay_constraints::{
@@ -94,25 +112,29 @@ use std::{
     path::PathBuf,
     pin::Pin,
     sync::{Arc, Weak},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 #[cfg(target_os = "android")]
 use talpid_core::connectivity_listener::ConnectivityListener;
+#[cfg(target_os = "linux")]
+use talpid_core::firewall::{self, FirewallDrift, FirewallRules};
 #[cfg(not(target_os = "android"))]
 use talpid_core::tunnel_state_machine::LockdownMode;
 use talpid_core::{
     mpsc::Sender,
     split_tunnel,
-    tunnel_state_machine::{self, TunnelCommand, TunnelStateMachineHandle},
+    tunnel_state_machine::{self, TunnelCommand, TunnelStateMachineHandle, tunnel_capture},
 };
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "android")]
 use talpid_types::android::AndroidContext;
+#[cfg(target_os = "linux")]
+use talpid_types::net::{DnsBackend, FirewallException, InboundPort, LanGateway, TrustedInterface};
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 use talpid_types::{
     ErrorExt,
-    net::{IpVersion, proxy::ShadowsocksCipher},
+    net::{IpVersion, TunnelCaptureSettings, proxy::ShadowsocksCipher, wireguard::DaitaLevel},
     tunnel::{ErrorStateCause, TunnelStateTransition},
 };
 use tokio::io;
@@ -204,6 +226,15 @@ pub enum Error {
     #[error("Account history error")]
     AccountHistory(#[source] account_history::Error),
 
+    #[error("Network hints error")]
+    NetworkHints(#[source] network_hints::Error),
+
+    #[error("Tunnel capture error")]
+    TunnelCapture(#[source] tunnel_capture::Error),
+
+    #[error("No log directory is configured")]
+    NoLogDirectory,
+
     #[cfg(not(target_os = "android"))]
     #[error("Factory reset partially failed: {0}")]
     FactoryResetError(&'static str),
@@ -211,6 +242,17 @@ pub enum Error {
     #[error("Tunnel state machine error")]
     TunnelError(#[source] tunnel_state_machine::Error),
 
+    #[error("Relay list error")]
+    RelayListError(#[source] mullvad_daemon_relay_selector::relay_list::error::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Metrics server error")]
+    MetricsServer(#[source] metrics::Error),
+
+    #[cfg(target_os = "linux")]
+    #[error("Failed to read firewall rules")]
+    FirewallRules(#[source] firewall::Error),
+
     /// Errors from [talpid_routing::RouteManagerHandle].
     #[error("Route manager error")]
     RouteManager(#[source] talpid_routing::Error),
@@ -262,6 +304,10 @@ pub enum DaemonCommand {
     GetAccountHistory(oneshot::Sender<Option<AccountNumber>>),
     /// Remove the last used account, if there is one
     ClearAccountHistory(ResponseTx<(), Error>),
+    /// Get the entry parameters that last established a tunnel on each known network
+    GetNetworkHints(oneshot::Sender<NetworkHints>),
+    /// Forget the entry parameters remembered for all networks
+    ClearNetworkHints(ResponseTx<(), Error>),
     /// Get the list of countries and cities where there are relays.
     GetRelayLocations(oneshot::Sender<RelayList>),
     /// Delete the account and log out the user
@@ -270,6 +316,11 @@ pub enum DaemonCommand {
     /// Trigger an asynchronous relay list update. This returns before the relay list is actually
     /// updated.
     UpdateRelayLocations,
+    /// Replace the relay list with one read from a file. The arguments are the contents and the
+    /// path of the file.
+    ImportRelayList(ResponseTx<RelayListInfo, Error>, String, String),
+    /// Return where the relay list was obtained from
+    GetRelayListInfo(ResponseTx<RelayListInfo, Error>),
     /// Get the list of bridges.
     GetBridges(oneshot::Sender<BridgeList>),
     /// Log in with a given account and create a new device.
@@ -293,8 +344,37 @@ pub enum DaemonCommand {
     /// Set the lockdown_mode setting.
     #[cfg(not(target_os = "android"))]
     SetLockdownMode(ResponseTx<(), settings::Error>, bool),
+    /// Set the tunnel namespace setting.
+    #[cfg(target_os = "linux")]
+    SetTunnelNamespace(ResponseTx<(), settings::Error>, bool),
+    /// Set the outbound traffic that is allowed outside the tunnel
+    #[cfg(target_os = "linux")]
+    SetFirewallExceptions(ResponseTx<(), settings::Error>, Vec<FirewallException>),
+    /// Set the ports on the tunnel interface that accept incoming connections
+    #[cfg(target_os = "linux")]
+    SetInboundPorts(ResponseTx<(), settings::Error>, Vec<InboundPort>),
+    /// Set the local interfaces and subnets whose traffic is forwarded through the tunnel
+    #[cfg(target_os = "linux")]
+    SetLanGateway(ResponseTx<(), settings::Error>, Option<LanGateway>),
+    /// Set the interfaces of other VPN or mesh network clients that are used alongside the tunnel
+    #[cfg(target_os = "linux")]
+    SetTrustedInterfaces(ResponseTx<(), settings::Error>, Vec<TrustedInterface>),
+    /// Set how DNS is configured while connected
+    #[cfg(target_os = "linux")]
+    SetDnsBackend(ResponseTx<(), settings::Error>, DnsBackend),
+    /// Set whether to apply the firewall rules again when another program changes them
+    #[cfg(target_os = "linux")]
+    SetReapplyFirewallOnDrift(ResponseTx<(), settings::Error>, bool),
+    /// Return the expected and the active firewall rules
+    #[cfg(target_os = "linux")]
+    GetFirewallRules(ResponseTx<FirewallRules, Error>),
     /// Set the auto-connect setting.
     SetAutoConnect(ResponseTx<(), settings::Error>, bool),
+    /// Set how long before the account expires clients are warned
+    SetExpiryWarningThresholds(ResponseTx<(), settings::Error>, ExpiryWarningThresholds),
+    /// Set where metrics are served, or stop serving them
+    #[cfg(not(target_os = "android"))]
+    SetMetricsEndpoint(ResponseTx<(), Error>, Option<MetricsEndpoint>),
     /// Set if IPv6 should be enabled in the tunnel
     SetEnableIpv6(ResponseTx<(), settings::Error>, bool),
     /// Set if userspace WireGuard should be forced.
@@ -303,8 +383,14 @@ pub enum DaemonCommand {
     SetEnableRecents(ResponseTx<(), settings::Error>, bool),
     /// Set whether to enable PQ PSK exchange in the tunnel
     SetQuantumResistantTunnel(ResponseTx<(), settings::Error>, QuantumResistantState),
+    /// Set how often the PQ PSK is renegotiated while connected
+    SetQuantumResistantRekeyInterval(ResponseTx<(), settings::Error>, Option<RekeyInterval>),
+    /// Get when the PQ PSK of the current tunnel was last renegotiated
+    GetLastQuantumResistantRekey(oneshot::Sender<Option<SystemTime>>),
     /// Set DAITA settings for the tunnel
     SetEnableDaita(ResponseTx<(), settings::Error>, bool),
+    /// Set the DAITA level to request from relays
+    SetDaitaLevel(ResponseTx<(), settings::Error>, Option<DaitaLevel>),
     /// Set DNS options or servers to use
     SetDnsOptions(ResponseTx<(), settings::Error>, DnsOptions),
     /// Set override options to use for a given relay
@@ -322,6 +408,16 @@ pub enum DaemonCommand {
     GetSettings(oneshot::Sender<Settings>),
     /// Reset all daemon settings to the defaults
     ResetSettings(ResponseTx<(), settings::Error>, SettingsKeyList),
+    /// Get all settings profiles
+    ListSettingsProfiles(oneshot::Sender<Vec<SettingsProfile>>),
+    /// Save the current values of some settings as a new profile
+    CreateSettingsProfile(ResponseTx<(), settings::Error>, String, Vec<SettingsKey>),
+    /// Delete a settings profile
+    DeleteSettingsProfile(ResponseTx<(), settings::Error>, String),
+    /// Return the settings that would change if a profile was activated
+    DiffSettingsProfile(ResponseTx<Vec<SettingsKey>, settings::Error>, String),
+    /// Apply all settings in a profile at once
+    ActivateSettingsProfile(ResponseTx<(), settings::Error>, String),
     /// Generate new wireguard key
     RotateWireguardKey(ResponseTx<(), Error>),
     /// Return a public key of the currently set wireguard private key, if there is one
@@ -426,6 +522,8 @@ pub enum DaemonCommand {
     VerifyPlayPurchase(ResponseTx<(), Error>, PlayPurchase),
     /// Patch the settings using a JSON patch
     ApplyJsonSettings(ResponseTx<(), settings::patch::Error>, String),
+    /// Validate a JSON patch and return the settings it would change, without applying it
+    PreviewJsonSettings(oneshot::Sender<SettingsPatchPreview>, String),
     /// Return a JSON blob containing all overridable settings, if there are any
     ExportJsonSettings(ResponseTx<String, settings::patch::Error>),
     /// Request the current feature indicators.
@@ -456,6 +554,11 @@ pub enum DaemonCommand {
         seed: u32,
         tx: oneshot::Sender<()>,
     },
+    /// Start capturing userspace WireGuard tunnel packets to a file in the log directory. Returns
+    /// the path of the capture file.
+    StartTunnelCapture(ResponseTx<PathBuf, Error>, TunnelCaptureSettings),
+    /// Stop the active tunnel capture, if any.
+    StopTunnelCapture(oneshot::Sender<()>),
 
     // App upgrade
     /// Prompt the daemon to start an app version upgrade.
@@ -506,6 +609,14 @@ pub(crate) enum InternalDaemonEvent {
     ExcludedPathsEvent(ExcludedPathsUpdate, oneshot::Sender<Result<(), Error>>),
     /// A network leak was detected.
     LeakDetected(LeakInfo),
+    /// The firewall rules were changed by someone else.
+    #[cfg(target_os = "linux")]
+    FirewallDrift(FirewallDrift),
+    /// The account is about to run out of time, or has run out of time.
+    AccountExpiryWarning(AccountExpiryWarning),
+    /// The metrics are being scraped.
+    #[cfg(not(target_os = "android"))]
+    ScrapeMetrics(metrics::ScrapeRequest),
 }
 
 #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
@@ -547,6 +658,26 @@ impl From<(AccessMethodEvent, oneshot::Sender<()>)> for InternalDaemonEvent {
     }
 }
 
+#[cfg(not(target_os = "android"))]
+impl From<metrics::ScrapeRequest> for InternalDaemonEvent {
+    fn from(request: metrics::ScrapeRequest) -> Self {
+        InternalDaemonEvent::ScrapeMetrics(request)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<FirewallDrift> for InternalDaemonEvent {
+    fn from(drift: FirewallDrift) -> Self {
+        InternalDaemonEvent::FirewallDrift(drift)
+    }
+}
+
+impl From<AccountExpiryWarning> for InternalDaemonEvent {
+    fn from(warning: AccountExpiryWarning) -> Self {
+        InternalDaemonEvent::AccountExpiryWarning(warning)
+    }
+}
+
 impl From<LocationEventData> for InternalDaemonEvent {
     fn from(location_event: LocationEventData) -> Self {
         InternalDaemonEvent::LocationEvent(location_event)
@@ -692,6 +823,7 @@ pub struct Daemon {
     account_history: account_history::AccountHistory,
     device_checker: device::TunnelStateChangeHandler,
     account_manager: device::AccountManagerHandle,
+    expiry_monitor: account_expiry::ExpiryMonitorHandle,
     access_mode_handler: mullvad_api::access_mode::AccessModeSelectorHandle,
     api_runtime: mullvad_api::Runtime,
     api_handle: mullvad_api::rest::MullvadRestHandle,
@@ -699,13 +831,18 @@ pub struct Daemon {
     relay_selector: RelaySelectorIO,
     relay_list_updater: RelayListUpdaterHandle,
     parameters_generator: tunnel::ParametersGenerator,
+    network_hints: network_hints::NetworkHintStore,
     shutdown_tasks: Vec<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
     tunnel_state_machine_handle: TunnelStateMachineHandle,
     #[cfg(target_os = "windows")]
     volume_update_tx: mpsc::UnboundedSender<()>,
     location_handler: GeoIpHandler,
     leak_checker: LeakChecker,
+    /// Serves metrics and keeps the counters, if enabled.
+    #[cfg(not(target_os = "android"))]
+    metrics: Option<metrics::MetricsServer>,
     cache_dir: PathBuf,
+    log_dir: Option<PathBuf>,
 }
 pub struct DaemonConfig {
     pub log_dir: Option<PathBuf>,
@@ -716,6 +853,8 @@ pub struct DaemonConfig {
     pub endpoint: ApiEndpoint,
     #[cfg(target_os = "android")]
     pub android_context: AndroidContext,
+    #[cfg(target_os = "linux")]
+    pub network_ids: network_ids::NetworkIdentifiers,
     pub log_handle: logging::LogHandle,
 }
 
@@ -729,9 +868,10 @@ impl Daemon {
 
         // Initialize relay selector asap, since it's a pre-requisite for accepting incoming gRPC
         // connections.
-        let initial_relay_list = parse_relays_from_file(&config.cache_dir, &config.resource_dir)
-            .inspect_err(|err| log::error!("{err}"))
-            .ok();
+        let initial_relay_list =
+            parse_relays_with_source_from_file(&config.cache_dir, &config.resource_dir)
+                .inspect_err(|err| log::error!("{err}"))
+                .ok();
 
         let migration_data = migrations::migrate_all(
             &config.cache_dir,
@@ -755,7 +895,7 @@ impl Daemon {
         let relay_selector = {
             let (initial_relay_list, initial_bridge_list) = initial_relay_list
                 .clone()
-                .map(CachedRelayList::into_internal_repr)
+                .map(|(relay_list, _source)| CachedRelayList::into_internal_repr(relay_list))
                 .unwrap_or_default();
             // TODO: This should preferably be done once, by the relay list updater.
             let initial_relay_list =
@@ -775,6 +915,8 @@ impl Daemon {
             app_upgrade_broadcast.clone(),
             config.log_handle,
             relay_selector.clone(),
+            #[cfg(target_os = "linux")]
+            management_interface::load_access_policy(&config.settings_dir),
         )
         .map_err(Error::ManagementInterfaceError)?;
 
@@ -877,6 +1019,17 @@ impl Daemon {
         .await
         .map_err(Error::LoadAccountHistory)?;
 
+        let expiry_monitor = account_expiry::spawn(
+            account_manager.clone(),
+            data.device().is_some(),
+            settings.expiry_warning_thresholds.clone(),
+            internal_event_tx.to_specialized_sender(),
+        );
+        let expiry_monitor_handle = expiry_monitor.clone();
+        settings.register_change_listener(move |settings| {
+            expiry_monitor_handle.set_thresholds(settings.expiry_warning_thresholds.clone());
+        });
+
         let target_state = if settings.auto_connect {
             log::info!("Automatically connecting since auto-connect is turned on");
             PersistentTargetState::new_secured(&config.cache_dir).await
@@ -900,11 +1053,30 @@ impl Daemon {
         #[cfg(target_os = "linux")]
         let split_tunneling_pid_manager = split_tunnel::PidManager::default();
 
+        let route_manager = RouteManagerHandle::spawn(
+            #[cfg(target_os = "linux")]
+            config.network_ids.fwmark,
+            #[cfg(target_os = "linux")]
+            config.network_ids.table_id,
+            #[cfg(target_os = "linux")]
+            config.network_ids.exception_table_id,
+            #[cfg(target_os = "android")]
+            config.android_context.clone(),
+        )
+        .await
+        .map_err(Error::RouteManager)?;
+
+        let network_hints =
+            network_hints::NetworkHintStore::load(&config.cache_dir, route_manager.clone()).await;
+
         let parameters_generator = tunnel::ParametersGenerator::new(
             account_manager.clone(),
             relay_selector.clone(),
             settings.relay_settings.clone(),
             settings.tunnel_options.clone(),
+            network_hints.clone(),
+            #[cfg(target_os = "linux")]
+            config.network_ids.clone(),
         );
 
         let param_gen = parameters_generator.clone();
@@ -932,17 +1104,6 @@ impl Daemon {
             let _ = settings_changed_event_sender.send(InternalDaemonEvent::SettingsChanged);
         });
 
-        let route_manager = RouteManagerHandle::spawn(
-            #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_FWMARK,
-            #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_TABLE_ID,
-            #[cfg(target_os = "android")]
-            config.android_context.clone(),
-        )
-        .await
-        .map_err(Error::RouteManager)?;
-
         let (offline_state_tx, offline_state_rx) = mpsc::unbounded();
         #[cfg(target_os = "windows")]
         let (volume_update_tx, volume_update_rx) = mpsc::unbounded();
@@ -958,11 +1119,25 @@ impl Daemon {
                     .map_err(Error::ApiConnectionModeError)?
                     .endpoint,
                 reset_firewall: *target_state != TargetState::Secured,
+                #[cfg(target_os = "linux")]
+                tunnel_namespace: settings.tunnel_namespace,
+                #[cfg(target_os = "linux")]
+                firewall_exceptions: settings.firewall_exceptions.clone(),
+                #[cfg(target_os = "linux")]
+                inbound_ports: settings.inbound_ports.clone(),
+                #[cfg(target_os = "linux")]
+                lan_gateway: settings.lan_gateway.clone(),
+                #[cfg(target_os = "linux")]
+                trusted_interfaces: settings.trusted_interfaces.clone(),
+                #[cfg(target_os = "linux")]
+                dns_backend: settings.dns_backend,
+                #[cfg(target_os = "linux")]
+                reapply_firewall_on_drift: settings.reapply_firewall_on_drift,
                 #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
                 exclude_paths,
             },
             parameters_generator.clone(),
-            config.log_dir,
+            config.log_dir.clone(),
             config.resource_dir.clone(),
             internal_event_tx.to_specialized_sender(),
             offline_state_tx,
@@ -975,8 +1150,10 @@ impl Daemon {
             connectivity_listener.clone(),
             #[cfg(target_os = "linux")]
             tunnel_state_machine::LinuxNetworkingIdentifiers {
-                fwmark: mullvad_types::TUNNEL_FWMARK,
-                table_id: mullvad_types::TUNNEL_TABLE_ID,
+                tunnel_interface: config.network_ids.tunnel_interface.clone(),
+                fwmark: config.network_ids.fwmark,
+                table_id: config.network_ids.table_id,
+                split_tunnel_mark: config.network_ids.split_tunnel_mark,
                 excluded_cgroup2: split_tunneling_pid_manager.excluded_cgroup(),
                 net_cls: split_tunneling_pid_manager.net_cls_classid(),
             },
@@ -984,6 +1161,12 @@ impl Daemon {
         .await
         .map_err(Error::TunnelError)?;
 
+        #[cfg(target_os = "linux")]
+        firewall_monitor::spawn(
+            Arc::downgrade(tunnel_state_machine_handle.command_tx()),
+            internal_event_tx.to_specialized_sender(),
+        );
+
         api::forward_offline_state(api_availability.clone(), offline_state_rx);
 
         let relay_list_listener = management_interface.notifier().clone();
@@ -1088,6 +1271,7 @@ impl Daemon {
             account_history,
             device_checker: device::TunnelStateChangeHandler::new(account_manager.clone()),
             account_manager,
+            expiry_monitor,
             access_mode_handler,
             api_runtime,
             api_handle,
@@ -1095,13 +1279,17 @@ impl Daemon {
             relay_selector,
             relay_list_updater,
             parameters_generator,
+            network_hints,
             shutdown_tasks: vec![],
             tunnel_state_machine_handle,
             #[cfg(target_os = "windows")]
             volume_update_tx,
             location_handler,
             leak_checker,
+            #[cfg(not(target_os = "android"))]
+            metrics: None,
             cache_dir: config.cache_dir,
+            log_dir: config.log_dir,
         };
 
         api_availability.unsuspend();
@@ -1127,6 +1315,10 @@ impl Daemon {
     /// Consume the `Daemon` and run the main event loop. Blocks until an error happens or a
     /// shutdown event is received.
     pub async fn run(mut self) -> Result<(), Error> {
+        #[cfg(not(target_os = "android"))]
+        if let Err(error) = self.update_metrics_server().await {
+            log::error!("{}", error.display_chain());
+        }
         self.handle_initial_target_state();
         self.handle_events().await;
         self.disconnect_tunnel_and_wait().await;
@@ -1247,6 +1439,10 @@ impl Daemon {
             LocationEvent(location_data) => self.handle_location_event(location_data),
             SettingsChanged => {
                 self.update_feature_indicators_on_settings_changed();
+                #[cfg(not(target_os = "android"))]
+                if let Err(error) = self.update_metrics_server().await {
+                    log::error!("{}", error.display_chain());
+                }
             }
             #[cfg(any(target_os = "windows", target_os = "android", target_os = "macos"))]
             ExcludedPathsEvent(update, tx) => self.handle_new_excluded_paths(update, tx).await,
@@ -1255,6 +1451,11 @@ impl Daemon {
                 log::warn!("{leak_info:?}");
                 self.handle_leak_event(leak_info)
             }
+            #[cfg(target_os = "linux")]
+            FirewallDrift(drift) => self.handle_firewall_drift(drift),
+            AccountExpiryWarning(warning) => self.handle_account_expiry_warning(warning),
+            #[cfg(not(target_os = "android"))]
+            ScrapeMetrics(request) => self.handle_metrics_scrape(request).await,
         }
         should_stop
     }
@@ -1291,6 +1492,7 @@ impl Daemon {
                 }
             }
             TunnelStateTransition::Connected(endpoint) => {
+                self.parameters_generator.remember_network_hint().await;
                 let feature_indicators = compute_feature_indicators(
                     self.settings.settings(),
                     &endpoint,
@@ -1322,6 +1524,11 @@ impl Daemon {
 
         log::debug!("New tunnel state: {:?}", tunnel_state);
 
+        #[cfg(not(target_os = "android"))]
+        if let Some(metrics) = &mut self.metrics {
+            metrics.counters_mut().record_tunnel_state(&tunnel_state);
+        }
+
         match tunnel_state {
             TunnelState::Disconnected { .. } => {
                 self.api_handle.availability.reset_inactivity_timer();
@@ -1534,6 +1741,10 @@ impl Daemon {
             SubmitVoucher(tx, voucher) => self.on_submit_voucher(tx, voucher),
             GetRelayLocations(tx) => self.on_get_relay_locations(tx),
             UpdateRelayLocations => self.on_update_relay_locations().await,
+            ImportRelayList(tx, relay_list, path) => {
+                self.on_import_relay_list(tx, relay_list, path)
+            }
+            GetRelayListInfo(tx) => self.on_get_relay_list_info(tx),
             UpdateDefaultLocationCountry(tx) => self.on_update_default_location(tx).await,
             LoginAccount(tx, account_number) => self.on_login_account(tx, account_number),
             LogoutAccount(tx) => self.on_logout_account(tx),
@@ -1545,6 +1756,8 @@ impl Daemon {
             }
             GetAccountHistory(tx) => self.on_get_account_history(tx),
             ClearAccountHistory(tx) => self.on_clear_account_history(tx).await,
+            GetNetworkHints(tx) => self.on_get_network_hints(tx).await,
+            ClearNetworkHints(tx) => self.on_clear_network_hints(tx).await,
             SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update).await,
             SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan).await,
             SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled).await,
@@ -1552,7 +1765,36 @@ impl Daemon {
             SetLockdownMode(tx, lockdown_mode) => {
                 self.on_set_lockdown_mode(tx, lockdown_mode).await
             }
+            #[cfg(target_os = "linux")]
+            SetTunnelNamespace(tx, enabled) => self.on_set_tunnel_namespace(tx, enabled).await,
+            #[cfg(target_os = "linux")]
+            SetFirewallExceptions(tx, exceptions) => {
+                self.on_set_firewall_exceptions(tx, exceptions).await
+            }
+            #[cfg(target_os = "linux")]
+            SetInboundPorts(tx, inbound_ports) => {
+                self.on_set_inbound_ports(tx, inbound_ports).await
+            }
+            #[cfg(target_os = "linux")]
+            SetLanGateway(tx, lan_gateway) => self.on_set_lan_gateway(tx, lan_gateway).await,
+            #[cfg(target_os = "linux")]
+            SetTrustedInterfaces(tx, trusted_interfaces) => {
+                self.on_set_trusted_interfaces(tx, trusted_interfaces).await
+            }
+            #[cfg(target_os = "linux")]
+            SetDnsBackend(tx, backend) => self.on_set_dns_backend(tx, backend).await,
+            #[cfg(target_os = "linux")]
+            SetReapplyFirewallOnDrift(tx, enabled) => {
+                self.on_set_reapply_firewall_on_drift(tx, enabled).await
+            }
+            #[cfg(target_os = "linux")]
+            GetFirewallRules(tx) => self.on_get_firewall_rules(tx),
             SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect).await,
+            SetExpiryWarningThresholds(tx, thresholds) => {
+                self.on_set_expiry_warning_thresholds(tx, thresholds).await
+            }
+            #[cfg(not(target_os = "android"))]
+            SetMetricsEndpoint(tx, endpoint) => self.on_set_metrics_endpoint(tx, endpoint).await,
             SetEnableIpv6(tx, enable_ipv6) => self.on_set_enable_ipv6(tx, enable_ipv6).await,
             SetUserspaceWireguard(tx, userspace) => {
                 self.on_set_userspace_wireguard(tx, userspace).await
@@ -1564,7 +1806,15 @@ impl Daemon {
                 self.on_set_quantum_resistant_tunnel(tx, quantum_resistant_state)
                     .await
             }
+            SetQuantumResistantRekeyInterval(tx, interval) => {
+                self.on_set_quantum_resistant_rekey_interval(tx, interval)
+                    .await
+            }
+            GetLastQuantumResistantRekey(tx) => {
+                self.send_tunnel_command(TunnelCommand::GetLastRekey(tx))
+            }
             SetEnableDaita(tx, value) => self.on_set_daita_enabled(tx, value).await,
+            SetDaitaLevel(tx, level) => self.on_set_daita_level(tx, level).await,
             SetDnsOptions(tx, dns_servers) => self.on_set_dns_options(tx, dns_servers).await,
             SetRelayOverride(tx, relay_override) => {
                 self.on_set_relay_override(tx, relay_override).await
@@ -1579,6 +1829,13 @@ impl Daemon {
             }
             GetSettings(tx) => self.on_get_settings(tx),
             ResetSettings(tx, preserved) => self.on_reset_settings(tx, preserved).await,
+            ListSettingsProfiles(tx) => self.on_list_settings_profiles(tx),
+            CreateSettingsProfile(tx, name, keys) => {
+                self.on_create_settings_profile(tx, name, keys).await
+            }
+            DeleteSettingsProfile(tx, name) => self.on_delete_settings_profile(tx, name).await,
+            DiffSettingsProfile(tx, name) => self.on_diff_settings_profile(tx, name),
+            ActivateSettingsProfile(tx, name) => self.on_activate_settings_profile(tx, name).await,
             RotateWireguardKey(tx) => self.on_rotate_wireguard_key(tx),
             GetWireguardKey(tx) => self.on_get_wireguard_key(tx).await,
             CreateCustomList(tx, name, locations) => {
@@ -1639,6 +1896,7 @@ impl Daemon {
                 self.on_verify_play_purchase(tx, play_purchase)
             }
             ApplyJsonSettings(tx, blob) => self.on_apply_json_settings(tx, blob).await,
+            PreviewJsonSettings(tx, blob) => self.on_preview_json_settings(tx, blob),
             ExportJsonSettings(tx) => self.on_export_json_settings(tx),
             GetFeatureIndicators(tx) => self.on_get_feature_indicators(tx),
             DisableRelay { relay, tx } => self.on_toggle_relay(relay, false, tx),
@@ -1656,6 +1914,8 @@ impl Daemon {
                 self.set_rollout_threshold_seed(seed).await;
                 let _ = tx.send(());
             }
+            StartTunnelCapture(tx, settings) => self.on_start_tunnel_capture(tx, settings),
+            StopTunnelCapture(tx) => self.on_stop_tunnel_capture(tx),
             AppUpgrade(tx) => self.on_app_upgrade(tx).await,
             AppUpgradeAbort(tx) => self.on_app_upgrade_abort(tx).await,
             GetAppUpgradeCacheDir(tx) => self.on_get_app_upgrade_cache_dir(tx).await,
@@ -1674,10 +1934,92 @@ impl Daemon {
     }
 
     fn handle_leak_event(&mut self, leak: LeakInfo) {
+        #[cfg(not(target_os = "android"))]
+        if let Some(metrics) = &mut self.metrics {
+            metrics.counters_mut().record_leak();
+        }
         self.management_interface.notifier().notify_leak(leak);
     }
 
+    /// Start, stop or move the metrics server so that it matches the settings.
+    #[cfg(not(target_os = "android"))]
+    async fn update_metrics_server(&mut self) -> Result<(), Error> {
+        let endpoint = self.settings.metrics_endpoint.clone();
+        if self.metrics.as_ref().map(metrics::MetricsServer::endpoint) == endpoint.as_ref() {
+            return Ok(());
+        }
+        // Stop the old server first, so that the counters are not kept if the new one fails
+        self.metrics = None;
+        if let Some(endpoint) = endpoint {
+            let server = metrics::MetricsServer::start(
+                endpoint,
+                &self.cache_dir,
+                self.tx.to_specialized_sender(),
+            )
+            .await
+            .map_err(Error::MetricsServer)?;
+            self.metrics = Some(server);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn handle_metrics_scrape(&mut self, request: metrics::ScrapeRequest) {
+        let Some(server) = &self.metrics else {
+            return;
+        };
+        let counters = server.counters().clone();
+        let tunnel_state = metrics::tunnel_state_name(&self.tunnel_state);
+        let retry_attempt = self.parameters_generator.last_retry_attempt().await;
+        let relay_list_path = self.cache_dir.join(RELAYS_FILENAME);
+
+        let (stats_tx, stats_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::GetTunnelStats(stats_tx));
+
+        tokio::spawn(async move {
+            let relay_list_age = tokio::fs::metadata(relay_list_path)
+                .await
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.elapsed().ok());
+            request.respond(metrics::Snapshot {
+                counters,
+                tunnel_state,
+                retry_attempt,
+                tunnel_stats: stats_rx.await.ok().flatten(),
+                relay_list_age,
+            });
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    fn handle_firewall_drift(&mut self, drift: FirewallDrift) {
+        log::warn!(
+            "Firewall rules were changed by another program. Re-applied: {}",
+            drift.reapplied
+        );
+        self.management_interface
+            .notifier()
+            .notify_firewall_drift(drift);
+    }
+
+    fn handle_account_expiry_warning(&mut self, warning: AccountExpiryWarning) {
+        if warning.is_expired() {
+            log::warn!("The account ran out of time at {}", warning.expiry);
+        } else {
+            log::warn!(
+                "The account runs out of time at {}, in less than {} hours",
+                warning.expiry,
+                warning.threshold_hours
+            );
+        }
+        self.management_interface
+            .notifier()
+            .notify_account_expiry_warning(warning);
+    }
+
     async fn handle_device_event(&mut self, event: AccountEvent) {
+        self.expiry_monitor.handle_account_event(event.clone());
         match &event {
             AccountEvent::Device(PrivateDeviceEvent::Login(device)) => {
                 if let Err(error) = self
@@ -1803,6 +2145,9 @@ impl Daemon {
                 connection_mode,
                 endpoint,
             } => {
+                if let Some(metrics) = &mut self.metrics {
+                    metrics.counters_mut().record_access_method_change();
+                }
                 self.save_connection_mode_to_cache(connection_mode.clone());
                 // Update the firewall to exempt a new API endpoint.
                 let (completion_tx, completion_rx) = oneshot::channel();
@@ -2030,6 +2375,39 @@ impl Daemon {
         self.relay_list_updater.update().await;
     }
 
+    fn on_import_relay_list(
+        &mut self,
+        tx: ResponseTx<RelayListInfo, Error>,
+        relay_list: String,
+        path: String,
+    ) {
+        let mut relay_list_updater = self.relay_list_updater.clone();
+        tokio::spawn(async move {
+            let result = relay_list_updater
+                .import(relay_list, path)
+                .await
+                .map_err(Error::RelayListError);
+            if let Err(error) = &result {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to import relay list")
+                );
+            }
+            Self::oneshot_send(tx, result, "import_relay_list response");
+        });
+    }
+
+    fn on_get_relay_list_info(&mut self, tx: ResponseTx<RelayListInfo, Error>) {
+        let mut relay_list_updater = self.relay_list_updater.clone();
+        tokio::spawn(async move {
+            let result = relay_list_updater
+                .info()
+                .await
+                .map_err(Error::RelayListError);
+            Self::oneshot_send(tx, result, "get_relay_list_info response");
+        });
+    }
+
     async fn on_update_default_location(&mut self, tx: ResponseTx<(), settings::Error>) {
         log::debug!(
             "should_update_default_country: {}",
@@ -2201,6 +2579,23 @@ impl Daemon {
         Self::oneshot_send(tx, result, "clear_account_history response");
     }
 
+    async fn on_get_network_hints(&self, tx: oneshot::Sender<NetworkHints>) {
+        Self::oneshot_send(
+            tx,
+            self.network_hints.list().await,
+            "get_network_hints response",
+        );
+    }
+
+    async fn on_clear_network_hints(&self, tx: ResponseTx<(), Error>) {
+        let result = self
+            .network_hints
+            .clear()
+            .await
+            .map_err(Error::NetworkHints);
+        Self::oneshot_send(tx, result, "clear_network_hints response");
+    }
+
     fn on_get_version_info(&mut self, tx: oneshot::Sender<Result<AppVersionInfo, Error>>) {
         let handle = self.version_handle.clone();
         tokio::spawn(async move {
@@ -2255,6 +2650,14 @@ impl Daemon {
             last_error = Some("Failed to reset settings");
         }
 
+        if let Err(error) = self.settings.clear_profiles().await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to remove settings profiles")
+            );
+            last_error = Some("Failed to remove settings profiles");
+        }
+
         // Shut the daemon down.
         let _ = self.tx.send(InternalDaemonEvent::TriggerShutdown(false));
 
@@ -2645,143 +3048,417 @@ impl Daemon {
         }
     }
 
-    async fn on_set_auto_connect(
+    #[cfg(target_os = "linux")]
+    async fn on_set_tunnel_namespace(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        auto_connect: bool,
+        enabled: bool,
     ) {
         match self
             .settings
-            .update(move |settings| settings.auto_connect = auto_connect)
+            .update(move |settings| settings.tunnel_namespace = enabled)
             .await
         {
-            Ok(_settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set auto-connect response");
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::TunnelNamespace(
+                        enabled,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_tunnel_namespace response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_tunnel_namespace response");
+                }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set auto-connect response");
+                Self::oneshot_send(tx, Err(e), "set_tunnel_namespace response");
             }
         }
     }
 
-    async fn on_set_obfuscation_settings(
+    #[cfg(target_os = "linux")]
+    async fn on_set_firewall_exceptions(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        new_settings: ObfuscationSettings,
+        exceptions: Vec<FirewallException>,
     ) {
+        let exceptions_copy = exceptions.clone();
         match self
             .settings
-            .update(move |settings| settings.obfuscation_settings = new_settings)
+            .update(move |settings| settings.firewall_exceptions = exceptions_copy)
             .await
         {
             Ok(settings_changed) => {
                 if settings_changed {
-                    self.reconnect_tunnel();
+                    self.send_tunnel_command(TunnelCommand::FirewallExceptions(
+                        exceptions,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_firewall_exceptions response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_firewall_exceptions response");
                 }
-                Self::oneshot_send(tx, Ok(()), "set_obfuscation_settings");
             }
-            Err(err) => {
-                log::error!(
-                    "{}",
-                    err.display_chain_with_msg("Failed to set obfuscation settings")
-                );
-                Self::oneshot_send(tx, Err(err), "set_obfuscation_settings");
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_firewall_exceptions response");
             }
         }
     }
 
-    async fn on_set_enable_ipv6(&mut self, tx: ResponseTx<(), settings::Error>, enable_ipv6: bool) {
+    #[cfg(target_os = "linux")]
+    async fn on_set_inbound_ports(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        inbound_ports: Vec<InboundPort>,
+    ) {
+        let inbound_ports_copy = inbound_ports.clone();
         match self
             .settings
-            .update(|settings| settings.tunnel_options.generic.enable_ipv6 = enable_ipv6)
+            .update(move |settings| settings.inbound_ports = inbound_ports_copy)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_enable_ipv6 response");
                 if settings_changed {
-                    log::info!("Initiating tunnel restart because the enable IPv6 setting changed");
-                    self.reconnect_tunnel();
+                    self.send_tunnel_command(TunnelCommand::InboundPorts(
+                        inbound_ports,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_inbound_ports response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_inbound_ports response");
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_enable_ipv6 response");
+                Self::oneshot_send(tx, Err(e), "set_inbound_ports response");
             }
         }
     }
 
-    async fn on_set_userspace_wireguard(
+    #[cfg(target_os = "linux")]
+    async fn on_set_lan_gateway(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        userspace: bool,
+        lan_gateway: Option<LanGateway>,
     ) {
+        let lan_gateway_copy = lan_gateway.clone();
         match self
             .settings
-            .update(|settings| settings.tunnel_options.wireguard.userspace = userspace)
+            .update(move |settings| settings.lan_gateway = lan_gateway_copy)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_userspace_wireguard response");
                 if settings_changed {
-                    log::info!(
-                        "Initiating tunnel restart because the userspace WireGuard setting changed"
-                    );
-                    self.reconnect_tunnel();
+                    self.send_tunnel_command(TunnelCommand::LanGateway(
+                        lan_gateway,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_lan_gateway response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_lan_gateway response");
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_userspace_wireguard response");
+                Self::oneshot_send(tx, Err(e), "set_lan_gateway response");
             }
         }
     }
 
-    async fn on_set_enable_recents(
+    #[cfg(target_os = "linux")]
+    async fn on_set_trusted_interfaces(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        enable_recents: bool,
+        trusted_interfaces: Vec<TrustedInterface>,
     ) {
+        let trusted_interfaces_copy = trusted_interfaces.clone();
         match self
             .settings
-            .update(|settings| match settings.recents {
-                None if enable_recents => {
-                    settings.recents = Some(mullvad_types::settings::Recents::default());
-                    settings.update_recents();
-                }
-                Some(_) if !enable_recents => {
-                    settings.recents = None;
-                }
-                _ => (),
-            })
+            .update(move |settings| settings.trusted_interfaces = trusted_interfaces_copy)
             .await
         {
-            Ok(_) => {
-                Self::oneshot_send(tx, Ok(()), "set_enable_recents response");
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::TrustedInterfaces(
+                        trusted_interfaces,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_trusted_interfaces response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_trusted_interfaces response");
+                }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_enable_recents response");
+                Self::oneshot_send(tx, Err(e), "set_trusted_interfaces response");
             }
         }
     }
 
-    async fn on_set_quantum_resistant_tunnel(
+    #[cfg(target_os = "linux")]
+    async fn on_set_dns_backend(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        quantum_resistant: QuantumResistantState,
+        backend: DnsBackend,
     ) {
         match self
             .settings
-            .update(|settings| {
-                settings.tunnel_options.wireguard.quantum_resistant = quantum_resistant
-            })
+            .update(move |settings| settings.dns_backend = backend)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_quantum_resistant_tunnel response");
                 if settings_changed {
-                    log::info!("Reconnecting because the PQ safety setting changed");
+                    self.send_tunnel_command(TunnelCommand::DnsBackend(
+                        backend,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_dns_backend response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_dns_backend response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_dns_backend response");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn on_set_reapply_firewall_on_drift(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        enabled: bool,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.reapply_firewall_on_drift = enabled)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::ReapplyFirewallOnDrift(
+                        enabled,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(
+                                tx,
+                                Ok(()),
+                                "set_reapply_firewall_on_drift response",
+                            );
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_reapply_firewall_on_drift response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_reapply_firewall_on_drift response");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn on_get_firewall_rules(&self, tx: ResponseTx<FirewallRules, Error>) {
+        self.send_tunnel_command(TunnelCommand::GetFirewallRules(oneshot_map(
+            tx,
+            |tx, result: Result<FirewallRules, firewall::Error>| {
+                Self::oneshot_send(
+                    tx,
+                    result.map_err(Error::FirewallRules),
+                    "get_firewall_rules response",
+                );
+            },
+        )));
+    }
+
+    async fn on_set_auto_connect(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        auto_connect: bool,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.auto_connect = auto_connect)
+            .await
+        {
+            Ok(_settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set auto-connect response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set auto-connect response");
+            }
+        }
+    }
+
+    async fn on_set_expiry_warning_thresholds(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        thresholds: ExpiryWarningThresholds,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.expiry_warning_thresholds = thresholds)
+            .await
+        {
+            Ok(_settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set expiry warning thresholds response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set expiry warning thresholds response");
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_metrics_endpoint(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        endpoint: Option<MetricsEndpoint>,
+    ) {
+        let result = match self
+            .settings
+            .update(move |settings| settings.metrics_endpoint = endpoint)
+            .await
+        {
+            Ok(_settings_changed) => self.update_metrics_server().await.inspect_err(|error| {
+                log::error!("{}", error.display_chain());
+            }),
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Err(Error::SettingsError(e))
+            }
+        };
+        Self::oneshot_send(tx, result, "set metrics endpoint response");
+    }
+
+    async fn on_set_obfuscation_settings(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        new_settings: ObfuscationSettings,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.obfuscation_settings = new_settings)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.reconnect_tunnel();
+                }
+                Self::oneshot_send(tx, Ok(()), "set_obfuscation_settings");
+            }
+            Err(err) => {
+                log::error!(
+                    "{}",
+                    err.display_chain_with_msg("Failed to set obfuscation settings")
+                );
+                Self::oneshot_send(tx, Err(err), "set_obfuscation_settings");
+            }
+        }
+    }
+
+    async fn on_set_enable_ipv6(&mut self, tx: ResponseTx<(), settings::Error>, enable_ipv6: bool) {
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.generic.enable_ipv6 = enable_ipv6)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_enable_ipv6 response");
+                if settings_changed {
+                    log::info!("Initiating tunnel restart because the enable IPv6 setting changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_enable_ipv6 response");
+            }
+        }
+    }
+
+    async fn on_set_userspace_wireguard(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        userspace: bool,
+    ) {
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.wireguard.userspace = userspace)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_userspace_wireguard response");
+                if settings_changed {
+                    log::info!(
+                        "Initiating tunnel restart because the userspace WireGuard setting changed"
+                    );
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_userspace_wireguard response");
+            }
+        }
+    }
+
+    async fn on_set_enable_recents(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        enable_recents: bool,
+    ) {
+        match self
+            .settings
+            .update(|settings| match settings.recents {
+                None if enable_recents => {
+                    settings.recents = Some(mullvad_types::settings::Recents::default());
+                    settings.update_recents();
+                }
+                Some(_) if !enable_recents => {
+                    settings.recents = None;
+                }
+                _ => (),
+            })
+            .await
+        {
+            Ok(_) => {
+                Self::oneshot_send(tx, Ok(()), "set_enable_recents response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_enable_recents response");
+            }
+        }
+    }
+
+    async fn on_set_quantum_resistant_tunnel(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        quantum_resistant: QuantumResistantState,
+    ) {
+        match self
+            .settings
+            .update(|settings| {
+                settings.tunnel_options.wireguard.quantum_resistant = quantum_resistant
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_quantum_resistant_tunnel response");
+                if settings_changed {
+                    log::info!("Reconnecting because the PQ safety setting changed");
                     self.reconnect_tunnel();
                 }
             }
@@ -2792,6 +3469,31 @@ impl Daemon {
         }
     }
 
+    async fn on_set_quantum_resistant_rekey_interval(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        interval: Option<RekeyInterval>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.wireguard.rekey_interval = interval)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_quantum_resistant_rekey_interval response");
+                let quantum_resistant = self.settings.tunnel_options.wireguard.quantum_resistant;
+                if settings_changed && quantum_resistant.enabled() {
+                    log::info!("Reconnecting because the PQ rekey interval changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_quantum_resistant_rekey_interval response");
+            }
+        }
+    }
+
     async fn on_set_daita_enabled(&mut self, tx: ResponseTx<(), settings::Error>, value: bool) {
         let result = self
             .settings
@@ -2819,6 +3521,37 @@ impl Daemon {
         }
     }
 
+    async fn on_set_daita_level(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        level: Option<DaitaLevel>,
+    ) {
+        let result = self
+            .settings
+            .update(|settings| {
+                settings.tunnel_options.wireguard.daita_level = level;
+            })
+            .await;
+
+        match result {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_daita_level response");
+                if let RelaySettings::CustomTunnelEndpoint(_) = &self.settings.relay_settings {
+                    return; // DAITA is not supported for custom relays
+                }
+
+                if settings_changed && self.settings.tunnel_options.wireguard.daita {
+                    log::info!("Reconnecting because the DAITA level changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_daita_level response");
+            }
+        }
+    }
+
     async fn on_set_dns_options(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -3226,6 +3959,48 @@ impl Daemon {
             ));
         }
 
+        #[cfg(target_os = "linux")]
+        {
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::TunnelNamespace(
+                self.settings.tunnel_namespace,
+                tx,
+            ));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::FirewallExceptions(
+                self.settings.firewall_exceptions.clone(),
+                tx,
+            ));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::InboundPorts(
+                self.settings.inbound_ports.clone(),
+                tx,
+            ));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::LanGateway(
+                self.settings.lan_gateway.clone(),
+                tx,
+            ));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::TrustedInterfaces(
+                self.settings.trusted_interfaces.clone(),
+                tx,
+            ));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::DnsBackend(self.settings.dns_backend, tx));
+
+            let (tx, _rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::ReapplyFirewallOnDrift(
+                self.settings.reapply_firewall_on_drift,
+                tx,
+            ));
+        }
+
         let (tx, _rx) = oneshot::channel();
         self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan, tx));
 
@@ -3267,6 +4042,221 @@ impl Daemon {
         self.reconnect_tunnel();
     }
 
+    fn on_list_settings_profiles(&self, tx: oneshot::Sender<Vec<SettingsProfile>>) {
+        let profiles = self.settings.profiles().iter().cloned().collect();
+        Self::oneshot_send(tx, profiles, "list_settings_profiles response");
+    }
+
+    async fn on_create_settings_profile(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        name: String,
+        keys: Vec<SettingsKey>,
+    ) {
+        let result = self.settings.create_profile(name, keys).await;
+        if let Err(error) = &result {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to create settings profile")
+            );
+        }
+        Self::oneshot_send(tx, result, "create_settings_profile response");
+    }
+
+    async fn on_delete_settings_profile(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        name: String,
+    ) {
+        let result = self.settings.delete_profile(&name).await;
+        if let Err(error) = &result {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to delete settings profile")
+            );
+        }
+        Self::oneshot_send(tx, result, "delete_settings_profile response");
+    }
+
+    fn on_diff_settings_profile(
+        &self,
+        tx: ResponseTx<Vec<SettingsKey>, settings::Error>,
+        name: String,
+    ) {
+        let result = self.settings.diff_profile(&name);
+        Self::oneshot_send(tx, result, "diff_settings_profile response");
+    }
+
+    async fn on_activate_settings_profile(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        name: String,
+    ) {
+        let changed = match self.settings.diff_profile(&name) {
+            Ok(changed) => changed,
+            Err(error) => {
+                Self::oneshot_send(tx, Err(error), "activate_settings_profile response");
+                return;
+            }
+        };
+        match self.settings.activate_profile(&name).await {
+            Ok(_settings_changed) => {
+                log::info!("Activated settings profile \"{name}\"");
+                Self::oneshot_send(tx, Ok(()), "activate_settings_profile response");
+                self.apply_changed_settings(&changed);
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to activate settings profile")
+                );
+                Self::oneshot_send(tx, Err(error), "activate_settings_profile response");
+            }
+        }
+    }
+
+    /// Pass settings that were changed together, such as by activating a settings profile, on to
+    /// the tunnel state machine and other components. Settings that are observed through
+    /// settings listeners are left alone. The tunnel is reconnected at most once.
+    fn apply_changed_settings(&mut self, changed: &[SettingsKey]) {
+        let mut reconnect = false;
+        for key in changed {
+            match key {
+                SettingsKey::AllowLan => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan, tx));
+                }
+                #[cfg(not(target_os = "android"))]
+                SettingsKey::LockdownMode => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::LockdownMode(
+                        LockdownMode::from(self.settings.lockdown_mode),
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::TunnelNamespace => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::TunnelNamespace(
+                        self.settings.tunnel_namespace,
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::FirewallExceptions => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::FirewallExceptions(
+                        self.settings.firewall_exceptions.clone(),
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::InboundPorts => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::InboundPorts(
+                        self.settings.inbound_ports.clone(),
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::LanGateway => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::LanGateway(
+                        self.settings.lan_gateway.clone(),
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::TrustedInterfaces => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::TrustedInterfaces(
+                        self.settings.trusted_interfaces.clone(),
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::DnsBackend => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::DnsBackend(
+                        self.settings.dns_backend,
+                        tx,
+                    ));
+                }
+                #[cfg(target_os = "linux")]
+                SettingsKey::ReapplyFirewallOnDrift => {
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::ReapplyFirewallOnDrift(
+                        self.settings.reapply_firewall_on_drift,
+                        tx,
+                    ));
+                }
+                #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
+                SettingsKey::SplitTunnel => {
+                    let split_tunnel = &self.settings.split_tunnel;
+                    let apps = if split_tunnel.enable_exclusions {
+                        split_tunnel
+                            .apps
+                            .iter()
+                            .cloned()
+                            .map(SplitApp::to_tunnel_command_repr)
+                            .collect()
+                    } else {
+                        vec![]
+                    };
+                    let (tx, _rx) = oneshot::channel();
+                    self.send_tunnel_command(TunnelCommand::SetExcludedApps(tx, apps));
+                }
+                SettingsKey::TunnelOptions => {
+                    let (tx, _rx) = oneshot::channel();
+                    let dns =
+                        dns::addresses_from_options(&self.settings.tunnel_options.dns_options);
+                    self.send_tunnel_command(TunnelCommand::Dns(dns, tx));
+
+                    let interval = self.settings.tunnel_options.wireguard.rotation_interval;
+                    let account_manager = self.account_manager.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = account_manager
+                            .set_rotation_interval(interval.unwrap_or_default())
+                            .await
+                        {
+                            log::error!(
+                                "{}",
+                                error.display_chain_with_msg("Failed to update rotation interval")
+                            );
+                        }
+                    });
+                    reconnect = true;
+                }
+                SettingsKey::ShowBetaReleases => {
+                    let version_handle = self.version_handle.clone();
+                    let show_beta_releases = self.settings.show_beta_releases;
+                    tokio::spawn(async move {
+                        if let Err(error) = version_handle
+                            .set_show_beta_releases(show_beta_releases)
+                            .await
+                        {
+                            log::error!("Failed to update beta releases state: {error}");
+                        }
+                    });
+                }
+                SettingsKey::RelaySettings
+                | SettingsKey::ObfuscationSettings
+                | SettingsKey::RelayOverrides
+                | SettingsKey::CustomLists => reconnect = true,
+                SettingsKey::ApiAccessMethods
+                | SettingsKey::UpdateDefaultLocation
+                | SettingsKey::AutoConnect
+                | SettingsKey::ExpiryWarningThresholds
+                | SettingsKey::Recents => (),
+                #[cfg(not(target_os = "android"))]
+                SettingsKey::MetricsEndpoint => (),
+            }
+        }
+        if reconnect {
+            self.reconnect_tunnel();
+        }
+    }
+
     #[cfg(not(target_os = "android"))]
     async fn on_get_rollout_threshold(&mut self, reply: oneshot::Sender<f32>) {
         let seed = match self.settings.rollout_threshold_seed {
@@ -3411,11 +4401,20 @@ impl Daemon {
         tx: ResponseTx<(), settings::patch::Error>,
         blob: String,
     ) {
-        let result = settings::patch::merge_validate_patch(&mut self.settings, &blob).await;
-        if result.is_ok() {
-            self.reconnect_tunnel();
+        match settings::patch::merge_validate_patch(&mut self.settings, &blob).await {
+            Ok(changed) => {
+                Self::oneshot_send(tx, Ok(()), "apply_json_settings response");
+                self.apply_changed_settings(&changed);
+            }
+            Err(error) => {
+                Self::oneshot_send(tx, Err(error), "apply_json_settings response");
+            }
         }
-        Self::oneshot_send(tx, result, "apply_json_settings response");
+    }
+
+    fn on_preview_json_settings(&self, tx: oneshot::Sender<SettingsPatchPreview>, blob: String) {
+        let preview = settings::patch::preview_patch(&self.settings, &blob);
+        Self::oneshot_send(tx, preview, "preview_json_settings response");
     }
 
     fn on_export_json_settings(&mut self, tx: ResponseTx<String, settings::patch::Error>) {
@@ -3476,6 +4475,30 @@ impl Daemon {
         Self::oneshot_send(tx, (), "on_toggle_relay response");
     }
 
+    fn on_start_tunnel_capture(
+        &self,
+        tx: ResponseTx<PathBuf, Error>,
+        settings: TunnelCaptureSettings,
+    ) {
+        let settings = settings.clamp_limits();
+        let result = self
+            .log_dir
+            .as_ref()
+            .ok_or(Error::NoLogDirectory)
+            .and_then(|log_dir| {
+                let timestamp = chrono::Local::now().format("%Y%m%dT%H%M%S");
+                let path = log_dir.join(format!("tunnel-capture-{timestamp}.pcapng"));
+                tunnel_capture::start(&path, settings).map_err(Error::TunnelCapture)?;
+                Ok(path)
+            });
+        Self::oneshot_send(tx, result, "start_tunnel_capture response");
+    }
+
+    fn on_stop_tunnel_capture(&self, tx: oneshot::Sender<()>) {
+        tunnel_capture::stop();
+        Self::oneshot_send(tx, (), "stop_tunnel_capture response");
+    }
+
     #[cfg_attr(not(in_app_upgrade), expect(clippy::unused_async))]
     async fn on_app_upgrade(&self, tx: ResponseTx<(), version::Error>) {
         #[cfg(in_app_upgrade)]
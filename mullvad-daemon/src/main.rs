@@ -247,6 +247,9 @@ async fn create_daemon(
         cache_dir,
         rpc_socket_path,
         endpoint: mullvad_api::ApiEndpoint::from_env_vars(),
+        #[cfg(target_os = "linux")]
+        network_ids: mullvad_daemon::network_ids::NetworkIdentifiers::from_env_vars()
+            .map_err(|e| e.display_chain_with_msg("Invalid network identifiers"))?,
         log_handle,
     };
     Daemon::start(config, DaemonCommandChannel::new())
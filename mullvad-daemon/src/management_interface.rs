@@ -15,31 +15,42 @@ use mullvad_management_interface::{
     Code, Request, Response, ServerJoinHandle, Status,
     types::{self, daemon_event, management_service_server::ManagementService},
 };
+#[cfg(target_os = "linux")]
+use mullvad_types::management_access::AccessPolicy;
+#[cfg(not(target_os = "android"))]
+use mullvad_types::metrics::MetricsEndpoint;
 use mullvad_types::relay_constraints::GeographicLocationConstraint;
 use mullvad_types::{
-    account::AccountNumber,
+    account::{AccountExpiryWarning, AccountNumber, ExpiryWarningThresholds},
     relay_constraints::{
         ObfuscationSettings, RelayOverride, RelaySettings, allowed_ip::AllowedIps,
     },
     relay_list::RelayList,
-    settings::{DnsOptions, Settings, SettingsKeyList},
+    settings::{DnsOptions, Settings, SettingsKey, SettingsKeyList},
     states::{TargetState, TunnelState},
     version,
-    wireguard::{RotationInterval, RotationIntervalError},
+    wireguard::{IntervalError, RekeyInterval, RotationInterval},
 };
 use std::collections::BTreeSet;
+#[cfg(target_os = "linux")]
+use std::path::Path;
 use std::{
     path::PathBuf,
     str::FromStr,
     sync::{Arc, Mutex},
     time::Duration,
 };
-use talpid_types::ErrorExt;
+use talpid_types::{ErrorExt, net::wireguard::DaitaLevel};
 use tokio::time::timeout;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const RPC_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
 
+/// Name of the file in the settings directory that assigns roles to management interface
+/// clients.
+#[cfg(target_os = "linux")]
+const ACCESS_POLICY_FILENAME: &str = "management-access.json";
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     // Unable to start the management interface server
@@ -51,7 +62,7 @@ pub type AppUpgradeBroadcast = tokio::sync::broadcast::Sender<version::AppUpgrad
 
 struct ManagementServiceImpl {
     daemon_tx: DaemonCommandSender,
-    subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    subscriptions: Arc<Mutex<Vec<EventsListener>>>,
     pub app_upgrade_broadcast: AppUpgradeBroadcast,
     log_reload_handle: crate::logging::LogHandle,
 }
@@ -60,6 +71,46 @@ pub type ServiceResult<T> = std::result::Result<Response<T>, Status>;
 type EventsListenerReceiver = UnboundedReceiverStream<Result<types::DaemonEvent, Status>>;
 type EventsListenerSender = tokio::sync::mpsc::UnboundedSender<Result<types::DaemonEvent, Status>>;
 
+/// A subscriber to daemon events.
+struct EventsListener {
+    tx: EventsListenerSender,
+    /// Whether secrets must be removed from events sent to this subscriber.
+    redact_secrets: bool,
+}
+
+/// Return whether secrets must be removed from the response to `request`. Only admins may read
+/// the private key of a custom tunnel and the passwords of custom API access methods.
+#[cfg(target_os = "linux")]
+fn redact_secrets<T>(request: &Request<T>) -> bool {
+    use mullvad_types::management_access::Role;
+    mullvad_management_interface::access::peer_role(request) < Role::Admin
+}
+
+#[cfg(not(target_os = "linux"))]
+fn redact_secrets<T>(_request: &Request<T>) -> bool {
+    false
+}
+
+/// Return an error unless the peer that sent `request` is an admin. This is for arguments that
+/// require more than the role needed to call the RPC itself.
+#[cfg(target_os = "linux")]
+fn require_admin<T>(request: &Request<T>, what: &str) -> Result<(), Status> {
+    use mullvad_types::management_access::Role;
+    let role = mullvad_management_interface::access::peer_role(request);
+    if role < Role::Admin {
+        return Err(Status::permission_denied(format!(
+            "{what} requires the {} role, but the user has the {role} role",
+            Role::Admin
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn require_admin<T>(_request: &Request<T>, _what: &str) -> Result<(), Status> {
+    Ok(())
+}
+
 type AppUpgradeEventListenerReceiver =
     Box<dyn futures::Stream<Item = Result<types::AppUpgradeEvent, Status>> + Send + Unpin>;
 
@@ -114,11 +165,14 @@ impl ManagementService for ManagementServiceImpl {
     // Control the daemon and receive events
     //
 
-    async fn events_listen(&self, _: Request<()>) -> ServiceResult<Self::EventsListenStream> {
+    async fn events_listen(&self, request: Request<()>) -> ServiceResult<Self::EventsListenStream> {
         let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
 
         let mut subscriptions = self.subscriptions.lock().unwrap();
-        subscriptions.push(tx);
+        subscriptions.push(EventsListener {
+            tx,
+            redact_secrets: redact_secrets(&request),
+        });
 
         Ok(Response::new(UnboundedReceiverStream::new(rx)))
     }
@@ -191,11 +245,43 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn import_relay_list(
+        &self,
+        request: Request<types::RelayListImport>,
+    ) -> ServiceResult<types::RelayListInfo> {
+        let types::RelayListImport { relay_list, path } = request.into_inner();
+        log::debug!("import_relay_list({path})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ImportRelayList(tx, relay_list, path))?;
+        let info = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(types::RelayListInfo::from(info)))
+    }
+
+    async fn get_relay_list_info(&self, _: Request<()>) -> ServiceResult<types::RelayListInfo> {
+        log::debug!("get_relay_list_info");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetRelayListInfo(tx))?;
+        let info = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(types::RelayListInfo::from(info)))
+    }
+
     async fn set_relay_settings(
         &self,
         request: Request<types::RelaySettings>,
     ) -> ServiceResult<()> {
         log::debug!("set_relay_settings");
+        // Custom servers are trusted with all traffic
+        let custom_server = match request.get_ref().endpoint.as_ref() {
+            Some(types::relay_settings::Endpoint::Custom(_)) => true,
+            Some(types::relay_settings::Endpoint::Normal(normal)) => normal
+                .wireguard_constraints
+                .as_ref()
+                .is_some_and(|constraints| constraints.custom_entry.is_some()),
+            None => false,
+        };
+        if custom_server {
+            require_admin(&request, "Using a custom tunnel endpoint or entry server")?;
+        }
         let (tx, rx) = oneshot::channel();
         let constraints_update =
             RelaySettings::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
@@ -240,16 +326,37 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn get_network_hints(&self, _: Request<()>) -> ServiceResult<types::NetworkHints> {
+        log::debug!("get_network_hints");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetNetworkHints(tx))?;
+        self.wait_for_result(rx)
+            .await
+            .map(|hints| Response::new(types::NetworkHints::from(hints)))
+    }
+
+    async fn clear_network_hints(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("clear_network_hints");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ClearNetworkHints(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
     // Settings
     //
 
-    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
+    async fn get_settings(&self, request: Request<()>) -> ServiceResult<types::Settings> {
         log::debug!("get_settings");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
-        self.wait_for_result(rx)
-            .await
-            .map(|settings| Response::new(types::Settings::from(&settings)))
+        let mut settings = types::Settings::from(&self.wait_for_result(rx).await?);
+        if redact_secrets(&request) {
+            settings.redact_secrets();
+        }
+        Ok(Response::new(settings))
     }
 
     async fn reset_settings(&self, request: Request<types::SettingsKeyList>) -> ServiceResult<()> {
@@ -261,6 +368,79 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn list_settings_profiles(
+        &self,
+        request: Request<()>,
+    ) -> ServiceResult<types::SettingsProfileList> {
+        log::debug!("list_settings_profiles");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ListSettingsProfiles(tx))?;
+        let mut profiles: Vec<_> = self
+            .wait_for_result(rx)
+            .await?
+            .iter()
+            .map(types::SettingsProfile::from)
+            .collect();
+        if redact_secrets(&request) {
+            for settings in profiles
+                .iter_mut()
+                .filter_map(|profile| profile.settings.as_mut())
+            {
+                settings.redact_secrets();
+            }
+        }
+        Ok(Response::new(types::SettingsProfileList { profiles }))
+    }
+
+    async fn create_settings_profile(
+        &self,
+        request: Request<types::NewSettingsProfile>,
+    ) -> ServiceResult<()> {
+        let request = request.into_inner();
+        let keys = SettingsKeyList::try_from(types::SettingsKeyList { keys: request.keys })?;
+        log::debug!("create_settings_profile({})", request.name);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::CreateSettingsProfile(
+            tx,
+            request.name,
+            keys.keys,
+        ))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn delete_settings_profile(&self, request: Request<String>) -> ServiceResult<()> {
+        let name = request.into_inner();
+        log::debug!("delete_settings_profile({name})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::DeleteSettingsProfile(tx, name))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn diff_settings_profile(
+        &self,
+        request: Request<String>,
+    ) -> ServiceResult<types::SettingsKeyList> {
+        let name = request.into_inner();
+        log::debug!("diff_settings_profile({name})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::DiffSettingsProfile(tx, name))?;
+        let keys: Vec<SettingsKey> = self.wait_for_result(rx).await??;
+        Ok(Response::new(types::SettingsKeyList::from(
+            SettingsKeyList { keys },
+        )))
+    }
+
+    async fn activate_settings_profile(&self, request: Request<String>) -> ServiceResult<()> {
+        let name = request.into_inner();
+        log::debug!("activate_settings_profile({name})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ActivateSettingsProfile(tx, name))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     async fn set_allow_lan(&self, request: Request<bool>) -> ServiceResult<()> {
         let allow_lan = request.into_inner();
         log::debug!("set_allow_lan({})", allow_lan);
@@ -289,6 +469,165 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(target_os = "linux")]
+    async fn set_tunnel_namespace(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_tunnel_namespace({})", enabled);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTunnelNamespace(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_tunnel_namespace(&self, _: Request<bool>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Tunnel namespaces are only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_firewall_exceptions(
+        &self,
+        request: Request<types::FirewallExceptionList>,
+    ) -> ServiceResult<()> {
+        let exceptions = request
+            .into_inner()
+            .exceptions
+            .into_iter()
+            .map(talpid_types::net::FirewallException::try_from)
+            .collect::<Result<Vec<_>, FromProtobufTypeError>>()?;
+        log::debug!("set_firewall_exceptions({:?})", exceptions);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetFirewallExceptions(tx, exceptions))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_firewall_exceptions(
+        &self,
+        _: Request<types::FirewallExceptionList>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Firewall exceptions are only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_inbound_ports(
+        &self,
+        request: Request<types::InboundPortList>,
+    ) -> ServiceResult<()> {
+        let inbound_ports = request
+            .into_inner()
+            .ports
+            .into_iter()
+            .map(talpid_types::net::InboundPort::try_from)
+            .collect::<Result<Vec<_>, FromProtobufTypeError>>()?;
+        log::debug!("set_inbound_ports({:?})", inbound_ports);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetInboundPorts(tx, inbound_ports))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_inbound_ports(&self, _: Request<types::InboundPortList>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Inbound ports are only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_lan_gateway(
+        &self,
+        request: Request<types::LanGatewaySetting>,
+    ) -> ServiceResult<()> {
+        let lan_gateway = request
+            .into_inner()
+            .lan_gateway
+            .map(talpid_types::net::LanGateway::try_from)
+            .transpose()?;
+        log::debug!("set_lan_gateway({:?})", lan_gateway);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetLanGateway(tx, lan_gateway))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_lan_gateway(&self, _: Request<types::LanGatewaySetting>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "LAN gateway mode is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_trusted_interfaces(
+        &self,
+        request: Request<types::TrustedInterfaceList>,
+    ) -> ServiceResult<()> {
+        let trusted_interfaces = request
+            .into_inner()
+            .interfaces
+            .into_iter()
+            .map(talpid_types::net::TrustedInterface::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        log::debug!("set_trusted_interfaces({:?})", trusted_interfaces);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTrustedInterfaces(tx, trusted_interfaces))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_trusted_interfaces(
+        &self,
+        _: Request<types::TrustedInterfaceList>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Trusted interfaces are only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_dns_backend(
+        &self,
+        request: Request<types::DnsBackendSetting>,
+    ) -> ServiceResult<()> {
+        let backend = talpid_types::net::DnsBackend::try_from(request.into_inner())?;
+        log::debug!("set_dns_backend({backend})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetDnsBackend(tx, backend))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_dns_backend(&self, _: Request<types::DnsBackendSetting>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Selecting a DNS backend is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_reapply_firewall_on_drift(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_reapply_firewall_on_drift({enabled})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetReapplyFirewallOnDrift(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_reapply_firewall_on_drift(&self, _: Request<bool>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Firewall drift detection is only supported on Linux",
+        ))
+    }
+
     #[cfg(target_os = "android")]
     async fn set_lockdown_mode(&self, request: Request<bool>) -> ServiceResult<()> {
         let lockdown_mode = request.into_inner();
@@ -307,6 +646,33 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(not(target_os = "android"))]
+    async fn set_metrics_endpoint(
+        &self,
+        request: Request<types::MetricsEndpointSetting>,
+    ) -> ServiceResult<()> {
+        let endpoint = request
+            .into_inner()
+            .endpoint
+            .map(MetricsEndpoint::try_from)
+            .transpose()?;
+        log::debug!("set_metrics_endpoint({:?})", endpoint);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetMetricsEndpoint(tx, endpoint))?;
+        self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_metrics_endpoint(
+        &self,
+        _: Request<types::MetricsEndpointSetting>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Serving metrics is not supported on Android",
+        ))
+    }
+
     async fn set_wireguard_mtu(&self, request: Request<u32>) -> ServiceResult<()> {
         let mtu = request.into_inner();
         let mtu = if mtu != 0 { Some(mtu as u16) } else { None };
@@ -349,6 +715,47 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn set_quantum_resistant_rekey_interval(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<()> {
+        let interval: RekeyInterval = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative rekey interval"))?
+            .try_into()
+            .map_err(|error: IntervalError| Status::invalid_argument(error.display_chain()))?;
+
+        log::debug!("set_quantum_resistant_rekey_interval({interval:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantRekeyInterval(
+            tx,
+            Some(interval),
+        ))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn reset_quantum_resistant_rekey_interval(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("reset_quantum_resistant_rekey_interval");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantRekeyInterval(tx, None))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn get_quantum_resistant_rekey_status(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::QuantumResistantRekeyStatus> {
+        log::debug!("get_quantum_resistant_rekey_status");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetLastQuantumResistantRekey(tx))?;
+        self.wait_for_result(rx).await.map(|last_rekey| {
+            Response::new(types::QuantumResistantRekeyStatus {
+                last_rekey: last_rekey.map(types::Timestamp::from),
+            })
+        })
+    }
+
     async fn set_enable_daita(&self, request: Request<bool>) -> ServiceResult<()> {
         let daita_enabled = request.into_inner();
         log::debug!("set_enable_daita({daita_enabled})");
@@ -367,6 +774,19 @@ impl ManagementService for ManagementServiceImpl {
         self.set_enable_daita(request).await
     }
 
+    async fn set_daita_level(
+        &self,
+        request: Request<types::DaitaLevelSetting>,
+    ) -> ServiceResult<()> {
+        let level =
+            Option::<DaitaLevel>::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_daita_level({level:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetDaitaLevel(tx, level))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     async fn set_dns_options(&self, request: Request<types::DnsOptions>) -> ServiceResult<()> {
         let options = DnsOptions::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
         log::debug!("set_dns_options({:?})", options);
@@ -521,6 +941,18 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
+    async fn set_expiry_warning_thresholds(
+        &self,
+        request: Request<types::ExpiryWarningThresholds>,
+    ) -> ServiceResult<()> {
+        let thresholds = ExpiryWarningThresholds::from(request.into_inner());
+        log::debug!("set_expiry_warning_thresholds({:?})", thresholds.hours());
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetExpiryWarningThresholds(tx, thresholds))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     // Device management
     async fn get_device(&self, _: Request<()>) -> ServiceResult<types::DeviceState> {
         log::debug!("get_device");
@@ -575,9 +1007,7 @@ impl ManagementService for ManagementServiceImpl {
         let interval: RotationInterval = Duration::try_from(request.into_inner())
             .map_err(|_| Status::invalid_argument("unexpected negative rotation interval"))?
             .try_into()
-            .map_err(|error: RotationIntervalError| {
-                Status::invalid_argument(error.display_chain())
-            })?;
+            .map_err(|error: IntervalError| Status::invalid_argument(error.display_chain()))?;
 
         log::debug!("set_wireguard_rotation_interval({:?})", interval);
         let (tx, rx) = oneshot::channel();
@@ -774,16 +1204,20 @@ impl ManagementService for ManagementServiceImpl {
     /// connect to the Mullvad API.
     async fn get_current_api_access_method(
         &self,
-        _: Request<()>,
+        request: Request<()>,
     ) -> ServiceResult<types::AccessMethodSetting> {
         log::debug!("get_current_api_access_method");
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::GetCurrentAccessMethod(tx))?;
-        self.wait_for_result(rx)
+        let mut method = self
+            .wait_for_result(rx)
             .await?
             .map(types::AccessMethodSetting::from)
-            .map(Response::new)
-            .map_err(map_daemon_error)
+            .map_err(map_daemon_error)?;
+        if redact_secrets(&request) {
+            method.redact_secrets();
+        }
+        Ok(Response::new(method))
     }
 
     async fn test_custom_api_access_method(
@@ -1058,6 +1492,17 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn preview_json_settings(
+        &self,
+        blob: Request<String>,
+    ) -> ServiceResult<types::SettingsPatchPreview> {
+        log::debug!("preview_json_settings");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::PreviewJsonSettings(tx, blob.into_inner()))?;
+        let preview = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::SettingsPatchPreview::from(preview)))
+    }
+
     async fn export_json_settings(&self, _: Request<()>) -> ServiceResult<String> {
         log::debug!("export_json_settings");
         let (tx, rx) = oneshot::channel();
@@ -1206,6 +1651,22 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(target_os = "linux")]
+    async fn get_firewall_rules(&self, _: Request<()>) -> ServiceResult<types::FirewallRules> {
+        log::debug!("get_firewall_rules");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetFirewallRules(tx))?;
+        let rules = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(convert_firewall_rules(rules)))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn get_firewall_rules(&self, _: Request<()>) -> ServiceResult<types::FirewallRules> {
+        Err(Status::unimplemented(
+            "Firewall rule introspection is only supported on Linux",
+        ))
+    }
+
     #[cfg(not(target_os = "android"))]
     async fn get_rollout_threshold(&self, _: Request<()>) -> ServiceResult<types::Rollout> {
         log::debug!("get_rollout_threshold");
@@ -1251,6 +1712,31 @@ impl ManagementService for ManagementServiceImpl {
         unreachable!("You should not call regenerate_rollout_threshold");
     }
 
+    async fn start_tunnel_capture(
+        &self,
+        request: Request<types::TunnelCaptureSettings>,
+    ) -> ServiceResult<String> {
+        log::debug!("start_tunnel_capture");
+        let settings = talpid_types::net::TunnelCaptureSettings::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::StartTunnelCapture(tx, settings))?;
+        let path = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+
+        path.into_os_string()
+            .into_string()
+            .map_err(|_| Status::internal("Failed to convert OsString to String"))
+            .map(Response::new)
+    }
+
+    async fn stop_tunnel_capture(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("stop_tunnel_capture");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::StopTunnelCapture(tx))?;
+        self.wait_for_result(rx).await?;
+        Ok(Response::new(()))
+    }
+
     // App upgrade
 
     async fn app_upgrade(&self, _: Request<()>) -> ServiceResult<()> {
@@ -1374,6 +1860,40 @@ impl ManagementServiceImpl {
     }
 }
 
+/// Read the roles of management interface clients from [`ACCESS_POLICY_FILENAME`] in
+/// `settings_dir`. Every client is an admin if the file does not exist. If the file cannot be
+/// parsed, only the root user is allowed to make changes.
+#[cfg(target_os = "linux")]
+pub fn load_access_policy(settings_dir: &Path) -> AccessPolicy {
+    let path = settings_dir.join(ACCESS_POLICY_FILENAME);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            return AccessPolicy::default();
+        }
+        Err(error) => {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg(&format!("Failed to read {}", path.display()))
+            );
+            return AccessPolicy::root_only();
+        }
+    };
+    match serde_json::from_str(&contents) {
+        Ok(policy) => {
+            log::info!("Loaded management interface roles from {}", path.display());
+            policy
+        }
+        Err(error) => {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg(&format!("Failed to parse {}", path.display()))
+            );
+            AccessPolicy::root_only()
+        }
+    }
+}
+
 /// The running management interface serving gRPC requests.
 pub struct ManagementInterfaceServer {
     /// The rpc server spawned by [`Self::start`]. When the underlying join handle yields, the rpc
@@ -1394,8 +1914,9 @@ impl ManagementInterfaceServer {
         app_upgrade_broadcast: AppUpgradeBroadcast,
         log_reload_handle: crate::logging::LogHandle,
         relay_selector: RelaySelectorIO,
+        #[cfg(target_os = "linux")] access_policy: AccessPolicy,
     ) -> Result<ManagementInterfaceServer, Error> {
-        let subscriptions = Arc::<Mutex<Vec<EventsListenerSender>>>::default();
+        let subscriptions = Arc::<Mutex<Vec<EventsListener>>>::default();
 
         // NOTE: It is important that the channel buffer size is kept at 0. When sending a signal
         // to abort the gRPC server, the sender can be awaited to know when the gRPC server has
@@ -1418,6 +1939,8 @@ impl ManagementInterfaceServer {
                 StreamExt::into_future(server_abort_rx).await;
             },
             rpc_socket_path.clone(),
+            #[cfg(target_os = "linux")]
+            access_policy,
         )
         .map_err(Error::SetupError)?;
 
@@ -1464,13 +1987,27 @@ impl ManagementInterfaceServer {
 /// A handle that allows broadcasting messages to all subscribers of the management interface.
 #[derive(Clone)]
 pub struct ManagementInterfaceEventBroadcaster {
-    subscriptions: Arc<Mutex<Vec<EventsListenerSender>>>,
+    subscriptions: Arc<Mutex<Vec<EventsListener>>>,
 }
 
 impl ManagementInterfaceEventBroadcaster {
     fn notify(&self, value: types::DaemonEvent) {
+        let mut redacted = None;
         let mut subscriptions = self.subscriptions.lock().unwrap();
-        subscriptions.retain(|tx| tx.send(Ok(value.clone())).is_ok());
+        subscriptions.retain(|listener| {
+            let event = if listener.redact_secrets {
+                redacted
+                    .get_or_insert_with(|| {
+                        let mut event = value.clone();
+                        event.redact_secrets();
+                        event
+                    })
+                    .clone()
+            } else {
+                value.clone()
+            };
+            listener.tx.send(Ok(event)).is_ok()
+        });
     }
 
     /// Notify that the tunnel state changed.
@@ -1519,6 +2056,28 @@ impl ManagementInterfaceEventBroadcaster {
         })
     }
 
+    /// Notify clients that the firewall rules were changed by another program.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn notify_firewall_drift(&self, drift: talpid_core::firewall::FirewallDrift) {
+        log::trace!("Broadcasting firewall drift");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::FirewallDrift(types::FirewallDrift {
+                rules: Some(convert_firewall_rules(drift.rules)),
+                reapplied: drift.reapplied,
+            })),
+        })
+    }
+
+    /// Notify clients that the account is about to run out of time, or has run out of time.
+    pub(crate) fn notify_account_expiry_warning(&self, warning: AccountExpiryWarning) {
+        log::trace!("Broadcasting account expiry warning");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::AccountExpiryWarning(
+                types::AccountExpiryWarning::from(warning),
+            )),
+        })
+    }
+
     /// Notify clients about a potential leak.
     pub(crate) fn notify_leak(&self, leak: mullvad_leak_checker::LeakInfo) {
         log::trace!("Broadcasting leak info: {leak:#?}");
@@ -1617,10 +2176,40 @@ fn map_daemon_error(error: crate::Error) -> Status {
             Status::unauthenticated(error.to_string())
         }
         DaemonError::VersionCheckError(error) => map_version_check_error(error),
+        DaemonError::RelayListError(error) => map_relay_list_error(error),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::MetricsServer(error) => Status::unavailable(error.display_chain()),
+        DaemonError::TunnelCapture(error) => map_tunnel_capture_error(error),
+        DaemonError::NoLogDirectory => Status::failed_precondition(error.to_string()),
         error => Status::unknown(error.to_string()),
     }
 }
 
+/// Converts [`talpid_core::tunnel_state_machine::tunnel_capture::Error`] into a tonic status.
+fn map_tunnel_capture_error(
+    error: talpid_core::tunnel_state_machine::tunnel_capture::Error,
+) -> Status {
+    use talpid_core::tunnel_state_machine::tunnel_capture::Error;
+
+    match &error {
+        Error::AlreadyRunning => Status::already_exists(error.to_string()),
+        Error::NothingToCapture => Status::invalid_argument(error.to_string()),
+        _ => Status::unknown(error.display_chain()),
+    }
+}
+
+/// Converts [`mullvad_daemon_relay_selector::relay_list::error::Error`] into a tonic status.
+fn map_relay_list_error(error: mullvad_daemon_relay_selector::relay_list::error::Error) -> Status {
+    use mullvad_daemon_relay_selector::relay_list::error::Error;
+
+    match &error {
+        Error::Serialize(_) | Error::MissingETag | Error::EmptyRelayList => {
+            Status::invalid_argument(error.display_chain())
+        }
+        _ => Status::unknown(error.display_chain()),
+    }
+}
+
 #[cfg(windows)]
 /// Converts [`talpid_core::split_tunnel::Error`] into a tonic status.
 fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
@@ -1644,6 +2233,15 @@ fn map_split_tunnel_error(error: talpid_core::split_tunnel::Error) -> Status {
     Status::unknown(error.to_string())
 }
 
+#[cfg(target_os = "linux")]
+fn convert_firewall_rules(rules: talpid_core::firewall::FirewallRules) -> types::FirewallRules {
+    types::FirewallRules {
+        policy: rules.policy,
+        expected: rules.expected,
+        actual: rules.actual,
+    }
+}
+
 /// Converts a REST API error into a tonic status.
 fn map_rest_error(error: &RestError) -> Status {
     match error {
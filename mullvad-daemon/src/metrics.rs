@@ -0,0 +1,614 @@
+//! Serves metrics about the daemon in the OpenMetrics text format, so that they can be scraped by
+//! Prometheus or compatible monitoring systems.
+//!
+//! The counters are kept by the daemon only while a [`MetricsServer`] is running. Everything else
+//! is sampled when the metrics are scraped.
+
+use crate::DaemonEventSender;
+use futures::{Stream, StreamExt, channel::oneshot};
+use mullvad_types::{metrics::MetricsEndpoint, states::TunnelState};
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Write},
+    io,
+    time::{Duration, SystemTime},
+};
+#[cfg(unix)]
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use talpid_core::{mpsc::Sender, tunnel_state_machine::StatsMap};
+use talpid_types::{ErrorExt, net::wireguard::PublicKey};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for the daemon to collect the metrics.
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest request head that is accepted. Requests for metrics are tiny.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Directory in the cache directory that metrics sockets are created in.
+#[cfg(unix)]
+const SOCKET_DIR_NAME: &str = "metrics";
+/// Permissions of metrics sockets. Anyone may connect, like to the TCP endpoint.
+#[cfg(unix)]
+const SOCKET_MODE: u32 = 0o666;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Names of the tunnel states, as used in the `state` label.
+const TUNNEL_STATES: [&str; 5] = [
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "error",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to listen on {0}")]
+    Bind(MetricsEndpoint, #[source] io::Error),
+
+    #[cfg(unix)]
+    #[error("Invalid metrics socket name: {0:?}")]
+    InvalidSocketName(String),
+
+    #[cfg(unix)]
+    #[error("Failed to create metrics socket directory {}", .0.display())]
+    CreateSocketDir(PathBuf, #[source] io::Error),
+
+    #[cfg(unix)]
+    #[error("Failed to set permissions of {}", .0.display())]
+    SetSocketPermissions(PathBuf, #[source] io::Error),
+}
+
+/// Counters that are updated by the daemon as things happen.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Counters {
+    tunnel_states: BTreeMap<&'static str, u64>,
+    access_method_changes: u64,
+    leaks_detected: u64,
+}
+
+impl Counters {
+    pub fn record_tunnel_state(&mut self, state: &TunnelState) {
+        *self
+            .tunnel_states
+            .entry(tunnel_state_name(state))
+            .or_default() += 1;
+    }
+
+    pub fn record_access_method_change(&mut self) {
+        self.access_method_changes += 1;
+    }
+
+    pub fn record_leak(&mut self) {
+        self.leaks_detected += 1;
+    }
+}
+
+/// Everything that is reported when the metrics are scraped.
+pub(crate) struct Snapshot {
+    pub counters: Counters,
+    pub tunnel_state: &'static str,
+    /// The retry attempt that the relay selector was last asked for a relay with.
+    pub retry_attempt: u32,
+    pub tunnel_stats: Option<StatsMap>,
+    /// Time since the relay list cache was last written.
+    pub relay_list_age: Option<Duration>,
+}
+
+/// Sent to the daemon when the metrics are scraped.
+pub(crate) struct ScrapeRequest(oneshot::Sender<Snapshot>);
+
+impl ScrapeRequest {
+    pub fn respond(self, snapshot: Snapshot) {
+        let _ = self.0.send(snapshot);
+    }
+}
+
+/// Serves metrics on a [`MetricsEndpoint`] until it is dropped.
+pub(crate) struct MetricsServer {
+    endpoint: MetricsEndpoint,
+    /// Path of the socket that was created for a Unix socket endpoint.
+    #[cfg(unix)]
+    socket_path: Option<PathBuf>,
+    counters: Counters,
+    server_task: tokio::task::JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// Start serving metrics on `endpoint`. Unix sockets are created in a directory in
+    /// `cache_dir`.
+    pub async fn start(
+        endpoint: MetricsEndpoint,
+        #[cfg_attr(not(unix), expect(unused_variables))] cache_dir: &std::path::Path,
+        scrape_tx: DaemonEventSender<ScrapeRequest>,
+    ) -> Result<Self, Error> {
+        #[cfg(unix)]
+        let mut socket_path = None;
+        let server_task = match &endpoint {
+            MetricsEndpoint::Tcp { .. } => {
+                let address = endpoint.socket_addr().expect("TCP endpoint has an address");
+                let listener = tokio::net::TcpListener::bind(address)
+                    .await
+                    .map_err(|error| Error::Bind(endpoint.clone(), error))?;
+                let connections = tokio_stream::wrappers::TcpListenerStream::new(listener);
+                tokio::spawn(serve(connections, scrape_tx))
+            }
+            #[cfg(unix)]
+            MetricsEndpoint::UnixSocket { name } => {
+                if !mullvad_types::metrics::is_valid_socket_name(name) {
+                    return Err(Error::InvalidSocketName(name.clone()));
+                }
+                let socket_dir = create_socket_dir(&cache_dir.join(SOCKET_DIR_NAME)).await?;
+                let path = socket_dir.join(name);
+                remove_stale_socket(&path).await;
+                let listener = tokio::net::UnixListener::bind(&path)
+                    .map_err(|error| Error::Bind(endpoint.clone(), error))?;
+                // Do not depend on the umask of the daemon
+                if let Err(error) =
+                    tokio::fs::set_permissions(&path, PermissionsExt::from_mode(SOCKET_MODE)).await
+                {
+                    let _ = tokio::fs::remove_file(&path).await;
+                    return Err(Error::SetSocketPermissions(path, error));
+                }
+                socket_path = Some(path);
+                let connections = tokio_stream::wrappers::UnixListenerStream::new(listener);
+                tokio::spawn(serve(connections, scrape_tx))
+            }
+        };
+        log::info!("Serving metrics on {endpoint}");
+
+        Ok(MetricsServer {
+            endpoint,
+            #[cfg(unix)]
+            socket_path,
+            counters: Counters::default(),
+            server_task,
+        })
+    }
+
+    pub fn endpoint(&self) -> &MetricsEndpoint {
+        &self.endpoint
+    }
+
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    pub fn counters_mut(&mut self) -> &mut Counters {
+        &mut self.counters
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.server_task.abort();
+        #[cfg(unix)]
+        if let Some(path) = &self.socket_path {
+            let _ = std::fs::remove_file(path);
+        }
+        log::info!("Stopped serving metrics on {}", self.endpoint);
+    }
+}
+
+/// Create the directory that metrics sockets are created in, unless it exists. Only the daemon
+/// may write to it, so that any socket in it was created by the daemon.
+#[cfg(unix)]
+async fn create_socket_dir(dir: &Path) -> Result<PathBuf, Error> {
+    const DIR_MODE: u32 = 0o755;
+
+    let map_err = |error| Error::CreateSocketDir(dir.to_owned(), error);
+    match tokio::fs::DirBuilder::new()
+        .mode(DIR_MODE)
+        .create(dir)
+        .await
+    {
+        Ok(()) => (),
+        Err(error) if error.kind() == io::ErrorKind::AlreadyExists => {
+            let metadata = tokio::fs::symlink_metadata(dir).await.map_err(map_err)?;
+            if !metadata.is_dir() {
+                return Err(map_err(io::Error::other("not a directory")));
+            }
+        }
+        Err(error) => return Err(map_err(error)),
+    }
+    // The cache directory is only writable by the daemon, so nobody else can have created it
+    tokio::fs::set_permissions(dir, PermissionsExt::from_mode(DIR_MODE))
+        .await
+        .map_err(map_err)?;
+    Ok(dir.to_owned())
+}
+
+/// Remove a socket left behind by a previous instance of the daemon, in the metrics socket
+/// directory. Other kinds of files are left alone, and binding to their path fails.
+#[cfg(unix)]
+async fn remove_stale_socket(path: &Path) {
+    use std::os::unix::fs::FileTypeExt;
+
+    if let Ok(metadata) = tokio::fs::symlink_metadata(path).await
+        && metadata.file_type().is_socket()
+    {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
+
+async fn serve<S>(
+    mut connections: impl Stream<Item = io::Result<S>> + Unpin,
+    scrape_tx: DaemonEventSender<ScrapeRequest>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    while let Some(connection) = connections.next().await {
+        match connection {
+            Ok(stream) => {
+                tokio::spawn(handle_connection(stream, scrape_tx.clone()));
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to accept metrics connection")
+                );
+                // Avoid spinning if the error persists, e.g. when running out of file descriptors
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+async fn handle_connection<S>(mut stream: S, scrape_tx: DaemonEventSender<ScrapeRequest>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let request_line = tokio::time::timeout(REQUEST_TIMEOUT, read_request_line(&mut stream))
+        .await
+        .ok()
+        .flatten();
+
+    let response = match request_line.as_deref().map(parse_request_line) {
+        Some(Some(("GET", "/metrics"))) => match scrape(&scrape_tx).await {
+            Some(snapshot) => Response::ok(render(&snapshot, SystemTime::now())),
+            None => Response::error("503 Service Unavailable"),
+        },
+        Some(Some(("GET", _))) => Response::error("404 Not Found"),
+        Some(Some(_)) => Response::error("405 Method Not Allowed"),
+        Some(None) | None => Response::error("400 Bad Request"),
+    };
+
+    if let Err(error) = response.write_to(&mut stream).await {
+        log::debug!(
+            "{}",
+            error.display_chain_with_msg("Failed to send metrics response")
+        );
+    }
+}
+
+async fn scrape(scrape_tx: &DaemonEventSender<ScrapeRequest>) -> Option<Snapshot> {
+    let (snapshot_tx, snapshot_rx) = oneshot::channel();
+    scrape_tx.send(ScrapeRequest(snapshot_tx)).ok()?;
+    tokio::time::timeout(SCRAPE_TIMEOUT, snapshot_rx)
+        .await
+        .ok()?
+        .ok()
+}
+
+/// Read the request until the end of its head, and return the first line. The headers are
+/// ignored.
+async fn read_request_line(stream: &mut (impl AsyncRead + Unpin)) -> Option<String> {
+    let mut request = Vec::with_capacity(1024);
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await.ok()?;
+        if read == 0 || request.len() + read > MAX_REQUEST_SIZE {
+            return None;
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    let request = String::from_utf8(request).ok()?;
+    request.lines().next().map(str::to_owned)
+}
+
+/// Return the method and the path, without the query string, of an HTTP/1.x request line.
+fn parse_request_line(line: &str) -> Option<(&str, &str)> {
+    let mut parts = line.split(' ');
+    let method = parts.next()?;
+    let target = parts.next()?;
+    let version = parts.next()?;
+    if !version.starts_with("HTTP/1.") || parts.next().is_some() {
+        return None;
+    }
+    let path = target.split_once('?').map_or(target, |(path, _query)| path);
+    Some((method, path))
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn ok(body: String) -> Self {
+        Response {
+            status: "200 OK",
+            content_type: CONTENT_TYPE,
+            body,
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{status}\n"),
+        }
+    }
+
+    async fn write_to(&self, stream: &mut (impl AsyncWrite + Unpin)) -> io::Result<()> {
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(self.body.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+pub(crate) fn tunnel_state_name(state: &TunnelState) -> &'static str {
+    match state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(_) => "disconnecting",
+        TunnelState::Error(_) => "error",
+    }
+}
+
+/// Render `snapshot` in the OpenMetrics text format.
+fn render(snapshot: &Snapshot, now: SystemTime) -> String {
+    let mut out = MetricsWriter::default();
+
+    out.family(
+        "mullvad_tunnel_state",
+        "stateset",
+        None,
+        "Current state of the tunnel.",
+    );
+    for state in TUNNEL_STATES {
+        let value = u8::from(state == snapshot.tunnel_state);
+        out.sample(
+            "mullvad_tunnel_state",
+            &[("mullvad_tunnel_state", state)],
+            value,
+        );
+    }
+
+    out.family(
+        "mullvad_tunnel_state_transitions",
+        "counter",
+        None,
+        "Number of times each tunnel state has been entered. Each transition to the connecting state is a connection attempt.",
+    );
+    for state in TUNNEL_STATES {
+        let count = snapshot
+            .counters
+            .tunnel_states
+            .get(state)
+            .copied()
+            .unwrap_or(0);
+        out.sample(
+            "mullvad_tunnel_state_transitions_total",
+            &[("state", state)],
+            count,
+        );
+    }
+
+    out.family(
+        "mullvad_relay_selector_retry_attempt",
+        "gauge",
+        None,
+        "Retry attempt of the last relay selection. Selects the entry constraints that were tried.",
+    );
+    out.sample(
+        "mullvad_relay_selector_retry_attempt",
+        &[],
+        snapshot.retry_attempt,
+    );
+
+    out.family(
+        "mullvad_api_access_method_changes",
+        "counter",
+        None,
+        "Number of times the method used to reach the API has changed.",
+    );
+    out.sample(
+        "mullvad_api_access_method_changes_total",
+        &[],
+        snapshot.counters.access_method_changes,
+    );
+
+    out.family(
+        "mullvad_leaks_detected",
+        "counter",
+        None,
+        "Number of times traffic was found to leak outside the tunnel.",
+    );
+    out.sample(
+        "mullvad_leaks_detected_total",
+        &[],
+        snapshot.counters.leaks_detected,
+    );
+
+    if let Some(age) = snapshot.relay_list_age {
+        out.family(
+            "mullvad_relay_list_age_seconds",
+            "gauge",
+            Some("seconds"),
+            "Time since the relay list was last updated.",
+        );
+        out.sample("mullvad_relay_list_age_seconds", &[], age.as_secs_f64());
+    }
+
+    if let Some(stats) = &snapshot.tunnel_stats {
+        // Sort the peers so that the output is stable
+        let peers: BTreeMap<String, _> = stats
+            .iter()
+            .map(|(key, stats)| (PublicKey::from(*key).to_base64(), stats))
+            .collect();
+
+        out.family(
+            "mullvad_tunnel_transmit_bytes",
+            "counter",
+            Some("bytes"),
+            "Bytes sent to each peer of the current tunnel.",
+        );
+        for (peer, stats) in &peers {
+            out.sample(
+                "mullvad_tunnel_transmit_bytes_total",
+                &[("peer", peer)],
+                stats.tx_bytes,
+            );
+        }
+
+        out.family(
+            "mullvad_tunnel_receive_bytes",
+            "counter",
+            Some("bytes"),
+            "Bytes received from each peer of the current tunnel.",
+        );
+        for (peer, stats) in &peers {
+            out.sample(
+                "mullvad_tunnel_receive_bytes_total",
+                &[("peer", peer)],
+                stats.rx_bytes,
+            );
+        }
+
+        out.family(
+            "mullvad_tunnel_last_handshake_age_seconds",
+            "gauge",
+            Some("seconds"),
+            "Time since the last handshake with each peer of the current tunnel.",
+        );
+        for (peer, stats) in &peers {
+            let Some(age) = stats
+                .last_handshake_time
+                .and_then(|time| now.duration_since(time).ok())
+            else {
+                continue;
+            };
+            out.sample(
+                "mullvad_tunnel_last_handshake_age_seconds",
+                &[("peer", peer)],
+                age.as_secs_f64(),
+            );
+        }
+    }
+
+    out.finish()
+}
+
+#[derive(Default)]
+struct MetricsWriter(String);
+
+impl MetricsWriter {
+    fn family(&mut self, name: &str, kind: &str, unit: Option<&str>, help: &str) {
+        let _ = writeln!(self.0, "# TYPE {name} {kind}");
+        if let Some(unit) = unit {
+            let _ = writeln!(self.0, "# UNIT {name} {unit}");
+        }
+        let _ = writeln!(self.0, "# HELP {name} {help}");
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: impl fmt::Display) {
+        self.0.push_str(name);
+        if !labels.is_empty() {
+            let labels: Vec<_> = labels
+                .iter()
+                .map(|(label, value)| format!("{label}=\"{}\"", escape_label_value(value)))
+                .collect();
+            let _ = write!(self.0, "{{{}}}", labels.join(","));
+        }
+        let _ = writeln!(self.0, " {value}");
+    }
+
+    fn finish(mut self) -> String {
+        self.0.push_str("# EOF\n");
+        self.0
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('"', r#"\""#)
+        .replace('\n', r"\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_core::tunnel_state_machine::Stats;
+
+    #[test]
+    fn test_parse_request_line() {
+        assert_eq!(
+            parse_request_line("GET /metrics HTTP/1.1"),
+            Some(("GET", "/metrics"))
+        );
+        assert_eq!(
+            parse_request_line("GET /metrics?name[]=foo HTTP/1.0"),
+            Some(("GET", "/metrics"))
+        );
+        assert_eq!(parse_request_line("GET /metrics"), None);
+        assert_eq!(parse_request_line("GET /metrics SPDY/3"), None);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut counters = Counters::default();
+        counters.tunnel_states.insert("error", 2);
+        counters.record_access_method_change();
+
+        let now = SystemTime::now();
+        let peer = [1u8; 32];
+        let stats = Stats {
+            tx_bytes: 100,
+            rx_bytes: 200,
+            last_handshake_time: Some(now - Duration::from_secs(30)),
+            daita: None,
+        };
+        let snapshot = Snapshot {
+            counters,
+            tunnel_state: "connected",
+            retry_attempt: 3,
+            tunnel_stats: Some(StatsMap::from([(peer, stats)])),
+            relay_list_age: Some(Duration::from_secs(90)),
+        };
+
+        let output = render(&snapshot, now);
+        let peer = PublicKey::from(peer).to_base64();
+
+        assert!(output.contains("mullvad_tunnel_state{mullvad_tunnel_state=\"connected\"} 1\n"));
+        assert!(output.contains("mullvad_tunnel_state{mullvad_tunnel_state=\"error\"} 0\n"));
+        assert!(output.contains("mullvad_tunnel_state_transitions_total{state=\"error\"} 2\n"));
+        assert!(output.contains("mullvad_tunnel_state_transitions_total{state=\"connected\"} 0\n"));
+        assert!(output.contains("mullvad_relay_selector_retry_attempt 3\n"));
+        assert!(output.contains("mullvad_api_access_method_changes_total 1\n"));
+        assert!(output.contains("mullvad_relay_list_age_seconds 90\n"));
+        assert!(output.contains(&format!(
+            "mullvad_tunnel_transmit_bytes_total{{peer=\"{peer}\"}} 100\n"
+        )));
+        assert!(output.contains(&format!(
+            "mullvad_tunnel_last_handshake_age_seconds{{peer=\"{peer}\"}} 30\n"
+        )));
+        assert!(output.ends_with("# EOF\n"));
+    }
+}
@@ -56,6 +56,7 @@ mod v13;
 mod v14;
 mod v15;
 mod v16;
+mod v18;
 mod v2;
 mod v3;
 mod v4;
@@ -246,6 +247,8 @@ async fn migrate_settings(
         multihop::migrate_without_relay_selector(settings)?
     };
 
+    v18::migrate(settings)?;
+
     Ok(MigrationData {
         v5,
         multihop_split_filter_migration,
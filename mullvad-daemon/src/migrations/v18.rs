@@ -0,0 +1,107 @@
+use super::Result;
+use mullvad_types::settings::SettingsVersion;
+use serde_json::{Value, json};
+
+const DAITA_LEVEL_KEY: &str = "daita_level";
+
+/// This migration handles:
+/// - Adds the DAITA level to the WireGuard tunnel options. Existing settings keep requesting the
+///   relay's default level, which is represented by `null`.
+pub fn migrate(settings: &mut Value) -> Result<()> {
+    if !version_matches(settings) {
+        return Ok(());
+    }
+
+    log::info!("Migrating settings format to V19");
+
+    add_daita_level(settings);
+
+    settings["settings_version"] = json!(SettingsVersion::V19);
+
+    Ok(())
+}
+
+/// { "tunnel_options": { "wireguard": { "daita_level": null } } }
+///                                      ^^^^^^^^^^^^^ -------------- Inserted unless present.
+fn add_daita_level(settings: &mut Value) -> Option<()> {
+    let wireguard = settings
+        .get_mut("tunnel_options")
+        .and_then(|tunnel_options| tunnel_options.get_mut("wireguard"))
+        .and_then(|wireguard| wireguard.as_object_mut())?;
+
+    wireguard.entry(DAITA_LEVEL_KEY).or_insert(Value::Null);
+
+    Some(())
+}
+
+fn version_matches(settings: &Value) -> bool {
+    settings
+        .get("settings_version")
+        .map(|version| version == SettingsVersion::V18 as u64)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_v18_to_v19_migration() {
+        let mut settings = json!({
+            "tunnel_options": {
+                "wireguard": {
+                    "daita": true,
+                    "mtu": null
+                }
+            },
+            "settings_version": 18
+        });
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(
+            settings,
+            json!({
+                "tunnel_options": {
+                    "wireguard": {
+                        "daita": true,
+                        "daita_level": null,
+                        "mtu": null
+                    }
+                },
+                "settings_version": 19
+            })
+        );
+    }
+
+    #[test]
+    fn test_v18_to_v19_migration_keeps_existing_level() {
+        let mut settings = json!({
+            "tunnel_options": {
+                "wireguard": {
+                    "daita": true,
+                    "daita_level": 7
+                }
+            },
+            "settings_version": 18
+        });
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(settings["tunnel_options"]["wireguard"]["daita_level"], 7);
+        assert_eq!(settings["settings_version"], 19);
+    }
+
+    #[test]
+    fn test_v18_to_v19_migration_skips_other_versions() {
+        let mut settings = json!({
+            "tunnel_options": {
+                "wireguard": {}
+            },
+            "settings_version": 17
+        });
+        let old_settings = settings.clone();
+        migrate(&mut settings).unwrap();
+
+        assert_eq!(settings, old_settings);
+    }
+}
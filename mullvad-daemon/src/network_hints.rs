@@ -0,0 +1,276 @@
+//! Remembers which retry entry last managed to establish a tunnel on each network, so that
+//! the next connection on the same network can skip the entries that are known not to work.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use mullvad_relay_selector::EntrySpecificConstraints;
+use mullvad_types::network_hints::{NetworkFingerprint, NetworkHint, NetworkHints};
+use talpid_routing::RouteManagerHandle;
+use talpid_types::ErrorExt;
+use tokio::{
+    fs,
+    io::{self, AsyncWriteExt},
+    sync::Mutex,
+};
+
+const NETWORK_HINTS_FILE: &str = "network-hints.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize network hints")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Unable to write network hints file")]
+    Write(#[source] io::Error),
+}
+
+/// Persistent store of [`NetworkHint`]s, backed by a file in the cache directory.
+#[derive(Clone)]
+pub struct NetworkHintStore {
+    hints: Arc<Mutex<NetworkHints>>,
+    path: PathBuf,
+    route_manager: RouteManagerHandle,
+}
+
+impl NetworkHintStore {
+    /// Load hints from the cache directory. If the file is missing or cannot be parsed, the store
+    /// starts out empty.
+    pub async fn load(cache_dir: &Path, route_manager: RouteManagerHandle) -> Self {
+        let path = cache_dir.join(NETWORK_HINTS_FILE);
+        let hints = match fs::read(&path).await {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|error| {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse network hints")
+                );
+                NetworkHints::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => NetworkHints::default(),
+            Err(error) => {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read network hints")
+                );
+                NetworkHints::default()
+            }
+        };
+        NetworkHintStore {
+            hints: Arc::new(Mutex::new(hints)),
+            path,
+            route_manager,
+        }
+    }
+
+    /// Identify the network that non-tunnel traffic is currently routed through.
+    pub async fn current_network(&self) -> Option<NetworkFingerprint> {
+        fingerprint(&self.route_manager).await
+    }
+
+    /// Returns the retry entry that last succeeded on `network`, if any.
+    pub async fn get(&self, network: &NetworkFingerprint) -> Option<EntrySpecificConstraints> {
+        let hints = self.hints.lock().await;
+        hints.get(network).map(NetworkHint::entry_constraints)
+    }
+
+    /// Remember that `entry` successfully established a tunnel on `network`.
+    pub async fn remember(
+        &self,
+        network: NetworkFingerprint,
+        entry: &EntrySpecificConstraints,
+    ) -> Result<(), Error> {
+        let mut hints = self.hints.lock().await;
+        if hints
+            .get(&network)
+            .map(NetworkHint::entry_constraints)
+            .as_ref()
+            != Some(entry)
+        {
+            log::debug!("Remembering successful entry parameters for network {network}");
+        }
+        hints.insert(NetworkHint::new(network, entry));
+        self.save(&hints).await
+    }
+
+    /// Returns all remembered hints, most recently successful first.
+    pub async fn list(&self) -> NetworkHints {
+        self.hints.lock().await.clone()
+    }
+
+    /// Forget all remembered hints.
+    pub async fn clear(&self) -> Result<(), Error> {
+        let mut hints = self.hints.lock().await;
+        hints.clear();
+        self.save(&hints).await
+    }
+
+    /// Write `hints` to the cache file. The hints reveal which networks the device has been on,
+    /// so the file is only readable by the daemon.
+    async fn save(&self, hints: &NetworkHints) -> Result<(), Error> {
+        let contents = serde_json::to_vec_pretty(hints).map_err(Error::Serialize)?;
+
+        let mut options = fs::OpenOptions::new();
+        #[cfg(unix)]
+        options.mode(0o600);
+        let mut file = options
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)
+            .await
+            .map_err(Error::Write)?;
+
+        // The mode is only applied when the file is created, so fix files written by older
+        // versions as well
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await
+                .map_err(Error::Write)?;
+        }
+
+        file.write_all(&contents).await.map_err(Error::Write)?;
+        file.flush().await.map_err(Error::Write)
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn fingerprint(route_manager: &RouteManagerHandle) -> Option<NetworkFingerprint> {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    // Any public address will do. Using the tunnel fwmark means that the lookup ignores the
+    // routes of the tunnel itself.
+    const PUBLIC_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1));
+
+    let route = route_manager
+        .get_destination_route(PUBLIC_ADDRESS, Some(route_manager.fwmark()))
+        .await
+        .inspect_err(|error| {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to look up default route")
+            )
+        })
+        .ok()??;
+    let node = route.get_node();
+    let gateway = node.get_address()?;
+    let interface = node.get_device()?.to_owned();
+
+    let gateway_mac = fs::read_to_string("/proc/net/arp")
+        .await
+        .ok()
+        .and_then(|table| parse_arp_table(&table, gateway, &interface));
+
+    Some(NetworkFingerprint {
+        interface,
+        gateway,
+        gateway_mac,
+    })
+}
+
+/// Find the link-layer address of `gateway` on `interface` in the contents of `/proc/net/arp`.
+#[cfg(target_os = "linux")]
+fn parse_arp_table(table: &str, gateway: std::net::IpAddr, interface: &str) -> Option<String> {
+    table.lines().skip(1).find_map(|line| {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let [ip, _hw_type, _flags, mac, _mask, device] = columns[..] else {
+            return None;
+        };
+        (ip.parse() == Ok(gateway) && device == interface && mac != "00:00:00:00:00:00")
+            .then(|| mac.to_owned())
+    })
+}
+
+#[cfg(target_os = "macos")]
+async fn fingerprint(route_manager: &RouteManagerHandle) -> Option<NetworkFingerprint> {
+    let (v4_route, v6_route) = route_manager
+        .get_default_routes()
+        .await
+        .inspect_err(|error| {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to look up default route")
+            )
+        })
+        .ok()?;
+    let route = v4_route.or(v6_route)?;
+
+    let gateway_mac = match route_manager.get_default_gateway().await {
+        Ok((v4_gateway, v6_gateway)) => [v4_gateway, v6_gateway]
+            .into_iter()
+            .flatten()
+            .find(|gateway| gateway.ip_address == route.router_ip)
+            .map(|gateway| gateway.mac_address.to_string()),
+        Err(_) => None,
+    };
+
+    Some(NetworkFingerprint {
+        interface: route.interface,
+        gateway: route.router_ip,
+        gateway_mac,
+    })
+}
+
+#[cfg(target_os = "windows")]
+async fn fingerprint(_route_manager: &RouteManagerHandle) -> Option<NetworkFingerprint> {
+    use talpid_windows::net::{AddressFamily, alias_from_luid};
+
+    let route = [AddressFamily::Ipv4, AddressFamily::Ipv6]
+        .into_iter()
+        .find_map(|family| {
+            talpid_routing::get_best_default_route(family)
+                .ok()
+                .flatten()
+        })?;
+    let interface = alias_from_luid(&route.iface)
+        .ok()?
+        .to_string_lossy()
+        .into_owned();
+
+    Some(NetworkFingerprint {
+        interface,
+        gateway: route.gateway.ip(),
+        gateway_mac: None,
+    })
+}
+
+#[cfg(target_os = "android")]
+async fn fingerprint(_route_manager: &RouteManagerHandle) -> Option<NetworkFingerprint> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    const ARP_TABLE: &str = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.1      0x1         0x2         00:11:22:33:44:55     *        wlan0
+10.0.0.1         0x1         0x2         66:77:88:99:aa:bb     *        eth0
+192.168.1.1      0x1         0x2         cc:dd:ee:ff:00:11     *        eth0
+10.0.0.2         0x1         0x0         00:00:00:00:00:00     *        eth0
+";
+
+    #[test]
+    fn test_parse_arp_table() {
+        let gateway = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1));
+        assert_eq!(
+            parse_arp_table(ARP_TABLE, gateway, "eth0").as_deref(),
+            Some("cc:dd:ee:ff:00:11")
+        );
+        assert_eq!(
+            parse_arp_table(ARP_TABLE, gateway, "wlan0").as_deref(),
+            Some("00:11:22:33:44:55")
+        );
+    }
+
+    #[test]
+    fn test_parse_arp_table_incomplete_entry() {
+        let gateway = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+        assert_eq!(parse_arp_table(ARP_TABLE, gateway, "eth0"), None);
+    }
+}
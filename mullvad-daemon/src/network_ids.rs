@@ -0,0 +1,224 @@
+//! The tunnel interface name, routing table and firewall marks that the daemon uses on Linux.
+//!
+//! These must not be used by any other program. The defaults can be overridden with environment
+//! variables, so that the daemon can run alongside programs that happen to use the same ones.
+
+use std::env;
+
+/// Name of the tunnel interface.
+pub const TUNNEL_INTERFACE_VAR: &str = "MULLVAD_TUNNEL_INTERFACE";
+/// ID of the routing table that routes traffic through the tunnel.
+pub const ROUTING_TABLE_VAR: &str = "MULLVAD_ROUTING_TABLE";
+/// ID of the routing table that routes firewall exceptions through their interfaces.
+pub const EXCEPTION_ROUTING_TABLE_VAR: &str = "MULLVAD_EXCEPTION_ROUTING_TABLE";
+/// Firewall mark of traffic that is allowed outside the tunnel.
+pub const FWMARK_VAR: &str = "MULLVAD_FWMARK";
+/// Connection tracking mark of split tunneled connections.
+pub const SPLIT_TUNNEL_MARK_VAR: &str = "MULLVAD_SPLIT_TUNNEL_MARK";
+
+/// Longest interface name that the kernel accepts, `IFNAMSIZ` minus the nul terminator.
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Routing tables that are reserved by the kernel: unspecified, default, main and local.
+const RESERVED_TABLE_IDS: [u32; 4] = [0, 253, 254, 255];
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum Error {
+    #[error("{var}={value} is not a valid interface name")]
+    InvalidInterfaceName { var: &'static str, value: String },
+
+    #[error("{var}={value} is not a non-zero 32-bit number")]
+    InvalidNumber { var: &'static str, value: String },
+
+    #[error("{var}={value} is a routing table that is reserved by the kernel")]
+    ReservedTable { var: &'static str, value: String },
+
+    #[error("The firewall mark and the split tunnel mark must be different")]
+    SameMarks,
+
+    #[error("The tunnel routing table and the exception routing table must be different")]
+    SameTables,
+}
+
+/// Identifiers of the network resources that the daemon creates on Linux.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkIdentifiers {
+    pub tunnel_interface: String,
+    pub table_id: u32,
+    pub exception_table_id: u32,
+    pub fwmark: u32,
+    pub split_tunnel_mark: u32,
+}
+
+impl Default for NetworkIdentifiers {
+    fn default() -> Self {
+        Self {
+            tunnel_interface: mullvad_types::TUNNEL_INTERFACE_NAME.to_owned(),
+            table_id: mullvad_types::TUNNEL_TABLE_ID,
+            exception_table_id: mullvad_types::EXCEPTION_TABLE_ID,
+            fwmark: mullvad_types::TUNNEL_FWMARK,
+            split_tunnel_mark: talpid_core::split_tunnel::DEFAULT_MARK,
+        }
+    }
+}
+
+impl NetworkIdentifiers {
+    /// Read the identifiers from the environment. Defaults are used for variables that are not
+    /// set.
+    pub fn from_env_vars() -> Result<Self, Error> {
+        Self::from_vars(|var| env::var(var).ok())
+    }
+
+    fn from_vars(read_var: impl Fn(&'static str) -> Option<String>) -> Result<Self, Error> {
+        let mut ids = Self::default();
+
+        if let Some(name) = read_var(TUNNEL_INTERFACE_VAR) {
+            if !is_valid_interface_name(&name) {
+                return Err(Error::InvalidInterfaceName {
+                    var: TUNNEL_INTERFACE_VAR,
+                    value: name,
+                });
+            }
+            ids.tunnel_interface = name;
+        }
+        if let Some(value) = read_var(ROUTING_TABLE_VAR) {
+            ids.table_id = parse_number(ROUTING_TABLE_VAR, &value)?;
+            if RESERVED_TABLE_IDS.contains(&ids.table_id) {
+                return Err(Error::ReservedTable {
+                    var: ROUTING_TABLE_VAR,
+                    value,
+                });
+            }
+        }
+        if let Some(value) = read_var(EXCEPTION_ROUTING_TABLE_VAR) {
+            ids.exception_table_id = parse_number(EXCEPTION_ROUTING_TABLE_VAR, &value)?;
+            if RESERVED_TABLE_IDS.contains(&ids.exception_table_id) {
+                return Err(Error::ReservedTable {
+                    var: EXCEPTION_ROUTING_TABLE_VAR,
+                    value,
+                });
+            }
+        }
+        if let Some(value) = read_var(FWMARK_VAR) {
+            ids.fwmark = parse_number(FWMARK_VAR, &value)?;
+        }
+        if let Some(value) = read_var(SPLIT_TUNNEL_MARK_VAR) {
+            ids.split_tunnel_mark = parse_number(SPLIT_TUNNEL_MARK_VAR, &value)?;
+        }
+        if ids.fwmark == ids.split_tunnel_mark {
+            return Err(Error::SameMarks);
+        }
+        if ids.table_id == ids.exception_table_id {
+            return Err(Error::SameTables);
+        }
+
+        if ids != Self::default() {
+            log::info!(
+                "Using tunnel interface {}, routing tables {} and {}, fwmark {:#x} and split tunnel \
+                 mark {:#x}",
+                ids.tunnel_interface,
+                ids.table_id,
+                ids.exception_table_id,
+                ids.fwmark,
+                ids.split_tunnel_mark
+            );
+        }
+        Ok(ids)
+    }
+}
+
+/// Parse a non-zero number in decimal, or in hexadecimal with a `0x` prefix, the way `ip rule`
+/// does.
+fn parse_number(var: &'static str, value: &str) -> Result<u32, Error> {
+    let number = match value.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    match number {
+        Ok(number) if number != 0 => Ok(number),
+        _ => Err(Error::InvalidNumber {
+            var,
+            value: value.to_owned(),
+        }),
+    }
+}
+
+/// Check that `name` would be accepted as an interface name by the kernel.
+fn is_valid_interface_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_INTERFACE_NAME_LEN
+        && name != "."
+        && name != ".."
+        && !name
+            .chars()
+            .any(|c| c == '/' || c == ':' || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn from_vars(vars: &[(&'static str, &str)]) -> Result<NetworkIdentifiers, Error> {
+        let vars: HashMap<_, _> = vars.iter().copied().collect();
+        NetworkIdentifiers::from_vars(|var| vars.get(var).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(from_vars(&[]), Ok(NetworkIdentifiers::default()));
+    }
+
+    #[test]
+    fn test_overrides() {
+        let ids = from_vars(&[
+            (TUNNEL_INTERFACE_VAR, "wg-mullvad1"),
+            (ROUTING_TABLE_VAR, "1000"),
+            (EXCEPTION_ROUTING_TABLE_VAR, "1001"),
+            (FWMARK_VAR, "0x10000"),
+            (SPLIT_TUNNEL_MARK_VAR, "0xf42"),
+        ]);
+        assert_eq!(
+            ids,
+            Ok(NetworkIdentifiers {
+                tunnel_interface: "wg-mullvad1".to_owned(),
+                table_id: 1000,
+                exception_table_id: 1001,
+                fwmark: 0x10000,
+                split_tunnel_mark: 0xf42,
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_values() {
+        assert!(matches!(
+            from_vars(&[(TUNNEL_INTERFACE_VAR, "wg0-mullvad-too-long")]),
+            Err(Error::InvalidInterfaceName { .. })
+        ));
+        assert!(matches!(
+            from_vars(&[(TUNNEL_INTERFACE_VAR, "wg0 mullvad")]),
+            Err(Error::InvalidInterfaceName { .. })
+        ));
+        assert!(matches!(
+            from_vars(&[(ROUTING_TABLE_VAR, "main")]),
+            Err(Error::InvalidNumber { .. })
+        ));
+        assert!(matches!(
+            from_vars(&[(ROUTING_TABLE_VAR, "254")]),
+            Err(Error::ReservedTable { .. })
+        ));
+        assert!(matches!(
+            from_vars(&[(FWMARK_VAR, "0")]),
+            Err(Error::InvalidNumber { .. })
+        ));
+        assert_eq!(
+            from_vars(&[(SPLIT_TUNNEL_MARK_VAR, "0x6d6f6c65")]),
+            Err(Error::SameMarks)
+        );
+        assert_eq!(
+            from_vars(&[(EXCEPTION_ROUTING_TABLE_VAR, "0x6d6f6c65")]),
+            Err(Error::SameTables)
+        );
+    }
+}
@@ -2,7 +2,11 @@ use mullvad_types::{
     access_method::Error as ApiAccessMethodError,
     custom_list::Error as CustomListError,
     relay_constraints::{Multihop, RelayConstraints, RelaySettings, WireguardConstraints},
-    settings::{DnsState, Settings, SettingsKey, SettingsKeyList},
+    settings::{
+        DnsState, Settings, SettingsKey, SettingsKeyList,
+        profile::{Error as ProfileError, SettingsProfile, SettingsProfiles},
+    },
+    wireguard::QuantumResistantState,
 };
 use std::{
     fmt::{self, Display},
@@ -20,6 +24,7 @@ use tokio::{
 pub mod patch;
 
 const SETTINGS_FILE: &str = "settings.json";
+const PROFILES_FILE: &str = "settings-profiles.json";
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -43,6 +48,15 @@ pub enum Error {
 
     #[error("Failed to parse IP network from string: {0}")]
     ParseIp(String),
+
+    #[error("Invalid settings profile operation")]
+    Profile(#[source] ProfileError),
+
+    #[error(
+        "A custom entry server cannot be combined with quantum resistance or DAITA. Turn them off \
+         first"
+    )]
+    CustomEntryConflict,
 }
 
 /// Converts an [Error] to a management interface status
@@ -65,6 +79,8 @@ impl From<Error> for mullvad_management_interface::Status {
                 let api_access_method_err = *err.downcast::<ApiAccessMethodError>().unwrap();
                 handle_api_access_method_error(api_access_method_err)
             }
+            Error::Profile(error) => handle_profile_error(error),
+            Error::CustomEntryConflict => Status::new(Code::InvalidArgument, error.to_string()),
             Error::SerializeError(..)
             | Error::ParseError(..)
             | Error::UpdateFailed(..)
@@ -112,12 +128,25 @@ fn handle_custom_list_error(
     }
 }
 
+fn handle_profile_error(profile_err: ProfileError) -> mullvad_management_interface::Status {
+    use mullvad_management_interface::Status;
+    match profile_err {
+        error @ ProfileError::DuplicateName => Status::already_exists(error.to_string()),
+        error @ ProfileError::ProfileNotFound => Status::not_found(error.to_string()),
+        error @ (ProfileError::InvalidName | ProfileError::NoSettings) => {
+            Status::invalid_argument(error.to_string())
+        }
+    }
+}
+
 type ChangeListener =
     Box<dyn FnMut(&Settings) -> Pin<Box<dyn Future<Output = ()> + Send + Sync>> + Send + Sync>;
 
 pub struct SettingsPersister {
     settings: Settings,
     path: PathBuf,
+    profiles: SettingsProfiles,
+    profiles_path: PathBuf,
     on_change_listeners: Vec<ChangeListener>,
 }
 
@@ -132,10 +161,14 @@ impl SettingsPersister {
             settings,
             should_save,
         } = Self::load_inner(|| Self::load_from_file(&path)).await;
+        let profiles_path = settings_dir.join(PROFILES_FILE);
+        let profiles = Self::load_profiles(&profiles_path).await;
 
         let mut persister = SettingsPersister {
             settings,
             path,
+            profiles,
+            profiles_path,
             on_change_listeners: vec![],
         };
 
@@ -235,6 +268,34 @@ impl SettingsPersister {
         serde_json::from_slice(bytes).map_err(Error::ParseError)
     }
 
+    /// Loads the settings profiles. Returns no profiles if they cannot be read.
+    async fn load_profiles(path: &Path) -> SettingsProfiles {
+        let bytes = match fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => {
+                return SettingsProfiles::default();
+            }
+            Err(error) => {
+                let error = Error::ReadError(path.display().to_string(), error);
+                log::error!("{}", error.display_chain());
+                return SettingsProfiles::default();
+            }
+        };
+        serde_json::from_slice(&bytes).unwrap_or_else(|error| {
+            log::error!(
+                "{}",
+                Error::ParseError(error).display_chain_with_msg("Ignoring settings profiles")
+            );
+            SettingsProfiles::default()
+        })
+    }
+
+    async fn save_profiles(path: &Path, profiles: &SettingsProfiles) -> Result<(), Error> {
+        log::debug!("Writing settings profiles to {}", path.display());
+        let buffer = serde_json::to_string_pretty(profiles).map_err(Error::SerializeError)?;
+        Self::save_bytes(path, &buffer).await
+    }
+
     async fn save(&mut self) -> Result<(), Error> {
         Self::save_inner(&self.path, &self.settings).await
     }
@@ -266,44 +327,14 @@ impl SettingsPersister {
     pub async fn reset(&mut self, preserved: SettingsKeyList) -> Result<(), Error> {
         let old_settings = std::mem::replace(&mut self.settings, Self::default_settings());
 
-        for key in preserved.keys {
-            match key {
-                SettingsKey::RelaySettings => {
-                    self.settings.relay_settings = old_settings.relay_settings.clone()
-                }
-                SettingsKey::ObfuscationSettings => {
-                    self.settings.obfuscation_settings = old_settings.obfuscation_settings.clone()
-                }
-                SettingsKey::CustomLists => {
-                    self.settings.custom_lists = old_settings.custom_lists.clone()
-                }
-                SettingsKey::ApiAccessMethods => {
-                    self.settings.api_access_methods = old_settings.api_access_methods.clone()
-                }
-                SettingsKey::UpdateDefaultLocation => {
-                    self.settings.update_default_location = old_settings.update_default_location
-                }
-                SettingsKey::AllowLan => self.settings.allow_lan = old_settings.allow_lan,
-                #[cfg(not(target_os = "android"))]
-                SettingsKey::LockdownMode => {
-                    self.settings.lockdown_mode = old_settings.lockdown_mode
-                }
-                SettingsKey::AutoConnect => self.settings.auto_connect = old_settings.auto_connect,
-                SettingsKey::TunnelOptions => {
-                    self.settings.tunnel_options = old_settings.tunnel_options.clone()
-                }
-                SettingsKey::RelayOverrides => {
-                    self.settings.relay_overrides = old_settings.relay_overrides.clone()
-                }
-                SettingsKey::ShowBetaReleases => {
-                    self.settings.show_beta_releases = old_settings.show_beta_releases
-                }
-                #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-                SettingsKey::SplitTunnel => {
-                    self.settings.split_tunnel = old_settings.split_tunnel.clone()
-                }
-                SettingsKey::Recents => self.settings.recents = old_settings.recents.clone(),
-            }
+        for key in &preserved.keys {
+            self.settings.copy_setting(&old_settings, key);
+        }
+        if custom_entry_conflict(&self.settings) {
+            log::info!("Turning off quantum resistance and DAITA for the preserved custom entry");
+            let options = &mut self.settings.tunnel_options.wireguard;
+            options.quantum_resistant = QuantumResistantState::Off;
+            options.daita = false;
         }
 
         #[cfg(not(test))]
@@ -429,6 +460,14 @@ impl SettingsPersister {
             .map_err(Box::from)
             .map_err(Error::UpdateFailed)?;
 
+        // Settings that conflicted before, e.g. when they were saved by an older version, may
+        // still be changed
+        if custom_entry_conflict(&new_settings) && !custom_entry_conflict(&self.settings) {
+            return Err(Error::CustomEntryConflict);
+        }
+
+        self.deactivate_diverged_profile(&mut new_settings);
+
         if self.settings == new_settings {
             return Ok(false);
         }
@@ -441,6 +480,89 @@ impl SettingsPersister {
         Ok(true)
     }
 
+    pub const fn profiles(&self) -> &SettingsProfiles {
+        &self.profiles
+    }
+
+    /// Save the current values of the settings in `keys` as a new profile.
+    pub async fn create_profile(
+        &mut self,
+        name: String,
+        keys: Vec<SettingsKey>,
+    ) -> Result<(), Error> {
+        let profile = SettingsProfile::new(name, keys, &self.settings).map_err(Error::Profile)?;
+        let mut profiles = self.profiles.clone();
+        profiles.add(profile).map_err(Error::Profile)?;
+        Self::save_profiles(&self.profiles_path, &profiles).await?;
+        self.profiles = profiles;
+        Ok(())
+    }
+
+    pub async fn delete_profile(&mut self, name: &str) -> Result<(), Error> {
+        let mut profiles = self.profiles.clone();
+        profiles.remove(name).map_err(Error::Profile)?;
+        Self::save_profiles(&self.profiles_path, &profiles).await?;
+        self.profiles = profiles;
+
+        if self.settings.active_settings_profile.as_deref() == Some(name) {
+            self.update(|settings| settings.active_settings_profile = None)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Delete all settings profiles.
+    pub async fn clear_profiles(&mut self) -> Result<(), Error> {
+        self.profiles = SettingsProfiles::default();
+        match fs::remove_file(&self.profiles_path).await {
+            Ok(()) => Ok(()),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(error) => Err(Error::DeleteError(
+                self.profiles_path.display().to_string(),
+                error,
+            )),
+        }
+    }
+
+    /// Return the settings that would change if the profile named `name` was activated.
+    pub fn diff_profile(&self, name: &str) -> Result<Vec<SettingsKey>, Error> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or(Error::Profile(ProfileError::ProfileNotFound))?;
+        Ok(profile.diff(&self.settings))
+    }
+
+    /// Apply all settings in the profile named `name` in a single update, so that listeners are
+    /// only notified once.
+    pub async fn activate_profile(&mut self, name: &str) -> Result<MadeChanges, Error> {
+        let profile = self
+            .profiles
+            .get(name)
+            .ok_or(Error::Profile(ProfileError::ProfileNotFound))?
+            .clone();
+        self.update(move |settings| {
+            profile.apply(settings);
+            settings.active_settings_profile = Some(profile.name);
+        })
+        .await
+    }
+
+    /// Forget the active profile if `settings` no longer match it.
+    fn deactivate_diverged_profile(&self, settings: &mut Settings) {
+        let Some(name) = &settings.active_settings_profile else {
+            return;
+        };
+        if !self
+            .profiles
+            .get(name)
+            .is_some_and(|profile| profile.matches(settings))
+        {
+            log::debug!("Settings no longer match settings profile \"{name}\"");
+            settings.active_settings_profile = None;
+        }
+    }
+
     /// Return a compact summary of important settings
     pub fn summary(&self) -> SettingsSummary<'_> {
         SettingsSummary {
@@ -608,6 +730,17 @@ impl SettingsSummary<'_> {
     }
 }
 
+/// Return whether a custom entry server is combined with options that it does not support.
+/// Quantum resistance and DAITA are negotiated with a Mullvad entry relay.
+fn custom_entry_conflict(settings: &Settings) -> bool {
+    let RelaySettings::Normal(constraints) = &settings.relay_settings else {
+        return false;
+    };
+    let options = &settings.tunnel_options.wireguard;
+    constraints.wireguard_constraints.custom_entry.is_some()
+        && (options.quantum_resistant.enabled() || options.daita)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -808,6 +941,8 @@ mod test {
             on_change_listeners: vec![],
             path: PathBuf::new(),
             settings: Settings::default(),
+            profiles: SettingsProfiles::default(),
+            profiles_path: PathBuf::new(),
         };
         settings.settings.allow_lan = true;
         settings
@@ -822,6 +957,42 @@ mod test {
         assert!(settings.settings.custom_lists.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_custom_entry_conflict() {
+        use mullvad_types::relay_constraints::CustomEntry;
+
+        let mut settings = SettingsPersister {
+            on_change_listeners: vec![],
+            path: PathBuf::new(),
+            settings: Settings::default(),
+            profiles: SettingsProfiles::default(),
+            profiles_path: PathBuf::new(),
+        };
+        let custom_entry = CustomEntry {
+            endpoint: "192.0.2.1:51820".parse().unwrap(),
+            public_key: talpid_types::net::wireguard::PrivateKey::new_from_random().public_key(),
+        };
+        let set_custom_entry = |settings: &mut Settings| {
+            if let RelaySettings::Normal(constraints) = &mut settings.relay_settings {
+                constraints.wireguard_constraints.custom_entry = Some(custom_entry.clone());
+            }
+        };
+
+        // Quantum resistance is on by default
+        let result = settings.update(set_custom_entry).await;
+        assert!(matches!(result, Err(Error::CustomEntryConflict)));
+        assert!(!custom_entry_conflict(&settings.settings));
+
+        // Resetting the tunnel options must not turn quantum resistance back on
+        settings.settings.tunnel_options.wireguard.quantum_resistant = QuantumResistantState::Off;
+        set_custom_entry(&mut settings.settings);
+        let preserved = SettingsKeyList {
+            keys: [SettingsKey::RelaySettings].into(),
+        };
+        settings.reset(preserved).await.unwrap();
+        assert!(!custom_entry_conflict(&settings.settings));
+    }
+
     #[tokio::test]
     async fn test_preserve_reset() {
         let custom_list = CustomList::new("testlist".to_string()).unwrap();
@@ -829,6 +1000,8 @@ mod test {
             on_change_listeners: vec![],
             path: PathBuf::new(),
             settings: Settings::default(),
+            profiles: SettingsProfiles::default(),
+            profiles_path: PathBuf::new(),
         };
         settings.settings.allow_lan = true;
         settings
@@ -12,13 +12,22 @@
 //!
 //! Permitted settings and merge strategies are defined in the [PERMITTED_SUBKEYS] constant.
 //!
+//! [preview_patch] performs the same steps without replacing the settings, and reports which
+//! settings would change.
+//!
 //! This implementation must be kept in sync with the
 //! [spec](../../../docs/settings-patch-format.md).
 
 use super::SettingsPersister;
-use mullvad_types::settings::Settings;
+use mullvad_types::{
+    access_method,
+    custom_list::CustomList,
+    relay_constraints::ObfuscationSettings,
+    settings::{Settings, SettingsKey, SettingsPatchPreview},
+};
 #[cfg(test)]
 use std::assert_matches;
+use talpid_types::ErrorExt;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -43,6 +52,9 @@ pub enum Error {
     /// Recursion limit reached
     #[error("Maximum JSON object depth reached")]
     RecursionLimit,
+    /// All built-in API access methods would be disabled
+    #[error("At least one of the built-in API access methods must remain enabled")]
+    NoBuiltInAccessMethod,
     /// Settings error
     #[error("Settings error")]
     Settings(#[source] super::Error),
@@ -58,7 +70,8 @@ impl From<Error> for mullvad_management_interface::Status {
             | Error::UnknownOrProhibitedKey(_)
             | Error::ParsePatch(_)
             | Error::DeserializePatched(_)
-            | Error::RecursionLimit => Status::invalid_argument(error.to_string()),
+            | Error::RecursionLimit
+            | Error::NoBuiltInAccessMethod => Status::invalid_argument(error.to_string()),
             Error::Settings(error) => Status::from(error),
             Error::SerializeSettings(error) | Error::SerializeValue(error) => {
                 Status::internal(error.to_string())
@@ -120,15 +133,85 @@ enum PermittedKeyValue {
     Any,
 }
 
-const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[(
-    "relay_overrides",
-    PermittedKey::array(&PermittedKey::object(&[
-        ("hostname", PermittedKey::any()),
-        ("ipv4_addr_in", PermittedKey::any()),
-        ("ipv6_addr_in", PermittedKey::any()),
-    ]))
-    .merge_strategy(MergeStrategy::Custom(merge_relay_overrides)),
-)]);
+const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[
+    (
+        "relay_overrides",
+        PermittedKey::array(&PermittedKey::object(&[
+            ("hostname", PermittedKey::any()),
+            ("ipv4_addr_in", PermittedKey::any()),
+            ("ipv6_addr_in", PermittedKey::any()),
+        ]))
+        .merge_strategy(MergeStrategy::Custom(merge_relay_overrides)),
+    ),
+    (
+        "obfuscation_settings",
+        PermittedKey::object(&[
+            ("selected_obfuscation", PermittedKey::any()),
+            ("udp2tcp", PermittedKey::any()),
+            ("shadowsocks", PermittedKey::any()),
+            ("wireguard_port", PermittedKey::any()),
+            ("lwo", PermittedKey::any()),
+            ("race", PermittedKey::any()),
+        ]),
+    ),
+    (
+        "tunnel_options",
+        PermittedKey::object(&[(
+            "wireguard",
+            PermittedKey::object(&[
+                ("daita", PermittedKey::any()),
+                ("daita_level", PermittedKey::any()),
+            ]),
+        )]),
+    ),
+    (
+        "custom_lists",
+        PermittedKey::object(&[(
+            "custom_lists",
+            PermittedKey::array(&PermittedKey::object(&[
+                ("name", PermittedKey::any()),
+                ("locations", PermittedKey::any()),
+            ]))
+            .merge_strategy(MergeStrategy::Custom(merge_custom_lists)),
+        )]),
+    ),
+    (
+        "api_access_methods",
+        PermittedKey::object(&[
+            (
+                "direct",
+                PermittedKey::object(&[("enabled", PermittedKey::any())]),
+            ),
+            (
+                "mullvad_bridges",
+                PermittedKey::object(&[("enabled", PermittedKey::any())]),
+            ),
+            (
+                "encrypted_dns_proxy",
+                PermittedKey::object(&[("enabled", PermittedKey::any())]),
+            ),
+            (
+                "custom",
+                PermittedKey::array(&PermittedKey::object(&[
+                    ("name", PermittedKey::any()),
+                    ("enabled", PermittedKey::any()),
+                    ("access_method", PermittedKey::any()),
+                ]))
+                .merge_strategy(MergeStrategy::Custom(merge_custom_access_methods)),
+            ),
+        ]),
+    ),
+]);
+
+/// Settings that may be changed by a patch. This must cover every key in [PERMITTED_SUBKEYS].
+const PATCHABLE_SETTINGS: &[SettingsKey] = &[
+    SettingsKey::RelayOverrides,
+    SettingsKey::ObfuscationSettings,
+    SettingsKey::TunnelOptions,
+    SettingsKey::CustomLists,
+    SettingsKey::ApiAccessMethods,
+];
+
 /// Prohibit stack overflow via excessive recursion. It might be possible to forgo this when
 /// tail-call optimization can be enforced?
 const RECURSE_LIMIT: usize = 15;
@@ -164,23 +247,95 @@ fn export_settings_inner(settings: &Settings) -> Result<serde_json::Value, Error
         );
     }
 
+    if settings.obfuscation_settings != ObfuscationSettings::default() {
+        out.insert(
+            "obfuscation_settings".to_owned(),
+            serde_json::to_value(&settings.obfuscation_settings).map_err(Error::SerializeValue)?,
+        );
+    }
+
+    let wireguard_options = &settings.tunnel_options.wireguard;
+    if wireguard_options.daita || wireguard_options.daita_level.is_some() {
+        out.insert(
+            "tunnel_options".to_owned(),
+            serde_json::json!({ "wireguard": {
+                "daita": wireguard_options.daita,
+                "daita_level": wireguard_options.daita_level,
+            } }),
+        );
+    }
+
+    // IDs are local to each device, so named entries are exported without them
+    let custom_lists = settings
+        .custom_lists
+        .iter()
+        .map(|list| serde_json::to_value(list).map(without_id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::SerializeValue)?;
+    if !custom_lists.is_empty() {
+        out.insert(
+            "custom_lists".to_owned(),
+            serde_json::json!({ "custom_lists": custom_lists }),
+        );
+    }
+
+    let mut access_methods = serde_json::Map::new();
+    let api_access_methods = &settings.api_access_methods;
+    for (key, method) in [
+        ("direct", api_access_methods.direct()),
+        ("mullvad_bridges", api_access_methods.mullvad_bridges()),
+        (
+            "encrypted_dns_proxy",
+            api_access_methods.encrypted_dns_proxy(),
+        ),
+    ] {
+        if !method.enabled {
+            access_methods.insert(key.to_owned(), serde_json::json!({ "enabled": false }));
+        }
+    }
+    let custom_methods = api_access_methods
+        .iter_custom()
+        .map(|method| serde_json::to_value(method).map(without_id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::SerializeValue)?;
+    if !custom_methods.is_empty() {
+        access_methods.insert(
+            "custom".to_owned(),
+            serde_json::Value::Array(custom_methods),
+        );
+    }
+    if !access_methods.is_empty() {
+        out.insert(
+            "api_access_methods".to_owned(),
+            serde_json::Value::Object(access_methods),
+        );
+    }
+
     Ok(serde_json::Value::Object(out))
 }
 
+fn without_id(mut value: serde_json::Value) -> serde_json::Value {
+    if let Some(object) = value.as_object_mut() {
+        object.remove("id");
+    }
+    value
+}
+
 /// Update the settings with the supplied patch. Only settings specified in `PERMITTED_SUBKEYS` can
-/// be updated. All other changes are rejected
+/// be updated. All other changes are rejected. Returns the settings that were changed.
 pub async fn merge_validate_patch(
     settings: &mut SettingsPersister,
     json_patch: &str,
-) -> Result<(), Error> {
+) -> Result<Vec<SettingsKey>, Error> {
     let new_settings = merge_validate_patch_inner(settings, json_patch)?;
+    let changed = changed_settings(settings, &new_settings);
 
     settings
         .update(move |settings| *settings = new_settings)
         .await
         .map_err(Error::Settings)?;
 
-    Ok(())
+    Ok(changed)
 }
 
 fn merge_validate_patch_inner(settings: &Settings, json_patch: &str) -> Result<Settings, Error> {
@@ -194,10 +349,98 @@ fn merge_validate_patch_inner(settings: &Settings, json_patch: &str) -> Result<S
 
     let new_settings: Settings =
         serde_json::from_value(settings_value).map_err(Error::DeserializePatched)?;
+    validate_api_access_methods(settings, &new_settings)?;
 
     Ok(new_settings)
 }
 
+/// Validate the supplied patch and return the settings it would change, without updating the
+/// settings. Unlike [merge_validate_patch], each top-level setting is validated separately, so
+/// that every rejected setting is reported at once.
+pub fn preview_patch(settings: &Settings, json_patch: &str) -> SettingsPatchPreview {
+    preview_patch_inner(settings, json_patch).unwrap_or_else(|error| SettingsPatchPreview {
+        changed: vec![],
+        errors: vec![error.display_chain()],
+    })
+}
+
+fn preview_patch_inner(
+    settings: &Settings,
+    json_patch: &str,
+) -> Result<SettingsPatchPreview, Error> {
+    let mut settings_value: serde_json::Value =
+        serde_json::to_value(settings).map_err(Error::SerializeSettings)?;
+    let patch_value: serde_json::Value =
+        serde_json::from_str(json_patch).map_err(Error::ParsePatch)?;
+    let patch = patch_value.as_object().ok_or(Error::InvalidOrMissingValue(
+        "expected JSON object in patch",
+    ))?;
+
+    let mut errors = vec![];
+    for (key, sub_patch) in patch {
+        let sub_patch = serde_json::Value::Object(serde_json::Map::from_iter([(
+            key.to_owned(),
+            sub_patch.to_owned(),
+        )]));
+        let mut merged_value = settings_value.clone();
+        let result = validate_patch_value(PERMITTED_SUBKEYS, &sub_patch, 0).and_then(|()| {
+            merge_patch_to_value(PERMITTED_SUBKEYS, &mut merged_value, &sub_patch, 0)
+        });
+        match result {
+            Ok(()) => settings_value = merged_value,
+            Err(error) => errors.push(error.display_chain()),
+        }
+    }
+
+    let changed = match serde_json::from_value::<Settings>(settings_value) {
+        Ok(mut new_settings) => {
+            if let Err(error) = validate_api_access_methods(settings, &new_settings) {
+                errors.push(error.display_chain());
+                new_settings.api_access_methods = settings.api_access_methods.clone();
+            }
+            changed_settings(settings, &new_settings)
+        }
+        Err(error) => {
+            errors.push(Error::DeserializePatched(error).display_chain());
+            vec![]
+        }
+    };
+
+    Ok(SettingsPatchPreview { changed, errors })
+}
+
+/// Reject patches that change the API access methods so that none of the built-in methods are
+/// enabled, since the app may then be unable to reach the API at all if the custom methods stop
+/// working.
+fn validate_api_access_methods(current: &Settings, patched: &Settings) -> Result<(), Error> {
+    let methods = &patched.api_access_methods;
+    if methods == &current.api_access_methods {
+        return Ok(());
+    }
+    let built_in = [
+        methods.direct(),
+        methods.mullvad_bridges(),
+        methods.encrypted_dns_proxy(),
+    ];
+    if !built_in.iter().any(|method| method.enabled()) {
+        return Err(Error::NoBuiltInAccessMethod);
+    }
+    Ok(())
+}
+
+/// Return the patchable settings that differ between `current` and `patched`.
+fn changed_settings(current: &Settings, patched: &Settings) -> Vec<SettingsKey> {
+    PATCHABLE_SETTINGS
+        .iter()
+        .filter(|key| {
+            let mut applied = current.clone();
+            applied.copy_setting(patched, key);
+            applied != *current
+        })
+        .cloned()
+        .collect()
+}
+
 /// Replace overrides for existing values in the array if there's a matching hostname. For hostnames
 /// that do not exist, just append the overrides.
 fn merge_relay_overrides(
@@ -261,6 +504,97 @@ fn merge_relay_overrides(
     Ok(serde_json::Value::Array(new_array))
 }
 
+/// Merge custom lists by name. See [merge_named_entries].
+fn merge_custom_lists(
+    current_settings: &serde_json::Value,
+    patch: &serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    merge_named_entries(current_settings, patch, |name| {
+        let list = CustomList::new(name.to_owned())
+            .map_err(|_| Error::InvalidOrMissingValue("custom list name is too long"))?;
+        serde_json::to_value(list.id()).map_err(Error::SerializeValue)
+    })
+}
+
+/// Merge custom API access methods by name. See [merge_named_entries]. Only custom proxies may
+/// be added this way.
+fn merge_custom_access_methods(
+    current_settings: &serde_json::Value,
+    patch: &serde_json::Value,
+) -> Result<serde_json::Value, Error> {
+    let patch_array = patch
+        .as_array()
+        .ok_or(Error::InvalidOrMissingValue("access methods must be array"))?;
+    let only_custom_proxies = patch_array.iter().all(|entry| {
+        entry
+            .get("access_method")
+            .map(|method| method.get("custom").is_some())
+            .unwrap_or(true)
+    });
+    if !only_custom_proxies {
+        return Err(Error::InvalidOrMissingValue(
+            "access method must be a custom proxy",
+        ));
+    }
+
+    merge_named_entries(current_settings, patch, |_name| {
+        serde_json::to_value(access_method::Id::new()).map_err(Error::SerializeValue)
+    })
+}
+
+/// Replace values for existing entries in the array if there's a matching name. Entries whose
+/// names do not exist are appended, and are given a new ID created by `new_id`.
+fn merge_named_entries(
+    current_settings: &serde_json::Value,
+    patch: &serde_json::Value,
+    new_id: impl Fn(&str) -> Result<serde_json::Value, Error>,
+) -> Result<serde_json::Value, Error> {
+    let patch_array = patch
+        .as_array()
+        .ok_or(Error::InvalidOrMissingValue("expected JSON array in patch"))?;
+    let mut new_array = if current_settings.is_null() {
+        vec![]
+    } else {
+        current_settings
+            .as_array()
+            .ok_or(Error::InvalidOrMissingValue(
+                "existing entries should be an array",
+            ))?
+            .clone()
+    };
+
+    for patch_entry in patch_array {
+        let patch_obj = patch_entry
+            .as_object()
+            .ok_or(Error::InvalidOrMissingValue("all entries must be objects"))?;
+        let patch_name = patch_obj
+            .get("name")
+            .and_then(|name| name.as_str())
+            .ok_or(Error::InvalidOrMissingValue("name"))?;
+
+        let existing_obj = new_array.iter_mut().find_map(|value| {
+            value
+                .as_object_mut()
+                .filter(|obj| obj.get("name").and_then(|name| name.as_str()) == Some(patch_name))
+        });
+
+        match existing_obj {
+            Some(existing_obj) => {
+                for (k, v) in patch_obj {
+                    existing_obj.insert(k.to_owned(), v.to_owned());
+                }
+            }
+            None => {
+                let mut new_obj = patch_obj.clone();
+                new_obj.insert("id".to_owned(), new_id(patch_name)?);
+                new_array.push(serde_json::Value::Object(new_obj));
+            }
+        }
+    }
+
+    Ok(serde_json::Value::Array(new_array))
+}
+
 fn merge_patch_to_value(
     permitted_key: &'static PermittedKey,
     current_value: &mut serde_json::Value,
@@ -453,10 +787,138 @@ fn test_overflow() {
 fn test_valid_patch_files() {
     const OVERRIDE_PATCH: &str =
         include_str!("../../../docs/patch-examples/override-relay-ips.json");
+    const CENSORED_NETWORK_PATCH: &str =
+        include_str!("../../../docs/patch-examples/censored-network.json");
 
     let prev_settings = Settings::default();
     let _ = merge_validate_patch_inner(&prev_settings, OVERRIDE_PATCH)
         .expect("failed to apply relay overrides");
+
+    let new_settings = merge_validate_patch_inner(&prev_settings, CENSORED_NETWORK_PATCH)
+        .expect("failed to apply censored network patch");
+    assert!(new_settings.tunnel_options.wireguard.daita);
+    assert!(!new_settings.api_access_methods.direct().enabled);
+    assert_eq!(new_settings.api_access_methods.iter_custom().count(), 1);
+    assert_eq!(new_settings.custom_lists.len(), 1);
+    assert_eq!(
+        changed_settings(&prev_settings, &new_settings),
+        vec![
+            SettingsKey::ObfuscationSettings,
+            SettingsKey::TunnelOptions,
+            SettingsKey::CustomLists,
+            SettingsKey::ApiAccessMethods,
+        ]
+    );
+}
+
+/// Test that security-sensitive settings next to patchable ones are still rejected
+#[test]
+fn test_prohibited_patches() {
+    let settings = Settings::default();
+    for patch in [
+        r#"{ "tunnel_options": { "dns_options": { "state": "custom" } } }"#,
+        r#"{ "tunnel_options": { "wireguard": { "mtu": 1280 } } }"#,
+        r#"{ "api_access_methods": { "direct": { "access_method": { "custom": {} } } } }"#,
+        r#"{ "api_access_methods": { "custom": [ { "name": "a", "access_method": { "built_in": "direct" } } ] } }"#,
+        r#"{ "custom_lists": { "custom_lists": [ { "name": "a", "id": "5c0f2ec8-9b61-4e5f-a5b6-3cd8e1b4e8d1" } ] } }"#,
+        r#"{ "relay_settings": {} }"#,
+    ] {
+        merge_validate_patch_inner(&settings, patch).unwrap_err();
+    }
+}
+
+/// Test that patches may not disable every built-in API access method
+#[test]
+fn test_patch_built_in_access_methods() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "api_access_methods": { "direct": { "enabled": false }, "mullvad_bridges": { "enabled": false } } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert!(
+        new_settings
+            .api_access_methods
+            .encrypted_dns_proxy()
+            .enabled()
+    );
+
+    let patch = r#"{ "api_access_methods": { "direct": { "enabled": false }, "mullvad_bridges": { "enabled": false }, "encrypted_dns_proxy": { "enabled": false }, "custom": [ { "name": "a", "enabled": true, "access_method": { "custom": { "socks5_remote": { "endpoint": "192.0.2.1:1080", "auth": null } } } } ] } }"#;
+    assert_matches!(
+        merge_validate_patch_inner(&settings, patch),
+        Err(Error::NoBuiltInAccessMethod)
+    );
+
+    let preview = preview_patch(&settings, patch);
+    assert!(preview.changed.is_empty());
+    assert_eq!(preview.errors.len(), 1, "errors: {:?}", preview.errors);
+}
+
+#[test]
+fn test_patch_custom_lists() {
+    let patch = r#"{ "custom_lists": { "custom_lists": [ { "name": "a", "locations": [ { "country": "se" } ] } ] } }"#;
+    let settings = merge_validate_patch_inner(&Settings::default(), patch).unwrap();
+    let list_id = settings.custom_lists[0].id();
+
+    // Lists with the same name are updated in place and keep their ID
+    let patch = r#"{ "custom_lists": { "custom_lists": [ { "name": "a", "locations": [ { "country": "no" } ] }, { "name": "b", "locations": [] } ] } }"#;
+    let settings = merge_validate_patch_inner(&settings, patch).unwrap();
+
+    assert_eq!(settings.custom_lists.len(), 2);
+    assert_eq!(settings.custom_lists[0].id(), list_id);
+    assert_eq!(
+        settings.custom_lists[0].locations,
+        [
+            mullvad_types::relay_constraints::GeographicLocationConstraint::Country(
+                "no".to_owned()
+            )
+        ]
+        .into()
+    );
+    assert_ne!(settings.custom_lists[1].id(), list_id);
+
+    let patch = r#"{ "custom_lists": { "custom_lists": [ { "name": "this name is much too long for a custom list", "locations": [] } ] } }"#;
+    assert_matches!(
+        merge_validate_patch_inner(&settings, patch),
+        Err(Error::InvalidOrMissingValue(_))
+    );
+}
+
+#[test]
+fn test_preview_patch() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "tunnel_options": { "wireguard": { "daita": true } }, "allow_lan": true, "relay_overrides": [ { "invalid": 0 } ] }"#;
+    let preview = preview_patch(&settings, patch);
+    assert_eq!(preview.changed, vec![SettingsKey::TunnelOptions]);
+    assert_eq!(preview.errors.len(), 2, "errors: {:?}", preview.errors);
+
+    let patch = r#"{ "tunnel_options": { "wireguard": { "daita": false } } }"#;
+    let preview = preview_patch(&settings, patch);
+    assert!(preview.changed.is_empty());
+    assert!(preview.errors.is_empty());
+
+    let preview = preview_patch(&settings, "[]");
+    assert!(preview.changed.is_empty());
+    assert_eq!(preview.errors.len(), 1);
+}
+
+#[test]
+fn test_patch_daita_level() {
+    let settings = Settings::default();
+
+    let patch = r#"{ "tunnel_options": { "wireguard": { "daita": true, "daita_level": 5 } } }"#;
+    let new_settings = merge_validate_patch_inner(&settings, patch).unwrap();
+    assert!(new_settings.tunnel_options.wireguard.daita);
+    assert_eq!(
+        new_settings
+            .tunnel_options
+            .wireguard
+            .daita_level
+            .map(|level| level.get()),
+        Some(5)
+    );
+
+    let patch = r#"{ "tunnel_options": { "wireguard": { "daita_level": 11 } } }"#;
+    assert!(merge_validate_patch_inner(&settings, patch).is_err());
 }
 
 #[test]
@@ -478,6 +940,43 @@ fn test_patch_export() {
     assert_eq!(exported, expected);
 }
 
+/// Test that exported patches reproduce the patchable settings
+#[test]
+fn test_patch_export_roundtrip() {
+    const CENSORED_NETWORK_PATCH: &str =
+        include_str!("../../../docs/patch-examples/censored-network.json");
+
+    let settings = merge_validate_patch_inner(&Settings::default(), CENSORED_NETWORK_PATCH)
+        .expect("failed to apply censored network patch");
+    let exported = export_settings(&settings).expect("patch export failed");
+    assert!(!exported.contains("\"id\""), "IDs must not be exported");
+
+    let imported = merge_validate_patch_inner(&Settings::default(), &exported)
+        .expect("failed to apply exported patch");
+    assert_eq!(imported.obfuscation_settings, settings.obfuscation_settings);
+    assert_eq!(
+        imported.tunnel_options.wireguard.daita,
+        settings.tunnel_options.wireguard.daita
+    );
+    assert_eq!(
+        imported.tunnel_options.wireguard.daita_level,
+        settings.tunnel_options.wireguard.daita_level
+    );
+    assert_eq!(imported.custom_lists[0].name, settings.custom_lists[0].name);
+    assert_eq!(
+        imported.custom_lists[0].locations,
+        settings.custom_lists[0].locations
+    );
+    assert_eq!(
+        imported.api_access_methods.direct().enabled,
+        settings.api_access_methods.direct().enabled
+    );
+    assert_eq!(
+        imported.api_access_methods.iter_custom().count(),
+        settings.api_access_methods.iter_custom().count()
+    );
+}
+
 #[test]
 fn test_patch_relay_override() {
     const PERMITTED_SUBKEYS: &PermittedKey = &PermittedKey::object(&[(
@@ -5,10 +5,11 @@ use talpid_types::net::wireguard::TunnelParameters;
 use tokio::sync::Mutex;
 
 use mullvad_daemon_relay_selector::relay_selector::RelaySelectorIO;
-use mullvad_relay_selector::{GetRelay, WireguardConfig};
+use mullvad_relay_selector::{EntrySpecificConstraints, GetRelay, WireguardConfig};
 use mullvad_types::{
     endpoint::MullvadEndpoint,
     location::GeoIpLocation,
+    network_hints::NetworkFingerprint,
     relay_constraints::RelaySettings,
     settings::{Settings, TunnelOptions},
 };
@@ -16,7 +17,12 @@ use talpid_core::tunnel_state_machine::TunnelParametersGenerator;
 use talpid_types::net::{obfuscation::Obfuscators, wireguard};
 use talpid_types::{ErrorExt, net::IpAvailability, tunnel::ParameterGenerationError};
 
-use crate::device::{AccountManagerHandle, Error as DeviceError, PrivateAccountAndDevice};
+#[cfg(target_os = "linux")]
+use crate::network_ids::NetworkIdentifiers;
+use crate::{
+    device::{AccountManagerHandle, Error as DeviceError, PrivateAccountAndDevice},
+    network_hints::NetworkHintStore,
+};
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -41,8 +47,14 @@ struct InnerParametersGenerator {
     relay_settings: RelaySettings,
     tunnel_options: TunnelOptions,
     account_manager: AccountManagerHandle,
+    network_hints: NetworkHintStore,
+    #[cfg(target_os = "linux")]
+    network_ids: NetworkIdentifiers,
 
     last_generated_relays: Option<LastSelectedRelays>,
+    last_retry_attempt: u32,
+    /// The relays selected for the last candidate parameters, which have not been used yet.
+    candidate_relays: Option<LastSelectedRelays>,
 }
 
 impl ParametersGenerator {
@@ -52,13 +64,20 @@ impl ParametersGenerator {
         relay_selector: RelaySelectorIO,
         relay_settings: RelaySettings,
         tunnel_options: TunnelOptions,
+        network_hints: NetworkHintStore,
+        #[cfg(target_os = "linux")] network_ids: NetworkIdentifiers,
     ) -> Self {
         Self(Arc::new(Mutex::new(InnerParametersGenerator {
             tunnel_options,
             relay_selector,
             relay_settings,
             account_manager,
+            network_hints,
+            #[cfg(target_os = "linux")]
+            network_ids,
             last_generated_relays: None,
+            last_retry_attempt: 0,
+            candidate_relays: None,
         })))
     }
 
@@ -82,6 +101,38 @@ impl ParametersGenerator {
         relays.server_override
     }
 
+    /// Gets the retry attempt that tunnel parameters were last generated for.
+    pub async fn last_retry_attempt(&self) -> u32 {
+        self.0.lock().await.last_retry_attempt
+    }
+
+    /// Remembers the retry entry that the last tunnel parameters were generated with as the one to
+    /// try first on the network that they were generated on.
+    pub async fn remember_network_hint(&self) {
+        let (network_hints, network, retry_entry) = {
+            let inner = self.0.lock().await;
+            let Some(LastSelectedRelays {
+                network: Some(network),
+                retry_entry: Some(retry_entry),
+                ..
+            }) = inner.last_generated_relays.as_ref()
+            else {
+                return;
+            };
+            (
+                inner.network_hints.clone(),
+                network.clone(),
+                retry_entry.clone(),
+            )
+        };
+        if let Err(error) = network_hints.remember(network, &retry_entry).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to save network hint")
+            );
+        }
+    }
+
     /// Gets the location associated with the last generated tunnel parameters.
     pub async fn get_last_location(&self) -> Option<GeoIpLocation> {
         let inner = self.0.lock().await;
@@ -89,7 +140,9 @@ impl ParametersGenerator {
         let relays = inner.last_generated_relays.as_ref()?;
 
         let (entry, exit) = match &relays.config {
-            WireguardConfig::Singlehop { exit } => (None, exit),
+            WireguardConfig::Singlehop { exit } | WireguardConfig::CustomEntry { exit, .. } => {
+                (None, exit)
+            }
             WireguardConfig::Multihop { exit, entry } => (Some(entry), exit),
         };
         let location = exit.location.clone();
@@ -116,21 +169,66 @@ impl InnerParametersGenerator {
         retry_attempt: u32,
         ip_availability: IpAvailability,
     ) -> Result<TunnelParameters, Error> {
+        self.last_retry_attempt = retry_attempt;
+        let (parameters, relays) = self.select(retry_attempt, ip_availability).await?;
+        self.last_generated_relays = relays;
+        Ok(parameters)
+    }
+
+    async fn generate_candidate(
+        &mut self,
+        ip_availability: IpAvailability,
+    ) -> Result<TunnelParameters, Error> {
+        let (parameters, relays) = self.select(0, ip_availability).await?;
+        self.candidate_relays = relays;
+        Ok(parameters)
+    }
+
+    fn use_candidate(&mut self) {
+        self.last_retry_attempt = 0;
+        self.last_generated_relays = self.candidate_relays.take();
+    }
+
+    /// Select relays and create tunnel parameters for them, without remembering the selection.
+    async fn select(
+        &self,
+        retry_attempt: u32,
+        ip_availability: IpAvailability,
+    ) -> Result<(TunnelParameters, Option<LastSelectedRelays>), Error> {
         // Custom tunnel endpoints bypass relay selection entirely.
         if let RelaySettings::CustomTunnelEndpoint(ref endpoint) = self.relay_settings {
-            self.last_generated_relays = None;
-            return endpoint
+            #[cfg_attr(not(target_os = "linux"), expect(unused_mut))]
+            let mut parameters = endpoint
                 .to_tunnel_parameters(self.tunnel_options.clone())
                 .map_err(|e| {
                     log::error!("Failed to resolve hostname for custom tunnel config: {}", e);
                     Error::ResolveCustomHostname
-                });
+                })?;
+            #[cfg(target_os = "linux")]
+            {
+                parameters.connection.fwmark = Some(self.network_ids.fwmark);
+                parameters.connection.interface_name =
+                    Some(self.network_ids.tunnel_interface.clone());
+            }
+            return Ok((parameters, None));
         }
 
         let data = self.device().await?;
-        let selected_relay = self
-            .relay_selector
-            .get_relay(retry_attempt as usize, ip_availability)?;
+        let network = self.network_hints.current_network().await;
+        let hint = match &network {
+            Some(network) => self.network_hints.get(network).await,
+            None => None,
+        };
+        if retry_attempt == 0
+            && let (Some(network), Some(hint)) = (&network, &hint)
+        {
+            log::debug!("Trying entry parameters that last worked on network {network}: {hint:?}");
+        }
+        let (selected_relay, retry_entry) = self.relay_selector.get_relay_with_hint(
+            retry_attempt as usize,
+            hint,
+            ip_availability,
+        )?;
 
         let GetRelay {
             endpoint,
@@ -140,21 +238,26 @@ impl InnerParametersGenerator {
 
         let server_override = {
             let first_relay = match &inner {
-                WireguardConfig::Singlehop { exit } => exit,
-                WireguardConfig::Multihop { exit: _, entry } => entry,
+                WireguardConfig::Singlehop { exit } => Some(exit),
+                WireguardConfig::Multihop { exit: _, entry } => Some(entry),
+                WireguardConfig::CustomEntry { .. } => None,
             };
-            match endpoint.peer.endpoint {
-                SocketAddr::V4(_) => first_relay.overridden_ipv4,
-                SocketAddr::V6(_) => first_relay.overridden_ipv6,
-            }
+            first_relay.is_some_and(|relay| match endpoint.peer.endpoint {
+                SocketAddr::V4(_) => relay.overridden_ipv4,
+                SocketAddr::V6(_) => relay.overridden_ipv6,
+            })
         };
+        let custom_entry = matches!(inner, WireguardConfig::CustomEntry { .. });
 
-        self.last_generated_relays = Some(LastSelectedRelays {
+        let parameters =
+            self.create_wireguard_tunnel_parameters(endpoint, data, obfuscator, custom_entry);
+        let relays = LastSelectedRelays {
             config: inner,
             server_override,
-        });
-
-        Ok(self.create_wireguard_tunnel_parameters(endpoint, data, obfuscator))
+            network,
+            retry_entry,
+        };
+        Ok((parameters, Some(relays)))
     }
 
     fn create_wireguard_tunnel_parameters(
@@ -162,6 +265,7 @@ impl InnerParametersGenerator {
         endpoint: MullvadEndpoint,
         data: PrivateAccountAndDevice,
         obfuscator_config: Option<Obfuscators>,
+        custom_entry: bool,
     ) -> TunnelParameters {
         let tunnel_ipv4 = data.device.wg_data.addresses.ipv4_address.ip();
         let tunnel_ipv6 = data.device.wg_data.addresses.ipv6_address.ip();
@@ -170,6 +274,21 @@ impl InnerParametersGenerator {
             addresses: vec![IpAddr::from(tunnel_ipv4), IpAddr::from(tunnel_ipv6)],
         };
 
+        let mut options = self
+            .tunnel_options
+            .wireguard
+            .clone()
+            .into_talpid_tunnel_options();
+        // Such settings are rejected, but may have been saved by an older version
+        if custom_entry && options.daita {
+            options.daita = false;
+            log::warn!("Ignoring DAITA option for custom entry");
+        }
+        if custom_entry && options.quantum_resistant {
+            options.quantum_resistant = false;
+            log::warn!("Ignoring quantum resistance option for custom entry");
+        }
+
         wireguard::TunnelParameters {
             connection: wireguard::ConnectionConfig {
                 tunnel,
@@ -178,13 +297,12 @@ impl InnerParametersGenerator {
                 ipv4_gateway: endpoint.ipv4_gateway,
                 ipv6_gateway: Some(endpoint.ipv6_gateway),
                 #[cfg(target_os = "linux")]
-                fwmark: Some(mullvad_types::TUNNEL_FWMARK),
+                fwmark: Some(self.network_ids.fwmark),
+                #[cfg(target_os = "linux")]
+                interface_name: Some(self.network_ids.tunnel_interface.clone()),
+                custom_entry,
             },
-            options: self
-                .tunnel_options
-                .wireguard
-                .clone()
-                .into_talpid_tunnel_options(),
+            options,
             generic_options: self.tunnel_options.generic.clone(),
             obfuscation: obfuscator_config,
         }
@@ -217,6 +335,32 @@ impl TunnelParametersGenerator for ParametersGenerator {
                 .map_err(ParameterGenerationError::from)
         })
     }
+
+    fn generate_candidate(
+        &mut self,
+        ip_availability: IpAvailability,
+    ) -> Pin<Box<dyn Future<Output = Result<TunnelParameters, ParameterGenerationError>> + Send>>
+    {
+        let generator = self.0.clone();
+        Box::pin(async move {
+            let mut inner = generator.lock().await;
+            inner
+                .generate_candidate(ip_availability)
+                .await
+                .inspect_err(|error| {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to generate tunnel parameters")
+                    );
+                })
+                .map_err(ParameterGenerationError::from)
+        })
+    }
+
+    fn use_candidate(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        let generator = self.0.clone();
+        Box::pin(async move { generator.lock().await.use_candidate() })
+    }
 }
 
 impl From<Error> for ParameterGenerationError {
@@ -254,4 +398,8 @@ impl From<Error> for ParameterGenerationError {
 struct LastSelectedRelays {
     config: WireguardConfig,
     server_override: bool,
+    /// The network that the relays were selected on.
+    network: Option<NetworkFingerprint>,
+    /// The retry entry that the relays were selected with.
+    retry_entry: Option<EntrySpecificConstraints>,
 }
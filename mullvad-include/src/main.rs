@@ -0,0 +1,113 @@
+fn main() {
+    #[cfg(target_os = "linux")]
+    inner::main();
+}
+
+#[cfg(target_os = "linux")]
+mod inner {
+    use nix::unistd::{Uid, execvp, getgid, getgroups, getuid, setgid, setgroups, setuid};
+    use std::{
+        convert::Infallible,
+        env,
+        error::Error as StdError,
+        ffi::{CString, NulError},
+        fmt::Write as _,
+        os::unix::ffi::OsStrExt,
+    };
+    use talpid_netns::{NetNs, TUNNEL_NETNS_NAME};
+
+    #[derive(thiserror::Error, Debug)]
+    enum Error {
+        #[error("Invalid arguments")]
+        InvalidArguments,
+
+        #[error("The tunnel namespace does not exist. Is tunnel namespace mode enabled?")]
+        NoNamespace,
+
+        #[error("Cannot move the process into the tunnel namespace")]
+        EnterNamespace(#[source] talpid_netns::Error),
+
+        #[error("Failed to drop root user privileges for the process")]
+        DropRootUid(#[source] nix::Error),
+
+        #[error("Failed to drop root group privileges for the process")]
+        DropRootGid(#[source] nix::Error),
+
+        #[error("Failed to drop supplementary group privileges for the process")]
+        DropRootGroups(#[source] nix::Error),
+
+        #[error("Root privileges could be regained after dropping them")]
+        RootNotDropped,
+
+        #[error("Failed to launch the process")]
+        Exec(#[source] nix::Error),
+
+        #[error("An argument contains interior nul bytes")]
+        ArgumentNul(#[source] NulError),
+    }
+
+    /// Launch a program in the network namespace that holds the VPN tunnel. The program can only
+    /// communicate through the tunnel, while the rest of the system is unaffected.
+    ///
+    /// Note: The `TALPID_NETNS_RUN_DIR` and `TALPID_NETNS_ETC_DIR` env variables are ignored when
+    /// this runs as a set-user-ID program, since they are controlled by the caller. See
+    /// (README.md)[../../README.md#Environment-variables-used-by-the-service] for details.
+    pub fn main() {
+        let Err(error) = run();
+
+        match error {
+            Error::InvalidArguments => {
+                let mut args = env::args();
+                let program = args
+                    .next()
+                    .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
+                eprintln!("Usage: {program} COMMAND [ARGS]");
+                std::process::exit(1);
+            }
+            e => {
+                let mut s = format!("Error: {e}");
+                let mut source = e.source();
+                while let Some(error) = source {
+                    write!(&mut s, "\nCaused by: {error}").expect("formatting failed");
+                    source = error.source();
+                }
+                eprintln!("{s}");
+
+                std::process::exit(1);
+            }
+        }
+    }
+
+    fn run() -> Result<Infallible, Error> {
+        let mut args_iter = env::args_os().skip(1);
+        let program = args_iter.next().ok_or(Error::InvalidArguments)?;
+        let program = CString::new(program.as_bytes()).map_err(Error::ArgumentNul)?;
+
+        let args: Vec<CString> = env::args_os()
+            .skip(1)
+            .map(|arg| CString::new(arg.as_bytes()))
+            .collect::<Result<Vec<CString>, NulError>>()
+            .map_err(Error::ArgumentNul)?;
+
+        let netns = NetNs::open(TUNNEL_NETNS_NAME).map_err(|error| match error {
+            talpid_netns::Error::NotFound(_) => Error::NoNamespace,
+            error => Error::EnterNamespace(error),
+        })?;
+        netns.enter().map_err(Error::EnterNamespace)?;
+
+        // Drop root privileges. Groups must be dropped first, since that requires root. The
+        // supplementary groups of the caller are kept as they were.
+        let groups = getgroups().map_err(Error::DropRootGroups)?;
+        setgroups(&groups).map_err(Error::DropRootGroups)?;
+        let real_gid = getgid();
+        setgid(real_gid).map_err(Error::DropRootGid)?;
+        let real_uid = getuid();
+        setuid(real_uid).map_err(Error::DropRootUid)?;
+        if !real_uid.is_root() && setuid(Uid::from_raw(0)).is_ok() {
+            return Err(Error::RootNotDropped);
+        }
+
+        // Launch the process
+        execvp(&program, &args).map_err(Error::Exec)
+    }
+}
@@ -139,6 +139,7 @@ impl EphemeralPeerExchange {
                 ephemeral_pub_key,
                 self.peer_parameters.enable_post_quantum,
                 self.peer_parameters.enable_daita,
+                None,
             ) =>  {
                 match ephemeral_peer {
                     Ok(EphemeralPeer { psk, daita }) => {
@@ -730,6 +730,7 @@ impl IosTunnelAdapter {
             ephemeral_pubkey,
             enable_pq,
             enable_daita,
+            None,
         )
         .await
         .map_err(|e| format!("Ephemeral peer exchange: {e}"))
@@ -0,0 +1,256 @@
+//! Authorization of management interface clients based on the credentials of the peer process.
+
+use mullvad_types::management_access::{AccessPolicy, Role};
+use nix::unistd::{Gid, Uid, User, getgrouplist};
+use std::{
+    convert::Infallible,
+    ffi::CString,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use tonic::{
+    Status,
+    body::Body,
+    codegen::{BoxFuture, http},
+    server::NamedService,
+};
+use tower::Service;
+
+/// Credentials of the process on the other end of a management interface connection, as
+/// reported by `SO_PEERCRED`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+}
+
+impl From<tokio::net::unix::UCred> for PeerCredentials {
+    fn from(cred: tokio::net::unix::UCred) -> Self {
+        PeerCredentials {
+            uid: cred.uid(),
+            gid: cred.gid(),
+            pid: cred.pid(),
+        }
+    }
+}
+
+/// Return the groups that the user is a member of. Falls back on only `gid` if the user cannot
+/// be looked up. This may block, e.g. if groups are looked up over the network.
+fn user_groups(uid: u32, gid: u32) -> Vec<u32> {
+    let groups = User::from_uid(Uid::from_raw(uid))
+        .ok()
+        .flatten()
+        .and_then(|user| CString::new(user.name).ok())
+        .and_then(|name| getgrouplist(&name, Gid::from_raw(gid)).ok());
+    match groups {
+        Some(groups) => groups.into_iter().map(Gid::as_raw).collect(),
+        None => vec![gid],
+    }
+}
+
+/// Return the role required to call the RPC at `path`, e.g.
+/// `/mullvad_daemon.management_interface.ManagementService/ConnectTunnel`. RPCs that are not
+/// listed require the admin role.
+pub fn required_role(path: &str) -> Role {
+    let method = path.rsplit('/').next().unwrap_or_default();
+    match method {
+        "GetTunnelState"
+        | "EventsListen"
+        | "GetCurrentVersion"
+        | "GetVersionInfo"
+        | "IsPerformingPostUpgrade"
+        | "GetRelayLocations"
+        | "GetRelayListInfo"
+        // Secrets are removed from the settings unless the peer is an admin
+        | "GetSettings"
+        | "ListSettingsProfiles"
+        | "DiffSettingsProfile"
+        | "PreviewJsonSettings"
+        | "GetFeatureIndicators"
+        | "GetCurrentApiAccessMethod"
+        | "ShadowsocksCiphers"
+        | "GetBridges"
+        | "SplitTunnelIsSupported"
+        | "GetSplitTunnelProcesses"
+        | "GetExcludedProcesses"
+        | "NeedFullDiskPermissions"
+        | "GetRolloutThreshold"
+        | "GetFirewallRules"
+        | "GetQuantumResistantRekeyStatus"
+        | "GetMigrationEvent"
+        | "AppUpgradeEventsListen"
+        | "GetAppUpgradeCacheDir"
+        | "PartitionRelays" => Role::ReadOnly,
+
+        "ConnectTunnel"
+        | "DisconnectTunnel"
+        | "ReconnectTunnel"
+        | "UpdateRelayLocations"
+        // Custom tunnel endpoints and entry servers additionally require the admin role
+        | "SetRelaySettings"
+        | "SetObfuscationSettings"
+        | "GetNetworkHints"
+        | "ClearNetworkHints"
+        | "SetQuantumResistantRekeyInterval"
+        | "ResetQuantumResistantRekeyInterval"
+        | "SetEnableDaita"
+        | "SetDaitaSettings"
+        | "SetDaitaLevel"
+        | "SetEnableRecents"
+        | "CreateCustomList"
+        | "DeleteCustomList"
+        | "UpdateCustomList"
+        | "GetAccountData"
+        | "GetAccountHistory"
+        | "GetDevice"
+        | "UpdateDevice"
+        | "ListDevices"
+        | "RotateWireguardKey"
+        | "GetWireguardKey"
+        | "TestCustomApiAccessMethod"
+        | "TestApiAccessMethodById"
+        | "CheckVolumes"
+        | "AppUpgradeAbort"
+        | "ClearMigrationMessage" => Role::Operator,
+
+        _ => Role::Admin,
+    }
+}
+
+/// Return the role of the peer that sent `request`, as determined by [`AuthorizedService`].
+/// Secrets must only be returned to peers with the admin role.
+pub fn peer_role<T>(request: &tonic::Request<T>) -> Role {
+    request
+        .extensions()
+        .get::<Role>()
+        .copied()
+        .unwrap_or(Role::ReadOnly)
+}
+
+/// Service that rejects calls that the role of the peer does not allow, before passing them on
+/// to the inner service. The role of the peer is added to the extensions of the request.
+#[derive(Clone)]
+pub struct AuthorizedService<S> {
+    inner: S,
+    policy: Arc<AccessPolicy>,
+}
+
+impl<S> AuthorizedService<S> {
+    pub fn new(inner: S, policy: Arc<AccessPolicy>) -> Self {
+        AuthorizedService { inner, policy }
+    }
+}
+
+/// Return the role of the peer that sent `request`, or an error if the role does not allow the
+/// call.
+async fn authorize(policy: &AccessPolicy, request: &http::Request<Body>) -> Result<Role, Status> {
+    let required = required_role(request.uri().path());
+    let Some(Some(peer)) = request
+        .extensions()
+        .get::<Option<PeerCredentials>>()
+        .cloned()
+    else {
+        if required == Role::ReadOnly {
+            return Ok(Role::ReadOnly);
+        }
+        log::error!("Denying {}: unknown peer", request.uri().path());
+        return Err(Status::permission_denied("Unknown peer credentials"));
+    };
+    let groups = tokio::task::spawn_blocking(move || user_groups(peer.uid, peer.gid))
+        .await
+        .unwrap_or_else(|_| vec![peer.gid]);
+    let role = policy.role(peer.uid, groups);
+    if role < required {
+        log::warn!(
+            "Denying {} to uid {} (pid {:?}): requires {required}, has {role}",
+            request.uri().path(),
+            peer.uid,
+            peer.pid,
+        );
+        return Err(Status::permission_denied(format!(
+            "This requires the {required} role, but the user has the {role} role"
+        )));
+    }
+    Ok(role)
+}
+
+impl<S: NamedService> NamedService for AuthorizedService<S> {
+    const NAME: &'static str = S::NAME;
+}
+
+impl<S> Service<http::Request<Body>> for AuthorizedService<S>
+where
+    S: Service<http::Request<Body>, Response = http::Response<Body>, Error = Infallible>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<Body>) -> Self::Future {
+        // Take the service that was driven to readiness, and leave a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            match authorize(&policy, &request).await {
+                Ok(role) => {
+                    request.extensions_mut().insert(role);
+                    inner.call(request).await
+                }
+                Err(status) => Ok(status.into_http()),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_required_role() {
+        const SERVICE: &str = "/mullvad_daemon.management_interface.ManagementService";
+        assert_eq!(
+            required_role(&format!("{SERVICE}/GetTunnelState")),
+            Role::ReadOnly
+        );
+        for method in ["ConnectTunnel", "GetNetworkHints"] {
+            assert_eq!(
+                required_role(&format!("{SERVICE}/{method}")),
+                Role::Operator
+            );
+        }
+        for method in [
+            "FactoryReset",
+            "LogoutAccount",
+            "SetLockdownMode",
+            "ApplyJsonSettings",
+            // Installs and runs code as root
+            "AppUpgrade",
+            // May route API traffic through an untrusted proxy
+            "SetApiAccessMethod",
+            // Grants access to the account on the website
+            "GetWwwAuthToken",
+            // May send DNS queries to an arbitrary server
+            "SetDnsOptions",
+            // Profiles may change any setting, including lockdown mode
+            "ActivateSettingsProfile",
+            // Exports the passwords of custom API access methods
+            "ExportJsonSettings",
+            // Could be used to turn off post-quantum protection
+            "SetQuantumResistantTunnel",
+            "SomeFutureRpc",
+        ] {
+            assert_eq!(required_role(&format!("{SERVICE}/{method}")), Role::Admin);
+        }
+    }
+}
@@ -13,18 +13,32 @@ use mullvad_types::{
 #[cfg(not(target_os = "android"))]
 use mullvad_types::{
     access_method::{self, AccessMethod},
-    account::{AccountData, AccountNumber, VoucherSubmission},
+    account::{
+        AccountData, AccountExpiryWarning, AccountNumber, ExpiryWarningThresholds,
+        VoucherSubmission,
+    },
     custom_list::{CustomList, Id},
     device::{Device, DeviceId, DeviceState},
     features::FeatureIndicators,
+    metrics::MetricsEndpoint,
+    network_hints::NetworkHints,
     relay_constraints::{AllowedIps, ObfuscationSettings, RelayOverride, RelaySettings},
-    relay_list::BridgeList,
-    settings::{DnsOptions, SettingsKeyList},
-    wireguard::{PublicKey, QuantumResistantState, RotationInterval},
+    relay_list::{BridgeList, RelayListInfo},
+    settings::{
+        DnsOptions, SettingsKey, SettingsKeyList, SettingsPatchPreview, profile::SettingsProfile,
+    },
+    wireguard::{PublicKey, QuantumResistantState, RekeyInterval, RotationInterval},
 };
 use std::net::IpAddr;
 #[cfg(not(target_os = "android"))]
-use std::{path::Path, str::FromStr};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+#[cfg(target_os = "linux")]
+use talpid_types::net::{DnsBackend, FirewallException, InboundPort, LanGateway, TrustedInterface};
+use talpid_types::net::{TunnelCaptureSettings, wireguard::DaitaLevel};
 #[cfg(target_os = "windows")]
 use talpid_types::split_tunnel::ExcludedProcess;
 #[cfg(not(target_os = "android"))]
@@ -49,6 +63,8 @@ pub enum DaemonEvent {
     RemoveDevice(RemoveDeviceEvent),
     NewAccessMethod(AccessMethodSetting),
     LeakDetected(LeakInfo),
+    FirewallDrift(FirewallDrift),
+    AccountExpiryWarning(AccountExpiryWarning),
 }
 
 impl TryFrom<types::daemon_event::Event> for DaemonEvent {
@@ -82,6 +98,14 @@ impl TryFrom<types::daemon_event::Event> for DaemonEvent {
             types::daemon_event::Event::LeakInfo(leak) => {
                 LeakInfo::try_from(leak).map(DaemonEvent::LeakDetected)
             }
+            types::daemon_event::Event::FirewallDrift(drift) => {
+                Ok(DaemonEvent::FirewallDrift(FirewallDrift::from(drift)))
+            }
+            types::daemon_event::Event::AccountExpiryWarning(warning) => {
+                AccountExpiryWarning::try_from(warning)
+                    .map(DaemonEvent::AccountExpiryWarning)
+                    .map_err(Error::InvalidResponse)
+            }
         }
     }
 }
@@ -93,6 +117,15 @@ impl MullvadProxyClient {
         crate::new_management_service_client().await.map(Self)
     }
 
+    /// Connect to a daemon whose management interface listens on `rpc_socket_path`, rather than
+    /// the default path.
+    pub async fn from_rpc_socket_path(rpc_socket_path: impl Into<PathBuf>) -> Result<Self> {
+        crate::grpc_transport_channel_at(rpc_socket_path.into())
+            .await
+            .map(crate::ManagementServiceClient::new)
+            .map(Self)
+    }
+
     pub fn from_rpc_client(client: crate::ManagementServiceClient) -> Self {
         Self(client)
     }
@@ -231,6 +264,25 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Replace the relay list with `relay_list`, which was read from the file at `path`.
+    pub async fn import_relay_list(
+        &mut self,
+        relay_list: String,
+        path: String,
+    ) -> Result<RelayListInfo> {
+        let info = self
+            .0
+            .import_relay_list(types::RelayListImport { relay_list, path })
+            .await?
+            .into_inner();
+        RelayListInfo::try_from(info).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn get_relay_list_info(&mut self) -> Result<RelayListInfo> {
+        let info = self.0.get_relay_list_info(()).await?.into_inner();
+        RelayListInfo::try_from(info).map_err(Error::InvalidResponse)
+    }
+
     pub async fn set_relay_settings(&mut self, update: RelaySettings) -> Result<()> {
         let update = types::RelaySettings::from(update);
         self.0.set_relay_settings(update).await?;
@@ -243,6 +295,16 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn get_network_hints(&mut self) -> Result<NetworkHints> {
+        let hints = self.0.get_network_hints(()).await?.into_inner();
+        NetworkHints::try_from(hints).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn clear_network_hints(&mut self) -> Result<()> {
+        self.0.clear_network_hints(()).await?;
+        Ok(())
+    }
+
     pub async fn get_settings(&mut self) -> Result<Settings> {
         let settings = self.0.get_settings(()).await?.into_inner();
         Settings::try_from(settings).map_err(Error::InvalidResponse)
@@ -254,6 +316,50 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn list_settings_profiles(&mut self) -> Result<Vec<SettingsProfile>> {
+        self.0
+            .list_settings_profiles(())
+            .await?
+            .into_inner()
+            .profiles
+            .into_iter()
+            .map(|profile| SettingsProfile::try_from(profile).map_err(Error::InvalidResponse))
+            .collect()
+    }
+
+    /// Save the current values of the settings in `keys` as a new profile.
+    pub async fn create_settings_profile(
+        &mut self,
+        name: String,
+        keys: Vec<SettingsKey>,
+    ) -> Result<()> {
+        let keys = SettingsKeyList { keys };
+        let request = types::NewSettingsProfile {
+            name,
+            keys: types::SettingsKeyList::from(keys).keys,
+        };
+        self.0.create_settings_profile(request).await?;
+        Ok(())
+    }
+
+    pub async fn delete_settings_profile(&mut self, name: String) -> Result<()> {
+        self.0.delete_settings_profile(name).await?;
+        Ok(())
+    }
+
+    /// Return the settings that would change if the profile named `name` was activated.
+    pub async fn diff_settings_profile(&mut self, name: String) -> Result<Vec<SettingsKey>> {
+        let keys = self.0.diff_settings_profile(name).await?.into_inner();
+        SettingsKeyList::try_from(keys)
+            .map(|list| list.keys)
+            .map_err(Error::InvalidResponse)
+    }
+
+    pub async fn activate_settings_profile(&mut self, name: String) -> Result<()> {
+        self.0.activate_settings_profile(name).await?;
+        Ok(())
+    }
+
     pub async fn set_allow_lan(&mut self, state: bool) -> Result<()> {
         self.0.set_allow_lan(state).await?;
         Ok(())
@@ -269,11 +375,101 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    #[cfg(target_os = "linux")]
+    pub async fn set_tunnel_namespace(&mut self, state: bool) -> Result<()> {
+        self.0.set_tunnel_namespace(state).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_firewall_exceptions(
+        &mut self,
+        exceptions: Vec<FirewallException>,
+    ) -> Result<()> {
+        let exceptions = types::FirewallExceptionList {
+            exceptions: exceptions
+                .into_iter()
+                .map(types::FirewallException::from)
+                .collect(),
+        };
+        self.0.set_firewall_exceptions(exceptions).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_inbound_ports(&mut self, inbound_ports: Vec<InboundPort>) -> Result<()> {
+        let inbound_ports = types::InboundPortList {
+            ports: inbound_ports
+                .into_iter()
+                .map(types::InboundPort::from)
+                .collect(),
+        };
+        self.0.set_inbound_ports(inbound_ports).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_lan_gateway(&mut self, lan_gateway: Option<LanGateway>) -> Result<()> {
+        let setting = types::LanGatewaySetting {
+            lan_gateway: lan_gateway.map(types::LanGateway::from),
+        };
+        self.0.set_lan_gateway(setting).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_trusted_interfaces(
+        &mut self,
+        trusted_interfaces: Vec<TrustedInterface>,
+    ) -> Result<()> {
+        let trusted_interfaces = types::TrustedInterfaceList {
+            interfaces: trusted_interfaces
+                .into_iter()
+                .map(types::TrustedInterface::from)
+                .collect(),
+        };
+        self.0.set_trusted_interfaces(trusted_interfaces).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_dns_backend(&mut self, backend: DnsBackend) -> Result<()> {
+        let backend = types::DnsBackendSetting {
+            backend: i32::from(types::DnsBackend::from(backend)),
+        };
+        self.0.set_dns_backend(backend).await?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_reapply_firewall_on_drift(&mut self, state: bool) -> Result<()> {
+        self.0.set_reapply_firewall_on_drift(state).await?;
+        Ok(())
+    }
+
     pub async fn set_auto_connect(&mut self, state: bool) -> Result<()> {
         self.0.set_auto_connect(state).await?;
         Ok(())
     }
 
+    pub async fn set_metrics_endpoint(&mut self, endpoint: Option<&MetricsEndpoint>) -> Result<()> {
+        let setting = types::MetricsEndpointSetting {
+            endpoint: endpoint.map(types::MetricsEndpoint::from),
+        };
+        self.0.set_metrics_endpoint(setting).await?;
+        Ok(())
+    }
+
+    pub async fn set_expiry_warning_thresholds(
+        &mut self,
+        thresholds: &ExpiryWarningThresholds,
+    ) -> Result<()> {
+        self.0
+            .set_expiry_warning_thresholds(types::ExpiryWarningThresholds::from(thresholds))
+            .await?;
+        Ok(())
+    }
+
     pub async fn set_wireguard_mtu(&mut self, mtu: Option<u16>) -> Result<()> {
         self.0
             .set_wireguard_mtu(mtu.map(u32::from).unwrap_or(0))
@@ -300,6 +496,41 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_quantum_resistant_rekey_interval(
+        &mut self,
+        interval: RekeyInterval,
+    ) -> Result<()> {
+        let duration = types::Duration::try_from(*interval.as_duration())
+            .map_err(|_| Error::DurationTooLarge)?;
+        self.0
+            .set_quantum_resistant_rekey_interval(duration)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn reset_quantum_resistant_rekey_interval(&mut self) -> Result<()> {
+        self.0.reset_quantum_resistant_rekey_interval(()).await?;
+        Ok(())
+    }
+
+    /// Return when the quantum-resistant PSK of the current tunnel was last renegotiated.
+    pub async fn get_last_quantum_resistant_rekey(&mut self) -> Result<Option<SystemTime>> {
+        let status = self
+            .0
+            .get_quantum_resistant_rekey_status(())
+            .await?
+            .into_inner();
+        status
+            .last_rekey
+            .map(SystemTime::try_from)
+            .transpose()
+            .map_err(|_| {
+                Error::InvalidResponse(types::FromProtobufTypeError::invalid_argument(
+                    "invalid rekey timestamp",
+                ))
+            })
+    }
+
     pub async fn set_enable_daita(&mut self, value: bool) -> Result<()> {
         self.0.set_enable_daita(value).await?;
         Ok(())
@@ -311,6 +542,12 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_daita_level(&mut self, level: Option<DaitaLevel>) -> Result<()> {
+        let level = types::DaitaLevelSetting::from(level);
+        self.0.set_daita_level(level).await?;
+        Ok(())
+    }
+
     pub async fn set_dns_options(&mut self, options: DnsOptions) -> Result<()> {
         let options = types::DnsOptions::from(&options);
         self.0.set_dns_options(options).await?;
@@ -603,6 +840,11 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn preview_json_settings(&mut self, blob: String) -> Result<SettingsPatchPreview> {
+        let preview = self.0.preview_json_settings(blob).await?.into_inner();
+        SettingsPatchPreview::try_from(preview).map_err(Error::InvalidResponse)
+    }
+
     pub async fn export_json_settings(&mut self) -> Result<String> {
         let blob = self.0.export_json_settings(()).await?;
         Ok(blob.into_inner())
@@ -625,6 +867,11 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn get_firewall_rules(&mut self) -> Result<FirewallRules> {
+        let rules = self.0.get_firewall_rules(()).await?.into_inner();
+        Ok(FirewallRules::from(rules))
+    }
+
     pub async fn get_rollout_threshold(&mut self) -> Result<f32> {
         let rollout = self.0.get_rollout_threshold(()).await?;
         let threshold = rollout.into_inner().threshold;
@@ -644,6 +891,24 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Start capturing tunnel packets. Returns the path of the capture file.
+    pub async fn start_tunnel_capture(
+        &mut self,
+        settings: TunnelCaptureSettings,
+    ) -> Result<PathBuf> {
+        let path = self
+            .0
+            .start_tunnel_capture(types::TunnelCaptureSettings::from(settings))
+            .await?
+            .into_inner();
+        Ok(PathBuf::from(path))
+    }
+
+    pub async fn stop_tunnel_capture(&mut self) -> Result<()> {
+        self.0.stop_tunnel_capture(()).await?;
+        Ok(())
+    }
+
     pub async fn set_wireguard_allowed_ips(&mut self, allowed_ips: AllowedIps) -> Result<()> {
         self.0
             .set_wireguard_allowed_ips(types::AllowedIpsList {
@@ -735,6 +1000,45 @@ impl TryFrom<types::LeakInfo> for LeakInfo {
     }
 }
 
+/// The firewall rules that the last applied policy resulted in, and the rules that are active.
+#[derive(Debug, Clone)]
+pub struct FirewallRules {
+    /// Description of the last applied policy, if it is still in effect.
+    pub policy: Option<String>,
+    /// Rules that were active right after the policy was applied.
+    pub expected: Vec<String>,
+    /// Rules that are currently active.
+    pub actual: Vec<String>,
+}
+
+impl From<types::FirewallRules> for FirewallRules {
+    fn from(rules: types::FirewallRules) -> Self {
+        FirewallRules {
+            policy: rules.policy,
+            expected: rules.expected,
+            actual: rules.actual,
+        }
+    }
+}
+
+/// The active firewall rules were changed by another program.
+#[derive(Debug, Clone)]
+pub struct FirewallDrift {
+    /// The expected and the active rules when the change was detected.
+    pub rules: FirewallRules,
+    /// Whether the daemon applied its firewall policy again.
+    pub reapplied: bool,
+}
+
+impl From<types::FirewallDrift> for FirewallDrift {
+    fn from(drift: types::FirewallDrift) -> Self {
+        FirewallDrift {
+            rules: FirewallRules::from(drift.rules.unwrap_or_default()),
+            reapplied: drift.reapplied,
+        }
+    }
+}
+
 #[cfg(not(target_os = "android"))]
 pub struct RelaySelectorClient(crate::RelaySelectorServiceClient);
 
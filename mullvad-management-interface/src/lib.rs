@@ -1,3 +1,5 @@
+#[cfg(target_os = "linux")]
+pub mod access;
 pub mod client;
 pub mod types;
 
@@ -144,9 +146,14 @@ pub async fn new_management_service_client() -> Result<ManagementServiceClient,
 /// [ManagementServiceClient]) and the management interface gRPC service.
 #[cfg(not(target_os = "android"))]
 pub(crate) async fn grpc_transport_channel() -> Result<Channel, Error> {
-    use futures::TryFutureExt;
+    grpc_transport_channel_at(mullvad_paths::get_rpc_socket_path()).await
+}
 
-    let ipc_path = mullvad_paths::get_rpc_socket_path();
+/// Like [grpc_transport_channel], but connect to the management interface listening on
+/// `ipc_path`.
+#[cfg(not(target_os = "android"))]
+pub(crate) async fn grpc_transport_channel_at(ipc_path: PathBuf) -> Result<Channel, Error> {
+    use futures::TryFutureExt;
 
     // The URI will be ignored
     Endpoint::from_static("lttp://[::]:50051")
@@ -162,6 +169,7 @@ pub use client::MullvadProxyClient;
 
 pub type ServerJoinHandle = tokio::task::JoinHandle<()>;
 
+#[cfg(not(target_os = "linux"))]
 pub fn spawn_rpc_server(
     management_service: impl ManagementService,
     relay_selector_service: impl RelaySelectorService,
@@ -208,6 +216,70 @@ pub fn spawn_rpc_server(
     Ok(server_task)
 }
 
+/// Spawn the RPC server on Linux. The socket is created without `tipsy` so that the credentials
+/// of each client can be read using `SO_PEERCRED`. Each call is checked against `access_policy`.
+#[cfg(target_os = "linux")]
+pub fn spawn_rpc_server(
+    management_service: impl ManagementService,
+    relay_selector_service: impl RelaySelectorService,
+    abort_rx: impl Future<Output = ()> + Send + 'static,
+    rpc_socket_path: PathBuf,
+    access_policy: mullvad_types::management_access::AccessPolicy,
+) -> std::result::Result<ServerJoinHandle, Error> {
+    use access::AuthorizedService;
+    use std::sync::Arc;
+
+    let listener = create_unix_listener(&rpc_socket_path)?;
+    let incoming = futures::stream::unfold(listener, |listener| async move {
+        let stream = listener.accept().await.map(|(stream, _)| StreamBox(stream));
+        Some((stream, listener))
+    });
+
+    let access_policy = Arc::new(access_policy);
+    let grpc_server = Server::builder()
+        .add_service(AuthorizedService::new(
+            ManagementServiceServer::new(management_service),
+            access_policy.clone(),
+        ))
+        .add_service(AuthorizedService::new(
+            RelaySelectorServiceServer::new(relay_selector_service),
+            access_policy,
+        ))
+        .serve_with_incoming_shutdown(incoming, abort_rx);
+
+    let server_task = tokio::spawn(async move {
+        if let Err(execution_error) = grpc_server.await.map_err(Error::GrpcTransportError) {
+            log::error!("Management server panic: {execution_error}");
+        }
+        log::trace!("gRPC server is shutting down");
+    });
+
+    Ok(server_task)
+}
+
+#[cfg(target_os = "linux")]
+fn create_unix_listener(
+    rpc_socket_path: &std::path::Path,
+) -> Result<tokio::net::UnixListener, Error> {
+    let listener =
+        tokio::net::UnixListener::bind(rpc_socket_path).map_err(Error::StartServerError)?;
+
+    if let Some(group_name) = MULLVAD_MANAGEMENT_SOCKET_GROUP.as_ref() {
+        let group = nix::unistd::Group::from_name(group_name)
+            .map_err(Error::ObtainGidError)?
+            .ok_or(Error::NoGidError)?;
+        nix::unistd::chown(rpc_socket_path, None, Some(group.gid)).map_err(Error::SetGidError)?;
+        fs::set_permissions(rpc_socket_path, PermissionsExt::from_mode(0o760))
+            .map_err(Error::PermissionsError)?;
+    } else {
+        fs::set_permissions(rpc_socket_path, PermissionsExt::from_mode(0o766))
+            .map_err(Error::PermissionsError)?;
+    }
+
+    Ok(listener)
+}
+
+#[cfg(not(target_os = "linux"))]
 fn create_endpoint(rpc_socket_path: PathBuf) -> Result<IpcEndpoint, Error> {
     let endpoint = IpcEndpoint::new(rpc_socket_path, tipsy::OnConflict::Error)
         .map_err(Error::StartServerError)?;
@@ -231,6 +303,7 @@ fn create_endpoint(rpc_socket_path: PathBuf) -> Result<IpcEndpoint, Error> {
 
 #[derive(Debug)]
 struct StreamBox<T: AsyncRead + AsyncWrite>(pub T);
+#[cfg(not(target_os = "linux"))]
 impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
     type ConnectInfo = Option<()>;
 
@@ -238,6 +311,18 @@ impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
         None
     }
 }
+#[cfg(target_os = "linux")]
+impl Connected for StreamBox<tokio::net::UnixStream> {
+    type ConnectInfo = Option<access::PeerCredentials>;
+
+    fn connect_info(&self) -> Self::ConnectInfo {
+        self.0
+            .peer_cred()
+            .inspect_err(|error| log::error!("Failed to read peer credentials: {error}"))
+            .ok()
+            .map(access::PeerCredentials::from)
+    }
+}
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
     fn poll_read(
         mut self: Pin<&mut Self>,
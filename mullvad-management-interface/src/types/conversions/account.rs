@@ -1,6 +1,8 @@
 use crate::types;
 use chrono::DateTime;
-use mullvad_types::account::{AccountData, VoucherSubmission};
+use mullvad_types::account::{
+    AccountData, AccountExpiryWarning, ExpiryWarningThresholds, VoucherSubmission,
+};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{
     PlayExternalObfuscatedAccountId, PlayPurchase, PlayPurchasePaymentToken,
@@ -68,6 +70,50 @@ impl TryFrom<types::AccountData> for AccountData {
     }
 }
 
+impl From<&ExpiryWarningThresholds> for types::ExpiryWarningThresholds {
+    fn from(thresholds: &ExpiryWarningThresholds) -> Self {
+        types::ExpiryWarningThresholds {
+            hours: thresholds.hours().to_vec(),
+        }
+    }
+}
+
+impl From<types::ExpiryWarningThresholds> for ExpiryWarningThresholds {
+    fn from(thresholds: types::ExpiryWarningThresholds) -> Self {
+        ExpiryWarningThresholds::new(thresholds.hours)
+    }
+}
+
+impl From<AccountExpiryWarning> for types::AccountExpiryWarning {
+    fn from(warning: AccountExpiryWarning) -> Self {
+        types::AccountExpiryWarning {
+            expiry: Some(types::Timestamp {
+                seconds: warning.expiry.timestamp(),
+                nanos: 0,
+            }),
+            threshold_hours: warning.threshold_hours,
+        }
+    }
+}
+
+impl TryFrom<types::AccountExpiryWarning> for AccountExpiryWarning {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(warning: types::AccountExpiryWarning) -> Result<Self, FromProtobufTypeError> {
+        let expiry = warning
+            .expiry
+            .ok_or(FromProtobufTypeError::invalid_argument("missing expiry"))?;
+
+        let expiry = DateTime::from_timestamp(expiry.seconds, expiry.nanos as u32)
+            .ok_or(FromProtobufTypeError::invalid_argument("invalid timestamp"))?;
+
+        Ok(AccountExpiryWarning {
+            expiry,
+            threshold_hours: warning.threshold_hours,
+        })
+    }
+}
+
 #[cfg(target_os = "android")]
 impl TryFrom<types::PlayPurchase> for PlayPurchase {
     type Error = FromProtobufTypeError;
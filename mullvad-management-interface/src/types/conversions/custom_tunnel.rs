@@ -73,8 +73,12 @@ impl TryFrom<proto::WireguardConfig> for wireguard::ConnectionConfig {
             exit_peer: None,
             ipv4_gateway,
             ipv6_gateway,
+            // Set by the daemon, which knows the firewall mark it uses
             #[cfg(target_os = "linux")]
-            fwmark: Some(mullvad_types::TUNNEL_FWMARK),
+            fwmark: None,
+            #[cfg(target_os = "linux")]
+            interface_name: None,
+            custom_entry: false,
         })
     }
 }
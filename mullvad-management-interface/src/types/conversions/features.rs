@@ -20,38 +20,55 @@ impl From<mullvad_types::features::FeatureIndicator> for proto::FeatureIndicator
             mullvad_types::features::FeatureIndicator::ServerIpOverride => ServerIpOverride,
             mullvad_types::features::FeatureIndicator::CustomMtu => CustomMtu,
             mullvad_types::features::FeatureIndicator::Daita => Daita,
+            mullvad_types::features::FeatureIndicator::FirewallExceptions => FirewallExceptions,
+            mullvad_types::features::FeatureIndicator::InboundPorts => InboundPorts,
+            mullvad_types::features::FeatureIndicator::LanGateway => LanGateway,
+            mullvad_types::features::FeatureIndicator::TrustedInterfaces => TrustedInterfaces,
+            mullvad_types::features::FeatureIndicator::SettingsProfile(_) => SettingsProfile,
         }
     }
 }
 
-impl From<proto::FeatureIndicator> for mullvad_types::features::FeatureIndicator {
-    fn from(feature: proto::FeatureIndicator) -> Self {
-        match feature {
-            proto::FeatureIndicator::QuantumResistance => Self::QuantumResistance,
-            proto::FeatureIndicator::Multihop => Self::Multihop,
-            proto::FeatureIndicator::MultihopAuto => Self::MultihopAuto,
-            proto::FeatureIndicator::SplitTunneling => Self::SplitTunneling,
-            proto::FeatureIndicator::LockdownMode => Self::LockdownMode,
-            proto::FeatureIndicator::WireguardPort => Self::WireguardPort,
-            proto::FeatureIndicator::Udp2Tcp => Self::Udp2Tcp,
-            proto::FeatureIndicator::Shadowsocks => Self::Shadowsocks,
-            proto::FeatureIndicator::Quic => Self::Quic,
-            proto::FeatureIndicator::Lwo => Self::Lwo,
-            proto::FeatureIndicator::LanSharing => Self::LanSharing,
-            proto::FeatureIndicator::DnsContentBlockers => Self::DnsContentBlockers,
-            proto::FeatureIndicator::CustomDns => Self::CustomDns,
-            proto::FeatureIndicator::ServerIpOverride => Self::ServerIpOverride,
-            proto::FeatureIndicator::CustomMtu => Self::CustomMtu,
-            proto::FeatureIndicator::Daita => Self::Daita,
+/// Convert `feature` to a [`mullvad_types::features::FeatureIndicator`]. `settings_profile` is the
+/// name of the active settings profile, which is only sent once for all features.
+fn feature_indicator_from_proto(
+    feature: proto::FeatureIndicator,
+    settings_profile: &str,
+) -> mullvad_types::features::FeatureIndicator {
+    use mullvad_types::features::FeatureIndicator;
+    match feature {
+        proto::FeatureIndicator::QuantumResistance => FeatureIndicator::QuantumResistance,
+        proto::FeatureIndicator::Multihop => FeatureIndicator::Multihop,
+        proto::FeatureIndicator::MultihopAuto => FeatureIndicator::MultihopAuto,
+        proto::FeatureIndicator::SplitTunneling => FeatureIndicator::SplitTunneling,
+        proto::FeatureIndicator::LockdownMode => FeatureIndicator::LockdownMode,
+        proto::FeatureIndicator::WireguardPort => FeatureIndicator::WireguardPort,
+        proto::FeatureIndicator::Udp2Tcp => FeatureIndicator::Udp2Tcp,
+        proto::FeatureIndicator::Shadowsocks => FeatureIndicator::Shadowsocks,
+        proto::FeatureIndicator::Quic => FeatureIndicator::Quic,
+        proto::FeatureIndicator::Lwo => FeatureIndicator::Lwo,
+        proto::FeatureIndicator::LanSharing => FeatureIndicator::LanSharing,
+        proto::FeatureIndicator::DnsContentBlockers => FeatureIndicator::DnsContentBlockers,
+        proto::FeatureIndicator::CustomDns => FeatureIndicator::CustomDns,
+        proto::FeatureIndicator::ServerIpOverride => FeatureIndicator::ServerIpOverride,
+        proto::FeatureIndicator::CustomMtu => FeatureIndicator::CustomMtu,
+        proto::FeatureIndicator::Daita => FeatureIndicator::Daita,
+        proto::FeatureIndicator::FirewallExceptions => FeatureIndicator::FirewallExceptions,
+        proto::FeatureIndicator::InboundPorts => FeatureIndicator::InboundPorts,
+        proto::FeatureIndicator::LanGateway => FeatureIndicator::LanGateway,
+        proto::FeatureIndicator::TrustedInterfaces => FeatureIndicator::TrustedInterfaces,
+        proto::FeatureIndicator::SettingsProfile => {
+            FeatureIndicator::SettingsProfile(settings_profile.to_owned())
         }
     }
 }
 
 impl From<proto::FeatureIndicators> for mullvad_types::features::FeatureIndicators {
     fn from(features: proto::FeatureIndicators) -> Self {
+        let settings_profile = features.settings_profile().to_owned();
         features
             .active_features()
-            .map(mullvad_types::features::FeatureIndicator::from)
+            .map(|feature| feature_indicator_from_proto(feature, &settings_profile))
             .collect()
     }
 }
@@ -60,10 +77,12 @@ impl From<mullvad_types::features::FeatureIndicators> for proto::FeatureIndicato
     fn from(features: mullvad_types::features::FeatureIndicators) -> Self {
         let mut proto_features = Self::default();
 
-        features
-            .into_iter()
-            .map(proto::FeatureIndicator::from)
-            .for_each(|feature| proto_features.push_active_features(feature));
+        for feature in features {
+            if let mullvad_types::features::FeatureIndicator::SettingsProfile(name) = &feature {
+                proto_features.settings_profile = Some(name.clone());
+            }
+            proto_features.push_active_features(proto::FeatureIndicator::from(feature));
+        }
 
         proto_features
     }
@@ -9,6 +9,7 @@ mod features;
 mod location;
 mod logging;
 mod net;
+mod network_hints;
 pub mod relay_constraints;
 mod relay_list;
 mod relay_selector;
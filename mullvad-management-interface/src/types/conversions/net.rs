@@ -12,6 +12,7 @@ impl From<talpid_types::net::TunnelEndpoint> for proto::TunnelEndpoint {
                 .tunnel_interface
                 .map(|tunnel_interface| proto::TunnelMetadata { tunnel_interface }),
             daita: endpoint.daita,
+            daita_parameters: endpoint.daita_parameters.map(proto::DaitaParameters::from),
         }
     }
 }
@@ -47,6 +48,7 @@ impl From<talpid_types::net::ObfuscationInfo> for proto::ObfuscationInfo {
             talpid_types::net::ObfuscationInfo::Multiplexer {
                 direct,
                 obfuscators,
+                selected,
             } => proto::ObfuscationInfo {
                 r#type: Some(proto::obfuscation_info::Type::Multiple(
                     proto::MultiplexObfuscation {
@@ -56,6 +58,7 @@ impl From<talpid_types::net::ObfuscationInfo> for proto::ObfuscationInfo {
                             .cloned()
                             .map(proto::ObfuscationEndpoint::from)
                             .collect(),
+                        selected: selected.map(proto::Endpoint::from),
                     },
                 )),
             },
@@ -150,9 +153,14 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                             .into_iter()
                             .map(talpid_net::ObfuscationEndpoint::try_from)
                             .collect::<Result<Vec<_>, _>>()?;
+                        let selected = multiple
+                            .selected
+                            .map(talpid_net::Endpoint::try_from)
+                            .transpose()?;
                         Ok(talpid_types::net::ObfuscationInfo::Multiplexer {
                             direct,
                             obfuscators,
+                            selected,
                         })
                     }
                     None => Err(FromProtobufTypeError::invalid_argument(
@@ -173,6 +181,10 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                 .tunnel_metadata
                 .map(|tunnel_metadata| tunnel_metadata.tunnel_interface),
             daita: endpoint.daita,
+            daita_parameters: endpoint
+                .daita_parameters
+                .map(talpid_net::DaitaParameters::try_from)
+                .transpose()?,
         })
     }
 }
@@ -213,6 +225,211 @@ impl From<proto::IpVersion> for talpid_types::net::IpVersion {
     }
 }
 
+impl From<talpid_types::net::FirewallException> for proto::FirewallException {
+    fn from(exception: talpid_types::net::FirewallException) -> Self {
+        proto::FirewallException {
+            destination: exception.destination.to_string(),
+            protocol: i32::from(proto::TransportProtocol::from(exception.protocol)),
+            port: u32::from(exception.port),
+            interface: exception.interface,
+        }
+    }
+}
+
+impl TryFrom<proto::FirewallException> for talpid_types::net::FirewallException {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(exception: proto::FirewallException) -> Result<Self, FromProtobufTypeError> {
+        let port = u16::try_from(exception.port)
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "invalid firewall exception port",
+            ))?;
+        if let Some(interface) = &exception.interface
+            && !talpid_types::net::FirewallException::is_valid_interface_name(interface)
+        {
+            return Err(FromProtobufTypeError::invalid_argument(
+                "invalid firewall exception interface",
+            ));
+        }
+        Ok(talpid_types::net::FirewallException {
+            destination: arg_from_str(
+                &exception.destination,
+                "invalid firewall exception destination",
+            )?,
+            protocol: try_transport_protocol_from_i32(exception.protocol)?,
+            port,
+            interface: exception.interface,
+        })
+    }
+}
+
+impl From<talpid_types::net::InboundPort> for proto::InboundPort {
+    fn from(inbound_port: talpid_types::net::InboundPort) -> Self {
+        proto::InboundPort {
+            protocol: i32::from(proto::TransportProtocol::from(inbound_port.protocol)),
+            port: u32::from(inbound_port.port),
+            source: inbound_port.source.map(|source| source.to_string()),
+        }
+    }
+}
+
+impl TryFrom<proto::InboundPort> for talpid_types::net::InboundPort {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(inbound_port: proto::InboundPort) -> Result<Self, FromProtobufTypeError> {
+        let port = u16::try_from(inbound_port.port)
+            .ok()
+            .filter(|port| *port != 0)
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "invalid inbound port",
+            ))?;
+        Ok(talpid_types::net::InboundPort {
+            protocol: try_transport_protocol_from_i32(inbound_port.protocol)?,
+            port,
+            source: inbound_port
+                .source
+                .map(|source| arg_from_str(&source, "invalid inbound port source"))
+                .transpose()?,
+        })
+    }
+}
+
+impl From<talpid_types::net::LanGateway> for proto::LanGateway {
+    fn from(lan_gateway: talpid_types::net::LanGateway) -> Self {
+        proto::LanGateway {
+            interfaces: lan_gateway.interfaces,
+            subnets: lan_gateway
+                .subnets
+                .iter()
+                .map(|subnet| subnet.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::LanGateway> for talpid_types::net::LanGateway {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(lan_gateway: proto::LanGateway) -> Result<Self, FromProtobufTypeError> {
+        if lan_gateway.interfaces.is_empty()
+            || !lan_gateway.interfaces.iter().all(|interface| {
+                talpid_types::net::FirewallException::is_valid_interface_name(interface)
+            })
+        {
+            return Err(FromProtobufTypeError::invalid_argument(
+                "invalid LAN gateway interface",
+            ));
+        }
+        Ok(talpid_types::net::LanGateway {
+            interfaces: lan_gateway.interfaces,
+            subnets: lan_gateway
+                .subnets
+                .iter()
+                .map(|subnet| arg_from_str(subnet, "invalid LAN gateway subnet"))
+                .collect::<Result<Vec<_>, _>>()?,
+        })
+    }
+}
+
+impl From<talpid_types::net::TrustedInterface> for proto::TrustedInterface {
+    fn from(interface: talpid_types::net::TrustedInterface) -> Self {
+        let (dns_resolver, dns_domains) = match interface.dns_forwarding {
+            Some(forwarding) => (Some(forwarding.resolver.to_string()), forwarding.domains),
+            None => (None, vec![]),
+        };
+        proto::TrustedInterface {
+            name: interface.name,
+            dns_resolver,
+            dns_domains,
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedInterface> for talpid_types::net::TrustedInterface {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(interface: proto::TrustedInterface) -> Result<Self, FromProtobufTypeError> {
+        use talpid_types::net::DnsForwarding;
+
+        if !talpid_types::net::FirewallException::is_valid_interface_name(&interface.name) {
+            return Err(FromProtobufTypeError::invalid_argument(
+                "invalid trusted interface name",
+            ));
+        }
+        if !interface
+            .dns_domains
+            .iter()
+            .all(|domain| DnsForwarding::is_valid_domain(domain))
+        {
+            return Err(FromProtobufTypeError::invalid_argument(
+                "invalid trusted interface DNS domain",
+            ));
+        }
+        let dns_forwarding = match interface.dns_resolver {
+            Some(resolver) if !interface.dns_domains.is_empty() => Some(DnsForwarding {
+                resolver: arg_from_str(&resolver, "invalid trusted interface DNS resolver")?,
+                domains: interface.dns_domains,
+            }),
+            None if interface.dns_domains.is_empty() => None,
+            _ => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "trusted interface DNS resolver and domains must be set together",
+                ));
+            }
+        };
+        Ok(talpid_types::net::TrustedInterface {
+            name: interface.name,
+            dns_forwarding,
+        })
+    }
+}
+
+impl From<talpid_types::net::DnsBackend> for proto::DnsBackend {
+    fn from(backend: talpid_types::net::DnsBackend) -> Self {
+        use talpid_types::net::DnsBackend;
+        match backend {
+            DnsBackend::Auto => proto::DnsBackend::Auto,
+            DnsBackend::SystemdResolved => proto::DnsBackend::SystemdResolved,
+            DnsBackend::NetworkManager => proto::DnsBackend::NetworkManager,
+            DnsBackend::Resolvconf => proto::DnsBackend::Resolvconf,
+            DnsBackend::StaticFile => proto::DnsBackend::StaticFile,
+            DnsBackend::NftablesRedirect => proto::DnsBackend::NftablesRedirect,
+        }
+    }
+}
+
+impl From<proto::DnsBackend> for talpid_types::net::DnsBackend {
+    fn from(backend: proto::DnsBackend) -> Self {
+        use talpid_types::net::DnsBackend;
+        match backend {
+            proto::DnsBackend::Auto => DnsBackend::Auto,
+            proto::DnsBackend::SystemdResolved => DnsBackend::SystemdResolved,
+            proto::DnsBackend::NetworkManager => DnsBackend::NetworkManager,
+            proto::DnsBackend::Resolvconf => DnsBackend::Resolvconf,
+            proto::DnsBackend::StaticFile => DnsBackend::StaticFile,
+            proto::DnsBackend::NftablesRedirect => DnsBackend::NftablesRedirect,
+        }
+    }
+}
+
+impl TryFrom<proto::DnsBackendSetting> for talpid_types::net::DnsBackend {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(setting: proto::DnsBackendSetting) -> Result<Self, FromProtobufTypeError> {
+        try_dns_backend_from_i32(setting.backend)
+    }
+}
+
+pub fn try_dns_backend_from_i32(
+    backend: i32,
+) -> Result<talpid_types::net::DnsBackend, FromProtobufTypeError> {
+    Ok(proto::DnsBackend::try_from(backend)
+        .map_err(|_| FromProtobufTypeError::invalid_argument("invalid DNS backend"))?
+        .into())
+}
+
 pub fn try_transport_protocol_from_i32(
     protocol: i32,
 ) -> Result<talpid_types::net::TransportProtocol, FromProtobufTypeError> {
@@ -431,3 +648,38 @@ mod proxy {
         }
     }
 }
+
+impl From<talpid_types::net::TunnelCaptureSettings> for proto::TunnelCaptureSettings {
+    fn from(settings: talpid_types::net::TunnelCaptureSettings) -> Self {
+        proto::TunnelCaptureSettings {
+            inner: settings.inner,
+            outer: settings.outer,
+            max_size: Some(settings.max_size),
+            max_duration: Some(
+                prost_types::Duration::try_from(settings.max_duration)
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration for tunnel capture"),
+            ),
+        }
+    }
+}
+
+impl TryFrom<proto::TunnelCaptureSettings> for talpid_types::net::TunnelCaptureSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::TunnelCaptureSettings) -> Result<Self, Self::Error> {
+        let max_duration = match settings.max_duration {
+            Some(duration) => std::time::Duration::try_from(duration)
+                .map_err(|_| FromProtobufTypeError::invalid_argument("invalid capture duration"))?,
+            None => talpid_types::net::DEFAULT_CAPTURE_MAX_DURATION,
+        };
+
+        Ok(talpid_types::net::TunnelCaptureSettings {
+            inner: settings.inner,
+            outer: settings.outer,
+            max_size: settings
+                .max_size
+                .unwrap_or(talpid_types::net::DEFAULT_CAPTURE_MAX_SIZE),
+            max_duration,
+        })
+    }
+}
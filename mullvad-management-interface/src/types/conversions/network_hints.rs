@@ -0,0 +1,85 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use chrono::DateTime;
+use mullvad_types::{
+    constraints::Constraint,
+    network_hints::{NetworkFingerprint, NetworkHint, NetworkHints},
+};
+use prost_types::Timestamp;
+
+impl From<NetworkHint> for proto::NetworkHint {
+    fn from(hint: NetworkHint) -> Self {
+        proto::NetworkHint {
+            interface: hint.network.interface,
+            gateway: hint.network.gateway.to_string(),
+            gateway_mac: hint.network.gateway_mac,
+            obfuscation: Some(proto::ObfuscationSettings::from(hint.obfuscation)),
+            ip_version: hint
+                .ip_version
+                .option()
+                .map(|ip_version| i32::from(proto::IpVersion::from(ip_version))),
+            last_success: Some(Timestamp {
+                seconds: hint.last_success.timestamp(),
+                nanos: 0,
+            }),
+        }
+    }
+}
+
+impl TryFrom<proto::NetworkHint> for NetworkHint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(hint: proto::NetworkHint) -> Result<Self, Self::Error> {
+        let gateway = hint
+            .gateway
+            .parse()
+            .map_err(|_| FromProtobufTypeError::invalid_argument("invalid gateway address"))?;
+        let obfuscation = hint
+            .obfuscation
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing obfuscation settings",
+            ))?
+            .try_into()?;
+        let ip_version = hint
+            .ip_version
+            .map(proto::IpVersion::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::invalid_argument("invalid IP protocol version"))?
+            .map(talpid_types::net::IpVersion::from);
+        let last_success = hint
+            .last_success
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing 'last_success' timestamp",
+            ))?;
+        let last_success =
+            DateTime::from_timestamp(last_success.seconds, last_success.nanos as u32)
+                .ok_or(FromProtobufTypeError::invalid_argument("invalid timestamp"))?;
+
+        Ok(NetworkHint {
+            network: NetworkFingerprint {
+                interface: hint.interface,
+                gateway,
+                gateway_mac: hint.gateway_mac,
+            },
+            obfuscation,
+            ip_version: Constraint::from(ip_version),
+            last_success,
+        })
+    }
+}
+
+impl From<NetworkHints> for proto::NetworkHints {
+    fn from(hints: NetworkHints) -> Self {
+        proto::NetworkHints {
+            hints: hints.into_iter().map(proto::NetworkHint::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::NetworkHints> for NetworkHints {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(hints: proto::NetworkHints) -> Result<Self, Self::Error> {
+        hints.hints.into_iter().map(NetworkHint::try_from).collect()
+    }
+}
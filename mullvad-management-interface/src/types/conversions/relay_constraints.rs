@@ -1,4 +1,4 @@
-use crate::types::{FromProtobufTypeError, proto};
+use crate::types::{FromProtobufTypeError, conversions::bytes_to_pubkey, proto};
 use mullvad_types::{
     constraints::Constraint,
     custom_list::Id,
@@ -48,10 +48,39 @@ impl TryFrom<&proto::WireguardConstraints>
                 .into(),
             entry_providers: providers_constraint_from_proto(&constraints.entry_providers),
             entry_ownership: try_ownership_constraint_from_i32(constraints.entry_ownership)?,
+            custom_entry: constraints
+                .custom_entry
+                .as_ref()
+                .map(mullvad_constraints::CustomEntry::try_from)
+                .transpose()?,
         })
     }
 }
 
+impl TryFrom<&proto::CustomEntry> for mullvad_types::relay_constraints::CustomEntry {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(custom_entry: &proto::CustomEntry) -> Result<Self, Self::Error> {
+        let endpoint = custom_entry.endpoint.parse().map_err(|_err| {
+            FromProtobufTypeError::invalid_argument("invalid custom entry endpoint")
+        })?;
+        let public_key = bytes_to_pubkey(&custom_entry.public_key)?;
+        Ok(Self {
+            endpoint,
+            public_key,
+        })
+    }
+}
+
+impl From<mullvad_types::relay_constraints::CustomEntry> for proto::CustomEntry {
+    fn from(custom_entry: mullvad_types::relay_constraints::CustomEntry) -> Self {
+        Self {
+            endpoint: custom_entry.endpoint.to_string(),
+            public_key: custom_entry.public_key.as_bytes().to_vec(),
+        }
+    }
+}
+
 impl From<Multihop> for proto::wireguard_constraints::Multihop {
     fn from(value: Multihop) -> Self {
         match value {
@@ -148,6 +177,7 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
             SelectedObfuscation::WireguardPort => {
                 proto::obfuscation_settings::SelectedObfuscation::WireguardPort
             }
+            SelectedObfuscation::Race => proto::obfuscation_settings::SelectedObfuscation::Race,
         });
         Self {
             selected_obfuscation,
@@ -161,6 +191,7 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
                 &settings.wireguard_port,
             )),
             lwo: Some(proto::obfuscation_settings::Lwo::from(&settings.lwo)),
+            race: Some(proto::obfuscation_settings::Race::from(&settings.race)),
         }
     }
 }
@@ -209,6 +240,31 @@ impl From<&mullvad_types::relay_constraints::LwoSettings> for proto::obfuscation
     }
 }
 
+impl From<&mullvad_types::relay_constraints::RaceSettings> for proto::obfuscation_settings::Race {
+    fn from(settings: &mullvad_types::relay_constraints::RaceSettings) -> Self {
+        use mullvad_types::relay_constraints::RaceTransport;
+        use proto::obfuscation_settings::race::Transport;
+        Self {
+            transports: settings
+                .transports
+                .iter()
+                .map(|transport| match transport {
+                    RaceTransport::Direct => Transport::Direct,
+                    RaceTransport::Lwo => Transport::Lwo,
+                    RaceTransport::Udp2Tcp => Transport::Udp2tcp,
+                    RaceTransport::Shadowsocks => Transport::Shadowsocks,
+                    RaceTransport::Quic => Transport::Quic,
+                })
+                .map(i32::from)
+                .collect(),
+            stagger_delay: Some(
+                prost_types::Duration::try_from(settings.stagger_delay)
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration for race stagger delay"),
+            ),
+        }
+    }
+}
+
 impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySettings {
     fn from(settings: mullvad_types::relay_constraints::RelaySettings) -> Self {
         use mullvad_types::relay_constraints::RelaySettings as MullvadRelaySettings;
@@ -258,6 +314,10 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
                         entry_ownership: convert_ownership_constraint(
                             &constraints.wireguard_constraints.entry_ownership,
                         ) as i32,
+                        custom_entry: constraints
+                            .wireguard_constraints
+                            .custom_entry
+                            .map(proto::CustomEntry::from),
                     }),
                 })
             }
@@ -379,6 +439,7 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
                 Ok(IpcSelectedObfuscation::Quic) => SelectedObfuscation::Quic,
                 Ok(IpcSelectedObfuscation::Lwo) => SelectedObfuscation::Lwo,
                 Ok(IpcSelectedObfuscation::WireguardPort) => SelectedObfuscation::WireguardPort,
+                Ok(IpcSelectedObfuscation::Race) => SelectedObfuscation::Race,
                 Err(_) => {
                     return Err(FromProtobufTypeError::invalid_argument(
                         "invalid obfuscation settings",
@@ -427,12 +488,20 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
             }
         };
 
+        // Older clients do not know about racing, so fall back on the defaults
+        let race = settings
+            .race
+            .map(|s| mullvad_types::relay_constraints::RaceSettings::try_from(&s))
+            .transpose()?
+            .unwrap_or_default();
+
         Ok(Self {
             selected_obfuscation,
             udp2tcp,
             shadowsocks,
             wireguard_port,
             lwo,
+            race,
         })
     }
 }
@@ -486,6 +555,42 @@ impl TryFrom<&proto::obfuscation_settings::Lwo> for mullvad_types::relay_constra
     }
 }
 
+impl TryFrom<&proto::obfuscation_settings::Race>
+    for mullvad_types::relay_constraints::RaceSettings
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: &proto::obfuscation_settings::Race) -> Result<Self, Self::Error> {
+        use mullvad_types::relay_constraints::RaceTransport;
+        use proto::obfuscation_settings::race::Transport;
+
+        let transports = settings
+            .transports
+            .iter()
+            .map(|transport| match Transport::try_from(*transport) {
+                Ok(Transport::Direct) => Ok(RaceTransport::Direct),
+                Ok(Transport::Lwo) => Ok(RaceTransport::Lwo),
+                Ok(Transport::Udp2tcp) => Ok(RaceTransport::Udp2Tcp),
+                Ok(Transport::Shadowsocks) => Ok(RaceTransport::Shadowsocks),
+                Ok(Transport::Quic) => Ok(RaceTransport::Quic),
+                Err(_) => Err(FromProtobufTypeError::invalid_argument(
+                    "invalid race transport",
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let stagger_delay = match settings.stagger_delay {
+            Some(delay) => std::time::Duration::try_from(delay)
+                .map_err(|_| FromProtobufTypeError::invalid_argument("invalid stagger delay"))?,
+            None => mullvad_types::relay_constraints::DEFAULT_RACE_STAGGER_DELAY,
+        };
+
+        Ok(Self {
+            transports,
+            stagger_delay,
+        })
+    }
+}
+
 impl TryFrom<proto::TransportPort> for mullvad_types::relay_constraints::TransportPort {
     type Error = FromProtobufTypeError;
 
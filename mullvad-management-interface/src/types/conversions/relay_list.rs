@@ -9,7 +9,7 @@ use mullvad_types::{
     location::Location,
     relay_list::{
         Bridge, BridgeEndpointData, BridgeList, EndpointData, Relay, RelayList, RelayListCountry,
-        WireguardRelay,
+        RelayListInfo, RelayListSource, WireguardRelay,
     },
 };
 use talpid_types::net::proxy::ShadowsocksCipher;
@@ -71,6 +71,45 @@ impl TryFrom<proto::BridgeEndpointData> for mullvad_types::relay_list::BridgeEnd
     }
 }
 
+impl From<RelayListInfo> for proto::RelayListInfo {
+    fn from(info: RelayListInfo) -> Self {
+        use proto::relay_list_info::Source;
+
+        let source = match info.source {
+            RelayListSource::Bundled => Source::Bundled(()),
+            RelayListSource::Api => Source::Api(()),
+            RelayListSource::Imported { path } => Source::Imported(path),
+        };
+        proto::RelayListInfo {
+            source: Some(source),
+            etag: info.etag,
+        }
+    }
+}
+
+impl TryFrom<proto::RelayListInfo> for RelayListInfo {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(info: proto::RelayListInfo) -> Result<Self, Self::Error> {
+        use proto::relay_list_info::Source;
+
+        let source = match info.source {
+            Some(Source::Bundled(())) => RelayListSource::Bundled,
+            Some(Source::Api(())) => RelayListSource::Api,
+            Some(Source::Imported(path)) => RelayListSource::Imported { path },
+            None => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "missing relay list source",
+                ));
+            }
+        };
+        Ok(RelayListInfo {
+            source,
+            etag: info.etag,
+        })
+    }
+}
+
 impl TryFrom<proto::RelayList> for mullvad_types::relay_list::RelayList {
     type Error = FromProtobufTypeError;
 
@@ -34,7 +34,61 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             lockdown_mode: settings.lockdown_mode,
             #[cfg(target_os = "android")]
             lockdown_mode: false,
+            #[cfg(target_os = "linux")]
+            tunnel_namespace: settings.tunnel_namespace,
+            #[cfg(not(target_os = "linux"))]
+            tunnel_namespace: false,
+            #[cfg(target_os = "linux")]
+            firewall_exceptions: settings
+                .firewall_exceptions
+                .iter()
+                .cloned()
+                .map(proto::FirewallException::from)
+                .collect(),
+            #[cfg(not(target_os = "linux"))]
+            firewall_exceptions: vec![],
+            #[cfg(target_os = "linux")]
+            inbound_ports: settings
+                .inbound_ports
+                .iter()
+                .cloned()
+                .map(proto::InboundPort::from)
+                .collect(),
+            #[cfg(not(target_os = "linux"))]
+            inbound_ports: vec![],
+            #[cfg(target_os = "linux")]
+            lan_gateway: settings.lan_gateway.clone().map(proto::LanGateway::from),
+            #[cfg(not(target_os = "linux"))]
+            lan_gateway: None,
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: settings
+                .trusted_interfaces
+                .iter()
+                .cloned()
+                .map(proto::TrustedInterface::from)
+                .collect(),
+            #[cfg(not(target_os = "linux"))]
+            trusted_interfaces: vec![],
+            #[cfg(target_os = "linux")]
+            dns_backend: i32::from(proto::DnsBackend::from(settings.dns_backend)),
+            #[cfg(not(target_os = "linux"))]
+            dns_backend: i32::from(proto::DnsBackend::Auto),
+            #[cfg(target_os = "linux")]
+            reapply_firewall_on_drift: settings.reapply_firewall_on_drift,
+            #[cfg(not(target_os = "linux"))]
+            reapply_firewall_on_drift: false,
             auto_connect: settings.auto_connect,
+            expiry_warning_thresholds: Some(proto::ExpiryWarningThresholds::from(
+                &settings.expiry_warning_thresholds,
+            )),
+            #[cfg(not(target_os = "android"))]
+            metrics_endpoint: settings
+                .metrics_endpoint
+                .as_ref()
+                .map(proto::MetricsEndpoint::from),
+            #[cfg(target_os = "android")]
+            metrics_endpoint: None,
+            active_settings_profile: settings.active_settings_profile.clone(),
             tunnel_options: Some(proto::TunnelOptions::from(&settings.tunnel_options)),
             show_beta_releases: settings.show_beta_releases,
             obfuscation_settings: Some(proto::ObfuscationSettings::from(
@@ -101,6 +155,11 @@ impl From<&mullvad_types::settings::TunnelOptions> for proto::TunnelOptions {
             enable_ipv6: options.generic.enable_ipv6,
             dns_options: Some(proto::DnsOptions::from(&options.dns_options)),
             userspace: options.wireguard.userspace,
+            daita_level: options.wireguard.daita_level.map(|level| u32::from(level.get())),
+            rekey_interval: options.wireguard.rekey_interval.map(|ivl| {
+                prost_types::Duration::try_from(std::time::Duration::from(ivl))
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration for tunnel_options.rekey_interval")
+            }),
         }
     }
 }
@@ -153,7 +212,46 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             allow_lan: settings.allow_lan,
             #[cfg(not(target_os = "android"))]
             lockdown_mode: settings.lockdown_mode,
+            #[cfg(target_os = "linux")]
+            tunnel_namespace: settings.tunnel_namespace,
+            #[cfg(target_os = "linux")]
+            firewall_exceptions: settings
+                .firewall_exceptions
+                .into_iter()
+                .map(talpid_types::net::FirewallException::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(target_os = "linux")]
+            inbound_ports: settings
+                .inbound_ports
+                .into_iter()
+                .map(talpid_types::net::InboundPort::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(target_os = "linux")]
+            lan_gateway: settings
+                .lan_gateway
+                .map(talpid_types::net::LanGateway::try_from)
+                .transpose()?,
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: settings
+                .trusted_interfaces
+                .into_iter()
+                .map(talpid_types::net::TrustedInterface::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(target_os = "linux")]
+            dns_backend: super::net::try_dns_backend_from_i32(settings.dns_backend)?,
+            #[cfg(target_os = "linux")]
+            reapply_firewall_on_drift: settings.reapply_firewall_on_drift,
             auto_connect: settings.auto_connect,
+            expiry_warning_thresholds: settings
+                .expiry_warning_thresholds
+                .map(mullvad_types::account::ExpiryWarningThresholds::from)
+                .unwrap_or_default(),
+            #[cfg(not(target_os = "android"))]
+            metrics_endpoint: settings
+                .metrics_endpoint
+                .map(mullvad_types::metrics::MetricsEndpoint::try_from)
+                .transpose()?,
+            active_settings_profile: settings.active_settings_profile,
             tunnel_options: mullvad_types::settings::TunnelOptions::try_from(tunnel_options)?,
             relay_overrides: settings
                 .relay_overrides
@@ -220,7 +318,7 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                     .map_err(|_| FromProtobufTypeError::invalid_argument("invalid duration"))?
                     .map(mullvad_types::wireguard::RotationInterval::try_from)
                     .transpose()
-                    .map_err(|error: mullvad_types::wireguard::RotationIntervalError| {
+                    .map_err(|error: mullvad_types::wireguard::IntervalError| {
                         log::error!(
                             "{}",
                             error.display_chain_with_msg("Invalid rotation interval")
@@ -236,7 +334,19 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                 daita: options.daita.map(|setting| setting.enabled).ok_or(
                     FromProtobufTypeError::invalid_argument("missing daita settings"),
                 )?,
+                daita_level: super::wireguard::try_daita_level_from_proto(options.daita_level)?,
                 userspace: options.userspace,
+                rekey_interval: options
+                    .rekey_interval
+                    .map(std::time::Duration::try_from)
+                    .transpose()
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("invalid duration"))?
+                    .map(mullvad_types::wireguard::RekeyInterval::try_from)
+                    .transpose()
+                    .map_err(|error: mullvad_types::wireguard::IntervalError| {
+                        log::error!("{}", error.display_chain_with_msg("Invalid rekey interval"));
+                        FromProtobufTypeError::invalid_argument("invalid rekey interval")
+                    })?,
             },
             generic: net::GenericTunnelOptions {
                 enable_ipv6: options.enable_ipv6,
@@ -329,3 +439,90 @@ impl From<mullvad_types::settings::Recents> for proto::Recents {
         }
     }
 }
+
+#[cfg(not(target_os = "android"))]
+impl From<&mullvad_types::metrics::MetricsEndpoint> for proto::MetricsEndpoint {
+    fn from(endpoint: &mullvad_types::metrics::MetricsEndpoint) -> Self {
+        use mullvad_types::metrics::MetricsEndpoint;
+        use proto::metrics_endpoint::Endpoint;
+
+        let endpoint = match endpoint {
+            MetricsEndpoint::Tcp { port } => Endpoint::TcpPort(u32::from(*port)),
+            #[cfg(unix)]
+            MetricsEndpoint::UnixSocket { name } => Endpoint::UnixSocketName(name.clone()),
+        };
+        proto::MetricsEndpoint {
+            endpoint: Some(endpoint),
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl TryFrom<proto::MetricsEndpoint> for mullvad_types::metrics::MetricsEndpoint {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(endpoint: proto::MetricsEndpoint) -> Result<Self, Self::Error> {
+        use mullvad_types::metrics::MetricsEndpoint;
+        use proto::metrics_endpoint::Endpoint;
+
+        match endpoint.endpoint {
+            Some(Endpoint::TcpPort(port)) => {
+                let port = u16::try_from(port)
+                    .map_err(|_| FromProtobufTypeError::invalid_argument("invalid port"))?;
+                Ok(MetricsEndpoint::Tcp { port })
+            }
+            #[cfg(unix)]
+            Some(Endpoint::UnixSocketName(name)) => {
+                if !mullvad_types::metrics::is_valid_socket_name(&name) {
+                    return Err(FromProtobufTypeError::invalid_argument(
+                        "invalid socket name",
+                    ));
+                }
+                Ok(MetricsEndpoint::UnixSocket { name })
+            }
+            #[cfg(not(unix))]
+            Some(Endpoint::UnixSocketName(_)) => Err(FromProtobufTypeError::invalid_argument(
+                "Unix sockets not supported on this platform",
+            )),
+            None => Err(FromProtobufTypeError::invalid_argument(
+                "missing metrics endpoint",
+            )),
+        }
+    }
+}
+
+impl From<&mullvad_types::settings::profile::SettingsProfile> for proto::SettingsProfile {
+    fn from(profile: &mullvad_types::settings::profile::SettingsProfile) -> Self {
+        proto::SettingsProfile {
+            name: profile.name.clone(),
+            keys: profile
+                .keys
+                .iter()
+                .cloned()
+                .map(|key| proto::SettingsKey::from(key) as i32)
+                .collect(),
+            settings: Some(proto::Settings::from(&profile.settings)),
+        }
+    }
+}
+
+impl TryFrom<proto::SettingsProfile> for mullvad_types::settings::profile::SettingsProfile {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(profile: proto::SettingsProfile) -> Result<Self, Self::Error> {
+        let keys = profile
+            .keys()
+            .map(TryFrom::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let settings = profile
+            .settings
+            .ok_or(FromProtobufTypeError::invalid_argument(
+                "missing profile settings",
+            ))?;
+        Ok(Self {
+            name: profile.name,
+            keys,
+            settings: mullvad_types::settings::Settings::try_from(settings)?,
+        })
+    }
+}
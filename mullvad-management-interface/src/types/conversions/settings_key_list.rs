@@ -12,7 +12,26 @@ impl From<mullvad_types::settings::SettingsKey> for proto::SettingsKey {
             mullvad_types::settings::SettingsKey::AllowLan => AllowLan,
             #[cfg(not(target_os = "android"))]
             mullvad_types::settings::SettingsKey::LockdownMode => LockdownModeKey,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::TunnelNamespace => TunnelNamespace,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::FirewallExceptions => FirewallExceptions,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::InboundPorts => InboundPorts,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::LanGateway => LanGatewayKey,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::TrustedInterfaces => TrustedInterfacesKey,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::DnsBackend => DnsBackend,
+            #[cfg(target_os = "linux")]
+            mullvad_types::settings::SettingsKey::ReapplyFirewallOnDrift => ReapplyFirewallOnDrift,
             mullvad_types::settings::SettingsKey::AutoConnect => AutoConnect,
+            mullvad_types::settings::SettingsKey::ExpiryWarningThresholds => {
+                ExpiryWarningThresholds
+            }
+            #[cfg(not(target_os = "android"))]
+            mullvad_types::settings::SettingsKey::MetricsEndpoint => MetricsEndpoint,
             mullvad_types::settings::SettingsKey::TunnelOptions => TunnelOptions,
             mullvad_types::settings::SettingsKey::RelayOverrides => RelayOverrides,
             mullvad_types::settings::SettingsKey::ShowBetaReleases => ShowBetaReleases,
@@ -38,7 +57,72 @@ impl TryFrom<proto::SettingsKey> for mullvad_types::settings::SettingsKey {
                     "lockdown mode not supported on this platform",
                 ));
             }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::TunnelNamespace => Self::TunnelNamespace,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::TunnelNamespace => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "tunnel namespace not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::FirewallExceptions => Self::FirewallExceptions,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::FirewallExceptions => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "firewall exceptions not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::InboundPorts => Self::InboundPorts,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::InboundPorts => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "inbound ports not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::LanGatewayKey => Self::LanGateway,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::LanGatewayKey => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "LAN gateway not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::TrustedInterfacesKey => Self::TrustedInterfaces,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::TrustedInterfacesKey => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "trusted interfaces not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::DnsBackend => Self::DnsBackend,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::DnsBackend => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "DNS backend not supported on this platform",
+                ));
+            }
+            #[cfg(target_os = "linux")]
+            proto::SettingsKey::ReapplyFirewallOnDrift => Self::ReapplyFirewallOnDrift,
+            #[cfg(not(target_os = "linux"))]
+            proto::SettingsKey::ReapplyFirewallOnDrift => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "firewall drift detection not supported on this platform",
+                ));
+            }
             proto::SettingsKey::AutoConnect => Self::AutoConnect,
+            proto::SettingsKey::ExpiryWarningThresholds => Self::ExpiryWarningThresholds,
+            #[cfg(not(target_os = "android"))]
+            proto::SettingsKey::MetricsEndpoint => Self::MetricsEndpoint,
+            #[cfg(target_os = "android")]
+            proto::SettingsKey::MetricsEndpoint => {
+                return Err(FromProtobufTypeError::invalid_argument(
+                    "metrics not supported on this platform",
+                ));
+            }
             proto::SettingsKey::TunnelOptions => Self::TunnelOptions,
             proto::SettingsKey::ShowBetaReleases => Self::ShowBetaReleases,
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
@@ -82,3 +166,31 @@ impl TryFrom<proto::SettingsKeyList> for mullvad_types::settings::SettingsKeyLis
         Ok(mullvad_types::settings::SettingsKeyList { keys })
     }
 }
+
+impl From<mullvad_types::settings::SettingsPatchPreview> for proto::SettingsPatchPreview {
+    fn from(preview: mullvad_types::settings::SettingsPatchPreview) -> Self {
+        proto::SettingsPatchPreview {
+            changed: preview
+                .changed
+                .into_iter()
+                .map(|key| proto::SettingsKey::from(key) as i32)
+                .collect(),
+            errors: preview.errors,
+        }
+    }
+}
+
+impl TryFrom<proto::SettingsPatchPreview> for mullvad_types::settings::SettingsPatchPreview {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(preview: proto::SettingsPatchPreview) -> Result<Self, Self::Error> {
+        let changed = preview
+            .changed()
+            .map(TryFrom::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(mullvad_types::settings::SettingsPatchPreview {
+            changed,
+            errors: preview.errors,
+        })
+    }
+}
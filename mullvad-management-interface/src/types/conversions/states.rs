@@ -142,6 +142,14 @@ impl From<mullvad_types::states::TunnelState> for proto::TunnelState {
                             talpid_tunnel::ErrorStateCause::NeedFullDiskPermissions => {
                                 i32::from(Cause::NeedFullDiskPermissions)
                             }
+                            #[cfg(target_os = "linux")]
+                            talpid_tunnel::ErrorStateCause::NetworkIdentifiersInUse => {
+                                i32::from(Cause::NetworkIdentifiersInUse)
+                            }
+                            #[cfg(target_os = "linux")]
+                            talpid_tunnel::ErrorStateCause::TunnelNamespaceError => {
+                                i32::from(Cause::TunnelNamespaceError)
+                            }
                         },
                         blocking_error: error_state.block_failure().map(map_firewall_error),
                         #[cfg(not(target_os = "android"))]
@@ -436,6 +444,14 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                     Ok(proto::error_state::Cause::NeedFullDiskPermissions) => {
                         talpid_tunnel::ErrorStateCause::NeedFullDiskPermissions
                     }
+                    #[cfg(target_os = "linux")]
+                    Ok(proto::error_state::Cause::NetworkIdentifiersInUse) => {
+                        talpid_tunnel::ErrorStateCause::NetworkIdentifiersInUse
+                    }
+                    #[cfg(target_os = "linux")]
+                    Ok(proto::error_state::Cause::TunnelNamespaceError) => {
+                        talpid_tunnel::ErrorStateCause::TunnelNamespaceError
+                    }
                     _ => {
                         return Err(FromProtobufTypeError::invalid_argument(
                             "invalid error cause",
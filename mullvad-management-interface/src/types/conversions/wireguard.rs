@@ -72,3 +72,56 @@ impl From<bool> for proto::DaitaSettings {
         proto::DaitaSettings { enabled }
     }
 }
+
+impl From<Option<talpid_types::net::wireguard::DaitaLevel>> for proto::DaitaLevelSetting {
+    fn from(level: Option<talpid_types::net::wireguard::DaitaLevel>) -> Self {
+        proto::DaitaLevelSetting {
+            level: level.map(|level| u32::from(level.get())),
+        }
+    }
+}
+
+impl TryFrom<proto::DaitaLevelSetting> for Option<talpid_types::net::wireguard::DaitaLevel> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(setting: proto::DaitaLevelSetting) -> Result<Self, Self::Error> {
+        try_daita_level_from_proto(setting.level)
+    }
+}
+
+pub(super) fn try_daita_level_from_proto(
+    level: Option<u32>,
+) -> Result<Option<talpid_types::net::wireguard::DaitaLevel>, FromProtobufTypeError> {
+    level
+        .map(|level| {
+            u8::try_from(level)
+                .ok()
+                .and_then(|level| talpid_types::net::wireguard::DaitaLevel::new(level).ok())
+                .ok_or(FromProtobufTypeError::invalid_argument(
+                    "invalid DAITA level",
+                ))
+        })
+        .transpose()
+}
+
+impl From<talpid_types::net::DaitaParameters> for proto::DaitaParameters {
+    fn from(parameters: talpid_types::net::DaitaParameters) -> Self {
+        proto::DaitaParameters {
+            level: parameters.level.map(|level| u32::from(level.get())),
+            max_padding_frac: parameters.max_padding_frac,
+            max_blocking_frac: parameters.max_blocking_frac,
+        }
+    }
+}
+
+impl TryFrom<proto::DaitaParameters> for talpid_types::net::DaitaParameters {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(parameters: proto::DaitaParameters) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::DaitaParameters {
+            level: try_daita_level_from_proto(parameters.level)?,
+            max_padding_frac: parameters.max_padding_frac,
+            max_blocking_frac: parameters.max_blocking_frac,
+        })
+    }
+}
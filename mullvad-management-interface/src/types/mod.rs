@@ -14,6 +14,7 @@ mod proto {
 pub use proto::*;
 
 mod conversions;
+mod redact;
 
 pub use prost_types::{Duration, Timestamp};
 
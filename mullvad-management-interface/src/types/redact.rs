@@ -0,0 +1,126 @@
+//! Removal of secrets from messages that are sent to clients that may not read them.
+
+use super::proto;
+
+impl proto::Settings {
+    /// Remove secrets, such as the private key of a custom tunnel and the passwords of custom API
+    /// access methods, from the settings.
+    pub fn redact_secrets(&mut self) {
+        if let Some(proto::relay_settings::Endpoint::Custom(custom)) = self
+            .relay_settings
+            .as_mut()
+            .and_then(|settings| settings.endpoint.as_mut())
+            && let Some(tunnel) = custom
+                .config
+                .as_mut()
+                .and_then(|config| config.tunnel.as_mut())
+        {
+            // Keep the length so that the key can still be parsed
+            tunnel.private_key = vec![0; tunnel.private_key.len()];
+        }
+        if let Some(methods) = self.api_access_methods.as_mut() {
+            for method in methods.custom.iter_mut() {
+                method.redact_secrets();
+            }
+        }
+    }
+}
+
+impl proto::AccessMethodSetting {
+    /// Remove the password of a custom proxy.
+    pub fn redact_secrets(&mut self) {
+        let Some(proto::access_method::AccessMethod::Custom(custom)) = self
+            .access_method
+            .as_mut()
+            .and_then(|method| method.access_method.as_mut())
+        else {
+            return;
+        };
+        match custom.proxy_method.as_mut() {
+            Some(proto::custom_proxy::ProxyMethod::Socks5remote(socks)) => {
+                if let Some(auth) = socks.auth.as_mut() {
+                    auth.password.clear();
+                }
+            }
+            Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)) => {
+                shadowsocks.password.clear();
+            }
+            Some(proto::custom_proxy::ProxyMethod::Socks5local(_)) | None => (),
+        }
+    }
+}
+
+impl proto::DaemonEvent {
+    /// Remove secrets from settings and access methods carried by the event.
+    pub fn redact_secrets(&mut self) {
+        match self.event.as_mut() {
+            Some(proto::daemon_event::Event::Settings(settings)) => settings.redact_secrets(),
+            Some(proto::daemon_event::Event::NewAccessMethod(method)) => method.redact_secrets(),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_redact_settings() {
+        let mut settings = proto::Settings {
+            relay_settings: Some(proto::RelaySettings {
+                endpoint: Some(proto::relay_settings::Endpoint::Custom(
+                    proto::CustomRelaySettings {
+                        host: "example.com".to_owned(),
+                        config: Some(proto::WireguardConfig {
+                            tunnel: Some(proto::wireguard_config::TunnelConfig {
+                                private_key: vec![1; 32],
+                                addresses: vec![],
+                            }),
+                            ..Default::default()
+                        }),
+                    },
+                )),
+            }),
+            api_access_methods: Some(proto::ApiAccessMethodSettings {
+                custom: vec![proto::AccessMethodSetting {
+                    access_method: Some(proto::AccessMethod {
+                        access_method: Some(proto::access_method::AccessMethod::Custom(
+                            proto::CustomProxy {
+                                proxy_method: Some(proto::custom_proxy::ProxyMethod::Shadowsocks(
+                                    proto::Shadowsocks {
+                                        password: "secret".to_owned(),
+                                        ..Default::default()
+                                    },
+                                )),
+                            },
+                        )),
+                    }),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        settings.redact_secrets();
+
+        let Some(proto::relay_settings::Endpoint::Custom(custom)) =
+            settings.relay_settings.unwrap().endpoint
+        else {
+            panic!("expected custom relay settings");
+        };
+        assert_eq!(
+            custom.config.unwrap().tunnel.unwrap().private_key,
+            vec![0; 32]
+        );
+        let method = &settings.api_access_methods.unwrap().custom[0];
+        let Some(proto::access_method::AccessMethod::Custom(proto::CustomProxy {
+            proxy_method: Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)),
+        })) = &method.access_method.as_ref().unwrap().access_method
+        else {
+            panic!("expected Shadowsocks access method");
+        };
+        assert!(shadowsocks.password.is_empty());
+    }
+}
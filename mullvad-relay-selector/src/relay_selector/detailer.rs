@@ -17,7 +17,11 @@ use mullvad_types::{
     relay_list::{Bridge, BridgeEndpointData, EndpointData, WireguardRelay},
 };
 use rand::seq::IndexedRandom;
-use talpid_types::net::{IpVersion, proxy::Shadowsocks, wireguard::PeerConfig};
+use talpid_types::net::{
+    IpVersion,
+    proxy::Shadowsocks,
+    wireguard::{PeerConfig, PublicKey},
+};
 
 use super::WireguardConfig;
 
@@ -37,7 +41,7 @@ pub enum Error {
 ///
 /// # Returns
 /// - A configured endpoint for Wireguard relay, encapsulating either a single-hop or multi-hop
-///   connection.
+///   connection. For a custom entry, `entry_endpoint` is the address of the user's server.
 pub fn wireguard_endpoint(
     allowed_ips: Constraint<&AllowedIps>,
     data: &EndpointData,
@@ -48,8 +52,15 @@ pub fn wireguard_endpoint(
         WireguardConfig::Singlehop { exit } => {
             wireguard_singlehop_endpoint(allowed_ips, data, exit, entry_endpoint)
         }
-        WireguardConfig::Multihop { exit, entry } => {
-            wireguard_multihop_endpoint(allowed_ips, data, exit, entry, entry_endpoint)
+        WireguardConfig::Multihop { exit, entry } => wireguard_multihop_endpoint(
+            allowed_ips,
+            data,
+            exit,
+            entry.get_public_key(),
+            entry_endpoint,
+        ),
+        WireguardConfig::CustomEntry { exit, entry } => {
+            wireguard_multihop_endpoint(allowed_ips, data, exit, &entry.public_key, entry_endpoint)
         }
     }
 }
@@ -88,12 +99,12 @@ fn wireguard_singlehop_endpoint(
 ///
 /// # Note
 /// In a multihop circuit, we need to provide an exit peer configuration in addition to the
-/// peer configuration.
+/// peer configuration. The entry peer may be either a relay or a custom entry server.
 fn wireguard_multihop_endpoint(
     allowed_ips: Constraint<&AllowedIps>,
     data: &EndpointData,
     exit: &WireguardRelay,
-    entry: &WireguardRelay,
+    entry_public_key: &PublicKey,
     entry_endpoint: SocketAddr,
 ) -> MullvadEndpoint {
     /// The standard port on which an exit relay accepts connections from an entry relay in a
@@ -123,7 +134,7 @@ fn wireguard_multihop_endpoint(
     };
 
     let entry = PeerConfig {
-        public_key: entry.get_public_key().clone(),
+        public_key: entry_public_key.clone(),
         endpoint: entry_endpoint,
         // The entry peer should only be able to route incoming VPN traffic to the
         // exit peer.
@@ -25,6 +25,9 @@ use rand::{
 
 use mullvad_types::{
     constraints::Constraint,
+    relay_constraints::{
+        LwoSettings, RaceSettings, RaceTransport, ShadowsocksSettings, Udp2TcpObfuscationSettings,
+    },
     relay_list::{EndpointData, Quic, WireguardRelay},
     relay_selector::{EntrySpecificConstraints, Reason},
 };
@@ -154,12 +157,20 @@ impl RelayEndpointSet {
             ip_version,
             ..
         }: &EntrySpecificConstraints,
+    ) -> Verdict {
+        self.mode_verdict(obfuscation, *ip_version)
+    }
+
+    fn mode_verdict(
+        &self,
+        obfuscation: &Constraint<ObfuscationMode>,
+        ip_version: Constraint<IpVersion>,
     ) -> Verdict {
         match obfuscation {
             // Constraint::Any means "auto" — we can always fallback to routing traffic through the plain WireGuard endpoint.
             Constraint::Any | Constraint::Only(ObfuscationMode::Off) => self
                 .wireguard
-                .supports_ip_version(*ip_version)
+                .supports_ip_version(ip_version)
                 .if_false(Reason::IpVersion),
 
             Constraint::Only(ObfuscationMode::Port(port)) => Verdict::all([
@@ -167,7 +178,7 @@ impl RelayEndpointSet {
                     .supports_port(port.get())
                     .if_false(Reason::Port),
                 self.wireguard
-                    .supports_ip_version(*ip_version)
+                    .supports_ip_version(ip_version)
                     .if_false(Reason::IpVersion),
             ]),
 
@@ -179,14 +190,14 @@ impl RelayEndpointSet {
                         .is_any_or(|p| ports.contains(&p))
                         .if_false(Reason::Port),
                     self.wireguard
-                        .supports_ip_version(*ip_version)
+                        .supports_ip_version(ip_version)
                         .if_false(Reason::IpVersion),
                 ]),
             },
 
             Constraint::Only(ObfuscationMode::Shadowsocks(settings)) => match &self.shadowsocks {
                 None => Verdict::reject(Reason::Obfuscation),
-                Some(ss) => ss.verdict(&self.wireguard, *ip_version, settings.port),
+                Some(ss) => ss.verdict(&self.wireguard, ip_version, settings.port),
             },
 
             Constraint::Only(ObfuscationMode::Quic) => match &self.quic {
@@ -196,7 +207,7 @@ impl RelayEndpointSet {
                 // need to distinguish: IPs match the version vs. IPs exist but are
                 // the wrong version.
                 Some(quic) => quic
-                    .supports_ip_version(*ip_version)
+                    .supports_ip_version(ip_version)
                     .if_false(Reason::IpVersion),
             },
 
@@ -208,9 +219,19 @@ impl RelayEndpointSet {
                     .supports_port(settings.port)
                     .if_false(Reason::Port),
                 self.wireguard
-                    .supports_ip_version(*ip_version)
+                    .supports_ip_version(ip_version)
                     .if_false(Reason::IpVersion),
             ]),
+
+            // Racing is possible as long as at least one of the transports can be used.
+            Constraint::Only(ObfuscationMode::Race(settings)) => settings
+                .transports
+                .iter()
+                .any(|transport| {
+                    let mode = Constraint::Only(race_transport_mode(*transport));
+                    self.mode_verdict(&mode, ip_version) == Verdict::Accept
+                })
+                .if_false(Reason::Obfuscation),
         }
     }
 
@@ -228,7 +249,9 @@ impl RelayEndpointSet {
         // need to match the requested IP version. Use the same family when possible so IP
         // overrides are derived correctly; otherwise, loosen the constraint.
         let wireguard_ip_version = match query {
-            Constraint::Only(ObfuscationMode::Shadowsocks(_) | ObfuscationMode::Quic) => {
+            Constraint::Only(
+                ObfuscationMode::Shadowsocks(_) | ObfuscationMode::Quic | ObfuscationMode::Race(_),
+            ) => {
                 if self.wireguard.supports_ip_version(ip_version) {
                     ip_version
                 } else {
@@ -246,7 +269,10 @@ impl RelayEndpointSet {
             Constraint::Any => {
                 let staggered_obfuscator = cfg_select! {
                     not(feature = "staggered-obfuscation") => None,
-                    feature = "staggered-obfuscation" => self.staggered_obfuscator(wireguard_endpoint, ip_version),
+                    feature = "staggered-obfuscation" => self
+                        .race_obfuscator(wireguard_endpoint, ip_version, &RaceSettings::default())
+                        .ok()
+                        .flatten(),
                 };
                 return Ok((wireguard_endpoint, staggered_obfuscator));
             }
@@ -265,29 +291,51 @@ impl RelayEndpointSet {
             ObfuscationMode::Lwo(settings) => Some(Obfuscators::Single(
                 self.lwo_config(wireguard_endpoint.ip(), settings.port)?,
             )),
+            ObfuscationMode::Race(settings) => {
+                self.race_obfuscator(wireguard_endpoint, ip_version, settings)?
+            }
         };
         Ok((wireguard_endpoint, obfuscator_config))
     }
 
-    /// Build a multiplexer [`Obfuscators`] config that tries all available obfuscation methods
-    /// in parallel alongside the plain `direct_endpoint`.
-    #[cfg(feature = "staggered-obfuscation")]
-    fn staggered_obfuscator(
+    /// Build a multiplexer [`Obfuscators`] config that races the transports in `settings` that
+    /// this relay supports. Transports that rely on the WireGuard endpoint are skipped if it does
+    /// not match `ip_version`.
+    ///
+    /// Returns `None` if only the plain `direct_endpoint` can be used, and an error if no
+    /// transport can be used at all.
+    fn race_obfuscator(
         &self,
         direct_endpoint: SocketAddr,
         ip_version: Constraint<IpVersion>,
-    ) -> Option<Obfuscators> {
-        let configs: Vec<ObfuscatorConfig> = [
-            self.lwo_config(direct_endpoint.ip(), Constraint::Any),
-            self.udp2tcp_config(direct_endpoint.ip(), Constraint::Any),
-            self.shadowsocks_config(ip_version, Constraint::Any),
-            self.quic_config(ip_version),
-        ]
-        .into_iter()
-        .flatten()
-        .collect();
+        settings: &RaceSettings,
+    ) -> Result<Option<Obfuscators>, Error> {
+        let wireguard_usable = self.wireguard.supports_ip_version(ip_version);
+        let mut direct = None;
+        let mut configs: Vec<ObfuscatorConfig> = vec![];
+
+        for transport in &settings.transports {
+            let config = match transport {
+                RaceTransport::Direct => {
+                    direct = wireguard_usable.then_some(direct_endpoint);
+                    continue;
+                }
+                RaceTransport::Lwo | RaceTransport::Udp2Tcp if !wireguard_usable => continue,
+                RaceTransport::Lwo => self.lwo_config(direct_endpoint.ip(), Constraint::Any),
+                RaceTransport::Udp2Tcp => {
+                    self.udp2tcp_config(direct_endpoint.ip(), Constraint::Any)
+                }
+                RaceTransport::Shadowsocks => self.shadowsocks_config(ip_version, Constraint::Any),
+                RaceTransport::Quic => self.quic_config(ip_version),
+            };
+            configs.extend(config.ok());
+        }
 
-        Obfuscators::multiplexer(Some(direct_endpoint), &configs)
+        match Obfuscators::multiplexer(direct, &configs, settings.stagger_delay) {
+            Some(obfuscators) => Ok(Some(obfuscators)),
+            None if direct.is_some() => Ok(None),
+            None => Err(Error::MissingSupport),
+        }
     }
 
     /// Build a Udp2Tcp obfuscator config, or return `None` if udp2tcp is unsupported or no
@@ -352,6 +400,17 @@ impl RelayEndpointSet {
     }
 }
 
+/// The obfuscation mode that corresponds to a single transport in a race.
+fn race_transport_mode(transport: RaceTransport) -> ObfuscationMode {
+    match transport {
+        RaceTransport::Direct => ObfuscationMode::Off,
+        RaceTransport::Lwo => ObfuscationMode::Lwo(LwoSettings::default()),
+        RaceTransport::Udp2Tcp => ObfuscationMode::Udp2tcp(Udp2TcpObfuscationSettings::default()),
+        RaceTransport::Shadowsocks => ObfuscationMode::Shadowsocks(ShadowsocksSettings::default()),
+        RaceTransport::Quic => ObfuscationMode::Quic,
+    }
+}
+
 impl WireguardEndpoints {
     /// Whether the WireGuard endpoint supports the given IP version.
     fn supports_ip_version(&self, ip_version: Constraint<IpVersion>) -> bool {
@@ -16,11 +16,12 @@ use crate::{
 };
 
 pub use mullvad_types::relay_list::Relay;
-use mullvad_types::relay_selector::MultihopConstraints;
+use mullvad_types::relay_selector::{EntryConstraints, MultihopConstraints};
 use mullvad_types::{
     constraints::Constraint,
     endpoint::MullvadEndpoint,
     location::Coordinates,
+    relay_constraints::CustomEntry,
     relay_list::{Bridge, BridgeList, RelayList, WireguardRelay},
 };
 use std::ops::Deref;
@@ -135,11 +136,28 @@ impl RelaySelector {
         // partitioning is the same one we look up in `endpoint_sets` afterwards.
         let annotated = self.relays.read().unwrap();
 
-        let inner = select_wireguard_relay(&annotated, &query)?;
+        let inner = match &query.custom_entry {
+            Some(custom_entry) => select_custom_entry_exit(&annotated, &query, custom_entry)?,
+            None => select_wireguard_relay(&annotated, &query)?,
+        };
 
         let entry = match &inner {
             WireguardConfig::Singlehop { exit } => exit,
             WireguardConfig::Multihop { entry, .. } => entry,
+            // The custom entry is not in the relay list and is always connected to directly.
+            WireguardConfig::CustomEntry { entry, .. } => {
+                let endpoint = wireguard_endpoint(
+                    query.allowed_ips.as_ref(),
+                    &annotated.inner.wireguard,
+                    &inner,
+                    entry.endpoint,
+                );
+                return Ok(GetRelay {
+                    endpoint,
+                    obfuscator: None,
+                    inner,
+                });
+            }
         };
 
         let endpoint_set = annotated
@@ -195,6 +213,27 @@ fn select_wireguard_relay(
     }
 }
 
+/// Select an exit relay to be reached through a user-provided entry server.
+///
+/// Only the exit constraints of `query` apply, regardless of its hop variant.
+fn select_custom_entry_exit(
+    relays: &AnnotatedRelayList,
+    query: &RelayQuery,
+    custom_entry: &CustomEntry,
+) -> Result<WireguardConfig, Error> {
+    let constraints = EntryConstraints {
+        general: query.exit().clone(),
+        entry_specific: query.entry_specific().clone(),
+    };
+    let partitions = filter::partition_entry(relays, &constraints);
+    let exit = helpers::pick_random_relay(&partitions.matches)
+        .ok_or_else(|| Error::NoRelayExit(Box::new(query.exit().clone())))?;
+    Ok(WireguardConfig::CustomEntry {
+        exit: exit.clone(),
+        entry: custom_entry.clone(),
+    })
+}
+
 /// Select separate entry and exit relays for a multihop configuration.
 ///
 /// If the entry location constraint is [`Constraint::Any`] (autohop), the entry relay
@@ -7,6 +7,8 @@
 //!   [`Hops::Multi`]) along with the entry/exit constraints meaningful to that count
 //! - **connection-level** constraints (`allowed_ips`, `quantum_resistant`) that apply regardless of
 //!   the count.
+//! - an optional **custom entry**, a user-provided WireGuard server that replaces the entry hop. Only
+//!   the exit is then picked from the relay list.
 //!
 //! [`RelayQuery`] is built either from the user's [`Settings`] (via [From]) or with the
 //! [`builder::RelayQueryBuilder`] fluent API used in tests.
@@ -17,7 +19,7 @@ pub use mullvad_types::relay_constraints::{
 use mullvad_types::{
     Intersection,
     constraints::Constraint,
-    relay_constraints::{AllowedIps, Multihop, RelaySettings},
+    relay_constraints::{AllowedIps, CustomEntry, Multihop, RelaySettings},
     relay_selector::{
         EntryConstraints, EntrySpecificConstraints, ExitConstraints, MultihopConstraints,
         ResolvedLocationConstraint,
@@ -40,6 +42,9 @@ pub struct RelayQuery {
     pub hops: Hops,
     pub allowed_ips: Constraint<AllowedIps>,
     pub quantum_resistant: Constraint<QuantumResistantState>,
+    /// Connect to the exit relay through this self-hosted server instead of a Mullvad entry relay.
+    /// Only [`Self::exit`] and [`Self::entry_specific`] are used to select the exit.
+    pub custom_entry: Option<CustomEntry>,
 }
 
 /// The multihop variant and corresponding constraints on each hop.
@@ -201,10 +206,21 @@ impl From<Settings> for RelayQuery {
             }
         };
 
-        let hops = match wg.multihop {
-            Multihop::Never => Hops::Single(singlehop(entry_specific, exit)),
-            Multihop::Always => Hops::Multi(multihop_constraints(entry_specific, exit)),
-            Multihop::Auto => Hops::Auto(singlehop(entry_specific, exit)),
+        let hops = if wg.custom_entry.is_some() {
+            // The entry hop is not a relay, so only the exit has to be selected. The custom entry
+            // server runs no obfuscation or DAITA, and it is always reached on its own address.
+            let entry_specific = EntrySpecificConstraints {
+                obfuscation: Constraint::Only(ObfuscationMode::Off),
+                daita: Constraint::Any,
+                ip_version: Constraint::Any,
+            };
+            Hops::Single(singlehop(entry_specific, exit))
+        } else {
+            match wg.multihop {
+                Multihop::Never => Hops::Single(singlehop(entry_specific, exit)),
+                Multihop::Always => Hops::Multi(multihop_constraints(entry_specific, exit)),
+                Multihop::Auto => Hops::Auto(singlehop(entry_specific, exit)),
+            }
         };
 
         RelayQuery {
@@ -213,6 +229,7 @@ impl From<Settings> for RelayQuery {
             quantum_resistant: Constraint::Only(
                 settings.tunnel_options.wireguard.quantum_resistant,
             ),
+            custom_entry: wg.custom_entry.clone(),
         }
     }
 }
@@ -225,6 +242,7 @@ impl Default for RelayQuery {
             hops: Hops::Single(EntryConstraints::default()),
             allowed_ips: Constraint::Any,
             quantum_resistant: Constraint::Any,
+            custom_entry: None,
         }
     }
 }
@@ -239,7 +257,8 @@ pub mod builder {
     use mullvad_types::{
         constraints::Constraint,
         relay_constraints::{
-            LwoSettings, ShadowsocksSettings, Udp2TcpObfuscationSettings, WireguardPortSettings,
+            CustomEntry, LwoSettings, RaceSettings, ShadowsocksSettings,
+            Udp2TcpObfuscationSettings, WireguardPortSettings,
         },
         relay_selector::{
             EntryConstraints, EntrySpecificConstraints, ExitConstraints, MultihopConstraints,
@@ -288,6 +307,7 @@ pub mod builder {
         hop_choice: HopChoice,
         allowed_ips: Constraint<AllowedIps>,
         quantum_resistant: Constraint<QuantumResistantState>,
+        custom_entry: Option<CustomEntry>,
         obfuscation_state: Obfuscation,
         _phantom: PhantomData<Multihop>,
     }
@@ -301,6 +321,7 @@ pub mod builder {
                 hop_choice: HopChoice::default(),
                 allowed_ips: Constraint::Any,
                 quantum_resistant: Constraint::Any,
+                custom_entry: None,
                 obfuscation_state: Any,
                 _phantom: PhantomData,
             }
@@ -350,6 +371,12 @@ pub mod builder {
             self
         }
 
+        /// Reach the exit relay through a self-hosted WireGuard server instead of an entry relay.
+        pub fn custom_entry(mut self, custom_entry: CustomEntry) -> Self {
+            self.custom_entry = Some(custom_entry);
+            self
+        }
+
         /// Switch to the autohop. Falls back from singlehop to multihop when
         /// no singlehop relay matches the constraints.
         pub fn autohop(mut self) -> Self {
@@ -380,6 +407,7 @@ pub mod builder {
                 hops,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
             }
         }
     }
@@ -395,6 +423,7 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: self.obfuscation_state,
                 _phantom: PhantomData,
             }
@@ -432,6 +461,7 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: port,
                 _phantom: PhantomData,
             }
@@ -448,6 +478,7 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: settings,
                 _phantom: PhantomData,
             }
@@ -464,6 +495,7 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: settings,
                 _phantom: PhantomData,
             }
@@ -478,6 +510,7 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: Quic,
                 _phantom: PhantomData,
             }
@@ -493,10 +526,28 @@ pub mod builder {
                 hop_choice: self.hop_choice,
                 allowed_ips: self.allowed_ips,
                 quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
                 obfuscation_state: Lwo,
                 _phantom: PhantomData,
             }
         }
+
+        /// Race the obfuscation methods given by `settings` against each other.
+        pub fn race(mut self, settings: RaceSettings) -> RelayQueryBuilder<Multihop, RaceSettings> {
+            self.entry_specific.obfuscation =
+                Constraint::Only(ObfuscationMode::Race(settings.clone()));
+            RelayQueryBuilder {
+                entry_specific: self.entry_specific,
+                exit: self.exit,
+                multihop_entry: self.multihop_entry,
+                hop_choice: self.hop_choice,
+                allowed_ips: self.allowed_ips,
+                quantum_resistant: self.quantum_resistant,
+                custom_entry: self.custom_entry,
+                obfuscation_state: settings,
+                _phantom: PhantomData,
+            }
+        }
     }
 
     impl<Multihop> RelayQueryBuilder<Multihop, Udp2TcpObfuscationSettings> {
@@ -612,6 +663,7 @@ mod test {
                 },
                 wireguard_port: port1.into(),
                 lwo: LwoSettings { port: port1 },
+                race: Default::default(),
             });
             assert_eq!(query, Constraint::Any);
         }
@@ -2,12 +2,14 @@
 //! guarantees or disambiguate the type of relay which is used in the relay selector's internal
 //! APIs.
 
-use mullvad_types::relay_list::WireguardRelay;
+use mullvad_types::{relay_constraints::CustomEntry, relay_list::WireguardRelay};
 
 /// - [`WireguardConfig::Singlehop`]: A wireguard relay where VPN traffic enters and exits.
 /// - [`WireguardConfig::Multihop`]: Two wireguard relays to be used in a multihop circuit. VPN
 ///   traffic will enter through `entry` and eventually exit through `exit` before the traffic will
 ///   actually be routed to the internet.
+/// - [`WireguardConfig::CustomEntry`]: A self-hosted WireGuard server used as the entry in front of
+///   a Mullvad exit relay.
 #[derive(Clone, Debug)]
 pub enum WireguardConfig {
     /// An exit relay.
@@ -17,6 +19,11 @@ pub enum WireguardConfig {
         exit: WireguardRelay,
         entry: WireguardRelay,
     },
+    /// A user-provided entry server and an exit relay.
+    CustomEntry {
+        exit: WireguardRelay,
+        entry: CustomEntry,
+    },
 }
 
 /// A type representing single Wireguard relay.
@@ -22,7 +22,8 @@ use mullvad_types::{
     endpoint::MullvadEndpoint,
     location::Location,
     relay_constraints::{
-        GeographicLocationConstraint, LwoSettings, Ownership, Providers, RelayOverride,
+        CustomEntry, GeographicLocationConstraint, LwoSettings, Ownership, Providers, RaceSettings,
+        RaceTransport, RelayOverride,
     },
     relay_list::{
         Bridge, BridgeEndpointData, BridgeList, EndpointData, Quic, Relay, RelayList,
@@ -220,6 +221,7 @@ fn unwrap_relay(get_result: GetRelay) -> WireguardRelay {
     match get_result.inner {
         crate::WireguardConfig::Singlehop { exit } => exit,
         crate::WireguardConfig::Multihop { exit, .. } => exit,
+        crate::WireguardConfig::CustomEntry { exit, .. } => exit,
     }
 }
 
@@ -227,6 +229,7 @@ fn unwrap_entry_relay(get_result: GetRelay) -> WireguardRelay {
     match get_result.inner {
         crate::WireguardConfig::Singlehop { exit } => exit,
         crate::WireguardConfig::Multihop { entry, .. } => entry,
+        crate::WireguardConfig::CustomEntry { .. } => panic!("Custom entry is not a relay"),
     }
 }
 
@@ -396,6 +399,44 @@ mod relay_selection {
         }
     }
 
+    /// Construct a query with a custom entry and assert that the relay selector only picks an exit
+    /// relay, which is reached through the custom entry without obfuscation.
+    #[test]
+    fn test_selecting_custom_entry() {
+        let relay_selector = default_relay_selector();
+        let custom_entry = CustomEntry {
+            endpoint: "192.0.2.1:51820".parse().unwrap(),
+            public_key: WIREGUARD_PUBKEY.clone(),
+        };
+
+        let query = RelayQueryBuilder::new()
+            .custom_entry(custom_entry.clone())
+            .build();
+        let relay = relay_selector.get_relay_by_query(query).unwrap();
+        let WireguardConfig::CustomEntry { exit, entry } = relay.inner else {
+            panic!(
+                "Relay selector returned unexpected relay: {:?}",
+                relay.inner
+            );
+        };
+        assert_eq!(entry, custom_entry);
+        assert!(relay.obfuscator.is_none());
+
+        let endpoint = relay.endpoint;
+        assert_eq!(endpoint.peer.endpoint, custom_entry.endpoint);
+        assert_eq!(endpoint.peer.public_key, custom_entry.public_key);
+        let exit_peer = endpoint.exit_peer.expect("Expected an exit peer");
+        assert_eq!(exit_peer.endpoint.ip(), IpAddr::V4(exit.ipv4_addr_in));
+        // The custom entry must only be used to reach the exit relay
+        assert!(
+            endpoint
+                .peer
+                .allowed_ips
+                .iter()
+                .all(|net| net.contains(exit_peer.endpoint.ip()) && net.prefix() == 32)
+        );
+    }
+
     /// Test whether Shadowsocks is always selected as the obfuscation protocol when Shadowsocks is
     /// selected.
     #[test]
@@ -525,6 +566,49 @@ mod relay_selection {
         )));
     }
 
+    /// Test that racing only includes the selected transports that the relay supports, using the
+    /// configured stagger delay.
+    #[test]
+    fn test_selecting_race() {
+        let mut relay_list = RelayListBuilder::new();
+        relay_list.add_relay("quic").endpoint_data.quic = Some(Quic::new(
+            vec1![Ipv4Addr::LOCALHOST.into()],
+            "Bearer test".to_owned(),
+            "quic.example".to_owned(),
+        ));
+        let relay_selector = RelaySelector::from(relay_list);
+
+        let settings = RaceSettings {
+            transports: vec![
+                RaceTransport::Direct,
+                RaceTransport::Lwo,
+                RaceTransport::Quic,
+            ],
+            stagger_delay: std::time::Duration::from_millis(250),
+        };
+        let query = RelayQueryBuilder::new().race(settings).build();
+
+        let relay = relay_selector.get_relay_by_query(query).unwrap();
+        let Some(Obfuscators::Multiplexer {
+            direct: Some(_),
+            configs: (ObfuscatorConfig::Quic { .. }, remaining),
+            stagger_delay,
+        }) = relay.obfuscator
+        else {
+            panic!("Relay selector expected a race between direct and QUIC")
+        };
+        assert!(remaining.is_empty(), "LWO is not supported by the relay");
+        assert_eq!(stagger_delay, std::time::Duration::from_millis(250));
+
+        // Racing only unsupported transports should not match the relay
+        let settings = RaceSettings {
+            transports: vec![RaceTransport::Lwo],
+            ..RaceSettings::default()
+        };
+        let query = RelayQueryBuilder::new().race(settings).build();
+        assert!(relay_selector.get_relay_by_query(query).is_err());
+    }
+
     #[test]
     fn test_selecting_over_quic_ipv6_without_wireguard_ipv6() {
         let mut relay_list = RelayListBuilder::new();
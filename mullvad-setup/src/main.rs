@@ -49,6 +49,10 @@ pub enum Error {
     #[error("Firewall error")]
     FirewallError(#[source] firewall::Error),
 
+    #[cfg(target_os = "linux")]
+    #[error("Invalid network identifiers")]
+    NetworkIdentifiers(#[source] mullvad_daemon::network_ids::Error),
+
     #[error("Failed to initialize mullvad RPC runtime")]
     RpcInitializationError(#[source] mullvad_api::Error),
 
@@ -235,9 +239,16 @@ async fn reset_firewall() -> Result<(), Error> {
         return Err(Error::DaemonIsRunning);
     }
 
+    // Use the same marks as the daemon, which may have been overridden by environment variables
+    #[cfg(target_os = "linux")]
+    let network_ids = mullvad_daemon::network_ids::NetworkIdentifiers::from_env_vars()
+        .map_err(Error::NetworkIdentifiers)?;
+
     Firewall::new(
         #[cfg(target_os = "linux")]
-        mullvad_types::TUNNEL_FWMARK,
+        network_ids.fwmark,
+        #[cfg(target_os = "linux")]
+        network_ids.split_tunnel_mark,
         #[cfg(target_os = "linux")]
         None,
         // TODO split-tunneling?
@@ -64,3 +64,74 @@ impl AccessTokenData {
         Utc::now() >= self.expiry
     }
 }
+
+/// How long before the account expires the daemon warns clients, in hours. A warning is also sent
+/// when the account has expired.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "Vec<u32>", into = "Vec<u32>")]
+pub struct ExpiryWarningThresholds(Vec<u32>);
+
+impl ExpiryWarningThresholds {
+    /// Create thresholds from a number of hours each. The thresholds are sorted from the largest
+    /// to the smallest, and duplicates and zeroes are removed.
+    pub fn new(hours: impl IntoIterator<Item = u32>) -> Self {
+        let mut hours: Vec<u32> = hours.into_iter().filter(|hours| *hours > 0).collect();
+        hours.sort_unstable_by(|a, b| b.cmp(a));
+        hours.dedup();
+        ExpiryWarningThresholds(hours)
+    }
+
+    /// Return the thresholds in hours, from the largest to the smallest.
+    pub fn hours(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl Default for ExpiryWarningThresholds {
+    /// Warn a week, three days and one day before the account expires.
+    fn default() -> Self {
+        ExpiryWarningThresholds::new([7 * 24, 3 * 24, 24])
+    }
+}
+
+impl From<Vec<u32>> for ExpiryWarningThresholds {
+    fn from(hours: Vec<u32>) -> Self {
+        ExpiryWarningThresholds::new(hours)
+    }
+}
+
+impl From<ExpiryWarningThresholds> for Vec<u32> {
+    fn from(thresholds: ExpiryWarningThresholds) -> Self {
+        thresholds.0
+    }
+}
+
+/// Sent by the daemon when the account has less time left than one of the
+/// [`ExpiryWarningThresholds`], and when it has run out of time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountExpiryWarning {
+    pub expiry: DateTime<Utc>,
+    /// The threshold that was passed, in hours. This is `0` if the account has expired.
+    pub threshold_hours: u32,
+}
+
+impl AccountExpiryWarning {
+    /// Return true if the account has no time left.
+    pub fn is_expired(&self) -> bool {
+        self.threshold_hours == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expiry_warning_thresholds_are_normalized() {
+        let thresholds = ExpiryWarningThresholds::new([24, 0, 168, 24, 72]);
+        assert_eq!(thresholds.hours(), &[168, 72, 24]);
+
+        let thresholds = ExpiryWarningThresholds::from(vec![1, 12, 12, 0]);
+        assert_eq!(thresholds.hours(), &[12, 1]);
+    }
+}
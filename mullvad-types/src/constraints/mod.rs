@@ -131,6 +131,7 @@ impl_intersection_partialeq!(relay_constraints::Ownership);
 impl_intersection_partialeq!(talpid_types::net::TransportProtocol);
 impl_intersection_partialeq!(talpid_types::net::IpVersion);
 impl_intersection_partialeq!(relay_constraints::AllowedIps);
+impl_intersection_partialeq!(relay_constraints::RaceSettings);
 impl_intersection_partialeq!(crate::relay_selector::ResolvedLocationConstraint);
 
 #[cfg(test)]
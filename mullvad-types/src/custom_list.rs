@@ -1,4 +1,4 @@
-use crate::relay_constraints::GeographicLocationConstraint;
+use crate::{relay_constraints::GeographicLocationConstraint, relay_list::RelayList};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeSet,
@@ -8,6 +8,9 @@ use std::{
 
 const CUSTOM_LIST_NAME_MAX_SIZE: usize = 30;
 
+/// Version of the format written by [`CustomListsExport`].
+pub const CUSTOM_LISTS_EXPORT_VERSION: u32 = 1;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Custom list name too long")]
@@ -176,3 +179,192 @@ impl CustomList {
         self.locations.append(&mut locations);
     }
 }
+
+/// A shareable set of custom lists, which can be imported on other devices.
+///
+/// Lists are identified by name rather than by [Id]. Each location is written as a country code
+/// (`se`), a country and city code (`se-got`) or a relay hostname (`se-got-wg-001`).
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomListsExport {
+    pub version: u32,
+    pub custom_lists: Vec<ExportedCustomList>,
+}
+
+impl CustomListsExport {
+    pub fn new<'a>(custom_lists: impl IntoIterator<Item = &'a CustomList>) -> Self {
+        Self {
+            version: CUSTOM_LISTS_EXPORT_VERSION,
+            custom_lists: custom_lists
+                .into_iter()
+                .map(ExportedCustomList::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ExportedCustomList {
+    pub name: String,
+    pub locations: Vec<String>,
+}
+
+impl From<&CustomList> for ExportedCustomList {
+    fn from(custom_list: &CustomList) -> Self {
+        let locations = custom_list
+            .locations
+            .iter()
+            .map(|location| match location {
+                GeographicLocationConstraint::Country(country) => country.clone(),
+                GeographicLocationConstraint::City(country, city) => format!("{country}-{city}"),
+                GeographicLocationConstraint::Hostname(_, _, hostname) => hostname.clone(),
+            })
+            .collect();
+        Self {
+            name: custom_list.name.clone(),
+            locations,
+        }
+    }
+}
+
+impl ExportedCustomList {
+    /// Look up every location of this list in `relay_list`.
+    ///
+    /// Returns the locations that were found, followed by the ones that were not.
+    pub fn resolve(
+        &self,
+        relay_list: &RelayList,
+    ) -> (BTreeSet<GeographicLocationConstraint>, Vec<String>) {
+        let mut resolved = BTreeSet::new();
+        let mut unknown = Vec::new();
+        for location in &self.locations {
+            match resolve_location(&location.trim().to_lowercase(), relay_list) {
+                Some(constraint) => {
+                    resolved.insert(constraint);
+                }
+                None => unknown.push(location.clone()),
+            }
+        }
+        (resolved, unknown)
+    }
+}
+
+fn resolve_location(
+    location: &str,
+    relay_list: &RelayList,
+) -> Option<GeographicLocationConstraint> {
+    // Take the country and city of a relay from the relay list rather than from its hostname,
+    // since the two do not have to agree.
+    if let Some(relay) = relay_list.relays().find(|relay| relay.hostname == location) {
+        return Some(GeographicLocationConstraint::hostname(
+            relay.location.country_code.clone(),
+            relay.location.city_code.clone(),
+            relay.hostname.clone(),
+        ));
+    }
+    match location.parse().ok()? {
+        GeographicLocationConstraint::Country(country) => relay_list
+            .lookup_country(country.clone())
+            .map(|_| GeographicLocationConstraint::Country(country)),
+        GeographicLocationConstraint::City(country, city) => relay_list
+            .lookup_country(country.clone())?
+            .lookup_city(city.clone())
+            .map(|_| GeographicLocationConstraint::City(country, city)),
+        GeographicLocationConstraint::Hostname(..) => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        location::Location,
+        relay_list::{
+            Relay, RelayListCity, RelayListCountry, WireguardRelay, WireguardRelayEndpointData,
+        },
+    };
+    use talpid_types::net::wireguard::PublicKey;
+
+    fn relay_list() -> RelayList {
+        let location = Location {
+            country: "Sweden".to_string(),
+            country_code: "se".to_string(),
+            city: "Gothenburg".to_string(),
+            city_code: "got".to_string(),
+            latitude: 57.71,
+            longitude: 11.97,
+        };
+        let relay = WireguardRelay::new(
+            false,
+            false,
+            true,
+            true,
+            "provider0".to_string(),
+            WireguardRelayEndpointData::new(
+                PublicKey::from_base64("BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=").unwrap(),
+            ),
+            Relay {
+                // The hostname does not match the location of the relay
+                hostname: "se-sto-wg-001".to_string(),
+                ipv4_addr_in: "185.213.154.68".parse().unwrap(),
+                ipv6_addr_in: None,
+                active: true,
+                weight: 1,
+                location,
+            },
+        );
+        RelayList {
+            countries: vec![RelayListCountry {
+                name: "Sweden".to_string(),
+                code: "se".to_string(),
+                cities: vec![RelayListCity {
+                    name: "Gothenburg".to_string(),
+                    code: "got".to_string(),
+                    latitude: 57.70887,
+                    longitude: 11.97456,
+                    relays: vec![relay],
+                }],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_export_and_resolve() {
+        let mut custom_list = CustomList::new("test".to_string()).unwrap();
+        custom_list.locations = BTreeSet::from([
+            GeographicLocationConstraint::country("se"),
+            GeographicLocationConstraint::city("se", "got"),
+            GeographicLocationConstraint::hostname("se", "got", "se-sto-wg-001"),
+        ]);
+
+        let export = CustomListsExport::new([&custom_list]);
+        assert_eq!(export.version, CUSTOM_LISTS_EXPORT_VERSION);
+        let exported = &export.custom_lists[0];
+        assert_eq!(exported.name, "test");
+        assert_eq!(exported.locations, ["se", "se-got", "se-sto-wg-001"]);
+
+        let (resolved, unknown) = exported.resolve(&relay_list());
+        assert_eq!(resolved, custom_list.locations);
+        assert!(unknown.is_empty());
+    }
+
+    #[test]
+    fn test_resolve_unknown_locations() {
+        let exported = ExportedCustomList {
+            name: "test".to_string(),
+            locations: vec![
+                " SE ".to_string(),
+                "de".to_string(),
+                "se-mma".to_string(),
+                "se-got-wg-999".to_string(),
+            ],
+        };
+
+        let (resolved, unknown) = exported.resolve(&relay_list());
+        assert_eq!(
+            resolved,
+            BTreeSet::from([GeographicLocationConstraint::country("se")])
+        );
+        assert_eq!(unknown, ["de", "se-mma", "se-got-wg-999"]);
+    }
+}
@@ -17,7 +17,7 @@ pub struct FeatureIndicators(HashSet<FeatureIndicator>);
 
 impl Debug for FeatureIndicators {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut indicators: Vec<&str> = self.0.iter().map(|feature| feature.to_str()).collect();
+        let mut indicators: Vec<String> = self.0.iter().map(ToString::to_string).collect();
         // Sort the features alphabetically (Just to have some order, arbitrarily chosen)
         indicators.sort();
         f.debug_tuple("FeatureIndicators")
@@ -34,7 +34,7 @@ impl FeatureIndicators {
 
 impl Display for FeatureIndicators {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut indicators: Vec<&str> = self.0.iter().map(|feature| feature.to_str()).collect();
+        let mut indicators: Vec<String> = self.0.iter().map(ToString::to_string).collect();
         // Sort the features alphabetically (Just to have some order, arbitrarily chosen)
         indicators.sort();
 
@@ -85,6 +85,12 @@ pub enum FeatureIndicator {
     ServerIpOverride,
     CustomMtu,
     Daita,
+    FirewallExceptions,
+    InboundPorts,
+    LanGateway,
+    TrustedInterfaces,
+    /// The settings match the active settings profile, whose name is given.
+    SettingsProfile(String),
 }
 
 impl FeatureIndicator {
@@ -106,6 +112,11 @@ impl FeatureIndicator {
             FeatureIndicator::ServerIpOverride => "Server Ip Override",
             FeatureIndicator::CustomMtu => "Custom MTU",
             FeatureIndicator::Daita => "DAITA",
+            FeatureIndicator::FirewallExceptions => "Firewall Exceptions",
+            FeatureIndicator::InboundPorts => "Inbound Ports",
+            FeatureIndicator::LanGateway => "LAN Gateway",
+            FeatureIndicator::TrustedInterfaces => "Trusted Interfaces",
+            FeatureIndicator::SettingsProfile(_) => "Settings Profile",
         }
     }
 }
@@ -113,7 +124,10 @@ impl FeatureIndicator {
 impl std::fmt::Display for FeatureIndicator {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let feature = self.to_str();
-        write!(f, "{feature}")
+        match self {
+            FeatureIndicator::SettingsProfile(name) => write!(f, "{feature} ({name})"),
+            _ => write!(f, "{feature}"),
+        }
     }
 }
 
@@ -137,18 +151,53 @@ pub fn compute_feature_indicators(
     #[cfg(not(target_os = "android"))]
     let lockdown_mode = settings.lockdown_mode;
     let lan_sharing = settings.allow_lan;
+    #[cfg(target_os = "linux")]
+    let firewall_exceptions = !settings.firewall_exceptions.is_empty();
+    #[cfg(not(target_os = "linux"))]
+    let firewall_exceptions = false;
+    #[cfg(target_os = "linux")]
+    let inbound_ports = !settings.inbound_ports.is_empty();
+    #[cfg(not(target_os = "linux"))]
+    let inbound_ports = false;
+    #[cfg(target_os = "linux")]
+    let lan_gateway = settings.lan_gateway.is_some();
+    #[cfg(not(target_os = "linux"))]
+    let lan_gateway = false;
+    #[cfg(target_os = "linux")]
+    let trusted_interfaces = !settings.trusted_interfaces.is_empty();
+    #[cfg(not(target_os = "linux"))]
+    let trusted_interfaces = false;
     let dns_content_blockers = settings
         .tunnel_options
         .dns_options
         .default_options
         .any_blockers_enabled();
     let custom_dns = settings.tunnel_options.dns_options.state == DnsState::Custom;
+    let settings_profile = settings
+        .active_settings_profile
+        .clone()
+        .map(FeatureIndicator::SettingsProfile);
 
     let quantum_resistant = endpoint.quantum_resistant;
 
     let has_obfuscation = |obfs| match &endpoint.obfuscation {
         Some(ObfuscationInfo::Single(endpoint)) => endpoint.obfuscation_type == obfs,
-        Some(ObfuscationInfo::Multiplexer { obfuscators, .. }) => obfuscators
+        // Once the multiplexer has settled on a transport, only that one is in use
+        Some(ObfuscationInfo::Multiplexer {
+            direct,
+            obfuscators,
+            selected: Some(selected),
+        }) => {
+            direct.as_ref() != Some(selected)
+                && obfuscators
+                    .iter()
+                    .any(|single| single.endpoint == *selected && single.obfuscation_type == obfs)
+        }
+        Some(ObfuscationInfo::Multiplexer {
+            obfuscators,
+            selected: None,
+            ..
+        }) => obfuscators
             .iter()
             .any(|single| single.obfuscation_type == obfs),
         None => false,
@@ -167,6 +216,11 @@ pub fn compute_feature_indicators(
     let daita = endpoint.daita;
 
     let (multihop, multihop_auto) = match &settings.relay_settings {
+        crate::relay_constraints::RelaySettings::Normal(constraints)
+            if constraints.wireguard_constraints.custom_entry.is_some() =>
+        {
+            (endpoint.entry_endpoint.is_some(), false)
+        }
         crate::relay_constraints::RelaySettings::Normal(constraints) => {
             match constraints.wireguard_constraints.multihop {
                 Multihop::Always => (endpoint.entry_endpoint.is_some(), false),
@@ -181,6 +235,10 @@ pub fn compute_feature_indicators(
     let protocol_features = vec![
         (split_tunneling, FeatureIndicator::SplitTunneling),
         (lan_sharing, FeatureIndicator::LanSharing),
+        (firewall_exceptions, FeatureIndicator::FirewallExceptions),
+        (inbound_ports, FeatureIndicator::InboundPorts),
+        (lan_gateway, FeatureIndicator::LanGateway),
+        (trusted_interfaces, FeatureIndicator::TrustedInterfaces),
         (dns_content_blockers, FeatureIndicator::DnsContentBlockers),
         (custom_dns, FeatureIndicator::CustomDns),
         (server_ip_override, FeatureIndicator::ServerIpOverride),
@@ -202,6 +260,7 @@ pub fn compute_feature_indicators(
     protocol_features
         .into_iter()
         .filter_map(|(active, feature)| active.then_some(feature))
+        .chain(settings_profile)
         .collect()
 }
 
@@ -228,6 +287,7 @@ mod tests {
             entry_endpoint: Default::default(),
             tunnel_interface: Default::default(),
             daita: Default::default(),
+            daita_parameters: Default::default(),
         };
 
         let mut expected_indicators: FeatureIndicators = [].into_iter().collect();
@@ -373,6 +433,69 @@ mod tests {
             expected_indicators,
         );
 
+        #[cfg(target_os = "linux")]
+        {
+            settings
+                .firewall_exceptions
+                .push(talpid_types::net::FirewallException {
+                    destination: "192.0.2.1/32".parse().unwrap(),
+                    protocol: talpid_types::net::TransportProtocol::Tcp,
+                    port: 443,
+                    interface: None,
+                });
+            expected_indicators
+                .0
+                .insert(FeatureIndicator::FirewallExceptions);
+            assert_eq!(
+                compute_feature_indicators(&settings, &endpoint, false),
+                expected_indicators,
+            );
+
+            settings.inbound_ports.push(talpid_types::net::InboundPort {
+                protocol: talpid_types::net::TransportProtocol::Udp,
+                port: 51820,
+                source: None,
+            });
+            expected_indicators.0.insert(FeatureIndicator::InboundPorts);
+            assert_eq!(
+                compute_feature_indicators(&settings, &endpoint, false),
+                expected_indicators,
+            );
+
+            settings.lan_gateway = Some(talpid_types::net::LanGateway {
+                interfaces: vec!["eth1".to_owned()],
+                subnets: vec![],
+            });
+            expected_indicators.0.insert(FeatureIndicator::LanGateway);
+            assert_eq!(
+                compute_feature_indicators(&settings, &endpoint, false),
+                expected_indicators,
+            );
+
+            settings
+                .trusted_interfaces
+                .push(talpid_types::net::TrustedInterface {
+                    name: "tailscale0".to_owned(),
+                    dns_forwarding: None,
+                });
+            expected_indicators
+                .0
+                .insert(FeatureIndicator::TrustedInterfaces);
+            assert_eq!(
+                compute_feature_indicators(&settings, &endpoint, false),
+                expected_indicators,
+            );
+        }
+
+        settings.active_settings_profile = Some("travel".to_string());
+        expected_indicators
+            .0
+            .insert(FeatureIndicator::SettingsProfile("travel".to_string()));
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected_indicators,
+        );
+
         // NOTE: If this match statement fails to compile, it means that a new feature indicator has
         // been added. Please update this test to include the new feature indicator.
         match FeatureIndicator::QuantumResistance {
@@ -392,6 +515,74 @@ mod tests {
             FeatureIndicator::ServerIpOverride => {}
             FeatureIndicator::CustomMtu => {}
             FeatureIndicator::Daita => {}
+            FeatureIndicator::FirewallExceptions => {}
+            FeatureIndicator::InboundPorts => {}
+            FeatureIndicator::LanGateway => {}
+            FeatureIndicator::TrustedInterfaces => {}
+            FeatureIndicator::SettingsProfile(_) => {}
         }
     }
+
+    /// A multiplexed obfuscation should only report the transport that was selected, once known.
+    #[test]
+    fn test_multiplexer_selected_obfuscation() {
+        let settings = Settings::default();
+        let direct = Endpoint {
+            address: SocketAddr::from(([1, 2, 3, 4], 51820)),
+            protocol: TransportProtocol::Udp,
+        };
+        let udp2tcp = ObfuscationEndpoint {
+            endpoint: Endpoint {
+                address: SocketAddr::from(([1, 2, 3, 4], 443)),
+                protocol: TransportProtocol::Tcp,
+            },
+            obfuscation_type: ObfuscationType::Udp2Tcp,
+        };
+        let quic = ObfuscationEndpoint {
+            endpoint: Endpoint {
+                address: SocketAddr::from(([1, 2, 3, 5], 443)),
+                protocol: TransportProtocol::Udp,
+            },
+            obfuscation_type: ObfuscationType::Quic,
+        };
+        let mut endpoint = TunnelEndpoint {
+            endpoint: direct,
+            quantum_resistant: Default::default(),
+            obfuscation: Some(ObfuscationInfo::Multiplexer {
+                direct: Some(direct),
+                obfuscators: vec![udp2tcp.clone(), quic.clone()],
+                selected: None,
+            }),
+            entry_endpoint: Default::default(),
+            tunnel_interface: Default::default(),
+            daita: Default::default(),
+            daita_parameters: Default::default(),
+        };
+
+        let expected: FeatureIndicators = [FeatureIndicator::Udp2Tcp, FeatureIndicator::Quic]
+            .into_iter()
+            .collect();
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected
+        );
+
+        endpoint
+            .obfuscation
+            .as_mut()
+            .unwrap()
+            .set_selected(quic.endpoint);
+        let expected: FeatureIndicators = [FeatureIndicator::Quic].into_iter().collect();
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected
+        );
+
+        endpoint.obfuscation.as_mut().unwrap().set_selected(direct);
+        let expected: FeatureIndicators = [].into_iter().collect();
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected
+        );
+    }
 }
@@ -7,6 +7,11 @@ pub mod device;
 pub mod endpoint;
 pub mod features;
 pub mod location;
+#[cfg(target_os = "linux")]
+pub mod management_access;
+#[cfg(not(target_os = "android"))]
+pub mod metrics;
+pub mod network_hints;
 pub mod relay_constraints;
 pub mod relay_list;
 pub mod relay_selector;
@@ -21,8 +26,13 @@ pub use crate::custom_tunnel::*;
 // b"mole" is [ 0x6d, 0x6f 0x6c, 0x65 ]
 #[cfg(target_os = "linux")]
 pub const TUNNEL_TABLE_ID: u32 = 0x6d6f6c65;
+// b"molf"
+#[cfg(target_os = "linux")]
+pub const EXCEPTION_TABLE_ID: u32 = 0x6d6f6c66;
 #[cfg(target_os = "linux")]
 pub const TUNNEL_FWMARK: u32 = 0x6d6f6c65;
+#[cfg(target_os = "linux")]
+pub const TUNNEL_INTERFACE_NAME: &str = "wg0-mullvad";
 
 pub use constraints::Intersection;
 pub use intersection_derive::Intersection;
@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, fmt};
+
+/// Access level of a management interface client. Each role is allowed to do everything the
+/// roles before it are allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May read the tunnel state and settings.
+    ReadOnly,
+    /// May also connect, disconnect and change how the tunnel is set up.
+    Operator,
+    /// May call every RPC, including the ones that log out, reset the app or lower the protection
+    /// offered by the firewall.
+    Admin,
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Role::ReadOnly => f.write_str("read-only"),
+            Role::Operator => f.write_str("operator"),
+            Role::Admin => f.write_str("admin"),
+        }
+    }
+}
+
+/// Assigns roles to management interface clients based on the user and groups of the connecting
+/// process. The root user is always an admin.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccessPolicy {
+    /// Role of clients whose user and groups are not listed.
+    pub default_role: Role,
+    /// Roles by user ID. These take precedence over the group roles.
+    pub users: HashMap<u32, Role>,
+    /// Roles by group ID. A client is given the highest role of the groups it is a member of.
+    pub groups: HashMap<u32, Role>,
+}
+
+impl Default for AccessPolicy {
+    /// Allow every client to call every RPC.
+    fn default() -> Self {
+        AccessPolicy {
+            default_role: Role::Admin,
+            users: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl AccessPolicy {
+    /// Only allow the root user to change anything.
+    pub fn root_only() -> Self {
+        AccessPolicy {
+            default_role: Role::ReadOnly,
+            users: HashMap::new(),
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Return the role of a client running as user `uid` and the groups `gids`.
+    pub fn role(&self, uid: u32, gids: impl IntoIterator<Item = u32>) -> Role {
+        if uid == 0 {
+            return Role::Admin;
+        }
+        if let Some(role) = self.users.get(&uid) {
+            return *role;
+        }
+        gids.into_iter()
+            .filter_map(|gid| self.groups.get(&gid))
+            .max()
+            .copied()
+            .unwrap_or(self.default_role)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_role() {
+        let policy = AccessPolicy {
+            default_role: Role::ReadOnly,
+            users: HashMap::from([(1000, Role::Admin), (1001, Role::ReadOnly)]),
+            groups: HashMap::from([(100, Role::Operator), (101, Role::Admin)]),
+        };
+
+        assert_eq!(policy.role(0, []), Role::Admin);
+        assert_eq!(policy.role(1000, []), Role::Admin);
+        assert_eq!(policy.role(1002, []), Role::ReadOnly);
+        assert_eq!(policy.role(1002, [100]), Role::Operator);
+        assert_eq!(policy.role(1002, [100, 101]), Role::Admin);
+        // User roles take precedence over group roles
+        assert_eq!(policy.role(1001, [101]), Role::ReadOnly);
+    }
+
+    #[test]
+    fn test_default_allows_everyone() {
+        assert_eq!(AccessPolicy::default().role(1000, [1000]), Role::Admin);
+    }
+}
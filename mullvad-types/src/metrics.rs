@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    net::{Ipv4Addr, SocketAddr},
+};
+
+/// Where the daemon serves its metrics, in the OpenMetrics text format. Metrics are only
+/// collected while an endpoint is configured.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricsEndpoint {
+    /// Listen on a TCP port on the loopback interface.
+    Tcp { port: u16 },
+    /// Listen on a Unix domain socket with this file name. The socket is created in a directory
+    /// that only the daemon can write to, so that it never replaces files that it did not create.
+    #[cfg(unix)]
+    UnixSocket { name: String },
+}
+
+impl MetricsEndpoint {
+    /// Return the address to listen on, if this is a TCP endpoint.
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        match self {
+            MetricsEndpoint::Tcp { port } => Some(SocketAddr::from((Ipv4Addr::LOCALHOST, *port))),
+            #[cfg(unix)]
+            MetricsEndpoint::UnixSocket { .. } => None,
+        }
+    }
+}
+
+/// Return whether `name` can be used as the file name of a metrics socket. It must be a single
+/// path component of at most [`MAX_SOCKET_NAME_LEN`] bytes.
+#[cfg(unix)]
+pub fn is_valid_socket_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_SOCKET_NAME_LEN
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\0'])
+}
+
+/// Longest accepted socket name, leaving room for the directory in `sun_path`.
+#[cfg(unix)]
+pub const MAX_SOCKET_NAME_LEN: usize = 64;
+
+impl fmt::Display for MetricsEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MetricsEndpoint::Tcp { port } => {
+                write!(f, "http://{}:{port}/metrics", Ipv4Addr::LOCALHOST)
+            }
+            #[cfg(unix)]
+            MetricsEndpoint::UnixSocket { name } => write!(f, "unix:{name}"),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_socket_name() {
+        assert!(is_valid_socket_name("metrics.sock"));
+        for name in ["", ".", "..", "../mullvad-vpn", "/run/metrics.sock", "a\0b"] {
+            assert!(!is_valid_socket_name(name), "{name:?}");
+        }
+        assert!(!is_valid_socket_name(&"a".repeat(MAX_SOCKET_NAME_LEN + 1)));
+    }
+}
@@ -0,0 +1,182 @@
+//! Per-network hints about which entry parameters last managed to establish a tunnel.
+//!
+//! Networks are identified by a [`NetworkFingerprint`]. When a tunnel comes up, the entry-specific
+//! constraints that were used are recorded for the current network, so that the next connection
+//! attempt on the same network can start from them instead of walking the whole retry order.
+
+use std::{fmt, net::IpAddr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use talpid_types::net::IpVersion;
+
+use crate::{
+    constraints::Constraint,
+    relay_constraints::{
+        ObfuscationSettings, obfuscation_constraint_from_settings, obfuscation_to_settings,
+    },
+    relay_selector::EntrySpecificConstraints,
+};
+
+/// The maximum number of networks to remember. The least recently successful network is evicted
+/// first.
+pub const MAX_NETWORK_HINTS: usize = 64;
+
+/// Identifies the physical network that the device is currently connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct NetworkFingerprint {
+    /// Name of the interface that the default route goes through.
+    pub interface: String,
+    /// IP address of the default gateway.
+    pub gateway: IpAddr,
+    /// Link-layer address of the default gateway, if it could be determined.
+    pub gateway_mac: Option<String>,
+}
+
+impl fmt::Display for NetworkFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} via {}", self.interface, self.gateway)?;
+        if let Some(mac) = &self.gateway_mac {
+            write!(f, " ({mac})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Entry parameters that successfully established a tunnel on a given network.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NetworkHint {
+    pub network: NetworkFingerprint,
+    pub obfuscation: ObfuscationSettings,
+    pub ip_version: Constraint<IpVersion>,
+    /// When a tunnel was last established using this hint.
+    pub last_success: DateTime<Utc>,
+}
+
+impl NetworkHint {
+    pub fn new(network: NetworkFingerprint, entry: &EntrySpecificConstraints) -> Self {
+        NetworkHint {
+            network,
+            obfuscation: obfuscation_to_settings(entry.obfuscation.clone()),
+            ip_version: entry.ip_version,
+            last_success: Utc::now(),
+        }
+    }
+
+    /// The retry entry that this hint represents.
+    pub fn entry_constraints(&self) -> EntrySpecificConstraints {
+        EntrySpecificConstraints {
+            obfuscation: obfuscation_constraint_from_settings(self.obfuscation.clone()),
+            daita: Constraint::Any,
+            ip_version: self.ip_version,
+        }
+    }
+}
+
+/// A bounded collection of [`NetworkHint`]s, at most one per network.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct NetworkHints(Vec<NetworkHint>);
+
+impl NetworkHints {
+    /// Returns the hint for `network`, if any.
+    pub fn get(&self, network: &NetworkFingerprint) -> Option<&NetworkHint> {
+        self.0.iter().find(|hint| &hint.network == network)
+    }
+
+    /// Insert `hint`, replacing any existing hint for the same network. If there are more than
+    /// [`MAX_NETWORK_HINTS`] hints, the least recently successful ones are removed.
+    pub fn insert(&mut self, hint: NetworkHint) {
+        self.0.retain(|existing| existing.network != hint.network);
+        self.0.push(hint);
+        self.0.sort_by(|a, b| b.last_success.cmp(&a.last_success));
+        self.0.truncate(MAX_NETWORK_HINTS);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NetworkHint> {
+        self.0.iter()
+    }
+}
+
+impl FromIterator<NetworkHint> for NetworkHints {
+    fn from_iter<T: IntoIterator<Item = NetworkHint>>(iter: T) -> Self {
+        let mut hints = NetworkHints::default();
+        for hint in iter {
+            hints.insert(hint);
+        }
+        hints
+    }
+}
+
+impl IntoIterator for NetworkHints {
+    type Item = NetworkHint;
+    type IntoIter = std::vec::IntoIter<NetworkHint>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeDelta;
+    use std::net::Ipv4Addr;
+
+    fn network(gateway: u8) -> NetworkFingerprint {
+        NetworkFingerprint {
+            interface: "eth0".to_owned(),
+            gateway: IpAddr::V4(Ipv4Addr::new(192, 168, 1, gateway)),
+            gateway_mac: Some("00:11:22:33:44:55".to_owned()),
+        }
+    }
+
+    #[test]
+    fn test_hint_roundtrips_entry_constraints() {
+        let entry = EntrySpecificConstraints::udp2tcp().ip_version(IpVersion::V6);
+        let hint = NetworkHint::new(network(1), &entry);
+        assert_eq!(hint.entry_constraints(), entry);
+    }
+
+    #[test]
+    fn test_insert_replaces_existing_network() {
+        let mut hints = NetworkHints::default();
+        hints.insert(NetworkHint::new(
+            network(1),
+            &EntrySpecificConstraints::lwo(),
+        ));
+        hints.insert(NetworkHint::new(
+            network(1),
+            &EntrySpecificConstraints::quic(),
+        ));
+
+        assert_eq!(hints.iter().count(), 1);
+        assert_eq!(
+            hints.get(&network(1)).unwrap().entry_constraints(),
+            EntrySpecificConstraints::quic()
+        );
+    }
+
+    #[test]
+    fn test_insert_evicts_least_recent() {
+        let now = Utc::now();
+        let mut hints = NetworkHints::default();
+        for i in 0..=MAX_NETWORK_HINTS {
+            let mut hint = NetworkHint::new(network(i as u8), &EntrySpecificConstraints::lwo());
+            hint.last_success = now + TimeDelta::seconds(i as i64);
+            hints.insert(hint);
+        }
+
+        assert_eq!(hints.iter().count(), MAX_NETWORK_HINTS);
+        assert!(hints.get(&network(0)).is_none());
+        assert!(hints.get(&network(MAX_NETWORK_HINTS as u8)).is_some());
+    }
+}
@@ -12,10 +12,11 @@ use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     fmt,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     str::FromStr,
+    time::Duration,
 };
-use talpid_types::net::{IpVersion, TransportProtocol};
+use talpid_types::net::{IpVersion, TransportProtocol, wireguard};
 
 /// Specifies a specific endpoint or [`RelayConstraints`] to use when `mullvad-daemon` selects a
 /// relay.
@@ -397,6 +398,24 @@ pub struct WireguardConstraints {
     pub entry_location: Constraint<LocationConstraint>,
     pub entry_providers: Constraint<Providers>,
     pub entry_ownership: Constraint<Ownership>,
+    /// Use a user-provided WireGuard server as the entry hop instead of a Mullvad relay. When set,
+    /// only the exit relay is selected and the other multihop settings are ignored.
+    pub custom_entry: Option<CustomEntry>,
+}
+
+/// A self-hosted WireGuard server used as the entry hop in front of a Mullvad exit relay.
+///
+/// The server must accept the device's tunnel addresses and forward traffic to the exit relay.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CustomEntry {
+    pub endpoint: SocketAddr,
+    pub public_key: wireguard::PublicKey,
+}
+
+impl fmt::Display for CustomEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.endpoint, self.public_key)
+    }
 }
 
 /// Possible multihop setting states.
@@ -583,7 +602,9 @@ impl fmt::Display for WireguardConstraintsFormatter<'_> {
         if let Constraint::Only(ip_version) = self.constraints.ip_version {
             write!(f, ", {ip_version},")?;
         }
-        if let Some(entry) = self.constraints.multihop_entry() {
+        if let Some(custom_entry) = &self.constraints.custom_entry {
+            write!(f, ", custom entry {custom_entry}")?;
+        } else if let Some(entry) = self.constraints.multihop_entry() {
             let location = LocationConstraintFormatter {
                 constraint: entry,
                 custom_lists: self.custom_lists,
@@ -607,6 +628,7 @@ pub enum SelectedObfuscation {
     Shadowsocks,
     Quic,
     Lwo,
+    Race,
 }
 
 impl Intersection for SelectedObfuscation {
@@ -634,6 +656,7 @@ impl fmt::Display for SelectedObfuscation {
             SelectedObfuscation::Quic => "quic".fmt(f),
             SelectedObfuscation::Lwo => "lwo".fmt(f),
             SelectedObfuscation::WireguardPort => "wireguard port".fmt(f),
+            SelectedObfuscation::Race => "race".fmt(f),
         }
     }
 }
@@ -722,6 +745,77 @@ impl fmt::Display for LwoSettings {
     }
 }
 
+/// A transport that can take part in an obfuscation race.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum RaceTransport {
+    /// Plain WireGuard without any obfuscation.
+    Direct,
+    Lwo,
+    #[cfg_attr(feature = "clap", clap(name = "udp2tcp"))]
+    Udp2Tcp,
+    Shadowsocks,
+    Quic,
+}
+
+impl fmt::Display for RaceTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RaceTransport::Direct => "direct".fmt(f),
+            RaceTransport::Lwo => "lwo".fmt(f),
+            RaceTransport::Udp2Tcp => "udp2tcp".fmt(f),
+            RaceTransport::Shadowsocks => "shadowsocks".fmt(f),
+            RaceTransport::Quic => "quic".fmt(f),
+        }
+    }
+}
+
+/// Delay between starting each transport in an obfuscation race, unless configured otherwise.
+pub const DEFAULT_RACE_STAGGER_DELAY: Duration = Duration::from_secs(1);
+
+/// Settings for racing several obfuscation methods against each other. The transports are started
+/// one after another, and the first one to receive a response from the relay is kept.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[serde(default)]
+pub struct RaceSettings {
+    /// Transports taking part in the race. A direct transport is always started first.
+    pub transports: Vec<RaceTransport>,
+    /// Delay between starting each transport.
+    pub stagger_delay: Duration,
+}
+
+impl Default for RaceSettings {
+    fn default() -> Self {
+        Self {
+            transports: vec![
+                RaceTransport::Direct,
+                RaceTransport::Lwo,
+                RaceTransport::Udp2Tcp,
+                RaceTransport::Shadowsocks,
+                RaceTransport::Quic,
+            ],
+            stagger_delay: DEFAULT_RACE_STAGGER_DELAY,
+        }
+    }
+}
+
+impl fmt::Display for RaceSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.transports.is_empty() {
+            write!(f, "no transports")?;
+        }
+        for (i, transport) in self.transports.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{transport}")?;
+        }
+        write!(f, " (stagger delay {} ms)", self.stagger_delay.as_millis())
+    }
+}
+
 /// Contains obfuscation settings
 #[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -732,6 +826,7 @@ pub struct ObfuscationSettings {
     pub shadowsocks: ShadowsocksSettings,
     pub wireguard_port: WireguardPortSettings,
     pub lwo: LwoSettings,
+    pub race: RaceSettings,
 }
 
 /// Represents a specific obfuscation method (or explicit "off").
@@ -746,6 +841,7 @@ pub enum ObfuscationMode {
     Shadowsocks(ShadowsocksSettings),
     Quic,
     Lwo(LwoSettings),
+    Race(RaceSettings),
 }
 
 impl ObfuscationMode {
@@ -781,6 +877,13 @@ impl ObfuscationMode {
                     ..Default::default()
                 };
             }
+            ObfuscationMode::Race(settings) => {
+                return ObfuscationSettings {
+                    selected_obfuscation: SelectedObfuscation::Race,
+                    race: settings,
+                    ..Default::default()
+                };
+            }
         };
         ObfuscationSettings {
             selected_obfuscation,
@@ -817,6 +920,7 @@ pub fn obfuscation_constraint_from_settings(
         Shadowsocks => Constraint::Only(ObfuscationMode::Shadowsocks(obfuscation.shadowsocks)),
         Quic => Constraint::Only(ObfuscationMode::Quic),
         Lwo => Constraint::Only(ObfuscationMode::Lwo(obfuscation.lwo)),
+        Race => Constraint::Only(ObfuscationMode::Race(obfuscation.race)),
     }
 }
 
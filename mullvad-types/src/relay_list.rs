@@ -5,6 +5,7 @@ use crate::{
 use serde::{Deserialize, Serialize};
 use std::{
     collections::{HashMap, HashSet},
+    fmt,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::RangeInclusive,
 };
@@ -33,6 +34,35 @@ pub struct BridgeList {
     pub bridge_endpoint: BridgeEndpointData,
 }
 
+/// Where the relay list in use was obtained from.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum RelayListSource {
+    /// The relay list that was shipped with the app.
+    Bundled,
+    /// Downloaded from the API.
+    Api,
+    /// Imported from a file by an administrator. Its authenticity is not verified.
+    Imported { path: String },
+}
+
+impl fmt::Display for RelayListSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayListSource::Bundled => write!(f, "bundled with the app"),
+            RelayListSource::Api => write!(f, "downloaded from the API"),
+            RelayListSource::Imported { path } => write!(f, "imported from {path}"),
+        }
+    }
+}
+
+/// The origin and version of the relay list in use.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RelayListInfo {
+    pub source: RelayListSource,
+    pub etag: Option<String>,
+}
+
 impl RelayList {
     pub fn empty() -> Self {
         Self::default()
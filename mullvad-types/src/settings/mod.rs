@@ -1,5 +1,8 @@
+#[cfg(not(target_os = "android"))]
+use crate::metrics::MetricsEndpoint;
 use crate::{
     access_method,
+    account::ExpiryWarningThresholds,
     constraints::Constraint,
     custom_list::CustomListsSettings,
     relay_constraints::{
@@ -13,8 +16,11 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use std::collections::HashSet;
 use talpid_types::net::GenericTunnelOptions;
+#[cfg(target_os = "linux")]
+use talpid_types::net::{DnsBackend, FirewallException, InboundPort, LanGateway, TrustedInterface};
 
 mod dns;
+pub mod profile;
 
 /// Top level settings that can be controlled by the user. (i.e. not metadata or purely internal items)
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, Hash)]
@@ -27,7 +33,24 @@ pub enum SettingsKey {
     AllowLan,
     #[cfg(not(target_os = "android"))]
     LockdownMode,
+    #[cfg(target_os = "linux")]
+    TunnelNamespace,
+    #[cfg(target_os = "linux")]
+    FirewallExceptions,
+    #[cfg(target_os = "linux")]
+    InboundPorts,
+    #[cfg(target_os = "linux")]
+    LanGateway,
+    #[cfg(target_os = "linux")]
+    TrustedInterfaces,
+    #[cfg(target_os = "linux")]
+    DnsBackend,
+    #[cfg(target_os = "linux")]
+    ReapplyFirewallOnDrift,
     AutoConnect,
+    ExpiryWarningThresholds,
+    #[cfg(not(target_os = "android"))]
+    MetricsEndpoint,
     TunnelOptions,
     RelayOverrides,
     ShowBetaReleases,
@@ -41,11 +64,20 @@ pub struct SettingsKeyList {
     pub keys: Vec<SettingsKey>,
 }
 
+/// The result of validating a settings patch without applying it.
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct SettingsPatchPreview {
+    /// Settings that would be changed by the accepted parts of the patch.
+    pub changed: Vec<SettingsKey>,
+    /// Reasons for rejecting the patch. The patch can only be applied if this is empty.
+    pub errors: Vec<String>,
+}
+
 /// The version used by the current version of the code. Should always be the
 /// latest version that exists in `SettingsVersion`.
 /// This should be bumped when a new version is introduced along with a migration
 /// being added to `mullvad-daemon`.
-pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V18;
+pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V19;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
 #[repr(u32)]
@@ -67,6 +99,7 @@ pub enum SettingsVersion {
     V16 = 16,
     V17 = 17,
     V18 = 18,
+    V19 = 19,
 }
 
 impl<'de> Deserialize<'de> for SettingsVersion {
@@ -92,6 +125,7 @@ impl<'de> Deserialize<'de> for SettingsVersion {
             v if v == SettingsVersion::V16 as u32 => Ok(SettingsVersion::V16),
             v if v == SettingsVersion::V17 as u32 => Ok(SettingsVersion::V17),
             v if v == SettingsVersion::V18 as u32 => Ok(SettingsVersion::V18),
+            v if v == SettingsVersion::V19 as u32 => Ok(SettingsVersion::V19),
             v => Err(serde::de::Error::custom(format!(
                 "{v} is not a valid SettingsVersion"
             ))),
@@ -126,8 +160,42 @@ pub struct Settings {
     /// the firewall to not allow any traffic in or out.
     #[cfg(not(target_os = "android"))]
     pub lockdown_mode: bool,
+    /// Confine the tunnel to a separate network namespace. Only programs launched with
+    /// `mullvad-include` use the tunnel, while the rest of the system is unaffected.
+    #[cfg(target_os = "linux")]
+    pub tunnel_namespace: bool,
+    /// Outbound traffic that is allowed outside the tunnel in every tunnel state, including
+    /// when lockdown mode is enabled.
+    #[cfg(target_os = "linux")]
+    pub firewall_exceptions: Vec<FirewallException>,
+    /// Ports on the tunnel interface that accept incoming connections while connected.
+    #[cfg(target_os = "linux")]
+    pub inbound_ports: Vec<InboundPort>,
+    /// Forward traffic from other devices on these local interfaces and subnets through the
+    /// tunnel. Forwarded traffic is blocked whenever the tunnel is not connected.
+    #[cfg(target_os = "linux")]
+    pub lan_gateway: Option<LanGateway>,
+    /// Interfaces of other VPN or mesh network clients, such as Tailscale, that may be used
+    /// alongside the tunnel.
+    #[cfg(target_os = "linux")]
+    pub trusted_interfaces: Vec<TrustedInterface>,
+    /// How DNS is configured while connected.
+    #[cfg(target_os = "linux")]
+    pub dns_backend: DnsBackend,
+    /// Apply the firewall rules again when another program is found to have changed or removed
+    /// them.
+    #[cfg(target_os = "linux")]
+    pub reapply_firewall_on_drift: bool,
     /// If the daemon should connect the VPN tunnel directly on start or not.
     pub auto_connect: bool,
+    /// How long before the account expires the daemon warns clients.
+    pub expiry_warning_thresholds: ExpiryWarningThresholds,
+    /// Where to serve metrics about the daemon. Metrics are not collected if this is `None`.
+    #[cfg(not(target_os = "android"))]
+    pub metrics_endpoint: Option<MetricsEndpoint>,
+    /// Name of the settings profile that was activated last, as long as the settings still match
+    /// it.
+    pub active_settings_profile: Option<String>,
     /// Options that should be applied to tunnels of a specific type regardless of where the relays
     /// might be located.
     pub tunnel_options: TunnelOptions,
@@ -264,7 +332,25 @@ impl Default for Settings {
             allow_lan: false,
             #[cfg(not(target_os = "android"))]
             lockdown_mode: false,
+            #[cfg(target_os = "linux")]
+            tunnel_namespace: false,
+            #[cfg(target_os = "linux")]
+            firewall_exceptions: vec![],
+            #[cfg(target_os = "linux")]
+            inbound_ports: vec![],
+            #[cfg(target_os = "linux")]
+            lan_gateway: None,
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: vec![],
+            #[cfg(target_os = "linux")]
+            dns_backend: DnsBackend::default(),
+            #[cfg(target_os = "linux")]
+            reapply_firewall_on_drift: false,
             auto_connect: false,
+            expiry_warning_thresholds: ExpiryWarningThresholds::default(),
+            #[cfg(not(target_os = "android"))]
+            metrics_endpoint: None,
+            active_settings_profile: None,
             tunnel_options: TunnelOptions::default(),
             relay_overrides: vec![],
             show_beta_releases: false,
@@ -323,6 +409,58 @@ impl Settings {
         }
     }
 
+    /// Replace the setting identified by `key` with its value in `other`.
+    pub fn copy_setting(&mut self, other: &Settings, key: &SettingsKey) {
+        match key {
+            SettingsKey::RelaySettings => self.relay_settings = other.relay_settings.clone(),
+            SettingsKey::ObfuscationSettings => {
+                self.obfuscation_settings = other.obfuscation_settings.clone()
+            }
+            SettingsKey::CustomLists => self.custom_lists = other.custom_lists.clone(),
+            SettingsKey::ApiAccessMethods => {
+                self.api_access_methods = other.api_access_methods.clone()
+            }
+            SettingsKey::UpdateDefaultLocation => {
+                self.update_default_location = other.update_default_location
+            }
+            SettingsKey::AllowLan => self.allow_lan = other.allow_lan,
+            #[cfg(not(target_os = "android"))]
+            SettingsKey::LockdownMode => self.lockdown_mode = other.lockdown_mode,
+            #[cfg(target_os = "linux")]
+            SettingsKey::TunnelNamespace => self.tunnel_namespace = other.tunnel_namespace,
+            #[cfg(target_os = "linux")]
+            SettingsKey::FirewallExceptions => {
+                self.firewall_exceptions = other.firewall_exceptions.clone()
+            }
+            #[cfg(target_os = "linux")]
+            SettingsKey::InboundPorts => self.inbound_ports = other.inbound_ports.clone(),
+            #[cfg(target_os = "linux")]
+            SettingsKey::LanGateway => self.lan_gateway = other.lan_gateway.clone(),
+            #[cfg(target_os = "linux")]
+            SettingsKey::TrustedInterfaces => {
+                self.trusted_interfaces = other.trusted_interfaces.clone()
+            }
+            #[cfg(target_os = "linux")]
+            SettingsKey::DnsBackend => self.dns_backend = other.dns_backend,
+            #[cfg(target_os = "linux")]
+            SettingsKey::ReapplyFirewallOnDrift => {
+                self.reapply_firewall_on_drift = other.reapply_firewall_on_drift
+            }
+            SettingsKey::AutoConnect => self.auto_connect = other.auto_connect,
+            SettingsKey::ExpiryWarningThresholds => {
+                self.expiry_warning_thresholds = other.expiry_warning_thresholds.clone()
+            }
+            #[cfg(not(target_os = "android"))]
+            SettingsKey::MetricsEndpoint => self.metrics_endpoint = other.metrics_endpoint.clone(),
+            SettingsKey::TunnelOptions => self.tunnel_options = other.tunnel_options.clone(),
+            SettingsKey::RelayOverrides => self.relay_overrides = other.relay_overrides.clone(),
+            SettingsKey::ShowBetaReleases => self.show_beta_releases = other.show_beta_releases,
+            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+            SettingsKey::SplitTunnel => self.split_tunnel = other.split_tunnel.clone(),
+            SettingsKey::Recents => self.recents = other.recents.clone(),
+        }
+    }
+
     // Add the current RelaySettings to the recents lists. If recents are disabled do nothing.
     pub fn update_recents(&mut self) {
         let Some(recents) = self.recents.as_mut() else {
@@ -0,0 +1,168 @@
+use super::{Settings, SettingsKey};
+use serde::{Deserialize, Serialize};
+
+const PROFILE_NAME_MAX_SIZE: usize = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Settings profile name must be between 1 and {PROFILE_NAME_MAX_SIZE} characters")]
+    InvalidName,
+    #[error("Settings profile must contain at least one setting")]
+    NoSettings,
+    #[error("Settings profile with name already exists")]
+    DuplicateName,
+    #[error("Settings profile not found")]
+    ProfileNotFound,
+}
+
+/// A named set of values for some of the settings, which are applied together when the profile is
+/// activated.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsProfile {
+    pub name: String,
+    /// The settings that are changed when the profile is activated.
+    pub keys: Vec<SettingsKey>,
+    /// Values of the settings in `keys`. All other settings are ignored.
+    pub settings: Settings,
+}
+
+impl SettingsProfile {
+    /// Create a profile that contains the current values of the settings in `keys`.
+    pub fn new(name: String, keys: Vec<SettingsKey>, current: &Settings) -> Result<Self, Error> {
+        if name.trim().is_empty() || name.chars().count() > PROFILE_NAME_MAX_SIZE {
+            return Err(Error::InvalidName);
+        }
+        if keys.is_empty() {
+            return Err(Error::NoSettings);
+        }
+
+        let mut unique_keys = Vec::with_capacity(keys.len());
+        let mut settings = Settings::default();
+        for key in keys {
+            if !unique_keys.contains(&key) {
+                settings.copy_setting(current, &key);
+                unique_keys.push(key);
+            }
+        }
+
+        Ok(SettingsProfile {
+            name,
+            keys: unique_keys,
+            settings,
+        })
+    }
+
+    /// Apply the profile to `settings`.
+    pub fn apply(&self, settings: &mut Settings) {
+        for key in &self.keys {
+            settings.copy_setting(&self.settings, key);
+        }
+    }
+
+    /// Return the settings that would change if the profile was applied to `settings`.
+    pub fn diff(&self, settings: &Settings) -> Vec<SettingsKey> {
+        self.keys
+            .iter()
+            .filter(|key| {
+                let mut applied = settings.clone();
+                applied.copy_setting(&self.settings, key);
+                applied != *settings
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Return whether applying the profile to `settings` would not change anything.
+    pub fn matches(&self, settings: &Settings) -> bool {
+        self.diff(settings).is_empty()
+    }
+}
+
+/// All settings profiles, in the order they were created.
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SettingsProfiles {
+    profiles: Vec<SettingsProfile>,
+}
+
+impl SettingsProfiles {
+    pub fn add(&mut self, profile: SettingsProfile) -> Result<(), Error> {
+        if self.get(&profile.name).is_some() {
+            return Err(Error::DuplicateName);
+        }
+        self.profiles.push(profile);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<SettingsProfile, Error> {
+        let index = self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+            .ok_or(Error::ProfileNotFound)?;
+        Ok(self.profiles.remove(index))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SettingsProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SettingsProfile> {
+        self.profiles.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_apply_and_diff() {
+        let mut current = Settings::default();
+        current.allow_lan = true;
+        current.auto_connect = true;
+
+        let profile = SettingsProfile::new(
+            "home".to_string(),
+            vec![SettingsKey::AllowLan, SettingsKey::AllowLan],
+            &current,
+        )
+        .unwrap();
+        assert_eq!(profile.keys, vec![SettingsKey::AllowLan]);
+        assert!(profile.matches(&current));
+
+        let mut other = Settings::default();
+        assert_eq!(profile.diff(&other), vec![SettingsKey::AllowLan]);
+
+        profile.apply(&mut other);
+        assert!(other.allow_lan);
+        assert!(
+            !other.auto_connect,
+            "Settings outside the profile must not change"
+        );
+        assert!(profile.matches(&other));
+    }
+
+    #[test]
+    fn test_invalid_profiles() {
+        let settings = Settings::default();
+        assert!(matches!(
+            SettingsProfile::new(" ".to_string(), vec![SettingsKey::AllowLan], &settings),
+            Err(Error::InvalidName)
+        ));
+        assert!(matches!(
+            SettingsProfile::new("home".to_string(), vec![], &settings),
+            Err(Error::NoSettings)
+        ));
+
+        let mut profiles = SettingsProfiles::default();
+        let profile =
+            SettingsProfile::new("home".to_string(), vec![SettingsKey::AllowLan], &settings)
+                .unwrap();
+        profiles.add(profile.clone()).unwrap();
+        assert!(matches!(profiles.add(profile), Err(Error::DuplicateName)));
+        assert!(matches!(
+            profiles.remove("travel"),
+            Err(Error::ProfileNotFound)
+        ));
+    }
+}
@@ -10,6 +10,9 @@ pub const MIN_ROTATION_INTERVAL: Duration = Duration::from_hours(24);
 pub const MAX_ROTATION_INTERVAL: Duration = Duration::from_hours(30 * 24);
 pub const DEFAULT_ROTATION_INTERVAL: Duration = MAX_ROTATION_INTERVAL;
 
+pub const MIN_REKEY_INTERVAL: Duration = Duration::from_hours(1);
+pub const MAX_REKEY_INTERVAL: Duration = Duration::from_hours(7 * 24);
+
 #[derive(Serialize, Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq, Intersection)]
 #[serde(rename_all = "lowercase")]
 #[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
@@ -77,59 +80,115 @@ impl WireguardData {
     }
 }
 
+/// Interval used for automatic key rotation.
+pub type RotationInterval = Interval<Rotation>;
+
+/// How often the quantum-resistant PSK is renegotiated while connected.
+pub type RekeyInterval = Interval<Rekey>;
+
+/// The range that an [Interval] must be within.
+pub trait IntervalBounds {
+    /// Name of the interval, used in error messages.
+    const NAME: &'static str;
+    const MIN: Duration;
+    const MAX: Duration;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation;
+
+impl IntervalBounds for Rotation {
+    const NAME: &'static str = "Rotation interval";
+    const MIN: Duration = MIN_ROTATION_INTERVAL;
+    const MAX: Duration = MAX_ROTATION_INTERVAL;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rekey;
+
+impl IntervalBounds for Rekey {
+    const NAME: &'static str = "Rekey interval";
+    const MIN: Duration = MIN_REKEY_INTERVAL;
+    const MAX: Duration = MAX_REKEY_INTERVAL;
+}
+
 #[derive(Debug, Clone)]
-pub enum RotationIntervalError {
-    TooSmall,
-    TooLarge,
+pub enum IntervalError {
+    TooSmall { name: &'static str, min: Duration },
+    TooLarge { name: &'static str, max: Duration },
 }
 
-impl fmt::Display for RotationIntervalError {
+impl fmt::Display for IntervalError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use RotationIntervalError::*;
+        use IntervalError::*;
 
         match *self {
-            TooSmall => write!(
+            TooSmall { name, min } => write!(
                 f,
-                "Rotation interval must be at least {} hours",
-                MIN_ROTATION_INTERVAL.as_secs() / 60 / 60
+                "{name} must be at least {} hours",
+                min.as_secs() / 60 / 60
             ),
-            TooLarge => write!(
+            TooLarge { name, max } => write!(
                 f,
-                "Rotation interval must be at most {} hours",
-                MAX_ROTATION_INTERVAL.as_secs() / 60 / 60
+                "{name} must be at most {} hours",
+                max.as_secs() / 60 / 60
             ),
         }
     }
 }
 
-impl std::error::Error for RotationIntervalError {}
+impl std::error::Error for IntervalError {}
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
-pub struct RotationInterval(Duration);
+/// A duration within the range given by `B`. It is specified in whole hours on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Interval<B> {
+    interval: Duration,
+    _bounds: std::marker::PhantomData<B>,
+}
 
-impl RotationInterval {
-    pub fn new(interval: Duration) -> Result<RotationInterval, RotationIntervalError> {
-        if interval < MIN_ROTATION_INTERVAL {
-            Err(RotationIntervalError::TooSmall)
-        } else if interval > MAX_ROTATION_INTERVAL {
-            Err(RotationIntervalError::TooLarge)
+impl<B: IntervalBounds> Interval<B> {
+    pub fn new(interval: Duration) -> Result<Self, IntervalError> {
+        if interval < B::MIN {
+            Err(IntervalError::TooSmall {
+                name: B::NAME,
+                min: B::MIN,
+            })
+        } else if interval > B::MAX {
+            Err(IntervalError::TooLarge {
+                name: B::NAME,
+                max: B::MAX,
+            })
         } else {
-            Ok(RotationInterval(interval))
+            Ok(Interval {
+                interval,
+                _bounds: std::marker::PhantomData,
+            })
         }
     }
+}
 
+impl<B> Interval<B> {
     pub fn as_duration(&self) -> &Duration {
-        &self.0
+        &self.interval
+    }
+}
+
+impl<B> Serialize for Interval<B> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.interval.serialize(serializer)
     }
 }
 
-impl<'de> Deserialize<'de> for RotationInterval {
+impl<'de, B: IntervalBounds> Deserialize<'de> for Interval<B> {
     fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         let ivl = <Duration>::deserialize(deserializer)?;
-        RotationInterval::new(ivl).map_err(|_error| {
+        Interval::new(ivl).map_err(|_error| {
             serde::de::Error::invalid_value(
                 serde::de::Unexpected::Other("Duration"),
                 &"interval within allowed range",
@@ -138,46 +197,48 @@ impl<'de> Deserialize<'de> for RotationInterval {
     }
 }
 
-impl TryFrom<Duration> for RotationInterval {
-    type Error = RotationIntervalError;
+impl<B: IntervalBounds> TryFrom<Duration> for Interval<B> {
+    type Error = IntervalError;
 
-    fn try_from(duration: Duration) -> Result<RotationInterval, RotationIntervalError> {
-        RotationInterval::new(duration)
+    fn try_from(duration: Duration) -> Result<Self, IntervalError> {
+        Interval::new(duration)
     }
 }
 
-impl fmt::Display for RotationInterval {
+impl<B> fmt::Display for Interval<B> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{} hours", self.as_duration().as_secs() / 60 / 60)
     }
 }
 
 #[cfg(feature = "clap")]
-impl clap::builder::ValueParserFactory for RotationInterval {
-    type Parser = clap::builder::RangedU64ValueParser<RotationInterval>;
+impl<B> clap::builder::ValueParserFactory for Interval<B>
+where
+    B: IntervalBounds + Clone + Send + Sync + 'static,
+{
+    type Parser = clap::builder::RangedU64ValueParser<Interval<B>>;
 
     fn value_parser() -> Self::Parser {
-        clap::builder::RangedU64ValueParser::new().range(
-            (MIN_ROTATION_INTERVAL.as_secs() / 60 / 60)
-                ..=(MAX_ROTATION_INTERVAL.as_secs() / 60 / 60),
-        )
+        clap::builder::RangedU64ValueParser::new()
+            .range((B::MIN.as_secs() / 60 / 60)..=(B::MAX.as_secs() / 60 / 60))
     }
 }
 
-impl TryFrom<u64> for RotationInterval {
-    type Error = RotationIntervalError;
+impl<B: IntervalBounds> TryFrom<u64> for Interval<B> {
+    type Error = IntervalError;
 
     fn try_from(value: u64) -> Result<Self, Self::Error> {
-        // Convert a u64, specified in hours, to a `RotationInterval`
-        let val = value
-            .checked_mul(60 * 60)
-            .ok_or(RotationIntervalError::TooLarge)?;
-        RotationInterval::new(Duration::from_secs(val))
+        // Convert a u64, specified in hours, to an `Interval`
+        let val = value.checked_mul(60 * 60).ok_or(IntervalError::TooLarge {
+            name: B::NAME,
+            max: B::MAX,
+        })?;
+        Interval::new(Duration::from_secs(val))
     }
 }
 
-impl From<RotationInterval> for Duration {
-    fn from(interval: RotationInterval) -> Duration {
+impl<B> From<Interval<B>> for Duration {
+    fn from(interval: Interval<B>) -> Duration {
         *interval.as_duration()
     }
 }
@@ -197,10 +258,15 @@ pub struct TunnelOptions {
     pub quantum_resistant: QuantumResistantState,
     /// Configure DAITA
     pub daita: bool,
+    /// DAITA level to request from relays. `None` lets the relay pick its default level.
+    pub daita_level: Option<wireguard::DaitaLevel>,
     /// Use userspace WireGuard.
     pub userspace: bool,
     /// Interval used for automatic key rotation
     pub rotation_interval: Option<RotationInterval>,
+    /// Interval used for renegotiating the quantum-resistant PSK while connected. `None`
+    /// disables renegotiation.
+    pub rekey_interval: Option<RekeyInterval>,
 }
 
 #[expect(clippy::derivable_impls)]
@@ -210,8 +276,10 @@ impl Default for TunnelOptions {
             mtu: None,
             quantum_resistant: QuantumResistantState::default(),
             daita: false,
+            daita_level: None,
             userspace: false,
             rotation_interval: None,
+            rekey_interval: None,
         }
     }
 }
@@ -222,7 +290,9 @@ impl TunnelOptions {
             mtu: self.mtu,
             quantum_resistant: self.quantum_resistant.enabled(),
             daita: self.daita,
+            daita_level: self.daita_level,
             userspace: self.userspace,
+            rekey_interval: self.rekey_interval.map(Duration::from),
         }
     }
 }
@@ -241,3 +311,26 @@ pub struct AssociatedAddresses {
     pub ipv4_address: ipnetwork::Ipv4Network,
     pub ipv6_address: ipnetwork::Ipv6Network,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interval_bounds() {
+        assert!(RekeyInterval::new(MIN_REKEY_INTERVAL).is_ok());
+        assert!(RekeyInterval::new(MAX_REKEY_INTERVAL).is_ok());
+        assert!(matches!(
+            RekeyInterval::try_from(0u64),
+            Err(IntervalError::TooSmall { .. })
+        ));
+        assert!(matches!(
+            RotationInterval::try_from(MAX_REKEY_INTERVAL),
+            Err(IntervalError::TooSmall { .. })
+        ));
+        assert!(matches!(
+            RotationInterval::try_from(u64::MAX),
+            Err(IntervalError::TooLarge { .. })
+        ));
+    }
+}
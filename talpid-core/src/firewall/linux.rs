@@ -1,5 +1,4 @@
-use super::{FirewallArguments, FirewallPolicy};
-use crate::split_tunnel;
+use super::{FirewallArguments, FirewallDrift, FirewallPolicy, FirewallRules};
 use ipnetwork::IpNetwork;
 use nftnl::{
     Batch, Chain, FinalizedBatch, ProtoFamily, Rule, Table,
@@ -8,18 +7,24 @@ use nftnl::{
 };
 use std::{
     env,
-    ffi::CStr,
+    ffi::{CStr, CString},
     fs, io,
-    net::{IpAddr, Ipv4Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::LazyLock,
 };
 use talpid_cgroup::v2::CGroup2;
+use talpid_netns::NetNs;
 use talpid_tunnel::TunnelMetadata;
-use talpid_types::net::{
-    ALLOWED_LAN_MULTICAST_NETS, ALLOWED_LAN_NETS, AllowedEndpoint, AllowedTunnelTraffic, Endpoint,
-    TransportProtocol,
+use talpid_types::{
+    ErrorExt,
+    net::{
+        ALLOWED_LAN_MULTICAST_NETS, ALLOWED_LAN_NETS, AllowedEndpoint, AllowedTunnelTraffic,
+        Endpoint, FirewallException, InboundPort, LanGateway, TransportProtocol, TrustedInterface,
+    },
 };
 
+mod ruleset;
+
 /// Priority for rules that tag split tunneling packets. Equals NF_IP_PRI_MANGLE.
 const MANGLE_CHAIN_PRIORITY: i32 = libc::NF_IP_PRI_MANGLE;
 const PREROUTING_CHAIN_PRIORITY: i32 = libc::NF_IP_PRI_CONNTRACK + 1;
@@ -56,6 +61,14 @@ pub enum Error {
     /// Unable to translate network interface name into index.
     #[error("Unable to translate network interface name \"{0}\" into index")]
     LookupIfaceIndexError(String, #[source] crate::linux::IfaceIndexLookupError),
+
+    /// The name of a network interface is not valid.
+    #[error("Invalid network interface name \"{0}\"")]
+    InvalidIfaceName(String),
+
+    /// Failed to apply rules inside the tunnel network namespace.
+    #[error("Failed to enter the tunnel network namespace")]
+    NamespaceError(#[source] talpid_netns::Error),
 }
 
 /// TODO(linus): This crate is not supposed to be Mullvad-aware. So at some point this should be
@@ -67,6 +80,8 @@ const FORWARD_CHAIN_NAME: &CStr = c"forward";
 const PREROUTING_CHAIN_NAME: &CStr = c"prerouting";
 const MANGLE_CHAIN_NAME: &CStr = c"mangle";
 const NAT_CHAIN_NAME: &CStr = c"nat";
+const NAT_OUTPUT_CHAIN_NAME: &CStr = c"nat-output";
+const NAT_PREROUTING_CHAIN_NAME: &CStr = c"nat-prerouting";
 
 /// Allows controlling whether firewall rules should have packet counters or not from an env
 /// variable. Useful for debugging the rules.
@@ -103,12 +118,22 @@ enum End {
 pub struct Firewall {
     /// Firewall mark is used to mark traffic which should be able to bypass the tunnel
     fwmark: u32,
+    /// Connection tracking mark of connections which should be able to bypass the tunnel
+    split_tunnel_mark: u32,
     /// The cgroup2 used for split tunneling.
     /// Traffic from processes in this cgroup2 should be allowed outside the tunnel.
     excluded_cgroup2: Option<CGroup2>,
     /// The net_cls id of the v1 cgroup used for split tunneling.
     /// This is used as a fallback to [`Self::excluded_cgroup2`] since old kernels don't support cgroups v2.
     net_cls: Option<u32>,
+    /// The last policy that was applied, if it is still in effect.
+    applied: Option<AppliedPolicy>,
+}
+
+/// A policy and the rules that were loaded into [`TABLE_NAME`] when it was applied.
+struct AppliedPolicy {
+    policy: FirewallPolicy,
+    rules: Vec<String>,
 }
 
 impl Firewall {
@@ -116,6 +141,7 @@ impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self> {
         Firewall::new(
             args.linux_ids.fwmark,
+            args.linux_ids.split_tunnel_mark,
             args.linux_ids.excluded_cgroup2,
             args.linux_ids.net_cls,
         )
@@ -124,9 +150,12 @@ impl Firewall {
     /// Create a `Firewall`.
     ///
     /// - `fwmark` is the metadata mark used by nft to allow some packets outside the tunnel.
+    /// - `split_tunnel_mark` is the conntrack mark used by nft to identify connections that are
+    ///   allowed outside the tunnel.
     /// - `excluded_cgroup2` is the cgroup2 used by nft to apply `fwmark` on some packets.
     pub fn new(
         fwmark: u32,
+        split_tunnel_mark: u32,
         excluded_cgroup2: Option<CGroup2>,
         net_cls: Option<u32>,
     ) -> Result<Self> {
@@ -136,17 +165,82 @@ impl Firewall {
 
         Ok(Firewall {
             fwmark,
+            split_tunnel_mark,
             excluded_cgroup2,
             net_cls,
+            applied: None,
         })
     }
 
     /// Apply a [`FirewallPolicy`] by setting up [`TABLE_NAME`] nftable.
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<()> {
+        self.applied = None;
+
         let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
-        let batch = PolicyBatch::new(&table).finalize(&policy, self)?;
+        let batch = PolicyBatch::new(&table, self.split_tunnel_mark).finalize(&policy, self)?;
         Self::send_and_process(&batch)?;
         Self::apply_kernel_config(&policy);
+        self.verify_tables(&[TABLE_NAME])?;
+
+        // Remember the resulting rules so that changes made by others can be detected
+        match ruleset::list_rules(TABLE_NAME) {
+            Ok(rules) => self.applied = Some(AppliedPolicy { policy, rules }),
+            Err(error) => log::warn!(
+                "{}",
+                error.display_chain_with_msg(
+                    "Failed to list firewall rules. Drift detection is disabled"
+                )
+            ),
+        }
+        Ok(())
+    }
+
+    /// Set up [`TABLE_NAME`] nftable inside `netns`. Only loopback traffic and traffic on
+    /// `tunnel_interface`, if any, is allowed in and out of the namespace.
+    pub fn apply_namespace_policy(
+        &mut self,
+        netns: &NetNs,
+        tunnel_interface: Option<&str>,
+    ) -> Result<()> {
+        netns
+            .run(|| {
+                let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+                let batch = PolicyBatch::new(&table, self.split_tunnel_mark)
+                    .finalize_namespace(tunnel_interface)?;
+                Self::send_and_process(&batch)
+            })
+            .map_err(Error::NamespaceError)?
+    }
+
+    /// Replace [`TABLE_NAME`] nftable with one that only blocks traffic forwarded from
+    /// `lan_gateway`. All other traffic is allowed.
+    pub fn block_lan_gateway(&mut self, lan_gateway: &LanGateway) -> Result<()> {
+        let table = Table::new(TABLE_NAME, ProtoFamily::Inet);
+        let mut batch = Batch::new();
+
+        batch.add(&table, nftnl::MsgType::Add);
+        batch.add(&table, nftnl::MsgType::Del);
+        batch.add(&table, nftnl::MsgType::Add);
+
+        let mut forward_chain = Chain::new(FORWARD_CHAIN_NAME, &table);
+        forward_chain.set_hook(nftnl::Hook::Forward, 0);
+        forward_chain.set_policy(nftnl::Policy::Accept);
+        batch.add(&forward_chain, nftnl::MsgType::Add);
+
+        for interface in &lan_gateway.interfaces {
+            let mut rule = Rule::new(&forward_chain);
+            check_iface_name(&mut rule, Direction::In, interface)?;
+            add_verdict(
+                &mut rule,
+                &Verdict::Reject(RejectionType::Icmp(IcmpCode::PortUnreach)),
+            );
+            batch.add(&rule, nftnl::MsgType::Add);
+        }
+
+        let batch = batch.finalize();
+
+        self.applied = None;
+        Self::send_and_process(&batch)?;
         self.verify_tables(&[TABLE_NAME])
     }
 
@@ -164,11 +258,65 @@ impl Firewall {
         let batch = batch.finalize();
 
         log::debug!("Removing table and chain from netfilter");
+        self.applied = None;
         Self::send_and_process(&batch)?;
 
         Ok(())
     }
 
+    /// Return the rules that the last applied policy resulted in, along with the rules that are
+    /// currently loaded into [`TABLE_NAME`].
+    pub fn rules(&self) -> Result<FirewallRules> {
+        let actual = ruleset::list_rules(TABLE_NAME)?;
+        Ok(match &self.applied {
+            Some(applied) => FirewallRules {
+                policy: Some(applied.policy.to_string()),
+                expected: applied.rules.clone(),
+                actual,
+            },
+            None => FirewallRules {
+                policy: None,
+                expected: vec![],
+                actual,
+            },
+        })
+    }
+
+    /// Return the rules outside of [`TABLE_NAME`] that set or compare the firewall mark or the
+    /// split tunnel mark.
+    pub fn find_conflicting_rules(&self) -> Result<Vec<String>> {
+        ruleset::find_rules_using_marks(TABLE_NAME, &[self.fwmark, self.split_tunnel_mark])
+    }
+
+    /// Check whether the rules in [`TABLE_NAME`] still match the last applied policy. If they do
+    /// not, the policy is applied again if `reapply` is set.
+    pub fn verify_policy(&mut self, reapply: bool) -> Result<Option<FirewallDrift>> {
+        let rules = self.rules()?;
+        if !rules.has_drifted() {
+            return Ok(None);
+        }
+        log::warn!("Firewall rules have been modified by another program");
+
+        let reapplied = match self.applied.as_ref().map(|applied| applied.policy.clone()) {
+            Some(policy) if reapply => match self.apply_policy(policy) {
+                Ok(()) => {
+                    log::info!("Re-applied firewall policy");
+                    true
+                }
+                Err(error) => {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to re-apply firewall policy")
+                    );
+                    false
+                }
+            },
+            _ => false,
+        };
+
+        Ok(Some(FirewallDrift { rules, reapplied }))
+    }
+
     fn apply_kernel_config(policy: &FirewallPolicy) {
         if *DONT_SET_SRC_VALID_MARK {
             log::debug!("Not setting src_valid_mark");
@@ -263,18 +411,22 @@ impl Firewall {
 
 struct PolicyBatch<'a> {
     batch: Batch,
+    /// Connection tracking mark of split tunneled connections.
+    split_tunnel_mark: u32,
     in_chain: Chain<'a>,
     out_chain: Chain<'a>,
     forward_chain: Chain<'a>,
     prerouting_chain: Chain<'a>,
     mangle_chain: Chain<'a>,
     nat_chain: Chain<'a>,
+    nat_output_chain: Chain<'a>,
+    nat_prerouting_chain: Chain<'a>,
 }
 
 impl<'a> PolicyBatch<'a> {
     /// Bootstrap a new nftnl message batch object and add the initial messages creating the
-    /// table and chains.
-    pub fn new(table: &'a Table) -> Self {
+    /// table and chains. `split_tunnel_mark` is used to identify split tunneled connections.
+    pub fn new(table: &'a Table, split_tunnel_mark: u32) -> Self {
         let mut batch = Batch::new();
 
         // Create the table if it does not exist and clear it otherwise.
@@ -314,14 +466,29 @@ impl<'a> PolicyBatch<'a> {
         nat_chain.set_policy(nftnl::Policy::Accept);
         batch.add(&nat_chain, nftnl::MsgType::Add);
 
+        let mut nat_output_chain = Chain::new(NAT_OUTPUT_CHAIN_NAME, table);
+        nat_output_chain.set_hook(nftnl::Hook::Out, libc::NF_IP_PRI_NAT_DST);
+        nat_output_chain.set_type(nftnl::ChainType::Nat);
+        nat_output_chain.set_policy(nftnl::Policy::Accept);
+        batch.add(&nat_output_chain, nftnl::MsgType::Add);
+
+        let mut nat_prerouting_chain = Chain::new(NAT_PREROUTING_CHAIN_NAME, table);
+        nat_prerouting_chain.set_hook(nftnl::Hook::PreRouting, libc::NF_IP_PRI_NAT_DST);
+        nat_prerouting_chain.set_type(nftnl::ChainType::Nat);
+        nat_prerouting_chain.set_policy(nftnl::Policy::Accept);
+        batch.add(&nat_prerouting_chain, nftnl::MsgType::Add);
+
         PolicyBatch {
             batch,
+            split_tunnel_mark,
             in_chain,
             out_chain,
             forward_chain,
             prerouting_chain,
             mangle_chain,
             nat_chain,
+            nat_output_chain,
+            nat_prerouting_chain,
         }
     }
 
@@ -333,11 +500,17 @@ impl<'a> PolicyBatch<'a> {
         firewall: &Firewall,
     ) -> Result<FinalizedBatch> {
         self.add_loopback_rules()?;
+        // Forwarded traffic from the LAN gateway must not reach trusted interfaces
+        if let Some(lan_gateway) = policy.lan_gateway() {
+            self.add_lan_gateway_rules(policy, lan_gateway)?;
+        }
+        self.add_trusted_interface_rules(policy.trusted_interfaces())?;
         // TODO: Investigate if these rules could/should be handled by PidManager instead.
         // It would allow for the firewall to be set up in a secure way even though split tunneling
         // does not work, which is okay. It would also allow us to de-duplicate some copy-paste
         // code which is present both in this module and in PidManager ..
         self.add_split_tunneling_rules(policy, firewall)?;
+        self.add_exception_rules(policy.exceptions(), firewall.fwmark)?;
         self.add_dhcp_client_rules();
         self.add_ndp_rules();
         self.add_policy_specific_rules(policy, firewall.fwmark)?;
@@ -345,6 +518,18 @@ impl<'a> PolicyBatch<'a> {
         Ok(self.batch.finalize())
     }
 
+    /// Finalize the nftnl message batch for the tunnel network namespace. The namespace only
+    /// contains the loopback interface and the tunnel interface, so nothing else is allowed.
+    pub fn finalize_namespace(mut self, tunnel_interface: Option<&str>) -> Result<FinalizedBatch> {
+        self.add_loopback_rules()?;
+        if let Some(tunnel_interface) = tunnel_interface {
+            self.add_allow_tunnel_rules(tunnel_interface)?;
+        }
+        self.add_reject_remaining_rules();
+
+        Ok(self.batch.finalize())
+    }
+
     /// Allow split-tunneled traffic outside the tunnel.
     fn add_split_tunneling_rules(
         &mut self,
@@ -421,15 +606,15 @@ impl<'a> PolicyBatch<'a> {
         // Split tunneled processes have their PIDs added to a cgroup (v1 or v2).
         //
         // This rule matches packets sent by those processes.
-        // Packet will have two new marks applied to it, the `split_tunnel::MARK`
+        // Packet will have two new marks applied to it, the split tunnel mark
         // as a connection tracking mark and the `fwmark` as packet metadata.
         let mut rule = Rule::new(&self.mangle_chain);
         // Add rules for matching packets from a cgroup.
         // This is the only implementation detail of the split tunneling rule that differs between cgroup v1 and v2.
         add_selector_rules(&mut rule);
-        // Loads `split_tunnel::MARK` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
-        // Sets `split_tunnel::MARK` as connection tracker mark
+        // Loads split tunnel mark into first nftnl register
+        rule.add_expr(&nft_expr!(immediate data self.split_tunnel_mark));
+        // Sets split tunnel mark as connection tracker mark
         rule.add_expr(&nft_expr!(ct mark set));
         // Loads `fwmark` into first nftnl register
         rule.add_expr(&nft_expr!(immediate data fwmark));
@@ -443,7 +628,7 @@ impl<'a> PolicyBatch<'a> {
         for chain in &[&self.in_chain, &self.out_chain, &self.forward_chain] {
             let mut rule = Rule::new(chain);
             rule.add_expr(&nft_expr!(ct mark));
-            rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            rule.add_expr(&nft_expr!(cmp == self.split_tunnel_mark));
             add_verdict(&mut rule, &Verdict::Accept);
             self.batch.add(&rule, nftnl::MsgType::Add);
         }
@@ -453,7 +638,7 @@ impl<'a> PolicyBatch<'a> {
             let mut block_tunnel_rule = Rule::new(&self.nat_chain);
             check_iface(&mut block_tunnel_rule, Direction::Out, &tunnel.interface)?;
             block_tunnel_rule.add_expr(&nft_expr!(ct mark));
-            block_tunnel_rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            block_tunnel_rule.add_expr(&nft_expr!(cmp == self.split_tunnel_mark));
             add_verdict(&mut block_tunnel_rule, &Verdict::Drop);
             self.batch.add(&block_tunnel_rule, nftnl::MsgType::Add);
         }
@@ -468,7 +653,7 @@ impl<'a> PolicyBatch<'a> {
         rule.add_expr(&nft_expr!(cmp != iface_index));
 
         rule.add_expr(&nft_expr!(ct mark));
-        rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+        rule.add_expr(&nft_expr!(cmp == self.split_tunnel_mark));
 
         rule.add_expr(&nft_expr!(masquerade));
         if *ADD_COUNTERS {
@@ -482,7 +667,7 @@ impl<'a> PolicyBatch<'a> {
             let mut prerouting_rule = Rule::new(&self.prerouting_chain);
             check_not_iface(&mut prerouting_rule, Direction::In, &tunnel.interface)?;
             prerouting_rule.add_expr(&nft_expr!(ct mark));
-            prerouting_rule.add_expr(&nft_expr!(cmp == split_tunnel::MARK));
+            prerouting_rule.add_expr(&nft_expr!(cmp == self.split_tunnel_mark));
             prerouting_rule.add_expr(&nft_expr!(immediate data fwmark));
             prerouting_rule.add_expr(&nft_expr!(meta mark set));
             if *ADD_COUNTERS {
@@ -494,6 +679,77 @@ impl<'a> PolicyBatch<'a> {
         Ok(())
     }
 
+    /// Allow outgoing traffic that matches any of `exceptions` outside the tunnel. Such
+    /// connections are marked with `fwmark`, both as connection tracking mark and as packet
+    /// metadata, so that they are routed outside the tunnel and replies are let through.
+    ///
+    /// The interface that an exception is restricted to cannot be checked when the packet is
+    /// marked, since the mangle chain sees the interface that was selected before rerouting.
+    /// Instead, the route manager routes marked traffic to the destination through that
+    /// interface, and the filter chains only accept the traffic if it leaves through it.
+    fn add_exception_rules(&mut self, exceptions: &[FirewallException], fwmark: u32) -> Result<()> {
+        if exceptions.is_empty() {
+            return Ok(());
+        }
+
+        for exception in exceptions {
+            // Reroute matching packets outside the tunnel. Only the destination is checked here
+            let mut mangle_rule = Rule::new(&self.mangle_chain);
+            check_exception(&mut mangle_rule, exception);
+            mangle_rule.add_expr(&nft_expr!(immediate data fwmark));
+            mangle_rule.add_expr(&nft_expr!(ct mark set));
+            mangle_rule.add_expr(&nft_expr!(immediate data fwmark));
+            mangle_rule.add_expr(&nft_expr!(meta mark set));
+            if *ADD_COUNTERS {
+                mangle_rule.add_expr(&nft_expr!(counter));
+            }
+            self.batch.add(&mangle_rule, nftnl::MsgType::Add);
+
+            for chain in &[&self.out_chain, &self.forward_chain] {
+                let mut out_rule = Rule::new(chain);
+                if let Some(interface) = &exception.interface {
+                    check_iface_name(&mut out_rule, Direction::Out, interface)?;
+                }
+                check_exception(&mut out_rule, exception);
+                add_verdict(&mut out_rule, &Verdict::Accept);
+                self.batch.add(&out_rule, nftnl::MsgType::Add);
+            }
+        }
+
+        // Allow replies to connections that were allowed by an exception
+        let mut in_rule = Rule::new(&self.in_chain);
+        in_rule.add_expr(&nft_expr!(ct mark));
+        in_rule.add_expr(&nft_expr!(cmp == fwmark));
+        check_ct_established(&mut in_rule);
+        add_verdict(&mut in_rule, &Verdict::Accept);
+        self.batch.add(&in_rule, nftnl::MsgType::Add);
+
+        // Route replies correctly to prevent strict rpf from rejecting them
+        let mut prerouting_rule = Rule::new(&self.prerouting_chain);
+        prerouting_rule.add_expr(&nft_expr!(ct mark));
+        prerouting_rule.add_expr(&nft_expr!(cmp == fwmark));
+        prerouting_rule.add_expr(&nft_expr!(immediate data fwmark));
+        prerouting_rule.add_expr(&nft_expr!(meta mark set));
+        self.batch.add(&prerouting_rule, nftnl::MsgType::Add);
+
+        // The source address may have been selected for the tunnel interface before the packet
+        // was rerouted, so fix it using masquerade.
+        let mut nat_rule = Rule::new(&self.nat_chain);
+        let iface_index = crate::linux::iface_index("lo")
+            .map_err(|e| Error::LookupIfaceIndexError("lo".to_string(), e))?;
+        nat_rule.add_expr(&nft_expr!(meta oif));
+        nat_rule.add_expr(&nft_expr!(cmp != iface_index));
+        nat_rule.add_expr(&nft_expr!(ct mark));
+        nat_rule.add_expr(&nft_expr!(cmp == fwmark));
+        nat_rule.add_expr(&nft_expr!(masquerade));
+        if *ADD_COUNTERS {
+            nat_rule.add_expr(&nft_expr!(counter));
+        }
+        self.batch.add(&nat_rule, nftnl::MsgType::Add);
+
+        Ok(())
+    }
+
     fn add_loopback_rules(&mut self) -> Result<()> {
         const LOOPBACK_IFACE_NAME: &str = "lo";
         self.batch.add(
@@ -507,6 +763,67 @@ impl<'a> PolicyBatch<'a> {
         Ok(())
     }
 
+    /// Allow all traffic on the interfaces of other VPN or mesh clients, except for DNS. DNS is
+    /// only allowed to the resolver that queries are forwarded to, and such queries are never
+    /// redirected to the tunnel DNS servers. These rules must come before any rules that block
+    /// DNS, but after the LAN gateway rules.
+    fn add_trusted_interface_rules(
+        &mut self,
+        trusted_interfaces: &[TrustedInterface],
+    ) -> Result<()> {
+        for interface in trusted_interfaces {
+            if let Some(dns_forwarding) = &interface.dns_forwarding {
+                for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                    let mut nat_rule = Rule::new(&self.nat_output_chain);
+                    check_iface_name(&mut nat_rule, Direction::Out, &interface.name)?;
+                    check_ip(&mut nat_rule, End::Dst, dns_forwarding.resolver);
+                    check_port(&mut nat_rule, protocol, End::Dst, 53);
+                    add_verdict(&mut nat_rule, &Verdict::Accept);
+                    self.batch.add(&nat_rule, nftnl::MsgType::Add);
+
+                    for chain in &[&self.out_chain, &self.forward_chain] {
+                        let mut rule = Rule::new(chain);
+                        check_iface_name(&mut rule, Direction::Out, &interface.name)?;
+                        check_ip(&mut rule, End::Dst, dns_forwarding.resolver);
+                        check_port(&mut rule, protocol, End::Dst, 53);
+                        add_verdict(&mut rule, &Verdict::Accept);
+                        self.batch.add(&rule, nftnl::MsgType::Add);
+                    }
+                }
+            }
+
+            for chain in &[&self.out_chain, &self.forward_chain] {
+                let mut block_udp_rule = Rule::new(chain);
+                check_iface_name(&mut block_udp_rule, Direction::Out, &interface.name)?;
+                check_port(&mut block_udp_rule, TransportProtocol::Udp, End::Dst, 53);
+                add_verdict(
+                    &mut block_udp_rule,
+                    &Verdict::Reject(RejectionType::Icmp(IcmpCode::PortUnreach)),
+                );
+                self.batch.add(&block_udp_rule, nftnl::MsgType::Add);
+
+                let mut block_tcp_rule = Rule::new(chain);
+                check_iface_name(&mut block_tcp_rule, Direction::Out, &interface.name)?;
+                check_port(&mut block_tcp_rule, TransportProtocol::Tcp, End::Dst, 53);
+                add_verdict(&mut block_tcp_rule, &Verdict::Reject(RejectionType::TcpRst));
+                self.batch.add(&block_tcp_rule, nftnl::MsgType::Add);
+
+                let mut out_rule = Rule::new(chain);
+                check_iface_name(&mut out_rule, Direction::Out, &interface.name)?;
+                add_verdict(&mut out_rule, &Verdict::Accept);
+                self.batch.add(&out_rule, nftnl::MsgType::Add);
+            }
+
+            for chain in &[&self.in_chain, &self.forward_chain] {
+                let mut in_rule = Rule::new(chain);
+                check_iface_name(&mut in_rule, Direction::In, &interface.name)?;
+                add_verdict(&mut in_rule, &Verdict::Accept);
+                self.batch.add(&in_rule, nftnl::MsgType::Add);
+            }
+        }
+        Ok(())
+    }
+
     fn add_dhcp_client_rules(&mut self) {
         use self::TransportProtocol::Udp;
         // Outgoing DHCPv4 request
@@ -626,6 +943,7 @@ impl<'a> PolicyBatch<'a> {
                 allow_lan,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
+                ..
             } => {
                 for endpoint in peer_endpoints {
                     self.add_allow_tunnel_endpoint_rules(endpoint, fwmark);
@@ -661,6 +979,9 @@ impl<'a> PolicyBatch<'a> {
                 tunnel,
                 allow_lan,
                 dns_config,
+                inbound_ports,
+                redirect_dns,
+                ..
             } => {
                 for endpoint in peer_endpoints {
                     self.add_allow_tunnel_endpoint_rules(endpoint, fwmark);
@@ -691,10 +1012,18 @@ impl<'a> PolicyBatch<'a> {
                     )?;
                 }
 
+                if *redirect_dns {
+                    self.add_dns_redirect_rules(
+                        &tunnel.interface,
+                        dns_config.tunnel_config(),
+                        dns_config.non_tunnel_config(),
+                    )?;
+                }
+
                 // Important to block DNS *before* we allow the tunnel and allow LAN. So DNS
                 // can't leak to the wrong IPs in the tunnel or on the LAN.
                 self.add_drop_dns_rule();
-                self.add_allow_tunnel_rules(&tunnel.interface)?;
+                self.add_allow_connected_tunnel_rules(&tunnel.interface, inbound_ports)?;
                 if *allow_lan {
                     self.add_block_cve_2019_14899(tunnel);
                 }
@@ -703,6 +1032,7 @@ impl<'a> PolicyBatch<'a> {
             FirewallPolicy::Blocked {
                 allow_lan,
                 allowed_endpoint,
+                ..
             } => {
                 if let Some(endpoint) = allowed_endpoint {
                     self.add_allow_endpoint_rules(endpoint);
@@ -713,7 +1043,7 @@ impl<'a> PolicyBatch<'a> {
                 *allow_lan
             }
             // Linux doesn't take any special actions when disconnecting. The existing firewall rules are sufficient to prevent leaks.
-            FirewallPolicy::Disconnecting { allow_lan } => {
+            FirewallPolicy::Disconnecting { allow_lan, .. } => {
                 // Important to drop DNS before allowing LAN (to stop DNS leaking to the LAN)
                 self.add_drop_dns_rule();
                 *allow_lan
@@ -724,7 +1054,13 @@ impl<'a> PolicyBatch<'a> {
             self.add_allow_lan_rules();
         }
 
-        // Reject any remaining outgoing traffic
+        self.add_reject_remaining_rules();
+
+        Ok(())
+    }
+
+    /// Reject any remaining outgoing traffic
+    fn add_reject_remaining_rules(&mut self) {
         for chain in &[&self.out_chain, &self.forward_chain] {
             let mut reject_rule = Rule::new(chain);
             add_verdict(
@@ -733,8 +1069,6 @@ impl<'a> PolicyBatch<'a> {
             );
             self.batch.add(&reject_rule, nftnl::MsgType::Add);
         }
-
-        Ok(())
     }
 
     fn add_allow_tunnel_endpoint_rules(&mut self, endpoint: &AllowedEndpoint, fwmark: u32) {
@@ -773,7 +1107,7 @@ impl<'a> PolicyBatch<'a> {
         if endpoint.clients.allow_all() {
             let mut rule = Rule::new(&self.mangle_chain);
             check_endpoint(&mut rule, End::Dst, &endpoint.endpoint);
-            rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
+            rule.add_expr(&nft_expr!(immediate data self.split_tunnel_mark));
             rule.add_expr(&nft_expr!(ct mark set));
             rule.add_expr(&nft_expr!(immediate data fwmark));
             rule.add_expr(&nft_expr!(meta mark set));
@@ -885,6 +1219,202 @@ impl<'a> PolicyBatch<'a> {
         }
     }
 
+    /// Redirects all DNS (port 53) traffic from this host to the first tunnel DNS server of each
+    /// IP version, or to the first non-tunnel DNS server if there are no tunnel DNS servers.
+    /// Traffic to loopback addresses, traffic to the non-tunnel DNS servers and split tunnel
+    /// traffic is left untouched.
+    fn add_dns_redirect_rules(
+        &mut self,
+        tunnel_interface: &str,
+        tunnel_servers: &[IpAddr],
+        non_tunnel_servers: &[IpAddr],
+    ) -> Result<()> {
+        for server in non_tunnel_servers {
+            for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                let mut rule = Rule::new(&self.nat_output_chain);
+                check_ip(&mut rule, End::Dst, *server);
+                check_port(&mut rule, protocol, End::Dst, 53);
+                add_verdict(&mut rule, &Verdict::Accept);
+                self.batch.add(&rule, nftnl::MsgType::Add);
+            }
+        }
+
+        let resolvers = if tunnel_servers.is_empty() {
+            non_tunnel_servers
+        } else {
+            tunnel_servers
+        };
+        for resolver in dns_redirect_targets(resolvers) {
+            for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                let mut rule = Rule::new(&self.nat_output_chain);
+                rule.add_expr(&nft_expr!(ct mark));
+                rule.add_expr(&nft_expr!(cmp != self.split_tunnel_mark));
+                check_l3proto(&mut rule, *resolver);
+                match resolver {
+                    IpAddr::V4(_) => {
+                        rule.add_expr(&nft_expr!(payload ipv4 daddr));
+                        rule.add_expr(
+                            &nft_expr!(bitwise mask Ipv4Addr::new(255, 0, 0, 0), xor 0u32),
+                        );
+                        rule.add_expr(&nft_expr!(cmp != Ipv4Addr::new(127, 0, 0, 0)));
+                    }
+                    IpAddr::V6(_) => {
+                        rule.add_expr(&nft_expr!(payload ipv6 daddr));
+                        rule.add_expr(&nft_expr!(cmp != Ipv6Addr::LOCALHOST));
+                    }
+                }
+                check_port(&mut rule, protocol, End::Dst, 53);
+                add_dnat(&mut rule, *resolver);
+                self.batch.add(&rule, nftnl::MsgType::Add);
+            }
+        }
+
+        // The source address was selected for the original destination, which may not be
+        // reachable through the tunnel, so fix it using masquerade.
+        for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+            let mut rule = Rule::new(&self.nat_chain);
+            check_iface(&mut rule, Direction::Out, tunnel_interface)?;
+            check_port(&mut rule, protocol, End::Dst, 53);
+            rule.add_expr(&nft_expr!(masquerade));
+            self.batch.add(&rule, nftnl::MsgType::Add);
+        }
+        // The same applies to non-tunnel DNS servers, unless they are on the loopback interface
+        if tunnel_servers.is_empty() {
+            for resolver in
+                dns_redirect_targets(non_tunnel_servers).filter(|resolver| !resolver.is_loopback())
+            {
+                for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                    let mut rule = Rule::new(&self.nat_chain);
+                    check_ip(&mut rule, End::Dst, *resolver);
+                    check_port(&mut rule, protocol, End::Dst, 53);
+                    rule.add_expr(&nft_expr!(masquerade));
+                    self.batch.add(&rule, nftnl::MsgType::Add);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Forward traffic from the LAN gateway through the tunnel while connected, and give it the
+    /// tunnel DNS servers. Forwarded traffic is rejected in every other case. These rules must
+    /// come before any other forwarding rules, so that nothing else, such as firewall exceptions
+    /// or trusted interfaces, lets forwarded traffic leave outside the tunnel.
+    fn add_lan_gateway_rules(
+        &mut self,
+        policy: &FirewallPolicy,
+        lan_gateway: &LanGateway,
+    ) -> Result<()> {
+        let sources: Vec<Option<IpNetwork>> = if lan_gateway.subnets.is_empty() {
+            vec![None]
+        } else {
+            lan_gateway.subnets.iter().copied().map(Some).collect()
+        };
+
+        for interface in &lan_gateway.interfaces {
+            if let FirewallPolicy::Connected {
+                tunnel, dns_config, ..
+            } = policy
+            {
+                for source in &sources {
+                    let mut forward_rule = Rule::new(&self.forward_chain);
+                    check_iface_name(&mut forward_rule, Direction::In, interface)?;
+                    if let Some(source) = source {
+                        check_net(&mut forward_rule, End::Src, *source);
+                    }
+                    check_iface(&mut forward_rule, Direction::Out, &tunnel.interface)?;
+                    add_verdict(&mut forward_rule, &Verdict::Accept);
+                    self.batch.add(&forward_rule, nftnl::MsgType::Add);
+
+                    // The relay only accepts traffic from the tunnel IPs
+                    let mut nat_rule = Rule::new(&self.nat_chain);
+                    check_iface_name(&mut nat_rule, Direction::In, interface)?;
+                    if let Some(source) = source {
+                        check_net(&mut nat_rule, End::Src, *source);
+                    }
+                    check_iface(&mut nat_rule, Direction::Out, &tunnel.interface)?;
+                    nat_rule.add_expr(&nft_expr!(masquerade));
+                    if *ADD_COUNTERS {
+                        nat_rule.add_expr(&nft_expr!(counter));
+                    }
+                    self.batch.add(&nat_rule, nftnl::MsgType::Add);
+
+                    for resolver in dns_redirect_targets(dns_config.tunnel_config()) {
+                        if source.is_some_and(|source| source.is_ipv4() != resolver.is_ipv4()) {
+                            continue;
+                        }
+                        for protocol in [TransportProtocol::Udp, TransportProtocol::Tcp] {
+                            let mut rule = Rule::new(&self.nat_prerouting_chain);
+                            check_iface_name(&mut rule, Direction::In, interface)?;
+                            match source {
+                                Some(source) => check_net(&mut rule, End::Src, *source),
+                                None => check_l3proto(&mut rule, *resolver),
+                            }
+                            check_port(&mut rule, protocol, End::Dst, 53);
+                            add_dnat(&mut rule, *resolver);
+                            self.batch.add(&rule, nftnl::MsgType::Add);
+                        }
+                    }
+                }
+            }
+
+            if policy.allow_lan() {
+                for net in ALLOWED_LAN_NETS {
+                    let mut rule = Rule::new(&self.forward_chain);
+                    check_iface_name(&mut rule, Direction::In, interface)?;
+                    check_net(&mut rule, End::Dst, net);
+                    add_verdict(&mut rule, &Verdict::Accept);
+                    self.batch.add(&rule, nftnl::MsgType::Add);
+                }
+            }
+
+            let mut reject_rule = Rule::new(&self.forward_chain);
+            check_iface_name(&mut reject_rule, Direction::In, interface)?;
+            add_verdict(
+                &mut reject_rule,
+                &Verdict::Reject(RejectionType::Icmp(IcmpCode::PortUnreach)),
+            );
+            self.batch.add(&reject_rule, nftnl::MsgType::Add);
+
+            // Let the forwarded devices obtain addresses from a DHCP server on this host
+            let mut in_rule = Rule::new(&self.in_chain);
+            check_iface_name(&mut in_rule, Direction::In, interface)?;
+            check_port(
+                &mut in_rule,
+                TransportProtocol::Udp,
+                End::Src,
+                super::DHCPV4_CLIENT_PORT,
+            );
+            check_port(
+                &mut in_rule,
+                TransportProtocol::Udp,
+                End::Dst,
+                super::DHCPV4_SERVER_PORT,
+            );
+            add_verdict(&mut in_rule, &Verdict::Accept);
+            self.batch.add(&in_rule, nftnl::MsgType::Add);
+
+            let mut out_rule = Rule::new(&self.out_chain);
+            check_iface_name(&mut out_rule, Direction::Out, interface)?;
+            check_port(
+                &mut out_rule,
+                TransportProtocol::Udp,
+                End::Src,
+                super::DHCPV4_SERVER_PORT,
+            );
+            check_port(
+                &mut out_rule,
+                TransportProtocol::Udp,
+                End::Dst,
+                super::DHCPV4_CLIENT_PORT,
+            );
+            add_verdict(&mut out_rule, &Verdict::Accept);
+            self.batch.add(&out_rule, nftnl::MsgType::Add);
+        }
+
+        Ok(())
+    }
+
     fn add_allow_in_tunnel_endpoint_rules(
         &mut self,
         tunnel_interface: &str,
@@ -929,6 +1459,60 @@ impl<'a> PolicyBatch<'a> {
         Ok(())
     }
 
+    /// Allow all outgoing traffic in the tunnel. If any `inbound_ports` are given, only incoming
+    /// traffic that belongs to connections initiated from this host, or that is sent to one of
+    /// them, is allowed. Otherwise, all incoming traffic in the tunnel is allowed.
+    fn add_allow_connected_tunnel_rules(
+        &mut self,
+        tunnel_interface: &str,
+        inbound_ports: &[InboundPort],
+    ) -> Result<()> {
+        if inbound_ports.is_empty() {
+            return self.add_allow_tunnel_rules(tunnel_interface);
+        }
+
+        self.batch.add(
+            &allow_interface_rule(&self.out_chain, Direction::Out, tunnel_interface)?,
+            nftnl::MsgType::Add,
+        );
+        self.batch.add(
+            &allow_interface_rule(&self.forward_chain, Direction::Out, tunnel_interface)?,
+            nftnl::MsgType::Add,
+        );
+
+        let mut in_rule = Rule::new(&self.in_chain);
+        check_iface(&mut in_rule, Direction::In, tunnel_interface)?;
+        check_ct_established_or_related(&mut in_rule);
+        add_verdict(&mut in_rule, &Verdict::Accept);
+        self.batch.add(&in_rule, nftnl::MsgType::Add);
+
+        for inbound_port in inbound_ports {
+            let mut rule = Rule::new(&self.in_chain);
+            check_iface(&mut rule, Direction::In, tunnel_interface)?;
+            if let Some(source) = inbound_port.source {
+                check_net(&mut rule, End::Src, source);
+            }
+            check_port(
+                &mut rule,
+                inbound_port.protocol,
+                End::Dst,
+                inbound_port.port,
+            );
+            add_verdict(&mut rule, &Verdict::Accept);
+            self.batch.add(&rule, nftnl::MsgType::Add);
+        }
+
+        // Forward packets coming from the tunnel interface only if they are from established
+        // connections.
+        let mut interface_rule = Rule::new(&self.forward_chain);
+        check_iface(&mut interface_rule, Direction::In, tunnel_interface)?;
+        check_ct_established(&mut interface_rule);
+        add_verdict(&mut interface_rule, &Verdict::Accept);
+        self.batch.add(&interface_rule, nftnl::MsgType::Add);
+
+        Ok(())
+    }
+
     /// Adds rules for stopping [CVE-2019-14899](https://seclists.org/oss-sec/2019/q4/122).
     /// An attacker on the same local network as the VPN connected device could figure out
     /// the tunnel IP the device used if the device was set to not filter reverse path (rp_filter.)
@@ -1024,6 +1608,40 @@ fn allow_tunnel_dns_rule<'a>(
     Ok(rule)
 }
 
+/// Return the first tunnel DNS server of each IP version. DNS traffic that is redirected to the
+/// tunnel is sent to these.
+fn dns_redirect_targets(tunnel_servers: &[IpAddr]) -> impl Iterator<Item = &IpAddr> {
+    [
+        tunnel_servers.iter().find(|server| server.is_ipv4()),
+        tunnel_servers.iter().find(|server| server.is_ipv6()),
+    ]
+    .into_iter()
+    .flatten()
+}
+
+/// Change the destination address of packets matching `rule` to `destination`.
+fn add_dnat(rule: &mut Rule<'_>, destination: IpAddr) {
+    let family = match destination {
+        IpAddr::V4(destination) => {
+            rule.add_expr(&nft_expr!(immediate data destination));
+            ProtoFamily::Ipv4
+        }
+        IpAddr::V6(destination) => {
+            rule.add_expr(&nft_expr!(immediate data destination));
+            ProtoFamily::Ipv6
+        }
+    };
+    rule.add_expr(&expr::Nat {
+        nat_type: expr::NatType::DNat,
+        family,
+        ip_register: expr::Register::Reg1,
+        port_register: None,
+    });
+    if *ADD_COUNTERS {
+        rule.add_expr(&nft_expr!(counter));
+    }
+}
+
 fn allow_interface_rule<'a>(
     chain: &'a Chain<'_>,
     direction: Direction,
@@ -1047,6 +1665,18 @@ fn check_iface(rule: &mut Rule<'_>, direction: Direction, iface: &str) -> Result
     Ok(())
 }
 
+/// Match packets by interface name. Unlike [`check_iface`], this does not require the interface
+/// to exist.
+fn check_iface_name(rule: &mut Rule<'_>, direction: Direction, iface: &str) -> Result<()> {
+    let name = CString::new(iface).map_err(|_| Error::InvalidIfaceName(iface.to_owned()))?;
+    rule.add_expr(&match direction {
+        Direction::In => nft_expr!(meta iifname),
+        Direction::Out => nft_expr!(meta oifname),
+    });
+    rule.add_expr(&nft_expr!(cmp == expr::InterfaceName::Exact(name)));
+    Ok(())
+}
+
 fn check_not_iface(rule: &mut Rule<'_>, direction: Direction, iface: &str) -> Result<()> {
     let iface_index = crate::linux::iface_index(iface)
         .map_err(|e| Error::LookupIfaceIndexError(iface.to_owned(), e))?;
@@ -1091,6 +1721,11 @@ fn check_icmpv6(rule: &mut Rule<'_>, r#type: u8, code: u8) {
     rule.add_expr(&nft_expr!(cmp == code));
 }
 
+fn check_exception(rule: &mut Rule<'_>, exception: &FirewallException) {
+    check_net(rule, End::Dst, exception.destination);
+    check_port(rule, exception.protocol, End::Dst, exception.port);
+}
+
 fn check_endpoint(rule: &mut Rule<'_>, end: End, endpoint: &Endpoint) {
     check_ip(rule, end, endpoint.address.ip());
     check_port(rule, endpoint.protocol, end, endpoint.address.port());
@@ -1163,6 +1798,14 @@ fn check_ct_established(rule: &mut Rule<'_>) {
     rule.add_expr(&nft_expr!(cmp != 0u32));
 }
 
+/// Add `ct state { established, related }` requirement to `rule`.
+fn check_ct_established_or_related(rule: &mut Rule<'_>) {
+    let states = (nftnl::expr::ct::States::ESTABLISHED | nftnl::expr::ct::States::RELATED).bits();
+    rule.add_expr(&nft_expr!(ct state));
+    rule.add_expr(&nft_expr!(bitwise mask states, xor 0u32));
+    rule.add_expr(&nft_expr!(cmp != 0u32));
+}
+
 fn add_verdict(rule: &mut Rule<'_>, verdict: &expr::Verdict) {
     if *ADD_COUNTERS {
         rule.add_expr(&nft_expr!(counter));
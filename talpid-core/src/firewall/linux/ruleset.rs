@@ -0,0 +1,256 @@
+//! Introspection of the rules that are currently loaded into netfilter.
+
+use super::{Error, Result};
+use nftnl::{ProtoFamily, nftnl_sys as sys};
+use std::collections::HashSet;
+use std::ffi::{CStr, c_char};
+
+/// Size of the buffer that a single rule is rendered into.
+const RULE_BUFFER_SIZE: usize = 4096;
+
+/// Return a textual representation of every rule in the inet table `table`, in the order that
+/// the rules are evaluated. Rule handles and packet counters are left out, so that the same
+/// rules always render the same way. Returns an empty list if the table does not exist.
+pub fn list_rules(table: &CStr) -> Result<Vec<String>> {
+    dump_rules(ProtoFamily::Inet, Some(table))
+}
+
+/// Return a textual representation of every rule in every table, except for the inet table
+/// `own_table`, that compares or sets any of `marks`. This is used to find other programs that
+/// use the same packet or connection tracking marks.
+pub fn find_rules_using_marks(own_table: &CStr, marks: &[u32]) -> Result<Vec<String>> {
+    let rules = dump_rules(ProtoFamily::Unspec, None)?;
+    Ok(rules_using_marks(rules, own_table, marks))
+}
+
+fn rules_using_marks(rules: Vec<String>, own_table: &CStr, marks: &[u32]) -> Vec<String> {
+    // Rules are rendered as `<family> <table> <chain> [ <expression> ] ...`
+    let own_table = own_table.to_string_lossy();
+    let marks: Vec<_> = marks.iter().map(|mark| format!("0x{mark:08x}")).collect();
+    rules
+        .into_iter()
+        .filter(|rule| {
+            let mut words = rule.split_whitespace();
+            let is_own_rule = words.next() == Some("inet") && words.next() == Some(&own_table);
+            !is_own_rule && uses_marks(rule, &marks)
+        })
+        .collect()
+}
+
+/// Return whether `rule` compares the packet or connection tracking mark with any of `marks`, or
+/// sets either mark to any of them. The marks must be rendered as 32-bit hexadecimal words, the
+/// way immediate and compared values are.
+fn uses_marks(rule: &str, marks: &[String]) -> bool {
+    let is_mark = |value: &str| marks.iter().any(|mark| mark == value);
+    // Registers that hold the packet or connection tracking mark
+    let mut mark_registers = HashSet::new();
+    // Registers that hold any of `marks`
+    let mut value_registers = HashSet::new();
+
+    let expressions = rule
+        .split('[')
+        .skip(1)
+        .filter_map(|expression| expression.split_once(']').map(|(expression, _)| expression));
+    for expression in expressions {
+        let words: Vec<_> = expression.split_whitespace().collect();
+        match words.as_slice() {
+            ["meta" | "ct", "load", "mark", "=>", "reg", reg] => {
+                mark_registers.insert(*reg);
+                value_registers.remove(reg);
+            }
+            // Any other value that is loaded into a register
+            [.., "=>", "reg", reg] => {
+                mark_registers.remove(reg);
+                value_registers.remove(reg);
+            }
+            ["immediate", "reg", reg, value] => {
+                mark_registers.remove(reg);
+                if is_mark(*value) {
+                    value_registers.insert(*reg);
+                } else {
+                    value_registers.remove(reg);
+                }
+            }
+            // E.g. `meta mark & 0xff00 == 0x0f00` or `meta mark set meta mark | 0x0f00`
+            ["bitwise", "reg", dst, "=", "(", "reg", src, .., "^", xor] => {
+                if mark_registers.contains(src) {
+                    mark_registers.insert(*dst);
+                } else {
+                    mark_registers.remove(dst);
+                }
+                if value_registers.contains(src) || is_mark(*xor) {
+                    value_registers.insert(*dst);
+                } else {
+                    value_registers.remove(dst);
+                }
+            }
+            ["cmp", _op, "reg", reg, value] if mark_registers.contains(reg) && is_mark(*value) => {
+                return true;
+            }
+            ["meta" | "ct", "set", "mark", "with", "reg", reg] if value_registers.contains(reg) => {
+                return true;
+            }
+            _ => (),
+        }
+    }
+    false
+}
+
+/// Dump the rules of family `family`, limited to `table` if one is given.
+fn dump_rules(family: ProtoFamily, table: Option<&CStr>) -> Result<Vec<String>> {
+    let socket = mnl::Socket::new(mnl::Bus::Netfilter).map_err(Error::NetlinkOpenError)?;
+    let portid = socket.portid();
+    let seq = 1;
+
+    let request = get_rules_nlmsg(family, table, seq);
+    socket.send(&request).map_err(Error::NetlinkSendError)?;
+
+    let mut rules = vec![];
+    let mut buffer = vec![0; nftnl::nft_nlmsg_maxsize() as usize];
+
+    // A dump may be split across several reads, so keep reading until it is done.
+    loop {
+        for message in socket
+            .recv(&mut buffer[..])
+            .map_err(Error::NetlinkRecvError)?
+        {
+            let message = message.map_err(Error::ProcessNetlinkError)?;
+            match mnl::cb_run2(message, seq, portid, get_rules_cb, &mut rules) {
+                Ok(mnl::CbResult::Stop) => return Ok(rules),
+                Ok(mnl::CbResult::Ok) => (),
+                // The table does not exist
+                Err(error) if error.raw_os_error() == Some(libc::ENOENT) => return Ok(vec![]),
+                Err(error) => return Err(Error::ProcessNetlinkError(error)),
+            }
+        }
+    }
+}
+
+/// Build a request that dumps all rules of family `family`, in `table` if one is given.
+fn get_rules_nlmsg(family: ProtoFamily, table: Option<&CStr>, seq: u32) -> Vec<u8> {
+    let mut buffer = vec![0; nftnl::nft_nlmsg_maxsize() as usize];
+    // SAFETY: `buffer` is large enough to hold any netfilter message, and `rule` is only used
+    // while it is valid.
+    unsafe {
+        let header = sys::nftnl_nlmsg_build_hdr(
+            buffer.as_mut_ptr() as *mut c_char,
+            libc::NFT_MSG_GETRULE as u16,
+            family as u16,
+            libc::NLM_F_DUMP as u16,
+            seq,
+        );
+        let rule = sys::nftnl_rule_alloc();
+        if let Some(table) = table {
+            sys::nftnl_rule_set_str(rule, sys::NFTNL_RULE_TABLE as u16, table.as_ptr());
+        }
+        sys::nftnl_rule_nlmsg_build_payload(header, rule);
+        sys::nftnl_rule_free(rule);
+        buffer.truncate((*header).nlmsg_len as usize);
+    }
+    buffer
+}
+
+fn get_rules_cb(header: &libc::nlmsghdr, rules: &mut Vec<String>) -> libc::c_int {
+    // SAFETY: `header` is a valid netlink message, and `rule` is freed before returning.
+    unsafe {
+        let rule = sys::nftnl_rule_alloc();
+        let err = sys::nftnl_rule_nlmsg_parse(header, rule);
+        if err < 0 {
+            sys::nftnl_rule_free(rule);
+            return err;
+        }
+        // Handles are assigned by the kernel and change whenever the rules are replaced.
+        sys::nftnl_rule_unset(rule, sys::NFTNL_RULE_HANDLE as u16);
+        sys::nftnl_rule_unset(rule, sys::NFTNL_RULE_POSITION as u16);
+
+        let mut buffer = vec![0u8; RULE_BUFFER_SIZE];
+        let len = sys::nftnl_rule_snprintf(
+            buffer.as_mut_ptr() as *mut c_char,
+            buffer.len(),
+            rule,
+            sys::NFTNL_OUTPUT_DEFAULT,
+            0,
+        );
+        sys::nftnl_rule_free(rule);
+        if len < 0 {
+            // MNL_CB_ERROR
+            return -1;
+        }
+        buffer.truncate((len as usize).min(RULE_BUFFER_SIZE - 1));
+        rules.push(strip_counters(&String::from_utf8_lossy(&buffer)));
+    }
+    // MNL_CB_OK
+    1
+}
+
+/// Remove the packet and byte counts from a rendered rule. Counters are only added when
+/// debugging the firewall, and their values change all the time.
+fn strip_counters(rule: &str) -> String {
+    const COUNTER: &str = "[ counter ";
+
+    let mut stripped = String::with_capacity(rule.len());
+    let mut rest = rule;
+    while let Some(start) = rest.find(COUNTER) {
+        stripped.push_str(&rest[..start]);
+        stripped.push_str("[ counter ]");
+        rest = &rest[start..];
+        rest = match rest.find(']') {
+            Some(end) => &rest[end + 1..],
+            None => "",
+        };
+    }
+    stripped.push_str(rest);
+    stripped.trim().to_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{rules_using_marks, strip_counters};
+
+    #[test]
+    fn test_strip_counters() {
+        assert_eq!(
+            strip_counters(
+                "inet mullvad output [ counter pkts 12 bytes 3456 ] [ immediate reg 0 accept ]"
+            ),
+            "inet mullvad output [ counter ] [ immediate reg 0 accept ]"
+        );
+        assert_eq!(
+            strip_counters("inet mullvad input [ immediate reg 0 drop ]\n"),
+            "inet mullvad input [ immediate reg 0 drop ]"
+        );
+    }
+
+    #[test]
+    fn test_rules_using_marks() {
+        let rules = vec![
+            "inet mullvad output [ meta load mark => reg 1 ] [ cmp eq reg 1 0x00000f41 ]"
+                .to_owned(),
+            "ip tailscale output [ immediate reg 1 0x00000f41 ] [ meta set mark with reg 1 ]"
+                .to_owned(),
+            "ip wg-quick-wg0 preraw [ meta load mark => reg 1 ] [ cmp eq reg 1 0x6d6f6c65 ]"
+                .to_owned(),
+            "inet filter output [ ct load mark => reg 1 ] [ bitwise reg 1 = ( reg 1 & 0x0000ffff ) \
+             ^ 0x00000000 ] [ cmp eq reg 1 0x00000f41 ]"
+                .to_owned(),
+            "inet filter output [ meta load mark => reg 1 ] [ bitwise reg 1 = ( reg 1 & 0xfffff0be \
+             ) ^ 0x00000f41 ] [ ct set mark with reg 1 ]"
+                .to_owned(),
+            "inet filter input [ meta load mark => reg 1 ] [ cmp eq reg 1 0x00080000 ]".to_owned(),
+            "inet filter input [ meta load mark => reg 1 ] [ cmp eq reg 1 0x10000f41 ]".to_owned(),
+            // Values that are not compared with or assigned to a mark
+            "inet filter input [ payload load 4b @ network header + 16 => reg 1 ] \
+             [ cmp eq reg 1 0x00000f41 ]"
+                .to_owned(),
+            "inet filter input [ meta load mark => reg 1 ] [ payload load 4b @ network header + \
+             16 => reg 1 ] [ cmp eq reg 1 0x00000f41 ]"
+                .to_owned(),
+            "inet filter input [ immediate reg 1 0x00000f41 ] [ meta set priority with reg 1 ]"
+                .to_owned(),
+        ];
+        assert_eq!(
+            rules_using_marks(rules.clone(), c"mullvad", &[0xf41, 0x6d6f6c65]),
+            rules[1..5]
+        );
+    }
+}
@@ -7,6 +7,8 @@ use std::{
 use talpid_dns::ResolvedDnsConfig;
 use talpid_tunnel::TunnelMetadata;
 use talpid_types::net::{ALLOWED_LAN_NETS, AllowedEndpoint, AllowedTunnelTraffic};
+#[cfg(target_os = "linux")]
+use talpid_types::net::{FirewallException, InboundPort, LanGateway, TrustedInterface};
 
 cfg_if::cfg_if! {
     if #[cfg(target_os = "windows")] {
@@ -32,6 +34,8 @@ cfg_if::cfg_if! {
 use crate::tunnel_state_machine::LinuxNetworkingIdentifiers;
 #[cfg(target_os = "linux")]
 use talpid_cgroup::v2::CGroup2;
+#[cfg(target_os = "linux")]
+use talpid_netns::NetNs;
 
 pub use self::imp::Error;
 
@@ -100,6 +104,15 @@ pub enum FirewallPolicy {
         /// Interface to redirect (VPN tunnel) traffic to
         #[cfg(target_os = "macos")]
         redirect_interface: Option<String>,
+        /// Outbound traffic that is allowed outside the tunnel.
+        #[cfg(target_os = "linux")]
+        exceptions: Vec<FirewallException>,
+        /// Local networks whose traffic is forwarded through the tunnel.
+        #[cfg(target_os = "linux")]
+        lan_gateway: Option<LanGateway>,
+        /// Interfaces of other VPN or mesh clients whose traffic is allowed.
+        #[cfg(target_os = "linux")]
+        trusted_interfaces: Vec<TrustedInterface>,
     },
 
     /// Allow traffic only to server and over tunnel interface
@@ -119,12 +132,36 @@ pub enum FirewallPolicy {
         /// Interface to redirect (VPN tunnel) traffic to
         #[cfg(target_os = "macos")]
         redirect_interface: Option<String>,
+        /// Outbound traffic that is allowed outside the tunnel.
+        #[cfg(target_os = "linux")]
+        exceptions: Vec<FirewallException>,
+        /// Local networks whose traffic is forwarded through the tunnel.
+        #[cfg(target_os = "linux")]
+        lan_gateway: Option<LanGateway>,
+        /// Interfaces of other VPN or mesh clients whose traffic is allowed.
+        #[cfg(target_os = "linux")]
+        trusted_interfaces: Vec<TrustedInterface>,
+        /// Ports on the tunnel interface that accept incoming connections.
+        #[cfg(target_os = "linux")]
+        inbound_ports: Vec<InboundPort>,
+        /// Redirect all DNS traffic from the host to the tunnel DNS servers.
+        #[cfg(target_os = "linux")]
+        redirect_dns: bool,
     },
 
     /// Allow traffic only to server
     Disconnecting {
         /// Flag setting if communication with LAN networks should be possible.
         allow_lan: bool,
+        /// Outbound traffic that is allowed outside the tunnel.
+        #[cfg(target_os = "linux")]
+        exceptions: Vec<FirewallException>,
+        /// Local networks whose traffic is forwarded through the tunnel.
+        #[cfg(target_os = "linux")]
+        lan_gateway: Option<LanGateway>,
+        /// Interfaces of other VPN or mesh clients whose traffic is allowed.
+        #[cfg(target_os = "linux")]
+        trusted_interfaces: Vec<TrustedInterface>,
     },
 
     /// Block all network traffic in and out from the computer.
@@ -133,6 +170,15 @@ pub enum FirewallPolicy {
         allow_lan: bool,
         /// Host that should be reachable while in the blocked state.
         allowed_endpoint: Option<AllowedEndpoint>,
+        /// Outbound traffic that is allowed outside the tunnel.
+        #[cfg(target_os = "linux")]
+        exceptions: Vec<FirewallException>,
+        /// Local networks whose traffic is forwarded through the tunnel.
+        #[cfg(target_os = "linux")]
+        lan_gateway: Option<LanGateway>,
+        /// Interfaces of other VPN or mesh clients whose traffic is allowed.
+        #[cfg(target_os = "linux")]
+        trusted_interfaces: Vec<TrustedInterface>,
     },
 }
 
@@ -190,7 +236,48 @@ impl FirewallPolicy {
             FirewallPolicy::Connecting { allow_lan, .. }
             | FirewallPolicy::Connected { allow_lan, .. }
             | FirewallPolicy::Blocked { allow_lan, .. }
-            | FirewallPolicy::Disconnecting { allow_lan } => *allow_lan,
+            | FirewallPolicy::Disconnecting { allow_lan, .. } => *allow_lan,
+        }
+    }
+
+    /// Return the outbound traffic that is allowed outside the tunnel
+    #[cfg(target_os = "linux")]
+    pub fn exceptions(&self) -> &[FirewallException] {
+        match self {
+            FirewallPolicy::Connecting { exceptions, .. }
+            | FirewallPolicy::Connected { exceptions, .. }
+            | FirewallPolicy::Blocked { exceptions, .. }
+            | FirewallPolicy::Disconnecting { exceptions, .. } => exceptions,
+        }
+    }
+
+    /// Return the local networks whose traffic is forwarded through the tunnel
+    #[cfg(target_os = "linux")]
+    pub fn lan_gateway(&self) -> Option<&LanGateway> {
+        match self {
+            FirewallPolicy::Connecting { lan_gateway, .. }
+            | FirewallPolicy::Connected { lan_gateway, .. }
+            | FirewallPolicy::Blocked { lan_gateway, .. }
+            | FirewallPolicy::Disconnecting { lan_gateway, .. } => lan_gateway.as_ref(),
+        }
+    }
+
+    /// Return the interfaces of other VPN or mesh clients whose traffic is allowed
+    #[cfg(target_os = "linux")]
+    pub fn trusted_interfaces(&self) -> &[TrustedInterface] {
+        match self {
+            FirewallPolicy::Connecting {
+                trusted_interfaces, ..
+            }
+            | FirewallPolicy::Connected {
+                trusted_interfaces, ..
+            }
+            | FirewallPolicy::Blocked {
+                trusted_interfaces, ..
+            }
+            | FirewallPolicy::Disconnecting {
+                trusted_interfaces, ..
+            } => trusted_interfaces,
         }
     }
 
@@ -313,6 +400,37 @@ impl fmt::Display for FirewallPolicy {
     }
 }
 
+/// The firewall rules that the last applied [`FirewallPolicy`] resulted in, and the rules that
+/// are currently active.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FirewallRules {
+    /// Description of the last applied policy, if it is still in effect.
+    pub policy: Option<String>,
+    /// Rules that were active right after the policy was applied.
+    pub expected: Vec<String>,
+    /// Rules that are currently active.
+    pub actual: Vec<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl FirewallRules {
+    /// Returns true if the active rules differ from the expected rules.
+    pub fn has_drifted(&self) -> bool {
+        self.expected != self.actual
+    }
+}
+
+/// The active firewall rules no longer match the last applied [`FirewallPolicy`].
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirewallDrift {
+    /// The expected and the active rules at the time the drift was detected.
+    pub rules: FirewallRules,
+    /// Whether the policy was applied again.
+    pub reapplied: bool,
+}
+
 /// Manages network security of the computer/device. Can apply and enforce firewall policies
 /// by manipulating the OS firewall and DNS settings.
 pub struct Firewall {
@@ -357,10 +475,11 @@ impl Firewall {
     #[cfg(target_os = "linux")]
     pub fn new(
         fwmark: u32,
+        split_tunnel_mark: u32,
         excluded_cgroup: Option<CGroup2>,
         net_cls: Option<u32>,
     ) -> Result<Self, Error> {
-        let inner = imp::Firewall::new(fwmark, excluded_cgroup, net_cls)?;
+        let inner = imp::Firewall::new(fwmark, split_tunnel_mark, excluded_cgroup, net_cls)?;
         Ok(Firewall { inner })
     }
 
@@ -371,6 +490,22 @@ impl Firewall {
         self.inner.apply_policy(policy)
     }
 
+    /// Applies the firewall rules of the tunnel network namespace `netns`. Only loopback traffic
+    /// and traffic on `tunnel_interface`, if given, is allowed inside the namespace.
+    #[cfg(target_os = "linux")]
+    pub fn apply_namespace_policy(
+        &mut self,
+        netns: &NetNs,
+        tunnel_interface: Option<&str>,
+    ) -> Result<(), Error> {
+        log::info!(
+            "Applying firewall policy for network namespace {}: tunnel interface {:?}",
+            netns.name(),
+            tunnel_interface
+        );
+        self.inner.apply_namespace_policy(netns, tunnel_interface)
+    }
+
     /// Resets/removes any currently enforced `FirewallPolicy`. Returns the system to the same state
     /// it had before any policy was applied through this `Firewall` instance.
     pub fn reset_policy(&mut self) -> Result<(), Error> {
@@ -378,6 +513,35 @@ impl Firewall {
         self.inner.reset_policy()
     }
 
+    /// Removes any currently enforced `FirewallPolicy`, except that traffic forwarded from the
+    /// local networks in `lan_gateway` is blocked. Used instead of `reset_policy` when the host
+    /// itself should not be firewalled.
+    #[cfg(target_os = "linux")]
+    pub fn block_lan_gateway(&mut self, lan_gateway: &LanGateway) -> Result<(), Error> {
+        log::info!("Blocking traffic forwarded from LAN gateway: {lan_gateway}");
+        self.inner.block_lan_gateway(lan_gateway)
+    }
+
+    /// Returns the rules of the last applied policy along with the rules that are currently
+    /// active.
+    #[cfg(target_os = "linux")]
+    pub fn rules(&self) -> Result<FirewallRules, Error> {
+        self.inner.rules()
+    }
+
+    /// Returns the rules of other programs that use the same firewall marks as this firewall.
+    #[cfg(target_os = "linux")]
+    pub fn find_conflicting_rules(&self) -> Result<Vec<String>, Error> {
+        self.inner.find_conflicting_rules()
+    }
+
+    /// Checks that the active rules still match the last applied policy. Returns the difference
+    /// if they do not, after applying the policy again if `reapply` is set.
+    #[cfg(target_os = "linux")]
+    pub fn verify_policy(&mut self, reapply: bool) -> Result<Option<FirewallDrift>, Error> {
+        self.inner.verify_policy(reapply)
+    }
+
     /// Sets whether the firewall should persist the blocking rules across a reboot.
     #[cfg(target_os = "windows")]
     pub fn persist(&mut self, persist: bool) {
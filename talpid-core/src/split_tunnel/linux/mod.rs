@@ -17,9 +17,9 @@ use talpid_cgroup::{
 #[cfg(feature = "cgroup2")]
 use crate::firewall;
 
-/// Value used to mark packets and associated connections.
+/// Default value used to mark packets and associated connections.
 /// This should be an arbitrary but unique integer.
-pub const MARK: u32 = 0xf41;
+pub const DEFAULT_MARK: u32 = 0xf41;
 
 /// Errors related to split tunneling.
 #[derive(thiserror::Error, Debug)]
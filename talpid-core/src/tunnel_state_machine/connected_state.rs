@@ -1,10 +1,12 @@
-use futures::StreamExt;
 use futures::channel::{mpsc, oneshot};
 use futures::stream::Fuse;
+use futures::{FutureExt, StreamExt};
 
 use talpid_tunnel::{TunnelEvent, TunnelMetadata};
-use talpid_types::net::{AllowedClients, AllowedEndpoint, wireguard::TunnelParameters};
-use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+use talpid_types::net::{
+    AllowedClients, AllowedEndpoint, TunnelEndpoint, wireguard::TunnelParameters,
+};
+use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError, ParameterGenerationError};
 use talpid_types::{BoxedError, ErrorExt};
 
 use crate::firewall::FirewallPolicy;
@@ -22,6 +24,10 @@ use super::{
 pub(crate) type TunnelEventsReceiver =
     Fuse<mpsc::UnboundedReceiver<(TunnelEvent, oneshot::Sender<()>)>>;
 
+type CandidateResult =
+    Result<Result<TunnelParameters, ParameterGenerationError>, oneshot::Canceled>;
+type PeerSwitchResult = Result<Result<(), talpid_wireguard::Error>, oneshot::Canceled>;
+
 /// The tunnel is up and working.
 pub struct ConnectedState {
     metadata: TunnelMetadata,
@@ -29,6 +35,27 @@ pub struct ConnectedState {
     tunnel_parameters: TunnelParameters,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    peer_switch: Option<PendingPeerSwitch>,
+}
+
+/// A switch to a new relay that has not completed yet.
+enum PendingPeerSwitch {
+    /// Waiting for the parameters of the new relay.
+    Generating {
+        result_rx: oneshot::Receiver<Result<TunnelParameters, ParameterGenerationError>>,
+    },
+    /// Waiting for a handshake with the new relay.
+    Switching {
+        tunnel_parameters: TunnelParameters,
+        result_rx: oneshot::Receiver<Result<(), talpid_wireguard::Error>>,
+    },
+}
+
+/// Events that only the connected state listens for.
+enum ConnectedEventResult {
+    Shared(EventResult),
+    Candidate(CandidateResult),
+    PeerSwitch(PeerSwitchResult),
 }
 
 impl ConnectedState {
@@ -46,13 +73,9 @@ impl ConnectedState {
             tunnel_parameters,
             tunnel_close_event,
             tunnel_close_tx,
+            peer_switch: None,
         };
-
-        let tunnel_interface = Some(connected_state.metadata.interface.clone());
-        let tunnel_endpoint = talpid_types::net::TunnelEndpoint {
-            tunnel_interface,
-            ..connected_state.tunnel_parameters.get_tunnel_endpoint()
-        };
+        let tunnel_endpoint = connected_state.tunnel_endpoint();
 
         if let Err(error) = connected_state.set_firewall_policy(shared_values) {
             DisconnectingState::enter(
@@ -77,14 +100,29 @@ impl ConnectedState {
         }
     }
 
+    fn tunnel_endpoint(&self) -> TunnelEndpoint {
+        let tunnel_interface = Some(self.metadata.interface.clone());
+        let mut tunnel_endpoint = TunnelEndpoint {
+            tunnel_interface,
+            daita_parameters: self.metadata.daita,
+            ..self.tunnel_parameters.get_tunnel_endpoint()
+        };
+        if let (Some(obfuscation), Some(selected)) = (
+            tunnel_endpoint.obfuscation.as_mut(),
+            self.metadata.selected_obfuscation,
+        ) {
+            obfuscation.set_selected(selected);
+        }
+        tunnel_endpoint
+    }
+
     fn set_firewall_policy(
         &self,
         shared_values: &mut SharedTunnelStateValues,
     ) -> Result<(), FirewallPolicyError> {
         let policy = self.get_firewall_policy(shared_values);
         shared_values
-            .firewall
-            .apply_policy(policy)
+            .apply_tunnel_firewall_policy(policy)
             .map_err(|error| {
                 log::error!(
                     "{}",
@@ -103,7 +141,14 @@ impl ConnectedState {
     }
 
     fn get_firewall_policy(&self, shared_values: &SharedTunnelStateValues) -> FirewallPolicy {
-        let endpoints = self.tunnel_parameters.get_next_hop_endpoints();
+        let mut endpoints = self.tunnel_parameters.get_next_hop_endpoints();
+        // Both relays must be reachable while switching between them
+        if let Some(PendingPeerSwitch::Switching {
+            tunnel_parameters, ..
+        }) = &self.peer_switch
+        {
+            endpoints.extend(tunnel_parameters.get_next_hop_endpoints());
+        }
 
         #[cfg(target_os = "windows")]
         let clients = AllowedClients::from(vec![std::env::current_exe().unwrap()]);
@@ -140,6 +185,16 @@ impl ConnectedState {
             dns_config: Self::resolve_dns(&self.metadata, shared_values),
             #[cfg(target_os = "macos")]
             redirect_interface,
+            #[cfg(target_os = "linux")]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(target_os = "linux")]
+            lan_gateway: shared_values.lan_gateway.clone(),
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: shared_values.trusted_interfaces.clone(),
+            #[cfg(target_os = "linux")]
+            inbound_ports: shared_values.inbound_ports.clone(),
+            #[cfg(target_os = "linux")]
+            redirect_dns: shared_values.dns_monitor.uses_firewall_redirect(),
         }
     }
 
@@ -157,6 +212,19 @@ impl ConnectedState {
     fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
         let dns_config: ResolvedDnsConfig = Self::resolve_dns(&self.metadata, shared_values);
 
+        // When the tunnel is confined to a namespace, only the namespace uses the tunnel DNS.
+        // Its firewall only allows traffic through the tunnel.
+        #[cfg(target_os = "linux")]
+        if let Some(netns) = &shared_values.tunnel_namespace {
+            netns
+                .set_dns_servers(dns_config.addresses())
+                .map_err(BoxedError::new)?;
+            return shared_values
+                .firewall
+                .apply_namespace_policy(netns, Some(&self.metadata.interface))
+                .map_err(BoxedError::new);
+        }
+
         #[cfg(not(target_os = "macos"))]
         shared_values
             .dns_monitor
@@ -195,6 +263,17 @@ impl ConnectedState {
     }
 
     fn reset_dns(shared_values: &mut SharedTunnelStateValues) {
+        #[cfg(target_os = "linux")]
+        if let Some(netns) = &shared_values.tunnel_namespace {
+            if let Err(error) = shared_values.firewall.apply_namespace_policy(netns, None) {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Unable to block tunnel namespace")
+                );
+            }
+            return;
+        }
+
         #[cfg(not(target_os = "macos"))]
         if let Err(error) = shared_values.dns_monitor.reset_before_interface_removal() {
             log::error!("{}", error.display_chain_with_msg("Unable to reset DNS"));
@@ -327,7 +406,156 @@ impl ConnectedState {
             #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::LockdownMode(lockdown_mode, complete_tx)) => {
                 shared_values.lockdown_mode = lockdown_mode;
+                // Lockdown mode decides whether the host is firewalled when the tunnel is
+                // confined to a namespace
+                #[cfg(target_os = "linux")]
+                if shared_values.tunnel_namespace.is_some()
+                    && let Err(error) = self.set_firewall_policy(shared_values)
+                {
+                    let _ = complete_tx.send(());
+                    return self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    );
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TunnelNamespace(enabled, complete_tx)) => {
+                let consequence = if shared_values.set_tunnel_namespace(enabled) {
+                    self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::GetFirewallRules(tx)) => {
+                let _ = tx.send(shared_values.firewall.rules());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::VerifyFirewall(tx)) => {
+                let _ = tx.send(shared_values.verify_firewall());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::ReapplyFirewallOnDrift(enabled, complete_tx)) => {
+                shared_values.set_reapply_firewall_on_drift(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let consequence = if shared_values.set_firewall_exceptions(exceptions) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LanGateway(lan_gateway, complete_tx)) => {
+                let consequence = if shared_values.set_lan_gateway(lan_gateway) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TrustedInterfaces(trusted_interfaces, complete_tx)) => {
+                let consequence = if shared_values.set_trusted_interfaces(trusted_interfaces) {
+                    if let Err(error) = self.set_firewall_policy(shared_values) {
+                        let _ = complete_tx.send(());
+                        return self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        );
+                    }
+
+                    // Apply the DNS forwarding to the resolvers of the trusted interfaces
+                    match self.set_dns(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => {
+                            log::error!("{}", error.display_chain_with_msg("Failed to set DNS"));
+                            self.disconnect(
+                                shared_values,
+                                AfterDisconnect::Block(ErrorStateCause::SetDnsError),
+                            )
+                        }
+                    }
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::InboundPorts(inbound_ports, complete_tx)) => {
+                let consequence = if shared_values.set_inbound_ports(inbound_ports) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::DnsBackend(dns_backend, complete_tx)) => {
+                let consequence = if shared_values.set_dns_backend(dns_backend) {
+                    if let Err(error) = self.set_firewall_policy(shared_values) {
+                        let _ = complete_tx.send(());
+                        return self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        );
+                    }
+
+                    match self.set_dns(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => {
+                            log::error!("{}", error.display_chain_with_msg("Failed to set DNS"));
+                            self.disconnect(
+                                shared_values,
+                                AfterDisconnect::Block(ErrorStateCause::SetDnsError),
+                            )
+                        }
+                    }
+                } else {
+                    SameState(self)
+                };
                 let _ = complete_tx.send(());
+                consequence
+            }
+            Some(TunnelCommand::GetTunnelStats(tx)) => {
+                shared_values.send_tunnel_stats(tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetLastRekey(tx)) => {
+                let _ = tx.send(shared_values.last_rekey());
                 SameState(self)
             }
             Some(TunnelCommand::Connectivity(connectivity)) => {
@@ -341,9 +569,7 @@ impl ConnectedState {
                     SameState(self)
                 }
             }
-            Some(TunnelCommand::Connect) => {
-                self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
-            }
+            Some(TunnelCommand::Connect) => self.switch_peer_or_reconnect(shared_values),
             Some(TunnelCommand::Disconnect) | None => {
                 self.disconnect(shared_values, AfterDisconnect::Nothing)
             }
@@ -398,6 +624,138 @@ impl ConnectedState {
         }
     }
 
+    /// Move the tunnel to a new relay without tearing it down, if nothing but the peer changes.
+    /// Otherwise, reconnect. The new relay is selected in the background, and handled by
+    /// [`Self::handle_candidate_result`].
+    fn switch_peer_or_reconnect(
+        mut self: Box<Self>,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        if self.peer_switch.is_some() {
+            return self.disconnect(shared_values, AfterDisconnect::Reconnect(0));
+        }
+        if self.tunnel_parameters.options.quantum_resistant {
+            log::info!(
+                "Reconnecting, since relays cannot be switched without reconnecting while quantum \
+                 resistance is enabled"
+            );
+            return self.disconnect(shared_values, AfterDisconnect::Reconnect(0));
+        }
+        let supports_peer_switch = shared_values
+            .tunnel_stats
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|handle| handle.supports_peer_switch());
+        let ip_availability = shared_values.connectivity.availability();
+        let Some(ip_availability) = ip_availability.filter(|_| supports_peer_switch) else {
+            return self.disconnect(shared_values, AfterDisconnect::Reconnect(0));
+        };
+
+        // Relay selection may take a while, so other events are handled in the meantime
+        let (result_tx, result_rx) = oneshot::channel();
+        let generate = shared_values
+            .tunnel_parameters_generator
+            .generate_candidate(ip_availability);
+        shared_values.runtime.spawn(async move {
+            let _ = result_tx.send(generate.await);
+        });
+        self.peer_switch = Some(PendingPeerSwitch::Generating { result_rx });
+
+        EventConsequence::SameState(self)
+    }
+
+    /// Switch to the relay in the generated parameters if only the peer changes. Otherwise,
+    /// reconnect.
+    fn handle_candidate_result(
+        mut self: Box<Self>,
+        result: CandidateResult,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        self.peer_switch = None;
+
+        let tunnel_parameters = match result {
+            Ok(Ok(tunnel_parameters)) => tunnel_parameters,
+            // The connecting state reports the error
+            Ok(Err(_)) | Err(_) => {
+                return self.disconnect(shared_values, AfterDisconnect::Reconnect(0));
+            }
+        };
+        let stats_handle = shared_values.tunnel_stats.lock().unwrap().clone();
+        let Some(stats_handle) = stats_handle
+            .filter(|handle| handle.can_switch_peer(&self.tunnel_parameters, &tunnel_parameters))
+        else {
+            return self.disconnect(shared_values, AfterDisconnect::Reconnect(0));
+        };
+
+        let (result_tx, result_rx) = oneshot::channel();
+        let current_parameters = self.tunnel_parameters.clone();
+        let new_parameters = tunnel_parameters.clone();
+        self.peer_switch = Some(PendingPeerSwitch::Switching {
+            tunnel_parameters,
+            result_rx,
+        });
+
+        if let Err(error) = self.set_firewall_policy(shared_values) {
+            return self.disconnect(
+                shared_values,
+                AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+            );
+        }
+
+        shared_values.runtime.spawn(async move {
+            let result = stats_handle
+                .switch_peer(&current_parameters, &new_parameters)
+                .await;
+            let _ = result_tx.send(result);
+        });
+
+        EventConsequence::SameState(self)
+    }
+
+    fn handle_peer_switch_result(
+        mut self: Box<Self>,
+        result: PeerSwitchResult,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        use self::EventConsequence::*;
+
+        let Some(PendingPeerSwitch::Switching {
+            tunnel_parameters, ..
+        }) = self.peer_switch.take()
+        else {
+            return SameState(self);
+        };
+
+        match result {
+            Ok(Ok(())) => {
+                self.tunnel_parameters = tunnel_parameters;
+                shared_values
+                    .runtime
+                    .block_on(shared_values.tunnel_parameters_generator.use_candidate());
+                if let Err(error) = self.set_firewall_policy(shared_values) {
+                    return self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    );
+                }
+                let tunnel_endpoint = self.tunnel_endpoint();
+                NewState((self, TunnelStateTransition::Connected(tunnel_endpoint)))
+            }
+            Ok(Err(error)) => {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to switch relay. Reconnecting")
+                );
+                self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+            }
+            Err(_) => {
+                log::warn!("Relay switch was aborted. Reconnecting");
+                self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+            }
+        }
+    }
+
     fn handle_tunnel_events(
         self: Box<Self>,
         event: Option<(TunnelEvent, oneshot::Sender<()>)>,
@@ -441,23 +799,53 @@ impl TunnelState for ConnectedState {
         shared_values: &mut SharedTunnelStateValues,
     ) -> EventConsequence {
         let result = runtime.block_on(async {
+            let ConnectedState {
+                tunnel_events,
+                tunnel_close_event,
+                peer_switch,
+                ..
+            } = &mut *self;
+            let peer_switch_result = async {
+                match peer_switch {
+                    Some(PendingPeerSwitch::Generating { result_rx }) => {
+                        ConnectedEventResult::Candidate(result_rx.await)
+                    }
+                    Some(PendingPeerSwitch::Switching { result_rx, .. }) => {
+                        ConnectedEventResult::PeerSwitch(result_rx.await)
+                    }
+                    None => futures::future::pending().await,
+                }
+            };
+
+            use ConnectedEventResult::Shared;
             futures::select! {
-                command = commands.next() => EventResult::Command(command),
-                event = self.tunnel_events.next() => EventResult::Event(event),
-                result = &mut self.tunnel_close_event => EventResult::Close(result),
+                command = commands.next() => Shared(EventResult::Command(command)),
+                event = tunnel_events.next() => Shared(EventResult::Event(event)),
+                result = tunnel_close_event => Shared(EventResult::Close(result)),
+                result = peer_switch_result.fuse() => result,
             }
         });
 
         match result {
-            EventResult::Command(command) => self.handle_commands(command, shared_values),
-            EventResult::Event(event) => self.handle_tunnel_events(event, shared_values),
-            EventResult::Close(result) => {
+            ConnectedEventResult::Shared(EventResult::Command(command)) => {
+                self.handle_commands(command, shared_values)
+            }
+            ConnectedEventResult::Shared(EventResult::Event(event)) => {
+                self.handle_tunnel_events(event, shared_values)
+            }
+            ConnectedEventResult::Shared(EventResult::Close(result)) => {
                 if result.is_err() {
                     log::warn!("Tunnel monitor thread has stopped unexpectedly");
                 }
                 let block_reason = result.unwrap_or(None);
                 self.handle_tunnel_close_event(block_reason, shared_values)
             }
+            ConnectedEventResult::Candidate(result) => {
+                self.handle_candidate_result(result, shared_values)
+            }
+            ConnectedEventResult::PeerSwitch(result) => {
+                self.handle_peer_switch_result(result, shared_values)
+            }
         }
     }
 }
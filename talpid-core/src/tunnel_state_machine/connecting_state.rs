@@ -6,6 +6,8 @@ use std::time::{Duration, Instant};
 use futures::channel::{mpsc, oneshot};
 use futures::future::Fuse;
 use futures::{FutureExt, StreamExt};
+#[cfg(target_os = "linux")]
+use talpid_netns::NetNs;
 use talpid_routing::RouteManagerHandle;
 use talpid_tunnel::tun_provider::TunProvider;
 use talpid_tunnel::{EventHook, TunnelArgs, TunnelEvent, TunnelMetadata};
@@ -14,6 +16,7 @@ use talpid_types::net::{
     AllowedClients, AllowedEndpoint, AllowedTunnelTraffic, wireguard::TunnelParameters,
 };
 use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+use talpid_wireguard::TunnelStatsHandle;
 
 use super::connected_state::TunnelEventsReceiver;
 use super::{
@@ -75,6 +78,17 @@ impl ConnectingState {
                 });
         }
 
+        #[cfg(target_os = "linux")]
+        if shared_values.identifiers_in_use() {
+            return ErrorState::enter(shared_values, ErrorStateCause::NetworkIdentifiersInUse);
+        }
+
+        #[cfg(target_os = "linux")]
+        if let Err(error) = shared_values.ensure_tunnel_namespace() {
+            log::error!("{}", error.display_chain());
+            return ErrorState::enter(shared_values, ErrorStateCause::TunnelNamespaceError);
+        }
+
         let ip_availability = match shared_values.connectivity.availability() {
             Some(ip_availability) => ip_availability,
             // If we're offline, enter the offline state
@@ -146,8 +160,11 @@ impl ConnectingState {
                         &shared_values.log_dir,
                         &shared_values.resource_dir,
                         shared_values.tun_provider.clone(),
+                        shared_values.tunnel_stats.clone(),
                         &shared_values.route_manager,
                         retry_attempt,
+                        #[cfg(target_os = "linux")]
+                        shared_values.tunnel_namespace.clone(),
                     );
 
                     let params = connecting_state.tunnel_parameters.clone();
@@ -203,10 +220,15 @@ impl ConnectingState {
             allowed_tunnel_traffic,
             #[cfg(target_os = "macos")]
             redirect_interface,
+            #[cfg(target_os = "linux")]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(target_os = "linux")]
+            lan_gateway: shared_values.lan_gateway.clone(),
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: shared_values.trusted_interfaces.clone(),
         };
         shared_values
-            .firewall
-            .apply_policy(policy)
+            .apply_tunnel_firewall_policy(policy)
             .map_err(|error| {
                 log::error!(
                     "{}",
@@ -222,14 +244,17 @@ impl ConnectingState {
             })
     }
 
+    #[expect(clippy::too_many_arguments)]
     fn start_tunnel(
         runtime: tokio::runtime::Handle,
         parameters: TunnelParameters,
         log_dir: &Option<PathBuf>,
         resource_dir: &Path,
         tun_provider: Arc<Mutex<TunProvider>>,
+        tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
         route_manager: &RouteManagerHandle,
         retry_attempt: u32,
+        #[cfg(target_os = "linux")] netns: Option<Arc<NetNs>>,
     ) -> Self {
         let (event_tx, event_rx) = mpsc::unbounded();
         let event_hook = EventHook::new(event_tx);
@@ -257,6 +282,8 @@ impl ConnectingState {
                 tun_provider,
                 retry_attempt,
                 route_manager,
+                #[cfg(target_os = "linux")]
+                netns,
             };
 
             #[cfg(target_os = "windows")]
@@ -274,7 +301,9 @@ impl ConnectingState {
 
             let block_reason = match TunnelMonitor::start(&tunnel_parameters, &log_dir, args) {
                 Ok(monitor) => {
+                    *tunnel_stats.lock().unwrap() = Some(monitor.stats_handle());
                     let reason = Self::wait_for_tunnel_monitor(monitor, retry_attempt);
+                    *tunnel_stats.lock().unwrap() = None;
                     log::debug!("Tunnel monitor exited with block reason: {:?}", reason);
                     reason
                 }
@@ -469,9 +498,104 @@ impl ConnectingState {
             #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::LockdownMode(lockdown_mode, complete_tx)) => {
                 shared_values.lockdown_mode = lockdown_mode;
+                // Lockdown mode decides whether the host is firewalled when the tunnel is
+                // confined to a namespace
+                #[cfg(target_os = "linux")]
+                if shared_values.tunnel_namespace.is_some()
+                    && let Err(error) = Self::set_firewall_policy(
+                        shared_values,
+                        &self.tunnel_parameters,
+                        &self.tunnel_metadata,
+                        self.allowed_tunnel_traffic.clone(),
+                    )
+                {
+                    let _ = complete_tx.send(());
+                    return self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    );
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TunnelNamespace(enabled, complete_tx)) => {
+                let consequence = if shared_values.set_tunnel_namespace(enabled) {
+                    self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::GetFirewallRules(tx)) => {
+                let _ = tx.send(shared_values.firewall.rules());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::VerifyFirewall(tx)) => {
+                let _ = tx.send(shared_values.verify_firewall());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::ReapplyFirewallOnDrift(enabled, complete_tx)) => {
+                shared_values.set_reapply_firewall_on_drift(enabled);
                 let _ = complete_tx.send(());
                 SameState(self)
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let consequence = if shared_values.set_firewall_exceptions(exceptions) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LanGateway(lan_gateway, complete_tx)) => {
+                let consequence = if shared_values.set_lan_gateway(lan_gateway) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TrustedInterfaces(trusted_interfaces, complete_tx)) => {
+                let consequence = if shared_values.set_trusted_interfaces(trusted_interfaces) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::InboundPorts(inbound_ports, complete_tx)) => {
+                // Inbound ports are only applied in the connected state
+                let _ = shared_values.set_inbound_ports(inbound_ports);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::DnsBackend(dns_backend, complete_tx)) => {
+                // The DNS backend is only used in the connected state
+                let _ = shared_values.set_dns_backend(dns_backend);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelStats(tx)) => {
+                shared_values.send_tunnel_stats(tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetLastRekey(tx)) => {
+                let _ = tx.send(shared_values.last_rekey());
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 if connectivity.is_offline() {
@@ -86,6 +86,12 @@ impl DisconnectedState {
             let policy = FirewallPolicy::Blocked {
                 allow_lan: shared_values.allow_lan,
                 allowed_endpoint: Some(shared_values.allowed_endpoint.clone()),
+                #[cfg(target_os = "linux")]
+                exceptions: shared_values.firewall_exceptions.clone(),
+                #[cfg(target_os = "linux")]
+                lan_gateway: shared_values.lan_gateway.clone(),
+                #[cfg(target_os = "linux")]
+                trusted_interfaces: shared_values.trusted_interfaces.clone(),
             };
 
             shared_values.firewall.apply_policy(policy).map_err(|e| {
@@ -95,8 +101,7 @@ impl DisconnectedState {
             })
         } else if should_reset_firewall {
             shared_values
-                .firewall
-                .reset_policy()
+                .reset_firewall_policy()
                 .map_err(|e| e.display_chain_with_msg("Failed to reset firewall policy"))
         } else {
             Ok(())
@@ -227,6 +232,74 @@ impl TunnelState for DisconnectedState {
                     SameState(self)
                 }
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TunnelNamespace(enabled, complete_tx)) => {
+                let _ = shared_values.set_tunnel_namespace(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::GetFirewallRules(tx)) => {
+                let _ = tx.send(shared_values.firewall.rules());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::VerifyFirewall(tx)) => {
+                let _ = tx.send(shared_values.verify_firewall());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::ReapplyFirewallOnDrift(enabled, complete_tx)) => {
+                shared_values.set_reapply_firewall_on_drift(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                if shared_values.set_firewall_exceptions(exceptions) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::InboundPorts(inbound_ports, complete_tx)) => {
+                // Inbound ports are only applied in the connected state
+                let _ = shared_values.set_inbound_ports(inbound_ports);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::DnsBackend(dns_backend, complete_tx)) => {
+                // The DNS backend is only used in the connected state
+                let _ = shared_values.set_dns_backend(dns_backend);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LanGateway(lan_gateway, complete_tx)) => {
+                if shared_values.set_lan_gateway(lan_gateway) {
+                    Self::set_firewall_policy(shared_values, true);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TrustedInterfaces(trusted_interfaces, complete_tx)) => {
+                if shared_values.set_trusted_interfaces(trusted_interfaces) {
+                    Self::set_firewall_policy(shared_values, true);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelStats(tx)) => {
+                shared_values.send_tunnel_stats(tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetLastRekey(tx)) => {
+                let _ = tx.send(shared_values.last_rekey());
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 SameState(self)
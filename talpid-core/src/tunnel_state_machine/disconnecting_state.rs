@@ -40,11 +40,15 @@ impl DisconnectingState {
     }
 
     fn set_firewall_policy(shared_values: &mut SharedTunnelStateValues) {
-        let result = shared_values
-            .firewall
-            .apply_policy(FirewallPolicy::Disconnecting {
-                allow_lan: shared_values.allow_lan,
-            });
+        let result = shared_values.apply_tunnel_firewall_policy(FirewallPolicy::Disconnecting {
+            allow_lan: shared_values.allow_lan,
+            #[cfg(target_os = "linux")]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(target_os = "linux")]
+            lan_gateway: shared_values.lan_gateway.clone(),
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: shared_values.trusted_interfaces.clone(),
+        });
 
         if let Err(err) = result {
             log::error!("{err}")
@@ -75,6 +79,57 @@ impl DisconnectingState {
                 shared_values.lockdown_mode = lockdown_mode;
                 let _ = complete_tx.send(());
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TunnelNamespace(enabled, complete_tx)) => {
+                let _ = shared_values.set_tunnel_namespace(enabled);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::GetFirewallRules(tx)) => {
+                let _ = tx.send(shared_values.firewall.rules());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::VerifyFirewall(tx)) => {
+                let _ = tx.send(shared_values.verify_firewall());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::ReapplyFirewallOnDrift(enabled, complete_tx)) => {
+                shared_values.set_reapply_firewall_on_drift(enabled);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let _ = shared_values.set_firewall_exceptions(exceptions);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LanGateway(lan_gateway, complete_tx)) => {
+                let _ = shared_values.set_lan_gateway(lan_gateway);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TrustedInterfaces(trusted_interfaces, complete_tx)) => {
+                let _ = shared_values.set_trusted_interfaces(trusted_interfaces);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::InboundPorts(inbound_ports, complete_tx)) => {
+                // Inbound ports are only applied in the connected state
+                let _ = shared_values.set_inbound_ports(inbound_ports);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::DnsBackend(dns_backend, complete_tx)) => {
+                // The DNS backend is only used in the connected state
+                let _ = shared_values.set_dns_backend(dns_backend);
+                let _ = complete_tx.send(());
+            }
+            Some(TunnelCommand::GetTunnelStats(tx)) => {
+                shared_values.send_tunnel_stats(tx);
+            }
+            Some(TunnelCommand::GetLastRekey(tx)) => {
+                let _ = tx.send(shared_values.last_rekey());
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
 
@@ -80,14 +80,19 @@ impl ErrorState {
         let policy = FirewallPolicy::Blocked {
             allow_lan: shared_values.allow_lan,
             allowed_endpoint: Some(shared_values.allowed_endpoint.clone()),
+            #[cfg(target_os = "linux")]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(target_os = "linux")]
+            lan_gateway: shared_values.lan_gateway.clone(),
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: shared_values.trusted_interfaces.clone(),
         };
 
         #[cfg(target_os = "linux")]
         shared_values.disable_connectivity_check();
 
         shared_values
-            .firewall
-            .apply_policy(policy)
+            .apply_tunnel_firewall_policy(policy)
             .map_err(|error| {
                 log::error!(
                     "{}",
@@ -178,9 +183,90 @@ impl TunnelState for ErrorState {
             #[cfg(not(target_os = "android"))]
             Some(TunnelCommand::LockdownMode(lockdown_mode, complete_tx)) => {
                 shared_values.lockdown_mode = lockdown_mode;
+                // Lockdown mode decides whether the host is firewalled when the tunnel is
+                // confined to a namespace
+                #[cfg(target_os = "linux")]
+                if shared_values.tunnel_namespace.is_some() {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TunnelNamespace(enabled, complete_tx)) => {
+                let consequence = if !shared_values.set_tunnel_namespace(enabled) {
+                    SameState(self)
+                } else if matches!(self.block_reason, ErrorStateCause::TunnelNamespaceError) {
+                    NewState(ConnectingState::enter(shared_values, 0))
+                } else {
+                    let _ = Self::set_firewall_policy(shared_values);
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::GetFirewallRules(tx)) => {
+                let _ = tx.send(shared_values.firewall.rules());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::VerifyFirewall(tx)) => {
+                let _ = tx.send(shared_values.verify_firewall());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::ReapplyFirewallOnDrift(enabled, complete_tx)) => {
+                shared_values.set_reapply_firewall_on_drift(enabled);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                if shared_values.set_firewall_exceptions(exceptions) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::LanGateway(lan_gateway, complete_tx)) => {
+                if shared_values.set_lan_gateway(lan_gateway) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::TrustedInterfaces(trusted_interfaces, complete_tx)) => {
+                if shared_values.set_trusted_interfaces(trusted_interfaces) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::InboundPorts(inbound_ports, complete_tx)) => {
+                // Inbound ports are only applied in the connected state
+                let _ = shared_values.set_inbound_ports(inbound_ports);
                 let _ = complete_tx.send(());
                 SameState(self)
             }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::DnsBackend(dns_backend, complete_tx)) => {
+                // The DNS backend is only used in the connected state
+                let _ = shared_values.set_dns_backend(dns_backend);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTunnelStats(tx)) => {
+                shared_values.send_tunnel_stats(tx);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetLastRekey(tx)) => {
+                let _ = tx.send(shared_values.last_rekey());
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 if !connectivity.is_offline()
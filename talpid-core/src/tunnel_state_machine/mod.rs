@@ -5,6 +5,10 @@ mod disconnecting_state;
 mod error_state;
 mod tunnel_monitor;
 
+/// On-demand capture of userspace WireGuard tunnel traffic.
+pub use talpid_wireguard::capture as tunnel_capture;
+pub use talpid_wireguard::{DaitaStats, Stats, StatsMap};
+
 use self::{
     connected_state::ConnectedState,
     connecting_state::ConnectingState,
@@ -12,24 +16,33 @@ use self::{
     disconnecting_state::{AfterDisconnect, DisconnectingState},
     error_state::ErrorState,
 };
+#[cfg(target_os = "linux")]
+use crate::firewall::{FirewallDrift, FirewallRules};
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use crate::split_tunnel;
 use crate::{
-    firewall::{Firewall, FirewallArguments, InitialFirewallState},
+    firewall::{Firewall, FirewallArguments, FirewallPolicy, InitialFirewallState},
     mpsc::Sender,
     offline,
 };
+#[cfg(target_os = "linux")]
+use ipnetwork::IpNetwork;
 #[cfg(any(target_os = "windows", target_os = "macos"))]
 use std::ffi::OsString;
 #[cfg(target_os = "linux")]
 use talpid_cgroup::v2::CGroup2;
 use talpid_dns::{DnsConfig, DnsMonitor};
+#[cfg(target_os = "linux")]
+use talpid_netns::{NetNs, TUNNEL_NETNS_NAME};
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "macos")]
 use talpid_tunnel::TunnelMetadata;
 use talpid_tunnel::{TunnelEvent, tun_provider::TunProvider};
-#[cfg(target_os = "macos")]
+#[cfg(any(target_os = "macos", target_os = "linux"))]
 use talpid_types::ErrorExt;
+#[cfg(target_os = "linux")]
+use talpid_types::net::{DnsBackend, FirewallException, InboundPort, LanGateway, TrustedInterface};
+use talpid_wireguard::TunnelStatsHandle;
 
 use futures::{
     StreamExt,
@@ -44,7 +57,7 @@ use std::{
     path::PathBuf,
     pin::Pin,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 #[cfg(target_os = "android")]
 use talpid_types::{ErrorExt, android::AndroidContext};
@@ -106,6 +119,28 @@ pub struct InitialTunnelState {
     pub allowed_endpoint: AllowedEndpoint,
     /// Whether to reset any existing firewall rules when initializing the disconnected state.
     pub reset_firewall: bool,
+    /// Confine the tunnel to a separate network namespace instead of routing all traffic through
+    /// it.
+    #[cfg(target_os = "linux")]
+    pub tunnel_namespace: bool,
+    /// Outbound traffic that is allowed outside the tunnel in every state.
+    #[cfg(target_os = "linux")]
+    pub firewall_exceptions: Vec<FirewallException>,
+    /// Ports on the tunnel interface that accept incoming connections while connected.
+    #[cfg(target_os = "linux")]
+    pub inbound_ports: Vec<InboundPort>,
+    /// How DNS is configured while connected.
+    #[cfg(target_os = "linux")]
+    pub dns_backend: DnsBackend,
+    /// Apply the firewall policy again when the active rules no longer match it.
+    #[cfg(target_os = "linux")]
+    pub reapply_firewall_on_drift: bool,
+    /// Local networks whose traffic is forwarded through the tunnel.
+    #[cfg(target_os = "linux")]
+    pub lan_gateway: Option<LanGateway>,
+    /// Interfaces of other VPN or mesh clients whose traffic is allowed in every state.
+    #[cfg(target_os = "linux")]
+    pub trusted_interfaces: Vec<TrustedInterface>,
     /// Programs to exclude from the tunnel using the split tunnel driver.
     #[cfg(any(target_os = "windows", target_os = "macos"))]
     pub exclude_paths: Vec<OsString>,
@@ -118,11 +153,15 @@ pub struct InitialTunnelState {
 /// state machine.
 #[cfg(target_os = "linux")]
 pub struct LinuxNetworkingIdentifiers {
+    /// Name of the tunnel interface.
+    pub tunnel_interface: String,
     /// Firewall mark is used to mark traffic which should be able to bypass the tunnel
     pub fwmark: u32,
     /// The table ID will be used for the routing table that will route all traffic through the
     /// tunnel interface.
     pub table_id: u32,
+    /// Connection tracking mark of split tunneled connections.
+    pub split_tunnel_mark: u32,
     /// The cgroup2 used for split tunneling.
     /// Traffic from processes in this cgroup2 should be allowed outside the tunnel.
     pub excluded_cgroup2: Option<CGroup2>,
@@ -217,6 +256,39 @@ pub enum TunnelCommand {
     /// Enable or disable the lockdown_mode feature.
     #[cfg(not(target_os = "android"))]
     LockdownMode(LockdownMode, oneshot::Sender<()>),
+    /// Enable or disable confining the tunnel to a network namespace.
+    #[cfg(target_os = "linux")]
+    TunnelNamespace(bool, oneshot::Sender<()>),
+    /// Set outbound traffic that is allowed outside the tunnel in every state.
+    #[cfg(target_os = "linux")]
+    FirewallExceptions(Vec<FirewallException>, oneshot::Sender<()>),
+    /// Set ports on the tunnel interface that accept incoming connections while connected.
+    #[cfg(target_os = "linux")]
+    InboundPorts(Vec<InboundPort>, oneshot::Sender<()>),
+    /// Set how DNS is configured while connected.
+    #[cfg(target_os = "linux")]
+    DnsBackend(DnsBackend, oneshot::Sender<()>),
+    /// Set whether to apply the firewall policy again when the active rules no longer match it.
+    #[cfg(target_os = "linux")]
+    ReapplyFirewallOnDrift(bool, oneshot::Sender<()>),
+    /// Set local networks whose traffic is forwarded through the tunnel.
+    #[cfg(target_os = "linux")]
+    LanGateway(Option<LanGateway>, oneshot::Sender<()>),
+    /// Set interfaces of other VPN or mesh clients whose traffic is allowed in every state.
+    #[cfg(target_os = "linux")]
+    TrustedInterfaces(Vec<TrustedInterface>, oneshot::Sender<()>),
+    /// Return the rules of the last applied firewall policy and the rules that are active.
+    #[cfg(target_os = "linux")]
+    GetFirewallRules(oneshot::Sender<Result<FirewallRules, crate::firewall::Error>>),
+    /// Check that the active firewall rules still match the last applied policy.
+    #[cfg(target_os = "linux")]
+    VerifyFirewall(oneshot::Sender<Result<Option<FirewallDrift>, crate::firewall::Error>>),
+    /// Return the traffic stats of each peer of the current tunnel, or `None` if there is no
+    /// tunnel.
+    GetTunnelStats(oneshot::Sender<Option<StatsMap>>),
+    /// Return when the quantum-resistant PSK of the current tunnel was last renegotiated, or
+    /// `None` if there is no tunnel or it has not been renegotiated yet.
+    GetLastRekey(oneshot::Sender<Option<SystemTime>>),
     /// Notify the state machine of the connectivity of the device.
     Connectivity(Connectivity),
     /// Open tunnel connection.
@@ -377,6 +449,8 @@ impl TunnelStateMachine {
 
         #[cfg(target_os = "linux")]
         let fwmark = args.linux_ids.fwmark;
+        #[cfg(target_os = "linux")]
+        let tunnel_interface = args.linux_ids.tunnel_interface.clone();
 
         let fw_args = FirewallArguments {
             #[cfg(not(target_os = "android"))]
@@ -394,15 +468,43 @@ impl TunnelStateMachine {
             linux_ids: args.linux_ids,
         };
 
-        let firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
+        #[cfg_attr(not(target_os = "linux"), expect(unused_mut))]
+        let mut firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
 
-        let dns_monitor = DnsMonitor::new(
+        #[cfg_attr(not(target_os = "linux"), expect(unused_mut))]
+        let mut dns_monitor = DnsMonitor::new(
             #[cfg(target_os = "linux")]
             runtime.clone(),
             #[cfg(target_os = "linux")]
             args.route_manager.clone(),
         )
         .map_err(Error::InitDnsMonitorError)?;
+        #[cfg(target_os = "linux")]
+        dns_monitor.set_backend(args.settings.dns_backend);
+        #[cfg(target_os = "linux")]
+        dns_monitor.set_trusted_interfaces(args.settings.trusted_interfaces.clone());
+        #[cfg(target_os = "linux")]
+        if let Err(error) = args
+            .route_manager
+            .set_trusted_interfaces(trusted_interface_names(&args.settings.trusted_interfaces))
+            .await
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to set trusted interfaces")
+            );
+        }
+        #[cfg(target_os = "linux")]
+        if let Err(error) = args
+            .route_manager
+            .set_exceptions(exception_interfaces(&args.settings.firewall_exceptions))
+            .await
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to route firewall exceptions")
+            );
+        }
 
         let (offline_tx, mut offline_rx) = mpsc::unbounded();
         let initial_offline_state_tx = args.offline_state_tx.clone();
@@ -457,6 +559,16 @@ impl TunnelStateMachine {
             );
         }
 
+        #[cfg(target_os = "linux")]
+        let tunnel_namespace = if args.settings.tunnel_namespace {
+            // A failure is reported by entering the error state when connecting
+            create_tunnel_namespace(&mut firewall)
+                .inspect_err(|error| log::error!("{}", error.display_chain()))
+                .ok()
+        } else {
+            None
+        };
+
         let mut shared_values = SharedTunnelStateValues {
             #[cfg(any(target_os = "windows", target_os = "macos"))]
             split_tunnel,
@@ -475,10 +587,31 @@ impl TunnelStateMachine {
             allowed_endpoint: args.settings.allowed_endpoint,
             tunnel_parameters_generator: Box::new(args.tunnel_parameters_generator),
             tun_provider: Arc::new(Mutex::new(args.tun_provider)),
+            tunnel_stats: Arc::new(Mutex::new(None)),
             log_dir: args.log_dir,
             resource_dir: args.resource_dir,
             #[cfg(target_os = "linux")]
             connectivity_check_was_enabled: None,
+            #[cfg(target_os = "linux")]
+            tunnel_interface,
+            #[cfg(target_os = "linux")]
+            fwmark,
+            #[cfg(target_os = "linux")]
+            tunnel_namespace_enabled: args.settings.tunnel_namespace,
+            #[cfg(target_os = "linux")]
+            tunnel_namespace,
+            #[cfg(target_os = "linux")]
+            firewall_exceptions: args.settings.firewall_exceptions,
+            #[cfg(target_os = "linux")]
+            inbound_ports: args.settings.inbound_ports,
+            #[cfg(target_os = "linux")]
+            dns_backend: args.settings.dns_backend,
+            #[cfg(target_os = "linux")]
+            reapply_firewall_on_drift: args.settings.reapply_firewall_on_drift,
+            #[cfg(target_os = "linux")]
+            lan_gateway: args.settings.lan_gateway,
+            #[cfg(target_os = "linux")]
+            trusted_interfaces: args.settings.trusted_interfaces,
             #[cfg(target_os = "macos")]
             filtering_resolver,
         };
@@ -542,6 +675,18 @@ pub trait TunnelParametersGenerator: Send + 'static {
         retry_attempt: u32,
         ip_availability: IpAvailability,
     ) -> Pin<Box<dyn Future<Output = Result<TunnelParameters, ParameterGenerationError>>>>;
+
+    /// Yield a `TunnelParameters` to move a working tunnel to, like the first attempt of
+    /// [`Self::generate`]. Unlike `generate`, the parameters do not replace the last generated
+    /// ones until [`Self::use_candidate`] is called, so they may be discarded.
+    fn generate_candidate(
+        &mut self,
+        ip_availability: IpAvailability,
+    ) -> Pin<Box<dyn Future<Output = Result<TunnelParameters, ParameterGenerationError>> + Send>>;
+
+    /// Make the parameters last returned by [`Self::generate_candidate`] the last generated ones,
+    /// once the tunnel has been moved to them.
+    fn use_candidate(&mut self) -> Pin<Box<dyn Future<Output = ()> + Send>>;
 }
 
 /// Values that are common to all tunnel states.
@@ -575,6 +720,8 @@ struct SharedTunnelStateValues {
     tunnel_parameters_generator: Box<dyn TunnelParametersGenerator>,
     /// The provider of tunnel devices.
     tun_provider: Arc<Mutex<TunProvider>>,
+    /// Reads the traffic stats of the tunnel, while one is running.
+    tunnel_stats: Arc<Mutex<Option<TunnelStatsHandle>>>,
     /// Directory to store tunnel log file.
     log_dir: Option<PathBuf>,
     /// Resource directory path.
@@ -584,12 +731,85 @@ struct SharedTunnelStateValues {
     #[cfg(target_os = "linux")]
     connectivity_check_was_enabled: Option<bool>,
 
+    /// Name of the tunnel interface.
+    #[cfg(target_os = "linux")]
+    tunnel_interface: String,
+    /// Firewall mark of traffic that is allowed outside the tunnel.
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
+
+    /// Whether the tunnel should be confined to a network namespace.
+    #[cfg(target_os = "linux")]
+    tunnel_namespace_enabled: bool,
+    /// Network namespace that the tunnel is confined to, if any. This is `None` while
+    /// `tunnel_namespace_enabled` is set if the namespace could not be created.
+    #[cfg(target_os = "linux")]
+    tunnel_namespace: Option<Arc<NetNs>>,
+
+    /// Outbound traffic that is allowed outside the tunnel in every state.
+    #[cfg(target_os = "linux")]
+    firewall_exceptions: Vec<FirewallException>,
+
+    /// Ports on the tunnel interface that accept incoming connections while connected.
+    #[cfg(target_os = "linux")]
+    inbound_ports: Vec<InboundPort>,
+
+    /// How DNS is configured while connected.
+    #[cfg(target_os = "linux")]
+    dns_backend: DnsBackend,
+
+    /// Apply the firewall policy again when the active rules no longer match it.
+    #[cfg(target_os = "linux")]
+    reapply_firewall_on_drift: bool,
+
+    /// Local networks whose traffic is forwarded through the tunnel.
+    #[cfg(target_os = "linux")]
+    lan_gateway: Option<LanGateway>,
+
+    /// Interfaces of other VPN or mesh clients whose traffic is allowed in every state.
+    #[cfg(target_os = "linux")]
+    trusted_interfaces: Vec<TrustedInterface>,
+
     /// Filtering resolver handle
     #[cfg(target_os = "macos")]
     filtering_resolver: crate::resolver::ResolverHandle,
 }
 
 impl SharedTunnelStateValues {
+    /// Check whether the tunnel interface, the routing table or the firewall marks are used by
+    /// another program. This is checked before every connection attempt, so that the tunnel can be
+    /// connected once the other program has stopped using them.
+    #[cfg(target_os = "linux")]
+    pub fn identifiers_in_use(&self) -> bool {
+        self.runtime.block_on(linux_identifiers_in_use(
+            &self.firewall,
+            &self.route_manager,
+            &self.tunnel_interface,
+            self.fwmark,
+        ))
+    }
+
+    /// Send the traffic stats of the current tunnel to `tx` once they have been read.
+    pub fn send_tunnel_stats(&self, tx: oneshot::Sender<Option<StatsMap>>) {
+        let handle = self.tunnel_stats.lock().unwrap().clone();
+        self.runtime.spawn(async move {
+            let stats = match handle {
+                Some(handle) => handle.get().await,
+                None => None,
+            };
+            let _ = tx.send(stats);
+        });
+    }
+
+    /// Return when the quantum-resistant PSK of the current tunnel was last renegotiated.
+    pub fn last_rekey(&self) -> Option<SystemTime> {
+        self.tunnel_stats
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(TunnelStatsHandle::last_rekey)
+    }
+
     /// Return whether a split tunnel interface was added or removed
     #[cfg(target_os = "macos")]
     pub fn set_exclude_paths(&mut self, paths: Vec<OsString>) -> Result<bool, split_tunnel::Error> {
@@ -657,6 +877,153 @@ impl SharedTunnelStateValues {
         }
     }
 
+    /// Create or destroy the network namespace that the tunnel is confined to. Return whether
+    /// the setting changed. If the namespace cannot be created, connecting fails until it can be.
+    #[cfg(target_os = "linux")]
+    pub fn set_tunnel_namespace(&mut self, enabled: bool) -> bool {
+        if enabled == self.tunnel_namespace_enabled {
+            return false;
+        }
+        self.tunnel_namespace_enabled = enabled;
+        if enabled {
+            self.tunnel_namespace = create_tunnel_namespace(&mut self.firewall)
+                .inspect_err(|error| log::error!("{}", error.display_chain()))
+                .ok();
+        } else if let Some(netns) = self.tunnel_namespace.take()
+            && let Err(error) = netns.destroy()
+        {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to destroy tunnel network namespace")
+            );
+        }
+        true
+    }
+
+    /// Create the tunnel network namespace if it is enabled but could not be created before.
+    #[cfg(target_os = "linux")]
+    pub fn ensure_tunnel_namespace(&mut self) -> Result<(), TunnelNamespaceError> {
+        if self.tunnel_namespace_enabled && self.tunnel_namespace.is_none() {
+            self.tunnel_namespace = Some(create_tunnel_namespace(&mut self.firewall)?);
+        }
+        Ok(())
+    }
+
+    /// Return whether the firewall exceptions changed. Exceptions that are restricted to an
+    /// interface are routed through it right away, but the firewall policy must be applied again
+    /// by the caller.
+    #[cfg(target_os = "linux")]
+    pub fn set_firewall_exceptions(&mut self, exceptions: Vec<FirewallException>) -> bool {
+        if self.firewall_exceptions == exceptions {
+            return false;
+        }
+        if let Err(error) = self.runtime.block_on(
+            self.route_manager
+                .set_exceptions(exception_interfaces(&exceptions)),
+        ) {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to route firewall exceptions")
+            );
+        }
+        self.firewall_exceptions = exceptions;
+        true
+    }
+
+    /// Return whether the inbound ports changed.
+    #[cfg(target_os = "linux")]
+    pub fn set_inbound_ports(&mut self, inbound_ports: Vec<InboundPort>) -> bool {
+        if self.inbound_ports != inbound_ports {
+            self.inbound_ports = inbound_ports;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return whether the LAN gateway changed.
+    #[cfg(target_os = "linux")]
+    pub fn set_lan_gateway(&mut self, lan_gateway: Option<LanGateway>) -> bool {
+        if self.lan_gateway != lan_gateway {
+            self.lan_gateway = lan_gateway;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Return whether the trusted interfaces changed. Their routes and DNS resolvers are updated
+    /// right away, but the firewall policy and DNS must be applied again by the caller.
+    #[cfg(target_os = "linux")]
+    pub fn set_trusted_interfaces(&mut self, trusted_interfaces: Vec<TrustedInterface>) -> bool {
+        if self.trusted_interfaces == trusted_interfaces {
+            return false;
+        }
+        if let Err(error) = self.runtime.block_on(
+            self.route_manager
+                .set_trusted_interfaces(trusted_interface_names(&trusted_interfaces)),
+        ) {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to set trusted interfaces")
+            );
+        }
+        self.dns_monitor
+            .set_trusted_interfaces(trusted_interfaces.clone());
+        self.trusted_interfaces = trusted_interfaces;
+        true
+    }
+
+    /// Return whether the DNS backend changed.
+    #[cfg(target_os = "linux")]
+    pub fn set_dns_backend(&mut self, dns_backend: DnsBackend) -> bool {
+        if self.dns_backend != dns_backend {
+            self.dns_backend = dns_backend;
+            self.dns_monitor.set_backend(dns_backend);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Set whether [`Self::verify_firewall`] applies the firewall policy again.
+    #[cfg(target_os = "linux")]
+    pub fn set_reapply_firewall_on_drift(&mut self, enabled: bool) {
+        self.reapply_firewall_on_drift = enabled;
+    }
+
+    /// Check that the active firewall rules still match the last applied policy, and apply it
+    /// again if they do not and [`Self::set_reapply_firewall_on_drift`] is enabled.
+    #[cfg(target_os = "linux")]
+    pub fn verify_firewall(&mut self) -> Result<Option<FirewallDrift>, crate::firewall::Error> {
+        self.firewall.verify_policy(self.reapply_firewall_on_drift)
+    }
+
+    /// Apply a firewall policy for a state where the tunnel may be up. If the tunnel is confined
+    /// to a network namespace, the host is not tunneled, so its firewall is reset instead. With
+    /// lockdown mode, the policy is still applied, which locks the host down in every state, like
+    /// the disconnected state does.
+    pub fn apply_tunnel_firewall_policy(
+        &mut self,
+        policy: FirewallPolicy,
+    ) -> Result<(), crate::firewall::Error> {
+        #[cfg(target_os = "linux")]
+        if self.tunnel_namespace.is_some() && !self.lockdown_mode.bool() {
+            return self.reset_firewall_policy();
+        }
+        self.firewall.apply_policy(policy)
+    }
+
+    /// Remove the firewall policy. Traffic forwarded from the LAN gateway is still blocked, since
+    /// nothing else keeps it from leaking outside the tunnel.
+    pub fn reset_firewall_policy(&mut self) -> Result<(), crate::firewall::Error> {
+        #[cfg(target_os = "linux")]
+        if let Some(lan_gateway) = &self.lan_gateway {
+            return self.firewall.block_lan_gateway(lan_gateway);
+        }
+        self.firewall.reset_policy()
+    }
+
     pub fn set_dns_config(&mut self, dns_config: DnsConfig) -> bool {
         if self.dns_config != dns_config {
             self.dns_config = dns_config;
@@ -755,6 +1122,97 @@ impl SharedTunnelStateValues {
     }
 }
 
+/// Check whether the tunnel interface, the routing table or the firewall marks are already used
+/// by another program. Every conflict that is found is logged.
+#[cfg(target_os = "linux")]
+async fn linux_identifiers_in_use(
+    firewall: &Firewall,
+    route_manager: &RouteManagerHandle,
+    tunnel_interface: &str,
+    fwmark: u32,
+) -> bool {
+    let mut in_use = false;
+
+    if talpid_wireguard::is_interface_in_use(tunnel_interface, fwmark).await {
+        log::error!("The tunnel interface name {tunnel_interface} is used by another interface");
+        in_use = true;
+    }
+    match route_manager.find_conflicting_rules().await {
+        Ok(rules) => {
+            for rule in rules {
+                log::error!("Routing rule uses the tunnel routing table or fwmark: {rule}");
+                in_use = true;
+            }
+        }
+        Err(error) => log::warn!(
+            "{}",
+            error.display_chain_with_msg("Failed to check for conflicting routing rules")
+        ),
+    }
+    match firewall.find_conflicting_rules() {
+        Ok(rules) => {
+            for rule in rules {
+                log::error!("Firewall rule uses the tunnel fwmark or split tunnel mark: {rule}");
+                in_use = true;
+            }
+        }
+        Err(error) => log::warn!(
+            "{}",
+            error.display_chain_with_msg("Failed to check for conflicting firewall rules")
+        ),
+    }
+
+    in_use
+}
+
+#[cfg(target_os = "linux")]
+fn trusted_interface_names(trusted_interfaces: &[TrustedInterface]) -> Vec<String> {
+    trusted_interfaces
+        .iter()
+        .map(|interface| interface.name.clone())
+        .collect()
+}
+
+/// Return the destination of every firewall exception that is restricted to an interface, along
+/// with that interface.
+#[cfg(target_os = "linux")]
+fn exception_interfaces(exceptions: &[FirewallException]) -> Vec<(IpNetwork, String)> {
+    exceptions
+        .iter()
+        .filter_map(|exception| {
+            let interface = exception.interface.clone()?;
+            Some((exception.destination, interface))
+        })
+        .collect()
+}
+
+/// Failure to set up the network namespace that the tunnel is confined to.
+#[cfg(target_os = "linux")]
+#[derive(thiserror::Error, Debug)]
+pub enum TunnelNamespaceError {
+    #[error("Failed to create tunnel network namespace")]
+    Create(#[source] talpid_netns::Error),
+
+    #[error("Failed to apply tunnel namespace firewall policy")]
+    Firewall(#[source] crate::firewall::Error),
+}
+
+/// Create the tunnel network namespace and block all traffic inside it until a tunnel is up.
+#[cfg(target_os = "linux")]
+fn create_tunnel_namespace(firewall: &mut Firewall) -> Result<Arc<NetNs>, TunnelNamespaceError> {
+    let netns = NetNs::create_or_open(TUNNEL_NETNS_NAME).map_err(TunnelNamespaceError::Create)?;
+    if let Err(error) = firewall.apply_namespace_policy(&netns, None) {
+        if let Err(error) = netns.destroy() {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to destroy tunnel network namespace")
+            );
+        }
+        return Err(TunnelNamespaceError::Firewall(error));
+    }
+    Ok(Arc::new(netns))
+}
+
 /// Asynchronous result of an attempt to progress a state.
 enum EventConsequence {
     /// Transition to a new state.
@@ -164,6 +164,11 @@ impl TunnelMonitor {
         }
     }
 
+    /// Return a handle for reading the traffic stats of the tunnel while it is up.
+    pub fn stats_handle(&self) -> talpid_wireguard::TunnelStatsHandle {
+        self.monitor.stats_handle()
+    }
+
     /// Consumes the monitor and blocks until the tunnel exits or there is an error.
     pub fn wait(self) -> Result<()> {
         self.monitor.wait().map_err(Error::from)
@@ -412,16 +412,32 @@ impl AsyncHandle {
             .map_err(Error::AsyncTaskError)?
     }
 
+    pub async fn get_domains(&self, interface_index: u32) -> Result<Vec<(String, bool)>, Error> {
+        let interface = self.dbus_interface.clone();
+        tokio::task::spawn_blocking(move || interface.get_domains(interface_index))
+            .await
+            .map_err(Error::AsyncTaskError)?
+    }
+
     pub async fn set_domains(
         &self,
         interface_index: u32,
-        domains: &[(&'static str, bool)],
+        domains: &[(&str, bool)],
     ) -> Result<(), Error> {
         let interface = self.dbus_interface.clone();
-        let domains = domains.to_vec();
-        tokio::task::spawn_blocking(move || interface.set_domains(interface_index, &domains))
-            .await
-            .map_err(Error::AsyncTaskError)?
+        let domains: Vec<_> = domains
+            .iter()
+            .map(|(domain, routing_only)| (domain.to_string(), *routing_only))
+            .collect();
+        tokio::task::spawn_blocking(move || {
+            let domains: Vec<_> = domains
+                .iter()
+                .map(|(domain, routing_only)| (domain.as_str(), *routing_only))
+                .collect();
+            interface.set_domains(interface_index, &domains)
+        })
+        .await
+        .map_err(Error::AsyncTaskError)?
     }
 
     pub async fn revert_link(&self, state: DnsState) -> Result<(), Error> {
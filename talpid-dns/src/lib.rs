@@ -192,6 +192,28 @@ impl DnsMonitor {
         self.inner.set(interface, config)
     }
 
+    /// Select how DNS is managed the next time it is set.
+    #[cfg(target_os = "linux")]
+    pub fn set_backend(&mut self, backend: talpid_types::net::DnsBackend) {
+        self.inner.set_backend(backend)
+    }
+
+    /// Set the trusted interfaces whose DNS forwarding is applied the next time DNS is set.
+    #[cfg(target_os = "linux")]
+    pub fn set_trusted_interfaces(
+        &mut self,
+        trusted_interfaces: Vec<talpid_types::net::TrustedInterface>,
+    ) {
+        self.inner.set_trusted_interfaces(trusted_interfaces)
+    }
+
+    /// Returns whether DNS must be redirected to the tunnel DNS servers by the firewall, rather
+    /// than being configured on the system.
+    #[cfg(target_os = "linux")]
+    pub fn uses_firewall_redirect(&self) -> bool {
+        self.inner.uses_firewall_redirect()
+    }
+
     /// Reset system DNS settings to what it was before being set by this instance.
     /// This succeeds if the interface does not exist.
     pub fn reset(&mut self) -> Result<(), Error> {
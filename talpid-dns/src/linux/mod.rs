@@ -8,6 +8,7 @@ use std::env;
 use std::fmt::{self, Display};
 use std::net::IpAddr;
 use talpid_routing::RouteManagerHandle;
+use talpid_types::net::{DnsBackend, TrustedInterface};
 
 use self::network_manager::NetworkManager;
 use self::resolvconf::Resolvconf;
@@ -44,9 +45,34 @@ pub enum Error {
 pub struct DnsMonitor {
     route_manager: RouteManagerHandle,
     handle: tokio::runtime::Handle,
+    backend: DnsBackend,
+    trusted_interfaces: Vec<TrustedInterface>,
     inner: Option<DnsMonitorHolder>,
 }
 
+impl DnsMonitor {
+    /// Select how DNS is managed the next time it is set.
+    pub fn set_backend(&mut self, backend: DnsBackend) {
+        self.backend = backend;
+    }
+
+    /// Set the trusted interfaces whose DNS forwarding is applied the next time DNS is set.
+    pub fn set_trusted_interfaces(&mut self, trusted_interfaces: Vec<TrustedInterface>) {
+        self.trusted_interfaces = trusted_interfaces;
+    }
+
+    /// Returns whether DNS must be redirected to the tunnel DNS servers by the firewall.
+    pub fn uses_firewall_redirect(&self) -> bool {
+        match self.backend {
+            DnsBackend::NftablesRedirect => true,
+            DnsBackend::Auto => {
+                env::var_os("TALPID_DNS_MODULE").is_some_and(|module| module == "nftables-redirect")
+            }
+            _ => false,
+        }
+    }
+}
+
 impl super::DnsMonitorT for DnsMonitor {
     type Error = Error;
 
@@ -54,6 +80,8 @@ impl super::DnsMonitorT for DnsMonitor {
         Ok(DnsMonitor {
             route_manager,
             handle,
+            backend: DnsBackend::default(),
+            trusted_interfaces: vec![],
             inner: None,
         })
     }
@@ -62,9 +90,15 @@ impl super::DnsMonitorT for DnsMonitor {
         let servers = config.tunnel_config();
         self.reset()?;
         // Creating a new DNS monitor for each set, in case the system changed how it manages DNS.
-        let mut inner = DnsMonitorHolder::new()?;
+        let mut inner = DnsMonitorHolder::new(self.backend)?;
         if !servers.is_empty() {
-            inner.set(&self.handle, &self.route_manager, interface, servers)?;
+            inner.set(
+                &self.handle,
+                &self.route_manager,
+                interface,
+                servers,
+                &self.trusted_interfaces,
+            )?;
             self.inner = Some(inner);
         }
         Ok(())
@@ -83,6 +117,9 @@ pub enum DnsMonitorHolder {
     NetworkManager(NetworkManager),
     Resolvconf(Resolvconf),
     StaticResolvConf(StaticResolvConf),
+    /// DNS is redirected to the tunnel DNS servers by the firewall, so the system DNS config is
+    /// left untouched.
+    NftablesRedirect,
 }
 
 impl fmt::Display for DnsMonitorHolder {
@@ -93,13 +130,29 @@ impl fmt::Display for DnsMonitorHolder {
             StaticResolvConf(..) => "/etc/resolv.conf",
             SystemdResolved(..) => "systemd-resolved",
             NetworkManager(..) => "NetworkManager",
+            NftablesRedirect => "nftables redirect",
         };
         f.write_str(name)
     }
 }
 
 impl DnsMonitorHolder {
-    fn new() -> Result<Self> {
+    fn new(backend: DnsBackend) -> Result<Self> {
+        let manager = match backend {
+            DnsBackend::Auto => Self::from_env()?,
+            DnsBackend::SystemdResolved => {
+                DnsMonitorHolder::SystemdResolved(SystemdResolved::new()?)
+            }
+            DnsBackend::NetworkManager => DnsMonitorHolder::NetworkManager(NetworkManager::new()?),
+            DnsBackend::Resolvconf => DnsMonitorHolder::Resolvconf(Resolvconf::new()?),
+            DnsBackend::StaticFile => DnsMonitorHolder::StaticResolvConf(StaticResolvConf::new()?),
+            DnsBackend::NftablesRedirect => DnsMonitorHolder::NftablesRedirect,
+        };
+        log::debug!("Managing DNS via {}", manager);
+        Ok(manager)
+    }
+
+    fn from_env() -> Result<Self> {
         let dns_module = env::var_os("TALPID_DNS_MODULE");
 
         let manager = match dns_module.as_ref().and_then(|value| value.to_str()) {
@@ -107,9 +160,9 @@ impl DnsMonitorHolder {
             Some("resolvconf") => DnsMonitorHolder::Resolvconf(Resolvconf::new()?),
             Some("systemd") => DnsMonitorHolder::SystemdResolved(SystemdResolved::new()?),
             Some("network-manager") => DnsMonitorHolder::NetworkManager(NetworkManager::new()?),
+            Some("nftables-redirect") => DnsMonitorHolder::NftablesRedirect,
             Some(_) | None => Self::with_detected_dns_manager()?,
         };
-        log::debug!("Managing DNS via {}", manager);
         Ok(manager)
     }
 
@@ -147,8 +200,18 @@ impl DnsMonitorHolder {
         route_manager: &RouteManagerHandle,
         interface: &str,
         servers: &[IpAddr],
+        trusted_interfaces: &[TrustedInterface],
     ) -> Result<()> {
         use self::DnsMonitorHolder::*;
+        let forwarding = trusted_interfaces
+            .iter()
+            .any(|interface| interface.dns_forwarding.is_some());
+        if forwarding && !matches!(self, SystemdResolved(..) | NftablesRedirect) {
+            log::warn!(
+                "DNS forwarding to trusted interfaces requires systemd-resolved or nftables \
+                 redirect, but DNS is managed via {self}"
+            );
+        }
         match self {
             Resolvconf(resolvconf) => resolvconf.set_dns(interface, servers)?,
             StaticResolvConf(static_resolv_conf) => static_resolv_conf.set_dns(servers.to_vec())?,
@@ -156,8 +219,10 @@ impl DnsMonitorHolder {
                 route_manager.clone(),
                 interface,
                 servers,
+                trusted_interfaces,
             ))?,
             NetworkManager(network_manager) => network_manager.set_dns(interface, servers)?,
+            NftablesRedirect => (),
         }
         Ok(())
     }
@@ -169,6 +234,7 @@ impl DnsMonitorHolder {
             StaticResolvConf(static_resolv_conf) => static_resolv_conf.reset()?,
             SystemdResolved(systemd_resolved) => handle.block_on(systemd_resolved.reset())?,
             NetworkManager(network_manager) => network_manager.reset()?,
+            NftablesRedirect => (),
         }
         Ok(())
     }
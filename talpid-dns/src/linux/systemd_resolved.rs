@@ -1,7 +1,7 @@
 use std::net::IpAddr;
-use talpid_dbus::systemd_resolved::{AsyncHandle, SystemdResolved as DbusInterface};
+use talpid_dbus::systemd_resolved::{AsyncHandle, DnsState, SystemdResolved as DbusInterface};
 use talpid_routing::RouteManagerHandle;
-use talpid_types::ErrorExt;
+use talpid_types::{ErrorExt, net::TrustedInterface};
 
 pub(crate) use talpid_dbus::systemd_resolved::Error as SystemdDbusError;
 
@@ -21,6 +21,9 @@ pub enum Error {
 pub struct SystemdResolved {
     pub dbus_interface: AsyncHandle,
     tunnel_index: u32,
+    /// DNS config of trusted interfaces from before their DNS was forwarded, which is restored
+    /// on reset.
+    forwarding_links: Vec<(DnsState, Vec<(String, bool)>)>,
 }
 
 impl SystemdResolved {
@@ -30,6 +33,7 @@ impl SystemdResolved {
         let systemd_resolved = SystemdResolved {
             dbus_interface,
             tunnel_index: 0,
+            forwarding_links: vec![],
         };
 
         Ok(systemd_resolved)
@@ -40,6 +44,7 @@ impl SystemdResolved {
         _route_manager: RouteManagerHandle,
         interface_name: &str,
         servers: &[IpAddr],
+        trusted_interfaces: &[TrustedInterface],
     ) -> Result<()> {
         let tunnel_index = iface_index(interface_name)?;
         self.tunnel_index = tunnel_index;
@@ -61,10 +66,68 @@ impl SystemdResolved {
             .set_dns(self.tunnel_index, servers.to_vec())
             .await?;
 
+        for interface in trusted_interfaces {
+            if let Err(error) = self.forward_dns(interface).await {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(&format!(
+                        "Failed to forward DNS to trusted interface {}",
+                        interface.name
+                    ))
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the forwarded domains of a trusted interface using its resolver. The routing
+    /// domains are more specific than the catch-all domain of the tunnel, so they take precedence.
+    async fn forward_dns(&mut self, interface: &TrustedInterface) -> Result<()> {
+        let Some(forwarding) = &interface.dns_forwarding else {
+            return Ok(());
+        };
+        let index = iface_index(&interface.name)?;
+
+        let dns_state = self.dbus_interface.get_dns(index).await?;
+        let domains = self.dbus_interface.get_domains(index).await?;
+        self.forwarding_links.push((dns_state, domains));
+
+        self.dbus_interface
+            .set_dns(index, vec![forwarding.resolver])
+            .await?;
+        let routing_domains: Vec<_> = forwarding
+            .domains
+            .iter()
+            .map(|domain| (domain.as_str(), true))
+            .collect();
+        self.dbus_interface
+            .set_domains(index, &routing_domains)
+            .await?;
         Ok(())
     }
 
     pub async fn reset(&mut self) -> Result<()> {
+        for (dns_state, domains) in std::mem::take(&mut self.forwarding_links) {
+            let index = dns_state.interface_index;
+            let domains: Vec<_> = domains
+                .iter()
+                .map(|(domain, routing_only)| (domain.as_str(), *routing_only))
+                .collect();
+            if let Err(error) = self.dbus_interface.set_domains(index, &domains).await {
+                log::error!(
+                    "Failed to restore search domains: {}",
+                    error.display_chain()
+                );
+            }
+            if let Err(error) = self.dbus_interface.set_dns_state(dns_state).await {
+                log::error!(
+                    "Failed to restore DNS of trusted interface: {}",
+                    error.display_chain()
+                );
+            }
+        }
+
         if let Err(error) = self
             .dbus_interface
             .set_domains(self.tunnel_index, &[])
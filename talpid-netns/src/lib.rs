@@ -0,0 +1,549 @@
+#![cfg(target_os = "linux")]
+//! Create, enter and destroy named Linux network namespaces.
+//!
+//! Namespaces are kept alive by bind mounting them below [`NETNS_RUN_DIR`], which is the same
+//! convention that `ip netns` uses. This means that they can be inspected with `ip netns` as well.
+
+use nix::{
+    mount::{MntFlags, MsFlags, mount, umount2},
+    sched::{CloneFlags, setns, unshare},
+    sys::statfs::{NSFS_MAGIC, statfs},
+    unistd::{getegid, geteuid, getgid, getuid},
+};
+use std::{
+    env,
+    ffi::OsString,
+    fmt::Write as _,
+    fs::{self, File},
+    io,
+    net::IpAddr,
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::fs::MetadataExt,
+    },
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// Name of the network namespace that holds the tunnel device when the tunnel is confined to a
+/// namespace.
+pub const TUNNEL_NETNS_NAME: &str = "mullvad-tunnel";
+
+/// The directory where named network namespaces are mounted.
+pub const NETNS_RUN_DIR: &str = "/run/netns";
+
+/// The directory containing per-namespace configuration files, such as `resolv.conf`.
+pub const NETNS_ETC_DIR: &str = "/etc/netns";
+
+/// Directory where network namespaces should be mounted. Overrides [`NETNS_RUN_DIR`].
+///
+/// Ignored by set-user-ID and set-group-ID programs, such as `mullvad-include`.
+pub const NETNS_RUN_DIR_OVERRIDE_ENV_VAR: &str = "TALPID_NETNS_RUN_DIR";
+
+/// Directory where per-namespace configuration is stored. Overrides [`NETNS_ETC_DIR`].
+///
+/// Ignored by set-user-ID and set-group-ID programs, such as `mullvad-include`.
+pub const NETNS_ETC_DIR_OVERRIDE_ENV_VAR: &str = "TALPID_NETNS_ETC_DIR";
+
+/// The only configuration file that is bind mounted over `/etc` by [`NetNs::enter`].
+const RESOLV_CONF: &str = "resolv.conf";
+
+/// The network namespace of the calling thread.
+const THREAD_NETNS_PATH: &str = "/proc/thread-self/ns/net";
+
+/// Errors related to network namespaces.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// The name cannot be used as a path component.
+    #[error("Invalid network namespace name: {0:?}")]
+    InvalidName(String),
+
+    /// There is no namespace mounted with the given name.
+    #[error("Network namespace {0:?} does not exist")]
+    NotFound(String),
+
+    /// Failed to create a directory.
+    #[error("Failed to create directory {0:?}")]
+    CreateDir(PathBuf, #[source] io::Error),
+
+    /// Failed to create the file that the namespace is mounted on.
+    #[error("Failed to create mount point {0:?}")]
+    CreateMountPoint(PathBuf, #[source] io::Error),
+
+    /// Failed to spawn a thread to create or enter the namespace in.
+    #[error("Failed to spawn thread")]
+    SpawnThread(#[source] io::Error),
+
+    /// Failed to unshare a namespace.
+    #[error("Failed to unshare namespace")]
+    Unshare(#[source] nix::Error),
+
+    /// Failed to mount or change the propagation of a mount.
+    #[error("Failed to mount {0:?}")]
+    Mount(PathBuf, #[source] nix::Error),
+
+    /// Failed to open the namespace.
+    #[error("Failed to open network namespace at {0:?}")]
+    Open(PathBuf, #[source] io::Error),
+
+    /// Failed to enter the namespace.
+    #[error("Failed to enter network namespace")]
+    Enter(#[source] nix::Error),
+
+    /// Failed to unmount the namespace.
+    #[error("Failed to unmount network namespace at {0:?}")]
+    Unmount(PathBuf, #[source] nix::Error),
+
+    /// Failed to remove the file that the namespace was mounted on.
+    #[error("Failed to remove {0:?}")]
+    Remove(PathBuf, #[source] io::Error),
+
+    /// Failed to read the configuration directory of the namespace.
+    #[error("Failed to read {0:?}")]
+    ReadConfigDir(PathBuf, #[source] io::Error),
+
+    /// The configuration of the namespace could have been written by someone other than root.
+    #[error("{0:?} must be owned by root and not writable by other users")]
+    InsecureConfig(PathBuf),
+
+    /// Failed to write `resolv.conf` for the namespace.
+    #[error("Failed to write {0:?}")]
+    WriteResolvConf(PathBuf, #[source] io::Error),
+}
+
+/// A handle to a named network namespace.
+#[derive(Debug)]
+pub struct NetNs {
+    name: String,
+    /// Path of the bind mount that keeps the namespace alive, e.g. `/run/netns/foobar`
+    path: PathBuf,
+    /// Directory containing configuration files for the namespace, e.g. `/etc/netns/foobar`
+    config_dir: PathBuf,
+    /// Open handle to the namespace. Used to enter it.
+    file: File,
+}
+
+impl NetNs {
+    /// Open the network namespace called `name`, creating it if it does not exist.
+    ///
+    /// See [`NETNS_RUN_DIR_OVERRIDE_ENV_VAR`] and [`NETNS_ETC_DIR_OVERRIDE_ENV_VAR`] for how to
+    /// control where namespaces and their configuration are stored.
+    pub fn create_or_open(name: &str) -> Result<Self, Error> {
+        Self::create_or_open_in(&run_dir(), &etc_dir(), name)
+    }
+
+    /// Open the existing network namespace called `name`.
+    pub fn open(name: &str) -> Result<Self, Error> {
+        Self::open_in(&run_dir(), &etc_dir(), name)
+    }
+
+    fn create_or_open_in(run_dir: &Path, etc_dir: &Path, name: &str) -> Result<Self, Error> {
+        match Self::open_in(run_dir, etc_dir, name) {
+            Ok(netns) => {
+                log::debug!("Network namespace {name:?} already exists");
+                Ok(netns)
+            }
+            Err(Error::NotFound(_)) => Self::create_in(run_dir, etc_dir, name),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn open_in(run_dir: &Path, etc_dir: &Path, name: &str) -> Result<Self, Error> {
+        validate_name(name)?;
+
+        let path = run_dir.join(name);
+        if !is_netns_mount(&path) {
+            return Err(Error::NotFound(name.to_owned()));
+        }
+        let file = File::open(&path).map_err(|error| Error::Open(path.clone(), error))?;
+
+        Ok(NetNs {
+            name: name.to_owned(),
+            path,
+            config_dir: etc_dir.join(name),
+            file,
+        })
+    }
+
+    fn create_in(run_dir: &Path, etc_dir: &Path, name: &str) -> Result<Self, Error> {
+        validate_name(name)?;
+        make_shared_mount(run_dir)?;
+
+        let path = run_dir.join(name);
+        File::options()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|error| Error::CreateMountPoint(path.clone(), error))?;
+
+        // Unsharing the network namespace only affects the calling thread, so do it on a
+        // separate thread to avoid moving the caller into the new namespace.
+        let result = spawn_and_join(|| {
+            unshare(CloneFlags::CLONE_NEWNET).map_err(Error::Unshare)?;
+            mount(
+                Some(THREAD_NETNS_PATH),
+                &path,
+                None::<&str>,
+                MsFlags::MS_BIND,
+                None::<&str>,
+            )
+            .map_err(|error| Error::Mount(path.clone(), error))
+        })
+        .and_then(|result| result);
+
+        if let Err(error) = result {
+            let _ = fs::remove_file(&path);
+            return Err(error);
+        }
+
+        log::debug!("Created network namespace {name:?}");
+
+        Self::open_in(run_dir, etc_dir, name)
+    }
+
+    /// Return the name of the namespace.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Run `f` on a new thread inside the namespace and return its result.
+    ///
+    /// Sockets created by `f` remain in the namespace after it returns, so this can be used to
+    /// set up netlink connections and similar resources that operate on the namespace.
+    pub fn run<T: Send>(&self, f: impl FnOnce() -> T + Send) -> Result<T, Error> {
+        spawn_and_join(|| {
+            setns(&self.file, CloneFlags::CLONE_NEWNET).map_err(Error::Enter)?;
+            Ok(f())
+        })
+        .and_then(|result| result)
+    }
+
+    /// Move the calling process into the namespace.
+    ///
+    /// Like `ip netns exec`, this also creates a private mount namespace and bind mounts
+    /// `resolv.conf` from the configuration directory of the namespace over `/etc/resolv.conf`.
+    /// Other files in the directory are ignored. The directory and the file must be owned by root
+    /// and not be writable by anyone else, since this is called by privileged programs.
+    /// The process must be single-threaded.
+    pub fn enter(&self) -> Result<(), Error> {
+        setns(&self.file, CloneFlags::CLONE_NEWNET).map_err(Error::Enter)?;
+        unshare(CloneFlags::CLONE_NEWNS).map_err(Error::Unshare)?;
+
+        // Make sure that the bind mounts below do not propagate back to the host.
+        mount(
+            None::<&str>,
+            "/",
+            None::<&str>,
+            MsFlags::MS_SLAVE | MsFlags::MS_REC,
+            None::<&str>,
+        )
+        .map_err(|error| Error::Mount(PathBuf::from("/"), error))?;
+
+        let source = self.config_dir.join(RESOLV_CONF);
+        match fs::symlink_metadata(&source) {
+            Ok(_) => (),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(error) => return Err(Error::ReadConfigDir(self.config_dir.clone(), error)),
+        }
+        check_root_owned(&self.config_dir)?;
+        check_root_owned(&source)?;
+
+        let target = Path::new("/etc").join(RESOLV_CONF);
+        mount(
+            Some(source.as_path()),
+            &target,
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        )
+        .map_err(|error| Error::Mount(target, error))
+    }
+
+    /// Set the DNS servers used by processes that enter the namespace using [`Self::enter`].
+    pub fn set_dns_servers(&self, servers: impl IntoIterator<Item = IpAddr>) -> Result<(), Error> {
+        fs::create_dir_all(&self.config_dir)
+            .map_err(|error| Error::CreateDir(self.config_dir.clone(), error))?;
+
+        let path = self.config_dir.join(RESOLV_CONF);
+        fs::write(&path, resolv_conf(servers)).map_err(|error| Error::WriteResolvConf(path, error))
+    }
+
+    /// Unmount the namespace and remove its configuration.
+    ///
+    /// Processes that are still running inside the namespace keep it alive until they exit, but
+    /// it can no longer be entered.
+    pub fn destroy(&self) -> Result<(), Error> {
+        umount2(&self.path, MntFlags::MNT_DETACH)
+            .map_err(|error| Error::Unmount(self.path.clone(), error))?;
+        fs::remove_file(&self.path).map_err(|error| Error::Remove(self.path.clone(), error))?;
+
+        if let Err(error) = fs::remove_dir_all(&self.config_dir)
+            && error.kind() != io::ErrorKind::NotFound
+        {
+            log::warn!("Failed to remove {:?}: {error}", self.config_dir);
+        }
+
+        log::debug!("Destroyed network namespace {:?}", self.name);
+        Ok(())
+    }
+}
+
+impl AsFd for NetNs {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+fn run_dir() -> PathBuf {
+    secure_var_os(NETNS_RUN_DIR_OVERRIDE_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(NETNS_RUN_DIR))
+}
+
+fn etc_dir() -> PathBuf {
+    secure_var_os(NETNS_ETC_DIR_OVERRIDE_ENV_VAR)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(NETNS_ETC_DIR))
+}
+
+/// Read an environment variable with the semantics of `secure_getenv`: nothing is returned if
+/// the process runs with privileges that its caller does not have, since the caller controls the
+/// environment.
+fn secure_var_os(var: &str) -> Option<OsString> {
+    if getuid() != geteuid() || getgid() != getegid() {
+        return None;
+    }
+    env::var_os(var)
+}
+
+/// Make sure that `path` is not a symlink, is owned by root and cannot be written by group or
+/// others.
+fn check_root_owned(path: &Path) -> Result<(), Error> {
+    let metadata =
+        fs::symlink_metadata(path).map_err(|error| Error::ReadConfigDir(path.to_owned(), error))?;
+    if metadata.file_type().is_symlink() || metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+        return Err(Error::InsecureConfig(path.to_owned()));
+    }
+    Ok(())
+}
+
+/// Namespace names are used as path components, so they must not be able to escape the directory
+/// they are stored in.
+fn validate_name(name: &str) -> Result<(), Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(['/', '\0']) {
+        return Err(Error::InvalidName(name.to_owned()));
+    }
+    Ok(())
+}
+
+fn is_netns_mount(path: &Path) -> bool {
+    statfs(path)
+        .map(|stat| stat.filesystem_type() == NSFS_MAGIC)
+        .unwrap_or(false)
+}
+
+/// Make `dir` a shared mount point, so that namespaces mounted in it are visible in every mount
+/// namespace. This is what `ip netns add` does as well.
+fn make_shared_mount(dir: &Path) -> Result<(), Error> {
+    fs::create_dir_all(dir).map_err(|error| Error::CreateDir(dir.to_owned(), error))?;
+
+    let make_shared = || {
+        mount(
+            None::<&str>,
+            dir,
+            None::<&str>,
+            MsFlags::MS_SHARED | MsFlags::MS_REC,
+            None::<&str>,
+        )
+    };
+
+    if make_shared().is_ok() {
+        return Ok(());
+    }
+
+    // `dir` is not a mount point yet, so bind mount it onto itself first.
+    mount(
+        Some(dir),
+        dir,
+        None::<&str>,
+        MsFlags::MS_BIND | MsFlags::MS_REC,
+        None::<&str>,
+    )
+    .map_err(|error| Error::Mount(dir.to_owned(), error))?;
+    make_shared().map_err(|error| Error::Mount(dir.to_owned(), error))
+}
+
+/// Run `f` on a new thread and wait for it to finish.
+fn spawn_and_join<T: Send>(f: impl FnOnce() -> T + Send) -> Result<T, Error> {
+    thread::scope(|scope| {
+        let handle = thread::Builder::new()
+            .name("netns".to_owned())
+            .spawn_scoped(scope, f)
+            .map_err(Error::SpawnThread)?;
+        Ok(handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic)))
+    })
+}
+
+fn resolv_conf(servers: impl IntoIterator<Item = IpAddr>) -> String {
+    let mut contents = String::new();
+    for server in servers {
+        writeln!(&mut contents, "nameserver {server}").expect("formatting failed");
+    }
+    contents
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use nix::{
+        sys::wait::{WaitStatus, waitpid},
+        unistd::{ForkResult, fork},
+    };
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Exit code used by the child process when user namespaces are unavailable.
+    const SKIP_EXIT_CODE: i32 = 77;
+
+    /// Run `test` in a child process with new user and mount namespaces. This makes it possible
+    /// to create and mount network namespaces without being root. The test is skipped if
+    /// unprivileged user namespaces are disabled on the host.
+    fn in_user_namespace(test: impl FnOnce()) {
+        let uid = getuid();
+        let gid = getgid();
+
+        // SAFETY: The child never returns to the test harness. It runs `test` and exits.
+        match unsafe { fork() }.expect("fork failed") {
+            ForkResult::Child => {
+                let code = if enter_user_namespace(uid, gid).is_err() {
+                    SKIP_EXIT_CODE
+                } else if std::panic::catch_unwind(std::panic::AssertUnwindSafe(test)).is_ok() {
+                    0
+                } else {
+                    1
+                };
+                // SAFETY: `_exit` does not return and is always safe to call.
+                unsafe { nix::libc::_exit(code) };
+            }
+            ForkResult::Parent { child } => match waitpid(child, None).expect("waitpid failed") {
+                WaitStatus::Exited(_, 0) => (),
+                WaitStatus::Exited(_, SKIP_EXIT_CODE) => {
+                    eprintln!("Skipping test since unprivileged user namespaces are unavailable")
+                }
+                status => panic!("Test failed in child process: {status:?}"),
+            },
+        }
+    }
+
+    fn enter_user_namespace(uid: nix::unistd::Uid, gid: nix::unistd::Gid) -> io::Result<()> {
+        unshare(CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS)?;
+        // Map our own user and group to root, so that files can be created in the namespace
+        fs::write("/proc/self/uid_map", format!("0 {uid} 1"))?;
+        fs::write("/proc/self/setgroups", "deny")?;
+        fs::write("/proc/self/gid_map", format!("0 {gid} 1"))?;
+        Ok(())
+    }
+
+    fn current_netns_inode() -> u64 {
+        fs::metadata(THREAD_NETNS_PATH).unwrap().ino()
+    }
+
+    #[test]
+    fn test_create_open_and_destroy() {
+        in_user_namespace(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let run_dir = dir.path().join("run");
+            let etc_dir = dir.path().join("etc");
+
+            let netns = NetNs::create_or_open_in(&run_dir, &etc_dir, "test").unwrap();
+            let path = run_dir.join("test");
+            assert!(is_netns_mount(&path));
+
+            let reopened = NetNs::create_or_open_in(&run_dir, &etc_dir, "test").unwrap();
+            assert_eq!(
+                netns.file.metadata().unwrap().ino(),
+                reopened.file.metadata().unwrap().ino()
+            );
+
+            netns.destroy().unwrap();
+            assert!(!path.exists());
+            assert!(matches!(
+                NetNs::open_in(&run_dir, &etc_dir, "test"),
+                Err(Error::NotFound(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_run_in_namespace() {
+        in_user_namespace(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let netns =
+                NetNs::create_or_open_in(&dir.path().join("run"), &dir.path().join("etc"), "test")
+                    .unwrap();
+            let netns_inode = netns.file.metadata().unwrap().ino();
+
+            assert_ne!(current_netns_inode(), netns_inode);
+            assert_eq!(netns.run(current_netns_inode).unwrap(), netns_inode);
+            // Running something in the namespace must not move the caller into it
+            assert_ne!(current_netns_inode(), netns_inode);
+        });
+    }
+
+    #[test]
+    fn test_set_dns_servers() {
+        in_user_namespace(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let etc_dir = dir.path().join("etc");
+            let netns =
+                NetNs::create_or_open_in(&dir.path().join("run"), &etc_dir, "test").unwrap();
+
+            netns
+                .set_dns_servers(["10.64.0.1".parse().unwrap(), "fc00::1".parse().unwrap()])
+                .unwrap();
+
+            assert_eq!(
+                fs::read_to_string(etc_dir.join("test").join("resolv.conf")).unwrap(),
+                "nameserver 10.64.0.1\nnameserver fc00::1\n"
+            );
+        });
+    }
+
+    #[test]
+    fn test_check_root_owned() {
+        in_user_namespace(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join(RESOLV_CONF);
+            fs::write(&path, "nameserver 10.64.0.1\n").unwrap();
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            check_root_owned(&path).unwrap();
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o666)).unwrap();
+            assert!(matches!(
+                check_root_owned(&path),
+                Err(Error::InsecureConfig(_))
+            ));
+
+            let link = dir.path().join("link");
+            std::os::unix::fs::symlink(&path, &link).unwrap();
+            assert!(matches!(
+                check_root_owned(&link),
+                Err(Error::InsecureConfig(_))
+            ));
+        });
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        for name in ["", ".", "..", "../foo", "foo/bar", "foo\0"] {
+            assert!(
+                matches!(validate_name(name), Err(Error::InvalidName(_))),
+                "{name:?} should be rejected"
+            );
+        }
+        validate_name("mullvad-tunnel").unwrap();
+    }
+}
@@ -21,7 +21,7 @@ use futures::{
     future::FutureExt,
 };
 use ipnetwork::IpNetwork;
-use libc::{RT_TABLE_COMPAT, RT_TABLE_MAIN};
+use libc::{RT_TABLE_COMPAT, RT_TABLE_LOCAL, RT_TABLE_MAIN};
 use netlink_packet_route::{
     AddressFamily, RouteNetlinkMessage,
     link::{LinkAttribute, LinkLayerType, LinkMessage},
@@ -73,6 +73,210 @@ fn all_rules(fwmark: u32, table: u32) -> [RuleMessage; 4] {
     ]
 }
 
+/// Priority of the routing rules created by [trusted_route_rule]. The priority is fixed so that
+/// rules left behind by a previous instance, e.g. after a crash, can be found and removed. It has
+/// to be lower than the priority the kernel assigns to the rules in [all_rules], which are added
+/// without one.
+const TRUSTED_RULE_PRIORITY: u32 = 100;
+
+/// Create a rule that looks up destinations in `prefix` in `table`. This lets a route of a trusted
+/// interface that is kept in another table, such as the one used by Tailscale, take precedence
+/// over the tunnel, without affecting other routes in that table.
+fn trusted_route_rule(prefix: IpNetwork, table: u32) -> RuleMessage {
+    let mut rule_msg = RuleMessage::default();
+    let family = match prefix {
+        IpNetwork::V4(_) => AddressFamily::Inet,
+        IpNetwork::V6(_) => AddressFamily::Inet6,
+    };
+    let header = RuleHeader {
+        family,
+        dst_len: prefix.prefix(),
+        action: RuleAction::ToTable, // FR_ACT_TO_TBL
+        ..RuleHeader::default()
+    };
+    let attributes = vec![
+        RuleAttribute::Priority(TRUSTED_RULE_PRIORITY),
+        RuleAttribute::Destination(prefix.network()),
+        RuleAttribute::Table(table),
+    ];
+
+    rule_msg.header = header;
+    rule_msg.attributes = attributes;
+    rule_msg
+}
+
+/// Priority of the routing rules created by [exception_route_rule]. Like
+/// [TRUSTED_RULE_PRIORITY], it is fixed so that rules left behind by a previous instance can be
+/// found and removed. The rules only match marked traffic, so they do not interfere with trusted
+/// route rules.
+const EXCEPTION_RULE_PRIORITY: u32 = 99;
+
+/// Create a rule that looks up marked packets destined for `destination` in `table`, which holds
+/// the routes through the interfaces that firewall exceptions are restricted to.
+fn exception_route_rule(destination: IpNetwork, fwmark: u32, table: u32) -> RuleMessage {
+    let mut rule_msg = trusted_route_rule(destination, table);
+    rule_msg.attributes = vec![
+        RuleAttribute::Priority(EXCEPTION_RULE_PRIORITY),
+        RuleAttribute::Destination(destination.network()),
+        RuleAttribute::FwMark(fwmark),
+        RuleAttribute::Table(table),
+    ];
+    rule_msg
+}
+
+/// Return whether `rule` was created by [exception_route_rule], possibly by a previous instance.
+fn is_exception_route_rule(rule: &RuleMessage) -> bool {
+    rule.header.action == RuleAction::ToTable
+        && rule
+            .attributes
+            .contains(&RuleAttribute::Priority(EXCEPTION_RULE_PRIORITY))
+        && rule
+            .attributes
+            .iter()
+            .any(|nla| matches!(nla, RuleAttribute::FwMark(_)))
+}
+
+/// Return routes in `table` that send traffic to the destination of each exception through the
+/// interface it is restricted to. Each route uses the gateway of the most specific route in the
+/// main table through that interface which covers the destination. Exceptions that the interface
+/// has no such route for are skipped, so their traffic is not routed through the interface.
+fn exception_routes(
+    routes: &[Route],
+    exceptions: &[(IpNetwork, String)],
+    table: u32,
+) -> HashSet<Route> {
+    exceptions
+        .iter()
+        .filter_map(|(destination, interface)| {
+            routes
+                .iter()
+                .filter(|route| route.table_id == u32::from(RT_TABLE_MAIN))
+                .filter(|route| route.node.get_device() == Some(interface.as_str()))
+                .filter(|route| {
+                    route.prefix.prefix() <= destination.prefix()
+                        && route.prefix.contains(destination.network())
+                })
+                .max_by_key(|route| route.prefix.prefix())
+                .map(|route| Route::new(route.node.clone(), *destination).table(table))
+        })
+        .collect()
+}
+
+/// Return whether `rule` was created by [trusted_route_rule], possibly by a previous instance.
+fn is_trusted_route_rule(rule: &RuleMessage) -> bool {
+    rule.header.action == RuleAction::ToTable
+        && rule
+            .attributes
+            .contains(&RuleAttribute::Priority(TRUSTED_RULE_PRIORITY))
+        && rule
+            .attributes
+            .iter()
+            .any(|nla| matches!(nla, RuleAttribute::Destination(_)))
+}
+
+/// Return the prefixes and tables of the routes through any of `trusted_interfaces` that should
+/// take precedence over the tunnel. Default routes and routes in the main table, the local table
+/// and `own_table` are excluded.
+fn trusted_routes(
+    routes: &[Route],
+    trusted_interfaces: &[String],
+    own_table: u32,
+) -> HashSet<(IpNetwork, u32)> {
+    routes
+        .iter()
+        .filter(|route| is_trusted_table(route.table_id, own_table) && route.prefix.prefix() > 0)
+        .filter(|route| {
+            route
+                .node
+                .get_device()
+                .is_some_and(|device| trusted_interfaces.iter().any(|name| name == device))
+        })
+        .map(|route| (route.prefix, route.table_id))
+        .collect()
+}
+
+/// Return whether routes in `table` may be preserved for trusted interfaces. Routes in the main
+/// table already take precedence over the tunnel.
+fn is_trusted_table(table: u32, own_table: u32) -> bool {
+    table != u32::from(RT_TABLE_MAIN) && table != u32::from(RT_TABLE_LOCAL) && table != own_table
+}
+
+/// Return whether `found_rule` is the rule `rule`, as it was added by the route manager.
+fn is_same_rule(found_rule: &RuleMessage, rule: &RuleMessage) -> bool {
+    // `RTM_DELRULE` is way too picky about which rules are considered the same.
+    // So ignore irrelevant attributes.
+    found_rule.header.family == rule.header.family
+        && found_rule.header.action == rule.header.action
+        && (found_rule.header.flags & rule.header.flags) == rule.header.flags
+        && rule
+            .attributes
+            .iter()
+            .all(|nla| found_rule.attributes.contains(nla))
+}
+
+/// Return the rules in `rules` that refer to `fwmark`, `table` or `exception_table`, except for
+/// those that the route manager adds itself.
+fn conflicting_rules(
+    rules: &[RuleMessage],
+    fwmark: u32,
+    table: u32,
+    exception_table: u32,
+) -> Vec<&RuleMessage> {
+    let own_rules = all_rules(fwmark, table);
+    let is_own_exception_rule = |rule: &RuleMessage| {
+        is_exception_route_rule(rule)
+            && rule.attributes.contains(&RuleAttribute::FwMark(fwmark))
+            && rule
+                .attributes
+                .contains(&RuleAttribute::Table(exception_table))
+    };
+    rules
+        .iter()
+        .filter(|rule| {
+            let uses_ids = [table, exception_table].contains(&u32::from(rule.header.table))
+                || rule.attributes.iter().any(|nla| match nla {
+                    RuleAttribute::FwMark(mark) => *mark == fwmark,
+                    RuleAttribute::Table(id) => *id == table || *id == exception_table,
+                    _ => false,
+                });
+            uses_ids
+                && !is_own_exception_rule(rule)
+                && !own_rules
+                    .iter()
+                    .any(|own_rule| is_same_rule(rule, own_rule))
+        })
+        .collect()
+}
+
+/// Describe a routing rule roughly the way `ip rule` would.
+fn describe_rule(rule: &RuleMessage) -> String {
+    let family = match rule.header.family {
+        AddressFamily::Inet6 => "ipv6",
+        _ => "ipv4",
+    };
+    let mut priority = None;
+    let mut selector = String::from("from all");
+    let mut table = u32::from(rule.header.table);
+    for nla in &rule.attributes {
+        match nla {
+            RuleAttribute::Priority(value) => priority = Some(*value),
+            RuleAttribute::FwMark(mark) if rule.header.flags.contains(RuleFlags::Invert) => {
+                selector.push_str(&format!(" not fwmark {mark:#x}"))
+            }
+            RuleAttribute::Destination(ip) => {
+                selector.push_str(&format!(" to {ip}/{}", rule.header.dst_len))
+            }
+            RuleAttribute::FwMark(mark) => selector.push_str(&format!(" fwmark {mark:#x}")),
+            RuleAttribute::Table(id) => table = *id,
+            _ => (),
+        }
+    }
+    match priority {
+        Some(priority) => format!("{family} {priority}: {selector} lookup {table}"),
+        None => format!("{family} {selector} lookup {table}"),
+    }
+}
+
 /// Create a routing rule that directs IPv4 packets without
 /// `fwmark` set to look up routes from the routing table `table`.
 fn no_fwmark_rule_v4(fwmark: u32, table: u32) -> RuleMessage {
@@ -155,13 +359,25 @@ pub struct RouteManagerImpl {
 
     /// Tunnel specific routing table, traffic not marked will be routed via this routing table.
     table_id: u32,
+    /// Routing table for marked traffic that firewall exceptions restrict to an interface.
+    exception_table_id: u32,
     /// Firewall mark identifies traffic which shouldn't be routed via the tunnel routing table. It
     /// is used to construct a routing rule.
     fwmark: u32,
+    /// Interfaces of other VPN or mesh clients whose routes take precedence over the tunnel.
+    trusted_interfaces: Vec<String>,
+    /// Routes through trusted interfaces that routing rules have been added for.
+    trusted_routes: HashSet<(IpNetwork, u32)>,
+    /// Destinations of firewall exceptions, and the interfaces they are restricted to.
+    exceptions: Vec<(IpNetwork, String)>,
+    /// Routes in the exception table that routing rules have been added for.
+    exception_routes: HashSet<Route>,
+    /// Whether IPv6 routing rules are enabled, or `None` if no routing rules have been created.
+    rules_ipv6: Option<bool>,
 }
 
 impl RouteManagerImpl {
-    pub async fn new(table_id: u32, fwmark: u32) -> Result<Self> {
+    pub async fn new(table_id: u32, exception_table_id: u32, fwmark: u32) -> Result<Self> {
         let (mut connection, handle, messages) =
             rtnetlink::new_connection().map_err(Error::Connect)?;
 
@@ -184,7 +400,13 @@ impl RouteManagerImpl {
             listeners: vec![],
             added_routes: HashSet::new(),
             table_id,
+            exception_table_id,
             fwmark,
+            trusted_interfaces: vec![],
+            trusted_routes: HashSet::new(),
+            exceptions: vec![],
+            exception_routes: HashSet::new(),
+            rules_ipv6: None,
         };
 
         monitor.clear_routing_rules().await?;
@@ -196,63 +418,197 @@ impl RouteManagerImpl {
         self.clear_routing_rules().await?;
 
         for rule in all_rules(self.fwmark, self.table_id)
-            .iter()
+            .into_iter()
             .filter(|rule| rule.header.family == AddressFamily::Inet || enable_ipv6)
         {
-            let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewRule((*rule).clone()));
-            req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_REPLACE;
+            self.add_rule(rule).await?;
+        }
+        self.rules_ipv6 = Some(enable_ipv6);
 
-            let mut response = self.handle.request(req).map_err(Error::Netlink)?;
+        if !self.trusted_interfaces.is_empty() {
+            let routes = self.get_routes().await?;
+            for (prefix, table) in trusted_routes(&routes, &self.trusted_interfaces, self.table_id)
+            {
+                self.add_trusted_route_rule(prefix, table).await?;
+            }
+        }
+        self.update_exception_routes().await
+    }
 
-            while let Some(message) = response.next().await {
-                if let NetlinkPayload::Error(error) = message.payload {
-                    return Err(Error::Netlink(rtnetlink::Error::NetlinkError(error)));
-                }
+    async fn add_rule(&mut self, rule: RuleMessage) -> Result<()> {
+        let mut req = NetlinkMessage::from(RouteNetlinkMessage::NewRule(rule));
+        req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_REPLACE;
+
+        let mut response = self.handle.request(req).map_err(Error::Netlink)?;
+
+        while let Some(message) = response.next().await {
+            if let NetlinkPayload::Error(error) = message.payload {
+                return Err(Error::Netlink(rtnetlink::Error::NetlinkError(error)));
             }
         }
         Ok(())
     }
 
+    /// Let the route to `prefix` in `table` take precedence over the tunnel.
+    async fn add_trusted_route_rule(&mut self, prefix: IpNetwork, table: u32) -> Result<()> {
+        if prefix.is_ipv6() && !self.rules_ipv6.unwrap_or(false) {
+            return Ok(());
+        }
+        log::debug!("Preserving trusted route to {prefix} in routing table {table}");
+        self.add_rule(trusted_route_rule(prefix, table)).await?;
+        self.trusted_routes.insert((prefix, table));
+        Ok(())
+    }
+
+    /// Set the interfaces whose routes take precedence over the tunnel. The routing rules are
+    /// updated if they have been created.
+    async fn set_trusted_interfaces(&mut self, trusted_interfaces: Vec<String>) -> Result<()> {
+        self.trusted_interfaces = trusted_interfaces;
+        match self.rules_ipv6 {
+            Some(enable_ipv6) => self.create_routing_rules(enable_ipv6).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Route marked traffic to the destination of each exception through the interface it is
+    /// restricted to. The routing rules are updated if they have been created.
+    async fn set_exceptions(&mut self, exceptions: Vec<(IpNetwork, String)>) -> Result<()> {
+        self.exceptions = exceptions;
+        self.update_exception_routes().await
+    }
+
+    /// Bring the routes in the exception table, and the rules that refer to them, in line with
+    /// the exceptions and the routes in the main table. There are none while the routing rules
+    /// have not been created.
+    async fn update_exception_routes(&mut self) -> Result<()> {
+        let wanted_routes = match self.rules_ipv6 {
+            Some(enable_ipv6) if !self.exceptions.is_empty() => {
+                let routes = self.get_routes().await?;
+                exception_routes(&routes, &self.exceptions, self.exception_table_id)
+                    .into_iter()
+                    .filter(|route| route.prefix.is_ipv4() || enable_ipv6)
+                    .collect()
+            }
+            _ => HashSet::new(),
+        };
+
+        let stale_routes: Vec<_> = self
+            .exception_routes
+            .difference(&wanted_routes)
+            .cloned()
+            .collect();
+        for route in stale_routes {
+            self.delete_rule_if_exists(exception_route_rule(
+                route.prefix,
+                self.fwmark,
+                self.exception_table_id,
+            ))
+            .await?;
+            self.delete_route_if_exists(&route).await?;
+            self.exception_routes.remove(&route);
+        }
+
+        let new_routes: Vec<_> = wanted_routes
+            .difference(&self.exception_routes)
+            .cloned()
+            .collect();
+        for route in new_routes {
+            log::debug!("Routing firewall exception to {route}");
+            self.add_route_direct(route.clone()).await?;
+            self.add_rule(exception_route_rule(
+                route.prefix,
+                self.fwmark,
+                self.exception_table_id,
+            ))
+            .await?;
+            self.exception_routes.insert(route);
+        }
+        Ok(())
+    }
+
+    /// Update the exception routes if `route` is a route in the main table through an interface
+    /// that an exception is restricted to.
+    async fn follow_exception_route(&mut self, route: &Route) -> Result<()> {
+        let is_exception_interface = route.node.get_device().is_some_and(|device| {
+            self.exceptions
+                .iter()
+                .any(|(_, interface)| interface == device)
+        });
+        if route.table_id == u32::from(RT_TABLE_MAIN) && is_exception_interface {
+            self.update_exception_routes().await?;
+        }
+        Ok(())
+    }
+
+    /// Preserve `route` if it is a new route through a trusted interface.
+    async fn preserve_trusted_route(&mut self, route: &Route) -> Result<()> {
+        if self.rules_ipv6.is_none() {
+            return Ok(());
+        }
+        for (prefix, table) in trusted_routes(
+            std::slice::from_ref(route),
+            &self.trusted_interfaces,
+            self.table_id,
+        ) {
+            if !self.trusted_routes.contains(&(prefix, table)) {
+                self.add_trusted_route_rule(prefix, table).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the routing rule that preserves `route`, if there is one.
+    async fn forget_trusted_route(&mut self, route: &Route) -> Result<()> {
+        if self.trusted_routes.remove(&(route.prefix, route.table_id)) {
+            log::debug!(
+                "Trusted route to {} in routing table {} was removed",
+                route.prefix,
+                route.table_id
+            );
+            self.delete_rule_if_exists(trusted_route_rule(route.prefix, route.table_id))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Remove the routing rules added by the route manager. This includes rules for trusted
+    /// routes that a previous instance did not remove.
     async fn clear_routing_rules(&mut self) -> Result<()> {
         let rules = self.get_rules().await?;
+        self.trusted_routes.clear();
+        self.rules_ipv6 = None;
+        for route in std::mem::take(&mut self.exception_routes) {
+            self.delete_route_if_exists(&route).await?;
+        }
         for rule in all_rules(self.fwmark, self.table_id) {
-            let mut matching_rule = None;
-
-            // `RTM_DELRULE` is way too picky about which rules are considered the same.
-            // So iterate over all rules and ignore irrelevant attributes.
-            for found_rule in &rules {
-                // Match header
-                if found_rule.header.family != rule.header.family {
-                    continue;
-                }
-                if found_rule.header.action != rule.header.action {
-                    continue;
-                }
-                if (found_rule.header.flags & rule.header.flags) != rule.header.flags {
-                    continue;
-                }
-                // Match NLAs
-                let mut contains_nlas = true;
-                for nla in &rule.attributes {
-                    if !found_rule.attributes.contains(nla) {
-                        contains_nlas = false;
-                        break;
-                    }
-                }
-                if contains_nlas {
-                    log::trace!("Existing routing rule matched: {:?}", found_rule);
-                    matching_rule = Some(found_rule);
-                    break;
-                }
-            }
-
+            let matching_rule = rules
+                .iter()
+                .find(|found_rule| is_same_rule(found_rule, &rule));
             if let Some(rule) = matching_rule {
-                self.delete_rule_if_exists((*rule).clone()).await?;
+                log::trace!("Existing routing rule matched: {:?}", rule);
+                self.delete_rule_if_exists(rule.clone()).await?;
             }
         }
+        for rule in rules
+            .iter()
+            .filter(|rule| is_trusted_route_rule(rule) || is_exception_route_rule(rule))
+        {
+            log::trace!("Existing trusted routing rule matched: {:?}", rule);
+            self.delete_rule_if_exists(rule.clone()).await?;
+        }
         Ok(())
     }
 
+    async fn find_conflicting_rules(&mut self) -> Result<Vec<String>> {
+        let rules = self.get_rules().await?;
+        Ok(
+            conflicting_rules(&rules, self.fwmark, self.table_id, self.exception_table_id)
+                .into_iter()
+                .map(describe_rule)
+                .collect(),
+        )
+    }
+
     async fn get_rules(&mut self) -> Result<Vec<RuleMessage>> {
         let mut req = NetlinkMessage::from(RouteNetlinkMessage::GetRule(RuleMessage::default()));
         req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_DUMP;
@@ -275,6 +631,36 @@ impl RouteManagerImpl {
         Ok(rules)
     }
 
+    /// Return all IPv4 and IPv6 routes in every routing table. Routes that cannot be parsed are
+    /// skipped.
+    async fn get_routes(&mut self) -> Result<Vec<Route>> {
+        let mut routes = vec![];
+
+        for family in [AddressFamily::Inet, AddressFamily::Inet6] {
+            let mut message = RouteMessage::default();
+            message.header.address_family = family;
+            let mut req = NetlinkMessage::from(RouteNetlinkMessage::GetRoute(message));
+            req.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_DUMP;
+
+            let mut response = self.handle.request(req).map_err(Error::Netlink)?;
+
+            while let Some(message) = response.next().await {
+                match message.payload {
+                    NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(route)) => {
+                        if let Ok(Some(route)) = self.parse_route_message(route) {
+                            routes.push(route);
+                        }
+                    }
+                    NetlinkPayload::Error(error) => {
+                        return Err(Error::Netlink(rtnetlink::Error::NetlinkError(error)));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        Ok(routes)
+    }
+
     async fn delete_rule_if_exists(&mut self, rule: RuleMessage) -> Result<()> {
         let mut req = NetlinkMessage::from(RouteNetlinkMessage::DelRule(rule));
         req.header.flags = NLM_F_REQUEST | NLM_F_ACK;
@@ -292,22 +678,10 @@ impl RouteManagerImpl {
     }
 
     async fn add_required_routes(&mut self, required_routes: HashSet<RequiredRoute>) -> Result<()> {
-        let mut required_normal_routes = HashSet::new();
-
-        for route in required_routes {
-            match route.node {
-                NetNode::RealNode(node) => {
-                    let table = if route.main_table {
-                        RT_TABLE_MAIN.into()
-                    } else {
-                        self.table_id
-                    };
-                    let mut new_route = Route::new(node, route.prefix).table(table);
-                    new_route.mtu = route.mtu.map(u32::from);
-                    required_normal_routes.insert(new_route);
-                }
-            }
-        }
+        let required_normal_routes: HashSet<_> = required_routes
+            .into_iter()
+            .map(|route| self.to_route(route))
+            .collect();
 
         for normal_route in required_normal_routes.into_iter() {
             self.add_route(normal_route).await?;
@@ -316,6 +690,34 @@ impl RouteManagerImpl {
         Ok(())
     }
 
+    /// Remove routes previously added by [Self::add_required_routes].
+    async fn delete_required_routes(&mut self, required_routes: HashSet<RequiredRoute>) {
+        for route in required_routes {
+            let route = self.to_route(route);
+            if !self.added_routes.remove(&route) {
+                continue;
+            }
+            if let Err(e) = self.delete_route_if_exists(&route).await {
+                log::error!("Failed to remove route: {}: {}", route, e);
+            }
+        }
+    }
+
+    fn to_route(&self, route: RequiredRoute) -> Route {
+        match route.node {
+            NetNode::RealNode(node) => {
+                let table = if route.main_table {
+                    RT_TABLE_MAIN.into()
+                } else {
+                    self.table_id
+                };
+                let mut new_route = Route::new(node, route.prefix).table(table);
+                new_route.mtu = route.mtu.map(u32::from);
+                new_route
+            }
+        }
+    }
+
     async fn initialize_link_map(
         handle: &rtnetlink::Handle,
     ) -> Result<BTreeMap<u32, NetworkInterface>> {
@@ -361,7 +763,7 @@ impl RouteManagerImpl {
                     self.process_command(command).await?;
                 },
                 (route_change, _socket) = self.messages.select_next_some().fuse() => {
-                    if let Err(error) = self.process_netlink_message(route_change) {
+                    if let Err(error) = self.process_netlink_message(route_change).await {
                         log::error!("{}", error.display_chain_with_msg("Failed to process netlink message"));
                     }
                 }
@@ -382,12 +784,26 @@ impl RouteManagerImpl {
                 log::debug!("Adding routes: {:?}", routes);
                 let _ = result_tx.send(self.add_required_routes(routes.clone()).await);
             }
+            RouteManagerCommand::DeleteRoutes(routes, result_tx) => {
+                log::debug!("Deleting routes: {:?}", routes);
+                self.delete_required_routes(routes).await;
+                let _ = result_tx.send(());
+            }
             RouteManagerCommand::CreateRoutingRules(enable_ipv6, result_tx) => {
                 let _ = result_tx.send(self.create_routing_rules(enable_ipv6).await);
             }
             RouteManagerCommand::ClearRoutingRules(result_tx) => {
                 let _ = result_tx.send(self.clear_routing_rules().await);
             }
+            RouteManagerCommand::FindConflictingRules(result_tx) => {
+                let _ = result_tx.send(self.find_conflicting_rules().await);
+            }
+            RouteManagerCommand::SetTrustedInterfaces(trusted_interfaces, result_tx) => {
+                let _ = result_tx.send(self.set_trusted_interfaces(trusted_interfaces).await);
+            }
+            RouteManagerCommand::SetExceptions(exceptions, result_tx) => {
+                let _ = result_tx.send(self.set_exceptions(exceptions).await);
+            }
             RouteManagerCommand::NewChangeListener(result_tx) => {
                 let _ = result_tx.send(self.listen());
             }
@@ -405,7 +821,10 @@ impl RouteManagerImpl {
         Ok(())
     }
 
-    fn process_netlink_message(&mut self, msg: NetlinkMessage<RouteNetlinkMessage>) -> Result<()> {
+    async fn process_netlink_message(
+        &mut self,
+        msg: NetlinkMessage<RouteNetlinkMessage>,
+    ) -> Result<()> {
         match msg.payload {
             NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewLink(new_link)) => {
                 if let Some((idx, name)) = Self::map_interface(new_link) {
@@ -419,12 +838,36 @@ impl RouteManagerImpl {
             }
             NetlinkPayload::InnerMessage(RouteNetlinkMessage::NewRoute(new_route)) => {
                 if let Some(addition) = self.parse_route_message(new_route)? {
+                    if let Err(error) = self.preserve_trusted_route(&addition).await {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to preserve trusted route")
+                        );
+                    }
+                    if let Err(error) = self.follow_exception_route(&addition).await {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to update exception routes")
+                        );
+                    }
                     self.notify_change_listeners(CallbackMessage::NewRoute(addition));
                 }
             }
             NetlinkPayload::InnerMessage(RouteNetlinkMessage::DelRoute(old_route)) => {
                 if let Some(deletion) = self.parse_route_message(old_route)? {
                     self.process_deleted_route(&deletion)?;
+                    if let Err(error) = self.forget_trusted_route(&deletion).await {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to remove trusted route rule")
+                        );
+                    }
+                    if let Err(error) = self.follow_exception_route(&deletion).await {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to update exception routes")
+                        );
+                    }
                     self.notify_change_listeners(CallbackMessage::DelRoute(deletion));
                 }
             }
@@ -605,16 +1048,23 @@ impl RouteManagerImpl {
 
     async fn delete_route(&self, route: &Route) -> Result<()> {
         let compat_table = compat_table_id(route.table_id);
+        // Routes through a gateway cannot have link scope
         let scope = match route.prefix {
             IpNetwork::V4(v4_prefix) => {
-                if v4_prefix.prefix() > 0 && v4_prefix.prefix() < 32 {
+                if route.node.get_address().is_none()
+                    && v4_prefix.prefix() > 0
+                    && v4_prefix.prefix() < 32
+                {
                     RouteScope::Link // RT_SCOPE_LINK
                 } else {
                     RouteScope::Universe // RT_SCOPE_UNIVERSE
                 }
             }
             IpNetwork::V6(v6_prefix) => {
-                if v6_prefix.prefix() > 0 && v6_prefix.prefix() < 128 {
+                if route.node.get_address().is_none()
+                    && v6_prefix.prefix() > 0
+                    && v6_prefix.prefix() < 128
+                {
                     RouteScope::Link // RT_SCOPE_LINK
                 } else {
                     RouteScope::Universe // RT_SCOPE_UNIVERSE
@@ -673,13 +1123,18 @@ impl RouteManagerImpl {
             .map_err(Error::Netlink)
     }
 
+    /// Add `route` without keeping track of it. Routes through a gateway are given universe
+    /// scope, since the kernel rejects gateways for routes with link scope.
     async fn add_route_direct(&mut self, route: Route) -> Result<()> {
         let add_message = match &route.prefix {
             IpNetwork::V4(v4_prefix) => {
                 let mut add_message = RouteMessageBuilder::<Ipv4Addr>::new()
                     .destination_prefix(v4_prefix.ip(), v4_prefix.prefix());
 
-                if v4_prefix.prefix() > 0 && v4_prefix.prefix() < 32 {
+                if route.node.get_address().is_none()
+                    && v4_prefix.prefix() > 0
+                    && v4_prefix.prefix() < 32
+                {
                     add_message = add_message.scope(RouteScope::Link); // RT_SCOPE_LINK
                 }
 
@@ -721,7 +1176,10 @@ impl RouteManagerImpl {
                 let mut add_message = RouteMessageBuilder::<Ipv6Addr>::new()
                     .destination_prefix(v6_prefix.ip(), v6_prefix.prefix());
 
-                if v6_prefix.prefix() > 0 && v6_prefix.prefix() < 128 {
+                if route.node.get_address().is_none()
+                    && v6_prefix.prefix() > 0
+                    && v6_prefix.prefix() < 128
+                {
                     add_message = add_message.scope(RouteScope::Link); // RT_SCOPE_LINK
                 }
 
@@ -957,10 +1415,167 @@ mod test {
     fn test_drop() {
         let runtime = tokio::runtime::Runtime::new().expect("Failed to initialize runtime");
         let manager = runtime.block_on(async {
-            RouteManagerImpl::new(1000, 1000)
+            RouteManagerImpl::new(1000, 1001, 1000)
                 .await
                 .expect("Failed to initialize route manager")
         });
         std::mem::drop(manager);
     }
+
+    #[test]
+    fn test_conflicting_rules() {
+        const FWMARK: u32 = 0x6d6f6c65;
+        const TABLE: u32 = 0x6d6f6c65;
+        const EXCEPTION_TABLE: u32 = 0x6d6f6c66;
+
+        let mut foreign_fwmark_rule = RuleMessage::default();
+        foreign_fwmark_rule.header.family = AddressFamily::Inet;
+        foreign_fwmark_rule.header.action = RuleAction::ToTable;
+        foreign_fwmark_rule.attributes = vec![
+            RuleAttribute::Priority(5270),
+            RuleAttribute::FwMark(FWMARK),
+            RuleAttribute::Table(52),
+        ];
+        let mut foreign_table_rule = RuleMessage::default();
+        foreign_table_rule.header.family = AddressFamily::Inet6;
+        foreign_table_rule.header.action = RuleAction::ToTable;
+        foreign_table_rule.attributes = vec![RuleAttribute::Table(TABLE)];
+        let mut foreign_exception_table_rule = foreign_table_rule.clone();
+        foreign_exception_table_rule.attributes = vec![RuleAttribute::Table(EXCEPTION_TABLE)];
+        let mut unrelated_rule = foreign_fwmark_rule.clone();
+        unrelated_rule.attributes = vec![RuleAttribute::FwMark(0x80000), RuleAttribute::Table(52)];
+
+        let mut rules = all_rules(FWMARK, TABLE).to_vec();
+        // The kernel adds a priority to every rule
+        for rule in &mut rules {
+            rule.attributes.push(RuleAttribute::Priority(32764));
+        }
+        rules.extend([
+            exception_route_rule("192.0.2.0/24".parse().unwrap(), FWMARK, EXCEPTION_TABLE),
+            foreign_fwmark_rule.clone(),
+            foreign_table_rule.clone(),
+            foreign_exception_table_rule.clone(),
+            unrelated_rule,
+        ]);
+
+        let conflicts = conflicting_rules(&rules, FWMARK, TABLE, EXCEPTION_TABLE);
+        assert_eq!(
+            conflicts,
+            vec![
+                &foreign_fwmark_rule,
+                &foreign_table_rule,
+                &foreign_exception_table_rule
+            ]
+        );
+        assert_eq!(
+            describe_rule(conflicts[0]),
+            "ipv4 5270: from all fwmark 0x6d6f6c65 lookup 52"
+        );
+        assert_eq!(
+            describe_rule(conflicts[1]),
+            "ipv6 from all lookup 1836018789"
+        );
+    }
+
+    #[test]
+    fn test_trusted_routes() {
+        const TABLE: u32 = 0x6d6f6c65;
+
+        let route = |device: &str, prefix: &str, table: u32| {
+            Route::new(Node::device(device.to_owned()), prefix.parse().unwrap()).table(table)
+        };
+        let routes = [
+            route("tailscale0", "100.64.0.0/10", 52),
+            route("tailscale0", "fd7a:115c:a1e0::/48", 52),
+            route("tailscale0", "0.0.0.0/0", 52),
+            route("eth0", "192.168.2.0/24", 52),
+            route("zt0", "10.147.17.0/24", RT_TABLE_MAIN.into()),
+            route("wg0-mullvad", "0.0.0.0/0", TABLE),
+            route("eth0", "192.168.1.0/24", 100),
+        ];
+        let trusted_interfaces = ["tailscale0".to_owned(), "zt0".to_owned()];
+
+        assert_eq!(
+            trusted_routes(&routes, &trusted_interfaces, TABLE),
+            HashSet::from([
+                ("100.64.0.0/10".parse().unwrap(), 52),
+                ("fd7a:115c:a1e0::/48".parse().unwrap(), 52),
+            ])
+        );
+        assert!(trusted_routes(&routes, &[], TABLE).is_empty());
+    }
+
+    #[test]
+    fn test_trusted_route_rule() {
+        let rule = trusted_route_rule("100.64.0.0/10".parse().unwrap(), 52);
+        assert_eq!(rule.header.family, AddressFamily::Inet);
+        assert_eq!(rule.header.dst_len, 10);
+        assert!(is_trusted_route_rule(&rule));
+        assert_eq!(
+            describe_rule(&rule),
+            format!("ipv4 {TRUSTED_RULE_PRIORITY}: from all to 100.64.0.0/10 lookup 52")
+        );
+
+        let rule = trusted_route_rule("fd7a:115c:a1e0::/48".parse().unwrap(), 52);
+        assert_eq!(rule.header.family, AddressFamily::Inet6);
+        assert!(is_trusted_route_rule(&rule));
+
+        // Rules of other programs and the route manager's own rules must not be removed
+        let mut foreign_rule = trusted_route_rule("100.64.0.0/10".parse().unwrap(), 52);
+        foreign_rule.attributes[0] = RuleAttribute::Priority(5270);
+        assert!(!is_trusted_route_rule(&foreign_rule));
+        assert!(!is_trusted_route_rule(&SUPPRESS_RULE_V4));
+        assert!(!is_trusted_route_rule(&no_fwmark_rule_v4(1, 2)));
+    }
+
+    #[test]
+    fn test_exception_routes() {
+        const TABLE: u32 = 0x6d6f6c66;
+
+        let main = u32::from(RT_TABLE_MAIN);
+        let route = |node: Node, prefix: &str, table: u32| {
+            Route::new(node, prefix.parse().unwrap()).table(table)
+        };
+        let via =
+            |gateway: &str, device: &str| Node::new(gateway.parse().unwrap(), device.to_owned());
+        let routes = [
+            route(via("192.168.1.1", "eth0"), "0.0.0.0/0", main),
+            route(Node::device("eth0".to_owned()), "192.168.1.0/24", main),
+            route(via("10.0.0.1", "eth1"), "0.0.0.0/0", main),
+            route(Node::device("wlan0".to_owned()), "198.51.100.0/24", 100),
+        ];
+        let exceptions = [
+            ("203.0.113.0/24".parse().unwrap(), "eth1".to_owned()),
+            ("192.168.1.10/32".parse().unwrap(), "eth0".to_owned()),
+            // Exceptions are skipped if the interface has no route to the destination
+            ("198.51.100.0/25".parse().unwrap(), "wlan0".to_owned()),
+            ("2001:db8::/32".parse().unwrap(), "eth0".to_owned()),
+        ];
+
+        assert_eq!(
+            exception_routes(&routes, &exceptions, TABLE),
+            HashSet::from([
+                route(via("10.0.0.1", "eth1"), "203.0.113.0/24", TABLE),
+                route(Node::device("eth0".to_owned()), "192.168.1.10/32", TABLE),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_exception_route_rule() {
+        let rule = exception_route_rule("203.0.113.0/24".parse().unwrap(), 0x100, 52);
+        assert_eq!(rule.header.dst_len, 24);
+        assert!(is_exception_route_rule(&rule));
+        assert!(!is_trusted_route_rule(&rule));
+        assert_eq!(
+            describe_rule(&rule),
+            format!(
+                "ipv4 {EXCEPTION_RULE_PRIORITY}: from all to 203.0.113.0/24 fwmark 0x100 lookup 52"
+            )
+        );
+        assert!(!is_exception_route_rule(&trusted_route_rule(
+            "100.64.0.0/10".parse().unwrap(),
+            52
+        )));
+    }
 }
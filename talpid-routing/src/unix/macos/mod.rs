@@ -241,6 +241,11 @@ impl RouteManagerImpl {
                             log::debug!("Adding routes: {routes:?}");
                             let _ = tx.send(self.add_required_routes(routes).await);
                         }
+                        Some(RouteManagerCommand::DeleteRoutes(routes, tx)) => {
+                            log::debug!("Deleting routes: {routes:?}");
+                            self.delete_required_routes(routes).await;
+                            let _ = tx.send(());
+                        }
                         Some(RouteManagerCommand::ClearRoutes) => {
                             if let Err(err) = self.cleanup_routes().await {
                                 log::error!("Failed to clean up rotues: {err}");
@@ -371,6 +376,27 @@ impl RouteManagerImpl {
         Ok(())
     }
 
+    /// Remove routes previously added by [Self::add_required_routes]. Default routes are only
+    /// removed by [Self::cleanup_routes], since the non-tunnel default routes must be restored.
+    async fn delete_required_routes(&mut self, required_routes: HashSet<RequiredRoute>) {
+        let mut prefixes = HashSet::new();
+        for route in required_routes {
+            if route.prefix.prefix() == 0 {
+                log::warn!("Not deleting default route: {route:?}");
+                continue;
+            }
+            if route.node == NetNode::DefaultNode {
+                self.non_tunnel_routes.remove(&route.prefix);
+            }
+            prefixes.insert(route.prefix);
+        }
+
+        self.remove_applied_routes(
+            |route| matches!(route.destination_ip(), Ok(Some(dest)) if prefixes.contains(&dest)),
+        )
+        .await;
+    }
+
     fn handle_route_message(
         &mut self,
         message: std::result::Result<RouteSocketMessage, watch::Error>,
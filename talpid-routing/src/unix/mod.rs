@@ -19,6 +19,8 @@ use futures::stream::Stream;
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 use std::collections::HashSet;
 
+#[cfg(target_os = "linux")]
+use ipnetwork::IpNetwork;
 #[cfg(target_os = "linux")]
 use std::net::IpAddr;
 
@@ -87,10 +89,17 @@ pub(crate) enum RouteManagerCommand {
         HashSet<RequiredRoute>,
         oneshot::Sender<Result<(), PlatformError>>,
     ),
+    DeleteRoutes(HashSet<RequiredRoute>, oneshot::Sender<()>),
     ClearRoutes,
     Shutdown(oneshot::Sender<()>),
     CreateRoutingRules(bool, oneshot::Sender<Result<(), PlatformError>>),
     ClearRoutingRules(oneshot::Sender<Result<(), PlatformError>>),
+    FindConflictingRules(oneshot::Sender<Result<Vec<String>, PlatformError>>),
+    SetTrustedInterfaces(Vec<String>, oneshot::Sender<Result<(), PlatformError>>),
+    SetExceptions(
+        Vec<(IpNetwork, String)>,
+        oneshot::Sender<Result<(), PlatformError>>,
+    ),
     NewChangeListener(oneshot::Sender<mpsc::UnboundedReceiver<CallbackMessage>>),
     GetMtuForRoute(IpAddr, oneshot::Sender<Result<u16, PlatformError>>),
     /// Attempt to fetch a route for the given destination with an optional firewall mark.
@@ -118,6 +127,7 @@ pub(crate) enum RouteManagerCommand {
         HashSet<RequiredRoute>,
         oneshot::Sender<Result<(), PlatformError>>,
     ),
+    DeleteRoutes(HashSet<RequiredRoute>, oneshot::Sender<()>),
     ClearRoutes,
     Shutdown(oneshot::Sender<()>),
     RefreshRoutes,
@@ -163,6 +173,8 @@ pub enum CallbackMessage {
 #[derive(Debug, Clone)]
 pub struct RouteManagerHandle {
     tx: Arc<UnboundedSender<RouteManagerCommand>>,
+    #[cfg(target_os = "linux")]
+    fwmark: Fwmark,
 }
 
 impl RouteManagerHandle {
@@ -170,15 +182,18 @@ impl RouteManagerHandle {
     pub async fn spawn(
         #[cfg(target_os = "linux")] fwmark: u32,
         #[cfg(target_os = "linux")] table_id: u32,
+        #[cfg(target_os = "linux")] exception_table_id: u32,
         #[cfg(target_os = "android")] android_context: AndroidContext,
     ) -> Result<Self, Error> {
         let (manage_tx, manage_rx) = mpsc::unbounded();
         let manage_tx = Arc::new(manage_tx);
         let manager = imp::RouteManagerImpl::new(
-            #[cfg(target_os = "linux")]
-            fwmark,
             #[cfg(target_os = "linux")]
             table_id,
+            #[cfg(target_os = "linux")]
+            exception_table_id,
+            #[cfg(target_os = "linux")]
+            fwmark,
             #[cfg(target_os = "macos")]
             Arc::downgrade(&manage_tx),
             #[cfg(target_os = "android")]
@@ -187,7 +202,17 @@ impl RouteManagerHandle {
         .await?;
         tokio::spawn(manager.run(manage_rx));
 
-        Ok(Self { tx: manage_tx })
+        Ok(Self {
+            tx: manage_tx,
+            #[cfg(target_os = "linux")]
+            fwmark,
+        })
+    }
+
+    /// The firewall mark of traffic that is not routed through the tunnel.
+    #[cfg(target_os = "linux")]
+    pub fn fwmark(&self) -> Fwmark {
+        self.fwmark
     }
 
     /// Stop route manager and revert all changes to routing
@@ -213,6 +238,18 @@ impl RouteManagerHandle {
             .map_err(Error::PlatformError)
     }
 
+    /// Removes the given routes, if they were previously applied in
+    /// [`RouteManagerHandle::add_routes`]. Failures to remove a route are logged.
+    #[cfg(not(target_os = "android"))]
+    pub async fn delete_routes(&self, routes: HashSet<RequiredRoute>) -> Result<(), Error> {
+        let (result_tx, result_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::DeleteRoutes(routes, result_tx))
+            .map_err(|_| Error::RouteManagerDown)?;
+
+        result_rx.await.map_err(|_| Error::ManagerChannelDown)
+    }
+
     /// Wait for routes to come up.
     ///
     /// This function is guaranteed to *not* wait for longer than 2 seconds.
@@ -345,6 +382,55 @@ impl RouteManagerHandle {
             .map_err(Error::PlatformError)
     }
 
+    /// Return a description of every routing rule that refers to the firewall mark or routing
+    /// table of the route manager, but was not created by it.
+    #[cfg(target_os = "linux")]
+    pub async fn find_conflicting_rules(&self) -> Result<Vec<String>, Error> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::FindConflictingRules(response_tx))
+            .map_err(|_| Error::RouteManagerDown)?;
+        response_rx
+            .await
+            .map_err(|_| Error::ManagerChannelDown)?
+            .map_err(Error::PlatformError)
+    }
+
+    /// Let the routes of `trusted_interfaces` take precedence over the tunnel, also when they
+    /// are kept in routing tables other than the main table.
+    #[cfg(target_os = "linux")]
+    pub async fn set_trusted_interfaces(
+        &self,
+        trusted_interfaces: Vec<String>,
+    ) -> Result<(), Error> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::SetTrustedInterfaces(
+                trusted_interfaces,
+                response_tx,
+            ))
+            .map_err(|_| Error::RouteManagerDown)?;
+        response_rx
+            .await
+            .map_err(|_| Error::ManagerChannelDown)?
+            .map_err(Error::PlatformError)
+    }
+
+    /// Route traffic that is marked with the firewall mark and destined for each of
+    /// `exceptions` through the interface given with it, instead of the interface that the main
+    /// routing table would select. This takes effect while the routing rules exist.
+    #[cfg(target_os = "linux")]
+    pub async fn set_exceptions(&self, exceptions: Vec<(IpNetwork, String)>) -> Result<(), Error> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::SetExceptions(exceptions, response_tx))
+            .map_err(|_| Error::RouteManagerDown)?;
+        response_rx
+            .await
+            .map_err(|_| Error::ManagerChannelDown)?
+            .map_err(Error::PlatformError)
+    }
+
     /// Listen for route changes.
     #[cfg(target_os = "linux")]
     pub async fn change_listener(
@@ -106,6 +106,7 @@ pub struct RouteManagerHandle {
 pub enum RouteManagerCommand {
     AddRoutes(HashSet<RequiredRoute>, oneshot::Sender<Result<()>>),
     GetMtuForRoute(IpAddr, oneshot::Sender<Result<u16>>),
+    DeleteRoutes(HashSet<RequiredRoute>, oneshot::Sender<()>),
     ClearRoutes,
     RegisterDefaultRouteChangeCallback(Callback, oneshot::Sender<CallbackHandle>),
     Shutdown(oneshot::Sender<()>),
@@ -147,6 +148,16 @@ impl RouteManagerHandle {
         response_rx.await.map_err(|_| Error::RouteManagerDown)?
     }
 
+    /// Removes the given routes, if they were previously applied in [`Self::add_routes`].
+    /// Failures to remove a route are logged.
+    pub async fn delete_routes(&self, routes: HashSet<RequiredRoute>) -> Result<()> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::DeleteRoutes(routes, response_tx))
+            .map_err(|_| Error::RouteManagerDown)?;
+        response_rx.await.map_err(|_| Error::RouteManagerDown)
+    }
+
     /// Retrieve MTU for the given destination/route.
     pub async fn get_mtu_for_route(&self, ip: IpAddr) -> Result<u16> {
         let (response_tx, response_rx) = oneshot::channel();
@@ -193,6 +204,17 @@ impl RouteManagerHandle {
                             .map_err(|e| Error::AddRoutesFailed(Box::new(e))),
                     );
                 }
+                RouteManagerCommand::DeleteRoutes(routes, tx) => {
+                    let routes: Vec<_> = routes
+                        .into_iter()
+                        .map(|route| Route {
+                            network: route.prefix,
+                            node: route.node,
+                        })
+                        .collect();
+                    internal.delete_routes(&routes);
+                    let _ = tx.send(());
+                }
                 RouteManagerCommand::GetMtuForRoute(ip, tx) => {
                     let addr_family = if ip.is_ipv4() {
                         AddressFamily::Ipv4
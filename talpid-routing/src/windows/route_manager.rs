@@ -438,6 +438,20 @@ impl RouteManagerInternal {
         Ok(())
     }
 
+    /// Delete the applied routes that match any of `routes`.
+    pub fn delete_routes(&self, routes: &[Route]) {
+        let mut records = self.routes.lock().unwrap();
+        records.retain(|record| {
+            let matches = routes.iter().any(|route| {
+                route.network == record.route.network && route.node == record.route.node
+            });
+            if matches && Self::delete_from_routing_table(&record.registered_route).is_err() {
+                log::error!("Failed to delete route {}", record.registered_route);
+            }
+            !matches
+        });
+    }
+
     pub fn register_default_route_changed_callback(&self, callback: Callback) -> CallbackHandle {
         let (nonce, callbacks) = &mut *self.callbacks.lock().unwrap();
         let old_nonce = *nonce;
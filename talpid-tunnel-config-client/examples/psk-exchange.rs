@@ -28,6 +28,7 @@ async fn main() {
         ephemeral_private_key.public_key(),
         true,  // Whether to negotiate a "PQ-safe" PSK.
         false, // Whether to use DAITA (Does not work with Linux kernel WireGuard.)
+        None,  // DAITA level. `None` lets the relay pick its default.
     )
     .await
     .unwrap();
@@ -6,7 +6,7 @@ use std::net::SocketAddr;
 #[cfg(not(target_os = "ios"))]
 use std::net::{IpAddr, Ipv4Addr};
 use std::time::Instant;
-use talpid_types::net::wireguard::{PresharedKey, PublicKey};
+use talpid_types::net::wireguard::{DaitaLevel, PresharedKey, PublicKey};
 use tonic::transport::Channel;
 #[cfg(not(target_os = "ios"))]
 use tonic::transport::Endpoint;
@@ -16,6 +16,8 @@ use zeroize::Zeroize;
 
 mod hqc;
 mod ml_kem;
+#[cfg(feature = "server")]
+pub mod server;
 #[cfg(not(target_os = "ios"))]
 mod socket;
 
@@ -115,6 +117,8 @@ pub struct EphemeralPeer {
 }
 
 pub struct DaitaSettings {
+    /// The level that was requested. `None` if the relay picked its default level.
+    pub level: Option<DaitaLevel>,
     pub client_machines: Vec<daita::Machine>,
     pub max_decoy_frac: f64,
     pub max_delay_frac: f64,
@@ -128,6 +132,7 @@ pub async fn request_ephemeral_peer(
     ephemeral_pubkey: PublicKey,
     enable_post_quantum: bool,
     enable_daita: bool,
+    daita_level: Option<DaitaLevel>,
 ) -> Result<EphemeralPeer, Error> {
     log::debug!("Connecting to relay config service at {service_address}");
     let client = connect_relay_config_client(service_address).await?;
@@ -139,6 +144,7 @@ pub async fn request_ephemeral_peer(
         ephemeral_pubkey,
         enable_post_quantum,
         enable_daita,
+        daita_level,
     )
     .await
 }
@@ -149,6 +155,7 @@ pub async fn request_ephemeral_peer_with(
     ephemeral_pubkey: PublicKey,
     enable_quantum_resistant: bool,
     enable_daita: bool,
+    daita_level: Option<DaitaLevel>,
 ) -> Result<EphemeralPeer, Error> {
     let (pq_request, kem_keypairs) = if enable_quantum_resistant {
         let start = Instant::now();
@@ -171,8 +178,10 @@ pub async fn request_ephemeral_peer_with(
             daita_v2: enable_daita.then(|| {
                 let platform = get_platform();
                 log::trace!("DAITA v2 platform: {platform:?}");
+                let level = daita_level_to_proto(daita_level);
+                log::trace!("DAITA v2 level: {level:?}");
                 proto::DaitaRequestV2 {
-                    level: i32::from(proto::DaitaLevel::LevelDefault),
+                    level: i32::from(level),
                     platform: i32::from(platform),
                     version: DAITA_VERSION,
                 }
@@ -227,7 +236,12 @@ pub async fn request_ephemeral_peer_with(
         None
     };
 
-    let daita = parse_daita_response_if_requested(response.daita, enable_daita)?;
+    let daita = parse_daita_response_if_requested(response.daita, enable_daita)?.map(|daita| {
+        DaitaSettings {
+            level: daita_level,
+            ..daita
+        }
+    });
     Ok(EphemeralPeer { psk, daita })
 }
 
@@ -263,6 +277,7 @@ fn parse_daita_response(daita: proto::DaitaResponseV2) -> Result<DaitaSettings,
             Error::ParseMaybenotMachines { reason }
         })?;
     Ok(DaitaSettings {
+        level: None,
         client_machines: machines,
         max_decoy_frac,
         max_delay_frac,
@@ -277,6 +292,12 @@ fn parse_daita_fraction(field: &'static str, value: f64) -> Result<f64, Error> {
     }
 }
 
+fn daita_level_to_proto(level: Option<DaitaLevel>) -> proto::DaitaLevel {
+    level
+        .and_then(|level| proto::DaitaLevel::try_from(i32::from(level.get())).ok())
+        .unwrap_or(proto::DaitaLevel::LevelDefault)
+}
+
 const fn get_platform() -> proto::DaitaPlatform {
     use proto::DaitaPlatform;
     const PLATFORM: DaitaPlatform = cfg_select! {
@@ -407,6 +428,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn daita_level_maps_to_proto_level() {
+        assert_eq!(daita_level_to_proto(None), proto::DaitaLevel::LevelDefault);
+        assert_eq!(
+            daita_level_to_proto(Some(DaitaLevel::MIN)),
+            proto::DaitaLevel::Level1
+        );
+        assert_eq!(
+            daita_level_to_proto(Some(DaitaLevel::new(5).unwrap())),
+            proto::DaitaLevel::Level5
+        );
+        assert_eq!(
+            daita_level_to_proto(Some(DaitaLevel::MAX)),
+            proto::DaitaLevel::Level10
+        );
+    }
+
     #[test]
     fn parse_daita_response_rejects_invalid_padding_fraction() {
         for invalid_fraction in [
@@ -0,0 +1,171 @@
+//! A server implementation of the tuncfg RegisterPeerV1 RPC, for testing the client side of the
+//! quantum-resistant PSK exchange against something other than a real relay.
+//!
+//! DAITA requests are accepted, but no DAITA settings are returned.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use rand_core::{CryptoRng, RngCore};
+use talpid_types::net::wireguard::{PresharedKey, PublicKey};
+use tokio::net::TcpListener;
+use tonic::{
+    Request, Response, Status,
+    transport::{Server, server::TcpIncoming},
+};
+
+use crate::proto::{
+    EphemeralPeerRequestV1, EphemeralPeerResponseV1, PostQuantumResponseV1,
+    ephemeral_peer_server::{EphemeralPeer, EphemeralPeerServer},
+};
+
+/// A peer that was registered with the server.
+#[derive(Clone, Debug)]
+pub struct Registration {
+    pub parent_pubkey: PublicKey,
+    pub ephemeral_pubkey: PublicKey,
+    pub psk: Option<PresharedKey>,
+}
+
+type RegistrationHook =
+    Arc<dyn Fn(Registration) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+/// Tuncfg service that derives a PSK for every registered peer.
+#[derive(Default, Clone)]
+pub struct EphemeralPeerService {
+    /// All peers registered so far, oldest first.
+    pub registrations: Arc<Mutex<Vec<Registration>>>,
+    hook: Option<RegistrationHook>,
+}
+
+impl EphemeralPeerService {
+    /// Call `hook` with every registered peer. The response is only sent once the future returned
+    /// by `hook` has completed, so that the peer can be set up before the client starts using it.
+    pub fn on_registration<F, Fut>(mut self, hook: F) -> Self
+    where
+        F: Fn(Registration) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.hook = Some(Arc::new(move |registration| Box::pin(hook(registration))));
+        self
+    }
+
+    /// Serve the RPC on `listener` until the returned future is dropped.
+    pub async fn serve(self, listener: TcpListener) -> Result<(), tonic::transport::Error> {
+        Server::builder()
+            .add_service(EphemeralPeerServer::new(self))
+            .serve_with_incoming(TcpIncoming::from(listener))
+            .await
+    }
+}
+
+#[tonic::async_trait]
+impl EphemeralPeer for EphemeralPeerService {
+    async fn register_peer_v1(
+        &self,
+        request: Request<EphemeralPeerRequestV1>,
+    ) -> Result<Response<EphemeralPeerResponseV1>, Status> {
+        let request = request.into_inner();
+        let parse_key = |key: &[u8]| {
+            PublicKey::try_from(key).map_err(|_| Status::invalid_argument("Invalid public key"))
+        };
+        let parent_pubkey = parse_key(&request.wg_parent_pubkey)?;
+        let ephemeral_pubkey = parse_key(&request.wg_ephemeral_peer_pubkey)?;
+        log::debug!("Registering ephemeral peer {ephemeral_pubkey} with parent {parent_pubkey}");
+
+        let (post_quantum, psk) = match request.post_quantum {
+            Some(post_quantum) => {
+                // The ciphertexts that will be returned to the client
+                let mut ciphertexts = Vec::new();
+                // The final PSK that is computed by XORing together all the KEM outputs.
+                let mut psk_data = Box::new([0u8; 32]);
+
+                for kem_pubkey in post_quantum.kem_pubkeys {
+                    let (ciphertext, shared_secret) = match kem_pubkey.algorithm_name.as_str() {
+                        "ML-KEM-1024" => {
+                            encapsulate_ml_kem(&kem_pubkey.key_data, &mut rand_core::OsRng)?
+                        }
+                        "HQC-256" => encapsulate_hqc(&kem_pubkey.key_data)?,
+                        name => {
+                            return Err(Status::invalid_argument(format!(
+                                "Unsupported KEM algorithm: {name}"
+                            )));
+                        }
+                    };
+
+                    ciphertexts.push(ciphertext);
+                    for (psk_byte, shared_secret_byte) in psk_data.iter_mut().zip(shared_secret) {
+                        *psk_byte ^= shared_secret_byte;
+                    }
+                }
+
+                (
+                    Some(PostQuantumResponseV1 { ciphertexts }),
+                    Some(PresharedKey::from(psk_data)),
+                )
+            }
+            None => (None, None),
+        };
+
+        let registration = Registration {
+            parent_pubkey,
+            ephemeral_pubkey,
+            psk,
+        };
+        self.registrations
+            .lock()
+            .unwrap()
+            .push(registration.clone());
+        if let Some(hook) = &self.hook {
+            hook(registration).await;
+        }
+
+        Ok(Response::new(EphemeralPeerResponseV1 {
+            post_quantum,
+            daita: None,
+        }))
+    }
+}
+
+/// Generate a random shared secret and encapsulate it with the given
+/// public key/encapsulation key. Returns the ciphertext to return
+/// to the owner of the public key, along with the shared secret.
+fn encapsulate_ml_kem<R: RngCore + CryptoRng>(
+    public_key: &[u8],
+    rng: &mut R,
+) -> Result<(Vec<u8>, [u8; 32]), Status> {
+    use ml_kem::{Encoded, EncodedSizeUser, KemCore, MlKem1024, kem::Encapsulate};
+
+    type EncapsulationKey = <MlKem1024 as KemCore>::EncapsulationKey;
+
+    let encapsulation_key_array = <Encoded<EncapsulationKey>>::try_from(public_key)
+        .map_err(|_| Status::invalid_argument("Invalid ML-KEM public key"))?;
+    let encapsulation_key = EncapsulationKey::from_bytes(&encapsulation_key_array);
+
+    let (ciphertext, shared_secret) = encapsulation_key
+        .encapsulate(rng)
+        .map_err(|_| Status::internal("ML-KEM encapsulation failed"))?;
+
+    Ok((ciphertext.to_vec(), shared_secret.into()))
+}
+
+/// Generate a random shared secret and encapsulate it with the given
+/// public key/encapsulation key. Returns the ciphertext to return
+/// to the owner of the public key, along with the shared secret.
+fn encapsulate_hqc(public_key_data: &[u8]) -> Result<(Vec<u8>, [u8; 32]), Status> {
+    use pqcrypto_hqc::hqc256;
+    use pqcrypto_traits::kem::{Ciphertext, PublicKey, SharedSecret};
+    use sha2::{Digest, Sha256};
+
+    let public_key = hqc256::PublicKey::from_bytes(public_key_data)
+        .map_err(|_| Status::invalid_argument("Invalid HQC public key"))?;
+    let (shared_secret, ciphertext) = hqc256::encapsulate(&public_key);
+
+    // By the ephemeral peer protocol spec (in the proto file), any KEM output that is not 32 bytes long
+    // must be hashed with SHA256 to produce a 32 byte output.
+    let output_shared_secret = <[u8; 32]>::from(Sha256::digest(shared_secret.as_bytes()));
+    Ok((ciphertext.as_bytes().to_vec(), output_shared_secret))
+}
@@ -0,0 +1,77 @@
+//! Renegotiates a PSK against a local instance of the test server, the same way the daemon does
+//! when it rekeys a long-lived tunnel.
+
+use std::time::Duration;
+
+use talpid_tunnel_config_client::{
+    RelayConfigService, request_ephemeral_peer_with, server::EphemeralPeerService,
+};
+use talpid_types::net::wireguard::PrivateKey;
+use tokio::{net::TcpListener, time::timeout};
+use tonic::transport::Endpoint;
+
+/// Start the test server on a random local port and connect a client to it.
+async fn start_server(server: EphemeralPeerService) -> RelayConfigService {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(server.serve(listener));
+
+    let channel = Endpoint::from_shared(format!("http://{addr}"))
+        .unwrap()
+        .connect()
+        .await
+        .unwrap();
+    RelayConfigService::new(channel)
+}
+
+/// Each rekey uses the current ephemeral key as the parent key, and must yield a new PSK that
+/// matches the one derived by the server.
+#[tokio::test]
+async fn test_rekey_with_ephemeral_parent() {
+    timeout(Duration::from_secs(30), async {
+        let server = EphemeralPeerService::default();
+        let client = start_server(server.clone()).await;
+
+        let device_key = PrivateKey::new_from_random();
+        let first_key = PrivateKey::new_from_random();
+        let second_key = PrivateKey::new_from_random();
+
+        let first = request_ephemeral_peer_with(
+            client.clone(),
+            device_key.public_key(),
+            first_key.public_key(),
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        let rekeyed = request_ephemeral_peer_with(
+            client,
+            first_key.public_key(),
+            second_key.public_key(),
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let registrations = server.registrations.lock().unwrap().clone();
+        assert_eq!(registrations.len(), 2);
+
+        assert_eq!(registrations[0].parent_pubkey, device_key.public_key());
+        assert_eq!(registrations[0].ephemeral_pubkey, first_key.public_key());
+        assert_eq!(registrations[1].parent_pubkey, first_key.public_key());
+        assert_eq!(registrations[1].ephemeral_pubkey, second_key.public_key());
+
+        let first_psk = first.psk.expect("missing PSK from first exchange");
+        let rekeyed_psk = rekeyed.psk.expect("missing PSK from rekey");
+        assert_eq!(registrations[0].psk.as_ref(), Some(&first_psk));
+        assert_eq!(registrations[1].psk.as_ref(), Some(&rekeyed_psk));
+        assert_ne!(first_psk, rekeyed_psk, "rekey must yield a new PSK");
+    })
+    .await
+    .unwrap();
+}
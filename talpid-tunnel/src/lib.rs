@@ -17,7 +17,7 @@ use futures::{
     },
 };
 use talpid_routing::RouteManagerHandle;
-use talpid_types::net::AllowedTunnelTraffic;
+use talpid_types::net::{AllowedTunnelTraffic, DaitaParameters, Endpoint};
 use tun_provider::TunProvider;
 
 /// Size of IPv4 header in bytes
@@ -49,6 +49,10 @@ pub struct TunnelArgs<'a> {
     pub retry_attempt: u32,
     /// Route manager handle.
     pub route_manager: RouteManagerHandle,
+    /// Network namespace to move the tunnel interface into once the tunnel is up. If set, the
+    /// tunnel only routes traffic from inside the namespace, and the host's routes are untouched.
+    #[cfg(target_os = "linux")]
+    pub netns: Option<Arc<talpid_netns::NetNs>>,
 }
 
 #[derive(Clone)]
@@ -80,6 +84,10 @@ pub struct TunnelMetadata {
     pub ipv4_gateway: Ipv4Addr,
     /// The IP to the IPv6 default gateway on the tunnel interface.
     pub ipv6_gateway: Option<Ipv6Addr>,
+    /// DAITA parameters negotiated with the relay, if DAITA is enabled.
+    pub daita: Option<DaitaParameters>,
+    /// The endpoint that the obfuscation multiplexer selected, if one was used.
+    pub selected_obfuscation: Option<Endpoint>,
 }
 
 impl TunnelMetadata {
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// How the tunnel DNS servers are applied to the system on Linux.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsBackend {
+    /// Use the first DNS manager that is available on the system.
+    #[default]
+    Auto,
+    /// Configure DNS for the tunnel interface using systemd-resolved.
+    SystemdResolved,
+    /// Configure DNS using NetworkManager.
+    NetworkManager,
+    /// Configure DNS using the `resolvconf` program.
+    Resolvconf,
+    /// Overwrite `/etc/resolv.conf`.
+    StaticFile,
+    /// Leave the system DNS config untouched, and redirect all DNS traffic from the host to the
+    /// tunnel DNS servers using the firewall.
+    NftablesRedirect,
+}
+
+impl DnsBackend {
+    const ALL: [DnsBackend; 6] = [
+        DnsBackend::Auto,
+        DnsBackend::SystemdResolved,
+        DnsBackend::NetworkManager,
+        DnsBackend::Resolvconf,
+        DnsBackend::StaticFile,
+        DnsBackend::NftablesRedirect,
+    ];
+
+    const fn as_str(&self) -> &'static str {
+        match self {
+            DnsBackend::Auto => "auto",
+            DnsBackend::SystemdResolved => "systemd-resolved",
+            DnsBackend::NetworkManager => "network-manager",
+            DnsBackend::Resolvconf => "resolvconf",
+            DnsBackend::StaticFile => "static-file",
+            DnsBackend::NftablesRedirect => "nftables-redirect",
+        }
+    }
+}
+
+impl FromStr for DnsBackend {
+    type Err = DnsBackendParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|backend| backend.as_str().eq_ignore_ascii_case(s))
+            .ok_or(DnsBackendParseError)
+    }
+}
+
+impl fmt::Display for DnsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Returned when `DnsBackend::from_str` fails to convert a string into a [`DnsBackend`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Not a valid DNS backend")]
+pub struct DnsBackendParseError;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_roundtrip() {
+        for backend in DnsBackend::ALL {
+            assert_eq!(backend.to_string().parse::<DnsBackend>(), Ok(backend));
+        }
+        assert_eq!("nonsense".parse::<DnsBackend>(), Err(DnsBackendParseError));
+    }
+}
@@ -0,0 +1,78 @@
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::TransportProtocol;
+
+/// Maximum length of a network interface name, excluding the nul terminator.
+const MAX_INTERFACE_NAME_LEN: usize = 15;
+
+/// Outbound traffic that is allowed outside the tunnel in every tunnel state, including when the
+/// firewall blocks all other traffic.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FirewallException {
+    /// Network that the traffic may be sent to.
+    pub destination: IpNetwork,
+    /// Transport protocol of the traffic.
+    pub protocol: TransportProtocol,
+    /// Destination port of the traffic.
+    pub port: u16,
+    /// Only allow the traffic to leave through this network interface.
+    pub interface: Option<String>,
+}
+
+impl FirewallException {
+    /// Returns whether `interface` can be used as [`FirewallException::interface`].
+    pub fn is_valid_interface_name(interface: &str) -> bool {
+        !interface.is_empty()
+            && interface.len() <= MAX_INTERFACE_NAME_LEN
+            && !interface
+                .bytes()
+                .any(|byte| byte == 0 || byte == b'/' || byte.is_ascii_whitespace())
+    }
+}
+
+impl fmt::Display for FirewallException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} to {} port {}",
+            self.protocol, self.destination, self.port
+        )?;
+        if let Some(interface) = &self.interface {
+            write!(f, " via {interface}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interface_name() {
+        assert!(FirewallException::is_valid_interface_name("eth0"));
+        assert!(FirewallException::is_valid_interface_name("wlp0s20f3"));
+        assert!(!FirewallException::is_valid_interface_name(""));
+        assert!(!FirewallException::is_valid_interface_name(
+            "a-very-long-name"
+        ));
+        assert!(!FirewallException::is_valid_interface_name("eth 0"));
+        assert!(!FirewallException::is_valid_interface_name("eth/0"));
+    }
+
+    #[test]
+    fn test_display() {
+        let exception = FirewallException {
+            destination: "192.0.2.0/24".parse().unwrap(),
+            protocol: TransportProtocol::Tcp,
+            port: 443,
+            interface: Some("eth0".to_owned()),
+        };
+        assert_eq!(
+            exception.to_string(),
+            "TCP to 192.0.2.0/24 port 443 via eth0"
+        );
+    }
+}
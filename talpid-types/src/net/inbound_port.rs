@@ -0,0 +1,44 @@
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::TransportProtocol;
+
+/// A port on the tunnel interface that accepts incoming connections while connected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct InboundPort {
+    /// Transport protocol of the traffic.
+    pub protocol: TransportProtocol,
+    /// Local port that the traffic is sent to.
+    pub port: u16,
+    /// Only accept traffic from this network.
+    pub source: Option<IpNetwork>,
+}
+
+impl fmt::Display for InboundPort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} port {}", self.protocol, self.port)?;
+        if let Some(source) = &self.source {
+            write!(f, " from {source}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let mut inbound_port = InboundPort {
+            protocol: TransportProtocol::Udp,
+            port: 51820,
+            source: None,
+        };
+        assert_eq!(inbound_port.to_string(), "UDP port 51820");
+
+        inbound_port.source = Some("10.64.0.0/10".parse().unwrap());
+        assert_eq!(inbound_port.to_string(), "UDP port 51820 from 10.64.0.0/10");
+    }
+}
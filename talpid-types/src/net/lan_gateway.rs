@@ -0,0 +1,46 @@
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Devices on local networks whose traffic is forwarded through the tunnel, turning this host into
+/// a VPN gateway for them. Their traffic is blocked whenever the tunnel is not connected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LanGateway {
+    /// Network interfaces that the forwarded devices are connected to.
+    pub interfaces: Vec<String>,
+    /// Only forward traffic from these networks. Traffic from any address on `interfaces` is
+    /// forwarded if this is empty.
+    pub subnets: Vec<IpNetwork>,
+}
+
+impl fmt::Display for LanGateway {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.interfaces.join(", "))?;
+        if !self.subnets.is_empty() {
+            let subnets = self
+                .subnets
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            write!(f, " from {}", subnets.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        let mut gateway = LanGateway {
+            interfaces: vec!["eth1".to_owned(), "wlan0".to_owned()],
+            subnets: vec![],
+        };
+        assert_eq!(gateway.to_string(), "eth1, wlan0");
+
+        gateway.subnets.push("192.168.50.0/24".parse().unwrap());
+        assert_eq!(gateway.to_string(), "eth1, wlan0 from 192.168.50.0/24");
+    }
+}
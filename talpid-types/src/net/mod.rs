@@ -17,8 +17,20 @@ pub mod proxy;
 pub mod wireguard;
 
 mod allowed_nets;
+mod dns_backend;
+mod firewall_exception;
+mod inbound_port;
+mod lan_gateway;
+mod trusted_interface;
+mod tunnel_capture;
 
 pub use allowed_nets::*;
+pub use dns_backend::*;
+pub use firewall_exception::*;
+pub use inbound_port::*;
+pub use lan_gateway::*;
+pub use trusted_interface::*;
+pub use tunnel_capture::*;
 
 /// A tunnel endpoint is broadcast during the connecting and connected states of the tunnel state
 /// machine.
@@ -31,6 +43,55 @@ pub struct TunnelEndpoint {
     pub entry_endpoint: Option<Endpoint>,
     pub tunnel_interface: Option<String>,
     pub daita: bool,
+    /// DAITA parameters negotiated with the relay. Only known once the tunnel is up.
+    #[serde(default)]
+    pub daita_parameters: Option<DaitaParameters>,
+}
+
+/// DAITA configuration that was negotiated with the relay.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DaitaParameters {
+    /// Requested level. `None` if the relay picked its default level.
+    pub level: Option<wireguard::DaitaLevel>,
+    /// Maximum fraction of traffic that may be padding.
+    pub max_padding_frac: f64,
+    /// Maximum fraction of time that traffic may be blocked.
+    pub max_blocking_frac: f64,
+}
+
+// The fractions are validated to be in `0.0..=1.0` by the config client, so comparing the bit
+// patterns is well-behaved.
+impl PartialEq for DaitaParameters {
+    fn eq(&self, other: &Self) -> bool {
+        self.level == other.level
+            && self.max_padding_frac.to_bits() == other.max_padding_frac.to_bits()
+            && self.max_blocking_frac.to_bits() == other.max_blocking_frac.to_bits()
+    }
+}
+
+impl Eq for DaitaParameters {}
+
+impl fmt::Display for DaitaParameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.level {
+            Some(level) => write!(f, "level {level}")?,
+            None => write!(f, "default level")?,
+        }
+        write!(
+            f,
+            " (max padding {:.0}%, max blocking {:.0}%)",
+            self.max_padding_frac * 100.0,
+            self.max_blocking_frac * 100.0
+        )
+    }
+}
+
+impl std::hash::Hash for DaitaParameters {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.level.hash(state);
+        self.max_padding_frac.to_bits().hash(state);
+        self.max_blocking_frac.to_bits().hash(state);
+    }
 }
 
 impl fmt::Display for TunnelEndpoint {
@@ -82,6 +143,9 @@ pub enum ObfuscationInfo {
         direct: Option<Endpoint>,
         /// All other obfuscators
         obfuscators: Vec<ObfuscationEndpoint>,
+        /// The endpoint that was first to receive a response, once known
+        #[serde(default)]
+        selected: Option<Endpoint>,
     },
 }
 
@@ -92,6 +156,7 @@ impl ObfuscationInfo {
             ObfuscationInfo::Multiplexer {
                 direct,
                 obfuscators,
+                ..
             } => {
                 let mut v = vec![];
                 if let Some(direct) = direct {
@@ -102,6 +167,26 @@ impl ObfuscationInfo {
             }
         }
     }
+
+    /// Return the endpoints that may be carrying traffic. Once a multiplexer has selected an
+    /// endpoint, only that one is returned.
+    pub fn active_endpoints(&self) -> Vec<Endpoint> {
+        match self {
+            ObfuscationInfo::Multiplexer {
+                selected: Some(selected),
+                ..
+            } => vec![*selected],
+            _ => self.get_endpoints(),
+        }
+    }
+
+    /// Record which of the multiplexed endpoints was selected. This has no effect on a single
+    /// obfuscator.
+    pub fn set_selected(&mut self, endpoint: Endpoint) {
+        if let ObfuscationInfo::Multiplexer { selected, .. } = self {
+            *selected = Some(endpoint);
+        }
+    }
 }
 
 impl fmt::Display for ObfuscationInfo {
@@ -111,6 +196,7 @@ impl fmt::Display for ObfuscationInfo {
             ObfuscationInfo::Multiplexer {
                 direct,
                 obfuscators,
+                selected,
             } => {
                 write!(f, "multiplex ")?;
 
@@ -123,7 +209,11 @@ impl fmt::Display for ObfuscationInfo {
                 for obfuscator in obfuscators {
                     write!(f, " | {obfuscator}")?;
                 }
-                write!(f, " }}")
+                write!(f, " }}")?;
+                if let Some(selected) = selected {
+                    write!(f, " selected {selected}")?;
+                }
+                Ok(())
             }
         }
     }
@@ -135,6 +225,7 @@ impl From<&Obfuscators> for ObfuscationInfo {
             Obfuscators::Multiplexer {
                 direct,
                 configs: (first_obfs, remaining_obfs),
+                ..
             } => ObfuscationInfo::Multiplexer {
                 direct: direct.map(|direct| Endpoint {
                     address: direct,
@@ -144,6 +235,7 @@ impl From<&Obfuscators> for ObfuscationInfo {
                     .chain(remaining_obfs)
                     .map(ObfuscationEndpoint::from)
                     .collect(),
+                selected: None,
             },
             Obfuscators::Single(obfs) => ObfuscationInfo::Single(ObfuscationEndpoint::from(obfs)),
         }
@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use super::{Endpoint, TransportProtocol};
 
@@ -17,6 +17,8 @@ pub enum Obfuscators {
         direct: Option<SocketAddr>,
         /// Obfuscation configurations to try.
         configs: (ObfuscatorConfig, Vec<ObfuscatorConfig>),
+        /// Delay between trying each method.
+        stagger_delay: Duration,
     },
 }
 
@@ -46,6 +48,7 @@ impl Obfuscators {
     /// # Arguments
     /// * `direct` - Optional direct connection endpoint (no obfuscation)
     /// * `obfuscators` - List of obfuscation methods to try, at least one.
+    /// * `stagger_delay` - Delay between trying each method
     ///
     /// # Returns
     /// * `Some(Obfuscators::Multiplexer)` if at least one obfuscation method is provided
@@ -53,6 +56,7 @@ impl Obfuscators {
     pub fn multiplexer(
         direct: Option<SocketAddr>,
         obfuscators: &[ObfuscatorConfig],
+        stagger_delay: Duration,
     ) -> Option<Self> {
         let [first, remaining @ ..] = obfuscators else {
             return None;
@@ -60,6 +64,7 @@ impl Obfuscators {
         Some(Obfuscators::Multiplexer {
             direct,
             configs: (first.clone(), remaining.to_vec()),
+            stagger_delay,
         })
     }
 
@@ -74,6 +79,7 @@ impl Obfuscators {
             Obfuscators::Multiplexer {
                 direct,
                 configs: (first_config, remaining_configs),
+                ..
             } => {
                 let mut endpoints = vec![];
                 if let Some(direct) = direct {
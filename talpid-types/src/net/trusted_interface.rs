@@ -0,0 +1,103 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::IpAddr};
+
+/// Longest domain name allowed by DNS, excluding the trailing dot.
+const MAX_DOMAIN_LEN: usize = 253;
+/// Longest label allowed by DNS.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Network interface of another VPN or mesh network client, such as Tailscale or ZeroTier. Its
+/// traffic is allowed in every tunnel state, and its routes take precedence over the tunnel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrustedInterface {
+    /// Name of the network interface.
+    pub name: String,
+    /// Send DNS queries for some domains to a resolver that is reached through the interface.
+    /// DNS traffic to other servers through the interface is blocked.
+    #[serde(default)]
+    pub dns_forwarding: Option<DnsForwarding>,
+}
+
+/// DNS queries for `domains` are sent to `resolver`, such as Tailscale's MagicDNS resolver,
+/// instead of the tunnel DNS servers.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DnsForwarding {
+    pub resolver: IpAddr,
+    /// Domains whose names, including subdomains, are resolved by `resolver`.
+    pub domains: Vec<String>,
+}
+
+impl DnsForwarding {
+    /// Returns whether `domain` can be used in [`DnsForwarding::domains`].
+    pub fn is_valid_domain(domain: &str) -> bool {
+        let domain = domain.strip_suffix('.').unwrap_or(domain);
+        !domain.is_empty()
+            && domain.len() <= MAX_DOMAIN_LEN
+            && domain.split('.').all(|label| {
+                !label.is_empty()
+                    && label.len() <= MAX_LABEL_LEN
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .bytes()
+                        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_')
+            })
+    }
+}
+
+impl fmt::Display for TrustedInterface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(dns_forwarding) = &self.dns_forwarding {
+            write!(f, " ({dns_forwarding})")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for DnsForwarding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "DNS for {} via {}",
+            self.domains.join(", "),
+            self.resolver
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_domain() {
+        assert!(DnsForwarding::is_valid_domain("ts.net"));
+        assert!(DnsForwarding::is_valid_domain("tail1234.ts.net."));
+        assert!(DnsForwarding::is_valid_domain("my-network"));
+        assert!(!DnsForwarding::is_valid_domain(""));
+        assert!(!DnsForwarding::is_valid_domain("."));
+        assert!(!DnsForwarding::is_valid_domain("ts..net"));
+        assert!(!DnsForwarding::is_valid_domain("-ts.net"));
+        assert!(!DnsForwarding::is_valid_domain("ts net"));
+        assert!(!DnsForwarding::is_valid_domain(&"a".repeat(64)));
+    }
+
+    #[test]
+    fn test_display() {
+        let mut interface = TrustedInterface {
+            name: "tailscale0".to_owned(),
+            dns_forwarding: None,
+        };
+        assert_eq!(interface.to_string(), "tailscale0");
+
+        interface.dns_forwarding = Some(DnsForwarding {
+            resolver: "100.100.100.100".parse().unwrap(),
+            domains: vec!["ts.net".to_owned(), "example.com".to_owned()],
+        });
+        assert_eq!(
+            interface.to_string(),
+            "tailscale0 (DNS for ts.net, example.com via 100.100.100.100)"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+/// Default value for [`TunnelCaptureSettings::max_size`].
+pub const DEFAULT_CAPTURE_MAX_SIZE: u64 = 100 * 1024 * 1024;
+/// Default value for [`TunnelCaptureSettings::max_duration`].
+pub const DEFAULT_CAPTURE_MAX_DURATION: Duration = Duration::from_secs(10 * 60);
+/// Largest allowed value of [`TunnelCaptureSettings::max_size`].
+pub const CAPTURE_SIZE_LIMIT: u64 = 1024 * 1024 * 1024;
+/// Largest allowed value of [`TunnelCaptureSettings::max_duration`].
+pub const CAPTURE_DURATION_LIMIT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Which packets of the userspace WireGuard tunnel to capture, and when to stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TunnelCaptureSettings {
+    /// Capture the plaintext IP packets that are sent and received through the tunnel.
+    pub inner: bool,
+    /// Capture the encrypted WireGuard packets that are exchanged with the entry relay. These are
+    /// captured before any obfuscation is applied.
+    pub outer: bool,
+    /// Stop capturing once the capture file has grown to this many bytes.
+    pub max_size: u64,
+    /// Stop capturing once this much time has passed.
+    pub max_duration: Duration,
+}
+
+impl Default for TunnelCaptureSettings {
+    fn default() -> Self {
+        TunnelCaptureSettings {
+            inner: true,
+            outer: true,
+            max_size: DEFAULT_CAPTURE_MAX_SIZE,
+            max_duration: DEFAULT_CAPTURE_MAX_DURATION,
+        }
+    }
+}
+
+impl TunnelCaptureSettings {
+    /// Lower the limits to [`CAPTURE_SIZE_LIMIT`] and [`CAPTURE_DURATION_LIMIT`] if they exceed
+    /// them, so that a capture cannot fill the disk or run indefinitely.
+    pub fn clamp_limits(self) -> Self {
+        TunnelCaptureSettings {
+            max_size: self.max_size.min(CAPTURE_SIZE_LIMIT),
+            max_duration: self.max_duration.min(CAPTURE_DURATION_LIMIT),
+            ..self
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_clamp_limits() {
+        let settings = TunnelCaptureSettings::default();
+        assert_eq!(settings.clamp_limits(), settings);
+
+        let settings = TunnelCaptureSettings {
+            max_size: u64::MAX,
+            max_duration: Duration::MAX,
+            ..settings
+        }
+        .clamp_limits();
+        assert_eq!(settings.max_size, CAPTURE_SIZE_LIMIT);
+        assert_eq!(settings.max_duration, CAPTURE_DURATION_LIMIT);
+    }
+}
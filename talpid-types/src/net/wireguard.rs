@@ -8,6 +8,7 @@ use std::{
     cmp, fmt,
     hash::{Hash, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -45,6 +46,7 @@ impl TunnelParameters {
                 .map(|_| self.connection.get_endpoint()),
             tunnel_interface: None,
             daita: self.options.daita,
+            daita_parameters: None,
         }
     }
 
@@ -74,6 +76,14 @@ pub struct ConnectionConfig {
     pub ipv6_gateway: Option<Ipv6Addr>,
     #[cfg(target_os = "linux")]
     pub fwmark: Option<u32>,
+    /// Name of the tunnel interface. A default name is used if this is not set.
+    #[cfg(target_os = "linux")]
+    #[serde(default)]
+    pub interface_name: Option<String>,
+    /// The entry `peer` is a user-provided WireGuard server in front of `exit_peer`, rather than
+    /// a Mullvad relay. Such a server can neither negotiate ephemeral peers nor run DAITA.
+    #[serde(default)]
+    pub custom_entry: bool,
 }
 
 impl ConnectionConfig {
@@ -130,10 +140,64 @@ pub struct TunnelOptions {
     pub quantum_resistant: bool,
     /// Enable DAITA during tunnel config
     pub daita: bool,
+    /// DAITA level to request from the relay. `None` lets the relay pick its default level.
+    pub daita_level: Option<DaitaLevel>,
     /// Use userspace WireGuard.
     pub userspace: bool,
+    /// How often to renegotiate the quantum-resistant PSK while connected. `None` disables
+    /// renegotiation.
+    pub rekey_interval: Option<Duration>,
+}
+
+/// DAITA intensity level, from 1 (least overhead) to 10 (strongest traffic-analysis resistance).
+/// Higher levels let the relay hand out machines that spend more bandwidth and latency on padding
+/// and blocking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct DaitaLevel(u8);
+
+impl DaitaLevel {
+    pub const MIN: DaitaLevel = DaitaLevel(1);
+    pub const MAX: DaitaLevel = DaitaLevel(10);
+
+    pub fn new(level: u8) -> Result<Self, InvalidDaitaLevel> {
+        if (Self::MIN.0..=Self::MAX.0).contains(&level) {
+            Ok(DaitaLevel(level))
+        } else {
+            Err(InvalidDaitaLevel(level))
+        }
+    }
+
+    pub const fn get(self) -> u8 {
+        self.0
+    }
+}
+
+impl TryFrom<u8> for DaitaLevel {
+    type Error = InvalidDaitaLevel;
+
+    fn try_from(level: u8) -> Result<Self, Self::Error> {
+        DaitaLevel::new(level)
+    }
+}
+
+impl From<DaitaLevel> for u8 {
+    fn from(level: DaitaLevel) -> u8 {
+        level.0
+    }
+}
+
+impl fmt::Display for DaitaLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
 }
 
+/// Error returned if a DAITA level is out of range
+#[derive(Debug, thiserror::Error)]
+#[error("DAITA level must be between 1 and 10, got {0}")]
+pub struct InvalidDaitaLevel(pub u8);
+
 /// Wireguard x25519 private key
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct PrivateKey(x25519_dalek::StaticSecret);
@@ -117,6 +117,12 @@ pub enum ErrorStateCause {
     /// Missing permissions required by macOS split tunneling.
     #[cfg(target_os = "macos")]
     NeedFullDiskPermissions,
+    /// The tunnel interface name, routing table or firewall marks are used by another program.
+    #[cfg(target_os = "linux")]
+    NetworkIdentifiersInUse,
+    /// The network namespace that the tunnel is confined to could not be set up.
+    #[cfg(target_os = "linux")]
+    TunnelNamespaceError,
 }
 
 impl ErrorStateCause {
@@ -252,6 +258,14 @@ impl fmt::Display for ErrorStateCause {
             SplitTunnelError => "The split tunneling module reported an error",
             #[cfg(target_os = "macos")]
             NeedFullDiskPermissions => "Need full disk access to enable split tunneling",
+            #[cfg(target_os = "linux")]
+            NetworkIdentifiersInUse => {
+                "The tunnel interface name, routing table or firewall marks are used by another \
+                 program. Stop that program and reconnect, or configure the daemon to use other \
+                 ones and restart it"
+            }
+            #[cfg(target_os = "linux")]
+            TunnelNamespaceError => "Failed to set up the network namespace for the tunnel",
             #[cfg(target_os = "android")]
             NotPrepared => "This device is not prepared",
             #[cfg(target_os = "android")]
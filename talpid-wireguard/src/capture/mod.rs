@@ -0,0 +1,208 @@
+//! On-demand capture of userspace WireGuard tunnel traffic to a pcapng file.
+//!
+//! At most one capture can be active at a time. The capture is process-global, so it keeps going
+//! across reconnects, and it stops once it exceeds the limits in its [`TunnelCaptureSettings`] or
+//! when [`stop`] is called.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::SystemTime,
+};
+
+use talpid_types::{ErrorExt, net::TunnelCaptureSettings};
+
+mod pcapng;
+
+pub(crate) use pcapng::Direction;
+use pcapng::PcapngWriter;
+
+/// Index of the interface that plaintext tunnel packets are recorded on.
+const INNER_INTERFACE: u32 = 0;
+/// Index of the interface that encrypted WireGuard packets are recorded on.
+const OUTER_INTERFACE: u32 = 1;
+
+/// Set while inner packets should be recorded. Checked before taking the lock, so that packets
+/// are passed through untouched when no capture is running.
+static CAPTURE_INNER: AtomicBool = AtomicBool::new(false);
+/// Set while outer packets should be recorded.
+static CAPTURE_OUTER: AtomicBool = AtomicBool::new(false);
+
+static ACTIVE_CAPTURE: Mutex<Option<Capture>> = Mutex::new(None);
+
+/// Identifies a capture, so that a stale timeout does not stop a later capture.
+static NEXT_CAPTURE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Errors that can happen when starting a tunnel capture.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// A capture is already running.
+    #[error("A tunnel capture is already running")]
+    AlreadyRunning,
+
+    /// Neither inner nor outer packets were selected.
+    #[error("No packets selected for capture")]
+    NothingToCapture,
+
+    /// Failed to create the capture file.
+    #[error("Failed to create capture file {}", _0.display())]
+    CreateFile(PathBuf, #[source] io::Error),
+
+    /// Failed to write the pcapng header.
+    #[error("Failed to write capture file header")]
+    WriteHeader(#[source] io::Error),
+}
+
+struct Capture {
+    id: u64,
+    path: PathBuf,
+    settings: TunnelCaptureSettings,
+    writer: PcapngWriter<BufWriter<File>>,
+}
+
+/// Start capturing tunnel packets to a new file at `path`. The capture stops automatically after
+/// `settings.max_duration`, or once the file has grown to `settings.max_size` bytes.
+///
+/// This must be called from within a tokio runtime.
+pub fn start(path: &Path, settings: TunnelCaptureSettings) -> Result<(), Error> {
+    if !settings.inner && !settings.outer {
+        return Err(Error::NothingToCapture);
+    }
+
+    let mut active = ACTIVE_CAPTURE.lock().unwrap();
+    if active.is_some() {
+        return Err(Error::AlreadyRunning);
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    // The capture contains plaintext tunnel traffic, and the log directory is readable by anyone.
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options
+        .open(path)
+        .map_err(|error| Error::CreateFile(path.to_owned(), error))?;
+    let writer =
+        PcapngWriter::new(BufWriter::new(file), &["inner", "outer"]).map_err(Error::WriteHeader)?;
+
+    let id = NEXT_CAPTURE_ID.fetch_add(1, Ordering::Relaxed);
+    *active = Some(Capture {
+        id,
+        path: path.to_owned(),
+        settings,
+        writer,
+    });
+    CAPTURE_INNER.store(settings.inner, Ordering::Release);
+    CAPTURE_OUTER.store(settings.outer, Ordering::Release);
+    drop(active);
+
+    log::info!("Started capturing tunnel packets to {}", path.display());
+
+    tokio::spawn(async move {
+        tokio::time::sleep(settings.max_duration).await;
+        let mut active = ACTIVE_CAPTURE.lock().unwrap();
+        if active.as_ref().is_some_and(|capture| capture.id == id) {
+            log::info!("Tunnel capture time limit reached");
+            finish(&mut active);
+        }
+    });
+
+    Ok(())
+}
+
+/// Stop the active capture, if any. Returns the path of the capture file.
+pub fn stop() -> Option<PathBuf> {
+    finish(&mut ACTIVE_CAPTURE.lock().unwrap())
+}
+
+/// Returns whether a capture is currently running.
+pub fn is_active() -> bool {
+    ACTIVE_CAPTURE.lock().unwrap().is_some()
+}
+
+/// Returns whether plaintext tunnel packets are currently being recorded.
+pub(crate) fn capturing_inner() -> bool {
+    CAPTURE_INNER.load(Ordering::Acquire)
+}
+
+/// Returns whether encrypted WireGuard packets are currently being recorded.
+pub(crate) fn capturing_outer() -> bool {
+    CAPTURE_OUTER.load(Ordering::Acquire)
+}
+
+/// Record a plaintext IP packet that is sent or received through the tunnel.
+pub(crate) fn record_inner(direction: Direction, packet: &[u8]) {
+    record(INNER_INTERFACE, direction, packet);
+}
+
+/// Record an encrypted WireGuard packet that is exchanged with `remote`. The packet is stored with
+/// synthesized IP and UDP headers.
+pub(crate) fn record_outer(
+    direction: Direction,
+    local: Option<SocketAddr>,
+    remote: SocketAddr,
+    payload: &[u8],
+) {
+    let local = local.unwrap_or_else(|| {
+        let unspecified = match remote {
+            SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        };
+        SocketAddr::new(unspecified, 0)
+    });
+    let packet = match direction {
+        Direction::Outbound => pcapng::udp_datagram(local, remote, payload),
+        Direction::Inbound => pcapng::udp_datagram(remote, local, payload),
+    };
+    record(OUTER_INTERFACE, direction, &packet);
+}
+
+fn record(interface: u32, direction: Direction, packet: &[u8]) {
+    let time = SystemTime::now();
+    let mut active = ACTIVE_CAPTURE.lock().unwrap();
+    let Some(capture) = active.as_mut() else {
+        return;
+    };
+
+    if let Err(error) = capture
+        .writer
+        .write_packet(interface, time, direction, packet)
+    {
+        log::error!(
+            "{}",
+            error.display_chain_with_msg("Failed to write to capture file")
+        );
+        finish(&mut active);
+        return;
+    }
+
+    if capture.writer.written() >= capture.settings.max_size {
+        log::info!("Tunnel capture size limit reached");
+        finish(&mut active);
+    }
+}
+
+/// Flush and close the capture in `active`, if any.
+fn finish(active: &mut Option<Capture>) -> Option<PathBuf> {
+    let mut capture = active.take()?;
+    CAPTURE_INNER.store(false, Ordering::Release);
+    CAPTURE_OUTER.store(false, Ordering::Release);
+
+    if let Err(error) = capture.writer.flush() {
+        log::error!(
+            "{}",
+            error.display_chain_with_msg("Failed to flush capture file")
+        );
+    }
+    log::info!(
+        "Stopped capturing tunnel packets to {}",
+        capture.path.display()
+    );
+    Some(capture.path)
+}
@@ -0,0 +1,285 @@
+//! Minimal writer for the pcapng file format.
+//!
+//! See <https://www.ietf.org/archive/id/draft-ietf-opsawg-pcapng-03.html> for the format.
+
+use std::{
+    io::{self, Write},
+    net::{IpAddr, SocketAddr},
+    time::SystemTime,
+};
+
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 0x00000001;
+const ENHANCED_PACKET_BLOCK: u32 = 0x00000006;
+
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B3C4D;
+
+/// Raw IPv4 or IPv6 packets, without a link-layer header.
+const LINKTYPE_RAW: u16 = 101;
+
+const OPT_ENDOFOPT: u16 = 0;
+const OPT_IF_NAME: u16 = 2;
+const OPT_EPB_FLAGS: u16 = 2;
+
+const EPB_FLAG_INBOUND: u32 = 0b01;
+const EPB_FLAG_OUTBOUND: u32 = 0b10;
+
+const UDP_PROTOCOL: u8 = 17;
+
+/// Direction of a captured packet, as seen from this host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// Writes packets to `W` in the pcapng format. All blocks are little-endian.
+pub struct PcapngWriter<W: Write> {
+    writer: W,
+    written: u64,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Write a section header followed by one interface description per name in `interfaces`.
+    /// Packets refer to an interface by its index in `interfaces`.
+    pub fn new(writer: W, interfaces: &[&str]) -> io::Result<Self> {
+        let mut pcapng = PcapngWriter { writer, written: 0 };
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&BYTE_ORDER_MAGIC.to_le_bytes());
+        body.extend_from_slice(&1u16.to_le_bytes());
+        body.extend_from_slice(&0u16.to_le_bytes());
+        // The section length is not known in advance
+        body.extend_from_slice(&(-1i64).to_le_bytes());
+        pcapng.write_block(SECTION_HEADER_BLOCK, &body)?;
+
+        for name in interfaces {
+            let mut body = Vec::new();
+            body.extend_from_slice(&LINKTYPE_RAW.to_le_bytes());
+            body.extend_from_slice(&0u16.to_le_bytes());
+            // No limit on the packet length
+            body.extend_from_slice(&0u32.to_le_bytes());
+            push_option(&mut body, OPT_IF_NAME, name.as_bytes());
+            push_option(&mut body, OPT_ENDOFOPT, &[]);
+            pcapng.write_block(INTERFACE_DESCRIPTION_BLOCK, &body)?;
+        }
+
+        Ok(pcapng)
+    }
+
+    /// Write a raw IP packet that was sent or received on `interface` at `time`.
+    pub fn write_packet(
+        &mut self,
+        interface: u32,
+        time: SystemTime,
+        direction: Direction,
+        packet: &[u8],
+    ) -> io::Result<()> {
+        // The default timestamp resolution is microseconds
+        let timestamp = time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        let flags = match direction {
+            Direction::Inbound => EPB_FLAG_INBOUND,
+            Direction::Outbound => EPB_FLAG_OUTBOUND,
+        };
+
+        let mut body = Vec::with_capacity(packet.len() + 40);
+        body.extend_from_slice(&interface.to_le_bytes());
+        body.extend_from_slice(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend_from_slice(&(timestamp as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        body.extend_from_slice(packet);
+        pad_to_32_bits(&mut body);
+        push_option(&mut body, OPT_EPB_FLAGS, &flags.to_le_bytes());
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        self.write_block(ENHANCED_PACKET_BLOCK, &body)
+    }
+
+    /// Number of bytes written so far.
+    pub fn written(&self) -> u64 {
+        self.written
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn write_block(&mut self, block_type: u32, body: &[u8]) -> io::Result<()> {
+        debug_assert_eq!(body.len() % 4, 0);
+        // Block type and both length fields
+        let total_length = (body.len() + 12) as u32;
+        self.writer.write_all(&block_type.to_le_bytes())?;
+        self.writer.write_all(&total_length.to_le_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total_length.to_le_bytes())?;
+        self.written += u64::from(total_length);
+        Ok(())
+    }
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend_from_slice(&code.to_le_bytes());
+    body.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    body.extend_from_slice(value);
+    pad_to_32_bits(body);
+}
+
+fn pad_to_32_bits(body: &mut Vec<u8>) {
+    body.resize(body.len().next_multiple_of(4), 0);
+}
+
+/// Wrap `payload` in IP and UDP headers, so that a UDP datagram can be stored as a raw IP packet.
+///
+/// If the address family of `source` and `destination` differ, the source address is replaced by
+/// the unspecified address of the destination's family.
+pub fn udp_datagram(source: SocketAddr, destination: SocketAddr, payload: &[u8]) -> Vec<u8> {
+    let source_ip = match (source.ip(), destination.ip()) {
+        (IpAddr::V4(ip), IpAddr::V4(_)) => IpAddr::V4(ip),
+        (IpAddr::V6(ip), IpAddr::V6(_)) => IpAddr::V6(ip),
+        (_, IpAddr::V4(_)) => IpAddr::V4(std::net::Ipv4Addr::UNSPECIFIED),
+        (_, IpAddr::V6(_)) => IpAddr::V6(std::net::Ipv6Addr::UNSPECIFIED),
+    };
+    let udp_length = (8 + payload.len()) as u16;
+
+    let mut udp = Vec::with_capacity(usize::from(udp_length));
+    udp.extend_from_slice(&source.port().to_be_bytes());
+    udp.extend_from_slice(&destination.port().to_be_bytes());
+    udp.extend_from_slice(&udp_length.to_be_bytes());
+    udp.extend_from_slice(&[0, 0]);
+    udp.extend_from_slice(payload);
+
+    let mut packet = match (source_ip, destination.ip()) {
+        (IpAddr::V4(source_ip), IpAddr::V4(destination_ip)) => {
+            let mut header = [0u8; 20];
+            header[0] = 0x45;
+            header[2..4].copy_from_slice(&(20 + udp_length).to_be_bytes());
+            header[8] = 64;
+            header[9] = UDP_PROTOCOL;
+            header[12..16].copy_from_slice(&source_ip.octets());
+            header[16..20].copy_from_slice(&destination_ip.octets());
+            let checksum = internet_checksum::checksum(&header);
+            header[10..12].copy_from_slice(&checksum);
+            header.to_vec()
+        }
+        (IpAddr::V6(source_ip), IpAddr::V6(destination_ip)) => {
+            let mut header = [0u8; 40];
+            header[0] = 0x60;
+            header[4..6].copy_from_slice(&udp_length.to_be_bytes());
+            header[6] = UDP_PROTOCOL;
+            header[7] = 64;
+            header[8..24].copy_from_slice(&source_ip.octets());
+            header[24..40].copy_from_slice(&destination_ip.octets());
+            header.to_vec()
+        }
+        _ => unreachable!("address families must match"),
+    };
+
+    // The UDP checksum covers a pseudo-header of the addresses, protocol and UDP length
+    let mut checksum = internet_checksum::Checksum::new();
+    for address in [source_ip, destination.ip()] {
+        match address {
+            IpAddr::V4(ip) => checksum.add_bytes(&ip.octets()),
+            IpAddr::V6(ip) => checksum.add_bytes(&ip.octets()),
+        }
+    }
+    checksum.add_bytes(&[0, UDP_PROTOCOL]);
+    checksum.add_bytes(&udp_length.to_be_bytes());
+    checksum.add_bytes(&udp);
+    let checksum = match checksum.checksum() {
+        // An all-zero checksum means that there is no checksum
+        [0, 0] => [0xff, 0xff],
+        checksum => checksum,
+    };
+    udp[6..8].copy_from_slice(&checksum);
+
+    packet.extend_from_slice(&udp);
+    packet
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::time::Duration;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Split `data` into (block type, block length) pairs, checking that both length fields of
+    /// each block agree.
+    fn blocks(data: &[u8]) -> Vec<(u32, usize)> {
+        let mut blocks = vec![];
+        let mut offset = 0;
+        while offset < data.len() {
+            let block_type = read_u32(data, offset);
+            let length = read_u32(data, offset + 4) as usize;
+            assert_eq!(length % 4, 0);
+            assert_eq!(read_u32(data, offset + length - 4) as usize, length);
+            blocks.push((block_type, length));
+            offset += length;
+        }
+        assert_eq!(offset, data.len());
+        blocks
+    }
+
+    #[test]
+    fn test_block_structure() {
+        let mut writer = PcapngWriter::new(vec![], &["inner", "outer"]).unwrap();
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1);
+        writer
+            .write_packet(1, time, Direction::Outbound, &[0x45; 21])
+            .unwrap();
+        let written = writer.written();
+        let data = writer.writer;
+
+        assert_eq!(written, data.len() as u64);
+        let blocks = blocks(&data);
+        let types: Vec<_> = blocks.iter().map(|(block_type, _)| *block_type).collect();
+        assert_eq!(
+            types,
+            [
+                SECTION_HEADER_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                INTERFACE_DESCRIPTION_BLOCK,
+                ENHANCED_PACKET_BLOCK
+            ]
+        );
+
+        let packet_offset = data.len() - blocks[3].1;
+        assert_eq!(read_u32(&data, packet_offset + 8), 1, "interface id");
+        assert_eq!(read_u32(&data, packet_offset + 16), 1_000_000, "timestamp");
+        assert_eq!(read_u32(&data, packet_offset + 20), 21, "captured length");
+    }
+
+    #[test]
+    fn test_udp_datagram_v4() {
+        let source: SocketAddr = "10.0.0.1:51820".parse().unwrap();
+        let destination: SocketAddr = "192.0.2.1:443".parse().unwrap();
+        let packet = udp_datagram(source, destination, b"hello");
+
+        assert_eq!(packet.len(), 20 + 8 + 5);
+        assert_eq!(
+            internet_checksum::checksum(&packet[..20]),
+            [0, 0],
+            "IPv4 header checksum"
+        );
+        assert_eq!(&packet[12..16], &[10, 0, 0, 1]);
+        assert_eq!(&packet[20..22], &51820u16.to_be_bytes());
+        assert_eq!(&packet[22..24], &443u16.to_be_bytes());
+        assert_eq!(&packet[28..], b"hello");
+    }
+
+    #[test]
+    fn test_udp_datagram_mismatched_families() {
+        let source: SocketAddr = "10.0.0.1:51820".parse().unwrap();
+        let destination: SocketAddr = "[2001:db8::1]:443".parse().unwrap();
+        let packet = udp_datagram(source, destination, b"hello");
+
+        assert_eq!(packet[0] >> 4, 6);
+        assert_eq!(&packet[8..24], &[0; 16], "unspecified source address");
+        assert_eq!(packet.len(), 40 + 8 + 5);
+    }
+}
@@ -1,9 +1,17 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
-use talpid_types::net::{GenericTunnelOptions, obfuscation::Obfuscators, wireguard};
+use std::{
+    net::{Ipv4Addr, Ipv6Addr},
+    time::Duration,
+};
+use talpid_types::net::{
+    DaitaParameters, GenericTunnelOptions,
+    obfuscation::{ObfuscatorConfig, Obfuscators},
+    wireguard,
+};
 
-/// Name to use for the tunnel device
+/// Name to use for the tunnel device, unless another name is given in the
+/// [`wireguard::ConnectionConfig`].
 #[cfg(target_os = "linux")]
-pub(crate) const MULLVAD_INTERFACE_NAME: &str = "wg0-mullvad";
+pub(crate) const DEFAULT_INTERFACE_NAME: &str = "wg0-mullvad";
 
 /// Config required to set up a single WireGuard tunnel
 #[derive(Debug, Clone)]
@@ -14,12 +22,17 @@ pub struct Config {
     pub entry_peer: wireguard::PeerConfig,
     /// Multihop exit peer
     pub exit_peer: Option<wireguard::PeerConfig>,
+    /// Whether the entry peer is a user-provided server rather than a Mullvad relay
+    pub custom_entry: bool,
     /// IPv4 gateway
     pub ipv4_gateway: Ipv4Addr,
     /// IPv6 gateway
     pub ipv6_gateway: Option<Ipv6Addr>,
     /// Maximum transmission unit for the tunnel
     pub mtu: u16,
+    /// Name of the tunnel device
+    #[cfg(target_os = "linux")]
+    pub interface_name: String,
     /// Firewall mark
     // TODO: Should this be optional? Should it even be configurable?
     #[cfg(target_os = "linux")]
@@ -33,8 +46,14 @@ pub struct Config {
     pub obfuscation_mtu: u16,
     /// Enable quantum-resistant PSK exchange
     pub quantum_resistant: bool,
+    /// How often to renegotiate the quantum-resistant PSK while connected
+    pub rekey_interval: Option<Duration>,
     /// Enable DAITA
     pub daita: bool,
+    /// DAITA level to request. `None` lets the relay pick its default level.
+    pub daita_level: Option<wireguard::DaitaLevel>,
+    /// DAITA parameters negotiated with the relay. Set during ephemeral peer negotiation.
+    pub daita_parameters: Option<DaitaParameters>,
 }
 
 /// Configuration errors
@@ -90,21 +109,35 @@ impl Config {
             .ipv6_gateway
             .filter(|_opt| generic_options.enable_ipv6);
 
+        let custom_entry = connection.custom_entry && connection.exit_peer.is_some();
+
         let mut config = Config {
             tunnel,
             entry_peer: connection.peer.clone(),
             exit_peer: connection.exit_peer.clone(),
+            custom_entry,
             ipv4_gateway: connection.ipv4_gateway,
             ipv6_gateway,
             mtu,
             #[cfg(target_os = "linux")]
+            interface_name: connection
+                .interface_name
+                .clone()
+                .unwrap_or_else(|| DEFAULT_INTERFACE_NAME.to_owned()),
+            #[cfg(target_os = "linux")]
             fwmark: connection.fwmark,
             #[cfg(target_os = "linux")]
             enable_ipv6: generic_options.enable_ipv6,
             obfuscator_config: obfuscator_config.to_owned(),
             obfuscation_mtu,
-            quantum_resistant: wg_options.quantum_resistant,
-            daita: wg_options.daita,
+            // The ephemeral private key replaces the device key on both peers, but a custom entry
+            // only knows the device key and cannot negotiate a PSK.
+            quantum_resistant: wg_options.quantum_resistant && !custom_entry,
+            rekey_interval: wg_options.rekey_interval,
+            // DAITA has to be supported by the entry peer, which a custom entry cannot do.
+            daita: wg_options.daita && !custom_entry,
+            daita_level: wg_options.daita_level,
+            daita_parameters: None,
         };
 
         for peer in config.peers_mut() {
@@ -148,6 +181,23 @@ impl Config {
         self.exit_peer.as_mut().unwrap_or(&mut self.entry_peer)
     }
 
+    /// Return whether the ephemeral peer can be renegotiated by only replacing the private key and
+    /// PSK of the running tunnel. This is not the case for multihop, since the config service of
+    /// the entry relay can only be reached using an entry-only tunnel, or for LWO obfuscation,
+    /// which depends on the private key.
+    pub fn can_rekey_in_place(&self) -> bool {
+        let is_lwo = |config: &ObfuscatorConfig| matches!(config, ObfuscatorConfig::Lwo { .. });
+        let uses_lwo = match &self.obfuscator_config {
+            None => false,
+            Some(Obfuscators::Single(config)) => is_lwo(config),
+            Some(Obfuscators::Multiplexer {
+                configs: (first, remaining),
+                ..
+            }) => is_lwo(first) || remaining.iter().any(is_lwo),
+        };
+        !self.is_multihop() && !uses_lwo
+    }
+
     /// Return an iterator over all peers.
     pub fn peers(&self) -> impl Iterator<Item = &wireguard::PeerConfig> {
         self.exit_peer
@@ -196,3 +246,94 @@ pub(crate) fn patch_allowed_ips(mut config: Config) -> Config {
     }
     config
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::wireguard::{
+        ConnectionConfig, PeerConfig, PrivateKey, TunnelConfig, TunnelOptions, TunnelParameters,
+    };
+
+    fn peer(key: [u8; 32], endpoint: &str) -> PeerConfig {
+        PeerConfig {
+            public_key: key.into(),
+            allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+            endpoint: endpoint.parse().unwrap(),
+            psk: None,
+            constant_packet_size: false,
+        }
+    }
+
+    fn params(custom_entry: bool) -> TunnelParameters {
+        TunnelParameters {
+            connection: ConnectionConfig {
+                tunnel: TunnelConfig {
+                    private_key: PrivateKey::from([1; 32]),
+                    addresses: vec!["10.64.0.2".parse().unwrap()],
+                },
+                peer: peer([2; 32], "192.0.2.1:51820"),
+                exit_peer: Some(peer([3; 32], "192.0.2.2:51820")),
+                ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                ipv6_gateway: None,
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+                #[cfg(target_os = "linux")]
+                interface_name: None,
+                custom_entry,
+            },
+            options: TunnelOptions {
+                mtu: None,
+                quantum_resistant: true,
+                daita: true,
+                daita_level: None,
+                userspace: true,
+                rekey_interval: None,
+            },
+            generic_options: GenericTunnelOptions { enable_ipv6: false },
+            obfuscation: None,
+        }
+    }
+
+    #[test]
+    fn test_custom_entry_disables_ephemeral_peer() {
+        let config = Config::from_parameters(&params(false), 1380, 1380).unwrap();
+        assert!(!config.custom_entry);
+        assert!(config.quantum_resistant);
+        assert!(config.daita);
+
+        // The custom entry only knows the device key, so no ephemeral peer may be negotiated
+        let config = Config::from_parameters(&params(true), 1380, 1380).unwrap();
+        assert!(config.custom_entry);
+        assert!(!config.quantum_resistant);
+        assert!(!config.daita);
+    }
+
+    #[test]
+    fn test_can_rekey_in_place() {
+        let mut params = params(false);
+        let config = Config::from_parameters(&params, 1380, 1380).unwrap();
+        assert!(
+            !config.can_rekey_in_place(),
+            "multihop requires an entry-only tunnel"
+        );
+
+        params.connection.exit_peer = None;
+        let config = Config::from_parameters(&params, 1380, 1380).unwrap();
+        assert!(config.can_rekey_in_place());
+
+        params.obfuscation = Some(Obfuscators::Single(ObfuscatorConfig::Udp2Tcp {
+            endpoint: "192.0.2.1:443".parse().unwrap(),
+        }));
+        let config = Config::from_parameters(&params, 1380, 1380).unwrap();
+        assert!(config.can_rekey_in_place());
+
+        params.obfuscation = Some(Obfuscators::Single(ObfuscatorConfig::Lwo {
+            endpoint: "192.0.2.1:51820".parse().unwrap(),
+        }));
+        let config = Config::from_parameters(&params, 1380, 1380).unwrap();
+        assert!(
+            !config.can_rekey_in_place(),
+            "LWO depends on the private key"
+        );
+    }
+}
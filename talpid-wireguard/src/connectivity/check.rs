@@ -111,6 +111,24 @@ impl Check {
         })
     }
 
+    /// Recreate the pinger inside `netns`, after the tunnel interface has been moved there.
+    #[cfg(target_os = "linux")]
+    pub fn move_to_namespace(
+        &mut self,
+        netns: &talpid_netns::NetNs,
+        addr: Ipv4Addr,
+        interface: String,
+    ) -> Result<(), Error> {
+        let runtime = tokio::runtime::Handle::current();
+        self.ping_state = netns
+            .run(move || {
+                let _guard = runtime.enter();
+                PingState::new(addr, interface)
+            })
+            .map_err(Error::Namespace)??;
+        Ok(())
+    }
+
     #[cfg(test)]
     /// Create a new [Check] with a custom initial state.
     pub(super) fn mock(conn_state: ConnState, ping_state: PingState) -> (Self, CancelToken) {
@@ -11,4 +11,9 @@ pub enum Error {
     /// Failed to send ping
     #[error("Ping failed")]
     PingError(#[from] pinger::Error),
+
+    /// Failed to enter the network namespace of the tunnel
+    #[cfg(target_os = "linux")]
+    #[error("Failed to enter the network namespace of the tunnel")]
+    Namespace(#[source] talpid_netns::Error),
 }
@@ -3,19 +3,21 @@
 
 use super::{CloseMsg, Error, TunnelType, config::Config, obfuscation::ObfuscatorHandle};
 
-#[cfg(target_os = "android")]
-use std::sync::Mutex;
 use std::{
+    convert::Infallible,
     net::IpAddr,
-    sync::{Arc, mpsc as sync_mpsc},
-    time::Duration,
+    sync::{Arc, Mutex, mpsc as sync_mpsc},
+    time::{Duration, SystemTime},
 };
 #[cfg(target_os = "android")]
 use talpid_tunnel::tun_provider::TunProvider;
 
 use ipnetwork::IpNetwork;
 use talpid_tunnel_config_client::{DaitaSettings, EphemeralPeer};
-use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+use talpid_types::net::{
+    DaitaParameters,
+    wireguard::{PrivateKey, PublicKey},
+};
 use tokio::sync::Mutex as AsyncMutex;
 
 const INITIAL_PSK_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(8);
@@ -86,6 +88,89 @@ pub async fn config_ephemeral_peers(
     .await
 }
 
+/// Renegotiate the ephemeral peer every `interval` for as long as the tunnel is up, so that the
+/// quantum-resistant PSK is replaced without tearing down the tunnel. Only the private key and PSK
+/// of the running tunnel are replaced, which requires [Config::can_rekey_in_place]. Since the
+/// current ephemeral key is the parent key of each new exchange, `config` must be the config the
+/// tunnel currently uses. It is updated after every successful exchange, and the time is stored in
+/// `last_rekey`.
+///
+/// A failed exchange is returned as an error.
+pub async fn rekey_ephemeral_peers(
+    interval: Duration,
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &mut Config,
+    last_rekey: &Mutex<Option<SystemTime>>,
+) -> Result<Infallible, CloseMsg> {
+    loop {
+        tokio::time::sleep(interval).await;
+
+        log::info!("Renegotiating ephemeral peer");
+        rekey_ephemeral_peer(tunnel, config)
+            .await
+            .inspect_err(|_| log::error!("Failed to renegotiate ephemeral peer"))?;
+
+        *last_rekey.lock().unwrap() = Some(SystemTime::now());
+        log::info!("Renegotiated ephemeral peer");
+    }
+}
+
+/// Negotiate a new ephemeral peer with the relay through the running tunnel, and apply the new
+/// private key and PSK to it.
+async fn rekey_ephemeral_peer(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &mut Config,
+) -> Result<(), CloseMsg> {
+    let ephemeral_private_key = PrivateKey::new_from_random();
+    let ephemeral_peer = request_ephemeral_peer(
+        0,
+        config,
+        ephemeral_private_key.public_key(),
+        config.quantum_resistant,
+        config.daita,
+    )
+    .await?;
+
+    apply_rekeyed_peer(tunnel, config, ephemeral_private_key, ephemeral_peer).await
+}
+
+/// Replace the private key and PSK of the running tunnel. `config` is only updated if the tunnel
+/// accepted the new config.
+async fn apply_rekeyed_peer(
+    tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
+    config: &mut Config,
+    ephemeral_private_key: PrivateKey,
+    ephemeral_peer: EphemeralPeer,
+) -> Result<(), CloseMsg> {
+    let mut new_config = config.clone();
+    new_config.entry_peer.psk = ephemeral_peer.psk;
+    new_config.tunnel.private_key = ephemeral_private_key;
+    new_config.daita_parameters = ephemeral_peer.daita.as_ref().map(daita_parameters);
+
+    if let Some(tunnel) = tunnel.lock().await.as_mut() {
+        tunnel
+            .set_config(new_config.clone(), ephemeral_peer.daita)
+            .await
+            .map_err(Error::TunnelError)
+            .map_err(CloseMsg::SetupError)?;
+    }
+    *config = new_config;
+    Ok(())
+}
+
+fn daita_parameters(daita: &DaitaSettings) -> DaitaParameters {
+    log::debug!(
+        "Negotiated DAITA parameters: max padding {}, max blocking {}",
+        daita.max_decoy_frac,
+        daita.max_delay_frac
+    );
+    DaitaParameters {
+        level: daita.level,
+        max_padding_frac: daita.max_decoy_frac,
+        max_blocking_frac: daita.max_delay_frac,
+    }
+}
+
 async fn config_ephemeral_peers_inner(
     tunnel: &Arc<AsyncMutex<Option<TunnelType>>>,
     config: &mut Config,
@@ -147,6 +232,7 @@ async fn config_ephemeral_peers_inner(
     }
 
     config.exit_peer_mut().psk = exit_ephemeral_peer.psk;
+    config.daita_parameters = daita.as_ref().map(daita_parameters);
     if config.daita {
         // NOTE: this option does nothing for GotaTun, and should be removed in future.
         log::trace!("Enabling constant packet size for entry peer");
@@ -277,6 +363,7 @@ async fn request_ephemeral_peer(
             wg_psk_pubkey,
             enable_pq,
             enable_daita,
+            config.daita_level,
         ),
     )
     .await
@@ -289,3 +376,156 @@ async fn request_ephemeral_peer(
 
     Ok(ephemeral)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Tunnel, TunnelError, stats::StatsMap};
+    use std::{future::Future, net::Ipv4Addr, pin::Pin};
+    use talpid_types::net::{
+        GenericTunnelOptions,
+        obfuscation::{ObfuscatorConfig, Obfuscators},
+        wireguard::{
+            ConnectionConfig, PeerConfig, PresharedKey, TunnelConfig, TunnelOptions,
+            TunnelParameters,
+        },
+    };
+
+    /// Tunnel that records every config that is applied to it.
+    struct RecordingTunnel {
+        configs: Arc<Mutex<Vec<Config>>>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl Tunnel for RecordingTunnel {
+        fn get_interface_name(&self) -> String {
+            "recording-tunnel".to_string()
+        }
+
+        fn stop(self: Box<Self>) -> Result<(), TunnelError> {
+            Ok(())
+        }
+
+        async fn get_tunnel_stats(&self) -> Result<StatsMap, TunnelError> {
+            Ok(StatsMap::new())
+        }
+
+        fn set_config<'a>(
+            &'a mut self,
+            config: Config,
+            _daita: Option<DaitaSettings>,
+        ) -> Pin<Box<dyn Future<Output = Result<(), TunnelError>> + Send + 'a>> {
+            let result = if self.fail {
+                Err(TunnelError::SetConfigError)
+            } else {
+                self.configs.lock().unwrap().push(config);
+                Ok(())
+            };
+            Box::pin(async { result })
+        }
+    }
+
+    fn config() -> Config {
+        let params = TunnelParameters {
+            connection: ConnectionConfig {
+                tunnel: TunnelConfig {
+                    private_key: PrivateKey::from([1; 32]),
+                    addresses: vec!["10.64.0.2".parse().unwrap()],
+                },
+                peer: PeerConfig {
+                    public_key: [2; 32].into(),
+                    allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+                    endpoint: "192.0.2.1:51820".parse().unwrap(),
+                    psk: Some(PresharedKey::from(Box::new([3; 32]))),
+                    constant_packet_size: false,
+                },
+                exit_peer: None,
+                ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                ipv6_gateway: None,
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+                #[cfg(target_os = "linux")]
+                interface_name: None,
+                custom_entry: false,
+            },
+            options: TunnelOptions {
+                mtu: None,
+                quantum_resistant: true,
+                daita: false,
+                daita_level: None,
+                userspace: true,
+                rekey_interval: None,
+            },
+            generic_options: GenericTunnelOptions { enable_ipv6: false },
+            obfuscation: Some(Obfuscators::Single(ObfuscatorConfig::Udp2Tcp {
+                endpoint: "192.0.2.1:443".parse().unwrap(),
+            })),
+        };
+        Config::from_parameters(&params, 1380, 1380).unwrap()
+    }
+
+    fn tunnel(fail: bool) -> (Arc<AsyncMutex<Option<TunnelType>>>, Arc<Mutex<Vec<Config>>>) {
+        let configs = Arc::new(Mutex::new(vec![]));
+        let tunnel: TunnelType = Box::new(RecordingTunnel {
+            configs: configs.clone(),
+            fail,
+        });
+        (Arc::new(AsyncMutex::new(Some(tunnel))), configs)
+    }
+
+    /// Only the private key and PSK of the running tunnel may change when rekeying.
+    #[tokio::test]
+    async fn test_apply_rekeyed_peer() {
+        let (tunnel, configs) = tunnel(false);
+        let mut config = config();
+        let old_config = config.clone();
+        let new_key = PrivateKey::from([4; 32]);
+        let new_psk = PresharedKey::from(Box::new([5; 32]));
+
+        let peer = EphemeralPeer {
+            psk: Some(new_psk.clone()),
+            daita: None,
+        };
+        apply_rekeyed_peer(&tunnel, &mut config, new_key.clone(), peer)
+            .await
+            .unwrap();
+
+        let configs = configs.lock().unwrap();
+        assert_eq!(configs.len(), 1);
+        let applied = &configs[0];
+        assert_eq!(applied.tunnel.private_key.to_bytes(), new_key.to_bytes());
+        assert_eq!(applied.entry_peer.psk, Some(new_psk));
+        assert_eq!(
+            applied.entry_peer.public_key,
+            old_config.entry_peer.public_key
+        );
+        assert_eq!(applied.entry_peer.endpoint, old_config.entry_peer.endpoint);
+        assert_eq!(applied.obfuscator_config, old_config.obfuscator_config);
+        assert_eq!(config.tunnel.private_key.to_bytes(), new_key.to_bytes());
+    }
+
+    /// The current config must be kept if the tunnel rejects the new one, since it is the parent
+    /// of the next exchange.
+    #[tokio::test]
+    async fn test_apply_rekeyed_peer_failure() {
+        let (tunnel, configs) = tunnel(true);
+        let mut config = config();
+        let old_config = config.clone();
+
+        let peer = EphemeralPeer {
+            psk: Some(PresharedKey::from(Box::new([5; 32]))),
+            daita: None,
+        };
+        let result =
+            apply_rekeyed_peer(&tunnel, &mut config, PrivateKey::from([4; 32]), peer).await;
+
+        assert!(result.is_err());
+        assert!(configs.lock().unwrap().is_empty());
+        assert_eq!(
+            config.tunnel.private_key.to_bytes(),
+            old_config.tunnel.private_key.to_bytes()
+        );
+        assert_eq!(config.entry_peer.psk, old_config.entry_peer.psk);
+    }
+}
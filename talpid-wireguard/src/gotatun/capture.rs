@@ -0,0 +1,188 @@
+//! GotaTun IP and UDP wrappers that copy packets to the active tunnel capture.
+//!
+//! See [`crate::capture`]. When no capture is running, packets are passed through untouched.
+
+use std::{io, net::SocketAddr};
+
+use gotatun::{
+    packet::{Ip, Packet, PacketBufPool},
+    tun::{IpRecv, IpSend, MtuWatcher},
+    udp::{UdpRecv, UdpSend, UdpTransportFactory, UdpTransportFactoryParams},
+};
+
+use crate::capture::{self, Direction};
+
+/// An [`IpSend`] and [`IpRecv`] wrapper that records plaintext tunnel packets. Packets sent to
+/// the wrapper are received from the tunnel, and packets received from it are sent through the
+/// tunnel.
+#[derive(Clone)]
+pub struct CaptureIp<T> {
+    inner: T,
+}
+
+impl<T> CaptureIp<T> {
+    pub fn new(inner: T) -> Self {
+        CaptureIp { inner }
+    }
+}
+
+impl<T: IpSend> IpSend for CaptureIp<T> {
+    async fn send(&mut self, packet: Packet<Ip>) -> io::Result<()> {
+        let packet = record_ip(packet, Direction::Inbound);
+        self.inner.send(packet).await
+    }
+}
+
+impl<T: IpRecv> IpRecv for CaptureIp<T> {
+    async fn recv<'a>(
+        &'a mut self,
+        pool: &mut PacketBufPool,
+    ) -> io::Result<impl Iterator<Item = Packet<Ip>> + Send + 'a> {
+        let packets = self.inner.recv(pool).await?;
+        Ok(packets.map(|packet| record_ip(packet, Direction::Outbound)))
+    }
+
+    fn mtu(&self) -> MtuWatcher {
+        self.inner.mtu()
+    }
+}
+
+fn record_ip(packet: Packet<Ip>, direction: Direction) -> Packet<Ip> {
+    if !capture::capturing_inner() {
+        return packet;
+    }
+    let bytes = packet.into_bytes();
+    capture::record_inner(direction, &bytes);
+    bytes
+        .try_into_ip()
+        .expect("packet was a valid IP packet before it was recorded")
+}
+
+/// A [`UdpSend`] wrapper that records outgoing WireGuard packets.
+#[derive(Clone)]
+pub struct CaptureUdpSend<S: UdpSend> {
+    inner: S,
+}
+
+impl<S: UdpSend> UdpSend for CaptureUdpSend<S> {
+    type SendManyBuf = S::SendManyBuf;
+
+    async fn send_to(&self, packet: Packet, destination: SocketAddr) -> io::Result<()> {
+        if capture::capturing_outer() {
+            let local = self.inner.local_addr().ok().flatten();
+            capture::record_outer(Direction::Outbound, local, destination, &packet);
+        }
+        self.inner.send_to(packet, destination).await
+    }
+
+    fn max_number_of_packets_to_send(&self) -> usize {
+        self.inner.max_number_of_packets_to_send()
+    }
+
+    async fn send_many_to(
+        &self,
+        send_buf: &mut Self::SendManyBuf,
+        packets: &mut Vec<(Packet, SocketAddr)>,
+    ) -> io::Result<()> {
+        if capture::capturing_outer() {
+            let local = self.inner.local_addr().ok().flatten();
+            for (packet, destination) in packets.iter() {
+                capture::record_outer(Direction::Outbound, local, *destination, packet);
+            }
+        }
+        self.inner.send_many_to(send_buf, packets).await
+    }
+
+    fn local_addr(&self) -> io::Result<Option<SocketAddr>> {
+        self.inner.local_addr()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn set_fwmark(&self, mark: u32) -> io::Result<()> {
+        self.inner.set_fwmark(mark)
+    }
+}
+
+/// A [`UdpRecv`] wrapper that records incoming WireGuard packets.
+pub struct CaptureUdpRecv<R: UdpRecv> {
+    inner: R,
+    /// Local address of the socket, used as the destination of recorded packets.
+    local_addr: Option<SocketAddr>,
+}
+
+impl<R: UdpRecv> UdpRecv for CaptureUdpRecv<R> {
+    type RecvManyBuf = R::RecvManyBuf;
+
+    async fn recv_from(&mut self, pool: &mut PacketBufPool) -> io::Result<(Packet, SocketAddr)> {
+        let (packet, source) = self.inner.recv_from(pool).await?;
+        if capture::capturing_outer() {
+            capture::record_outer(Direction::Inbound, self.local_addr, source, &packet);
+        }
+        Ok((packet, source))
+    }
+
+    async fn recv_many_from(
+        &mut self,
+        recv_buf: &mut Self::RecvManyBuf,
+        pool: &mut PacketBufPool,
+        packets: &mut Vec<(Packet, SocketAddr)>,
+    ) -> io::Result<()> {
+        // The trait contract appends to `packets`, so only record the entries added here
+        let start = packets.len();
+        self.inner.recv_many_from(recv_buf, pool, packets).await?;
+        if capture::capturing_outer() {
+            for (packet, source) in &packets[start..] {
+                capture::record_outer(Direction::Inbound, self.local_addr, *source, packet);
+            }
+        }
+        Ok(())
+    }
+
+    fn enable_udp_gro(&self) -> io::Result<()> {
+        self.inner.enable_udp_gro()
+    }
+}
+
+/// A [`UdpTransportFactory`] that wraps another factory and records the packets that pass
+/// through it.
+pub struct CaptureTransportFactory<F: UdpTransportFactory> {
+    inner: F,
+}
+
+impl<F: UdpTransportFactory> CaptureTransportFactory<F> {
+    pub fn new(inner: F) -> Self {
+        CaptureTransportFactory { inner }
+    }
+}
+
+impl<F: UdpTransportFactory> UdpTransportFactory for CaptureTransportFactory<F> {
+    type SendV4 = CaptureUdpSend<F::SendV4>;
+    type SendV6 = CaptureUdpSend<F::SendV6>;
+    type RecvV4 = CaptureUdpRecv<F::RecvV4>;
+    type RecvV6 = CaptureUdpRecv<F::RecvV6>;
+
+    async fn bind(
+        &mut self,
+        params: &UdpTransportFactoryParams,
+    ) -> io::Result<((Self::SendV4, Self::RecvV4), (Self::SendV6, Self::RecvV6))> {
+        let ((send_v4, recv_v4), (send_v6, recv_v6)) = self.inner.bind(params).await?;
+        let local_v4 = send_v4.local_addr().ok().flatten();
+        let local_v6 = send_v6.local_addr().ok().flatten();
+        Ok((
+            (
+                CaptureUdpSend { inner: send_v4 },
+                CaptureUdpRecv {
+                    inner: recv_v4,
+                    local_addr: local_v4,
+                },
+            ),
+            (
+                CaptureUdpSend { inner: send_v6 },
+                CaptureUdpRecv {
+                    inner: recv_v6,
+                    local_addr: local_v6,
+                },
+            ),
+        ))
+    }
+}
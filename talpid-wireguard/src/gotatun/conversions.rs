@@ -28,6 +28,10 @@ pub fn to_gotatun_peer(peer: &PeerConfig, daita: Option<&DaitaSettings>) -> Peer
     }
 
     if let Some(daita) = daita {
+        match daita.level {
+            Some(level) => log::debug!("Configuring DAITA machines for level {level}"),
+            None => log::debug!("Configuring DAITA machines for the default level"),
+        }
         let daita = gotatun::device::daita::DaitaSettings {
             maybenot_machines: daita.client_machines.clone(),
             max_decoy_frac: daita.max_decoy_frac,
@@ -35,7 +35,7 @@ use std::{
 use talpid_net::bypass::SocketBypass;
 use talpid_tunnel::tun_provider::{self, Tun, TunProvider};
 use talpid_tunnel_config_client::DaitaSettings;
-use talpid_types::net::wireguard::PeerConfig;
+use talpid_types::net::wireguard::{PeerConfig, PublicKey};
 use tun::{AbstractDevice, AsyncDevice};
 use tunnel_obfuscation::Settings as ObfuscationSettings;
 
@@ -45,9 +45,11 @@ use gotatun::tun::{
     pcap::{PcapSniffer, PcapStream},
 };
 
+mod capture;
 mod conversions;
 mod obfuscation;
 
+use capture::{CaptureIp, CaptureTransportFactory};
 use conversions::to_gotatun_peer;
 use obfuscation::MaybeObfuscatingTransportFactory;
 
@@ -57,10 +59,14 @@ type UdpFactory = AndroidUdpSocketFactory;
 #[cfg(not(target_os = "android"))]
 type UdpFactory = UdpSocketFactory;
 
-type TransportFactory = MaybeObfuscatingTransportFactory<UdpFactory>;
+/// Outer packets are captured before they are obfuscated.
+type TransportFactory = CaptureTransportFactory<MaybeObfuscatingTransportFactory<UdpFactory>>;
 
-type SinglehopDevice = Device<(TransportFactory, GotaTunDevice, GotaTunDevice)>;
-type ExitDevice = Device<(UdpChannelFactory, GotaTunDevice, GotaTunDevice)>;
+/// The tunnel device, with plaintext packets copied to the active capture, if any.
+type TunnelDevice = CaptureIp<GotaTunDevice>;
+
+type SinglehopDevice = Device<(TransportFactory, TunnelDevice, TunnelDevice)>;
+type ExitDevice = Device<(UdpChannelFactory, TunnelDevice, TunnelDevice)>;
 
 #[cfg(not(all(feature = "multihop-pcap", target_os = "linux")))]
 type EntryDevice = Device<(TransportFactory, TunChannelTx, TunChannelRx)>;
@@ -521,6 +527,35 @@ impl Tunnel for GotaTun {
             Ok(())
         })
     }
+
+    async fn add_peer(&mut self, peer: &PeerConfig) -> Result<(), TunnelError> {
+        // Peers can only be switched on a singlehop device
+        let Some(Devices::Singlehop(Singlehop { device })) = self.devices.as_ref() else {
+            return Err(TunnelError::PeerSwitchUnsupported);
+        };
+        let gotatun_peer = to_gotatun_peer(peer, None);
+        device
+            .write(async |device| {
+                device.add_peer(gotatun_peer);
+            })
+            .await
+            .map_err(TunnelError::GotaTunDevice)?;
+        self.config.entry_peer = peer.clone();
+        Ok(())
+    }
+
+    async fn remove_peer(&mut self, public_key: &PublicKey) -> Result<(), TunnelError> {
+        let Some(Devices::Singlehop(Singlehop { device })) = self.devices.as_ref() else {
+            return Err(TunnelError::PeerSwitchUnsupported);
+        };
+        let public_key = gotatun::x25519::PublicKey::from(*public_key.as_bytes());
+        device
+            .write(async |device| {
+                device.remove_peer(&public_key);
+            })
+            .await
+            .map_err(TunnelError::GotaTunDevice)
+    }
 }
 
 /// Create and configure gotatun devices.
@@ -553,13 +588,13 @@ async fn create_devices(
         bypass: Arc<dyn SocketBypass>,
         optimize_buffer_size: bool,
     ) -> Result<Devices, gotatun::device::Error> {
-        let factory = udp_obfuscator_factory(
+        let factory = CaptureTransportFactory::new(udp_obfuscator_factory(
             config.obfuscation_settings().as_ref(),
             optimize_buffer_size,
             #[cfg(target_os = "android")]
             android_tun,
             bypass,
-        );
+        ));
         let devices = if let Some(exit_peer) = &config.exit_peer {
             // Multihop setup
             let source_v4 = config
@@ -596,7 +631,7 @@ async fn create_devices(
 
             let exit_device = DeviceBuilder::new()
                 .with_udp(udp_channels)
-                .with_ip(tun_dev)
+                .with_ip(CaptureIp::new(tun_dev))
                 .build()
                 .await?;
 
@@ -622,7 +657,7 @@ async fn create_devices(
 
             let device = DeviceBuilder::new()
                 .with_udp(factory)
-                .with_ip(tun_dev)
+                .with_ip(CaptureIp::new(tun_dev))
                 .build()
                 .await?;
             let mut device = Singlehop { device };
@@ -705,7 +740,7 @@ fn get_tunnel_for_userspace(
     let tun_config = tun_provider.config_mut();
     #[cfg(target_os = "linux")]
     {
-        tun_config.name = Some(crate::config::MULLVAD_INTERFACE_NAME.to_string());
+        tun_config.name = Some(config.interface_name.clone());
         tun_config.packet_information = false;
     }
     tun_config.addresses = config.tunnel.addresses.clone();
@@ -16,7 +16,8 @@ use std::{
     net::IpAddr,
     path::Path,
     pin::Pin,
-    sync::{Arc, mpsc as sync_mpsc},
+    sync::{Arc, Mutex, mpsc as sync_mpsc},
+    time::{Duration, SystemTime},
 };
 #[cfg(not(target_os = "android"))]
 use talpid_routing::{self, RequiredRoute};
@@ -26,18 +27,26 @@ use talpid_tunnel::{IPV4_HEADER_SIZE, IPV6_HEADER_SIZE, WIREGUARD_HEADER_SIZE};
 use talpid_tunnel_config_client::DaitaSettings;
 use talpid_types::{
     BoxedError, ErrorExt,
-    net::{AllowedTunnelTraffic, Endpoint, TransportProtocol, wireguard::TunnelParameters},
+    net::{
+        AllowedTunnelTraffic, Endpoint, ObfuscationInfo, TransportProtocol,
+        wireguard::{PeerConfig, PublicKey, TunnelParameters},
+    },
 };
 use tokio::sync::Mutex as AsyncMutex;
 
 mod gotatun;
 
+/// On-demand capture of tunnel traffic
+pub mod capture;
 /// WireGuard config data-types
 pub mod config;
 mod connectivity;
 mod ephemeral;
 mod logging;
+#[cfg(target_os = "linux")]
+mod netns;
 mod obfuscation;
+mod peer_switch;
 mod stats;
 #[cfg(target_os = "linux")]
 pub(crate) mod wireguard_kernel;
@@ -47,6 +56,11 @@ mod wireguard_nt;
 #[cfg(not(target_os = "android"))]
 mod mtu_detection;
 
+pub use peer_switch::can_switch_peer;
+pub use stats::{DaitaStats, Stats, StatsMap};
+#[cfg(target_os = "linux")]
+pub use wireguard_kernel::is_interface_in_use;
+
 type TunnelType = Box<dyn Tunnel>;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -91,6 +105,19 @@ pub enum Error {
     #[cfg(target_os = "windows")]
     #[error("Failed to set IP addresses on WireGuard interface")]
     SetIpAddressesError(#[source] talpid_windows::net::Error),
+
+    /// Failed to move the tunnel into its network namespace
+    #[cfg(target_os = "linux")]
+    #[error("Failed to move the tunnel into its network namespace")]
+    NamespaceError(#[source] netns::Error),
+
+    /// The tunnel has already been torn down
+    #[error("The tunnel has been torn down")]
+    TunnelDown,
+
+    /// No handshake with the new peer completed while switching peers
+    #[error("Timed out waiting for a handshake with the new peer")]
+    PeerSwitchTimeout,
 }
 
 impl Error {
@@ -125,6 +152,66 @@ impl Error {
     }
 }
 
+/// Reads the traffic stats and rekey status of a tunnel started by a [`WireguardMonitor`], and
+/// switches its peer while it is up.
+#[derive(Clone)]
+pub struct TunnelStatsHandle {
+    tunnel: Arc<AsyncMutex<Option<TunnelType>>>,
+    last_rekey: Arc<Mutex<Option<SystemTime>>>,
+    /// Set if the tunnel supports switching peers in place.
+    #[cfg(not(target_os = "android"))]
+    peer_switch_routes: Option<talpid_routing::RouteManagerHandle>,
+}
+
+impl TunnelStatsHandle {
+    /// Return the stats of each peer, keyed by public key. Returns `None` if the tunnel has been
+    /// torn down or the stats could not be read.
+    pub async fn get(&self) -> Option<StatsMap> {
+        let tunnel = self.tunnel.lock().await;
+        tunnel.as_ref()?.get_tunnel_stats().await.ok()
+    }
+
+    /// Return when the quantum-resistant PSK was last renegotiated, or `None` if it has not been
+    /// renegotiated since the tunnel was established.
+    pub fn last_rekey(&self) -> Option<SystemTime> {
+        *self.last_rekey.lock().unwrap()
+    }
+
+    /// Returns whether the tunnel supports [`Self::switch_peer`] at all.
+    pub fn supports_peer_switch(&self) -> bool {
+        #[cfg(not(target_os = "android"))]
+        {
+            self.peer_switch_routes.is_some()
+        }
+        #[cfg(target_os = "android")]
+        {
+            false
+        }
+    }
+
+    /// Returns whether the tunnel, started with `current`, can be moved to `new` using
+    /// [`Self::switch_peer`].
+    pub fn can_switch_peer(&self, current: &TunnelParameters, new: &TunnelParameters) -> bool {
+        self.supports_peer_switch() && can_switch_peer(current, new)
+    }
+
+    /// Move the tunnel, started with `current`, to the entry peer of `new` without tearing it
+    /// down. The new peer is added before the current one is removed. On failure, the tunnel is
+    /// left in an unknown state and should be reconnected.
+    pub async fn switch_peer(
+        &self,
+        current: &TunnelParameters,
+        new: &TunnelParameters,
+    ) -> Result<()> {
+        #[cfg(not(target_os = "android"))]
+        if let Some(route_manager) = &self.peer_switch_routes {
+            return peer_switch::switch_peer(&self.tunnel, route_manager, current, new).await;
+        }
+        let _ = (current, new);
+        Err(Error::TunnelError(TunnelError::PeerSwitchUnsupported))
+    }
+}
+
 /// Spawns and monitors a wireguard tunnel
 pub struct WireguardMonitor {
     runtime: tokio::runtime::Handle,
@@ -135,6 +222,12 @@ pub struct WireguardMonitor {
     close_msg_receiver: sync_mpsc::Receiver<CloseMsg>,
     pinger_stop_sender: connectivity::CancelToken,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
+    /// When the ephemeral peers were last renegotiated
+    last_rekey: Arc<Mutex<Option<SystemTime>>>,
+    /// Used to route new peer endpoints when switching peers. Only set for tunnels that support
+    /// switching peers in place.
+    #[cfg(not(target_os = "android"))]
+    peer_switch_routes: Option<talpid_routing::RouteManagerHandle>,
 }
 
 #[cfg(not(target_os = "android"))]
@@ -171,7 +264,15 @@ impl WireguardMonitor {
         args: TunnelArgs<'_>,
         _log_path: Option<&Path>,
     ) -> Result<WireguardMonitor> {
-        let require_userspace_wireguard = params.use_userspace_wg() || *FORCE_USERSPACE_WIREGUARD;
+        #[cfg(target_os = "linux")]
+        let in_namespace = args.netns.is_some();
+        #[cfg(not(target_os = "linux"))]
+        let in_namespace = false;
+
+        // The stats of a kernel device cannot be read once it has been moved into another
+        // namespace, so GotaTun is required when the tunnel is confined to one.
+        let require_userspace_wireguard =
+            params.use_userspace_wg() || *FORCE_USERSPACE_WIREGUARD || in_namespace;
         let userspace_obfuscation = obfuscation::userspace_transport_available(params)
             && !*FORCE_LOCAL_SOCKET_OBFUSCATION
             && !*FORCE_KERNEL_WIREGUARD;
@@ -247,13 +348,16 @@ impl WireguardMonitor {
             close_msg_receiver: close_obfs_listener,
             pinger_stop_sender: cancel_token,
             obfuscator,
+            last_rekey: Arc::new(Mutex::new(None)),
+            peer_switch_routes: userspace_wireguard.then(|| args.route_manager.clone()),
         };
 
         let mut event_hook = args.event_hook.clone();
         let moved_tunnel = monitor.tunnel.clone();
         let moved_close_obfs_sender = close_obfs_sender.clone();
         let moved_obfuscator = monitor.obfuscator.clone();
-        let detect_mtu = params.options.mtu.is_none();
+        let last_rekey = monitor.last_rekey.clone();
+        let detect_mtu = params.options.mtu.is_none() && !in_namespace;
         let tunnel_fut = async move {
             let tunnel = moved_tunnel;
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
@@ -367,30 +471,63 @@ impl WireguardMonitor {
             }?;
             drop(lock);
 
+            // Only route the traffic of the namespace through the tunnel, if there is one.
+            #[cfg(target_os = "linux")]
+            if let Some(netns) = args.netns.as_deref() {
+                netns::move_tunnel(netns, &iface_name, &config)
+                    .await
+                    .map_err(Error::NamespaceError)
+                    .map_err(CloseMsg::SetupError)?;
+                connectivity_monitor
+                    .move_to_namespace(netns, gateway, iface_name.clone())
+                    .map_err(Error::ConnectivityMonitorError)
+                    .map_err(CloseMsg::SetupError)?;
+            }
+
             // Add any default route(s) that may exist.
-            args.route_manager
-                .add_routes(
-                    Self::get_post_tunnel_routes(&iface_name, &config, userspace_wireguard)
-                        .collect(),
-                )
-                .await
-                .map_err(Error::SetupRoutingError)
-                .map_err(CloseMsg::SetupError)?;
+            if !in_namespace {
+                args.route_manager
+                    .add_routes(
+                        Self::get_post_tunnel_routes(&iface_name, &config, userspace_wireguard)
+                            .collect(),
+                    )
+                    .await
+                    .map_err(Error::SetupRoutingError)
+                    .map_err(CloseMsg::SetupError)?;
+            }
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let mut metadata = Self::tunnel_metadata(&iface_name, &config);
+            metadata.selected_obfuscation = Self::selected_obfuscation(&config, &obfuscator).await;
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
-                .run(Arc::downgrade(&tunnel))
-                .await
-            {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
-            }
+            let connectivity = async {
+                if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
+                    .run(Arc::downgrade(&tunnel))
+                    .await
+                {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Connectivity monitor failed")
+                    );
+                }
+                Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            };
 
-            Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            match rekey_interval(&config) {
+                Some(interval) => {
+                    let rekey = ephemeral::rekey_ephemeral_peers(
+                        interval,
+                        &tunnel,
+                        &mut config,
+                        &last_rekey,
+                    );
+                    tokio::select! {
+                        result = connectivity => result,
+                        result = rekey => result,
+                    }
+                }
+                None => connectivity.await,
+            }
         };
 
         let close_sender = close_obfs_sender.clone();
@@ -484,10 +621,12 @@ impl WireguardMonitor {
             close_msg_receiver: close_obfs_listener,
             pinger_stop_sender: cancel_token,
             obfuscator: Arc::new(AsyncMutex::new(obfuscator)),
+            last_rekey: Arc::new(Mutex::new(None)),
         };
 
         let moved_close_obfs_sender = close_obfs_sender.clone();
         let moved_obfuscator = monitor.obfuscator.clone();
+        let last_rekey = monitor.last_rekey.clone();
         let tunnel_fut = async move {
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
             let obfuscator = moved_obfuscator;
@@ -550,20 +689,38 @@ impl WireguardMonitor {
                     .await;
             }
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let mut metadata = Self::tunnel_metadata(&iface_name, &config);
+            metadata.selected_obfuscation = Self::selected_obfuscation(&config, &obfuscator).await;
             event_hook.on_event(TunnelEvent::Up(metadata)).await;
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
-                .run(Arc::downgrade(&tunnel))
-                .await
-            {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
-            }
+            let connectivity = async {
+                if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
+                    .run(Arc::downgrade(&tunnel))
+                    .await
+                {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Connectivity monitor failed")
+                    );
+                }
+                Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            };
 
-            Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            match rekey_interval(&config) {
+                Some(interval) => {
+                    let rekey = ephemeral::rekey_ephemeral_peers(
+                        interval,
+                        &tunnel,
+                        &mut config,
+                        &last_rekey,
+                    );
+                    tokio::select! {
+                        result = connectivity => result,
+                        result = rekey => result,
+                    }
+                }
+                None => connectivity.await,
+            }
         };
 
         let close_sender = close_obfs_sender.clone();
@@ -731,6 +888,16 @@ impl WireguardMonitor {
         }
     }
 
+    /// Return a handle for reading the traffic stats of the tunnel while it is up.
+    pub fn stats_handle(&self) -> TunnelStatsHandle {
+        TunnelStatsHandle {
+            tunnel: self.tunnel.clone(),
+            last_rekey: self.last_rekey.clone(),
+            #[cfg(not(target_os = "android"))]
+            peer_switch_routes: self.peer_switch_routes.clone(),
+        }
+    }
+
     /// Blocks the current thread until tunnel disconnects
     pub fn wait(mut self) -> Result<()> {
         let wait_result = {
@@ -938,8 +1105,20 @@ impl WireguardMonitor {
             ips: config.tunnel.addresses.clone(),
             ipv4_gateway: config.ipv4_gateway,
             ipv6_gateway: config.ipv6_gateway,
+            daita: config.daita_parameters,
+            selected_obfuscation: None,
         }
     }
+
+    /// Return the endpoint that the obfuscation multiplexer has selected, if any.
+    async fn selected_obfuscation(
+        config: &Config,
+        obfuscator: &AsyncMutex<Option<ObfuscatorHandle>>,
+    ) -> Option<Endpoint> {
+        let index = obfuscator.lock().await.as_ref()?.selected_transport()?;
+        let info = ObfuscationInfo::from(config.obfuscator_config.as_ref()?);
+        info.get_endpoints().get(index).copied()
+    }
 }
 
 fn get_obfuscator(
@@ -979,6 +1158,20 @@ fn get_obfuscator(
     Ok(Some(obfuscator))
 }
 
+/// Return how often the ephemeral peer of a tunnel using `config` should be renegotiated, if at
+/// all.
+fn rekey_interval(config: &Config) -> Option<Duration> {
+    let interval = config.rekey_interval.filter(|_| config.quantum_resistant)?;
+    if !config.can_rekey_in_place() {
+        log::info!(
+            "Not renegotiating the ephemeral peer periodically, since the tunnel cannot be rekeyed \
+             without reconnecting"
+        );
+        return None;
+    }
+    Some(interval)
+}
+
 /// Log the tunnel stats from the current tunnel.
 ///
 /// This will log the amount of outgoing and incoming data to and from the exit (and entry) relay
@@ -1046,6 +1239,17 @@ pub(crate) trait Tunnel: Send + Sync {
         _config: Config,
         _daita: Option<DaitaSettings>,
     ) -> Pin<Box<dyn Future<Output = std::result::Result<(), TunnelError>> + Send + 'a>>;
+    /// Add `peer` next to the current peers. Its allowed IPs are taken over from them.
+    async fn add_peer(&mut self, _peer: &PeerConfig) -> std::result::Result<(), TunnelError> {
+        Err(TunnelError::PeerSwitchUnsupported)
+    }
+    /// Remove the peer with the given public key.
+    async fn remove_peer(
+        &mut self,
+        _public_key: &PublicKey,
+    ) -> std::result::Result<(), TunnelError> {
+        Err(TunnelError::PeerSwitchUnsupported)
+    }
 }
 
 /// Errors to be returned from WireGuard implementations, namely implementers of the Tunnel trait
@@ -1126,6 +1330,10 @@ pub enum TunnelError {
     /// GotaTun device error
     #[error("GotaTun: {0:?}")]
     GotaTunDevice(::gotatun::device::Error),
+
+    /// This tunnel cannot switch peers without being recreated.
+    #[error("Tunnel implementation does not support switching peers")]
+    PeerSwitchUnsupported,
 }
 
 #[cfg(target_os = "linux")]
@@ -0,0 +1,189 @@
+//! Confine an established tunnel to a network namespace.
+//!
+//! The tunnel interface is moved into the namespace once connectivity has been established. The
+//! sockets that carry the encrypted traffic stay in the host namespace, so the tunnel itself is
+//! still routed by the host, while only processes inside the namespace use the tunnel.
+
+use crate::{config::Config, wireguard_kernel::add_ip_addr_message};
+use futures::future::{AbortHandle, abortable};
+use netlink_packet_core::{
+    NLM_F_ACK, NLM_F_CREATE, NLM_F_REPLACE, NLM_F_REQUEST, NetlinkMessage, NetlinkPayload,
+};
+use netlink_packet_route::RouteNetlinkMessage;
+use rtnetlink::{LinkMessageBuilder, LinkUnspec, RouteMessageBuilder};
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    os::fd::{AsFd, AsRawFd},
+};
+use talpid_netns::NetNs;
+use tokio_stream::StreamExt;
+
+/// Errors that can occur while moving the tunnel into a network namespace.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to open a netlink socket.
+    #[error("Failed to open netlink socket")]
+    NetlinkSocket(#[source] std::io::Error),
+
+    /// Failed to enter the network namespace.
+    #[error("Failed to enter the network namespace")]
+    Namespace(#[source] talpid_netns::Error),
+
+    /// The tunnel interface could not be found.
+    #[error("Tunnel interface {0} does not exist")]
+    NoInterface(String),
+
+    /// Failed to look up the tunnel interface.
+    #[error("Failed to look up tunnel interface")]
+    GetLink(#[source] rtnetlink::Error),
+
+    /// Failed to move the tunnel interface into the namespace.
+    #[error("Failed to move tunnel interface into the network namespace")]
+    MoveLink(#[source] rtnetlink::Error),
+
+    /// Failed to bring the tunnel interface up.
+    #[error("Failed to bring up tunnel interface")]
+    SetLinkUp(#[source] rtnetlink::Error),
+
+    /// Failed to assign an IP address to the tunnel interface.
+    #[error("Failed to set IP address of tunnel interface")]
+    SetIpAddress(#[source] rtnetlink::Error),
+
+    /// Failed to add a default route via the tunnel interface.
+    #[error("Failed to add default route")]
+    AddRoute(#[source] rtnetlink::Error),
+}
+
+/// Move `interface` into `netns` and route all traffic in the namespace through it.
+///
+/// Moving a link resets its addresses and state, so these are configured again inside the
+/// namespace.
+pub async fn move_tunnel(netns: &NetNs, interface: &str, config: &Config) -> Result<(), Error> {
+    let host = Connection::new()?;
+    let index = host.link_index(interface).await?;
+    host.handle
+        .link()
+        .set(
+            LinkMessageBuilder::<LinkUnspec>::new_with_index(index)
+                .setns_by_fd(netns.as_fd().as_raw_fd())
+                .build(),
+        )
+        .execute()
+        .await
+        .map_err(Error::MoveLink)?;
+
+    let runtime = tokio::runtime::Handle::current();
+    let namespace = netns
+        .run(move || {
+            let _guard = runtime.enter();
+            Connection::new()
+        })
+        .map_err(Error::Namespace)??;
+    let index = namespace.link_index(interface).await?;
+
+    namespace
+        .handle
+        .link()
+        .set(
+            LinkMessageBuilder::<LinkUnspec>::new_with_index(index)
+                .up()
+                .build(),
+        )
+        .execute()
+        .await
+        .map_err(Error::SetLinkUp)?;
+
+    for address in &config.tunnel.addresses {
+        namespace.set_ip_address(index, *address).await?;
+    }
+
+    namespace
+        .handle
+        .route()
+        .add(
+            RouteMessageBuilder::<Ipv4Addr>::new()
+                .output_interface(index)
+                .build(),
+        )
+        .execute()
+        .await
+        .map_err(Error::AddRoute)?;
+    if config.enable_ipv6 {
+        namespace
+            .handle
+            .route()
+            .add(
+                RouteMessageBuilder::<Ipv6Addr>::new()
+                    .output_interface(index)
+                    .build(),
+            )
+            .execute()
+            .await
+            .map_err(Error::AddRoute)?;
+    }
+
+    log::debug!(
+        "Moved tunnel interface {interface} into network namespace {}",
+        netns.name()
+    );
+
+    Ok(())
+}
+
+/// A route netlink connection bound to the namespace that it was created in.
+struct Connection {
+    handle: rtnetlink::Handle,
+    abort_handle: AbortHandle,
+}
+
+impl Connection {
+    fn new() -> Result<Self, Error> {
+        let (connection, handle, _messages) =
+            rtnetlink::new_connection().map_err(Error::NetlinkSocket)?;
+        let (connection, abort_handle) = abortable(connection);
+        tokio::spawn(connection);
+        Ok(Self {
+            handle,
+            abort_handle,
+        })
+    }
+
+    async fn link_index(&self, interface: &str) -> Result<u32, Error> {
+        let mut links = self
+            .handle
+            .link()
+            .get()
+            .match_name(interface.to_owned())
+            .execute();
+        match links.next().await {
+            Some(Ok(link)) => Ok(link.header.index),
+            Some(Err(error)) => Err(Error::GetLink(error)),
+            None => Err(Error::NoInterface(interface.to_owned())),
+        }
+    }
+
+    async fn set_ip_address(&self, index: u32, address: IpAddr) -> Result<(), Error> {
+        let mut request = NetlinkMessage::from(RouteNetlinkMessage::NewAddress(
+            add_ip_addr_message(index, address),
+        ));
+        request.header.flags = NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_REPLACE;
+
+        let mut response = self
+            .handle
+            .clone()
+            .request(request)
+            .map_err(Error::SetIpAddress)?;
+        while let Some(message) = response.next().await {
+            if let NetlinkPayload::Error(error) = message.payload {
+                return Err(Error::SetIpAddress(rtnetlink::Error::NetlinkError(error)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
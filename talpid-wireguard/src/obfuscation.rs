@@ -41,6 +41,11 @@ pub async fn spawn_local_socket_obfuscator(
 ) -> Result<ObfuscatorHandle> {
     log::trace!("Obfuscation settings: {obfuscation_settings:?}");
 
+    let selected_transport = match &obfuscation_settings {
+        ObfuscationSettings::Multiplexer(settings) => Some(settings.selected.clone()),
+        _ => None,
+    };
+
     let bypass = Arc::new(ObfuscatorSocketBypass {
         #[cfg(target_os = "linux")]
         fwmark: fwmark.unwrap_or_else(|| {
@@ -79,6 +84,7 @@ pub async fn spawn_local_socket_obfuscator(
     Ok(ObfuscatorHandle {
         obfuscation_task,
         packet_overhead,
+        selected_transport,
     })
 }
 
@@ -116,6 +122,7 @@ pub fn settings_from_config(
         Obfuscators::Multiplexer {
             direct,
             configs: (first_obfs, remaining_obfs),
+            stagger_delay,
         } => {
             let mut transports = vec![];
             if let Some(direct) = direct {
@@ -130,7 +137,11 @@ pub fn settings_from_config(
                 );
                 transports.push(multiplexer::Transport::Obfuscated(settings));
             }
-            ObfuscationSettings::Multiplexer(multiplexer::Settings { transports })
+            ObfuscationSettings::Multiplexer(multiplexer::Settings {
+                transports,
+                stagger_delay: *stagger_delay,
+                selected: multiplexer::SelectedTransport::default(),
+            })
         }
     }
 }
@@ -182,6 +193,7 @@ fn settings_from_single_config(
 pub struct ObfuscatorHandle {
     obfuscation_task: tokio::task::JoinHandle<()>,
     packet_overhead: u16,
+    selected_transport: Option<multiplexer::SelectedTransport>,
 }
 
 impl ObfuscatorHandle {
@@ -192,6 +204,14 @@ impl ObfuscatorHandle {
     pub fn packet_overhead(&self) -> u16 {
         self.packet_overhead
     }
+
+    /// Return the index of the transport that a multiplexing obfuscator has selected, if any.
+    /// The index refers to the order in [ObfuscationInfo::get_endpoints].
+    ///
+    /// [ObfuscationInfo::get_endpoints]: talpid_types::net::ObfuscationInfo::get_endpoints
+    pub fn selected_transport(&self) -> Option<usize> {
+        self.selected_transport.as_ref()?.get()
+    }
 }
 
 impl Drop for ObfuscatorHandle {
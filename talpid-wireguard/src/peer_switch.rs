@@ -0,0 +1,246 @@
+//! Switching the entry relay of a running tunnel without tearing it down.
+//!
+//! The new peer is added next to the current one and takes over its allowed IPs, so traffic moves
+//! to the new relay right away. The old peer is removed once a handshake with the new peer has
+//! completed. The tunnel interface, its addresses and routes are left untouched, so connections
+//! through the tunnel survive the switch.
+
+#[cfg(not(target_os = "android"))]
+use std::time::Duration;
+
+use talpid_types::net::wireguard::TunnelParameters;
+#[cfg(not(target_os = "android"))]
+use tokio::sync::Mutex as AsyncMutex;
+
+#[cfg(not(target_os = "android"))]
+use crate::{Error, TunnelType};
+#[cfg(not(target_os = "android"))]
+use talpid_types::ErrorExt;
+
+/// How long to wait for a handshake with the new peer before giving up.
+#[cfg(not(target_os = "android"))]
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often to check whether the new peer has completed a handshake.
+#[cfg(not(target_os = "android"))]
+const HANDSHAKE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Returns whether a tunnel started with `current` can be moved to `new` by only replacing its
+/// peer. Anything that changes the tunnel interface, or that has to be negotiated with the relay,
+/// requires a full reconnect.
+///
+/// This includes quantum resistance and DAITA, since an ephemeral peer would have to be negotiated
+/// with the new relay before traffic can move to it. Quantum resistance is enabled by default, so
+/// peers are only switched if the user has turned it off.
+pub fn can_switch_peer(current: &TunnelParameters, new: &TunnelParameters) -> bool {
+    fn is_plain_singlehop(params: &TunnelParameters) -> bool {
+        params.connection.exit_peer.is_none()
+            && params.obfuscation.is_none()
+            && params.connection.peer.psk.is_none()
+            && !params.options.quantum_resistant
+            && !params.options.daita
+    }
+
+    let current_peer = &current.connection.peer;
+    let new_peer = &new.connection.peer;
+
+    is_plain_singlehop(current)
+        && is_plain_singlehop(new)
+        && current_peer.public_key != new_peer.public_key
+        && current_peer.allowed_ips == new_peer.allowed_ips
+        // The tunnel MTU depends on the IP version of the endpoint
+        && current_peer.endpoint.is_ipv4() == new_peer.endpoint.is_ipv4()
+        && current.connection.tunnel == new.connection.tunnel
+        && current.connection.ipv4_gateway == new.connection.ipv4_gateway
+        && current.connection.ipv6_gateway == new.connection.ipv6_gateway
+        && same_linux_identifiers(current, new)
+        && current.options == new.options
+        && current.generic_options == new.generic_options
+}
+
+#[cfg(target_os = "linux")]
+fn same_linux_identifiers(current: &TunnelParameters, new: &TunnelParameters) -> bool {
+    current.connection.fwmark == new.connection.fwmark
+        && current.connection.interface_name == new.connection.interface_name
+}
+
+#[cfg(not(target_os = "linux"))]
+fn same_linux_identifiers(_current: &TunnelParameters, _new: &TunnelParameters) -> bool {
+    true
+}
+
+/// Replace the entry peer of `tunnel`, which was started with `current`, by the peer in `new`.
+///
+/// The caller must have checked [`can_switch_peer`], and must allow traffic to the new endpoint
+/// in the firewall before calling this. On success, the endpoint route of the old relay is
+/// removed. On failure, the endpoint route of the new relay is removed instead.
+#[cfg(not(target_os = "android"))]
+pub(crate) async fn switch_peer(
+    tunnel: &AsyncMutex<Option<TunnelType>>,
+    route_manager: &talpid_routing::RouteManagerHandle,
+    current: &TunnelParameters,
+    new: &TunnelParameters,
+) -> Result<(), Error> {
+    let new_peer = &new.connection.peer;
+    log::info!(
+        "Switching peer from {} to {}",
+        current.connection.peer.endpoint,
+        new_peer.endpoint
+    );
+
+    let result = add_new_peer(tunnel, route_manager, current, new).await;
+
+    let (unused, in_use) = match result {
+        Ok(()) => (current, new),
+        Err(_) => (new, current),
+    };
+    let unused_ip = unused.connection.peer.endpoint.ip();
+    if unused_ip != in_use.connection.peer.endpoint.ip()
+        && let Err(error) = route_manager
+            .delete_routes(crate::WireguardMonitor::get_endpoint_routes(&[unused_ip]).collect())
+            .await
+    {
+        log::error!(
+            "{}",
+            error.display_chain_with_msg("Failed to remove endpoint route")
+        );
+    }
+
+    result?;
+    log::info!("Switched peer to {}", new_peer.endpoint);
+    Ok(())
+}
+
+/// Route traffic to the entry peer of `new`, add it to the tunnel, and remove the entry peer of
+/// `current` once a handshake has completed.
+#[cfg(not(target_os = "android"))]
+async fn add_new_peer(
+    tunnel: &AsyncMutex<Option<TunnelType>>,
+    route_manager: &talpid_routing::RouteManagerHandle,
+    current: &TunnelParameters,
+    new: &TunnelParameters,
+) -> Result<(), Error> {
+    let endpoint_ip = [new.connection.peer.endpoint.ip()];
+    route_manager
+        .add_routes(crate::WireguardMonitor::get_endpoint_routes(&endpoint_ip).collect())
+        .await
+        .map_err(Error::SetupRoutingError)?;
+
+    tunnel
+        .lock()
+        .await
+        .as_mut()
+        .ok_or(Error::TunnelDown)?
+        .add_peer(&new.connection.peer)
+        .await?;
+
+    tokio::time::timeout(HANDSHAKE_TIMEOUT, wait_for_handshake(tunnel, new))
+        .await
+        .map_err(|_| Error::PeerSwitchTimeout)??;
+
+    tunnel
+        .lock()
+        .await
+        .as_mut()
+        .ok_or(Error::TunnelDown)?
+        .remove_peer(&current.connection.peer.public_key)
+        .await?;
+
+    Ok(())
+}
+
+/// Wait until the entry peer of `new` has completed a handshake.
+#[cfg(not(target_os = "android"))]
+async fn wait_for_handshake(
+    tunnel: &AsyncMutex<Option<TunnelType>>,
+    new: &TunnelParameters,
+) -> Result<(), Error> {
+    let public_key = new.connection.peer.public_key.as_bytes();
+    loop {
+        let stats = {
+            let tunnel = tunnel.lock().await;
+            tunnel
+                .as_ref()
+                .ok_or(Error::TunnelDown)?
+                .get_tunnel_stats()
+                .await?
+        };
+        if stats
+            .get(public_key)
+            .is_some_and(|stats| stats.last_handshake_time.is_some())
+        {
+            return Ok(());
+        }
+        tokio::time::sleep(HANDSHAKE_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use talpid_types::net::{
+        GenericTunnelOptions,
+        wireguard::{ConnectionConfig, PeerConfig, PrivateKey, TunnelConfig, TunnelOptions},
+    };
+
+    fn params(peer_key: [u8; 32], endpoint: &str) -> TunnelParameters {
+        TunnelParameters {
+            connection: ConnectionConfig {
+                tunnel: TunnelConfig {
+                    private_key: PrivateKey::from([1; 32]),
+                    addresses: vec!["10.64.0.2".parse().unwrap()],
+                },
+                peer: PeerConfig {
+                    public_key: peer_key.into(),
+                    allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
+                    endpoint: endpoint.parse().unwrap(),
+                    psk: None,
+                    constant_packet_size: false,
+                },
+                exit_peer: None,
+                ipv4_gateway: Ipv4Addr::new(10, 64, 0, 1),
+                ipv6_gateway: None,
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+                #[cfg(target_os = "linux")]
+                interface_name: None,
+                custom_entry: false,
+            },
+            options: TunnelOptions {
+                mtu: None,
+                quantum_resistant: false,
+                daita: false,
+                daita_level: None,
+                userspace: true,
+                rekey_interval: None,
+            },
+            generic_options: GenericTunnelOptions { enable_ipv6: false },
+            obfuscation: None,
+        }
+    }
+
+    #[test]
+    fn test_can_switch_peer() {
+        let current = params([2; 32], "192.0.2.1:51820");
+        let new = params([3; 32], "192.0.2.2:51820");
+        assert!(can_switch_peer(&current, &new));
+
+        // Reconnecting to the same relay should recreate the tunnel
+        assert!(!can_switch_peer(&current, &current.clone()));
+
+        let ipv6_endpoint = params([3; 32], "[2001:db8::1]:51820");
+        assert!(!can_switch_peer(&current, &ipv6_endpoint));
+
+        let mut quantum_resistant = new.clone();
+        quantum_resistant.options.quantum_resistant = true;
+        assert!(!can_switch_peer(&current, &quantum_resistant));
+
+        let mut multihop = new.clone();
+        multihop.connection.exit_peer = Some(current.connection.peer.clone());
+        assert!(!can_switch_peer(&current, &multihop));
+
+        let mut new_address = new.clone();
+        new_address.connection.tunnel.addresses = vec!["10.64.0.3".parse().unwrap()];
+        assert!(!can_switch_peer(&current, &new_address));
+    }
+}
@@ -21,6 +21,7 @@ use rtnetlink::LinkWireguard;
 use std::net::IpAddr;
 use std::net::Ipv4Addr;
 use std::net::Ipv6Addr;
+use talpid_types::ErrorExt;
 use tokio_stream::StreamExt;
 
 mod parsers;
@@ -239,6 +240,65 @@ impl Drop for Handle {
     }
 }
 
+/// Return whether a network interface named `name` is used by another program.
+///
+/// Interfaces that were created by this program are not considered to be in use. A WireGuard
+/// device with the firewall mark `fwmark` is assumed to be left behind by an earlier tunnel, and is
+/// removed. A tun device that this process has open belongs to a userspace tunnel that is being
+/// torn down.
+pub async fn is_interface_in_use(name: &str, fwmark: u32) -> bool {
+    let Ok(c_name) = std::ffi::CString::new(name) else {
+        return false;
+    };
+    // SAFETY: `c_name` is a valid nul-terminated string.
+    let index = unsafe { libc::if_nametoindex(c_name.as_ptr()) };
+    if index == 0 {
+        return false;
+    }
+    if is_own_tun_device(name) {
+        return false;
+    }
+
+    // The interface is not a kernel WireGuard device if the kernel does not support them
+    let Ok(mut handle) = Handle::connect().await else {
+        return true;
+    };
+    let is_own_device = match handle.wg_handle.get_by_name(name.to_owned()).await {
+        Ok(device) => device
+            .nlas
+            .iter()
+            .any(|nla| matches!(nla, DeviceNla::Fwmark(mark) if *mark == fwmark)),
+        Err(_) => false,
+    };
+    if !is_own_device {
+        return true;
+    }
+
+    log::debug!("Removing WireGuard device {name} left behind by an earlier tunnel");
+    if let Err(error) = handle.delete_device(index).await {
+        log::warn!(
+            "{}",
+            error.display_chain_with_msg("Failed to remove stale WireGuard device")
+        );
+    }
+    false
+}
+
+/// Return whether this process has the tun device `name` open. Each open tun device has an `iff`
+/// entry with its name in the fdinfo of its file descriptor.
+fn is_own_tun_device(name: &str) -> bool {
+    let Ok(entries) = std::fs::read_dir("/proc/self/fdinfo") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        std::fs::read_to_string(entry.path()).is_ok_and(|info| {
+            info.lines()
+                .filter_map(|line| line.strip_prefix("iff:"))
+                .any(|iff| iff.trim() == name)
+        })
+    })
+}
+
 #[derive(Debug, Clone)]
 pub struct WireguardConnection {
     connection: ConnectionHandle<DeviceMessage>,
@@ -320,7 +380,7 @@ fn consume_netlink_error<
 
 // the built-in support for adding addresses is too helpful, so a simple AddressMessage with a
 // single Address nla is created
-fn add_ip_addr_message(if_index: u32, addr: IpAddr) -> AddressMessage {
+pub(crate) fn add_ip_addr_message(if_index: u32, addr: IpAddr) -> AddressMessage {
     // Note: Default scope is RT_SCOPE_UNIVERSE;
     match addr {
         IpAddr::V4(ipv4_addr) => AddressMessageBuilder::<Ipv4Addr>::new()
@@ -3,8 +3,6 @@ use std::pin::Pin;
 use futures::Future;
 use talpid_tunnel_config_client::DaitaSettings;
 
-use crate::config::MULLVAD_INTERFACE_NAME;
-
 use super::{
     super::stats::{Stats, StatsMap},
     Config, Error, Handle, Tunnel, TunnelError,
@@ -13,6 +11,8 @@ use super::{
 
 pub struct NetlinkTunnel {
     interface_index: u32,
+    /// The name that the interface was created with.
+    interface_name: String,
     netlink_connections: Handle,
     tokio_handle: tokio::runtime::Handle,
 }
@@ -22,11 +22,12 @@ impl NetlinkTunnel {
         tokio_handle.clone().block_on(async {
             let mut netlink_connections = Handle::connect().await?;
             let interface_index = netlink_connections
-                .create_device(MULLVAD_INTERFACE_NAME.to_string(), config.mtu as u32)
+                .create_device(config.interface_name.clone(), config.mtu as u32)
                 .await?;
 
             let mut tunnel = Self {
                 interface_index,
+                interface_name: config.interface_name.clone(),
                 netlink_connections,
                 tokio_handle,
             };
@@ -83,10 +84,10 @@ impl Tunnel for NetlinkTunnel {
             Ok(name) => name.to_string_lossy().to_string(),
             Err(err) => {
                 log::error!(
-                    "Failed to deduce interface name at runtime, will attempt to use the default name. {}",
+                    "Failed to deduce interface name at runtime, will attempt to use the configured name. {}",
                     err
                 );
-                MULLVAD_INTERFACE_NAME.to_string()
+                self.interface_name.clone()
             }
         }
     }
@@ -96,6 +97,7 @@ impl Tunnel for NetlinkTunnel {
             mut netlink_connections,
             interface_index,
             tokio_handle,
+            ..
         } = *self;
         tokio_handle.block_on(async move {
             if let Err(err) = netlink_connections.delete_device(interface_index).await {
@@ -1,5 +1,3 @@
-use crate::config::MULLVAD_INTERFACE_NAME;
-
 use super::{
     super::stats::{Stats, StatsMap},
     Config, Error as WgKernelError, Handle, Tunnel, TunnelError,
@@ -49,7 +47,7 @@ impl NetworkManagerTunnel {
             Ok(name) => name,
             Err(error) => {
                 log::error!("Failed to fetch interface name from NM: {}", error);
-                MULLVAD_INTERFACE_NAME.to_string()
+                config.interface_name.clone()
             }
         };
         let netlink_connections = tokio_handle.block_on(Handle::connect())?;
@@ -162,11 +160,11 @@ fn convert_config_to_dbus(config: &Config) -> DeviceConfig {
     connection_config.insert("type".into(), Variant(Box::new("wireguard".to_string())));
     connection_config.insert(
         "id".into(),
-        Variant(Box::new(MULLVAD_INTERFACE_NAME.to_string())),
+        Variant(Box::new(config.interface_name.clone())),
     );
     connection_config.insert(
         "interface-name".into(),
-        Variant(Box::new(MULLVAD_INTERFACE_NAME.to_string())),
+        Variant(Box::new(config.interface_name.clone())),
     );
     connection_config.insert("autoconnect".into(), Variant(Box::new(true)));
 
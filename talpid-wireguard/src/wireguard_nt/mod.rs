@@ -1036,13 +1036,17 @@ mod tests {
             constant_packet_size: false,
         },
         exit_peer: None,
+        custom_entry: false,
         ipv4_gateway: "0.0.0.0".parse().unwrap(),
         ipv6_gateway: None,
         mtu: 0,
         obfuscator_config: None,
         obfuscation_mtu: 0,
         quantum_resistant: false,
+        rekey_interval: None,
         daita: false,
+        daita_level: None,
+        daita_parameters: None,
     });
 
     static WG_STRUCT_CONFIG: LazyLock<Interface> = LazyLock::new(|| Interface {
@@ -0,0 +1,124 @@
+//! Stand-in for the Mullvad REST API.
+//!
+//! This is a [`FakeApi`] with a single account, [`ACCOUNT_NUMBER`], that does not expire for a
+//! long time. Registered devices are added as peers on the [`Relay`], so that the daemon can
+//! connect right away.
+//!
+//! The API is served over plain HTTP, so the daemon must be built with the `api-override`
+//! feature and started with `MULLVAD_API_DISABLE_TLS=1`.
+
+use anyhow::{Context, Result};
+use chrono::{TimeDelta, Utc};
+use ipnetwork::IpNetwork;
+use mullvad_api_fake::{Device, DeviceEvent, FakeApi};
+use serde_json::json;
+use std::{net::Ipv4Addr, sync::Arc};
+use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+
+use crate::{
+    network::{
+        API_ADDR, RELAY_ADDR, RELAY_PORT, RELAY_TUNNEL_ADDR_V4, RELAY_TUNNEL_ADDR_V6,
+        SHADOWSOCKS_PORT, UNREACHABLE_RELAY_ADDR,
+    },
+    relay::Relay,
+};
+
+/// The only valid account number.
+pub const ACCOUNT_NUMBER: &str = "1234123412341234";
+/// Country code of every relay.
+pub const RELAY_COUNTRY_CODE: &str = "se";
+/// City code of every relay.
+pub const RELAY_CITY_CODE: &str = "got";
+/// Hostname of the relay that the daemon can connect to.
+pub const RELAY_HOSTNAME: &str = "se-got-wg-001";
+/// Hostname of a relay that never responds.
+pub const UNREACHABLE_RELAY_HOSTNAME: &str = "se-got-wg-002";
+
+/// The fake API, listening on [`API_ADDR`].
+pub struct Api {
+    fake: FakeApi,
+}
+
+impl Api {
+    /// Start the API. Devices are registered as peers on `relay`. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime).
+    pub async fn start(relay: Arc<Relay>) -> Result<Self> {
+        let fake = FakeApi::bind(API_ADDR)
+            .await
+            .with_context(|| format!("Failed to bind fake API to {API_ADDR}"))?;
+
+        fake.add_account(ACCOUNT_NUMBER, Utc::now() + TimeDelta::days(365));
+        let unreachable_relay_key = PrivateKey::new_from_random().public_key();
+        fake.set_relay_list(relay_list(relay.public_key(), &unreachable_relay_key));
+        fake.on_device_event(move |event| {
+            let relay = Arc::clone(&relay);
+            async move { update_peers(&relay, event).await }
+        });
+
+        Ok(Self { fake })
+    }
+
+    /// The underlying fake API. This can be used to inject faults or inspect devices.
+    pub fn fake(&self) -> &FakeApi {
+        &self.fake
+    }
+}
+
+/// Keep the peers of `relay` in sync with the registered devices.
+async fn update_peers(relay: &Relay, event: DeviceEvent) -> Result<()> {
+    match event {
+        DeviceEvent::Created(device) => relay.add_peer(&device.pubkey, allowed_ips(&device)).await,
+        DeviceEvent::Removed(device) => relay.remove_peer(&device.pubkey).await,
+        DeviceEvent::KeyRotated { old_pubkey, device } => {
+            relay.remove_peer(&old_pubkey).await?;
+            relay.add_peer(&device.pubkey, allowed_ips(&device)).await
+        }
+    }
+}
+
+fn allowed_ips(device: &Device) -> Vec<IpNetwork> {
+    vec![device.ipv4_address.into(), device.ipv6_address.into()]
+}
+
+/// The relay list, in the format served by `app/v1/relays`.
+fn relay_list(relay_key: &PublicKey, unreachable_relay_key: &PublicKey) -> serde_json::Value {
+    let location = format!("{RELAY_COUNTRY_CODE}-{RELAY_CITY_CODE}");
+    let relay = |hostname: &str, address: Ipv4Addr, public_key: &PublicKey| {
+        json!({
+            "hostname": hostname,
+            "active": true,
+            "owned": true,
+            "location": location,
+            "provider": "e2e",
+            "ipv4_addr_in": address,
+            "weight": 100,
+            "include_in_country": true,
+            "public_key": public_key,
+        })
+    };
+
+    json!({
+        "locations": {
+            location.as_str(): {
+                "city": "Gothenburg",
+                "country": "Sweden",
+                "latitude": 57.70887,
+                "longitude": 11.97456,
+            },
+        },
+        "wireguard": {
+            "port_ranges": [[RELAY_PORT, RELAY_PORT]],
+            "ipv4_gateway": RELAY_TUNNEL_ADDR_V4,
+            "ipv6_gateway": RELAY_TUNNEL_ADDR_V6,
+            "shadowsocks_port_ranges": [[SHADOWSOCKS_PORT, SHADOWSOCKS_PORT]],
+            "relays": [
+                relay(RELAY_HOSTNAME, RELAY_ADDR, relay_key),
+                relay(UNREACHABLE_RELAY_HOSTNAME, UNREACHABLE_RELAY_ADDR, unreachable_relay_key),
+            ],
+        },
+        "bridge": {
+            "shadowsocks": [],
+            "relays": [],
+        },
+    })
+}
@@ -0,0 +1,167 @@
+//! Running `mullvad-daemon` inside the client namespace.
+
+use anyhow::{Context, Result, bail};
+use mullvad_management_interface::MullvadProxyClient;
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use talpid_netns::NetNs;
+use tempfile::TempDir;
+use tokio::process::{Child, Command};
+
+use crate::network::API_ADDR;
+
+/// Environment variable with the path of the daemon to test. The daemon must be built with the
+/// `api-override` feature. Defaults to `target/debug/mullvad-daemon` in the main workspace.
+pub const DAEMON_PATH_VAR: &str = "MULLVAD_E2E_DAEMON_PATH";
+
+/// Host name that the daemon sends API requests to. It is never resolved.
+const API_HOST: &str = "api.mullvad.e2e.test";
+/// Host name used by the connection check of the daemon. It does not resolve.
+const CONNCHECK_HOST: &str = "mullvad.e2e.test";
+/// How long to wait for the management interface to come up.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long to wait for the daemon to exit after asking it to.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A `mullvad-daemon` process. Its settings, cache and log are kept in a temporary directory,
+/// which is kept if the daemon is dropped while panicking.
+pub struct Daemon {
+    child: Child,
+    dir: Option<TempDir>,
+    rpc_socket_path: PathBuf,
+}
+
+impl Daemon {
+    /// Start the daemon in `netns` and wait until its management interface is up.
+    pub async fn start(netns: &NetNs) -> Result<Self> {
+        let dir = tempfile::Builder::new()
+            .prefix("mullvad-e2e-")
+            .tempdir()
+            .context("Failed to create daemon directory")?;
+        let subdir = |name: &str| -> Result<PathBuf> {
+            let path = dir.path().join(name);
+            fs::create_dir(&path).with_context(|| format!("Failed to create {path:?}"))?;
+            Ok(path)
+        };
+        let settings_dir = subdir("settings")?;
+        let cache_dir = subdir("cache")?;
+        let log_dir = subdir("logs")?;
+        let resource_dir = subdir("resources")?;
+        let rpc_socket_path = dir.path().join("rpc-socket");
+
+        let log = File::create(log_dir.join("daemon.log")).context("Failed to create log file")?;
+        let daemon_path = daemon_path();
+        log::debug!("Starting {daemon_path:?} in namespace {}", netns.name());
+
+        let child = Command::new("ip")
+            .args(["netns", "exec", netns.name()])
+            .arg(&daemon_path)
+            .args(["-vv", "--disable-log-to-file", "--disable-stdout-timestamps"])
+            .env("MULLVAD_API_HOST", API_HOST)
+            .env("MULLVAD_API_ADDR", API_ADDR.to_string())
+            .env("MULLVAD_API_DISABLE_TLS", "1")
+            // The connection check of the daemon uses TLS, so it can't reach the fake geoip
+            // service. Point it somewhere that does not exist rather than at the real one.
+            .env("MULLVAD_CONNCHECK_HOST", CONNCHECK_HOST)
+            .env("TALPID_DNS_MODULE", "nftables-redirect")
+            .env("MULLVAD_SETTINGS_DIR", settings_dir)
+            .env("MULLVAD_CACHE_DIR", cache_dir)
+            .env("MULLVAD_LOG_DIR", log_dir)
+            .env("MULLVAD_RESOURCE_DIR", resource_dir)
+            .env("MULLVAD_RPC_SOCKET_PATH", &rpc_socket_path)
+            .stdin(Stdio::null())
+            .stdout(log.try_clone().context("Failed to clone log file")?)
+            .stderr(log)
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("Failed to start {daemon_path:?}"))?;
+
+        let mut daemon = Self {
+            child,
+            dir: Some(dir),
+            rpc_socket_path,
+        };
+        tokio::time::timeout(STARTUP_TIMEOUT, daemon.wait_until_ready())
+            .await
+            .with_context(|| {
+                format!(
+                    "Timed out waiting for daemon to start, see {:?}",
+                    daemon.log_path()
+                )
+            })??;
+        Ok(daemon)
+    }
+
+    async fn wait_until_ready(&mut self) -> Result<()> {
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                bail!("Daemon exited with {status}, see {:?}", self.log_path());
+            }
+            if let Ok(mut rpc) = self.rpc().await
+                && rpc.get_tunnel_state().await.is_ok()
+            {
+                return Ok(());
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Connect to the management interface of the daemon.
+    pub async fn rpc(&self) -> Result<MullvadProxyClient> {
+        MullvadProxyClient::from_rpc_socket_path(&self.rpc_socket_path)
+            .await
+            .context("Failed to connect to daemon")
+    }
+
+    /// Path of the file that the daemon logs to.
+    pub fn log_path(&self) -> PathBuf {
+        self.dir().join("logs").join("daemon.log")
+    }
+
+    fn dir(&self) -> &Path {
+        self.dir
+            .as_ref()
+            .expect("Directory is only taken on drop")
+            .path()
+    }
+
+    /// Ask the daemon to shut down, and wait for it to exit.
+    pub async fn stop(mut self) -> Result<()> {
+        let pid = self.child.id().context("Daemon has already exited")?;
+        nix::sys::signal::kill(
+            nix::unistd::Pid::from_raw(i32::try_from(pid).unwrap()),
+            nix::sys::signal::Signal::SIGTERM,
+        )
+        .context("Failed to signal daemon")?;
+
+        let status = tokio::time::timeout(SHUTDOWN_TIMEOUT, self.child.wait())
+            .await
+            .context("Timed out waiting for daemon to exit")??;
+        if !status.success() {
+            bail!("Daemon exited with {status}, see {:?}", self.log_path());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        if std::thread::panicking()
+            && let Some(dir) = self.dir.take()
+        {
+            let dir = dir.keep();
+            eprintln!("Keeping daemon settings and logs in {dir:?}");
+        }
+    }
+}
+
+fn daemon_path() -> PathBuf {
+    match std::env::var_os(DAEMON_PATH_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("../../target/debug/mullvad-daemon"),
+    }
+}
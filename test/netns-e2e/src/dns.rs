@@ -0,0 +1,175 @@
+//! A minimal DNS resolver, and a client for it.
+//!
+//! The resolver answers every query with a single A record for a fixed address, and remembers
+//! where queries came from. Running one as the ISP resolver and one as the tunnel resolver, with
+//! different answers, shows which of them a lookup ended up at.
+
+use anyhow::{Context, Result, ensure};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{net::UdpSocket, task::JoinHandle};
+
+/// Size of the DNS header.
+const HEADER_LEN: usize = 12;
+/// Largest message sent over UDP without EDNS.
+const MAX_MESSAGE_LEN: usize = 512;
+
+/// A DNS resolver listening on UDP.
+pub struct Resolver {
+    query_sources: Arc<Mutex<Vec<IpAddr>>>,
+    task: JoinHandle<()>,
+}
+
+impl Resolver {
+    /// Start a resolver on `address` that resolves every name to `answer`. This must be called
+    /// from a [`NamespaceRuntime`](crate::network::NamespaceRuntime).
+    pub async fn start(address: SocketAddr, answer: Ipv4Addr) -> Result<Self> {
+        let socket = UdpSocket::bind(address)
+            .await
+            .with_context(|| format!("Failed to bind DNS resolver to {address}"))?;
+        let query_sources = Arc::default();
+        let task = tokio::spawn(serve(socket, answer, Arc::clone(&query_sources)));
+        Ok(Self {
+            query_sources,
+            task,
+        })
+    }
+
+    /// Return the source address of every query received so far.
+    pub fn query_sources(&self) -> Vec<IpAddr> {
+        self.query_sources.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Resolver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(socket: UdpSocket, answer: Ipv4Addr, query_sources: Arc<Mutex<Vec<IpAddr>>>) {
+    let mut buffer = [0u8; MAX_MESSAGE_LEN];
+    loop {
+        let (len, source) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(error) => {
+                log::error!("DNS resolver failed to receive: {error}");
+                return;
+            }
+        };
+        query_sources.lock().unwrap().push(source.ip());
+
+        let Some(response) = response(&buffer[..len], answer) else {
+            log::warn!("Ignoring malformed DNS query from {source}");
+            continue;
+        };
+        if let Err(error) = socket.send_to(&response, source).await {
+            log::warn!("Failed to send DNS response to {source}: {error}");
+        }
+    }
+}
+
+/// Build a response to `query` with a single A record for `answer`. The record type that was
+/// asked for is ignored.
+fn response(query: &[u8], answer: Ipv4Addr) -> Option<Vec<u8>> {
+    // Only handle queries with exactly one question
+    if query.get(4..6)? != [0, 1] {
+        return None;
+    }
+    let question_end = question_end(query)?;
+
+    let mut response = Vec::with_capacity(question_end + 16);
+    response.extend_from_slice(&query[..2]);
+    // Response, recursion desired, recursion available, no error
+    response.extend_from_slice(&[0x81, 0x80]);
+    // One question, one answer, no authority or additional records
+    response.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 0]);
+    response.extend_from_slice(&query[HEADER_LEN..question_end]);
+    // Pointer to the name in the question, type A, class IN
+    response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1]);
+    response.extend_from_slice(&60u32.to_be_bytes());
+    response.extend_from_slice(&4u16.to_be_bytes());
+    response.extend_from_slice(&answer.octets());
+    Some(response)
+}
+
+/// Return the offset of the end of the first question in `message`.
+fn question_end(message: &[u8]) -> Option<usize> {
+    let mut offset = HEADER_LEN;
+    loop {
+        let label_len = usize::from(*message.get(offset)?);
+        offset += 1 + label_len;
+        if label_len == 0 {
+            break;
+        }
+    }
+    // Type and class
+    let end = offset + 4;
+    (end <= message.len()).then_some(end)
+}
+
+/// Ask `server` for the A record of `name`. Only responses from a [`Resolver`] are understood.
+pub async fn lookup(server: SocketAddr, name: &str, timeout: Duration) -> Result<Ipv4Addr> {
+    const QUERY_ID: [u8; 2] = [0x4d, 0x56];
+
+    let mut query = Vec::with_capacity(MAX_MESSAGE_LEN);
+    query.extend_from_slice(&QUERY_ID);
+    // Standard query, recursion desired, one question
+    query.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.split('.') {
+        let label_len = u8::try_from(label.len()).context("DNS label is too long")?;
+        query.push(label_len);
+        query.extend_from_slice(label.as_bytes());
+    }
+    // End of name, type A, class IN
+    query.extend_from_slice(&[0, 0, 1, 0, 1]);
+
+    let socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))
+        .await
+        .context("Failed to bind DNS client socket")?;
+    socket
+        .send_to(&query, server)
+        .await
+        .context("Failed to send DNS query")?;
+
+    let mut buffer = [0u8; MAX_MESSAGE_LEN];
+    let (len, _) = tokio::time::timeout(timeout, socket.recv_from(&mut buffer))
+        .await
+        .context("Timed out waiting for DNS response")?
+        .context("Failed to receive DNS response")?;
+    let response = &buffer[..len];
+
+    ensure!(
+        response.get(..2) == Some(&QUERY_ID[..]),
+        "DNS response does not match the query"
+    );
+    // The answer is a 16 byte A record following the question
+    ensure!(len >= query.len() + 16, "DNS response has no answer");
+    let answer: [u8; 4] = response[len - 4..].try_into().unwrap();
+    Ok(Ipv4Addr::from(answer))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_response() {
+        let query = [
+            0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, // Header
+            7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 0, 0, 1, 0, 1, // Question
+        ];
+        let response = response(&query, Ipv4Addr::new(10, 64, 0, 1)).unwrap();
+
+        assert_eq!(response[..2], query[..2]);
+        assert_eq!(response[6..8], [0, 1], "there should be one answer");
+        assert_eq!(response[HEADER_LEN..query.len()], query[HEADER_LEN..]);
+        assert_eq!(response[response.len() - 4..], [10, 64, 0, 1]);
+
+        let truncated = &query[..query.len() - 1];
+        assert!(super::response(truncated, Ipv4Addr::LOCALHOST).is_none());
+    }
+}
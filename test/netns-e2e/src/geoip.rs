@@ -0,0 +1,85 @@
+//! Stand-in for the `am.i.mullvad.net` connection check.
+//!
+//! Requests coming from the tunnel network are reported as coming from a Mullvad exit IP.
+
+use am_i_mullvad_client::AmIMullvadResponse;
+use anyhow::{Context, Result, ensure};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::{Method, Request, StatusCode, Uri};
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    api::RELAY_HOSTNAME,
+    http::{self, Response},
+    network::{GEOIP_ADDR, TUNNEL_NETWORK_V4, TUNNEL_NETWORK_V6},
+};
+
+/// The geoip service, listening on [`GEOIP_ADDR`].
+pub struct GeoIp {
+    task: JoinHandle<()>,
+}
+
+impl GeoIp {
+    /// Start the service. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime).
+    pub async fn start() -> Result<Self> {
+        let task = http::serve(GEOIP_ADDR, |request, client| async move {
+            respond(&request, client)
+        })
+        .await?;
+        Ok(Self { task })
+    }
+}
+
+impl Drop for GeoIp {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn respond(request: &Request<hyper::body::Incoming>, client: SocketAddr) -> Response {
+    if request.method() != Method::GET || request.uri().path() != "/json" {
+        return http::empty(StatusCode::NOT_FOUND);
+    }
+
+    let ip = client.ip().to_canonical();
+    let mullvad_exit_ip = match ip {
+        IpAddr::V4(ip) => TUNNEL_NETWORK_V4.contains(ip),
+        IpAddr::V6(ip) => TUNNEL_NETWORK_V6.contains(ip),
+    };
+    let response = AmIMullvadResponse {
+        ip,
+        mullvad_exit_ip,
+        mullvad_exit_ip_hostname: mullvad_exit_ip.then(|| RELAY_HOSTNAME.to_owned()),
+    };
+    http::json(StatusCode::OK, &response)
+}
+
+/// Ask the geoip service where the calling namespace appears to be connecting from.
+pub async fn lookup(timeout: Duration) -> Result<AmIMullvadResponse> {
+    let client: Client<_, Full<Bytes>> = Client::builder(TokioExecutor::new()).build_http();
+    let uri = Uri::try_from(format!("http://{GEOIP_ADDR}/json")).unwrap();
+
+    let response = tokio::time::timeout(timeout, client.get(uri))
+        .await
+        .context("Timed out waiting for geoip response")?
+        .context("Geoip request failed")?;
+    ensure!(
+        response.status() == StatusCode::OK,
+        "Unexpected geoip status: {}",
+        response.status()
+    );
+    let body = response
+        .into_body()
+        .collect()
+        .await
+        .context("Failed to read geoip response")?
+        .to_bytes();
+    serde_json::from_slice(&body).context("Failed to parse geoip response")
+}
@@ -0,0 +1,69 @@
+//! A small HTTP/1.1 server shared by the fake services.
+
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use http_body_util::Full;
+use hyper::{
+    Request, StatusCode, body::Incoming, header, server::conn::http1, service::service_fn,
+};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use std::{convert::Infallible, future::Future, net::SocketAddr};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+pub(crate) type Response = hyper::Response<Full<Bytes>>;
+
+/// Serve HTTP on `address` until the returned task is aborted. `handler` is called with every
+/// request and the address of the client that sent it.
+pub(crate) async fn serve<H, F>(address: SocketAddr, handler: H) -> Result<JoinHandle<()>>
+where
+    H: Fn(Request<Incoming>, SocketAddr) -> F + Clone + Send + 'static,
+    F: Future<Output = Response> + Send + 'static,
+{
+    let listener = TcpListener::bind(address)
+        .await
+        .with_context(|| format!("Failed to bind HTTP server to {address}"))?;
+
+    Ok(tokio::spawn(async move {
+        loop {
+            let (stream, client) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(error) => {
+                    log::error!("HTTP server on {address} failed to accept: {error}");
+                    return;
+                }
+            };
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                let service = service_fn(move |request| {
+                    let response = handler(request, client);
+                    async move { Ok::<_, Infallible>(response.await) }
+                });
+                if let Err(error) = http1::Builder::new()
+                    .serve_connection(TokioIo::new(stream), service)
+                    .await
+                {
+                    log::debug!("HTTP connection from {client} failed: {error}");
+                }
+            });
+        }
+    }))
+}
+
+/// A response with `body` serialized as JSON.
+pub(crate) fn json(status: StatusCode, body: &impl Serialize) -> Response {
+    let body = serde_json::to_vec(body).expect("Failed to serialize response");
+    hyper::Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+/// A response without a body.
+pub(crate) fn empty(status: StatusCode) -> Response {
+    hyper::Response::builder()
+        .status(status)
+        .body(Full::default())
+        .unwrap()
+}
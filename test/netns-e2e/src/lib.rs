@@ -0,0 +1,272 @@
+#![cfg(target_os = "linux")]
+//! Hermetic end-to-end tests of `mullvad-daemon`.
+//!
+//! The daemon runs in a network namespace of its own, connected to a second namespace that stands
+//! in for the internet. That namespace hosts a fake REST API, a WireGuard relay with a tuncfg
+//! service and udp2tcp and Shadowsocks servers, a geoip service and DNS resolvers, so tests need
+//! neither a Mullvad account nor network access. See [`network`] for the addresses in use.
+//!
+//! Everything here requires root.
+
+use anyhow::{Context, Result, bail};
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::{
+    constraints::Constraint,
+    relay_constraints::{GeographicLocationConstraint, LocationConstraint, RelaySettings},
+    states::TunnelState,
+};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+pub mod api;
+pub mod daemon;
+pub mod dns;
+pub mod geoip;
+mod http;
+pub mod network;
+pub mod relay;
+pub mod shadowsocks;
+pub mod tuncfg;
+pub mod udp2tcp;
+
+use api::Api;
+use daemon::Daemon;
+use dns::Resolver;
+use geoip::GeoIp;
+use network::{INTERNET_WAN_ADDR, NamespaceRuntime, RELAY_TUNNEL_ADDR_V4, Topology};
+use relay::Relay;
+use shadowsocks::Shadowsocks;
+use tuncfg::Tuncfg;
+use udp2tcp::Udp2Tcp;
+
+/// What the ISP resolver resolves every name to.
+pub const ISP_DNS_ANSWER: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 53);
+/// What the resolver on the relay resolves every name to.
+pub const TUNNEL_DNS_ANSWER: Ipv4Addr = Ipv4Addr::new(10, 64, 0, 53);
+
+/// How often to poll the daemon while waiting for something to change.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// How long to wait for the daemon to fetch the relay list.
+const RELAY_LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The services running in the internet namespace.
+pub struct Backend {
+    pub api: Api,
+    pub relay: Arc<Relay>,
+    pub tuncfg: Tuncfg,
+    pub udp2tcp: Udp2Tcp,
+    pub shadowsocks: Shadowsocks,
+    pub geoip: GeoIp,
+    /// The resolver of the ISP, which the client uses while disconnected.
+    pub isp_resolver: Resolver,
+    /// The resolver on the relay, which the client should use while connected.
+    pub tunnel_resolver: Resolver,
+}
+
+impl Backend {
+    /// Start all services. This must be called from a [`NamespaceRuntime`] in the internet
+    /// namespace.
+    pub async fn start() -> Result<Self> {
+        let relay = Arc::new(Relay::start().await.context("Failed to start relay")?);
+        let tuncfg = Tuncfg::start(Arc::clone(&relay))
+            .await
+            .context("Failed to start tuncfg")?;
+        let udp2tcp = Udp2Tcp::start()
+            .await
+            .context("Failed to start udp2tcp server")?;
+        let shadowsocks = Shadowsocks::start()
+            .await
+            .context("Failed to start Shadowsocks server")?;
+        let api = Api::start(Arc::clone(&relay))
+            .await
+            .context("Failed to start API")?;
+        let geoip = GeoIp::start().await.context("Failed to start geoip")?;
+        let isp_resolver = Resolver::start(
+            SocketAddr::new(INTERNET_WAN_ADDR.into(), 53),
+            ISP_DNS_ANSWER,
+        )
+        .await
+        .context("Failed to start ISP resolver")?;
+        let tunnel_resolver = Resolver::start(
+            SocketAddr::new(RELAY_TUNNEL_ADDR_V4.into(), 53),
+            TUNNEL_DNS_ANSWER,
+        )
+        .await
+        .context("Failed to start tunnel resolver")?;
+        Ok(Self {
+            api,
+            relay,
+            tuncfg,
+            udp2tcp,
+            shadowsocks,
+            geoip,
+            isp_resolver,
+            tunnel_resolver,
+        })
+    }
+}
+
+/// A daemon running against a fake backend.
+pub struct Harness {
+    daemon: Option<Daemon>,
+    backend: Option<Backend>,
+    client: NamespaceRuntime,
+    internet: NamespaceRuntime,
+    // Dropped last, once nothing is running in the namespaces anymore
+    _topology: Topology,
+}
+
+impl Harness {
+    /// Set up the namespaces, start the backend and then the daemon.
+    pub async fn start() -> Result<Self> {
+        let topology = Topology::create()?;
+        let client = NamespaceRuntime::start(topology.client())?;
+        let internet = NamespaceRuntime::start(topology.internet())?;
+        let backend = internet.run(Backend::start()).await?;
+        let daemon = Daemon::start(topology.client()).await?;
+
+        Ok(Self {
+            daemon: Some(daemon),
+            backend: Some(backend),
+            client,
+            internet,
+            _topology: topology,
+        })
+    }
+
+    /// The daemon under test.
+    pub fn daemon(&self) -> &Daemon {
+        self.daemon
+            .as_ref()
+            .expect("Daemon is only taken on shutdown")
+    }
+
+    /// The services in the internet namespace.
+    pub fn backend(&self) -> &Backend {
+        self.backend
+            .as_ref()
+            .expect("Backend is only taken on drop")
+    }
+
+    /// Connect to the management interface of the daemon.
+    pub async fn rpc(&self) -> Result<MullvadProxyClient> {
+        self.daemon().rpc().await
+    }
+
+    /// Log in to [`api::ACCOUNT_NUMBER`], and wait for the relay list to be fetched.
+    pub async fn login(&self) -> Result<()> {
+        let mut rpc = self.rpc().await?;
+        rpc.login_account(api::ACCOUNT_NUMBER.to_owned())
+            .await
+            .context("Failed to log in")?;
+
+        let wait = async {
+            while rpc.get_relay_locations().await?.countries.is_empty() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        tokio::time::timeout(RELAY_LIST_TIMEOUT, wait)
+            .await
+            .context("Timed out waiting for relay list")?
+    }
+
+    /// Only connect to the relay with `hostname`.
+    pub async fn select_relay(&self, hostname: &str) -> Result<()> {
+        let mut rpc = self.rpc().await?;
+        let RelaySettings::Normal(mut constraints) = rpc.get_settings().await?.relay_settings
+        else {
+            bail!("A custom tunnel endpoint is in use");
+        };
+        constraints.location = Constraint::Only(LocationConstraint::from(
+            GeographicLocationConstraint::hostname(
+                api::RELAY_COUNTRY_CODE,
+                api::RELAY_CITY_CODE,
+                hostname,
+            ),
+        ));
+        rpc.set_relay_settings(RelaySettings::Normal(constraints))
+            .await
+            .context("Failed to set relay constraints")
+    }
+
+    /// Wait until the tunnel state satisfies `predicate`, and return that state.
+    pub async fn wait_for_tunnel_state(
+        &self,
+        timeout: Duration,
+        predicate: impl Fn(&TunnelState) -> bool,
+    ) -> Result<TunnelState> {
+        let mut rpc = self.rpc().await?;
+        let wait = async {
+            loop {
+                let state = rpc.get_tunnel_state().await?;
+                if predicate(&state) {
+                    return Ok::<_, anyhow::Error>(state);
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        };
+        tokio::time::timeout(timeout, wait)
+            .await
+            .context("Timed out waiting for tunnel state")?
+    }
+
+    /// Look up where the client appears to be connecting from, using the fake geoip service.
+    pub async fn geoip_lookup(
+        &self,
+        timeout: Duration,
+    ) -> Result<am_i_mullvad_client::AmIMullvadResponse> {
+        self.client.run(geoip::lookup(timeout)).await
+    }
+
+    /// Like [`Self::geoip_lookup`], but with this process excluded from the tunnel by split
+    /// tunneling. The client namespace runtime is a thread in this process, so its requests are
+    /// excluded too.
+    pub async fn geoip_lookup_excluded(
+        &self,
+        timeout: Duration,
+    ) -> Result<am_i_mullvad_client::AmIMullvadResponse> {
+        let mut rpc = self.rpc().await?;
+        let pid = i32::try_from(std::process::id()).context("PID out of range")?;
+        rpc.add_split_tunnel_process(pid)
+            .await
+            .context("Failed to exclude process")?;
+        let result = self.geoip_lookup(timeout).await;
+        rpc.remove_split_tunnel_process(pid)
+            .await
+            .context("Failed to stop excluding process")?;
+        result
+    }
+
+    /// Resolve `name` using `server`, from the client namespace.
+    pub async fn dns_lookup(
+        &self,
+        server: SocketAddr,
+        name: &'static str,
+        timeout: Duration,
+    ) -> Result<Ipv4Addr> {
+        self.client.run(dns::lookup(server, name, timeout)).await
+    }
+
+    /// Stop the daemon gracefully, so that it can clean up after itself.
+    pub async fn shutdown(mut self) -> Result<()> {
+        match self.daemon.take() {
+            Some(daemon) => daemon.stop().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        // Stop the daemon before the namespaces go away
+        drop(self.daemon.take());
+        // The backend owns sockets and tasks on the internet runtime, so drop it there
+        if let Some(backend) = self.backend.take() {
+            drop(self.internet.spawn(async move { drop(backend) }));
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! The network namespaces that the daemon and the fake backend run in, and the link between them.
+//!
+//! ```text
+//!  client namespace                    internet namespace
+//! +------------------+                +--------------------------------+
+//! | mullvad-daemon   |                | wan0 192.0.2.1/24              |
+//! | wan0 192.0.2.2 --+---- veth ------+ lo   198.51.100.1  (relay)     |
+//! |                  |                |      198.51.100.10 (API)       |
+//! |                  |                |      203.0.113.80  (geoip)     |
+//! |                  |                | wg-relay 10.64.0.1/10          |
+//! +------------------+                +--------------------------------+
+//! ```
+//!
+//! Every service address lives on the loopback interface of the internet namespace rather than on
+//! the link, so that the client reaches them through its default route. Otherwise, the daemon
+//! would treat them as being on the LAN.
+
+use anyhow::{Context, Result, bail};
+pub use mullvad_api_fake::{TUNNEL_NETWORK_V4, TUNNEL_NETWORK_V6};
+use nix::sched::{CloneFlags, setns};
+use std::{
+    future::Future,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    os::fd::AsFd,
+    process::Command,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        mpsc,
+    },
+    thread,
+};
+use talpid_netns::NetNs;
+use tokio::{runtime, sync::oneshot};
+
+/// Address of the client on the link to the internet namespace.
+pub const CLIENT_WAN_ADDR: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 2);
+/// Address of the internet namespace on the link to the client. This is the default gateway of
+/// the client, and the DNS resolver of its ISP.
+pub const INTERNET_WAN_ADDR: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+/// Address that the WireGuard relay listens on.
+pub const RELAY_ADDR: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 1);
+/// Address of a relay in the relay list that nothing listens on.
+pub const UNREACHABLE_RELAY_ADDR: Ipv4Addr = Ipv4Addr::new(198, 51, 100, 2);
+/// Port that the WireGuard relay listens on.
+pub const RELAY_PORT: u16 = 51820;
+/// TCP ports that the udp2tcp server listens on, at [`RELAY_ADDR`]. The daemon assumes that every
+/// relay supports these ports.
+pub const UDP2TCP_PORTS: [u16; 3] = [80, 443, 5001];
+/// UDP port that the Shadowsocks server listens on, at [`RELAY_ADDR`].
+pub const SHADOWSOCKS_PORT: u16 = 51900;
+/// Address of the fake REST API. The API is served over plain HTTP.
+pub const API_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(198, 51, 100, 10)), 80);
+/// Address of the fake `am.i.mullvad.net` geoip service.
+pub const GEOIP_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 80)), 80);
+
+/// IPv4 address of the relay inside the tunnel. This is the tunnel gateway and DNS resolver.
+pub const RELAY_TUNNEL_ADDR_V4: Ipv4Addr = Ipv4Addr::new(10, 64, 0, 1);
+/// IPv6 address of the relay inside the tunnel.
+pub const RELAY_TUNNEL_ADDR_V6: Ipv6Addr =
+    Ipv6Addr::new(0xfc00, 0xbbbb, 0xbbbb, 0xbb01, 0, 0, 0, 1);
+
+/// Name of the veth interface in both namespaces.
+const WAN_INTERFACE: &str = "wan0";
+
+/// A client namespace connected to an internet namespace. Both are destroyed on drop.
+pub struct Topology {
+    client: NetNs,
+    internet: NetNs,
+}
+
+impl Topology {
+    /// Create and connect a new pair of namespaces. Their names are unique to this process.
+    pub fn create() -> Result<Self> {
+        static NEXT_ID: AtomicU32 = AtomicU32::new(0);
+        let prefix = format!(
+            "mullvad-e2e-{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let client = NetNs::create_or_open(&format!("{prefix}-client"))
+            .context("Failed to create client namespace")?;
+        let internet = match NetNs::create_or_open(&format!("{prefix}-internet")) {
+            Ok(internet) => internet,
+            Err(error) => {
+                let _ = client.destroy();
+                return Err(error).context("Failed to create internet namespace");
+            }
+        };
+
+        let topology = Self { client, internet };
+        topology.connect().context("Failed to connect namespaces")?;
+        Ok(topology)
+    }
+
+    /// The namespace that the daemon runs in.
+    pub fn client(&self) -> &NetNs {
+        &self.client
+    }
+
+    /// The namespace that the fake backend runs in.
+    pub fn internet(&self) -> &NetNs {
+        &self.internet
+    }
+
+    fn connect(&self) -> Result<()> {
+        let client = self.client.name();
+        let internet = self.internet.name();
+
+        ip(&[
+            "-n",
+            client,
+            "link",
+            "add",
+            WAN_INTERFACE,
+            "type",
+            "veth",
+            "peer",
+            "name",
+            WAN_INTERFACE,
+            "netns",
+            internet,
+        ])?;
+        for netns in [client, internet] {
+            ip(&["-n", netns, "link", "set", "lo", "up"])?;
+            ip(&["-n", netns, "link", "set", WAN_INTERFACE, "up"])?;
+        }
+
+        let client_addr = format!("{CLIENT_WAN_ADDR}/24");
+        ip(&[
+            "-n",
+            client,
+            "addr",
+            "add",
+            &client_addr,
+            "dev",
+            WAN_INTERFACE,
+        ])?;
+        let gateway = INTERNET_WAN_ADDR.to_string();
+        ip(&["-n", client, "route", "add", "default", "via", &gateway])?;
+
+        let internet_addr = format!("{INTERNET_WAN_ADDR}/24");
+        ip(&[
+            "-n",
+            internet,
+            "addr",
+            "add",
+            &internet_addr,
+            "dev",
+            WAN_INTERFACE,
+        ])?;
+        for service in [RELAY_ADDR, API_ADDR.ip(), GEOIP_ADDR.ip()] {
+            let service = format!("{service}/32");
+            ip(&["-n", internet, "addr", "add", &service, "dev", "lo"])?;
+        }
+
+        self.client
+            .set_dns_servers([IpAddr::V4(INTERNET_WAN_ADDR)])
+            .context("Failed to set DNS server of client namespace")
+    }
+}
+
+impl Drop for Topology {
+    fn drop(&mut self) {
+        for netns in [&self.client, &self.internet] {
+            if let Err(error) = netns.destroy() {
+                log::error!("Failed to destroy namespace {}: {error}", netns.name());
+            }
+        }
+    }
+}
+
+/// A single-threaded Tokio runtime running on a thread inside a network namespace.
+///
+/// Sockets and tun devices created by tasks on this runtime belong to the namespace. Anything
+/// spawned on it is dropped on that thread when the runtime is dropped.
+pub struct NamespaceRuntime {
+    handle: runtime::Handle,
+    shutdown_tx: Option<oneshot::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl NamespaceRuntime {
+    /// Start a runtime inside `netns`.
+    pub fn start(netns: &NetNs) -> Result<Self> {
+        let netns_fd = netns
+            .as_fd()
+            .try_clone_to_owned()
+            .context("Failed to duplicate namespace handle")?;
+        let (handle_tx, handle_rx) = mpsc::sync_channel(1);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+
+        let thread = thread::Builder::new()
+            .name(format!("netns-{}", netns.name()))
+            .spawn(move || {
+                let runtime = setns(&netns_fd, CloneFlags::CLONE_NEWNET)
+                    .context("Failed to enter namespace")
+                    .and_then(|()| {
+                        runtime::Builder::new_current_thread()
+                            .enable_all()
+                            .build()
+                            .context("Failed to create runtime")
+                    });
+                match runtime {
+                    Ok(runtime) => {
+                        let _ = handle_tx.send(Ok(runtime.handle().clone()));
+                        runtime.block_on(async {
+                            let _ = shutdown_rx.await;
+                        });
+                    }
+                    Err(error) => {
+                        let _ = handle_tx.send(Err(error));
+                    }
+                }
+            })
+            .context("Failed to spawn runtime thread")?;
+
+        let handle = handle_rx
+            .recv()
+            .context("Runtime thread exited unexpectedly")??;
+
+        Ok(Self {
+            handle,
+            shutdown_tx: Some(shutdown_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// Spawn `future` on the runtime.
+    pub fn spawn<F>(&self, future: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        self.handle.spawn(future)
+    }
+
+    /// Run `future` on the runtime and wait for its output. Panics in `future` are propagated.
+    pub async fn run<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        match self.spawn(future).await {
+            Ok(output) => output,
+            Err(error) => match error.try_into_panic() {
+                Ok(panic) => std::panic::resume_unwind(panic),
+                Err(error) => panic!("Namespace task did not complete: {error}"),
+            },
+        }
+    }
+}
+
+impl Drop for NamespaceRuntime {
+    fn drop(&mut self) {
+        if let Some(shutdown_tx) = self.shutdown_tx.take() {
+            let _ = shutdown_tx.send(());
+        }
+        if let Some(thread) = self.thread.take()
+            && thread.join().is_err()
+        {
+            log::error!("Namespace runtime thread panicked");
+        }
+    }
+}
+
+/// Run `ip` with `args`. Unless `-n` is given, this applies to the namespace of the calling
+/// thread.
+pub(crate) fn ip(args: &[&str]) -> Result<()> {
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .context("Failed to run ip")?;
+    if !output.status.success() {
+        bail!(
+            "`ip {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! A userspace WireGuard relay.
+//!
+//! Traffic from the client leaves the relay through its tun device and is delivered to the
+//! services in the internet namespace, with a source address in the tunnel network. There is no
+//! exit NAT, so services can tell tunnel traffic apart from direct traffic.
+//!
+//! Ephemeral peers negotiated with the [`Tuncfg`](crate::tuncfg::Tuncfg) service take over the
+//! tunnel addresses of the device they were derived from. The relay does not notice when a client
+//! goes back to its device key, so each device can only use ephemeral peers in its first tunnel.
+
+use anyhow::{Context, Result};
+use gotatun::device::{self, DefaultDeviceTransports, Device, Peer};
+use gotatun::tun::tun_async_device::TunDevice;
+use ipnetwork::IpNetwork;
+use std::{collections::HashMap, sync::Mutex};
+use talpid_types::net::wireguard::{PresharedKey, PrivateKey, PublicKey};
+
+use crate::network::{
+    RELAY_PORT, RELAY_TUNNEL_ADDR_V4, RELAY_TUNNEL_ADDR_V6, TUNNEL_NETWORK_V4, TUNNEL_NETWORK_V6,
+    ip,
+};
+
+/// Name of the tun device of the relay.
+pub const RELAY_INTERFACE: &str = "wg-relay";
+
+/// A WireGuard relay listening on [`RELAY_PORT`].
+pub struct Relay {
+    device: Device<DefaultDeviceTransports>,
+    public_key: PublicKey,
+    /// The allowed IPs of every device.
+    devices: Mutex<HashMap<PublicKey, Vec<IpNetwork>>>,
+    /// The device that each ephemeral peer was derived from.
+    ephemeral_peers: Mutex<HashMap<PublicKey, PublicKey>>,
+}
+
+impl Relay {
+    /// Create the relay in the namespace of the calling thread. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime).
+    pub async fn start() -> Result<Self> {
+        let private_key = PrivateKey::new_from_random();
+        let public_key = private_key.public_key();
+
+        let tun = {
+            let mut tun_config = tun::Configuration::default();
+            tun_config.tun_name(RELAY_INTERFACE);
+            tun_config.up();
+            let tun = tun::create_as_async(&tun_config).context("Failed to open tun device")?;
+            TunDevice::from_tun_device(tun).context("Failed to create tun device")?
+        };
+
+        let device = device::build()
+            .with_default_udp()
+            .with_ip(tun)
+            .with_private_key(private_key.to_bytes().into())
+            .with_listen_port(RELAY_PORT)
+            .build()
+            .await
+            .context("Failed to create gotatun device")?;
+
+        let addr_v4 = format!("{RELAY_TUNNEL_ADDR_V4}/{}", TUNNEL_NETWORK_V4.prefix());
+        ip(&["addr", "add", &addr_v4, "dev", RELAY_INTERFACE])?;
+        let addr_v6 = format!("{RELAY_TUNNEL_ADDR_V6}/{}", TUNNEL_NETWORK_V6.prefix());
+        ip(&[
+            "-6",
+            "addr",
+            "add",
+            &addr_v6,
+            "dev",
+            RELAY_INTERFACE,
+            "nodad",
+        ])?;
+
+        log::debug!("Started relay with public key {public_key}");
+
+        Ok(Self {
+            device,
+            public_key,
+            devices: Mutex::default(),
+            ephemeral_peers: Mutex::default(),
+        })
+    }
+
+    /// The public key of the relay.
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    /// Let the device with `public_key` connect, and route `allowed_ips` to it.
+    pub async fn add_peer(
+        &self,
+        public_key: &PublicKey,
+        allowed_ips: Vec<IpNetwork>,
+    ) -> Result<()> {
+        self.devices
+            .lock()
+            .unwrap()
+            .insert(public_key.clone(), allowed_ips.clone());
+        self.add_gotatun_peer(public_key, allowed_ips, None).await
+    }
+
+    /// Disconnect and forget the device with `public_key`, along with its ephemeral peers.
+    pub async fn remove_peer(&self, public_key: &PublicKey) -> Result<()> {
+        self.devices.lock().unwrap().remove(public_key);
+        let mut removed = vec![public_key.clone()];
+        self.ephemeral_peers
+            .lock()
+            .unwrap()
+            .retain(|ephemeral, device| {
+                if device == public_key {
+                    removed.push(ephemeral.clone());
+                    return false;
+                }
+                true
+            });
+
+        let removed: Vec<_> = removed
+            .iter()
+            .map(|key| gotatun::x25519::PublicKey::from(*key.as_bytes()))
+            .collect();
+        self.device
+            .write(async |device| {
+                for public_key in &removed {
+                    device.remove_peer(public_key);
+                }
+            })
+            .await
+            .context("Failed to remove peer")
+    }
+
+    /// Let the ephemeral peer with `ephemeral` connect using `psk`, and move the tunnel addresses
+    /// of the device that `parent` belongs to over to it. `parent` is either the device key or a
+    /// previous ephemeral key of the device.
+    pub async fn add_ephemeral_peer(
+        &self,
+        parent: &PublicKey,
+        ephemeral: &PublicKey,
+        psk: Option<PresharedKey>,
+    ) -> Result<()> {
+        let allowed_ips = {
+            let mut ephemeral_peers = self.ephemeral_peers.lock().unwrap();
+            let device = ephemeral_peers.get(parent).unwrap_or(parent).clone();
+            let allowed_ips = self
+                .devices
+                .lock()
+                .unwrap()
+                .get(&device)
+                .cloned()
+                .with_context(|| format!("Unknown parent peer {parent}"))?;
+            ephemeral_peers.insert(ephemeral.clone(), device);
+            allowed_ips
+        };
+        self.add_gotatun_peer(ephemeral, allowed_ips, psk).await
+    }
+
+    async fn add_gotatun_peer(
+        &self,
+        public_key: &PublicKey,
+        allowed_ips: Vec<IpNetwork>,
+        psk: Option<PresharedKey>,
+    ) -> Result<()> {
+        let mut peer = Peer::new((*public_key.as_bytes()).into()).with_allowed_ips(allowed_ips);
+        if let Some(psk) = psk {
+            peer = peer.with_preshared_key(*psk.as_bytes());
+        }
+        self.device
+            .write(async |device| {
+                device.add_peer(peer);
+            })
+            .await
+            .context("Failed to add peer")
+    }
+}
@@ -0,0 +1,145 @@
+//! Stand-in for the Shadowsocks obfuscation server on the relay.
+//!
+//! Packets are decrypted and sent on to the address they are destined for, which is the WireGuard
+//! port of the [`Relay`](crate::relay::Relay) on the loopback interface. Every client gets a UDP
+//! socket of its own, and responses are encrypted and sent back to it.
+
+use anyhow::{Context, Result};
+use shadowsocks::{
+    ProxySocket,
+    config::{ServerConfig, ServerType},
+    context::Context as ShadowsocksContext,
+    crypto::CipherKind,
+    relay::{
+        Address,
+        udprelay::proxy_socket::{ProxySocketError, UdpSocketType},
+    },
+};
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    net::UdpSocket,
+    task::{JoinHandle, JoinSet},
+};
+
+use crate::network::{RELAY_ADDR, SHADOWSOCKS_PORT};
+
+/// The cipher used by the daemon. This must match `tunnel-obfuscation`.
+const CIPHER: CipherKind = CipherKind::AES_256_GCM;
+/// The password used by the daemon. This must match `tunnel-obfuscation`.
+const PASSWORD: &str = "mullvad";
+
+type ServerSocket = ProxySocket<shadowsocks::net::UdpSocket>;
+
+/// The Shadowsocks server, listening on [`SHADOWSOCKS_PORT`] of [`RELAY_ADDR`].
+pub struct Shadowsocks {
+    clients: Arc<Mutex<Vec<SocketAddr>>>,
+    task: JoinHandle<()>,
+}
+
+impl Shadowsocks {
+    /// Start the server. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime), after the relay has been started.
+    pub async fn start() -> Result<Self> {
+        let address = SocketAddr::new(RELAY_ADDR.into(), SHADOWSOCKS_PORT);
+        let socket = UdpSocket::bind(address)
+            .await
+            .with_context(|| format!("Failed to bind Shadowsocks server to {address}"))?;
+        let config = ServerConfig::new(address, PASSWORD, CIPHER)
+            .context("Invalid Shadowsocks server config")?;
+        let socket = ProxySocket::from_socket(
+            UdpSocketType::Server,
+            ShadowsocksContext::new_shared(ServerType::Server),
+            &config,
+            shadowsocks::net::UdpSocket::from(socket),
+        );
+
+        let clients = Arc::default();
+        let task = tokio::spawn(serve(Arc::new(socket), Arc::clone(&clients)));
+        Ok(Self { clients, task })
+    }
+
+    /// Return the address of every client that has sent a valid packet so far.
+    pub fn clients(&self) -> Vec<SocketAddr> {
+        self.clients.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Shadowsocks {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(socket: Arc<ServerSocket>, clients: Arc<Mutex<Vec<SocketAddr>>>) {
+    let mut upstreams: HashMap<SocketAddr, Arc<UdpSocket>> = HashMap::new();
+    // Forwarders are aborted along with this task
+    let mut forwarders = JoinSet::new();
+    let mut buffer = vec![0u8; usize::from(u16::MAX)];
+    loop {
+        let (len, client, target) = match socket.recv_from(&mut buffer).await {
+            Ok((len, client, target, _control)) => (len, client, target),
+            Err(ProxySocketError::IoError(error)) => {
+                log::error!("Shadowsocks server failed to receive: {error}");
+                return;
+            }
+            Err(error) => {
+                log::warn!("Ignoring invalid Shadowsocks packet: {error}");
+                continue;
+            }
+        };
+        let Address::SocketAddress(target) = target else {
+            log::warn!("Ignoring Shadowsocks packet for {target}");
+            continue;
+        };
+
+        let upstream = match upstreams.get(&client) {
+            Some(upstream) => Arc::clone(upstream),
+            None => {
+                let upstream = match UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await {
+                    Ok(upstream) => Arc::new(upstream),
+                    Err(error) => {
+                        log::error!("Failed to bind upstream socket for {client}: {error}");
+                        continue;
+                    }
+                };
+                clients.lock().unwrap().push(client);
+                upstreams.insert(client, Arc::clone(&upstream));
+                forwarders.spawn(forward_responses(
+                    Arc::clone(&socket),
+                    Arc::clone(&upstream),
+                    client,
+                ));
+                upstream
+            }
+        };
+        if let Err(error) = upstream.send_to(&buffer[..len], target).await {
+            log::warn!("Failed to forward Shadowsocks packet to {target}: {error}");
+        }
+    }
+}
+
+/// Send everything that `upstream` receives back to `client`.
+async fn forward_responses(
+    socket: Arc<ServerSocket>,
+    upstream: Arc<UdpSocket>,
+    client: SocketAddr,
+) {
+    let mut buffer = vec![0u8; usize::from(u16::MAX)];
+    loop {
+        let (len, source) = match upstream.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(error) => {
+                log::error!("Upstream socket of {client} failed to receive: {error}");
+                return;
+            }
+        };
+        let source = Address::SocketAddress(source);
+        if let Err(error) = socket.send_to(client, &source, &buffer[..len]).await {
+            log::warn!("Failed to send Shadowsocks packet to {client}: {error}");
+        }
+    }
+}
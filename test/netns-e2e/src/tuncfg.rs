@@ -0,0 +1,67 @@
+//! Stand-in for the tuncfg service on the relay, which negotiates ephemeral peers for
+//! quantum-resistant tunnels.
+//!
+//! Every negotiated peer is added to the [`Relay`] with its PSK. DAITA is not supported.
+
+use anyhow::{Context, Result};
+use std::{net::SocketAddr, sync::Arc};
+use talpid_tunnel_config_client::{
+    CONFIG_SERVICE_PORT,
+    server::{EphemeralPeerService, Registration},
+};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+use crate::{network::RELAY_TUNNEL_ADDR_V4, relay::Relay};
+
+/// The tuncfg service, listening on [`CONFIG_SERVICE_PORT`] inside the tunnel.
+pub struct Tuncfg {
+    service: EphemeralPeerService,
+    task: JoinHandle<()>,
+}
+
+impl Tuncfg {
+    /// Start the service. Negotiated peers are added to `relay`. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime), after the relay has been started.
+    pub async fn start(relay: Arc<Relay>) -> Result<Self> {
+        let address = SocketAddr::new(RELAY_TUNNEL_ADDR_V4.into(), CONFIG_SERVICE_PORT);
+        let listener = TcpListener::bind(address)
+            .await
+            .with_context(|| format!("Failed to bind tuncfg service to {address}"))?;
+
+        let service = EphemeralPeerService::default().on_registration(move |registration| {
+            let relay = Arc::clone(&relay);
+            async move {
+                let Registration {
+                    parent_pubkey,
+                    ephemeral_pubkey,
+                    psk,
+                } = registration;
+                if let Err(error) = relay
+                    .add_ephemeral_peer(&parent_pubkey, &ephemeral_pubkey, psk)
+                    .await
+                {
+                    log::error!("Failed to add ephemeral peer: {error:#}");
+                }
+            }
+        });
+        let server = service.clone().serve(listener);
+        let task = tokio::spawn(async move {
+            if let Err(error) = server.await {
+                log::error!("Tuncfg service failed: {error}");
+            }
+        });
+
+        Ok(Self { service, task })
+    }
+
+    /// Return every peer negotiated so far, oldest first.
+    pub fn registrations(&self) -> Vec<Registration> {
+        self.service.registrations.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Tuncfg {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,110 @@
+//! Stand-in for the udp2tcp obfuscation server on the relay.
+//!
+//! Clients connect over TCP and send WireGuard packets, each prefixed by its length as a 16-bit
+//! big-endian integer. Every connection gets a UDP socket of its own, which forwards the packets
+//! to the [`Relay`](crate::relay::Relay) and frames its responses the same way.
+
+use anyhow::{Context, Result};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{
+        TcpListener, TcpStream, UdpSocket,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    task::{JoinHandle, JoinSet},
+};
+
+use crate::network::{RELAY_ADDR, RELAY_PORT, UDP2TCP_PORTS};
+
+/// The udp2tcp server, listening on every port in [`UDP2TCP_PORTS`] of [`RELAY_ADDR`].
+pub struct Udp2Tcp {
+    clients: Arc<Mutex<Vec<SocketAddr>>>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Udp2Tcp {
+    /// Start the server. This must be called from a
+    /// [`NamespaceRuntime`](crate::network::NamespaceRuntime), after the relay has been started.
+    pub async fn start() -> Result<Self> {
+        let clients = Arc::<Mutex<Vec<SocketAddr>>>::default();
+        let mut tasks = vec![];
+        for port in UDP2TCP_PORTS {
+            let address = SocketAddr::new(RELAY_ADDR.into(), port);
+            let listener = TcpListener::bind(address)
+                .await
+                .with_context(|| format!("Failed to bind udp2tcp server to {address}"))?;
+            tasks.push(tokio::spawn(serve(listener, Arc::clone(&clients))));
+        }
+        Ok(Self { clients, tasks })
+    }
+
+    /// Return the address of every client that has connected so far.
+    pub fn clients(&self) -> Vec<SocketAddr> {
+        self.clients.lock().unwrap().clone()
+    }
+}
+
+impl Drop for Udp2Tcp {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, clients: Arc<Mutex<Vec<SocketAddr>>>) {
+    // Connections are aborted along with this task
+    let mut connections = JoinSet::new();
+    loop {
+        let (stream, client) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(error) => {
+                log::error!("udp2tcp server failed to accept: {error}");
+                return;
+            }
+        };
+        clients.lock().unwrap().push(client);
+        connections.spawn(async move {
+            if let Err(error) = forward(stream).await {
+                log::debug!("udp2tcp connection from {client} closed: {error}");
+            }
+        });
+    }
+}
+
+/// Forward packets between `stream` and the relay until either side fails.
+async fn forward(stream: TcpStream) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let upstream = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+    upstream.connect((RELAY_ADDR, RELAY_PORT)).await?;
+
+    let (reader, writer) = stream.into_split();
+    tokio::select! {
+        result = tcp_to_udp(reader, &upstream) => result,
+        result = udp_to_tcp(&upstream, writer) => result,
+    }
+}
+
+async fn tcp_to_udp(mut reader: OwnedReadHalf, upstream: &UdpSocket) -> io::Result<()> {
+    let mut buffer = vec![0u8; usize::from(u16::MAX)];
+    loop {
+        let len = usize::from(reader.read_u16().await?);
+        reader.read_exact(&mut buffer[..len]).await?;
+        upstream.send(&buffer[..len]).await?;
+    }
+}
+
+async fn udp_to_tcp(upstream: &UdpSocket, mut writer: OwnedWriteHalf) -> io::Result<()> {
+    let mut buffer = vec![0u8; usize::from(u16::MAX)];
+    loop {
+        let len = upstream.recv(&mut buffer).await?;
+        let header = u16::try_from(len).map_err(io::Error::other)?;
+        writer.write_u16(header).await?;
+        writer.write_all(&buffer[..len]).await?;
+    }
+}
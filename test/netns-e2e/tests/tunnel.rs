@@ -0,0 +1,293 @@
+#![cfg(target_os = "linux")]
+//! Tests of the tunnel against the fake backend. These need root, and a daemon built with the
+//! `api-override` feature. See the README.
+
+use mullvad_types::{
+    relay_constraints::{ObfuscationSettings, SelectedObfuscation},
+    states::TunnelState,
+    wireguard::QuantumResistantState,
+};
+use netns_e2e::{
+    Harness, ISP_DNS_ANSWER, TUNNEL_DNS_ANSWER,
+    api::{RELAY_HOSTNAME, UNREACHABLE_RELAY_HOSTNAME},
+    network::{INTERNET_WAN_ADDR, TUNNEL_NETWORK_V4},
+};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use talpid_types::net::{ObfuscationInfo, ObfuscationType};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a blocked request is given to leak before it counts as blocked.
+const LEAK_TIMEOUT: Duration = Duration::from_secs(2);
+
+fn isp_resolver() -> SocketAddr {
+    SocketAddr::new(INTERNET_WAN_ADDR.into(), 53)
+}
+
+async fn start() -> Harness {
+    let _ = env_logger::builder().is_test(true).try_init();
+    let harness = Harness::start().await.expect("Failed to start harness");
+    harness.login().await.expect("Failed to log in");
+    harness
+}
+
+/// Traffic should leave through the relay once connected.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_connect() {
+    let harness = start().await;
+
+    let direct = harness.geoip_lookup(REQUEST_TIMEOUT).await.unwrap();
+    assert!(
+        !direct.mullvad_exit_ip,
+        "Traffic should not be tunneled yet"
+    );
+
+    harness.select_relay(RELAY_HOSTNAME).await.unwrap();
+    harness.rpc().await.unwrap().connect_tunnel().await.unwrap();
+    harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connected { .. })
+        })
+        .await
+        .unwrap();
+
+    let tunneled = harness.geoip_lookup(REQUEST_TIMEOUT).await.unwrap();
+    assert!(tunneled.mullvad_exit_ip, "Traffic should be tunneled");
+    assert_eq!(
+        tunneled.mullvad_exit_ip_hostname.as_deref(),
+        Some(RELAY_HOSTNAME)
+    );
+
+    harness.shutdown().await.unwrap();
+}
+
+/// Connect to the relay using `obfuscation`, and check that traffic is tunneled through it.
+async fn connect_obfuscated(harness: &Harness, obfuscation: SelectedObfuscation) {
+    let mut rpc = harness.rpc().await.unwrap();
+    rpc.set_obfuscation_settings(ObfuscationSettings {
+        selected_obfuscation: obfuscation,
+        ..ObfuscationSettings::default()
+    })
+    .await
+    .unwrap();
+    harness.select_relay(RELAY_HOSTNAME).await.unwrap();
+    rpc.connect_tunnel().await.unwrap();
+    let state = harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connected { .. })
+        })
+        .await
+        .unwrap();
+
+    let TunnelState::Connected { endpoint, .. } = state else {
+        unreachable!();
+    };
+    let expected_type = match obfuscation {
+        SelectedObfuscation::Udp2Tcp => ObfuscationType::Udp2Tcp,
+        SelectedObfuscation::Shadowsocks => ObfuscationType::Shadowsocks,
+        _ => unimplemented!("No stand-in for {obfuscation:?}"),
+    };
+    assert!(
+        matches!(
+            &endpoint.obfuscation,
+            Some(ObfuscationInfo::Single(obfuscator))
+                if obfuscator.obfuscation_type == expected_type
+        ),
+        "Expected {expected_type} obfuscation, got {:?}",
+        endpoint.obfuscation
+    );
+
+    let tunneled = harness.geoip_lookup(REQUEST_TIMEOUT).await.unwrap();
+    assert!(tunneled.mullvad_exit_ip, "Traffic should be tunneled");
+}
+
+/// The tunnel should work through the udp2tcp server.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_udp2tcp() {
+    let harness = start().await;
+
+    connect_obfuscated(&harness, SelectedObfuscation::Udp2Tcp).await;
+    assert!(
+        !harness.backend().udp2tcp.clients().is_empty(),
+        "The udp2tcp server should have been used"
+    );
+
+    harness.shutdown().await.unwrap();
+}
+
+/// The tunnel should work through the Shadowsocks server.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_shadowsocks() {
+    let harness = start().await;
+
+    connect_obfuscated(&harness, SelectedObfuscation::Shadowsocks).await;
+    assert!(
+        !harness.backend().shadowsocks.clients().is_empty(),
+        "The Shadowsocks server should have been used"
+    );
+
+    harness.shutdown().await.unwrap();
+}
+
+/// A quantum-resistant tunnel should negotiate a PSK with the tuncfg service, and then carry
+/// traffic using the ephemeral peer.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_quantum_resistant_tunnel() {
+    let harness = start().await;
+
+    let mut rpc = harness.rpc().await.unwrap();
+    rpc.set_quantum_resistant_tunnel(QuantumResistantState::On)
+        .await
+        .unwrap();
+    harness.select_relay(RELAY_HOSTNAME).await.unwrap();
+    rpc.connect_tunnel().await.unwrap();
+    let state = harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connected { .. })
+        })
+        .await
+        .unwrap();
+    let TunnelState::Connected { endpoint, .. } = state else {
+        unreachable!();
+    };
+    assert!(endpoint.quantum_resistant);
+
+    let tunneled = harness.geoip_lookup(REQUEST_TIMEOUT).await.unwrap();
+    assert!(tunneled.mullvad_exit_ip, "Traffic should be tunneled");
+
+    let registrations = harness.backend().tuncfg.registrations();
+    let device_key = rpc.get_wireguard_key().await.unwrap().key;
+    assert_eq!(
+        registrations.len(),
+        1,
+        "Exactly one peer should be negotiated"
+    );
+    assert_eq!(registrations[0].parent_pubkey, device_key);
+    assert!(registrations[0].psk.is_some(), "A PSK should be negotiated");
+
+    harness.shutdown().await.unwrap();
+}
+
+/// Excluded processes should bypass the tunnel, and go back to using it once they are no longer
+/// excluded.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_split_tunnel() {
+    let harness = start().await;
+
+    harness.select_relay(RELAY_HOSTNAME).await.unwrap();
+    let mut rpc = harness.rpc().await.unwrap();
+    rpc.set_split_tunnel_state(true).await.unwrap();
+    rpc.connect_tunnel().await.unwrap();
+    harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connected { .. })
+        })
+        .await
+        .unwrap();
+
+    let excluded = harness
+        .geoip_lookup_excluded(REQUEST_TIMEOUT)
+        .await
+        .unwrap();
+    assert!(
+        !excluded.mullvad_exit_ip,
+        "Excluded traffic should not be tunneled"
+    );
+
+    let tunneled = harness.geoip_lookup(REQUEST_TIMEOUT).await.unwrap();
+    assert!(
+        tunneled.mullvad_exit_ip,
+        "Traffic should be tunneled once no longer excluded"
+    );
+
+    harness.shutdown().await.unwrap();
+}
+
+/// DNS queries should go to the resolver on the relay while connected, whichever server they are
+/// sent to.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_dns_through_tunnel() {
+    let harness = start().await;
+
+    let answer = harness
+        .dns_lookup(isp_resolver(), "example.com", REQUEST_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, ISP_DNS_ANSWER);
+
+    harness.select_relay(RELAY_HOSTNAME).await.unwrap();
+    harness.rpc().await.unwrap().connect_tunnel().await.unwrap();
+    harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connected { .. })
+        })
+        .await
+        .unwrap();
+
+    let queries_before = harness.backend().isp_resolver.query_sources().len();
+    let answer = harness
+        .dns_lookup(isp_resolver(), "example.com", REQUEST_TIMEOUT)
+        .await
+        .unwrap();
+    assert_eq!(answer, TUNNEL_DNS_ANSWER);
+    assert_eq!(
+        harness.backend().isp_resolver.query_sources().len(),
+        queries_before,
+        "The ISP resolver should not see queries while connected"
+    );
+    let tunnel_queries = harness.backend().tunnel_resolver.query_sources();
+    assert!(
+        !tunnel_queries.is_empty()
+            && tunnel_queries.iter().all(|source| {
+                matches!(source, IpAddr::V4(source) if TUNNEL_NETWORK_V4.contains(*source))
+            }),
+        "Queries should arrive through the tunnel, got {tunnel_queries:?}"
+    );
+
+    harness.shutdown().await.unwrap();
+}
+
+/// Nothing should get through while the daemon is trying to reach a relay that does not answer.
+#[tokio::test]
+#[ignore = "requires root"]
+async fn test_no_leaks_while_connecting() {
+    let harness = start().await;
+
+    harness
+        .select_relay(UNREACHABLE_RELAY_HOSTNAME)
+        .await
+        .unwrap();
+    harness.rpc().await.unwrap().connect_tunnel().await.unwrap();
+    harness
+        .wait_for_tunnel_state(CONNECT_TIMEOUT, |state| {
+            matches!(state, TunnelState::Connecting { .. })
+        })
+        .await
+        .unwrap();
+
+    let isp_queries_before = harness.backend().isp_resolver.query_sources().len();
+    harness
+        .geoip_lookup(LEAK_TIMEOUT)
+        .await
+        .expect_err("HTTP request should be blocked");
+    harness
+        .dns_lookup(isp_resolver(), "example.com", LEAK_TIMEOUT)
+        .await
+        .expect_err("DNS query should be blocked");
+    assert_eq!(
+        harness.backend().isp_resolver.query_sources().len(),
+        isp_queries_before,
+        "The ISP resolver should not see queries while connecting"
+    );
+
+    harness.shutdown().await.unwrap();
+}
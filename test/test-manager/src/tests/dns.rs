@@ -667,7 +667,10 @@ async fn connect_local_wg_relay(mullvad_client: &mut MullvadProxyClient) -> Resu
             exit_peer: None,
             #[cfg(target_os = "linux")]
             fwmark: None,
+            #[cfg(target_os = "linux")]
+            interface_name: None,
             ipv6_gateway: None,
+            custom_entry: false,
         },
     };
     set_custom_endpoint(mullvad_client, custom_tunnel_endpoint)
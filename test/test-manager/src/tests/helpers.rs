@@ -664,6 +664,9 @@ pub fn unreachable_wireguard_tunnel() -> talpid_types::net::wireguard::Connectio
         ipv6_gateway: None,
         #[cfg(target_os = "linux")]
         fwmark: None,
+        #[cfg(target_os = "linux")]
+        interface_name: None,
+        custom_entry: false,
     }
 }
 
@@ -1368,6 +1371,7 @@ pub fn into_settings(
         hops,
         allowed_ips,
         quantum_resistant: _,
+        custom_entry,
     }: RelayQuery,
 ) -> (RelayConstraints, ObfuscationSettings) {
     let location_constraint = |exit: &ExitConstraints| {
@@ -1392,6 +1396,7 @@ pub fn into_settings(
                     entry_location: Constraint::Any,
                     entry_providers: Constraint::Any,
                     entry_ownership: Constraint::Any,
+                    custom_entry,
                 },
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
@@ -1409,6 +1414,7 @@ pub fn into_settings(
                     entry_location: Constraint::Any,
                     entry_providers: Constraint::Any,
                     entry_ownership: Constraint::Any,
+                    custom_entry,
                 },
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
@@ -1426,6 +1432,7 @@ pub fn into_settings(
                     entry_location: location_constraint(&entry.general),
                     entry_providers: entry.general.providers,
                     entry_ownership: entry.general.ownership,
+                    custom_entry,
                 },
             };
             let obfuscation = obfuscation_to_settings(entry.entry_specific.obfuscation);
@@ -357,6 +357,7 @@ pub async fn test_connected_state(
                     entry_endpoint: None,
                     tunnel_interface: _,
                     daita: _,
+                    daita_parameters: _,
                 },
             ..
         } => {
@@ -24,7 +24,7 @@ use std::{
     collections::{BTreeMap, VecDeque},
     io,
     net::{Ipv4Addr, SocketAddr},
-    sync::Arc,
+    sync::{Arc, Mutex},
     time::Duration,
 };
 
@@ -55,10 +55,16 @@ pub struct Multiplexer {
     proxy_socket_v4: Arc<BypassSocket<UdpSocket>>,
     /// IPv6 socket for communicating with obfuscation proxies
     proxy_socket_v6: Arc<BypassSocket<UdpSocket>>,
-    /// Map of currently active transport endpoints and their configurations
-    running_endpoints: BTreeMap<SocketAddr, Transport>,
-    /// Queue of transports to spawn (in priority order)
-    transports: VecDeque<Transport>,
+    /// Map of currently active transport endpoints, their index in [Settings::transports], and
+    /// their configurations
+    running_endpoints: BTreeMap<SocketAddr, (usize, Transport)>,
+    /// Queue of transports to spawn (in priority order), along with their index in
+    /// [Settings::transports]
+    transports: VecDeque<(usize, Transport)>,
+    /// Delay between spawning each transport
+    stagger_delay: Duration,
+    /// Set to the index of the transport that was selected
+    selected: SelectedTransport,
     /// Buffer of initial packets received from WireGuard to replay to new transports
     initial_packets_to_send: Vec<Vec<u8>>,
     /// Handles to spawned obfuscation tasks
@@ -94,7 +100,9 @@ impl Multiplexer {
             proxy_socket_v4: Arc::new(proxy_socket_v4),
             proxy_socket_v6: Arc::new(proxy_socket_v6),
             running_endpoints: BTreeMap::new(),
-            transports: VecDeque::from(settings.transports.clone()),
+            transports: settings.transports.iter().cloned().enumerate().collect(),
+            stagger_delay: settings.stagger_delay,
+            selected: settings.selected.clone(),
             tasks: vec![],
             initial_packets_to_send: vec![],
             wg_addr: None,
@@ -124,11 +132,12 @@ impl Multiplexer {
         let mut obfs_recv_v4_buf = vec![0u8; MAX_DATAGRAM_SIZE];
         let mut obfs_recv_v6_buf = vec![0u8; MAX_DATAGRAM_SIZE];
 
-        let mut delay = tokio::time::interval(Duration::from_secs(1));
+        // `interval` panics if the period is zero
+        let mut delay = tokio::time::interval(self.stagger_delay.max(MIN_STAGGER_DELAY));
 
         /// Helper to fan out a packet to all currently running endpoints
         async fn send_to_all<'a>(
-            endpoints: &BTreeMap<SocketAddr, Transport>,
+            endpoints: &BTreeMap<SocketAddr, (usize, Transport)>,
             get_socket: impl Fn(SocketAddr) -> &'a Arc<BypassSocket<UdpSocket>>,
             packet: &[u8],
         ) {
@@ -196,8 +205,8 @@ impl Multiplexer {
 
                 // Spawning the next transport
                 _ = delay.tick() => {
-                    let Some(transport) = self.transports.pop_front() else { continue; };
-                    if let Err(err) = self.spawn_new_transport(transport).await {
+                    let Some((index, transport)) = self.transports.pop_front() else { continue; };
+                    if let Err(err) = self.spawn_new_transport(index, transport).await {
                         log::error!("Failed to spawn new transport: {err}");
                     }
                 }
@@ -217,7 +226,8 @@ impl Multiplexer {
     ) -> io::Result<()> {
         match obfuscator_recv {
             Ok((received, obfuscator_addr)) => {
-                let Some(transport_config) = self.running_endpoints.get(&obfuscator_addr) else {
+                let Some((index, transport_config)) = self.running_endpoints.get(&obfuscator_addr)
+                else {
                     log::trace!("Ignoring data from unexpected address {obfuscator_addr}");
                     return Ok(());
                 };
@@ -231,6 +241,7 @@ impl Multiplexer {
                     "Selecting {:?} as valid transport configuration via {obfuscator_addr}",
                     transport_config
                 );
+                self.selected.set(*index);
                 let _ = self.client_socket.send_to(received, wg_addr).await;
                 self.run_connected(wg_addr, obfuscator_addr).await
             }
@@ -305,11 +316,16 @@ impl Multiplexer {
     /// transports, start the obfuscation process in a background task.
     ///
     /// # Arguments
+    /// * `index` - Index of the transport in [Settings::transports]
     /// * `transport` - The obfuscation type to spawn
-    async fn spawn_new_transport(&mut self, transport: Transport) -> crate::Result<()> {
+    async fn spawn_new_transport(
+        &mut self,
+        index: usize,
+        transport: Transport,
+    ) -> crate::Result<()> {
         let endpoint = match transport.clone() {
             Transport::Direct(addr) => {
-                self.running_endpoints.insert(addr, transport);
+                self.running_endpoints.insert(addr, (index, transport));
                 log::info!("Spawning direct forwarder");
                 Ok(addr)
             }
@@ -320,8 +336,10 @@ impl Multiplexer {
                 )
                 .await?;
                 let endpoint = obfuscator.endpoint();
-                self.running_endpoints
-                    .insert(endpoint, Transport::Obfuscated(obfuscator_settings));
+                self.running_endpoints.insert(
+                    endpoint,
+                    (index, Transport::Obfuscated(obfuscator_settings)),
+                );
                 self.tasks
                     .push(AbortOnDropHandle::new(tokio::spawn(async move {
                         log::info!("Spawning new obfuscator");
@@ -353,6 +371,29 @@ pub struct Settings {
     /// Spawn these transports progressively and select
     /// the first one that successfully establishes a connection.
     pub transports: Vec<Transport>,
+    /// Delay between spawning each transport.
+    pub stagger_delay: Duration,
+    /// Updated with the index in `transports` of the selected transport.
+    pub selected: SelectedTransport,
+}
+
+/// Shortest delay allowed between spawning two transports.
+const MIN_STAGGER_DELAY: Duration = Duration::from_millis(1);
+
+/// Shared handle to the transport that the multiplexer has selected, if any.
+#[derive(Debug, Clone, Default)]
+pub struct SelectedTransport(Arc<Mutex<Option<usize>>>);
+
+impl SelectedTransport {
+    /// Return the index in [Settings::transports] of the selected transport, if one has
+    /// received a response yet.
+    pub fn get(&self) -> Option<usize> {
+        *self.0.lock().unwrap()
+    }
+
+    fn set(&self, index: usize) {
+        *self.0.lock().unwrap() = Some(index);
+    }
 }
 
 /// Represents a transport method that the multiplexer can use.
@@ -404,6 +445,8 @@ mod tests {
                 Transport::Direct(server_addr),
                 Transport::Direct(server_addr2),
             ],
+            stagger_delay: Duration::from_secs(1),
+            selected: SelectedTransport::default(),
         };
 
         let multiplexer = Multiplexer::new(Arc::new(NoopBypass), &settings)
@@ -478,4 +521,53 @@ mod tests {
 
         assert_eq!(&server_buf[..bytes_received], second_test_data);
     }
+
+    /// Test that the index of the transport that responds first is reported, and that the
+    /// configured stagger delay is respected
+    #[tokio::test(start_paused = true)]
+    async fn test_multiplexer_reports_selected_transport() {
+        let server_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server_socket2 = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        let settings = Settings {
+            transports: vec![
+                Transport::Direct(server_socket.local_addr().unwrap()),
+                Transport::Direct(server_socket2.local_addr().unwrap()),
+            ],
+            stagger_delay: Duration::from_secs(10),
+            selected: SelectedTransport::default(),
+        };
+
+        let multiplexer = Multiplexer::new(Arc::new(NoopBypass), &settings)
+            .await
+            .unwrap();
+        let multiplexer_endpoint = multiplexer.endpoint();
+
+        let client_socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        tokio::spawn(async move { Box::new(multiplexer).run().await });
+
+        client_socket
+            .send_to(b"Ping!", multiplexer_endpoint)
+            .await
+            .unwrap();
+
+        let mut server_buf = vec![0u8; 1024];
+        server_socket.recv_from(&mut server_buf).await.unwrap();
+
+        // The second transport should not be started until the stagger delay has passed
+        tokio::time::advance(Duration::from_secs(5)).await;
+        tokio::task::yield_now().await;
+        assert!(server_socket2.try_recv_from(&mut server_buf).is_err());
+
+        let (_, client_addr) = server_socket2.recv_from(&mut server_buf).await.unwrap();
+        assert_eq!(settings.selected.get(), None);
+
+        server_socket2.send_to(b"Pong!", client_addr).await.unwrap();
+
+        let mut client_buf = vec![0u8; 1024];
+        let (bytes_received, _) = client_socket.recv_from(&mut client_buf).await.unwrap();
+        assert_eq!(&client_buf[..bytes_received], b"Pong!");
+        assert_eq!(settings.selected.get(), Some(1));
+    }
 }